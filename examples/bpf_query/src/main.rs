@@ -83,6 +83,15 @@ fn link() {
             query::LinkTypeInfo::Cgroup(_) => "cgroup",
             query::LinkTypeInfo::Iter => "iter",
             query::LinkTypeInfo::NetNs(_) => "netns",
+            query::LinkTypeInfo::Xdp(_) => "xdp",
+            query::LinkTypeInfo::PerfEvent => "perf_event",
+            query::LinkTypeInfo::KprobeMulti(_) => "kprobe_multi",
+            query::LinkTypeInfo::StructOps(_) => "struct_ops",
+            query::LinkTypeInfo::Netfilter(_) => "netfilter",
+            query::LinkTypeInfo::Tcx(_) => "tcx",
+            query::LinkTypeInfo::UprobeMulti(_) => "uprobe_multi",
+            query::LinkTypeInfo::Netkit(_) => "netkit",
+            query::LinkTypeInfo::SockMap(_) => "sockmap",
             query::LinkTypeInfo::Unknown => "unknown",
         };
 
@@ -8,6 +8,7 @@ use anyhow::Result;
 
 use clap::Parser;
 
+use libbpf_rs::ifindex_from_name;
 use libbpf_rs::skel::OpenSkel;
 use libbpf_rs::skel::SkelBuilder;
 use libbpf_rs::MapFlags;
@@ -18,8 +19,6 @@ use libbpf_rs::TC_H_CLSACT;
 use libbpf_rs::TC_H_MIN_INGRESS;
 use libbpf_rs::TC_INGRESS;
 
-use nix::net::if_::if_nametoindex;
-
 mod tc {
     include!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/bpf/tc.skel.rs"));
 }
@@ -73,7 +72,7 @@ fn main() -> Result<()> {
     let open = builder.open()?;
     let mut skel = open.load()?;
     let progs = skel.progs();
-    let ifidx = if_nametoindex(opts.iface.as_str())? as i32;
+    let ifidx = ifindex_from_name(&opts.iface)?;
 
     let mut tc_builder = TcHookBuilder::new(progs.handle_tc().as_fd());
     tc_builder
@@ -96,15 +95,15 @@ fn main() -> Result<()> {
     if opts.query {
         match custom.query() {
             Err(e) => println!("failed to find custom hook: {e}"),
-            Ok(prog_id) => println!("found custom hook prog_id: {prog_id}"),
+            Ok(filter) => println!("found custom hook prog_id: {}", filter.prog_id),
         }
         match egress.query() {
             Err(e) => println!("failed to find custom hook: {e}"),
-            Ok(prog_id) => println!("found custom hook prog_id: {prog_id}"),
+            Ok(filter) => println!("found custom hook prog_id: {}", filter.prog_id),
         }
         match ingress.query() {
             Err(e) => println!("failed to find custom hook: {e}"),
-            Ok(prog_id) => println!("found custom hook prog_id: {prog_id}"),
+            Ok(filter) => println!("found custom hook prog_id: {}", filter.prog_id),
         }
     }
 
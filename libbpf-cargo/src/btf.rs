@@ -0,0 +1,66 @@
+//! Generation of Rust type definitions from BTF.
+//!
+//! This allows for sharing type definitions, such as those of events sent
+//! through a ring buffer, between BPF programs and Rust code, without
+//! having to maintain mirrors of them by hand.
+//!
+//! Generated skeletons contain definitions of such types automatically, if
+//! they are tagged with `__attribute__((btf_decl_tag("libbpf_rs.export")))`
+//! in the BPF program.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::anyhow;
+use anyhow::Context as _;
+use anyhow::Result;
+
+use libbpf_rs::btf::BtfType;
+use libbpf_rs::Btf;
+
+use crate::gen::btf::GenBtf;
+
+/// Generate `#[repr(C)]` Rust definitions for the types named `names`
+/// in `btf`, along with all the types they depend on.
+///
+/// Structs, unions, enums, and typedefs are supported. Padding, bitfields,
+/// and anonymous members are handled the same way as in generated
+/// skeletons.
+///
+/// ```no_run
+/// use libbpf_cargo::btf::codegen;
+/// use libbpf_rs::Btf;
+///
+/// let btf = Btf::from_path("myobject.bpf.o").unwrap();
+/// let code = codegen(btf, &["event"]).unwrap();
+/// std::fs::write("event.rs", code).unwrap();
+/// ```
+pub fn codegen(btf: Btf<'_>, names: &[&str]) -> Result<String> {
+    let btf = GenBtf::from(btf);
+    let mut processed = HashSet::new();
+    let mut def = String::new();
+
+    for name in names {
+        let ty = btf
+            .type_by_name::<BtfType<'_>>(name)
+            .ok_or_else(|| anyhow!("type `{name}` not found"))?;
+
+        let type_def = btf
+            .named_type_definition(ty, &mut processed)
+            .with_context(|| format!("failed to generate definition of type `{name}`"))?;
+        def.push_str(&type_def);
+    }
+
+    Ok(def)
+}
+
+/// Generate Rust definitions for the types named `names` in the BTF of the
+/// BPF object file at `path`.
+///
+/// See [`codegen`] for details.
+pub fn codegen_from_object<P: AsRef<Path>>(path: P, names: &[&str]) -> Result<String> {
+    let path = path.as_ref();
+    let btf = Btf::from_path(path)
+        .with_context(|| format!("failed to load BTF from `{}`", path.display()))?;
+    codegen(btf, names)
+}
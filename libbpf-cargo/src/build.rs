@@ -1,8 +1,11 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::env::consts::ARCH;
 use std::ffi::OsStr;
 use std::ffi::OsString;
 use std::fs;
+use std::mem;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
@@ -13,11 +16,75 @@ use anyhow::Context;
 use anyhow::Result;
 use regex::Regex;
 use semver::Version;
+use serde::Deserialize;
 use tempfile::tempdir;
 
 use crate::metadata;
 use crate::metadata::UnprocessedObj;
 
+/// The byte order to compile BPF programs for.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    /// The byte order of the host compiling the programs.
+    #[default]
+    Native,
+    /// Little endian byte order.
+    Little,
+    /// Big endian byte order.
+    Big,
+}
+
+impl Endianness {
+    /// The `clang` target to use for compiling for this byte order.
+    fn clang_target(&self) -> &'static str {
+        match self {
+            Self::Native => "bpf",
+            Self::Little => "bpfel",
+            Self::Big => "bpfeb",
+        }
+    }
+
+    /// The `bpf-gcc` flag to use for compiling for this byte order.
+    fn gcc_flag(&self) -> &'static str {
+        match self {
+            Self::Native if cfg!(target_endian = "big") => "-mbig-endian",
+            Self::Native | Self::Little => "-mlittle-endian",
+            Self::Big => "-mbig-endian",
+        }
+    }
+}
+
+/// The compiler to build BPF programs with.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Compiler {
+    /// LLVM's `clang`.
+    #[default]
+    Clang,
+    /// GCC's BPF backend.
+    Gcc,
+}
+
+impl Compiler {
+    /// The binary to search `$PATH` for if no compiler path is provided.
+    fn default_path(&self) -> &'static str {
+        match self {
+            Self::Clang => "clang",
+            Self::Gcc => "bpf-gcc",
+        }
+    }
+
+    /// The minimum compiler version able to build BPF programs usable with
+    /// libbpf.
+    fn min_version(&self) -> Version {
+        match self {
+            Self::Clang => Version::new(10, 0, 0),
+            Self::Gcc => Version::new(13, 0, 0),
+        }
+    }
+}
+
 fn check_progs(objs: &[UnprocessedObj]) -> Result<()> {
     let mut set = HashSet::with_capacity(objs.len());
     for obj in objs {
@@ -49,6 +116,20 @@ fn extract_version(output: &str) -> Result<&str> {
     )
 }
 
+fn extract_gcc_version(output: &str) -> Result<&str> {
+    // GCC reports its version at the end of the first line, following the
+    // (potentially vendor specific) package version in parentheses.
+    let re = Regex::new(r"^\S*gcc\S*\s+\(.*\)\s+(?P<version_str>\d+\.\d+\.\d+)")?;
+    let captures = re
+        .captures(output)
+        .ok_or_else(|| anyhow!("Failed to run regex on version string"))?;
+
+    captures.name("version_str").map_or_else(
+        || Err(anyhow!("Failed to find version capture group")),
+        |v| Ok(v.as_str()),
+    )
+}
+
 /// Extract vendored libbpf header files to a temporary directory.
 ///
 /// Directory and enclosed contents will be removed when return object is dropped.
@@ -79,39 +160,59 @@ fn extract_libbpf_headers_to_disk(_target_dir: &Path) -> Result<Option<PathBuf>>
     Ok(None)
 }
 
-fn check_clang(debug: bool, clang: &Path, skip_version_checks: bool) -> Result<()> {
-    let output = Command::new(clang.as_os_str())
+fn check_compiler(
+    debug: bool,
+    compiler: Compiler,
+    path: &Path,
+    skip_version_checks: bool,
+) -> Result<()> {
+    let output = Command::new(path.as_os_str())
         .arg("--version")
         .output()
-        .context("Failed to execute clang")?;
+        .with_context(|| format!("Failed to execute {}", path.display()))?;
 
     if !output.status.success() {
-        bail!("Failed to execute clang binary");
+        bail!("Failed to execute {} binary", path.display());
     }
 
     if skip_version_checks {
         return Ok(());
     }
 
-    // Example output:
-    //
-    //     clang version 10.0.0
-    //     Target: x86_64-pc-linux-gnu
-    //     Thread model: posix
-    //     InstalledDir: /bin
-    //
     let output = String::from_utf8_lossy(&output.stdout);
-    let version_str = extract_version(&output)?;
+    let version_str = match compiler {
+        // Example output:
+        //
+        //     clang version 10.0.0
+        //     Target: x86_64-pc-linux-gnu
+        //     Thread model: posix
+        //     InstalledDir: /bin
+        //
+        Compiler::Clang => extract_version(&output)?,
+        // Example output:
+        //
+        //     bpf-gcc (GCC) 14.1.0
+        //     Copyright (C) 2024 Free Software Foundation, Inc.
+        //
+        Compiler::Gcc => extract_gcc_version(&output)?,
+    };
     let version = Version::parse(version_str)?;
     if debug {
-        println!("{} is version {}", clang.display(), version);
+        println!("{} is version {}", path.display(), version);
     }
 
-    if version < Version::parse("10.0.0").unwrap() {
-        bail!(
-            "version {} is too old. Use --skip-clang-version-checks to skip version check",
-            version
-        );
+    let min_version = compiler.min_version();
+    if version < min_version {
+        match compiler {
+            Compiler::Clang => bail!(
+                "version {} is too old. Use --skip-clang-version-checks to skip version check",
+                version
+            ),
+            Compiler::Gcc => bail!(
+                "{} version {version} is too old, version {min_version} or newer is required",
+                path.display()
+            ),
+        }
     }
 
     Ok(())
@@ -163,19 +264,25 @@ fn format_command(command: &Command) -> String {
 ///
 ///   clang -g -O2 -target bpf -c -D__TARGET_ARCH_$(ARCH) runqslower.bpf.c -o runqslower.bpf.o
 ///
+/// or, with GCC:
+///
+///   bpf-gcc -gbtf -mco-re -O2 -mlittle-endian -c -D__TARGET_ARCH_$(ARCH) runqslower.bpf.c -o runqslower.bpf.o
+///
 /// for each prog.
 fn compile_one(
     debug: bool,
     source: &Path,
     out: &Path,
-    clang: &Path,
+    compiler: Compiler,
+    compiler_path: &Path,
     clang_args: &[OsString],
+    endianness: Endianness,
 ) -> Result<()> {
     if debug {
         println!("Building {}", source.display());
     }
 
-    let mut cmd = Command::new(clang.as_os_str());
+    let mut cmd = Command::new(compiler_path.as_os_str());
     cmd.args(clang_args);
 
     if !clang_args
@@ -195,16 +302,24 @@ fn compile_one(
         cmd.arg(format!("-D__TARGET_ARCH_{arch}"));
     }
 
-    cmd.arg("-g")
-        .arg("-O2")
-        .arg("-target")
-        .arg("bpf")
-        .arg("-c")
-        .arg(source.as_os_str())
-        .arg("-o")
-        .arg(out);
-
-    let output = cmd.output().context("Failed to execute clang")?;
+    match compiler {
+        Compiler::Clang => cmd
+            .arg("-g")
+            .arg("-O2")
+            .arg("-target")
+            .arg(endianness.clang_target()),
+        // GCC only emits BTF and CO-RE relocations when asked to.
+        Compiler::Gcc => cmd
+            .arg("-gbtf")
+            .arg("-mco-re")
+            .arg("-O2")
+            .arg(endianness.gcc_flag()),
+    };
+    cmd.arg("-c").arg(source.as_os_str()).arg("-o").arg(out);
+
+    let output = cmd
+        .output()
+        .with_context(|| format!("Failed to execute {}", compiler_path.display()))?;
     if !output.status.success() {
         let err = Err(anyhow!(String::from_utf8_lossy(&output.stderr).to_string()))
             .with_context(|| {
@@ -224,7 +339,7 @@ fn compile_one(
         return err;
     }
 
-    // Compilation with clang may contain DWARF information that references
+    // Compilation may emit DWARF information that references
     // system specific and temporary paths. That can render our generated
     // skeletons unstable, potentially rendering them unsuitable for inclusion
     // in version control systems. So strip this information.
@@ -234,7 +349,7 @@ fn compile_one(
 fn compile(
     debug: bool,
     objs: &[UnprocessedObj],
-    clang: &Path,
+    clang: Option<&PathBuf>,
     mut clang_args: Vec<OsString>,
     target_dir: &Path,
 ) -> Result<()> {
@@ -258,17 +373,104 @@ fn compile(
         let mut dest_path = obj.out.to_path_buf();
         dest_path.push(&dest_name);
         fs::create_dir_all(&obj.out)?;
-        compile_one(debug, &obj.path, &dest_path, clang, &clang_args)?;
+
+        let mut obj_clang_args = clang_args.clone();
+        obj_clang_args.extend(obj.clang_args.iter().cloned());
+        let compiler_path = compiler_path_or_default(clang, obj.compiler);
+        let sources = [obj.path.clone()]
+            .into_iter()
+            .chain(obj.extra_sources.iter().cloned())
+            .collect::<Vec<_>>();
+        let () = compile_and_link(debug, &sources, &dest_path, |_idx, source, out| {
+            compile_one(
+                debug,
+                source,
+                out,
+                obj.compiler,
+                &compiler_path,
+                &obj_clang_args,
+                obj.endianness,
+            )
+        })?;
     }
 
     Ok(())
 }
 
-fn extract_clang_or_default(clang: Option<&PathBuf>) -> PathBuf {
-    match clang {
+/// Build the BPF object file `out` from `sources` by invoking `compile`
+/// with the index of each source, the source, and the object file to
+/// compile it to.
+///
+/// If more than one source file is provided, each is compiled separately
+/// and the resulting object files are statically linked into `out`.
+fn compile_and_link<F>(debug: bool, sources: &[PathBuf], out: &Path, mut compile: F) -> Result<()>
+where
+    F: FnMut(usize, &PathBuf, &Path) -> Result<()>,
+{
+    match sources {
+        [] => bail!("No source file provided"),
+        [source] => compile(0, source, out),
+        sources => {
+            let obj_dir = tempdir().context("failed to create temporary directory")?;
+            let mut linker = libbpf_rs::Linker::new(out)
+                .context("Failed to instantiate libbpf object file linker")?;
+
+            for (idx, source) in sources.iter().enumerate() {
+                // Source files may share a name, so just number the
+                // intermediate object files.
+                let obj = obj_dir.path().join(format!("{idx}.o"));
+                let () = compile(idx, source, &obj)?;
+                linker
+                    .add_file(&obj)
+                    .with_context(|| format!("Failed to add {} to BPF linker", source.display()))?;
+            }
+
+            if debug {
+                println!("Linking {}", out.display());
+            }
+            linker
+                .link()
+                .with_context(|| format!("Failed to link {}", out.display()))
+        }
+    }
+}
+
+/// Parse a Makefile style dependency file, as written by the compiler when
+/// invoked with `-MD`, and return the prerequisites listed in it.
+fn parse_depfile(contents: &str) -> Vec<PathBuf> {
+    // Rules may be continued on the next line using a backslash.
+    let contents = contents.replace("\\\r\n", " ").replace("\\\n", " ");
+    let prereqs = match contents.split_once(": ") {
+        Some((_target, prereqs)) => prereqs,
+        None => return Vec::new(),
+    };
+
+    let mut deps = Vec::new();
+    let mut dep = String::new();
+    let mut chars = prereqs.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some(' ' | '#')) => dep.extend(chars.next()),
+            '$' if chars.peek() == Some(&'$') => dep.extend(chars.next()),
+            c if c.is_whitespace() => {
+                if !dep.is_empty() {
+                    deps.push(PathBuf::from(mem::take(&mut dep)));
+                }
+            }
+            c => dep.push(c),
+        }
+    }
+    if !dep.is_empty() {
+        deps.push(PathBuf::from(dep));
+    }
+    deps
+}
+
+fn compiler_path_or_default(path: Option<&PathBuf>, compiler: Compiler) -> PathBuf {
+    match path {
         Some(c) => c.into(),
         // Searches $PATH
-        None => "clang".into(),
+        None => compiler.default_path().into(),
     }
 }
 
@@ -293,27 +495,46 @@ pub fn build(
 
     check_progs(&to_compile)?;
 
-    let clang = extract_clang_or_default(clang);
-    check_clang(debug, &clang, skip_clang_version_checks)
-        .with_context(|| anyhow!("{} is invalid", clang.display()))?;
-    compile(debug, &to_compile, &clang, clang_args, &target_dir)
+    let compilers = to_compile
+        .iter()
+        .map(|obj| obj.compiler)
+        .collect::<HashSet<_>>();
+    for compiler in compilers {
+        let path = compiler_path_or_default(clang, compiler);
+        check_compiler(debug, compiler, &path, skip_clang_version_checks)
+            .with_context(|| anyhow!("{} is invalid", path.display()))?;
+    }
+    compile(debug, &to_compile, clang, clang_args, &target_dir)
         .context("Failed to compile progs")?;
 
     Ok(())
 }
 
+/// Build the BPF object file `out` from `sources`.
+///
+/// If more than one source file is provided, each is compiled separately
+/// and the resulting object files are statically linked into `out`.
+/// Arguments in `source_clang_args` are passed to the compiler only when
+/// compiling the respective source file, in addition to `clang_args`.
+///
+/// On success, the source files along with all headers they include,
+/// directly or transitively, are returned.
 // Only used in libbpf-cargo library
 #[allow(dead_code)]
+#[allow(clippy::too_many_arguments)]
 pub fn build_single(
     debug: bool,
-    source: &Path,
+    sources: &[PathBuf],
     out: &Path,
     clang: Option<&PathBuf>,
     skip_clang_version_checks: bool,
     mut clang_args: Vec<OsString>,
-) -> Result<()> {
-    let clang = extract_clang_or_default(clang);
-    check_clang(debug, &clang, skip_clang_version_checks)?;
+    source_clang_args: &HashMap<PathBuf, Vec<OsString>>,
+    endianness: Endianness,
+    compiler: Compiler,
+) -> Result<Vec<PathBuf>> {
+    let compiler_path = compiler_path_or_default(clang, compiler);
+    check_compiler(debug, compiler, &compiler_path, skip_clang_version_checks)?;
     let header_parent_dir = tempdir()?;
     let header_dir = extract_libbpf_headers_to_disk(header_parent_dir.path())?;
 
@@ -326,9 +547,43 @@ pub fn build_single(
     // BPF. See https://lkml.org/lkml/2020/2/21/1000.
     clang_args.push(OsString::from("-fno-stack-protector"));
 
-    compile_one(debug, source, out, &clang, &clang_args)?;
+    let dep_dir = tempdir().context("failed to create temporary directory")?;
+    let mut deps = BTreeSet::new();
+    let compile_source = |idx: usize, source: &PathBuf, out: &Path| -> Result<()> {
+        let depfile = dep_dir.path().join(format!("{idx}.d"));
+        let mut clang_args = clang_args.clone();
+        if let Some(args) = source_clang_args.get(source) {
+            clang_args.extend(args.iter().cloned());
+        }
+        // Have the compiler record all files the source depends on.
+        clang_args.push(OsString::from("-MD"));
+        clang_args.push(OsString::from("-MF"));
+        clang_args.push(depfile.clone().into_os_string());
+
+        let () = compile_one(
+            debug,
+            source,
+            out,
+            compiler,
+            &compiler_path,
+            &clang_args,
+            endianness,
+        )?;
+
+        let contents = fs::read_to_string(&depfile)
+            .with_context(|| format!("Failed to read {}", depfile.display()))?;
+        // The dependencies include the source itself. Our extracted libbpf
+        // headers are temporary, though, and of no interest.
+        deps.extend(
+            parse_depfile(&contents)
+                .into_iter()
+                .filter(|dep| !dep.starts_with(header_parent_dir.path())),
+        );
+        Ok(())
+    };
 
-    Ok(())
+    let () = compile_and_link(debug, sources, out, compile_source)?;
+    Ok(deps.into_iter().collect())
 }
 
 #[test]
@@ -350,3 +605,33 @@ InstalledDir: /bin
     assert!(extract_version("askldfjwe").is_err());
     assert!(extract_version("my clang version 1.5").is_err());
 }
+
+#[test]
+fn test_parse_depfile() {
+    let depfile = "/tmp/0.o: src/bpf/prog.bpf.c src/bpf/vmlinux.h \\\n  /usr/include/with\\ space.h \\\n  src/bpf/dollar$$.h\n";
+    assert_eq!(
+        parse_depfile(depfile),
+        vec![
+            PathBuf::from("src/bpf/prog.bpf.c"),
+            PathBuf::from("src/bpf/vmlinux.h"),
+            PathBuf::from("/usr/include/with space.h"),
+            PathBuf::from("src/bpf/dollar$.h"),
+        ]
+    );
+    assert_eq!(parse_depfile(""), Vec::<PathBuf>::new());
+}
+
+#[test]
+fn test_extract_gcc_version() {
+    let upstream_format = r"bpf-unknown-none-gcc (GCC) 14.1.0
+Copyright (C) 2024 Free Software Foundation, Inc.
+";
+    assert_eq!(extract_gcc_version(upstream_format).unwrap(), "14.1.0");
+
+    let debian_format = r"bpf-gcc (Debian 13.2.0-7+b1) 13.2.0
+Copyright (C) 2023 Free Software Foundation, Inc.
+";
+    assert_eq!(extract_gcc_version(debian_format).unwrap(), "13.2.0");
+
+    assert!(extract_gcc_version("clang version 17.0.6").is_err());
+}
@@ -0,0 +1,295 @@
+//! Verification of built BPF objects against the running kernel.
+
+use std::fmt::Write as _;
+use std::os::unix::io::AsFd as _;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use anyhow::Context as _;
+use anyhow::Result;
+use serde::Serialize;
+
+use libbpf_rs::query::ProgInfoQueryOptions;
+use libbpf_rs::query::ProgramInfo;
+use libbpf_rs::ObjectBuilder;
+use libbpf_rs::ProgramInput;
+
+use crate::metadata;
+
+/// The markers libbpf surrounds the verifier log of a program with.
+const LOG_BEGIN: &str = "-- BEGIN PROG LOAD LOG --";
+const LOG_END: &str = "-- END PROG LOAD LOG --";
+
+/// The result of test running a program.
+#[derive(Debug, Serialize)]
+pub struct TestRunReport {
+    /// The value the program returned, if it could be run.
+    pub return_value: Option<u32>,
+    /// The error that occurred, if the program could not be run.
+    pub error: Option<String>,
+}
+
+/// The result of loading a single program.
+#[derive(Debug, Serialize)]
+pub struct ProgramReport {
+    /// The name of the program.
+    pub name: String,
+    /// The section the program is defined in.
+    pub section: String,
+    /// Whether the program was loaded (and, hence, verified) successfully.
+    pub loaded: bool,
+    /// The error that occurred while loading, if any.
+    pub error: Option<String>,
+    /// The verifier log, if loading failed.
+    pub verifier_log: Option<String>,
+    /// The number of instructions the verifier processed, if reported by
+    /// the kernel.
+    pub verified_insns: Option<u32>,
+    /// The time it took to load the program, in microseconds.
+    pub load_time_us: Option<u64>,
+    /// The result of test running the program, if requested.
+    pub test_run: Option<TestRunReport>,
+}
+
+/// The results of checking all programs of an object.
+#[derive(Debug, Serialize)]
+pub struct ObjectReport {
+    /// The path to the object file.
+    pub path: PathBuf,
+    /// The reports of the object's programs.
+    pub programs: Vec<ProgramReport>,
+}
+
+impl ObjectReport {
+    /// Check whether all programs of the object loaded successfully and
+    /// ran successfully, if requested.
+    fn is_success(&self) -> bool {
+        self.programs.iter().all(|prog| {
+            prog.loaded
+                && prog
+                    .test_run
+                    .as_ref()
+                    .map_or(true, |test_run| test_run.error.is_none())
+        })
+    }
+}
+
+/// Extract the verifier log of program `prog` from libbpf's output.
+fn extract_verifier_log<'msg, I>(messages: I, prog: &str) -> Option<String>
+where
+    I: IntoIterator<Item = &'msg str>,
+{
+    let prefix = format!("prog '{prog}': {LOG_BEGIN}");
+    messages.into_iter().find_map(|msg| {
+        let log = msg.strip_prefix(&prefix)?;
+        let log = log.trim_start_matches('\n');
+        let log = log.strip_suffix(LOG_END).unwrap_or(log);
+        Some(log.trim_end().to_string())
+    })
+}
+
+/// Load only program `name` of the object at `path` and report the
+/// outcome, test running the program afterwards if `test_run` is set.
+fn check_program(path: &Path, name: &str, section: &str, test_run: bool) -> Result<ProgramReport> {
+    let mut open_obj = ObjectBuilder::default()
+        .open_file(path)
+        .with_context(|| format!("failed to open `{}`", path.display()))?;
+    for prog in open_obj.progs_iter_mut() {
+        let autoload = prog.name()? == name;
+        let () = prog.set_autoload(autoload)?;
+    }
+
+    let (result, load_report) = open_obj.load_with_report();
+    let mut report = ProgramReport {
+        name: name.to_string(),
+        section: section.to_string(),
+        loaded: result.is_ok(),
+        error: None,
+        verifier_log: None,
+        verified_insns: None,
+        load_time_us: load_report
+            .programs
+            .iter()
+            .find(|prog| prog.name == name)
+            .map(|prog| prog.duration.as_micros() as u64),
+        test_run: None,
+    };
+
+    let mut obj = match result {
+        Ok(obj) => obj,
+        Err(err) => {
+            report.error = Some(format!("{err:#}"));
+            report.verifier_log = extract_verifier_log(
+                load_report
+                    .events
+                    .iter()
+                    .map(|event| event.message.as_str()),
+                name,
+            );
+            return Ok(report);
+        }
+    };
+
+    // Unwrap is safe here since we just loaded the program
+    let prog = obj.prog_mut(name).unwrap();
+    if let Ok(info) = ProgramInfo::load_from_fd(prog.as_fd(), &ProgInfoQueryOptions::default()) {
+        // Kernels not reporting the count leave it at zero.
+        report.verified_insns = Some(info.verified_insns).filter(|insns| *insns != 0);
+    }
+
+    if test_run {
+        let test_run = match prog.test_run(ProgramInput::default()) {
+            Ok(output) => TestRunReport {
+                return_value: Some(output.return_value),
+                error: None,
+            },
+            Err(err) => TestRunReport {
+                return_value: None,
+                error: Some(format!("{err:#}")),
+            },
+        };
+        report.test_run = Some(test_run);
+    }
+
+    Ok(report)
+}
+
+/// Load each program of the object at `path` separately and report the
+/// outcome, test running those of the programs named in `test_run` that
+/// the object contains.
+///
+/// Programs are loaded separately so that a single failing program does
+/// not hide the results of the others.
+pub fn check_object(path: &Path, test_run: &[String]) -> Result<ObjectReport> {
+    let open_obj = ObjectBuilder::default()
+        .open_file(path)
+        .with_context(|| format!("failed to open `{}`", path.display()))?;
+    let progs = open_obj
+        .progs_iter()
+        .map(|prog| Ok((prog.name()?.to_string(), prog.section().to_string())))
+        .collect::<Result<Vec<_>>>()?;
+    drop(open_obj);
+
+    let programs = progs
+        .iter()
+        .map(|(name, section)| {
+            check_program(path, name, section, test_run.contains(name))
+                .with_context(|| format!("failed to check program `{name}`"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(ObjectReport {
+        path: path.to_path_buf(),
+        programs,
+    })
+}
+
+/// Render `reports` in human readable form.
+fn format_reports(reports: &[ObjectReport]) -> Result<String> {
+    let mut out = String::new();
+    for report in reports {
+        writeln!(out, "{}", report.path.display())?;
+        for prog in &report.programs {
+            write!(out, "  {} ({}): ", prog.name, prog.section)?;
+            if let Some(err) = &prog.error {
+                writeln!(out, "FAILED: {err}")?;
+                if let Some(log) = &prog.verifier_log {
+                    for line in log.lines() {
+                        writeln!(out, "    {line}")?;
+                    }
+                }
+                continue;
+            }
+
+            write!(out, "ok")?;
+            if let Some(insns) = prog.verified_insns {
+                write!(out, ", {insns} insns verified")?;
+            }
+            if let Some(time) = prog.load_time_us {
+                write!(out, ", loaded in {time}us")?;
+            }
+            writeln!(out)?;
+
+            match &prog.test_run {
+                Some(TestRunReport {
+                    return_value: Some(ret),
+                    ..
+                }) => writeln!(out, "    test run: returned {ret}")?,
+                Some(TestRunReport {
+                    error: Some(err), ..
+                }) => writeln!(out, "    test run: FAILED: {err}")?,
+                _ => (),
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Check the objects of the project at `manifest_path` or, if provided,
+/// the single object file `object`, and print a report.
+///
+/// An error is returned if any program failed to load or to test run.
+pub fn check(
+    debug: bool,
+    manifest_path: Option<&PathBuf>,
+    object: Option<&PathBuf>,
+    test_run: &[String],
+    json: bool,
+) -> Result<()> {
+    let objects = if let Some(object) = object {
+        vec![object.clone()]
+    } else {
+        let (_target_dir, objs) = metadata::get(debug, manifest_path)?;
+        if objs.is_empty() {
+            bail!("Did not find any bpf objects to check");
+        }
+        objs.iter()
+            .map(|obj| obj.out.join(format!("{}.bpf.o", obj.name)))
+            .collect()
+    };
+
+    let reports = objects
+        .iter()
+        .map(|path| check_object(path, test_run))
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(name) = test_run.iter().find(|name| {
+        !reports
+            .iter()
+            .flat_map(|report| report.programs.iter())
+            .any(|prog| &prog.name == *name)
+    }) {
+        bail!("program `{name}` to test run not found");
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        print!("{}", format_reports(&reports)?);
+    }
+
+    let failed = reports.iter().filter(|report| !report.is_success()).count();
+    if failed > 0 {
+        bail!("{failed} object(s) failed verification");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that we extract the verifier log from libbpf's messages.
+    #[test]
+    fn verifier_log_extraction() {
+        let messages = [
+            "prog 'foo': BPF program load failed: Permission denied",
+            "prog 'foo': -- BEGIN PROG LOAD LOG --\n0: R1=ctx() R10=fp0\ninvalid mem access 'scalar'\n-- END PROG LOAD LOG --",
+            "prog 'foo': failed to load: -13",
+        ];
+        let log = extract_verifier_log(messages, "foo").unwrap();
+        assert_eq!(log, "0: R1=ctx() R10=fp0\ninvalid mem access 'scalar'");
+        assert_eq!(extract_verifier_log(messages, "bar"), None);
+    }
+}
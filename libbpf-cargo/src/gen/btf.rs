@@ -2,6 +2,7 @@ use std::borrow::Cow;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::ffi::CStr;
 use std::fmt::Write;
 use std::mem::size_of;
 use std::num::NonZeroUsize;
@@ -10,6 +11,7 @@ use std::ops::Deref;
 use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::ensure;
+use anyhow::Context as _;
 use anyhow::Result;
 use libbpf_rs::btf::types;
 use libbpf_rs::btf::types::Linkage;
@@ -23,10 +25,16 @@ use libbpf_rs::HasSize;
 use libbpf_rs::ReferencesType;
 
 use super::canonicalize_internal_map_name;
+use super::escape_raw_name;
 use super::InternalMapType;
 
 const ANON_PREFIX: &str = "__anon_";
 
+/// The value of the `btf_decl_tag` attribute marking types for which Rust
+/// definitions should be generated, even if they are not otherwise
+/// referenced by the skeleton (e.g., types of ring buffer events).
+pub(crate) const EXPORT_DECL_TAG: &str = "libbpf_rs.export";
+
 /// Check whether the provided type is "unsafe" to use.
 ///
 /// A type is considered unsafe by this function if it is not valid for
@@ -108,8 +116,54 @@ fn required_padding(
     }
 }
 
+/// Check whether the Rust definition generated for `composite` contains
+/// bytes not covered by any of its fields, i.e., implicit padding or, for
+/// unions, bytes beyond the end of smaller members.
+///
+/// Such bytes are uninitialized in values constructed on the Rust side, so
+/// the definition must not be reinterpreted as raw bytes.
+pub(crate) fn has_implicit_padding(
+    composite: &types::Composite<'_>,
+    btf: &Btf<'_>,
+) -> Result<bool> {
+    let size = composite.size();
+    if !composite.is_struct {
+        for member in composite.iter() {
+            let member_ty = btf.type_by_id::<BtfType<'_>>(member.ty).unwrap();
+            if size_of_type(member_ty, btf)? != size {
+                return Ok(true);
+            }
+        }
+        return Ok(false);
+    }
+
+    // Mirror the layout logic of struct definition generation, which only
+    // inserts explicit padding where natural alignment would not produce the
+    // required offset.
+    let packed = is_struct_packed(composite, btf)?;
+    let mut offset = 0;
+    for member in composite.iter() {
+        let member_offset = match member.attr {
+            MemberAttr::Normal { offset } => offset as usize / 8,
+            MemberAttr::BitField { .. } => continue,
+        };
+        let member_ty = btf.type_by_id::<BtfType<'_>>(member.ty).unwrap();
+        if member_offset != offset
+            && required_padding(offset, member_offset, &member_ty, packed)? == 0
+        {
+            return Ok(true);
+        }
+        offset = member_offset + size_of_type(member_ty.skip_mods_and_typedefs(), btf)?;
+    }
+
+    Ok(offset != size && required_padding(offset, size, composite, packed)? == 0)
+}
+
 struct TypeDeclOpts {
-    func_type: &'static str,
+    /// The type to use for functions, instead of `c_void`.
+    func_type: Option<&'static str>,
+    /// The crate to refer to for language items, `std` or `core`.
+    krate: &'static str,
 }
 
 fn type_declaration_impl(
@@ -120,7 +174,7 @@ fn type_declaration_impl(
     let ty = ty.skip_mods_and_typedefs();
 
     let s = btf_type_match!(match ty {
-        BtfKind::Void => "std::ffi::c_void".to_string(),
+        BtfKind::Void => format!("{}::ffi::c_void", opts.krate),
         BtfKind::Int(t) => {
             let width = match (t.bits + 7) / 8 {
                 1 => "8",
@@ -164,17 +218,25 @@ fn type_declaration_impl(
         }
         BtfKind::Struct | BtfKind::Union | BtfKind::Enum | BtfKind::Enum64 =>
             anon_types.type_name_or_anon(&ty).into_owned(),
-        BtfKind::Func | BtfKind::FuncProto => opts.func_type.to_string(),
-        BtfKind::Fwd => "std::ffi::c_void".to_string(),
+        BtfKind::Func | BtfKind::FuncProto => match opts.func_type {
+            Some(func_type) => func_type.to_string(),
+            None => format!("{}::ffi::c_void", opts.krate),
+        },
+        BtfKind::Fwd => format!("{}::ffi::c_void", opts.krate),
         BtfKind::Var(t) => type_declaration_impl(t.referenced_type(), anon_types, opts)?,
         _ => bail!("Invalid type: {ty:?}"),
     });
     Ok(s)
 }
 
-fn type_declaration(ty: BtfType<'_>, anon_types: &AnonTypes) -> Result<String> {
+fn type_declaration(
+    ty: BtfType<'_>,
+    anon_types: &AnonTypes,
+    krate: &'static str,
+) -> Result<String> {
     let opts = TypeDeclOpts {
-        func_type: "std::ffi::c_void",
+        func_type: None,
+        krate,
     };
     type_declaration_impl(ty, anon_types, &opts)
 }
@@ -187,17 +249,17 @@ fn type_declaration(ty: BtfType<'_>, anon_types: &AnonTypes) -> Result<String> {
 /// Rule of thumb is `ty` must be a type a variable can have.
 ///
 /// Type qualifiers are discarded (eg `const`, `volatile`, etc).
-fn type_default(ty: BtfType<'_>, anon_types: &AnonTypes) -> Result<String> {
+fn type_default(ty: BtfType<'_>, anon_types: &AnonTypes, krate: &'static str) -> Result<String> {
     let ty = ty.skip_mods_and_typedefs();
 
     Ok(btf_type_match!(match ty {
-        BtfKind::Int => format!("{}::default()", type_declaration(ty, anon_types)?),
-        BtfKind::Float => format!("{}::default()", type_declaration(ty, anon_types)?),
-        BtfKind::Ptr => "std::ptr::null_mut()".to_string(),
+        BtfKind::Int => format!("{}::default()", type_declaration(ty, anon_types, krate)?),
+        BtfKind::Float => format!("{}::default()", type_declaration(ty, anon_types, krate)?),
+        BtfKind::Ptr => format!("{krate}::ptr::null_mut()"),
         BtfKind::Array(t) => {
             format!(
                 "[{}; {}]",
-                type_default(t.contained_type(), anon_types)
+                type_default(t.contained_type(), anon_types, krate)
                     .map_err(|err| anyhow!("in {ty:?}: {err}"))?,
                 t.capacity()
             )
@@ -206,7 +268,7 @@ fn type_default(ty: BtfType<'_>, anon_types: &AnonTypes) -> Result<String> {
             format!("{}::default()", anon_types.type_name_or_anon(&ty)),
         BtfKind::Var(t) => format!(
             "{}::default()",
-            type_declaration(t.referenced_type(), anon_types)?
+            type_declaration(t.referenced_type(), anon_types, krate)?
         ),
         _ => bail!("Invalid type: {ty:?}"),
     }))
@@ -281,6 +343,9 @@ impl AnonTypes {
 pub struct GenBtf<'s> {
     btf: Btf<'s>,
     anon_types: AnonTypes,
+    /// The crate generated code refers to for language items, such as
+    /// `c_void` or `MaybeUninit`.
+    krate: &'static str,
 }
 
 impl<'s> From<Btf<'s>> for GenBtf<'s> {
@@ -288,6 +353,7 @@ impl<'s> From<Btf<'s>> for GenBtf<'s> {
         Self {
             btf,
             anon_types: Default::default(),
+            krate: "std",
         }
     }
 }
@@ -300,13 +366,20 @@ impl<'s> Deref for GenBtf<'s> {
 }
 
 impl<'s> GenBtf<'s> {
+    /// Refer to language items through `core` instead of `std` in generated
+    /// code, making type definitions usable in `no_std` environments.
+    pub fn no_std(mut self, no_std: bool) -> Self {
+        self.krate = if no_std { "core" } else { "std" };
+        self
+    }
+
     /// Returns the rust-ified type declaration of `ty` in string format.
     ///
     /// Rule of thumb is `ty` must be a type a variable can have.
     ///
     /// Type qualifiers are discarded (eg `const`, `volatile`, etc).
     pub fn type_declaration(&self, ty: BtfType<'s>) -> Result<String> {
-        type_declaration(ty, &self.anon_types)
+        type_declaration(ty, &self.anon_types, self.krate)
     }
 
     /// Returns an expression that evaluates to the Default value
@@ -318,7 +391,7 @@ impl<'s> GenBtf<'s> {
     ///
     /// Type qualifiers are discarded (eg `const`, `volatile`, etc).
     fn type_default(&self, ty: BtfType<'s>) -> Result<String> {
-        type_default(ty, &self.anon_types)
+        type_default(ty, &self.anon_types, self.krate)
     }
 
     /// Returns rust type definition of `ty` in string format, including dependent types.
@@ -380,6 +453,193 @@ impl<'s> GenBtf<'s> {
         Ok(def)
     }
 
+    /// Returns rust type definition of the named type `ty` in string format,
+    /// including dependent types.
+    ///
+    /// In addition to what [`GenBtf::type_definition`] supports, `ty` may be a
+    /// typedef, in which case a type alias is emitted.
+    pub fn named_type_definition(
+        &self,
+        ty: BtfType<'s>,
+        processed: &mut HashSet<TypeId>,
+    ) -> Result<String> {
+        let mut def = String::new();
+        match ty.kind() {
+            BtfKind::Struct | BtfKind::Union | BtfKind::Enum | BtfKind::Enum64 => {
+                def.push_str(&self.type_definition(ty, processed)?);
+            }
+            BtfKind::Typedef => {
+                let typedef = types::Typedef::try_from(ty).unwrap();
+                if let Some(next_ty) = next_type(typedef.resolve())? {
+                    def.push_str(&self.type_definition(next_ty, processed)?);
+                }
+                if processed.insert(ty.type_id()) {
+                    writeln!(
+                        def,
+                        "pub type {} = {};",
+                        self.anon_types.type_name_or_anon(&ty),
+                        self.type_declaration(typedef.resolve())?
+                    )?;
+                }
+            }
+            kind => bail!("unsupported type kind {kind:?}"),
+        }
+        Ok(def)
+    }
+
+    /// Returns rust type definitions of all types tagged for export with
+    /// [`EXPORT_DECL_TAG`], including dependent types.
+    pub fn exported_type_definitions(&self, processed: &mut HashSet<TypeId>) -> Result<String> {
+        let mut def = String::new();
+        for tag in self.type_by_kind::<types::DeclTag<'_>>() {
+            // Tags on members and parameters are not of interest.
+            if tag.component_index().is_some()
+                || tag.name().map(CStr::to_bytes) != Some(EXPORT_DECL_TAG.as_bytes())
+            {
+                continue;
+            }
+
+            let ty = tag.referenced_type();
+            let name = self.anon_types.type_name_or_anon(&ty);
+            let type_def = self
+                .named_type_definition(ty, processed)
+                .with_context(|| format!("failed to export type `{name}`"))?;
+            def.push_str(&type_def);
+        }
+        Ok(def)
+    }
+
+    /// Returns the IDs of all enums used by the object, in ascending order.
+    ///
+    /// An enum is considered used if it is referenced, directly or through
+    /// other types, by a global variable, a map definition, or a type
+    /// tagged for export. Types only referenced by programs' signatures
+    /// (e.g., kernel types pulled in via `vmlinux.h`) are not considered.
+    fn used_enums(&self) -> Vec<TypeId> {
+        let mut pending = Vec::new();
+        for sec in self.type_by_kind::<types::DataSec<'_>>() {
+            pending.extend(sec.iter().map(|var| var.ty));
+        }
+        for tag in self.type_by_kind::<types::DeclTag<'_>>() {
+            if tag.component_index().is_none()
+                && tag.name().map(CStr::to_bytes) == Some(EXPORT_DECL_TAG.as_bytes())
+            {
+                pending.push(tag.referenced_type().type_id());
+            }
+        }
+
+        let mut visited = HashSet::new();
+        let mut enums = Vec::new();
+        while let Some(id) = pending.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            let Some(ty) = self.type_by_id::<BtfType<'_>>(id) else {
+                continue;
+            };
+
+            match types::Type::from(ty) {
+                types::Type::Enum(_) | types::Type::Enum64(_) => enums.push(id),
+                types::Type::Struct(_) | types::Type::Union(_) => {
+                    // SANITY: We checked the kind.
+                    let composite = types::Composite::try_from(ty).unwrap();
+                    pending.extend(composite.iter().map(|member| member.ty))
+                }
+                types::Type::Array(t) => pending.push(t.contained_type().type_id()),
+                types::Type::Ptr(t) => pending.push(t.referenced_type().type_id()),
+                types::Type::Typedef(t) => pending.push(t.referenced_type().type_id()),
+                types::Type::Volatile(t) => pending.push(t.referenced_type().type_id()),
+                types::Type::Const(t) => pending.push(t.referenced_type().type_id()),
+                types::Type::Restrict(t) => pending.push(t.referenced_type().type_id()),
+                types::Type::TypeTag(t) => pending.push(t.referenced_type().type_id()),
+                types::Type::Var(t) => pending.push(t.referenced_type().type_id()),
+                _ => (),
+            }
+        }
+        enums.sort_unstable();
+        enums
+    }
+
+    /// Returns rust definitions of all named enums used by the object and
+    /// not yet processed, along with constants for the enumerators of all
+    /// used enums, named or not.
+    ///
+    /// Enums with multiple enumerators of the same value can't be mirrored
+    /// by a Rust enum, so only constants are emitted for them. Constants
+    /// with names defined with different values by different enums (as
+    /// may happen when linking multiple objects) are omitted.
+    pub fn enum_definitions(&self, processed: &mut HashSet<TypeId>) -> Result<String> {
+        let mut def = String::new();
+        let mut consts = Vec::<(String, String, String)>::new();
+        let mut conflicts = HashSet::new();
+        let mut add_const = |name: Option<&CStr>, ty: &str, value: String| -> Result<()> {
+            let name = match name {
+                Some(name) => name.to_str()?.to_string(),
+                None => return Ok(()),
+            };
+            match consts.iter().find(|(other, ..)| *other == name) {
+                Some((.., other_value)) if *other_value != value => {
+                    let _new = conflicts.insert(name);
+                }
+                Some(_) => (),
+                None => consts.push((name, ty.to_string(), value)),
+            }
+            Ok(())
+        };
+
+        let enums = self.used_enums();
+        for ty in enums
+            .iter()
+            .filter_map(|id| self.type_by_id::<types::Enum<'_>>(*id))
+        {
+            let repr = match ty.size() {
+                1 => "8",
+                2 => "16",
+                4 => "32",
+                8 => "64",
+                _ => bail!("Invalid enum size: {}", ty.size()),
+            };
+            let signed = if ty.iter().any(|value| value.value < 0) {
+                "i"
+            } else {
+                "u"
+            };
+            for value in ty.iter() {
+                let () = add_const(
+                    value.name,
+                    &format!("{signed}{repr}"),
+                    value.value.to_string(),
+                )?;
+            }
+
+            let values = ty.iter().map(|value| value.value).collect::<HashSet<_>>();
+            if ty.name().is_some() && !ty.is_empty() && values.len() == ty.len() {
+                def.push_str(&self.type_definition(*ty, processed)?);
+            }
+        }
+
+        for ty in enums
+            .iter()
+            .filter_map(|id| self.type_by_id::<types::Enum64<'_>>(*id))
+        {
+            for value in ty.iter() {
+                let () = if ty.is_signed() {
+                    add_const(value.name, "i64", (value.value as i64).to_string())?
+                } else {
+                    add_const(value.name, "u64", value.value.to_string())?
+                };
+            }
+        }
+
+        for (name, ty, value) in consts {
+            if !conflicts.contains(&name) {
+                let name = escape_reserved_keyword(Cow::Owned(name));
+                writeln!(def, "pub const {name}: {ty} = {value};")?;
+            }
+        }
+        Ok(def)
+    }
+
     pub fn struct_ops_type_definition(&self, processed: &mut HashSet<TypeId>) -> Result<String> {
         let mut def = String::new();
         let mut dependent_types = vec![];
@@ -485,7 +745,8 @@ impl struct_ops {{
                 BtfKind::Composite(t) => {
                     if vars.contains(&ty.type_id()) {
                         let opts = TypeDeclOpts {
-                            func_type: "libbpf_rs::libbpf_sys::bpf_program",
+                            func_type: Some("libbpf_rs::libbpf_sys::bpf_program"),
+                            krate: self.krate,
                         };
                         self.type_definition_for_composites_with_opts(
                             &mut def,
@@ -511,7 +772,8 @@ impl struct_ops {{
         t: types::Composite<'_>,
     ) -> Result<()> {
         let opts = TypeDeclOpts {
-            func_type: "std::ffi::c_void",
+            func_type: None,
+            krate: self.krate,
         };
         self.type_definition_for_composites_with_opts(def, dependent_types, t, &opts)
     }
@@ -598,7 +860,7 @@ impl struct_ops {{
             match self.type_default(field_ty) {
                 Ok(mut def) => {
                     if is_unsafe(field_ty) {
-                        def = format!("std::mem::MaybeUninit::new({def})")
+                        def = format!("{}::mem::MaybeUninit::new({def})", self.krate)
                     }
 
                     impl_default.push(format!(
@@ -618,7 +880,7 @@ impl struct_ops {{
 
             let field_ty_str = type_declaration_impl(field_ty, &self.anon_types, opts)?;
             let field_ty_str = if is_unsafe(field_ty) {
-                Cow::Owned(format!("std::mem::MaybeUninit<{field_ty_str}>"))
+                Cow::Owned(format!("{}::mem::MaybeUninit<{field_ty_str}>", self.krate))
             } else {
                 Cow::Borrowed(field_ty_str.as_str())
             };
@@ -684,12 +946,14 @@ impl struct_ops {{
             // write a Debug implementation for a union
             writeln!(
                 def,
-                r#"impl std::fmt::Debug for {} {{"#,
+                r#"impl {krate}::fmt::Debug for {} {{"#,
                 self.anon_types.type_name_or_anon(&t),
+                krate = self.krate,
             )?;
             writeln!(
                 def,
-                r#"    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{"#
+                r#"    fn fmt(&self, f: &mut {krate}::fmt::Formatter<'_>) -> {krate}::fmt::Result {{"#,
+                krate = self.krate,
             )?;
             writeln!(def, r#"        write!(f, "(???)")"#)?;
             writeln!(def, r#"    }}"#)?;
@@ -772,7 +1036,7 @@ impl struct_ops {{
             Some(s) => s.into_owned(),
         };
         sec_name.remove(0);
-        let sec_name = sec_name.replace('.', "_");
+        let sec_name = escape_raw_name(&sec_name);
 
         writeln!(def, r#"#[derive(Debug, Copy, Clone)]"#)?;
         writeln!(def, r#"#[repr(C)]"#)?;
@@ -815,7 +1079,7 @@ impl struct_ops {{
     }
 }
 
-fn next_type(mut t: BtfType<'_>) -> Result<Option<BtfType<'_>>> {
+pub(crate) fn next_type(mut t: BtfType<'_>) -> Result<Option<BtfType<'_>>> {
     loop {
         match t.kind() {
             BtfKind::Struct
@@ -0,0 +1,235 @@
+//! Machine-readable descriptions of generated skeletons.
+
+use std::ffi::CStr;
+use std::fs::File;
+use std::os::raw::c_char;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use anyhow::Result;
+use serde::Serialize;
+
+use libbpf_rs::btf::types;
+use libbpf_rs::btf::BtfType;
+use libbpf_rs::btf::TypeId;
+use libbpf_rs::libbpf_sys;
+use libbpf_rs::Btf;
+
+use memmap2::Mmap;
+
+use super::btf::GenBtf;
+use super::canonicalize_internal_map_name;
+use super::get_map_name;
+use super::get_prog_name;
+use super::get_prog_section;
+use super::get_raw_map_name;
+use super::open_bpf_object;
+use super::InternalMapType;
+use super::MapIter;
+use super::ProgIter;
+
+/// A program of a skeleton.
+#[derive(Debug, Serialize)]
+pub struct ProgramInfo {
+    /// The name of the program.
+    pub name: String,
+    /// The section the program is defined in.
+    pub section: String,
+    /// The program type, as named by libbpf (e.g., `kprobe`).
+    pub prog_type: Option<String>,
+}
+
+/// A map of a skeleton.
+#[derive(Debug, Serialize)]
+pub struct MapInfo {
+    /// The name of the map's accessor in the skeleton, if any.
+    pub name: Option<String>,
+    /// The name of the map in the object file.
+    pub raw_name: String,
+    /// The map type, as named by libbpf (e.g., `hash`).
+    pub map_type: Option<String>,
+    /// The Rust type of the map's keys, if described by BTF.
+    pub key_type: Option<String>,
+    /// The size of the map's keys, in bytes.
+    pub key_size: u32,
+    /// The Rust type of the map's values, if described by BTF.
+    pub value_type: Option<String>,
+    /// The size of the map's values, in bytes.
+    pub value_size: u32,
+    /// The maximum number of entries of the map.
+    pub max_entries: u32,
+}
+
+/// A global variable in a data section.
+#[derive(Debug, Serialize)]
+pub struct VariableInfo {
+    /// The name of the variable.
+    pub name: String,
+    /// The Rust type of the variable.
+    pub type_name: String,
+    /// The offset of the variable inside the data section, in bytes.
+    pub offset: u32,
+    /// The size of the variable, in bytes.
+    pub size: u32,
+}
+
+/// A data section holding global variables.
+#[derive(Debug, Serialize)]
+pub struct DatasecInfo {
+    /// The name of the data section's accessor and type in the skeleton
+    /// (e.g., `bss`).
+    pub name: String,
+    /// The name of the section in the object file (e.g., `.bss`).
+    pub section: String,
+    /// The non-static variables of the section.
+    pub variables: Vec<VariableInfo>,
+}
+
+/// A description of a skeleton and the object it is generated for.
+#[derive(Debug, Serialize)]
+pub struct SkeletonInfo {
+    /// The name of the skeleton's object.
+    pub name: String,
+    /// The path to the object file.
+    pub object: PathBuf,
+    /// The path the skeleton was written to, if any.
+    pub skeleton: Option<PathBuf>,
+    /// The name of the module containing the skeleton's types.
+    pub types_module: String,
+    /// The programs of the object.
+    pub programs: Vec<ProgramInfo>,
+    /// The maps of the object.
+    pub maps: Vec<MapInfo>,
+    /// The data sections of the object.
+    pub datasecs: Vec<DatasecInfo>,
+}
+
+/// Convert a string returned by libbpf, if any, into an owned one.
+fn libbpf_str(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned())
+}
+
+/// Retrieve the Rust declaration of the type with ID `id`, if any.
+fn type_name(btf: Option<&GenBtf<'_>>, id: u32) -> Result<Option<String>> {
+    let btf = match btf {
+        Some(btf) if id != 0 => btf,
+        _ => return Ok(None),
+    };
+    let ty = match btf.type_by_id::<BtfType<'_>>(TypeId::from(id)) {
+        Some(ty) => ty,
+        None => return Ok(None),
+    };
+    // The values of internal maps are data sections, which are
+    // represented by the type of the same name in the skeleton.
+    if let Ok(datasec) = types::DataSec::try_from(ty) {
+        let name = match datasec.name() {
+            Some(name) => canonicalize_internal_map_name(name.to_str()?),
+            None => None,
+        };
+        return Ok(name.map(|name| name.to_string()));
+    }
+    Ok(Some(btf.type_declaration(ty)?))
+}
+
+/// Describe the skeleton of the object named `name` at `obj_file_path`,
+/// which was written to `skeleton`, if anywhere.
+pub fn skeleton_info(
+    name: &str,
+    obj_file_path: &Path,
+    skeleton: Option<&Path>,
+) -> Result<SkeletonInfo> {
+    let file = File::open(obj_file_path)
+        .with_context(|| format!("failed to open BPF object `{}`", obj_file_path.display()))?;
+    let mmap = unsafe { Mmap::map(&file)? };
+    let mut object = open_bpf_object(&format!("{name}_bpf"), &mmap)?;
+    let obj_ptr = object.as_mut_ptr();
+    let btf = Btf::from_bpf_object(&object)?.map(GenBtf::from);
+
+    let programs = ProgIter::new(obj_ptr)
+        .map(|prog| {
+            Ok(ProgramInfo {
+                name: get_prog_name(prog)?,
+                section: get_prog_section(prog)?,
+                prog_type: libbpf_str(unsafe {
+                    libbpf_sys::libbpf_bpf_prog_type_str(libbpf_sys::bpf_program__type(prog))
+                }),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let maps = MapIter::new(obj_ptr)
+        .map(|map| {
+            let (key_id, value_id) = unsafe {
+                (
+                    libbpf_sys::bpf_map__btf_key_type_id(map),
+                    libbpf_sys::bpf_map__btf_value_type_id(map),
+                )
+            };
+            Ok(MapInfo {
+                name: get_map_name(map)?,
+                raw_name: get_raw_map_name(map)?,
+                map_type: libbpf_str(unsafe {
+                    libbpf_sys::libbpf_bpf_map_type_str(libbpf_sys::bpf_map__type(map))
+                }),
+                key_type: type_name(btf.as_ref(), key_id)?,
+                key_size: unsafe { libbpf_sys::bpf_map__key_size(map) },
+                value_type: type_name(btf.as_ref(), value_id)?,
+                value_size: unsafe { libbpf_sys::bpf_map__value_size(map) },
+                max_entries: unsafe { libbpf_sys::bpf_map__max_entries(map) },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut datasecs = Vec::new();
+    if let Some(btf) = &btf {
+        for ty in btf.type_by_kind::<types::DataSec<'_>>() {
+            let section = match ty.name() {
+                Some(s) => s.to_str()?,
+                None => continue,
+            };
+            let name = match canonicalize_internal_map_name(section) {
+                None | Some(InternalMapType::StructOps) => continue,
+                Some(name) => name.to_string(),
+            };
+
+            let mut variables = Vec::new();
+            for datasec_var in ty.iter() {
+                let var = match btf.type_by_id::<types::Var<'_>>(datasec_var.ty) {
+                    Some(var) if var.linkage() != types::Linkage::Static => var,
+                    _ => continue,
+                };
+                // Anonymous variables have no accessor in the skeleton.
+                let var_name = match var.name() {
+                    Some(name) => name.to_string_lossy().into_owned(),
+                    None => continue,
+                };
+                variables.push(VariableInfo {
+                    name: var_name,
+                    type_name: btf.type_declaration(*var)?,
+                    offset: datasec_var.offset,
+                    size: datasec_var.size as u32,
+                });
+            }
+
+            datasecs.push(DatasecInfo {
+                name,
+                section: section.to_string(),
+                variables,
+            });
+        }
+    }
+
+    Ok(SkeletonInfo {
+        name: name.to_string(),
+        object: obj_file_path.to_path_buf(),
+        skeleton: skeleton.map(Path::to_path_buf),
+        types_module: format!("{name}_types"),
+        programs,
+        maps,
+        datasecs,
+    })
+}
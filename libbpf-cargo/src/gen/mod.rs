@@ -1,4 +1,5 @@
 pub mod btf;
+pub mod json;
 
 use std::borrow::Cow;
 use std::collections::BTreeMap;
@@ -10,6 +11,7 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
 use std::fmt::Write as fmt_write;
+use std::fs::read;
 use std::fs::File;
 use std::io::stdout;
 use std::io::ErrorKind;
@@ -29,19 +31,54 @@ use anyhow::Context;
 use anyhow::Result;
 
 use libbpf_rs::btf::types;
+use libbpf_rs::btf::BtfKind;
+use libbpf_rs::btf::BtfType;
+use libbpf_rs::btf::TypeId;
 use libbpf_rs::libbpf_sys;
 use libbpf_rs::Btf;
+use libbpf_rs::MapType;
 
 use memmap2::Mmap;
+#[cfg(feature = "zstd")]
+use zstd::encode_all;
 
 use crate::metadata;
 use crate::metadata::UnprocessedObj;
 
+use self::btf::has_implicit_padding;
+use self::btf::next_type;
 use self::btf::GenBtf;
 
+/// The zstd compression level used for objects embedded in skeletons.
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 19;
+
+/// Compress the object `data` for embedding it in a skeleton.
+#[cfg(feature = "zstd")]
+fn compress_object(data: &[u8]) -> Result<Vec<u8>> {
+    encode_all(data, ZSTD_LEVEL).map_err(Into::into)
+}
+
+/// Compress the object `data` for embedding it in a skeleton.
+#[cfg(not(feature = "zstd"))]
+fn compress_object(_data: &[u8]) -> Result<Vec<u8>> {
+    bail!("compressing objects requires the `zstd` feature of libbpf-cargo")
+}
+
 /// Escape certain characters in a "raw" name of a section, for example.
-fn escape_raw_name(name: &str) -> String {
-    name.replace('.', "_")
+///
+/// All characters that are not valid in a Rust identifier (such as `.` or
+/// `-`) are replaced with `_`.
+pub(crate) fn escape_raw_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
 }
 
 #[derive(Debug, PartialEq)]
@@ -260,6 +297,139 @@ fn get_prog_name(prog: *const libbpf_sys::bpf_program) -> Result<String> {
     Ok(unsafe { CStr::from_ptr(name_ptr) }.to_str()?.to_string())
 }
 
+fn get_prog_section(prog: *const libbpf_sys::bpf_program) -> Result<String> {
+    let sec_ptr = unsafe { libbpf_sys::bpf_program__section_name(prog) };
+    ensure!(!sec_ptr.is_null(), "Prog section unknown");
+
+    Ok(unsafe { CStr::from_ptr(sec_ptr) }.to_str()?.to_string())
+}
+
+/// Determine the signature of a typed attach method for a program defined
+/// in section `section`, as a tuple of generic parameters, method
+/// parameters, and the attach call to make with them.
+///
+/// Programs whose section fully describes the attach target are attached
+/// via [`libbpf_rs::Program::attach`] without any parameters. `None` is
+/// returned for programs that cannot be attached by themselves. With
+/// `no_std`, paths are accepted as strings, as `Path` is not available in
+/// `core`.
+pub(crate) fn prog_attach_signature(
+    section: &str,
+    no_std: bool,
+) -> Option<(&'static str, &'static str, String)> {
+    // Sections that libbpf auto-attaches programs of, either because the
+    // target is part of the section name or because it is implied by it.
+    // Prefixes are matched including the separator, so that, for
+    // example, sections without target like `uprobe.multi` are not
+    // mistaken for `uprobe/<target>`.
+    const AUTO_ATTACH: [&str; 28] = [
+        "kprobe/",
+        "kretprobe/",
+        "kprobe.multi/",
+        "kretprobe.multi/",
+        "ksyscall/",
+        "kretsyscall/",
+        "uprobe/",
+        "uprobe.s/",
+        "uretprobe/",
+        "uretprobe.s/",
+        "uprobe.multi/",
+        "uprobe.multi.s/",
+        "uretprobe.multi/",
+        "uretprobe.multi.s/",
+        "usdt/",
+        "tp/",
+        "tracepoint/",
+        "raw_tp/",
+        "raw_tracepoint/",
+        "raw_tp.w/",
+        "raw_tracepoint.w/",
+        "tp_btf/",
+        "fentry",
+        "fexit",
+        "fmod_ret",
+        "lsm/",
+        "lsm.s/",
+        "iter",
+    ];
+
+    let (generics, params, call) = match section {
+        "kprobe" | "kretprobe" => (
+            "",
+            "func_name: impl AsRef<str>",
+            format!("attach_kprobe({}, func_name)", section == "kretprobe"),
+        ),
+        "kprobe.multi" | "kretprobe.multi" => (
+            "<T: AsRef<str>>",
+            "symbols: impl IntoIterator<Item = T>",
+            format!(
+                "attach_kprobe_multi({}, symbols)",
+                section == "kretprobe.multi"
+            ),
+        ),
+        "ksyscall" | "kretsyscall" => (
+            "",
+            "syscall_name: impl AsRef<str>",
+            format!(
+                "attach_ksyscall({}, syscall_name)",
+                section == "kretsyscall"
+            ),
+        ),
+        "uprobe" | "uprobe.s" | "uretprobe" | "uretprobe.s" => (
+            "",
+            if no_std {
+                "pid: i32, binary_path: impl AsRef<str>, func_offset: usize"
+            } else {
+                "pid: i32, binary_path: impl AsRef<std::path::Path>, func_offset: usize"
+            },
+            format!(
+                "attach_uprobe({}, pid, binary_path.as_ref(), func_offset)",
+                section.starts_with("uretprobe")
+            ),
+        ),
+        "usdt" => (
+            "",
+            if no_std {
+                "pid: i32, binary_path: impl AsRef<str>, usdt_provider: impl AsRef<str>, usdt_name: impl AsRef<str>"
+            } else {
+                "pid: i32, binary_path: impl AsRef<std::path::Path>, usdt_provider: impl AsRef<str>, usdt_name: impl AsRef<str>"
+            },
+            "attach_usdt(pid, binary_path.as_ref(), usdt_provider, usdt_name)".to_string(),
+        ),
+        "tp" | "tracepoint" => (
+            "",
+            "tp_category: impl AsRef<str>, tp_name: impl AsRef<str>",
+            "attach_tracepoint(tp_category, tp_name)".to_string(),
+        ),
+        "raw_tp" | "raw_tracepoint" => (
+            "",
+            "tp_name: impl AsRef<str>",
+            "attach_raw_tracepoint(tp_name)".to_string(),
+        ),
+        "raw_tp.w" | "raw_tracepoint.w" => (
+            "",
+            "tp_name: impl AsRef<str>",
+            "attach_raw_tracepoint_writable(tp_name)".to_string(),
+        ),
+        "xdp" | "xdp.frags" => ("", "ifindex: i32", "attach_xdp(ifindex)".to_string()),
+        "sk_lookup" => ("", "netns_fd: i32", "attach_netns(netns_fd)".to_string()),
+        "perf_event" => ("", "pfd: i32", "attach_perf_event(pfd)".to_string()),
+        _ if section.starts_with("lsm_cgroup") => (
+            "",
+            "cgroup_fd: i32",
+            "attach_lsm_cgroup(cgroup_fd)".to_string(),
+        ),
+        _ if section.starts_with("cgroup") || section == "sockops" => {
+            ("", "cgroup_fd: i32", "attach_cgroup(cgroup_fd)".to_string())
+        }
+        _ if AUTO_ATTACH.iter().any(|prefix| section.starts_with(prefix)) => {
+            ("", "", "attach()".to_string())
+        }
+        _ => return None,
+    };
+    Some((generics, params, call))
+}
+
 fn map_is_mmapable(map: *const libbpf_sys::bpf_map) -> bool {
     (unsafe { libbpf_sys::bpf_map__map_flags(map) } & libbpf_sys::BPF_F_MMAPABLE) > 0
 }
@@ -271,6 +441,38 @@ fn map_is_datasec(map: *const libbpf_sys::bpf_map) -> bool {
     internal && mmapable
 }
 
+fn map_is_struct_ops(map: *const libbpf_sys::bpf_map) -> bool {
+    unsafe { libbpf_sys::bpf_map__type(map) == libbpf_sys::BPF_MAP_TYPE_STRUCT_OPS }
+}
+
+/// Check whether the skeleton of `object` has any links, i.e., whether the
+/// object contains any programs or `struct_ops` maps.
+fn has_links(object: &mut BpfObj) -> bool {
+    ProgIter::new(object.as_mut_ptr()).next().is_some()
+        || MapIter::new(object.as_mut_ptr()).any(|map| map_is_struct_ops(map))
+}
+
+/// Ensure that the Rust names of all datasec maps of `object` are unique.
+///
+/// Custom data sections may differ only in characters that get escaped,
+/// in which case we would generate conflicting accessors and types.
+fn check_datasec_names(object: &mut BpfObj) -> Result<()> {
+    let mut names = BTreeMap::new();
+    for map in MapIter::new(object.as_mut_ptr()) {
+        if !map_is_datasec(map) {
+            continue;
+        }
+
+        let raw_name = get_raw_map_name(map)?;
+        if let Some(name) = get_map_name(map)? {
+            if let Some(other) = names.insert(name.clone(), raw_name.clone()) {
+                bail!("sections `{other}` and `{raw_name}` both map to Rust name `{name}`");
+            }
+        }
+    }
+    Ok(())
+}
+
 fn map_is_readonly(map: *const libbpf_sys::bpf_map) -> bool {
     assert!(map_is_mmapable(map));
 
@@ -278,13 +480,123 @@ fn map_is_readonly(map: *const libbpf_sys::bpf_map) -> bool {
     (unsafe { libbpf_sys::bpf_map__map_flags(map) } & libbpf_sys::BPF_F_RDONLY_PROG) > 0
 }
 
-fn gen_skel_c_skel_constructor(skel: &mut String, object: &mut BpfObj, name: &str) -> Result<()> {
+/// Retrieve the BTF key and value types of `map`, if typed accessors can be
+/// generated for it.
+fn map_btf_types<'btf>(
+    btf: &'btf GenBtf<'_>,
+    map: *const libbpf_sys::bpf_map,
+) -> Option<(BtfType<'btf>, BtfType<'btf>)> {
+    if map_is_datasec(map) {
+        return None;
+    }
+
+    let map_type = MapType::try_from(unsafe { libbpf_sys::bpf_map__type(map) }).ok()?;
+    if map_type.is_percpu() || map_type == MapType::StructOps {
+        return None;
+    }
+
+    let key = unsafe { libbpf_sys::bpf_map__btf_key_type_id(map) };
+    let value = unsafe { libbpf_sys::bpf_map__btf_value_type_id(map) };
+    if key == 0 || value == 0 {
+        return None;
+    }
+
+    let key = btf.type_by_id(TypeId::from(key))?;
+    let value = btf.type_by_id(TypeId::from(value))?;
+    Some((key, value))
+}
+
+/// Returns the Rust type to use for `ty` as key or value type of a typed
+/// map accessor, or `None` if `ty` is not suitable, because not every bit
+/// pattern is a valid value of it or because its Rust definition contains
+/// bytes not covered by any field (e.g., unions with members of different
+/// sizes), which are uninitialized when accessed as raw bytes.
+///
+/// Types defined in the skeleton's types module are qualified with
+/// `types_mod`.
+fn typed_map_decl(btf: &GenBtf<'_>, ty: BtfType<'_>, types_mod: &str) -> Result<Option<String>> {
+    let ty = ty.skip_mods_and_typedefs();
+    let decl = match ty.kind() {
+        BtfKind::Int => {
+            let int = types::Int::try_from(ty).unwrap();
+            if matches!(int.encoding, types::IntEncoding::Bool) {
+                return Ok(None);
+            }
+            btf.type_declaration(ty)?
+        }
+        BtfKind::Float => btf.type_declaration(ty)?,
+        BtfKind::Array => {
+            let array = types::Array::try_from(ty).unwrap();
+            match typed_map_decl(btf, array.contained_type(), types_mod)? {
+                Some(elem) => format!("[{elem}; {}]", array.capacity()),
+                None => return Ok(None),
+            }
+        }
+        BtfKind::Struct | BtfKind::Union => {
+            // Names of anonymous types are not stable across `GenBtf`
+            // instances.
+            let name = match ty.name() {
+                Some(name) => name.to_str()?,
+                None => return Ok(None),
+            };
+            let composite = types::Composite::try_from(ty).unwrap();
+            if has_implicit_padding(&composite, btf)? {
+                return Ok(None);
+            }
+            for member in composite.iter() {
+                let member_ty = btf.type_by_id::<BtfType<'_>>(member.ty).unwrap();
+                if typed_map_decl(btf, member_ty, types_mod)?.is_none() {
+                    return Ok(None);
+                }
+            }
+            format!("{types_mod}::{name}")
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(decl))
+}
+
+/// Determine the Rust key and value types of all maps of `object` for which
+/// typed accessors can be generated, keyed by raw map name.
+fn typed_map_types(
+    object: &mut BpfObj,
+    raw_obj_name: &str,
+) -> Result<BTreeMap<String, (String, String)>> {
+    let mut typed = BTreeMap::new();
+    let obj_ptr = object.as_mut_ptr();
+    let btf = match Btf::from_bpf_object(object)? {
+        Some(btf) => GenBtf::from(btf),
+        None => return Ok(typed),
+    };
+    let types_mod = format!("{raw_obj_name}_types");
+
+    for map in MapIter::new(obj_ptr) {
+        let (key, value) = match map_btf_types(&btf, map) {
+            Some(types) => types,
+            None => continue,
+        };
+        let key = typed_map_decl(&btf, key, &types_mod)?;
+        let value = typed_map_decl(&btf, value, &types_mod)?;
+        if let (Some(key), Some(value)) = (key, value) {
+            let _prev = typed.insert(get_raw_map_name(map)?, (key, value));
+        }
+    }
+    Ok(typed)
+}
+
+fn gen_skel_c_skel_constructor(
+    skel: &mut String,
+    object: &mut BpfObj,
+    name: &str,
+    compress: bool,
+) -> Result<()> {
+    let data = if compress { "OBJECT.data()?" } else { "DATA" };
     write!(
         skel,
         r#"
         fn build_skel_config() -> libbpf_rs::Result<libbpf_rs::__internal_skel::ObjectSkeletonConfig<'static>>
         {{
-            let mut builder = libbpf_rs::__internal_skel::ObjectSkeletonConfigBuilder::new(DATA);
+            let mut builder = libbpf_rs::__internal_skel::ObjectSkeletonConfigBuilder::new({data});
             builder
                 .name("{name}")
         "#,
@@ -334,8 +646,16 @@ fn gen_skel_map_defs(
     skel: &mut String,
     object: &mut BpfObj,
     obj_name: &str,
+    raw_obj_name: &str,
     open: bool,
 ) -> Result<()> {
+    // Typed accessors are only available once maps have been created.
+    let typed = if open {
+        BTreeMap::new()
+    } else {
+        typed_map_types(object, raw_obj_name)?
+    };
+
     let mut gen = |mutable| -> Result<()> {
         if MapIter::new(object.as_mut_ptr()).next().is_none() {
             return Ok(());
@@ -391,6 +711,20 @@ fn gen_skel_map_defs(
                 mut_prefix = mut_prefix,
                 map_fn = map_fn
             )?;
+
+            if let Some((key_ty, value_ty)) = typed.get(&get_raw_map_name(map)?) {
+                write!(
+                    skel,
+                    r#"
+                    pub fn {map_name}_typed(&self) -> libbpf_rs::Result<libbpf_rs::TypedMap<'_, {key_ty}, {value_ty}>> {{
+                        // SAFETY: The key and value types were generated
+                        //         from the map's BTF.
+                        unsafe {{ libbpf_rs::TypedMap::new(self.inner.map("{raw_map_name}").unwrap()) }}
+                    }}
+                    "#,
+                    raw_map_name = get_raw_map_name(map)?,
+                )?;
+            }
         }
 
         writeln!(skel, "}}")?;
@@ -409,6 +743,7 @@ fn gen_skel_prog_defs(
     obj_name: &str,
     open: bool,
     mutable: bool,
+    no_std: bool,
 ) -> Result<()> {
     if ProgIter::new(object.as_mut_ptr()).next().is_none() {
         return Ok(());
@@ -458,6 +793,26 @@ fn gen_skel_prog_defs(
             mut_prefix = mut_prefix,
             prog_fn = prog_fn
         )?;
+
+        // Attaching requires a loaded program and mutable access to it.
+        if open || !mutable {
+            continue;
+        }
+
+        let section = get_prog_section(prog)?;
+        if let Some((generics, params, call)) = prog_attach_signature(&section, no_std) {
+            write!(
+                skel,
+                r#"
+                /// Attach the `{prog_name}` program, as appropriate for
+                /// programs in section `{section}`.
+                pub fn attach_{prog_name}{generics}(&mut self, {params}) -> libbpf_rs::Result<libbpf_rs::Link> {{
+                    self.inner.prog_mut("{prog_name}").unwrap().{call}
+                }}
+                "#,
+                prog_name = get_prog_name(prog)?,
+            )?;
+        }
     }
 
     writeln!(skel, "}}")?;
@@ -465,13 +820,16 @@ fn gen_skel_prog_defs(
     Ok(())
 }
 
-fn gen_skel_datasec_types(skel: &mut String, object: &BpfObj) -> Result<()> {
+/// Generate the definitions of the types of global variables as well as of
+/// the key and value types of maps with typed accessors.
+fn gen_skel_datasec_types(skel: &mut String, object: &mut BpfObj, no_std: bool) -> Result<()> {
+    let obj_ptr = object.as_mut_ptr();
     let btf = if let Some(btf) = Btf::from_bpf_object(object)? {
         btf
     } else {
         return Ok(());
     };
-    let btf = GenBtf::from(btf);
+    let btf = GenBtf::from(btf).no_std(no_std);
 
     let mut processed = HashSet::new();
     for ty in btf.type_by_kind::<types::DataSec<'_>>() {
@@ -490,15 +848,41 @@ fn gen_skel_datasec_types(skel: &mut String, object: &BpfObj) -> Result<()> {
         let sec_def = btf.type_definition(*ty, &mut processed)?;
         write!(skel, "{sec_def}")?;
     }
+
+    let exported = btf.exported_type_definitions(&mut processed)?;
+    write!(skel, "{exported}")?;
+
+    let enums = btf.enum_definitions(&mut processed)?;
+    write!(skel, "{enums}")?;
+
+    for map in MapIter::new(obj_ptr) {
+        let (key, value) = match map_btf_types(&btf, map) {
+            Some(types) => types,
+            None => continue,
+        };
+        // The module name doesn't matter here, we just check whether an
+        // accessor will be generated.
+        if typed_map_decl(&btf, key, "")?.is_none() || typed_map_decl(&btf, value, "")?.is_none() {
+            continue;
+        }
+
+        for ty in [key, value] {
+            if let Some(ty) = next_type(ty)? {
+                let def = btf.type_definition(ty, &mut processed)?;
+                write!(skel, "{def}")?;
+            }
+        }
+    }
     Ok(())
 }
 
 fn gen_skel_struct_ops_types(
     skel: &mut String,
     object: &BpfObj, /*, programs: &mut HashMap*/
+    no_std: bool,
 ) -> Result<()> {
     if let Some(btf) = Btf::from_bpf_object(object)? {
-        let btf = GenBtf::from(btf);
+        let btf = GenBtf::from(btf).no_std(no_std);
 
         let mut processed = HashSet::new();
         let def = btf.struct_ops_type_definition(&mut processed)?;
@@ -674,7 +1058,7 @@ fn gen_skel_datasec_getters(
 }
 
 fn gen_skel_link_defs(skel: &mut String, object: &mut BpfObj, obj_name: &str) -> Result<()> {
-    if ProgIter::new(object.as_mut_ptr()).next().is_none() {
+    if !has_links(object) {
         return Ok(());
     }
 
@@ -695,13 +1079,22 @@ fn gen_skel_link_defs(skel: &mut String, object: &mut BpfObj, obj_name: &str) ->
         )?;
     }
 
+    for map in MapIter::new(object.as_mut_ptr()).filter(|map| map_is_struct_ops(*map)) {
+        write!(
+            skel,
+            r#"pub {}: Option<libbpf_rs::Link>,
+            "#,
+            get_raw_map_name(map)?
+        )?;
+    }
+
     writeln!(skel, "}}")?;
 
     Ok(())
 }
 
 fn gen_skel_link_getter(skel: &mut String, object: &mut BpfObj, obj_name: &str) -> Result<()> {
-    if ProgIter::new(object.as_mut_ptr()).next().is_none() {
+    if !has_links(object) {
         return Ok(());
     }
 
@@ -734,20 +1127,61 @@ fn open_bpf_object(name: &str, data: &[u8]) -> Result<BpfObj> {
 }
 
 fn gen_skel_attach(skel: &mut String, object: &mut BpfObj, obj_name: &str) -> Result<()> {
-    if ProgIter::new(object.as_mut_ptr()).next().is_none() {
+    if !has_links(object) {
         return Ok(());
     }
 
+    // libbpf skips programs and maps that already have a link in their
+    // skeleton slot. We synchronize the slots with the links we hold
+    // before attaching, so that links created via `attach_prog` are left
+    // in place and links dropped by the user are not referenced anymore.
     write!(
         skel,
         r#"
         fn attach(&mut self) -> libbpf_rs::Result<()> {{
+        "#,
+    )?;
+
+    for (idx, prog) in ProgIter::new(object.as_mut_ptr()).enumerate() {
+        let prog_name = get_prog_name(prog)?;
+
+        write!(
+            skel,
+            r#"let () = self.skel_config.set_prog_link_ptr(
+                {idx},
+                self.links.{prog_name}.as_ref().map_or(core::ptr::null_mut(), |link| {{
+                    libbpf_rs::AsRawLibbpf::as_libbpf_object(link).as_ptr()
+                }}),
+            )?;
+            "#
+        )?;
+    }
+
+    for (idx, map) in MapIter::new(object.as_mut_ptr()).enumerate() {
+        if !map_is_struct_ops(map) {
+            continue;
+        }
+        let map_name = get_raw_map_name(map)?;
+
+        write!(
+            skel,
+            r#"let () = self.skel_config.set_map_link_ptr(
+                {idx},
+                self.links.{map_name}.as_ref().map_or(core::ptr::null_mut(), |link| {{
+                    libbpf_rs::AsRawLibbpf::as_libbpf_object(link).as_ptr()
+                }}),
+            )?;
+            "#
+        )?;
+    }
+
+    write!(
+        skel,
+        r#"
             let ret = unsafe {{ libbpf_sys::bpf_object__attach_skeleton(self.skel_config.get()) }};
-            if ret != 0 {{
-                return Err(libbpf_rs::Error::from_raw_os_error(-ret));
-            }}
 
-            self.links = {obj_name}Links {{
+            // Take ownership of the links libbpf created, even if attaching
+            // failed part way through.
         "#,
     )?;
 
@@ -756,8 +1190,28 @@ fn gen_skel_attach(skel: &mut String, object: &mut BpfObj, obj_name: &str) -> Re
 
         write!(
             skel,
-            r#"{prog_name}: core::ptr::NonNull::new(self.skel_config.prog_link_ptr({idx})?)
-                        .map(|ptr| unsafe {{ libbpf_rs::Link::from_ptr(ptr) }}),
+            r#"if self.links.{prog_name}.is_none() {{
+                if let Some(ptr) = core::ptr::NonNull::new(self.skel_config.prog_link_ptr({idx})?) {{
+                    self.links.{prog_name} = Some(unsafe {{ libbpf_rs::Link::from_ptr(ptr) }});
+                }}
+            }}
+            "#
+        )?;
+    }
+
+    for (idx, map) in MapIter::new(object.as_mut_ptr()).enumerate() {
+        if !map_is_struct_ops(map) {
+            continue;
+        }
+        let map_name = get_raw_map_name(map)?;
+
+        write!(
+            skel,
+            r#"if self.links.{map_name}.is_none() {{
+                if let Some(ptr) = core::ptr::NonNull::new(self.skel_config.map_link_ptr({idx})?) {{
+                    self.links.{map_name} = Some(unsafe {{ libbpf_rs::Link::from_ptr(ptr) }});
+                }}
+            }}
             "#
         )?;
     }
@@ -765,9 +1219,7 @@ fn gen_skel_attach(skel: &mut String, object: &mut BpfObj, obj_name: &str) -> Re
     write!(
         skel,
         r#"
-            }};
-
-            Ok(())
+            libbpf_rs::__internal_skel::check_call("bpf_object__attach_skeleton", "{obj_name}", ret)
         }}
         "#,
     )?;
@@ -775,72 +1227,337 @@ fn gen_skel_attach(skel: &mut String, object: &mut BpfObj, obj_name: &str) -> Re
     Ok(())
 }
 
-fn gen_skel_struct_ops_init(object: &mut BpfObj) -> Result<String> {
-    let mut def = String::new();
+/// Retrieve the path `map` gets pinned at by default, if it is declared
+/// to be pinned.
+fn get_map_pin_path(map: *const libbpf_sys::bpf_map) -> Result<Option<String>> {
+    let path_ptr = unsafe { libbpf_sys::bpf_map__pin_path(map) };
+    if path_ptr.is_null() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        unsafe { CStr::from_ptr(path_ptr) }.to_str()?.to_string(),
+    ))
+}
 
+/// Generate constants for the default pin paths of pinned maps, along
+/// with a type for opening all of them from a separate process.
+///
+/// Opening maps below a custom pin root path requires `std`, and is not
+/// supported with `no_std`.
+fn gen_skel_pins(
+    skel: &mut String,
+    object: &mut BpfObj,
+    obj_name: &str,
+    raw_obj_name: &str,
+    no_std: bool,
+) -> Result<()> {
+    let mut pinned = Vec::new();
+    let mut const_names = BTreeMap::new();
     for map in MapIter::new(object.as_mut_ptr()) {
-        let type_ = unsafe { libbpf_sys::bpf_map__type(map) };
-        if type_ != libbpf_sys::BPF_MAP_TYPE_STRUCT_OPS {
-            continue;
+        if let Some(path) = get_map_pin_path(map)? {
+            let name = match get_map_name(map)? {
+                Some(name) => name,
+                None => continue,
+            };
+            // Map names differing only in case would result in
+            // conflicting constants.
+            let const_name = name.to_uppercase();
+            if let Some(other) = const_names.insert(const_name.clone(), name.clone()) {
+                bail!("maps `{other}` and `{name}` both map to pin path constant `{const_name}`");
+            }
+            let () = pinned.push((name, get_raw_map_name(map)?, path));
         }
+    }
 
-        let raw_name = get_raw_map_name(map)?;
+    if pinned.is_empty() {
+        return Ok(());
+    }
 
+    write!(
+        skel,
+        r#"
+        /// The paths maps declared as pinned get pinned at by default.
+        pub mod {raw_obj_name}_pins {{
+        "#,
+    )?;
+    for (name, _raw_name, path) in &pinned {
         write!(
-            def,
+            skel,
             r#"
-            skel.struct_ops.{raw_name} = skel.maps_mut().{raw_name}().initial_value_mut().unwrap().as_mut_ptr().cast();
+            pub const {const_name}: &str = "{path}";
             "#,
+            const_name = name.to_uppercase(),
         )?;
     }
-    Ok(def)
-}
-
-/// Generate contents of a single skeleton
-fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) -> Result<String> {
-    let mut skel = String::new();
+    writeln!(skel, "}}")?;
 
     write!(
         skel,
-        r#"// SPDX-License-Identifier: (LGPL-2.1 OR BSD-2-Clause)
-           //
-           // THIS FILE IS AUTOGENERATED BY CARGO-LIBBPF-GEN!
-
-           pub use self::imp::*;
-
-           #[allow(dead_code)]
-           #[allow(non_snake_case)]
-           #[allow(non_camel_case_types)]
-           #[allow(clippy::absolute_paths)]
-           #[allow(clippy::transmute_ptr_to_ref)]
-           #[allow(clippy::upper_case_acronyms)]
-           #[warn(single_use_lifetimes)]
-           mod imp {{
-           #[allow(unused_imports)]
-           use super::*;
-           use libbpf_rs::libbpf_sys;
-           use libbpf_rs::skel::OpenSkel;
-           use libbpf_rs::skel::Skel;
-           use libbpf_rs::skel::SkelBuilder;
-        "#
+        r#"
+        /// Handles to the maps declared as pinned, opened from their pins.
+        pub struct {obj_name}PinnedMaps {{
+        "#,
     )?;
+    for (name, _raw_name, _path) in &pinned {
+        write!(
+            skel,
+            r#"
+            pub {name}: libbpf_rs::MapHandle,
+            "#,
+        )?;
+    }
+    write!(
+        skel,
+        r#"
+        }}
 
-    // The name we'll always hand to libbpf
-    //
-    // Note it's important this remains consistent b/c libbpf infers map/prog names from this name
-    let libbpf_obj_name = format!("{raw_obj_name}_bpf");
-    // We'll use `obj_name` as the rust-ified object name
-    let obj_name = capitalize_first_letter(raw_obj_name);
-
-    // Open bpf_object so we can iterate over maps and progs
-    let file = File::open(obj_file_path)
-        .with_context(|| format!("failed to open BPF object `{}`", obj_file_path.display()))?;
-    let mmap = unsafe { Mmap::map(&file)? };
-    let mut object = open_bpf_object(&libbpf_obj_name, &mmap)?;
+        impl {obj_name}PinnedMaps {{
+            /// Open all pinned maps at their default pin paths.
+            pub fn open_pinned() -> libbpf_rs::Result<Self> {{
+                Ok(Self {{
+        "#,
+    )?;
+    for (name, _raw_name, _path) in &pinned {
+        write!(
+            skel,
+            r#"
+                    {name}: libbpf_rs::MapHandle::from_pinned_path({raw_obj_name}_pins::{const_name})?,
+            "#,
+            const_name = name.to_uppercase(),
+        )?;
+    }
+    writeln!(
+        skel,
+        r#"
+                }})
+            }}"#,
+    )?;
 
-    gen_skel_c_skel_constructor(&mut skel, &mut object, &libbpf_obj_name)?;
+    if no_std {
+        writeln!(skel, "}}")?;
+        return Ok(());
+    }
 
-    #[allow(clippy::uninlined_format_args)]
+    write!(
+        skel,
+        r#"
+            /// Open all pinned maps inside `pin_root_path`, for use with
+            /// objects opened with a custom pin root path.
+            pub fn open_pinned_in<P: AsRef<std::path::Path>>(pin_root_path: P) -> libbpf_rs::Result<Self> {{
+                let pin_root_path = pin_root_path.as_ref();
+                Ok(Self {{
+        "#,
+    )?;
+    for (name, raw_name, _path) in &pinned {
+        write!(
+            skel,
+            r#"
+                    {name}: libbpf_rs::MapHandle::from_pinned_path(pin_root_path.join("{raw_name}"))?,
+            "#,
+        )?;
+    }
+    write!(
+        skel,
+        r#"
+                }})
+            }}
+        }}
+        "#,
+    )?;
+
+    Ok(())
+}
+
+/// Generate a method for pinning the links of a loaded skeleton and
+/// keeping them in place once it is dropped.
+///
+/// Pinning requires `std` paths, so nothing is generated with `no_std`.
+fn gen_skel_persist(skel: &mut String, object: &mut BpfObj, no_std: bool) -> Result<()> {
+    if no_std || !has_links(object) {
+        return Ok(());
+    }
+
+    write!(
+        skel,
+        r#"
+        /// Pin all current links under the directory `path` on a bpffs and
+        /// leave them in place once they (or the skeleton) are dropped,
+        /// instead of detaching the programs.
+        ///
+        /// Each link is pinned as `<path>/<name>`, with `<name>` being the
+        /// name of the program (or `struct_ops` map) it belongs to, unless
+        /// it is pinned already. The programs hence stay attached after the
+        /// process exits, until the pins are removed. Should pinning any
+        /// link fail, pins created up to that point are removed again and
+        /// no link is persisted. Pinned maps are not unpinned on drop
+        /// either way.
+        pub fn persist<P: AsRef<std::path::Path>>(&mut self, path: P) -> libbpf_rs::Result<()> {{
+            let mut links = [
+        "#,
+    )?;
+
+    let names = ProgIter::new(object.as_mut_ptr())
+        .map(|prog| get_prog_name(prog))
+        .chain(
+            MapIter::new(object.as_mut_ptr())
+                .filter(|map| map_is_struct_ops(*map))
+                .map(|map| get_raw_map_name(map)),
+        )
+        .collect::<Result<Vec<_>>>()?;
+
+    for name in names {
+        write!(
+            skel,
+            r#"
+                ("{name}", self.links.{name}.as_mut()),
+            "#,
+        )?;
+    }
+
+    write!(
+        skel,
+        r#"
+            ];
+            libbpf_rs::__internal_skel::persist_links(path.as_ref(), &mut links)
+        }}
+        "#,
+    )?;
+
+    Ok(())
+}
+
+/// Generate methods for attaching individual programs of a loaded
+/// skeleton by name.
+fn gen_skel_attach_progs(skel: &mut String, object: &mut BpfObj) -> Result<()> {
+    if ProgIter::new(object.as_mut_ptr()).next().is_none() {
+        return Ok(());
+    }
+
+    write!(
+        skel,
+        r#"
+        /// Attach the program `name` in the same manner as `attach` would,
+        /// storing the resulting link in `links`.
+        ///
+        /// In contrast to `attach`, the program is attached regardless of
+        /// its autoattach setting. A later `attach` leaves the program's
+        /// link in place.
+        pub fn attach_prog(&mut self, name: &str) -> libbpf_rs::Result<()> {{
+            match name {{
+        "#,
+    )?;
+
+    for prog in ProgIter::new(object.as_mut_ptr()) {
+        let prog_name = get_prog_name(prog)?;
+        write!(
+            skel,
+            r#"
+                "{prog_name}" => {{
+                    let link = self.obj.prog_mut("{prog_name}").unwrap().attach()?;
+                    self.links.{prog_name} = Some(link);
+                }}
+            "#,
+        )?;
+    }
+
+    write!(
+        skel,
+        r#"
+                _ => return Err(libbpf_rs::__internal_skel::prog_not_found(name)),
+            }}
+            Ok(())
+        }}
+
+        /// Attach only the programs in `names`, in the given order.
+        ///
+        /// Programs attached before an error is encountered stay attached.
+        pub fn attach_only(&mut self, names: &[&str]) -> libbpf_rs::Result<()> {{
+            for name in names {{
+                let () = self.attach_prog(name)?;
+            }}
+            Ok(())
+        }}
+        "#,
+    )?;
+
+    Ok(())
+}
+
+fn gen_skel_struct_ops_init(object: &mut BpfObj) -> Result<String> {
+    let mut def = String::new();
+
+    for map in MapIter::new(object.as_mut_ptr()) {
+        if !map_is_struct_ops(map) {
+            continue;
+        }
+
+        let raw_name = get_raw_map_name(map)?;
+
+        write!(
+            def,
+            r#"
+            skel.struct_ops.{raw_name} = skel.maps_mut().{raw_name}().initial_value_mut().unwrap().as_mut_ptr().cast();
+            "#,
+        )?;
+    }
+    Ok(def)
+}
+
+/// Generate contents of a single skeleton
+fn gen_skel_contents(
+    _debug: bool,
+    raw_obj_name: &str,
+    obj_file_path: &Path,
+    no_std: bool,
+    compress: bool,
+) -> Result<String> {
+    let mut skel = String::new();
+    let krate = if no_std { "core" } else { "std" };
+
+    write!(
+        skel,
+        r#"// SPDX-License-Identifier: (LGPL-2.1 OR BSD-2-Clause)
+           //
+           // THIS FILE IS AUTOGENERATED BY CARGO-LIBBPF-GEN!
+
+           pub use self::imp::*;
+
+           #[allow(dead_code)]
+           #[allow(non_snake_case)]
+           #[allow(non_camel_case_types)]
+           #[allow(non_upper_case_globals)]
+           #[allow(clippy::absolute_paths)]
+           #[allow(clippy::transmute_ptr_to_ref)]
+           #[allow(clippy::upper_case_acronyms)]
+           #[warn(single_use_lifetimes)]
+           mod imp {{
+           #[allow(unused_imports)]
+           use super::*;
+           use libbpf_rs::libbpf_sys;
+           use libbpf_rs::skel::OpenSkel;
+           use libbpf_rs::skel::Skel;
+           use libbpf_rs::skel::SkelBuilder;
+        "#
+    )?;
+
+    // The name we'll always hand to libbpf
+    //
+    // Note it's important this remains consistent b/c libbpf infers map/prog names from this name
+    let libbpf_obj_name = format!("{raw_obj_name}_bpf");
+    // We'll use `obj_name` as the rust-ified object name
+    let obj_name = capitalize_first_letter(raw_obj_name);
+
+    // Open bpf_object so we can iterate over maps and progs
+    let file = File::open(obj_file_path)
+        .with_context(|| format!("failed to open BPF object `{}`", obj_file_path.display()))?;
+    let mmap = unsafe { Mmap::map(&file)? };
+    let mut object = open_bpf_object(&libbpf_obj_name, &mmap)?;
+    let () = check_datasec_names(&mut object)?;
+
+    gen_skel_c_skel_constructor(&mut skel, &mut object, &libbpf_obj_name, compress)?;
+
+    #[allow(clippy::uninlined_format_args)]
     write!(
         skel,
         r#"
@@ -849,6 +1566,12 @@ fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) ->
             pub obj_builder: libbpf_rs::ObjectBuilder,
         }}
 
+        impl From<libbpf_rs::ObjectBuilder> for {name}SkelBuilder {{
+            fn from(obj_builder: libbpf_rs::ObjectBuilder) -> Self {{
+                Self {{ obj_builder }}
+            }}
+        }}
+
         impl<'a> SkelBuilder<'a> for {name}SkelBuilder {{
             type Output = Open{name}Skel<'a>;
             fn open(self) -> libbpf_rs::Result<Open{name}Skel<'a>> {{
@@ -873,7 +1596,7 @@ fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) ->
                     //         which are allowed to be NULL.
                     // TODO: Generate and use a `Default` representation
                     //       instead, to cut down on unsafe code.
-                    struct_ops: unsafe {{ std::mem::zeroed() }},
+                    struct_ops: unsafe {{ {krate}::mem::zeroed() }},
                     skel_config
                 }};
                 {struct_ops_init}
@@ -892,9 +1615,9 @@ fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) ->
         struct_ops_init = gen_skel_struct_ops_init(&mut object)?,
     )?;
 
-    gen_skel_map_defs(&mut skel, &mut object, &obj_name, true)?;
-    gen_skel_prog_defs(&mut skel, &mut object, &obj_name, true, false)?;
-    gen_skel_prog_defs(&mut skel, &mut object, &obj_name, true, true)?;
+    gen_skel_map_defs(&mut skel, &mut object, &obj_name, raw_obj_name, true)?;
+    gen_skel_prog_defs(&mut skel, &mut object, &obj_name, true, false, no_std)?;
+    gen_skel_prog_defs(&mut skel, &mut object, &obj_name, true, true, no_std)?;
     write!(
         skel,
         r#"
@@ -904,8 +1627,8 @@ fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) ->
             "#
     )?;
 
-    gen_skel_datasec_types(&mut skel, &object)?;
-    gen_skel_struct_ops_types(&mut skel, &object)?;
+    gen_skel_datasec_types(&mut skel, &mut object, no_std)?;
+    gen_skel_struct_ops_types(&mut skel, &object, no_std)?;
     writeln!(skel, "}}")?;
 
     write!(
@@ -920,10 +1643,9 @@ fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) ->
         impl<'a> OpenSkel for Open{name}Skel<'a> {{
             type Output = {name}Skel<'a>;
             fn load(mut self) -> libbpf_rs::Result<{name}Skel<'a>> {{
-                let ret = unsafe {{ libbpf_sys::bpf_object__load_skeleton(self.skel_config.get()) }};
-                if ret != 0 {{
-                    return Err(libbpf_rs::Error::from_raw_os_error(-ret));
-                }}
+                let () = libbpf_rs::__internal_skel::load_object("bpf_object__load_skeleton", "{libbpf_obj_name}", || unsafe {{
+                    libbpf_sys::bpf_object__load_skeleton(self.skel_config.get())
+                }})?;
 
                 let obj = unsafe {{ libbpf_rs::Object::from_ptr(self.obj.take_ptr())? }};
 
@@ -944,7 +1666,7 @@ fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) ->
             }}
         "#,
         name = &obj_name,
-        links = if ProgIter::new(object.as_mut_ptr()).next().is_some() {
+        links = if has_links(&mut object) {
             format!(r#"links: {obj_name}Links::default()"#)
         } else {
             "".to_string()
@@ -958,9 +1680,9 @@ fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) ->
     gen_skel_datasec_getters(&mut skel, &mut object, raw_obj_name, false)?;
     writeln!(skel, "}}")?;
 
-    gen_skel_map_defs(&mut skel, &mut object, &obj_name, false)?;
-    gen_skel_prog_defs(&mut skel, &mut object, &obj_name, false, false)?;
-    gen_skel_prog_defs(&mut skel, &mut object, &obj_name, false, true)?;
+    gen_skel_map_defs(&mut skel, &mut object, &obj_name, raw_obj_name, false)?;
+    gen_skel_prog_defs(&mut skel, &mut object, &obj_name, false, false, no_std)?;
+    gen_skel_prog_defs(&mut skel, &mut object, &obj_name, false, true, no_std)?;
     gen_skel_link_defs(&mut skel, &mut object, &obj_name)?;
 
     write!(
@@ -993,7 +1715,7 @@ fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) ->
         "#,
         name = &obj_name,
     )?;
-    gen_skel_attach(&mut skel, &mut object, &obj_name)?;
+    gen_skel_attach(&mut skel, &mut object, &libbpf_obj_name)?;
     writeln!(skel, "}}")?;
 
     write!(skel, "impl {name}Skel<'_> {{", name = &obj_name)?;
@@ -1001,14 +1723,502 @@ fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) ->
     gen_skel_map_getters(&mut skel, &mut object, &obj_name, false)?;
     gen_skel_struct_ops_getters(&mut skel, &mut object, raw_obj_name)?;
     gen_skel_datasec_getters(&mut skel, &mut object, raw_obj_name, true)?;
+    gen_skel_attach_progs(&mut skel, &mut object)?;
+    gen_skel_persist(&mut skel, &mut object, no_std)?;
+    writeln!(skel, "}}")?;
+
+    gen_skel_pins(&mut skel, &mut object, &obj_name, raw_obj_name, no_std)?;
+
+    if compress {
+        let bytes = compress_object(&mmap[..]).with_context(|| {
+            format!(
+                "failed to compress BPF object `{}`",
+                obj_file_path.display()
+            )
+        })?;
+        write!(
+            skel,
+            r#"
+            const DATA: &[u8] = &{bytes:?};
+
+            static OBJECT: libbpf_rs::__internal_skel::CompressedObject =
+                libbpf_rs::__internal_skel::CompressedObject::new(DATA);
+            "#
+        )?;
+    } else {
+        // Coerce to &[u8] just to be safe, as we'll be using debug formatting
+        let bytes: &[u8] = &mmap;
+        write!(
+            skel,
+            r#"
+            const DATA: &[u8] = &{bytes:?};
+            "#
+        )?;
+    }
+
+    writeln!(skel, "}}")?;
+
+    Ok(skel)
+}
+
+/// Generate contents of a single subskeleton
+///
+/// A subskeleton provides access to the maps, programs, and global
+/// variables of a BPF library (`obj_file_path`), once the library has been
+/// statically linked into and loaded as part of another object.
+fn gen_subskel_contents(raw_obj_name: &str, obj_file_path: &Path, no_std: bool) -> Result<String> {
+    let mut skel = String::new();
+    let krate = if no_std { "core" } else { "std" };
+
+    write!(
+        skel,
+        r#"// SPDX-License-Identifier: (LGPL-2.1 OR BSD-2-Clause)
+           //
+           // THIS FILE IS AUTOGENERATED BY CARGO-LIBBPF-GEN!
+
+           pub use self::imp::*;
+
+           #[allow(dead_code)]
+           #[allow(non_snake_case)]
+           #[allow(non_camel_case_types)]
+           #[allow(clippy::absolute_paths)]
+           #[allow(clippy::upper_case_acronyms)]
+           #[warn(single_use_lifetimes)]
+           mod imp {{
+           #[allow(unused_imports)]
+           use super::*;
+           use libbpf_rs::libbpf_sys;
+           use libbpf_rs::AsRawLibbpf as _;
+        "#
+    )?;
+
+    let libbpf_obj_name = format!("{raw_obj_name}_bpf");
+    let obj_name = capitalize_first_letter(raw_obj_name);
+
+    let file = File::open(obj_file_path)
+        .with_context(|| format!("failed to open BPF object `{}`", obj_file_path.display()))?;
+    let mmap = unsafe { Mmap::map(&file)? };
+    let mut object = open_bpf_object(&libbpf_obj_name, &mmap)?;
+    let () = check_datasec_names(&mut object)?;
+    let obj_ptr = object.as_mut_ptr();
+    let btf = Btf::from_bpf_object(&object)?
+        .map(|btf| GenBtf::from(btf).no_std(no_std))
+        .ok_or_else(|| anyhow::anyhow!("subskeletons require BTF"))?;
+
+    // Collect the variables of each data section map, referencing maps by
+    // their section name, as the object name prefix differs between the
+    // library and the object it is linked into.
+    struct SubskelVar {
+        name: String,
+        ty: String,
+        map: String,
+        readonly: bool,
+    }
+
+    let mut maps = Vec::new();
+    let mut datasecs = Vec::new();
+    let mut vars = Vec::new();
+    let mut types = String::new();
+    let mut processed = HashSet::new();
+    for map in MapIter::new(obj_ptr) {
+        if !map_is_datasec(map) {
+            maps.push(get_raw_map_name(map)?);
+            continue;
+        }
+
+        // The map name may contain parts of the object name, which itself
+        // may contain dots, so use the name of the map's DATASEC type.
+        let type_id = unsafe { libbpf_sys::bpf_map__btf_value_type_id(map) };
+        let datasec = match btf.type_by_id::<types::DataSec<'_>>(type_id.into()) {
+            Some(datasec) => datasec,
+            None => continue,
+        };
+        let sec_name = match datasec.name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        if matches!(
+            canonicalize_internal_map_name(&sec_name),
+            None | Some(InternalMapType::Kconfig) | Some(InternalMapType::StructOps)
+        ) {
+            continue;
+        }
+
+        let readonly = map_is_readonly(map);
+        let mut has_vars = false;
+        for info in datasec.iter() {
+            let var = btf
+                .type_by_id::<types::Var<'_>>(info.ty)
+                .ok_or_else(|| anyhow::anyhow!("datasec type does not point to a variable"))?;
+            // Static variables can't be looked up reliably by name.
+            if var.linkage() == types::Linkage::Static {
+                continue;
+            }
+
+            if let Some(next_ty) = next_type(*var)? {
+                let def = btf.type_definition(next_ty, &mut processed)?;
+                types.push_str(&def);
+            }
+
+            vars.push(SubskelVar {
+                name: var.name().unwrap().to_string_lossy().into_owned(),
+                ty: btf.type_declaration(*var)?,
+                map: sec_name.clone(),
+                readonly,
+            });
+            has_vars = true;
+        }
+
+        if has_vars {
+            datasecs.push(sec_name);
+        }
+    }
+
+    let progs = ProgIter::new(obj_ptr)
+        .map(|prog| get_prog_name(prog))
+        .collect::<Result<Vec<_>>>()?;
+
+    write!(
+        skel,
+        r#"
+        fn build_subskel_config(
+            obj: {krate}::ptr::NonNull<libbpf_sys::bpf_object>,
+        ) -> libbpf_rs::Result<libbpf_rs::__internal_skel::ObjectSubskeletonConfig> {{
+            let mut builder = libbpf_rs::__internal_skel::ObjectSubskeletonConfigBuilder::new(obj);
+        "#
+    )?;
+    for map in maps.iter().chain(&datasecs) {
+        writeln!(skel, r#"builder.map("{map}");"#)?;
+    }
+    for prog in &progs {
+        writeln!(skel, r#"builder.prog("{prog}");"#)?;
+    }
+    for var in &vars {
+        writeln!(skel, r#"builder.var("{}", "{}")?;"#, var.name, var.map)?;
+    }
+    write!(
+        skel,
+        r#"
+            builder.build()
+        }}
+
+        pub mod {raw_obj_name}_types {{
+            #[allow(unused_imports)]
+            use super::*;
+            {types}
+        }}
+        #[allow(unused_imports)]
+        use self::{raw_obj_name}_types::*;
+        "#
+    )?;
+
+    let mut gen_accessors =
+        |kind: &str, lookup: &str, what: &str, ty: &str, names: &[String]| -> Result<()> {
+            if names.is_empty() {
+                return Ok(());
+            }
+
+            for mutable in [false, true] {
+                let (struct_suffix, mut_prefix, fn_suffix) = if mutable {
+                    ("Mut", "mut ", "_mut")
+                } else {
+                    ("", "", "")
+                };
+
+                write!(
+                    skel,
+                    r#"
+                pub struct {obj_name}Subskel{kind}{struct_suffix}<'a> {{
+                    inner: &'a {mut_prefix}libbpf_rs::Object,
+                }}
+
+                impl {obj_name}Subskel{kind}{struct_suffix}<'_> {{
+                "#
+                )?;
+
+                for name in names {
+                    write!(
+                        skel,
+                        r#"
+                    pub fn {ident}(&{mut_prefix}self) -> libbpf_rs::Result<&{mut_prefix}{ty}> {{
+                        self.inner.{lookup}{fn_suffix}("{name}").ok_or_else(|| {{
+                            libbpf_rs::__internal_skel::subskel_not_found("{what}", "{name}")
+                        }})
+                    }}
+                    "#,
+                        ident = escape_raw_name(name),
+                    )?;
+                }
+                writeln!(skel, "}}")?;
+            }
+            Ok(())
+        };
+    let () = gen_accessors("Maps", "map", "map", "libbpf_rs::Map", &maps)?;
+    let () = gen_accessors("Progs", "prog", "program", "libbpf_rs::Program", &progs)?;
+
+    write!(
+        skel,
+        r#"
+        pub struct {obj_name}Subskel<'obj> {{
+            pub obj: &'obj mut libbpf_rs::Object,
+            skel_config: libbpf_rs::__internal_skel::ObjectSubskeletonConfig,
+        }}
+
+        impl<'obj> {obj_name}Subskel<'obj> {{
+            /// Open the subskeleton for `obj`, an object that the library
+            /// has been linked into.
+            pub fn open(obj: &'obj mut libbpf_rs::Object) -> libbpf_rs::Result<Self> {{
+                let mut skel_config = build_subskel_config(obj.as_libbpf_object())?;
+                let ret = unsafe {{ libbpf_sys::bpf_object__open_subskeleton(skel_config.get()) }};
+                if ret != 0 {{
+                    return Err(libbpf_rs::Error::from_raw_os_error(-ret));
+                }}
+
+                Ok(Self {{ obj, skel_config }})
+            }}
+        "#
+    )?;
+
+    let accessor_names = [
+        ("Maps", "maps", !maps.is_empty()),
+        ("Progs", "progs", !progs.is_empty()),
+    ];
+    for (kind, fn_name, present) in accessor_names {
+        if !present {
+            continue;
+        }
+
+        write!(
+            skel,
+            r#"
+            pub fn {fn_name}(&self) -> {obj_name}Subskel{kind}<'_> {{
+                {obj_name}Subskel{kind} {{
+                    inner: &*self.obj,
+                }}
+            }}
+
+            pub fn {fn_name}_mut(&mut self) -> {obj_name}Subskel{kind}Mut<'_> {{
+                {obj_name}Subskel{kind}Mut {{
+                    inner: &mut *self.obj,
+                }}
+            }}
+            "#
+        )?;
+    }
+
+    for (idx, var) in vars.iter().enumerate() {
+        let SubskelVar {
+            name, ty, readonly, ..
+        } = var;
+
+        write!(
+            skel,
+            r#"
+            pub fn {name}(&self) -> libbpf_rs::Result<&{ty}> {{
+                let ptr = self.skel_config.var_ptr({idx})?;
+                // SAFETY: libbpf ensures that the variable has the
+                //         expected type and is backed by memory mapped
+                //         data, which lives as long as the object.
+                Ok(unsafe {{ &*ptr.cast::<{ty}>() }})
+            }}
+            "#
+        )?;
+
+        if !readonly {
+            write!(
+                skel,
+                r#"
+                pub fn {name}_mut(&mut self) -> libbpf_rs::Result<&mut {ty}> {{
+                    let ptr = self.skel_config.var_ptr({idx})?;
+                    // SAFETY: See above. We borrow the object mutably.
+                    Ok(unsafe {{ &mut *ptr.cast::<{ty}>() }})
+                }}
+                "#
+            )?;
+        }
+    }
+
     writeln!(skel, "}}")?;
+    writeln!(skel, "}}")?;
+
+    Ok(skel)
+}
+
+/// Generate contents of a single light skeleton
+///
+/// A light skeleton embeds a loader program generated from the object
+/// (`obj_file_path`) instead of the object itself. Loading it runs this
+/// program, which creates the object's maps and loads its programs without
+/// involving libbpf.
+fn gen_light_skel_contents(raw_obj_name: &str, obj_file_path: &Path) -> Result<String> {
+    let mut skel = String::new();
+
+    write!(
+        skel,
+        r#"// SPDX-License-Identifier: (LGPL-2.1 OR BSD-2-Clause)
+           //
+           // THIS FILE IS AUTOGENERATED BY CARGO-LIBBPF-GEN!
+
+           pub use self::imp::*;
+
+           #[allow(dead_code)]
+           #[allow(non_snake_case)]
+           #[allow(non_camel_case_types)]
+           #[allow(clippy::absolute_paths)]
+           #[allow(clippy::upper_case_acronyms)]
+           #[warn(single_use_lifetimes)]
+           mod imp {{
+           #[allow(unused_imports)]
+           use super::*;
+        "#
+    )?;
+
+    let libbpf_obj_name = format!("{raw_obj_name}_bpf");
+    let obj_name = capitalize_first_letter(raw_obj_name);
+
+    let file = File::open(obj_file_path)
+        .with_context(|| format!("failed to open BPF object `{}`", obj_file_path.display()))?;
+    let mmap = unsafe { Mmap::map(&file)? };
+    let mut object = open_bpf_object(&libbpf_obj_name, &mmap)?;
+    let loader = libbpf_rs::ObjectBuilder::default()
+        .name(&libbpf_obj_name)?
+        .open_memory(&mmap)?
+        .gen_loader()
+        .context("failed to generate loader program")?;
+
+    // Maps not getting created and programs not getting loaded have no
+    // file descriptors to provide access to.
+    let maps = MapIter::new(object.as_mut_ptr())
+        .filter(|map| unsafe { libbpf_sys::bpf_map__autocreate(*map) })
+        .filter_map(|map| match get_map_name(map) {
+            Ok(Some(name)) => Some(get_raw_map_name(map).map(|raw_name| (name, raw_name))),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let progs = ProgIter::new(object.as_mut_ptr())
+        .filter(|prog| unsafe { libbpf_sys::bpf_program__autoload(*prog) })
+        .map(|prog| get_prog_name(prog))
+        .collect::<Result<Vec<_>>>()?;
+
+    if !maps.is_empty() {
+        write!(
+            skel,
+            r#"
+            pub struct {obj_name}LightMaps<'obj> {{
+                inner: &'obj libbpf_rs::LightObject,
+            }}
+
+            impl<'obj> {obj_name}LightMaps<'obj> {{
+            "#,
+        )?;
+
+        for (map_name, raw_map_name) in &maps {
+            write!(
+                skel,
+                r#"
+                pub fn {map_name}(&self) -> &'obj libbpf_rs::MapHandle {{
+                    self.inner.map("{raw_map_name}").unwrap()
+                }}
+                "#,
+            )?;
+        }
+
+        writeln!(skel, "}}")?;
+    }
+
+    if !progs.is_empty() {
+        write!(
+            skel,
+            r#"
+            pub struct {obj_name}LightProgs<'obj> {{
+                inner: &'obj libbpf_rs::LightObject,
+            }}
+
+            impl<'obj> {obj_name}LightProgs<'obj> {{
+            "#,
+        )?;
+
+        for prog_name in &progs {
+            write!(
+                skel,
+                r#"
+                pub fn {prog_name}(&self) -> std::os::unix::io::BorrowedFd<'obj> {{
+                    self.inner.prog_fd("{prog_name}").unwrap()
+                }}
+                "#,
+            )?;
+        }
+
+        writeln!(skel, "}}")?;
+    }
 
-    // Coerce to &[u8] just to be safe, as we'll be using debug formatting
-    let bytes: &[u8] = &mmap;
     write!(
         skel,
         r#"
-        const DATA: &[u8] = &{bytes:?};
+        pub struct {obj_name}LightSkel {{
+            obj: libbpf_rs::LightObject,
+        }}
+
+        impl {obj_name}LightSkel {{
+            /// Load the object by running the embedded loader program.
+            ///
+            /// Programs are loaded, but not attached.
+            pub fn load() -> libbpf_rs::Result<Self> {{
+                let loader = libbpf_rs::LoaderProgram::from_parts(
+                    INSNS.to_vec(),
+                    DATA.to_vec(),
+                    MAPS.iter().map(|name| name.to_string()).collect(),
+                    PROGS.iter().map(|name| name.to_string()).collect(),
+                );
+                let obj = loader.run()?;
+                Ok(Self {{ obj }})
+            }}
+
+            /// Retrieve the underlying [`libbpf_rs::LightObject`].
+            pub fn object(&self) -> &libbpf_rs::LightObject {{
+                &self.obj
+            }}
+        "#,
+    )?;
+
+    if !maps.is_empty() {
+        write!(
+            skel,
+            r#"
+            pub fn maps(&self) -> {obj_name}LightMaps<'_> {{
+                {obj_name}LightMaps {{ inner: &self.obj }}
+            }}
+            "#,
+        )?;
+    }
+
+    if !progs.is_empty() {
+        write!(
+            skel,
+            r#"
+            pub fn progs(&self) -> {obj_name}LightProgs<'_> {{
+                {obj_name}LightProgs {{ inner: &self.obj }}
+            }}
+            "#,
+        )?;
+    }
+
+    writeln!(skel, "}}")?;
+
+    let insns = loader.insns();
+    let data = loader.data();
+    let map_count = loader.maps().len();
+    let map_names = loader.maps();
+    let prog_count = loader.progs().len();
+    let prog_names = loader.progs();
+    write!(
+        skel,
+        r#"
+        const INSNS: &[u8] = &{insns:?};
+        const DATA: &[u8] = &{data:?};
+        const MAPS: [&str; {map_count}] = {map_names:?};
+        const PROGS: [&str; {prog_count}] = {prog_names:?};
         "#
     )?;
 
@@ -1018,34 +2228,105 @@ fn gen_skel_contents(_debug: bool, raw_obj_name: &str, obj_file_path: &Path) ->
 }
 
 /// Generate a single skeleton
+#[allow(clippy::too_many_arguments)]
 fn gen_skel(
     debug: bool,
     name: &str,
     obj: &Path,
     out: OutputDest<'_>,
     rustfmt_path: Option<&PathBuf>,
+    subskel: bool,
+    light: bool,
+    no_std: bool,
+    compress: bool,
 ) -> Result<()> {
     ensure!(!name.is_empty(), "Object file has no name");
-
-    let skel = gen_skel_contents(debug, name, obj)?;
+    ensure!(
+        !light || !(subskel || no_std || compress),
+        "light skeletons can't be combined with subskeletons, no_std, or compression"
+    );
+    ensure!(
+        !subskel || !compress,
+        "subskeletons don't embed an object and can't be compressed"
+    );
+
+    let skel = if subskel {
+        gen_subskel_contents(name, obj, no_std)?
+    } else if light {
+        gen_light_skel_contents(name, obj)?
+    } else {
+        gen_skel_contents(debug, name, obj, no_std, compress)?
+    };
     let skel = try_rustfmt(&skel, rustfmt_path)?;
 
     match out {
         OutputDest::Stdout => stdout().write_all(&skel)?,
         OutputDest::Directory(dir) => {
-            let path = dir.join(format!("{name}.skel.rs"));
-            let mut file = File::create(path)?;
-            file.write_all(&skel)?;
+            let path = dir.join(skel_file_name(name, subskel, light));
+            let () = write_if_changed(&path, &skel)?;
         }
         OutputDest::File(file) => {
-            let mut file = File::create(file)?;
-            file.write_all(&skel)?;
+            let () = write_if_changed(file, &skel)?;
         }
     };
 
     Ok(())
 }
 
+/// Derive the name of the object at `obj_file` from its file name.
+fn obj_name(obj_file: &Path) -> Result<&str> {
+    let filename = match obj_file.file_name() {
+        Some(n) => n,
+        None => bail!(
+            "Could not determine file name for object file: {}",
+            obj_file.to_string_lossy()
+        ),
+    };
+
+    match filename.to_str() {
+        Some(n) => {
+            ensure!(
+                n.ends_with(".o"),
+                "Object file does not have `.o` suffix: {n}"
+            );
+
+            Ok(n.split('.').next().unwrap())
+        }
+        None => bail!(
+            "Object file name is not valid unicode: {}",
+            filename.to_string_lossy()
+        ),
+    }
+}
+
+/// Retrieve the file name of the skeleton of the object named `name`.
+fn skel_file_name(name: &str, subskel: bool, light: bool) -> String {
+    let suffix = if subskel {
+        "subskel"
+    } else if light {
+        "lskel"
+    } else {
+        "skel"
+    };
+    format!("{name}.{suffix}.rs")
+}
+
+/// Write `contents` to the file at `path`, unless it already has these
+/// contents.
+///
+/// Leaving unchanged files untouched preserves their modification time,
+/// so that cargo does not needlessly recompile crates including them.
+fn write_if_changed(path: &Path, contents: &[u8]) -> Result<()> {
+    if read(path).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+
+    let mut file =
+        File::create(path).with_context(|| format!("failed to create `{}`", path.display()))?;
+    let () = file.write_all(contents)?;
+    Ok(())
+}
+
 /// Generate mod.rs in src/bpf directory of each project.
 ///
 /// Each `UnprocessedObj` in `objs` must belong to same project.
@@ -1090,42 +2371,35 @@ pub fn gen_mods(objs: &[UnprocessedObj], rustfmt_path: Option<&PathBuf>) -> Resu
         )?;
     }
 
-    let mut file = File::create(path)?;
-    file.write_all(&try_rustfmt(&contents, rustfmt_path)?)?;
+    let () = write_if_changed(&path, &try_rustfmt(&contents, rustfmt_path)?)?;
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn gen_single(
     debug: bool,
     obj_file: &Path,
     output: OutputDest<'_>,
     rustfmt_path: Option<&PathBuf>,
+    subskel: bool,
+    light: bool,
+    no_std: bool,
+    compress: bool,
 ) -> Result<()> {
-    let filename = match obj_file.file_name() {
-        Some(n) => n,
-        None => bail!(
-            "Could not determine file name for object file: {}",
-            obj_file.to_string_lossy()
-        ),
-    };
-
-    let name = match filename.to_str() {
-        Some(n) => {
-            ensure!(
-                n.ends_with(".o"),
-                "Object file does not have `.o` suffix: {n}"
-            );
-
-            n.split('.').next().unwrap()
-        }
-        None => bail!(
-            "Object file name is not valid unicode: {}",
-            filename.to_string_lossy()
-        ),
-    };
-
-    let () = gen_skel(debug, name, obj_file, output, rustfmt_path).with_context(|| {
+    let name = obj_name(obj_file)?;
+    let () = gen_skel(
+        debug,
+        name,
+        obj_file,
+        output,
+        rustfmt_path,
+        subskel,
+        light,
+        no_std,
+        compress,
+    )
+    .with_context(|| {
         format!(
             "Failed to generate skeleton for {}",
             obj_file.to_string_lossy(),
@@ -1139,6 +2413,9 @@ fn gen_project(
     debug: bool,
     manifest_path: Option<&PathBuf>,
     rustfmt_path: Option<&PathBuf>,
+    no_std: bool,
+    compress: bool,
+    emit_json: bool,
 ) -> Result<()> {
     let (_target_dir, to_gen) = metadata::get(debug, manifest_path)?;
     if debug && !to_gen.is_empty() {
@@ -1152,6 +2429,7 @@ fn gen_project(
 
     // Map to store package_name -> [UnprocessedObj]
     let mut package_objs: BTreeMap<String, Vec<UnprocessedObj>> = BTreeMap::new();
+    let mut infos = Vec::new();
 
     for obj in to_gen {
         let mut obj_file_path = obj.out.clone();
@@ -1166,6 +2444,10 @@ fn gen_project(
             obj_file_path.as_path(),
             OutputDest::Directory(skel_path.as_path()),
             rustfmt_path,
+            false,
+            false,
+            no_std,
+            compress,
         )
         .with_context(|| {
             format!(
@@ -1174,6 +2456,12 @@ fn gen_project(
             )
         })?;
 
+        if emit_json {
+            let skel_file = skel_path.join(skel_file_name(&obj.name, false, false));
+            let info = json::skeleton_info(&obj.name, &obj_file_path, Some(&skel_file))?;
+            let () = infos.push(info);
+        }
+
         match package_objs.get_mut(&obj.package) {
             Some(v) => v.push(obj.clone()),
             None => {
@@ -1187,22 +2475,72 @@ fn gen_project(
             .with_context(|| format!("Failed to generate mod.rs for package={package}"))?;
     }
 
+    if emit_json {
+        println!("{}", serde_json::to_string_pretty(&infos)?);
+    }
+
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn gen(
     debug: bool,
     manifest_path: Option<&PathBuf>,
     rustfmt_path: Option<&PathBuf>,
     object: Option<&PathBuf>,
+    subskel: bool,
+    light: bool,
+    no_std: bool,
+    compress: bool,
+    emit_json: bool,
 ) -> Result<()> {
     if manifest_path.is_some() && object.is_some() {
         bail!("--manifest-path and --object cannot be used together");
     }
 
     if let Some(obj_file) = object {
-        gen_single(debug, obj_file, OutputDest::Stdout, rustfmt_path)
+        if emit_json {
+            // The skeleton can't share stdout with the JSON description,
+            // so place it next to the object file instead.
+            let name = obj_name(obj_file)?;
+            let dir = match obj_file.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            let () = gen_single(
+                debug,
+                obj_file,
+                OutputDest::Directory(dir),
+                rustfmt_path,
+                subskel,
+                light,
+                no_std,
+                compress,
+            )?;
+            let skel_file = dir.join(skel_file_name(name, subskel, light));
+            let info = json::skeleton_info(name, obj_file, Some(&skel_file))?;
+            println!("{}", serde_json::to_string_pretty(&info)?);
+            return Ok(());
+        }
+
+        gen_single(
+            debug,
+            obj_file,
+            OutputDest::Stdout,
+            rustfmt_path,
+            subskel,
+            light,
+            no_std,
+            compress,
+        )
     } else {
-        gen_project(debug, manifest_path, rustfmt_path)
+        gen_project(
+            debug,
+            manifest_path,
+            rustfmt_path,
+            no_std,
+            compress,
+            emit_json,
+        )
     }
 }
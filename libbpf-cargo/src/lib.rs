@@ -20,10 +20,26 @@
 //! [package.metadata.libbpf]
 //! prog_dir = "src/other_bpf_dir"  # default: <manifest_directory>/src/bpf
 //! target_dir = "other_target_dir" # default: <target_dir>/bpf
+//! clang_args = ["-DFOO=1"]        # default: []
+//! endianness = "big"              # default: "native"
+//! compiler = "gcc"                # default: "clang"
+//!
+//! [package.metadata.libbpf.progs.runqslower]
+//! clang_args = ["-Iinclude"]      # default: []
+//! extra_sources = ["lib.c"]       # default: []
 //! ```
 //!
 //! * `prog_dir`: path relative to package Cargo.toml to search for bpf progs
 //! * `target_dir`: path relative to workspace target directory to place compiled bpf progs
+//! * `clang_args`: additional arguments to pass to `clang` when compiling any bpf prog
+//! * `endianness`: byte order to compile bpf progs for; one of `native`, `little`, or `big`
+//! * `compiler`: compiler to build bpf progs with; one of `clang` or `gcc` (for GCC's BPF
+//!   backend, invoked as `bpf-gcc` unless `--clang-path` is provided)
+//! * `progs.<NAME>.clang_args`: additional arguments to pass to `clang` when compiling
+//!   `<NAME>.bpf.c`
+//! * `progs.<NAME>.extra_sources`: paths relative to `prog_dir` of additional C files to
+//!   compile separately and statically link with `<NAME>.bpf.c` into `<NAME>.bpf.o`; these
+//!   files are not built into objects of their own
 //!
 //! # Subcommands
 //!
@@ -33,6 +49,10 @@
 //! object files. Each object file may contain one or more BPF programs, maps, and associated
 //! metadata. The object file may then be handed over to `libbpf-rs` for loading and interaction.
 //!
+//! With `--watch`, cargo-libbpf-build keeps running after the initial build and rebuilds all
+//! objects and regenerates their skeletons whenever a C source or header file in any of the
+//! directories containing `<NAME>.bpf.c` files changes.
+//!
 //! cargo-libbpf-build enforces a few conventions:
 //!
 //! * source file names must be in the `<NAME>.bpf.c` format
@@ -40,6 +60,17 @@
 //! * there may not be any two identical `<NAME>.bpf.c` file names in any two projects in a
 //!   cargo workspace
 //!
+//! ## check
+//!
+//! `cargo libbpf check` loads each program of the project's `<NAME>.bpf.o` object files, or of
+//! the object file provided via `--object`, into the running kernel and reports whether it passed
+//! the verifier, along with the number of verified instructions and the verifier log of failing
+//! programs. Programs named via `--test-run` are additionally run once using
+//! `BPF_PROG_TEST_RUN`. With `--json` the report is printed as JSON. The command fails if any
+//! program fails to load or to run, making it suitable for gating CI on verifier acceptance.
+//!
+//! Loading programs generally requires elevated privileges.
+//!
 //! ## gen
 //!
 //! `cargo libbpf gen` generates a skeleton module for each BPF object file in the project.  Each
@@ -49,6 +80,81 @@
 //! Be careful to run cargo-libbpf-build before running cargo-libbpf-gen. cargo-libbpf-gen reads
 //! object files from `package.metadata.libbpf.target_dir`.
 //!
+//! Types that are not otherwise referenced by the skeleton, such as those of
+//! events sent through a ring buffer, can be included in the generated
+//! `<NAME>_types` module by tagging them in the BPF program:
+//!
+//! ```c
+//! struct event {
+//!     u32 pid;
+//! } __attribute__((btf_decl_tag("libbpf_rs.export")));
+//! ```
+//!
+//! Note that the type still has to be referenced by the program for the
+//! compiler to emit it into the object file's BTF.
+//!
+//! Similarly, all enums used by global variables, maps, or exported types are mirrored in
+//! the `<NAME>_types` module, as Rust enums (if named and free of duplicate values) and as
+//! constants for each enumerator. As preprocessor definitions don't make it into BTF, constants that are to be
+//! shared with user space should be defined as enumerators instead:
+//!
+//! ```c
+//! enum {
+//!     MAX_EVENTS = 1024,
+//! } __max_events;
+//! ```
+//!
+//! For each program that can be attached, the mutable programs accessor of a loaded skeleton
+//! provides an `attach_<PROG>` method taking exactly the parameters required by the program's
+//! section, e.g., a cgroup file descriptor for `SEC("cgroup/connect4")` or a process ID, binary
+//! path, and offset for `SEC("uprobe")`.
+//!
+//! Loaded skeletons furthermore provide `attach_prog` and `attach_only` methods for attaching
+//! individual programs by name, storing the resulting links just like `attach` does. `attach`
+//! skips programs whose autoattach setting was disabled via `OpenProgram::set_autoattach`.
+//!
+//! For maps declared with `__uint(pinning, LIBBPF_PIN_BY_NAME)`, the `<NAME>_pins` module
+//! contains constants with their default pin paths. `<NAME>PinnedMaps::open_pinned` opens handles
+//! to all of these maps, e.g., from a separate process than the one loading the skeleton.
+//!
+//! By default, dropping a skeleton detaches all of its programs. Calling `persist` on a loaded
+//! skeleton disconnects its links instead, so that programs can stay attached after the loader
+//! exits (provided that links based on file descriptors are pinned).
+//!
+//! Skeleton builders can be created from a configured `libbpf_rs::ObjectBuilder`, e.g., via
+//! `<NAME>SkelBuilder::from(obj_builder)`, to open the skeleton with custom options such as a BTF
+//! path, kconfig values, or a pin root path.
+//!
+//! With `--compress`, the object embedded in each skeleton is compressed using zstd and
+//! decompressed when the skeleton is first opened, which requires enabling the `zstd` feature of
+//! `libbpf-rs`. Compressing objects requires the `zstd` feature of `libbpf-cargo`. Subskeletons
+//! don't embed an object and can't be compressed.
+//!
+//! With `--no-std`, generated code refers to `core` instead of `std`, so that the definitions in
+//! the `<NAME>_types` modules can be used in `no_std` environments, for example when sharing them
+//! with a minimal consumer of the data a BPF program produces. Methods that require `std` types,
+//! namely `persist` and `<NAME>PinnedMaps::open_pinned_in`, are not generated, and typed attach
+//! methods accept binary paths as strings. `--no-std` can't be combined with `--light`.
+//!
+//! With `--emit-json`, a JSON description of each generated skeleton is printed to stdout. It
+//! lists the object's programs with their sections and types, its maps with their key and value
+//! types and sizes, the variables of its data sections, and the paths of the object file and the
+//! skeleton, for consumption by external tooling. When combined with `--object`, the skeleton is
+//! written next to the object file rather than to stdout.
+//!
+//! With `--light` (along with `--object`), a light skeleton is generated instead. It embeds a
+//! loader program generated from the object rather than the object itself, and
+//! `<NAME>LightSkel::load` runs this program to create the object's maps and load its programs,
+//! without involving libbpf. Programs are not attached; their file descriptors are available via
+//! `progs()`.
+//!
+//! ## gen-vmlinux
+//!
+//! `cargo libbpf gen-vmlinux` generates a `vmlinux.h` header containing all type definitions of
+//! the running kernel, or of the kernel BTF file provided via `--btf`, and prints it to stdout or
+//! writes it to the file provided via `--output`. Headers are cached in the workspace target
+//! directory, per kernel release for the running kernel and by contents for BTF files.
+//!
 //! ## make
 //!
 //! `cargo libbpf make` sequentially runs cargo-libbpf-build, cargo-libbpf-gen, and `cargo
@@ -64,6 +170,8 @@
 )]
 #![deny(unsafe_op_in_unsafe_fn)]
 
+use std::collections::HashMap;
+use std::env;
 use std::ffi::OsStr;
 use std::ffi::OsString;
 use std::path::Path;
@@ -76,20 +184,31 @@ use anyhow::Result;
 use tempfile::tempdir;
 use tempfile::TempDir;
 
+pub mod btf;
+
 // libbpf-cargo binary is the primary consumer of the following modules. As such,
 // we do not use all the symbols. Silence any unused code warnings.
 #[allow(dead_code)]
 mod build;
 #[allow(dead_code)]
+mod check;
+#[allow(dead_code)]
 mod gen;
 #[allow(dead_code)]
 mod make;
 #[allow(dead_code)]
 mod metadata;
+#[allow(dead_code)]
+mod vmlinux;
+#[allow(dead_code)]
+mod watch;
 
 #[cfg(test)]
 mod test;
 
+pub use crate::build::Compiler;
+pub use crate::build::Endianness;
+
 /// `SkeletonBuilder` builds and generates a single skeleton.
 ///
 /// This interface is meant to be used in build scripts.
@@ -108,11 +227,21 @@ mod test;
 /// ```
 pub struct SkeletonBuilder {
     debug: bool,
-    source: Option<PathBuf>,
+    sources: Vec<PathBuf>,
     obj: Option<PathBuf>,
     clang: Option<PathBuf>,
     clang_args: Vec<OsString>,
+    source_clang_args: HashMap<PathBuf, Vec<OsString>>,
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<(String, Option<String>)>,
+    endianness: Endianness,
+    compiler: Compiler,
+    rerun_if_changed: bool,
+    vmlinux_h: bool,
+    vmlinux_btf: Option<PathBuf>,
     skip_clang_version_check: bool,
+    no_std: bool,
+    compress: bool,
     rustfmt: PathBuf,
     dir: Option<TempDir>,
 }
@@ -127,11 +256,21 @@ impl SkeletonBuilder {
     pub fn new() -> Self {
         SkeletonBuilder {
             debug: false,
-            source: None,
+            sources: Vec::new(),
             obj: None,
             clang: None,
             clang_args: Vec::new(),
+            source_clang_args: HashMap::new(),
+            include_dirs: Vec::new(),
+            defines: Vec::new(),
+            endianness: Endianness::default(),
+            compiler: Compiler::default(),
+            rerun_if_changed: false,
+            vmlinux_h: false,
+            vmlinux_btf: None,
             skip_clang_version_check: false,
+            no_std: false,
+            compress: false,
             rustfmt: "rustfmt".into(),
             dir: None,
         }
@@ -139,9 +278,42 @@ impl SkeletonBuilder {
 
     /// Point the [`SkeletonBuilder`] to a source file for compilation
     ///
+    /// This replaces any source files set earlier.
+    ///
     /// Default is None
     pub fn source<P: AsRef<Path>>(&mut self, source: P) -> &mut SkeletonBuilder {
-        self.source = Some(source.as_ref().to_path_buf());
+        self.sources = vec![source.as_ref().to_path_buf()];
+        self
+    }
+
+    /// Point the [`SkeletonBuilder`] to multiple source files for
+    /// compilation
+    ///
+    /// Each source file is compiled separately and the resulting object
+    /// files are statically linked into a single object, for which the
+    /// skeleton is generated. Unless [`SkeletonBuilder::obj`] is set, the
+    /// object is named after the first source file. This replaces any
+    /// source files set earlier.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libbpf_cargo::SkeletonBuilder;
+    ///
+    /// SkeletonBuilder::new()
+    ///     .sources(["myobject.bpf.c", "helpers.bpf.c"])
+    ///     .build_and_generate("/output/path")
+    ///     .unwrap();
+    /// ```
+    pub fn sources<I, P>(&mut self, sources: I) -> &mut SkeletonBuilder
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        self.sources = sources
+            .into_iter()
+            .map(|source| source.as_ref().to_path_buf())
+            .collect();
         self
     }
 
@@ -163,12 +335,46 @@ impl SkeletonBuilder {
 
     /// Specify which `clang` binary to use
     ///
-    /// Default searches `$PATH` for `clang`
+    /// If a different [compiler][SkeletonBuilder::compiler] is selected,
+    /// this is the path to its binary instead.
+    ///
+    /// Default searches `$PATH` for `clang`, or `bpf-gcc` for
+    /// [`Compiler::Gcc`]
     pub fn clang<P: AsRef<Path>>(&mut self, clang: P) -> &mut SkeletonBuilder {
         self.clang = Some(clang.as_ref().to_path_buf());
         self
     }
 
+    /// Whether to instruct cargo to rerun the build script when any source
+    /// file or any header included by one, directly or transitively,
+    /// changes
+    ///
+    /// This is accomplished by emitting `cargo:rerun-if-changed` for each
+    /// of these files, as reported by the compiler, when invoked from a
+    /// build script. Note that emitting any such instruction disables
+    /// cargo's default of rerunning the build script on any change to the
+    /// package, so build scripts enabling this option have to list any
+    /// other files they depend on themselves.
+    ///
+    /// Default is false
+    pub fn rerun_if_changed(&mut self, rerun: bool) -> &mut SkeletonBuilder {
+        self.rerun_if_changed = rerun;
+        self
+    }
+
+    /// Specify the compiler to build BPF programs with
+    ///
+    /// Flags that are specific to a compiler, such as those for selecting
+    /// the BPF target and byte order or for emitting BTF, are translated
+    /// accordingly. Additional arguments, e.g., those provided via
+    /// [`SkeletonBuilder::clang_args`], are passed verbatim.
+    ///
+    /// Default is [`Compiler::Clang`]
+    pub fn compiler(&mut self, compiler: Compiler) -> &mut SkeletonBuilder {
+        self.compiler = compiler;
+        self
+    }
+
     /// Pass additional arguments to `clang` when building BPF object file
     ///
     /// # Examples
@@ -197,6 +403,103 @@ impl SkeletonBuilder {
         self
     }
 
+    /// Pass additional arguments to `clang` when compiling the source file
+    /// `source` only
+    ///
+    /// These arguments are passed in addition to those provided via
+    /// [`SkeletonBuilder::clang_args`]. `source` has to match a path set via
+    /// [`SkeletonBuilder::source`] or [`SkeletonBuilder::sources`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libbpf_cargo::SkeletonBuilder;
+    ///
+    /// SkeletonBuilder::new()
+    ///     .sources(["myobject.bpf.c", "vendored.bpf.c"])
+    ///     .source_clang_args("vendored.bpf.c", ["-Wno-unused-function"])
+    ///     .build_and_generate("/output/path")
+    ///     .unwrap();
+    /// ```
+    pub fn source_clang_args<P, A, S>(&mut self, source: P, args: A) -> &mut SkeletonBuilder
+    where
+        P: AsRef<Path>,
+        A: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let args = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect();
+        let _prev = self
+            .source_clang_args
+            .insert(source.as_ref().to_path_buf(), args);
+        self
+    }
+
+    /// Add a directory to the `clang` include search path
+    ///
+    /// Default is no additional directories
+    pub fn include_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut SkeletonBuilder {
+        self.include_dirs.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Define the preprocessor macro `name`, optionally with `value`, when
+    /// compiling BPF programs
+    ///
+    /// Default is no additional macros
+    pub fn define<N, V>(&mut self, name: N, value: Option<V>) -> &mut SkeletonBuilder
+    where
+        N: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.defines.push((
+            name.as_ref().to_string(),
+            value.map(|value| value.as_ref().to_string()),
+        ));
+        self
+    }
+
+    /// Specify the byte order to compile BPF programs for
+    ///
+    /// This is necessary when cross-compiling for a target with a byte
+    /// order different from that of the host.
+    ///
+    /// Default is [`Endianness::Native`]
+    pub fn endianness(&mut self, endianness: Endianness) -> &mut SkeletonBuilder {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Generate a `vmlinux.h` header for the running kernel and make it
+    /// available to BPF programs via `#include "vmlinux.h"`
+    ///
+    /// Headers are cached per kernel release in `$OUT_DIR`, if set, i.e.,
+    /// when used from a build script. A `vmlinux.h` located next to the
+    /// source file takes precedence over the generated one.
+    ///
+    /// Default is `false`
+    pub fn generate_vmlinux_h(&mut self, generate: bool) -> &mut SkeletonBuilder {
+        self.vmlinux_h = generate;
+        self
+    }
+
+    /// Generate the `vmlinux.h` header from the kernel BTF in the file at
+    /// `btf` instead of from that of the running kernel
+    ///
+    /// The header is cached by the contents of the file. When used from a
+    /// build script and [`SkeletonBuilder::rerun_if_changed`] is enabled,
+    /// cargo is instructed to rerun the build script when the file
+    /// changes.
+    ///
+    /// This implies [`SkeletonBuilder::generate_vmlinux_h`].
+    pub fn vmlinux_btf<P: AsRef<Path>>(&mut self, btf: P) -> &mut SkeletonBuilder {
+        self.vmlinux_h = true;
+        self.vmlinux_btf = Some(btf.as_ref().to_path_buf());
+        self
+    }
+
     /// Specify whether or not to skip clang version check
     ///
     /// Default is `false`
@@ -205,6 +508,34 @@ impl SkeletonBuilder {
         self
     }
 
+    /// Refer to `core` instead of `std` in the generated code
+    ///
+    /// The type definitions in the generated `<NAME>_types` module then no
+    /// longer depend on `std`, allowing their use in `no_std` environments.
+    /// Note that the skeleton itself is built on `libbpf-rs`, which does
+    /// require `std`. Skeleton methods that require `std` types, such as
+    /// `persist`, are not generated.
+    ///
+    /// Can't be combined with light skeletons.
+    ///
+    /// Default is false
+    pub fn no_std(&mut self, no_std: bool) -> &mut SkeletonBuilder {
+        self.no_std = no_std;
+        self
+    }
+
+    /// Embed the object in the skeleton in zstd compressed form
+    ///
+    /// The object gets decompressed when the skeleton is first opened,
+    /// which requires the `zstd` feature of `libbpf-rs` to be enabled.
+    /// Compressing the object requires the `zstd` feature of this crate.
+    ///
+    /// Default is false
+    pub fn compress(&mut self, compress: bool) -> &mut SkeletonBuilder {
+        self.compress = compress;
+        self
+    }
+
     /// Specify which `rustfmt` binary to use
     ///
     /// Default searches `$PATH` for `rustfmt`
@@ -221,29 +552,69 @@ impl SkeletonBuilder {
         Ok(())
     }
 
+    /// Build BPF programs and generate the skeleton at path `output`, then
+    /// do so again whenever a C source or header file in the directory of
+    /// any source or in any [include directory][SkeletonBuilder::include_dir]
+    /// changes
+    ///
+    /// This function does not return unless the initial build or setting up
+    /// the watch fails; later build failures are reported on stderr. As
+    /// such, it is meant for development tooling and not for build scripts.
+    pub fn watch<P: AsRef<Path>>(&mut self, output: P) -> Result<()> {
+        let output = output.as_ref();
+        let dirs = self
+            .sources
+            .iter()
+            .filter_map(|source| source.parent())
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(Path::to_path_buf)
+            .collect::<Vec<_>>();
+        let mut watcher = watch::Watcher::new(dirs)?;
+        let () = self.build_and_generate(output)?;
+
+        loop {
+            let changed = watcher.wait()?;
+            if self.debug {
+                for path in &changed {
+                    println!("Changed: {}", path.display());
+                }
+            }
+
+            match self.build_and_generate(output) {
+                Ok(()) => println!("Regenerated `{}`", output.display()),
+                Err(err) => eprintln!("Build failed: {err:?}"),
+            }
+        }
+    }
+
     // Build BPF programs without generating a skeleton.
     //
-    // [`SkeletonBuilder::source`] must be set for this to succeed.
+    // [`SkeletonBuilder::source`] or [`SkeletonBuilder::sources`] must be
+    // set for this to succeed.
     pub fn build(&mut self) -> Result<()> {
         let source = self
-            .source
-            .as_ref()
+            .sources
+            .first()
             .ok_or_else(|| anyhow!("No source file provided"))?;
 
-        let filename = source
-            .file_name()
-            .ok_or_else(|| anyhow!("Missing file name"))?
-            .to_str()
-            .ok_or_else(|| anyhow!("Invalid unicode in file name"))?;
+        for source in &self.sources {
+            let filename = source
+                .file_name()
+                .ok_or_else(|| anyhow!("Missing file name"))?
+                .to_str()
+                .ok_or_else(|| anyhow!("Invalid unicode in file name"))?;
 
-        if !filename.ends_with(".bpf.c") {
-            return Err(anyhow!(
-                "Source `{}` does not have .bpf.c suffix",
-                source.display()
-            ));
+            if !filename.ends_with(".bpf.c") {
+                return Err(anyhow!(
+                    "Source `{}` does not have .bpf.c suffix",
+                    source.display()
+                ));
+            }
         }
 
         if self.obj.is_none() {
+            // Unwrap is safe here since we checked all file names above
+            let filename = source.file_name().unwrap().to_str().unwrap();
             let name = filename.split('.').next().unwrap();
             let dir = tempdir().context("failed to create temporary directory")?;
             let objfile = dir.path().join(format!("{name}.o"));
@@ -252,16 +623,76 @@ impl SkeletonBuilder {
             self.dir = Some(dir);
         }
 
-        build::build_single(
+        if let Some(source) = self
+            .source_clang_args
+            .keys()
+            .find(|source| !self.sources.contains(source))
+        {
+            return Err(anyhow!(
+                "`clang` arguments provided for unknown source `{}`",
+                source.display()
+            ));
+        }
+
+        let mut clang_args = self.clang_args.clone();
+        if self.vmlinux_h {
+            let cache_dir = match env::var_os("OUT_DIR") {
+                Some(dir) => PathBuf::from(dir).join("vmlinux"),
+                None => {
+                    if self.dir.is_none() {
+                        self.dir = Some(tempdir().context("failed to create temporary directory")?);
+                    }
+                    // Unwrap is safe here since we guarantee that dir.is_some() above
+                    self.dir.as_ref().unwrap().path().join("vmlinux")
+                }
+            };
+            let header =
+                vmlinux::gen_vmlinux_h(self.debug, self.vmlinux_btf.as_deref(), &cache_dir)
+                    .context("failed to generate vmlinux.h")?;
+            // Unwrap is safe here since the header is always inside a directory
+            clang_args.push(OsString::from("-I"));
+            clang_args.push(header.parent().unwrap().as_os_str().to_os_string());
+        }
+        for dir in &self.include_dirs {
+            clang_args.push(OsString::from("-I"));
+            clang_args.push(dir.as_os_str().to_os_string());
+        }
+        for (name, value) in &self.defines {
+            let define = match value {
+                Some(value) => format!("-D{name}={value}"),
+                None => format!("-D{name}"),
+            };
+            clang_args.push(OsString::from(define));
+        }
+
+        let deps = build::build_single(
             self.debug,
-            source,
+            &self.sources,
             // Unwrap is safe here since we guarantee that obj.is_some() above
             self.obj.as_ref().unwrap(),
             self.clang.as_ref(),
             self.skip_clang_version_check,
-            self.clang_args.clone(),
+            clang_args,
+            &self.source_clang_args,
+            self.endianness,
+            self.compiler,
         )
-        .with_context(|| format!("failed to build `{}`", source.display()))?;
+        .with_context(|| {
+            let sources = self
+                .sources
+                .iter()
+                .map(|source| format!("`{}`", source.display()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("failed to build {sources}")
+        })?;
+
+        // Only build scripts are expected to talk to cargo.
+        if self.rerun_if_changed && env::var_os("OUT_DIR").is_some() {
+            for dep in deps.iter().chain(self.vmlinux_btf.as_ref()) {
+                println!("cargo:rerun-if-changed={}", dep.display());
+            }
+        }
 
         Ok(())
     }
@@ -277,6 +708,62 @@ impl SkeletonBuilder {
             objfile,
             gen::OutputDest::File(output.as_ref()),
             Some(&self.rustfmt),
+            false,
+            false,
+            self.no_std,
+            self.compress,
+        )
+        .with_context(|| format!("failed to generate `{}`", objfile.display()))?;
+
+        Ok(())
+    }
+
+    // Generate a subskeleton at path `output` without building BPF programs.
+    //
+    // A subskeleton provides access to the maps, programs, and global
+    // variables of a BPF library, i.e., an object that is statically linked
+    // into the object a skeleton gets generated for. It is opened from an
+    // already existing `libbpf_rs::Object` of the latter.
+    //
+    // [`SkeletonBuilder::obj`] must be set for this to succeed.
+    pub fn generate_subskel<P: AsRef<Path>>(&mut self, output: P) -> Result<()> {
+        let objfile = self.obj.as_ref().ok_or_else(|| anyhow!("No object file"))?;
+
+        gen::gen_single(
+            self.debug,
+            objfile,
+            gen::OutputDest::File(output.as_ref()),
+            Some(&self.rustfmt),
+            true,
+            false,
+            self.no_std,
+            self.compress,
+        )
+        .with_context(|| format!("failed to generate `{}`", objfile.display()))?;
+
+        Ok(())
+    }
+
+    // Generate a light skeleton at path `output` without building BPF
+    // programs.
+    //
+    // A light skeleton embeds a loader program generated from the object
+    // instead of the object itself. Loading it runs this program, creating
+    // the object's maps and loading its programs without involving libbpf.
+    //
+    // [`SkeletonBuilder::obj`] must be set for this to succeed.
+    pub fn generate_light<P: AsRef<Path>>(&mut self, output: P) -> Result<()> {
+        let objfile = self.obj.as_ref().ok_or_else(|| anyhow!("No object file"))?;
+
+        gen::gen_single(
+            self.debug,
+            objfile,
+            gen::OutputDest::File(output.as_ref()),
+            Some(&self.rustfmt),
+            false,
+            true,
+            self.no_std,
+            self.compress,
         )
         .with_context(|| format!("failed to generate `{}`", objfile.display()))?;
 
@@ -11,9 +11,12 @@ use clap::Subcommand;
 
 #[doc(hidden)]
 mod build;
+mod check;
 mod gen;
 mod make;
 mod metadata;
+mod vmlinux;
+mod watch;
 
 #[doc(hidden)]
 #[derive(Debug, Parser)]
@@ -69,6 +72,10 @@ enum Command {
         manifest_path: Option<PathBuf>,
         #[command(flatten)]
         clang_opts: ClangOpts,
+        #[arg(long)]
+        /// Keep running and rebuild objects and skeletons whenever a source
+        /// or header file in a prog dir changes
+        watch: bool,
     },
     /// Generate skeleton files
     Gen {
@@ -83,6 +90,74 @@ enum Command {
         ///
         /// When specified, skeletons for the rest of the project will not be generated
         object: Option<PathBuf>,
+        #[arg(long, requires = "object")]
+        /// Generate a subskeleton for the object file instead
+        ///
+        /// Subskeletons provide access to the maps, programs, and global
+        /// variables of a BPF library linked into another object
+        subskel: bool,
+        #[arg(long, requires = "object", conflicts_with_all = ["subskel", "no_std", "compress"])]
+        /// Generate a light skeleton for the object file instead
+        ///
+        /// Light skeletons embed a loader program generated from the
+        /// object, which loads the object when run, without involving
+        /// libbpf
+        light: bool,
+        #[arg(long)]
+        /// Refer to `core` instead of `std` in generated code
+        ///
+        /// This allows using the generated type definitions in `no_std`
+        /// environments. Methods requiring `std` types, such as persisting
+        /// links, are not generated
+        no_std: bool,
+        #[arg(long, conflicts_with = "subskel")]
+        /// Embed zstd compressed objects in skeletons
+        ///
+        /// Objects get decompressed when the skeleton is first opened, which
+        /// requires the `zstd` feature of `libbpf-rs`. Compressing objects
+        /// requires the `zstd` feature of `libbpf-cargo`
+        compress: bool,
+        #[arg(long)]
+        /// Print a JSON description of the generated skeletons to stdout
+        ///
+        /// The description covers programs, maps, global variables, and
+        /// output paths. When combined with `--object`, the skeleton is
+        /// written next to the object file instead of to stdout
+        emit_json: bool,
+    },
+    /// Generate a vmlinux.h header from kernel BTF
+    ///
+    /// Headers for the running kernel are cached per kernel release in the
+    /// workspace target directory.
+    GenVmlinux {
+        #[arg(long, value_parser)]
+        /// Path to top level Cargo.toml
+        manifest_path: Option<PathBuf>,
+        #[arg(long, value_parser)]
+        /// Path to a file containing kernel BTF to use instead of that of
+        /// the running kernel
+        btf: Option<PathBuf>,
+        #[arg(short, long, value_parser)]
+        /// Path to write the header to instead of stdout
+        output: Option<PathBuf>,
+    },
+    /// Load built bpf objects and report verifier results
+    ///
+    /// Each program is loaded separately, so that all verifier failures are
+    /// reported. Exits with an error if any program fails to load.
+    Check {
+        #[arg(long, value_parser)]
+        /// Path to top level Cargo.toml
+        manifest_path: Option<PathBuf>,
+        #[arg(long, value_parser, conflicts_with = "manifest_path")]
+        /// Check the specified object file instead of the project's objects
+        object: Option<PathBuf>,
+        #[arg(long = "test-run", value_name = "PROG")]
+        /// Test run the named program after loading it (can be repeated)
+        test_run: Vec<String>,
+        #[arg(long)]
+        /// Print the report as JSON
+        json: bool,
     },
     /// Build project
     Make {
@@ -119,22 +194,62 @@ fn main() -> Result<()> {
                         clang_args,
                         skip_clang_version_checks,
                     },
-            } => build::build(
-                debug,
-                manifest_path.as_ref(),
-                clang_path.as_ref(),
-                clang_args,
-                skip_clang_version_checks,
-            ),
+                watch,
+            } => {
+                if watch {
+                    watch::watch(
+                        debug,
+                        manifest_path.as_ref(),
+                        clang_path.as_ref(),
+                        clang_args,
+                        skip_clang_version_checks,
+                    )
+                } else {
+                    build::build(
+                        debug,
+                        manifest_path.as_ref(),
+                        clang_path.as_ref(),
+                        clang_args,
+                        skip_clang_version_checks,
+                    )
+                }
+            }
             Command::Gen {
                 manifest_path,
                 rustfmt_path,
                 object,
+                subskel,
+                light,
+                no_std,
+                compress,
+                emit_json,
             } => gen::gen(
                 debug,
                 manifest_path.as_ref(),
                 rustfmt_path.as_ref(),
                 object.as_ref(),
+                subskel,
+                light,
+                no_std,
+                compress,
+                emit_json,
+            ),
+            Command::GenVmlinux {
+                manifest_path,
+                btf,
+                output,
+            } => vmlinux::gen_vmlinux(debug, manifest_path.as_ref(), btf.as_ref(), output.as_ref()),
+            Command::Check {
+                manifest_path,
+                object,
+                test_run,
+                json,
+            } => check::check(
+                debug,
+                manifest_path.as_ref(),
+                object.as_ref(),
+                &test_run,
+                json,
             ),
             Command::Make {
                 manifest_path,
@@ -35,7 +35,18 @@ pub fn make(
     if !quiet {
         println!("Generating skeletons");
     }
-    gen::gen(debug, manifest_path, None, rustfmt_path).context("Failed to generate skeletons")?;
+    gen::gen(
+        debug,
+        manifest_path,
+        None,
+        rustfmt_path,
+        false,
+        false,
+        false,
+        false,
+        false,
+    )
+    .context("Failed to generate skeletons")?;
 
     let mut cmd = Command::new("cargo");
     cmd.arg("build");
@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ffi::OsString;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
@@ -10,10 +13,29 @@ use cargo_metadata::Package;
 use serde::Deserialize;
 use serde_json::value::Value;
 
+use crate::build::Compiler;
+use crate::build::Endianness;
+
+#[derive(Default, Deserialize)]
+struct LibbpfProgMetadata {
+    #[serde(default)]
+    clang_args: Vec<String>,
+    #[serde(default)]
+    extra_sources: Vec<PathBuf>,
+}
+
 #[derive(Default, Deserialize)]
 struct LibbpfPackageMetadata {
     prog_dir: Option<PathBuf>,
     target_dir: Option<PathBuf>,
+    #[serde(default)]
+    clang_args: Vec<String>,
+    #[serde(default)]
+    endianness: Endianness,
+    #[serde(default)]
+    compiler: Compiler,
+    #[serde(default)]
+    progs: HashMap<String, LibbpfProgMetadata>,
 }
 
 #[derive(Deserialize)]
@@ -29,10 +51,19 @@ pub struct UnprocessedObj {
     pub package: String,
     /// Path to .c
     pub path: PathBuf,
+    /// Paths to additional .c files to compile separately and to link
+    /// with `path` into the object
+    pub extra_sources: Vec<PathBuf>,
     /// Where to place compiled object
     pub out: PathBuf,
     /// Object name (eg: `runqslower.bpf.c` -> `runqslower`)
     pub name: String,
+    /// Additional arguments to pass to `clang` when compiling the object
+    pub clang_args: Vec<OsString>,
+    /// Byte order to compile the object for
+    pub endianness: Endianness,
+    /// Compiler to build the object with
+    pub compiler: Compiler,
 }
 
 fn get_package(
@@ -81,6 +112,15 @@ fn get_package(
         target_dir.push("bpf");
     };
 
+    // Additional source files are only compiled as part of the object
+    // they belong to.
+    let extra_sources = package_metadata
+        .progs
+        .values()
+        .flat_map(|prog| prog.extra_sources.iter())
+        .map(|source| package_root.join(source))
+        .collect::<HashSet<_>>();
+
     // Get an iterator to the input directory. If directory is missing,
     // skip the current project
     let dir_iter = match fs::read_dir(&package_root) {
@@ -110,7 +150,7 @@ fn get_package(
                 Err(_) => return None,
             };
 
-            if !path.is_file() {
+            if !path.is_file() || extra_sources.contains(&path) {
                 return None;
             }
 
@@ -127,11 +167,35 @@ fn get_package(
                         .unwrap() // Already know it has enough '.'s
                         .to_string();
 
+                    let clang_args = package_metadata
+                        .clang_args
+                        .iter()
+                        .chain(
+                            package_metadata
+                                .progs
+                                .get(&name)
+                                .into_iter()
+                                .flat_map(|prog| prog.clang_args.iter()),
+                        )
+                        .map(OsString::from)
+                        .collect();
+                    let extra_sources = package_metadata
+                        .progs
+                        .get(&name)
+                        .into_iter()
+                        .flat_map(|prog| prog.extra_sources.iter())
+                        .map(|source| package_root.join(source))
+                        .collect();
+
                     return Some(UnprocessedObj {
                         package: package.name.clone(),
                         out: target_dir.clone(),
                         path,
+                        extra_sources,
                         name,
+                        clang_args,
+                        endianness: package_metadata.endianness,
+                        compiler: package_metadata.compiler,
                     });
                 }
             }
@@ -1,14 +1,21 @@
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fs::copy as copy_file;
 use std::fs::create_dir;
 use std::fs::read;
 use std::fs::read_to_string;
+use std::fs::remove_file;
+use std::fs::set_permissions;
 use std::fs::write;
 use std::fs::File;
 use std::fs::OpenOptions;
+use std::fs::Permissions;
 use std::io::Write;
+use std::os::unix::fs::PermissionsExt as _;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
+use std::slice;
 
 use goblin::Object;
 use libbpf_rs::btf::types;
@@ -19,9 +26,19 @@ use tempfile::tempdir;
 use tempfile::NamedTempFile;
 use tempfile::TempDir;
 
+use crate::btf::codegen;
 use crate::build::build;
+use crate::build::build_single;
+use crate::check::check_object;
+use crate::gen::btf::has_implicit_padding;
 use crate::gen::btf::GenBtf;
+use crate::gen::json::skeleton_info;
+use crate::gen::prog_attach_signature;
 use crate::make::make;
+use crate::vmlinux::gen_vmlinux_h;
+use crate::watch::Watcher;
+use crate::Compiler;
+use crate::Endianness;
 use crate::SkeletonBuilder;
 
 /// Creates a temporary directory and initializes a default cargo project inside.
@@ -203,6 +220,136 @@ fn test_build_custom() {
     );
 }
 
+#[test]
+fn test_build_custom_clang_args() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add custom build rules
+    let mut cargo_toml_file = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+    let libbpf_metadata = r#"[package.metadata.libbpf]
+    clang_args = ["-DFOO"]
+    endianness = "big"
+
+    [package.metadata.libbpf.progs.prog]
+    clang_args = ["-DBAR=1"]
+    "#;
+    cargo_toml_file
+        .write_all(libbpf_metadata.as_bytes())
+        .expect("write to Cargo.toml failed");
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog relying on both macros
+    let mut prog_file =
+        File::create(proj_dir.join("src/bpf/prog.bpf.c")).expect("failed to create prog file");
+    writeln!(
+        prog_file,
+        r#"
+        #if !defined(FOO) || BAR != 1
+        #error "missing clang arguments"
+        #endif
+        "#
+    )
+    .expect("write to prog file failed");
+
+    // Add another prog that must not see per-prog arguments of the first
+    let mut other_file =
+        File::create(proj_dir.join("src/bpf/other.bpf.c")).expect("failed to create prog file");
+    writeln!(
+        other_file,
+        r#"
+        #if !defined(FOO) || defined(BAR)
+        #error "unexpected clang arguments"
+        #endif
+        "#
+    )
+    .expect("write to prog file failed");
+
+    build(true, Some(&cargo_toml), None, Vec::new(), true).unwrap();
+
+    // Validate generated object file
+    let path = proj_dir.as_path().join("target/bpf/prog.bpf.o");
+    let buffer = read(&path).expect("failed to read object file");
+    match Object::parse(&buffer).expect("failed to parse object file") {
+        Object::Elf(elf) => assert!(!elf.little_endian),
+        _ => panic!("wrong object file format"),
+    }
+}
+
+#[test]
+fn test_build_extra_sources() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    let mut cargo_toml_file = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+    let libbpf_metadata = r#"[package.metadata.libbpf.progs.prog]
+    extra_sources = ["lib.c"]
+    "#;
+    cargo_toml_file
+        .write_all(libbpf_metadata.as_bytes())
+        .expect("write to Cargo.toml failed");
+
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog calling a function defined in a separate source
+    let mut prog_file =
+        File::create(proj_dir.join("src/bpf/prog.bpf.c")).expect("failed to create prog file");
+    writeln!(
+        prog_file,
+        r#"
+        int helper(int x);
+
+        __attribute__((section("xdp"), used))
+        int handle(void *ctx)
+        {{
+            return helper(2);
+        }}
+
+        char LICENSE[] __attribute__((section("license"), used)) = "GPL";
+        "#
+    )
+    .expect("write to prog file failed");
+
+    let mut lib_file =
+        File::create(proj_dir.join("src/bpf/lib.c")).expect("failed to create lib file");
+    writeln!(
+        lib_file,
+        r#"
+        __attribute__((noinline))
+        int helper(int x)
+        {{
+            return x;
+        }}
+        "#
+    )
+    .expect("write to lib file failed");
+
+    build(true, Some(&cargo_toml), None, Vec::new(), true).unwrap();
+
+    // The linked object contains both functions
+    let path = proj_dir.as_path().join("target/bpf/prog.bpf.o");
+    let buffer = read(&path).expect("failed to read object file");
+    let Object::Elf(elf) = Object::parse(&buffer).expect("failed to parse object file") else {
+        panic!("wrong object file format")
+    };
+    for name in ["handle", "helper"] {
+        assert!(
+            elf.syms
+                .iter()
+                .any(|sym| elf.strtab.get_at(sym.st_name) == Some(name)),
+            "symbol `{name}` missing"
+        );
+    }
+    // The extra source is not built into an object of its own
+    assert!(!proj_dir.join("target/bpf/lib.o").exists());
+}
+
 #[test]
 fn test_unknown_metadata_section() {
     let (_dir, proj_dir, cargo_toml) = setup_temp_project();
@@ -560,9 +707,22 @@ fn test_skeleton_basic() {
             // Check that attach() is generated
             skel.attach().expect("failed to attach progs");
 
+            // Check that links can be kept in place
+            skel.persist("/sys/fs/bpf/prog").expect("failed to persist links");
+
             // Check that Option<Link> field is generated
             let _mylink = skel.links.this_is_my_prog.unwrap();
         }}
+
+        // Check that skeletons can be opened with a configured object
+        // builder.
+        fn _open_configured() -> libbpf_rs::Result<OpenProgSkel<'static>> {{
+            let mut obj_builder = libbpf_rs::ObjectBuilder::default();
+            let _builder = obj_builder
+                .btf_custom_path("/tmp/vmlinux.btf")?
+                .pin_root_path("/sys/fs/bpf/prog")?;
+            ProgSkelBuilder::from(obj_builder).open()
+        }}
         "#,
     )
     .expect("failed to write to main.rs");
@@ -651,6 +811,7 @@ fn test_skeleton_datasec() {
         int mycustomdata SEC(".data.custom");
         int mycustombss SEC(".bss.custom");
         const int mycustomrodata SEC(".rodata.custom.1") = 43;
+        int mydasheddata SEC(".data.with-dash");
 
         SEC("kprobe/foo")
         int this_is_my_prog(u64 *ctx)
@@ -721,6 +882,7 @@ fn test_skeleton_datasec() {
             open_skel.data_custom_mut().mycustomdata = 1337;
             open_skel.bss_custom_mut().mycustombss = 12;
             assert_eq!(open_skel.rodata_custom_1().mycustomrodata, 43);
+            open_skel.data_with_dash_mut().mydasheddata = 7;
 
             let mut skel = open_skel
                 .load()
@@ -730,6 +892,7 @@ fn test_skeleton_datasec() {
             skel.bss_mut().myglobal = 24;
             skel.data_custom_mut().mycustomdata += 1;
             skel.bss_custom_mut().mycustombss += 1;
+            skel.data_with_dash_mut().mydasheddata += 1;
             assert_eq!(skel.rodata_custom_1().mycustomrodata, 43);
 
             // Read only for rodata after load
@@ -751,7 +914,83 @@ fn test_skeleton_datasec() {
 }
 
 #[test]
-fn test_skeleton_builder_basic() {
+fn test_skeleton_datasec_name_conflict() {
+    let (_dir, proj_dir, _cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        int dashed __attribute__((section(".data.foo-bar")));
+        int dotted __attribute__((section(".data.foo.bar")));
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    let skel = NamedTempFile::new().unwrap();
+
+    // Should fail b/c both sections map to `data_foo_bar`
+    let err = SkeletonBuilder::new()
+        .source(proj_dir.join("src/bpf/prog.bpf.c"))
+        .debug(true)
+        .clang("clang")
+        .build_and_generate(skel.path())
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("data_foo_bar"), "{err:#}");
+}
+
+/// Check that we detect composites whose Rust definitions contain bytes
+/// not covered by any field.
+#[test]
+fn test_btf_implicit_padding() {
+    let mut btf = Btf::new_empty().unwrap();
+    let u8_ = btf.add_int("u8", 1, types::IntEncoding::None).unwrap();
+    let u32_ = btf.add_int("u32", 4, types::IntEncoding::None).unwrap();
+    let u64_ = btf.add_int("u64", 8, types::IntEncoding::None).unwrap();
+
+    let dense = btf.add_struct("dense", 8).unwrap();
+    let () = btf.add_field("a", u32_, 0, None).unwrap();
+    let () = btf.add_field("b", u32_, 32, None).unwrap();
+    // `u8` followed by a naturally aligned `u32`.
+    let padded = btf.add_struct("padded", 8).unwrap();
+    let () = btf.add_field("tag", u8_, 0, None).unwrap();
+    let () = btf.add_field("value", u32_, 32, None).unwrap();
+    // The gap in front of `value` is covered by explicit padding.
+    let explicit = btf.add_struct("explicit", 16).unwrap();
+    let () = btf.add_field("tag", u8_, 0, None).unwrap();
+    let () = btf.add_field("value", u32_, 64, None).unwrap();
+    let () = btf.add_field("value2", u32_, 96, None).unwrap();
+    let uniform = btf.add_union("uniform", 8).unwrap();
+    let () = btf.add_field("a", u64_, 0, None).unwrap();
+    let () = btf.add_field("b", u64_, 0, None).unwrap();
+    let mixed = btf.add_union("mixed", 8).unwrap();
+    let () = btf.add_field("small", u32_, 0, None).unwrap();
+    let () = btf.add_field("large", u64_, 0, None).unwrap();
+
+    let check = |id| {
+        let ty = btf.type_by_id::<BtfType<'_>>(id).unwrap();
+        let composite = types::Composite::try_from(ty).unwrap();
+        has_implicit_padding(&composite, &btf).unwrap()
+    };
+    assert!(!check(dense));
+    assert!(check(padded));
+    assert!(!check(explicit));
+    assert!(!check(uniform));
+    assert!(check(mixed));
+}
+
+#[test]
+fn test_skeleton_typed_map() {
     let (_dir, proj_dir, cargo_toml) = setup_temp_project();
 
     // Add prog dir
@@ -771,12 +1010,53 @@ fn test_skeleton_builder_basic() {
         #include "vmlinux.h"
         #include <bpf/bpf_helpers.h>
 
+        struct flow_key {{
+                u32 saddr;
+                u32 daddr;
+        }};
+
+        struct flow_stats {{
+                u64 packets;
+                u64 bytes;
+        }};
+
         struct {{
                 __uint(type, BPF_MAP_TYPE_HASH);
-                __uint(max_entries, 1024);
+                __uint(max_entries, 16);
+                __type(key, struct flow_key);
+                __type(value, struct flow_stats);
+        }} flows SEC(".maps");
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_ARRAY);
+                __uint(max_entries, 4);
                 __type(key, u32);
-                __type(value, u64);
-        }} mymap SEC(".maps");
+                __type(value, u64[2]);
+        }} counters SEC(".maps");
+
+        struct padded {{
+                u8 tag;
+                u32 value;
+        }};
+
+        union mixed {{
+                u32 small;
+                u64 large;
+        }};
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_HASH);
+                __uint(max_entries, 4);
+                __type(key, u32);
+                __type(value, struct padded);
+        }} padded SEC(".maps");
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_HASH);
+                __uint(max_entries, 4);
+                __type(key, u32);
+                __type(value, union mixed);
+        }} mixed SEC(".maps");
 
         SEC("kprobe/foo")
         int this_is_my_prog(u64 *ctx)
@@ -790,13 +1070,24 @@ fn test_skeleton_builder_basic() {
     // Lay down the necessary header files
     add_vmlinux_header(&proj_dir);
 
-    // Generate skeleton file
-    let skel = NamedTempFile::new().unwrap();
-    SkeletonBuilder::new()
-        .source(proj_dir.join("src/bpf/prog.bpf.c"))
-        .debug(true)
-        .build_and_generate(skel.path())
-        .unwrap();
+    make(
+        true,
+        Some(&cargo_toml),
+        None,
+        Vec::new(),
+        true,
+        true,
+        Vec::new(),
+        None,
+    )
+    .unwrap();
+
+    // Values with bytes not covered by any field must not be accessed
+    // through their raw bytes.
+    let skel = read_to_string(proj_dir.join("src/bpf/prog.skel.rs")).unwrap();
+    assert!(skel.contains("fn flows_typed("));
+    assert!(!skel.contains("fn padded_typed("));
+    assert!(!skel.contains("fn mixed_typed("));
 
     let mut cargo = OpenOptions::new()
         .append(true)
@@ -822,99 +1113,1599 @@ fn test_skeleton_builder_basic() {
     write!(
         source,
         r#"
-        #[path = "{skel_path}"]
-        mod skel;
-        use skel::*;
+        #![warn(elided_lifetimes_in_paths)]
+        mod bpf;
+        use bpf::*;
         use libbpf_rs::skel::SkelBuilder;
         use libbpf_rs::skel::OpenSkel;
-        use libbpf_rs::skel::Skel;
+        use libbpf_rs::MapFlags;
 
         fn main() {{
             let builder = ProgSkelBuilder::default();
-            let mut open_skel = builder
+            let open_skel = builder
                 .open()
                 .expect("failed to open skel");
 
-            // Check that we can grab handles to open maps/progs
-            let _open_map = open_skel.maps().mymap();
-            let _open_prog = open_skel.progs().this_is_my_prog();
-            let _open_map_mut = open_skel.maps_mut().mymap();
-            let _open_prog_mut = open_skel.progs_mut().this_is_my_prog();
-
-
-            let mut skel = open_skel
+            let skel = open_skel
                 .load()
                 .expect("failed to load skel");
 
-            // Check that we can grab handles to loaded maps/progs
-            let _map = skel.maps().mymap();
-            let _prog = skel.progs().this_is_my_prog();
-            let _map_mut = skel.maps_mut().mymap();
-            let _prog_mut = skel.progs_mut().this_is_my_prog();
+            let maps = skel.maps();
+            let flows = maps.flows_typed().expect("failed to create typed map");
+            let key = prog_types::flow_key {{
+                saddr: 1,
+                daddr: 2,
+            }};
+            let stats = prog_types::flow_stats {{
+                packets: 3,
+                bytes: 4,
+            }};
+            let () = flows.update(&key, &stats, MapFlags::ANY).unwrap();
+            let _stats: Option<prog_types::flow_stats> =
+                flows.lookup(&key, MapFlags::ANY).unwrap();
+
+            let counters = maps.counters_typed().expect("failed to create typed map");
+            let _counter: Option<[u64; 2]> = counters.lookup(&0u32, MapFlags::ANY).unwrap();
+        }}
+        "#,
+    )
+    .expect("failed to write to main.rs");
 
-            // Check that attach() is generated
-            skel.attach().expect("failed to attach progs");
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(cargo_toml.into_os_string())
+        .env("RUSTFLAGS", "-Dwarnings")
+        .status()
+        .expect("failed to spawn cargo-build");
+    assert!(status.success());
+}
 
-            // Check that Option<Link> field is generated
-            let _mylink = skel.links.this_is_my_prog.unwrap();
+/// Check that we can describe the skeleton of an object in a machine
+/// readable manner.
+#[test]
+fn test_skeleton_info() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_HASH);
+                __uint(max_entries, 16);
+                __type(key, u32);
+                __type(value, u64);
+        }} counts SEC(".maps");
+
+        u64 total = 0;
+
+        SEC("kprobe/foo")
+        int handle_kprobe(void *ctx)
+        {{
+                total++;
+                return 0;
         }}
+
+        char LICENSE[] SEC("license") = "GPL";
         "#,
-        skel_path = skel.path().display(),
     )
-    .expect("failed to write to main.rs");
+    .expect("failed to write prog.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    build(true, Some(&cargo_toml), None, Vec::new(), true).unwrap();
+
+    let obj = proj_dir.join("target/bpf/prog.bpf.o");
+    let info = skeleton_info("prog", &obj, None).unwrap();
+    assert_eq!(info.name, "prog");
+    assert_eq!(info.types_module, "prog_types");
+    assert_eq!(info.skeleton, None);
+
+    assert_eq!(info.programs.len(), 1);
+    let prog = &info.programs[0];
+    assert_eq!(prog.name, "handle_kprobe");
+    assert_eq!(prog.section, "kprobe/foo");
+    assert_eq!(prog.prog_type.as_deref(), Some("kprobe"));
+
+    let map = info
+        .maps
+        .iter()
+        .find(|map| map.raw_name == "counts")
+        .unwrap();
+    assert_eq!(map.name.as_deref(), Some("counts"));
+    assert_eq!(map.map_type.as_deref(), Some("hash"));
+    assert_eq!(map.key_type.as_deref(), Some("u32"));
+    assert_eq!(map.key_size, 4);
+    assert_eq!(map.value_type.as_deref(), Some("u64"));
+    assert_eq!(map.value_size, 8);
+    assert_eq!(map.max_entries, 16);
+
+    let bss = info.datasecs.iter().find(|sec| sec.name == "bss").unwrap();
+    assert_eq!(bss.section, ".bss");
+    assert_eq!(bss.variables.len(), 1);
+    assert_eq!(bss.variables[0].name, "total");
+    assert_eq!(bss.variables[0].type_name, "u64");
+    assert_eq!(bss.variables[0].size, 8);
+
+    // The description should be serializable as JSON.
+    let json = serde_json::to_value(&info).unwrap();
+    assert_eq!(json["programs"][0]["section"], "kprobe/foo");
+}
+
+#[test]
+fn test_skeleton_typed_attach() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        SEC("kprobe/foo")
+        int auto_kprobe(void *ctx)
+        {{
+                return 0;
+        }}
+
+        SEC("kprobe")
+        int manual_kprobe(void *ctx)
+        {{
+                return 0;
+        }}
+
+        SEC("uretprobe")
+        int manual_uretprobe(void *ctx)
+        {{
+                return 0;
+        }}
+
+        SEC("tp")
+        int manual_tp(void *ctx)
+        {{
+                return 0;
+        }}
+
+        SEC("cgroup/connect4")
+        int handle_connect(struct bpf_sock_addr *ctx)
+        {{
+                return 1;
+        }}
+
+        SEC("xdp")
+        int handle_xdp(struct xdp_md *ctx)
+        {{
+                return XDP_PASS;
+        }}
+
+        SEC("socket")
+        int handle_socket(struct __sk_buff *skb)
+        {{
+                return 0;
+        }}
+
+        char LICENSE[] SEC("license") = "GPL";
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    make(
+        true,
+        Some(&cargo_toml),
+        None,
+        Vec::new(),
+        true,
+        true,
+        Vec::new(),
+        None,
+    )
+    .unwrap();
+
+    let skel = read_to_string(proj_dir.join("src/bpf/prog.skel.rs")).unwrap();
+    // Programs that can't be attached by themselves don't get a method.
+    assert!(!skel.contains("attach_handle_socket"));
+
+    let mut cargo = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+
+    // Make test project use our development libbpf-rs version
+    writeln!(
+        cargo,
+        r#"
+        libbpf-rs = {{ path = "{}" }}
+        "#,
+        get_libbpf_rs_path().as_path().display()
+    )
+    .expect("failed to write to Cargo.toml");
+
+    let mut source = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(proj_dir.join("src/main.rs"))
+        .expect("failed to open main.rs");
+
+    write!(
+        source,
+        r#"
+        #![warn(elided_lifetimes_in_paths)]
+        mod bpf;
+        use bpf::*;
+        use libbpf_rs::Link;
+        use libbpf_rs::Result;
+
+        fn _attach(skel: &mut ProgSkel<'_>) -> Result<Vec<Link>> {{
+            let mut progs = skel.progs_mut();
+            Ok(vec![
+                progs.attach_auto_kprobe()?,
+                progs.attach_manual_kprobe("do_sys_open")?,
+                progs.attach_manual_uretprobe(-1, "/bin/true", 0)?,
+                progs.attach_manual_tp("syscalls", "sys_enter_openat")?,
+                progs.attach_handle_connect(3)?,
+                progs.attach_handle_xdp(1)?,
+            ])
+        }}
+
+        fn _attach_some(skel: &mut ProgSkel<'_>) -> Result<()> {{
+            skel.attach_prog("auto_kprobe")?;
+            skel.attach_only(&["auto_kprobe", "handle_xdp"])
+        }}
+
+        fn main() {{}}
+        "#,
+    )
+    .expect("failed to write to main.rs");
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(cargo_toml.into_os_string())
+        .env("RUSTFLAGS", "-Dwarnings")
+        .status()
+        .expect("failed to spawn cargo-build");
+    assert!(status.success());
+}
+
+/// Check that we pick the correct attach method based on a program's
+/// section.
+#[test]
+fn test_skeleton_attach_signature() {
+    let call = |section| prog_attach_signature(section, false).map(|(_, _, call)| call);
+
+    assert_eq!(call("kprobe/foo").as_deref(), Some("attach()"));
+    assert_eq!(call("kprobe.multi/foo*").as_deref(), Some("attach()"));
+    assert_eq!(call("kretprobe.multi/foo*").as_deref(), Some("attach()"));
+    assert_eq!(
+        call("kprobe.multi").as_deref(),
+        Some("attach_kprobe_multi(false, symbols)")
+    );
+    assert_eq!(
+        call("uprobe.multi/libc.so.6:malloc").as_deref(),
+        Some("attach()")
+    );
+    assert_eq!(
+        call("uretprobe").as_deref(),
+        Some("attach_uprobe(true, pid, binary_path.as_ref(), func_offset)")
+    );
+    assert_eq!(call("lsm/file_open").as_deref(), Some("attach()"));
+    // Sections lacking a target can't be attached by themselves.
+    assert_eq!(call("uprobe.multi"), None);
+    assert_eq!(call("uretprobe.multi.s"), None);
+    assert_eq!(call("socket"), None);
+}
+
+/// Check that we generate pin path constants and helpers for opening
+/// pinned maps.
+#[test]
+fn test_skeleton_pinned_maps() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_HASH);
+                __uint(max_entries, 16);
+                __type(key, u32);
+                __type(value, u64);
+                __uint(pinning, LIBBPF_PIN_BY_NAME);
+        }} pinned SEC(".maps");
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_HASH);
+                __uint(max_entries, 16);
+                __type(key, u32);
+                __type(value, u64);
+        }} unpinned SEC(".maps");
+
+        SEC("kprobe/foo")
+        int this_is_my_prog(u64 *ctx)
+        {{
+                return 0;
+        }}
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    make(
+        true,
+        Some(&cargo_toml),
+        None,
+        Vec::new(),
+        true,
+        true,
+        Vec::new(),
+        None,
+    )
+    .unwrap();
+
+    let skel = read_to_string(proj_dir.join("src/bpf/prog.skel.rs")).unwrap();
+    assert!(skel.contains(r#"pub const PINNED: &str = "/sys/fs/bpf/pinned";"#));
+    assert!(!skel.contains("UNPINNED"));
+
+    let mut cargo = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+
+    // Make test project use our development libbpf-rs version
+    writeln!(
+        cargo,
+        r#"
+        libbpf-rs = {{ path = "{}" }}
+        "#,
+        get_libbpf_rs_path().as_path().display()
+    )
+    .expect("failed to write to Cargo.toml");
+
+    let mut source = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(proj_dir.join("src/main.rs"))
+        .expect("failed to open main.rs");
+
+    write!(
+        source,
+        r#"
+        mod bpf;
+        use bpf::*;
+
+        fn _open() -> libbpf_rs::Result<libbpf_rs::MapHandle> {{
+            let _maps = ProgPinnedMaps::open_pinned_in("/sys/fs/bpf/other")?;
+            let maps = ProgPinnedMaps::open_pinned()?;
+            Ok(maps.pinned)
+        }}
+
+        fn main() {{
+            assert_eq!(prog_pins::PINNED, "/sys/fs/bpf/pinned");
+        }}
+        "#,
+    )
+    .expect("failed to write to main.rs");
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(cargo_toml.into_os_string())
+        .env("RUSTFLAGS", "-Dwarnings")
+        .status()
+        .expect("failed to spawn cargo-build");
+    assert!(status.success());
+}
+
+/// Check that we detect pinned maps whose pin path constants would
+/// conflict.
+#[test]
+fn test_skeleton_pinned_maps_name_conflict() {
+    let (_dir, proj_dir, _cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_ARRAY);
+                __uint(max_entries, 1);
+                __type(key, u32);
+                __type(value, u64);
+                __uint(pinning, LIBBPF_PIN_BY_NAME);
+        }} pinned SEC(".maps");
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_ARRAY);
+                __uint(max_entries, 1);
+                __type(key, u32);
+                __type(value, u64);
+                __uint(pinning, LIBBPF_PIN_BY_NAME);
+        }} PINNED SEC(".maps");
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    let skel = NamedTempFile::new().unwrap();
+
+    // Should fail b/c both maps map to `PINNED`
+    let err = SkeletonBuilder::new()
+        .source(proj_dir.join("src/bpf/prog.bpf.c"))
+        .debug(true)
+        .clang("clang")
+        .build_and_generate(skel.path())
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("`PINNED`"), "{err:#}");
+}
+
+#[test]
+fn test_skeleton_struct_ops() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+        #include <bpf/bpf_tracing.h>
+
+        SEC("struct_ops/test_1")
+        int BPF_PROG(test_1, struct bpf_dummy_ops_state *state)
+        {{
+                return 0;
+        }}
+
+        SEC(".struct_ops.link")
+        struct bpf_dummy_ops dummy_1 = {{
+                .test_1 = (void *)test_1,
+        }};
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    make(
+        true,
+        Some(&cargo_toml),
+        None,
+        Vec::new(),
+        true,
+        true,
+        Vec::new(),
+        None,
+    )
+    .unwrap();
+
+    let mut cargo = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+
+    // Make test project use our development libbpf-rs version
+    writeln!(
+        cargo,
+        r#"
+        libbpf-rs = {{ path = "{}" }}
+        "#,
+        get_libbpf_rs_path().as_path().display()
+    )
+    .expect("failed to write to Cargo.toml");
+
+    let mut source = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(proj_dir.join("src/main.rs"))
+        .expect("failed to open main.rs");
+
+    write!(
+        source,
+        r#"
+        #![warn(elided_lifetimes_in_paths)]
+        mod bpf;
+        use bpf::*;
+        use libbpf_rs::skel::SkelBuilder;
+        use libbpf_rs::skel::OpenSkel;
+        use libbpf_rs::skel::Skel;
+
+        fn main() {{
+            let builder = ProgSkelBuilder::default();
+            let mut open_skel = builder
+                .open()
+                .expect("failed to open skel");
+
+            // struct_ops members can be adjusted before load
+            open_skel.struct_ops.dummy_1_mut().test_2 = std::ptr::null_mut();
+
+            let mut skel = open_skel
+                .load()
+                .expect("failed to load skel");
+
+            skel.attach().expect("failed to attach skel");
+            let _link: &Option<libbpf_rs::Link> = &skel.links.dummy_1;
+            let _prog_link: &Option<libbpf_rs::Link> = &skel.links.test_1;
+        }}
+        "#,
+    )
+    .expect("failed to write to main.rs");
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(cargo_toml.into_os_string())
+        .env("RUSTFLAGS", "-Dwarnings")
+        .status()
+        .expect("failed to spawn cargo-build");
+    assert!(status.success());
+}
+
+#[test]
+fn test_skeleton_builder_basic() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_HASH);
+                __uint(max_entries, 1024);
+                __type(key, u32);
+                __type(value, u64);
+        }} mymap SEC(".maps");
+
+        SEC("kprobe/foo")
+        int this_is_my_prog(u64 *ctx)
+        {{
+                return 0;
+        }}
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    // Generate skeleton file
+    let skel = NamedTempFile::new().unwrap();
+    SkeletonBuilder::new()
+        .source(proj_dir.join("src/bpf/prog.bpf.c"))
+        .debug(true)
+        .build_and_generate(skel.path())
+        .unwrap();
+
+    let mut cargo = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+
+    // Make test project use our development libbpf-rs version
+    writeln!(
+        cargo,
+        r#"
+        libbpf-rs = {{ path = "{}" }}
+        "#,
+        get_libbpf_rs_path().as_path().display()
+    )
+    .expect("failed to write to Cargo.toml");
+
+    let mut source = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(proj_dir.join("src/main.rs"))
+        .expect("failed to open main.rs");
+
+    write!(
+        source,
+        r#"
+        #[path = "{skel_path}"]
+        mod skel;
+        use skel::*;
+        use libbpf_rs::skel::SkelBuilder;
+        use libbpf_rs::skel::OpenSkel;
+        use libbpf_rs::skel::Skel;
+
+        fn main() {{
+            let builder = ProgSkelBuilder::default();
+            let mut open_skel = builder
+                .open()
+                .expect("failed to open skel");
+
+            // Check that we can grab handles to open maps/progs
+            let _open_map = open_skel.maps().mymap();
+            let _open_prog = open_skel.progs().this_is_my_prog();
+            let _open_map_mut = open_skel.maps_mut().mymap();
+            let _open_prog_mut = open_skel.progs_mut().this_is_my_prog();
+
+
+            let mut skel = open_skel
+                .load()
+                .expect("failed to load skel");
+
+            // Check that we can grab handles to loaded maps/progs
+            let _map = skel.maps().mymap();
+            let _prog = skel.progs().this_is_my_prog();
+            let _map_mut = skel.maps_mut().mymap();
+            let _prog_mut = skel.progs_mut().this_is_my_prog();
+
+            // Check that attach() is generated
+            skel.attach().expect("failed to attach progs");
+
+            // Check that Option<Link> field is generated
+            let _mylink = skel.links.this_is_my_prog.unwrap();
+        }}
+        "#,
+        skel_path = skel.path().display(),
+    )
+    .expect("failed to write to main.rs");
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(cargo_toml.into_os_string())
+        .env("RUSTFLAGS", "-Dwarnings")
+        .status()
+        .expect("failed to spawn cargo-build");
+    assert!(status.success());
+}
+
+/// Check that we can generate skeletons embedding compressed objects.
+#[cfg(feature = "zstd")]
+#[test]
+fn test_skeleton_builder_compressed() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        SEC("kprobe/foo")
+        int this_is_my_prog(u64 *ctx)
+        {{
+                return 0;
+        }}
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    let skel = NamedTempFile::new().unwrap();
+    SkeletonBuilder::new()
+        .source(proj_dir.join("src/bpf/prog.bpf.c"))
+        .build_and_generate(skel.path())
+        .unwrap();
+    let uncompressed = read_to_string(skel.path()).unwrap();
+
+    SkeletonBuilder::new()
+        .source(proj_dir.join("src/bpf/prog.bpf.c"))
+        .compress(true)
+        .build_and_generate(skel.path())
+        .unwrap();
+    let compressed = read_to_string(skel.path()).unwrap();
+    assert!(compressed.contains("CompressedObject"));
+    assert!(compressed.len() < uncompressed.len());
+
+    let mut cargo = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+
+    // Make test project use our development libbpf-rs version
+    writeln!(
+        cargo,
+        r#"
+        libbpf-rs = {{ path = "{}", features = ["zstd"] }}
+        "#,
+        get_libbpf_rs_path().as_path().display()
+    )
+    .expect("failed to write to Cargo.toml");
+
+    let mut source = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(proj_dir.join("src/main.rs"))
+        .expect("failed to open main.rs");
+
+    write!(
+        source,
+        r#"
+        #[path = "{skel_path}"]
+        mod skel;
+        use skel::*;
+        use libbpf_rs::skel::SkelBuilder;
+
+        fn main() {{
+            let _open_skel = ProgSkelBuilder::default()
+                .open()
+                .expect("failed to open skel");
+        }}
+        "#,
+        skel_path = skel.path().display(),
+    )
+    .expect("failed to write to main.rs");
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(cargo_toml.into_os_string())
+        .env("RUSTFLAGS", "-Dwarnings")
+        .status()
+        .expect("failed to spawn cargo-build");
+    assert!(status.success());
+}
+
+/// Check that skeletons generated for `no_std` compile in a `no_std` crate.
+#[test]
+fn test_skeleton_builder_no_std() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_HASH);
+                __uint(max_entries, 1024);
+                __type(key, u32);
+                __type(value, u64);
+                __uint(pinning, LIBBPF_PIN_BY_NAME);
+        }} mymap SEC(".maps");
+
+        u64 counter = 0;
+
+        SEC("kprobe/foo")
+        int this_is_my_prog(u64 *ctx)
+        {{
+                counter++;
+                return 0;
+        }}
+
+        SEC("uprobe")
+        int this_is_my_uprobe(u64 *ctx)
+        {{
+                return 0;
+        }}
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    let skel = NamedTempFile::new().unwrap();
+    SkeletonBuilder::new()
+        .source(proj_dir.join("src/bpf/prog.bpf.c"))
+        .no_std(true)
+        .build_and_generate(skel.path())
+        .unwrap();
+
+    let mut cargo = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+
+    // Make test project use our development libbpf-rs version
+    writeln!(
+        cargo,
+        r#"
+        libbpf-rs = {{ path = "{}" }}
+        "#,
+        get_libbpf_rs_path().as_path().display()
+    )
+    .expect("failed to write to Cargo.toml");
+
+    let mut source = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/lib.rs"))
+        .expect("failed to open lib.rs");
+
+    write!(
+        source,
+        r#"
+        #![no_std]
+
+        #[path = "{skel_path}"]
+        pub mod skel;
+        "#,
+        skel_path = skel.path().display(),
+    )
+    .expect("failed to write to lib.rs");
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .arg("--lib")
+        .arg("--manifest-path")
+        .arg(cargo_toml.into_os_string())
+        .env("RUSTFLAGS", "-Dwarnings")
+        .status()
+        .expect("failed to spawn cargo-build");
+    assert!(status.success());
+}
+
+#[test]
+fn test_skeleton_builder_multiple_sources() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add the main prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        extern int helper(u64 *ctx);
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_HASH);
+                __uint(max_entries, 1024);
+                __type(key, u32);
+                __type(value, u64);
+        }} mymap SEC(".maps");
+
+        SEC("kprobe/foo")
+        int this_is_my_prog(u64 *ctx)
+        {{
+                return helper(ctx);
+        }}
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    // Add a second compilation unit providing a function and a program
+    let mut lib = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/lib.bpf.c"))
+        .expect("failed to open lib.bpf.c");
+
+    write!(
+        lib,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        __noinline int helper(u64 *ctx)
+        {{
+                return 0;
+        }}
+
+        SEC("kprobe/bar")
+        int this_is_my_other_prog(u64 *ctx)
+        {{
+                return 0;
+        }}
+        "#,
+    )
+    .expect("failed to write lib.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    // Generate skeleton file
+    let skel = NamedTempFile::new().unwrap();
+    SkeletonBuilder::new()
+        .sources([
+            proj_dir.join("src/bpf/prog.bpf.c"),
+            proj_dir.join("src/bpf/lib.bpf.c"),
+        ])
+        .debug(true)
+        .build_and_generate(skel.path())
+        .unwrap();
+
+    let mut cargo = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+
+    // Make test project use our development libbpf-rs version
+    writeln!(
+        cargo,
+        r#"
+        libbpf-rs = {{ path = "{}" }}
+        "#,
+        get_libbpf_rs_path().as_path().display()
+    )
+    .expect("failed to write to Cargo.toml");
+
+    let mut source = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(proj_dir.join("src/main.rs"))
+        .expect("failed to open main.rs");
+
+    write!(
+        source,
+        r#"
+        #[path = "{skel_path}"]
+        mod skel;
+        use skel::*;
+        use libbpf_rs::skel::SkelBuilder;
+        use libbpf_rs::skel::OpenSkel;
+        use libbpf_rs::skel::Skel;
+
+        fn main() {{
+            let builder = ProgSkelBuilder::default();
+            let open_skel = builder
+                .open()
+                .expect("failed to open skel");
+
+            // Check that programs from all compilation units are present
+            let _open_map = open_skel.maps().mymap();
+            let _open_prog = open_skel.progs().this_is_my_prog();
+            let _open_other_prog = open_skel.progs().this_is_my_other_prog();
+
+            let mut skel = open_skel
+                .load()
+                .expect("failed to load skel");
+
+            skel.attach().expect("failed to attach progs");
+            let _mylink = skel.links.this_is_my_prog.unwrap();
+            let _myotherlink = skel.links.this_is_my_other_prog.unwrap();
+        }}
+        "#,
+        skel_path = skel.path().display(),
+    )
+    .expect("failed to write to main.rs");
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(cargo_toml.into_os_string())
+        .env("RUSTFLAGS", "-Dwarnings")
+        .status()
+        .expect("failed to spawn cargo-build");
+    assert!(status.success());
+}
+
+#[test]
+fn test_skeleton_builder_subskel() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a "library" providing a map, a program, and global variables
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/lib.bpf.c"))
+        .expect("failed to open lib.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_HASH);
+                __uint(max_entries, 1024);
+                __type(key, u32);
+                __type(value, u64);
+        }} libmap SEC(".maps");
+
+        int lib_counter = 0;
+        const volatile int lib_config = 1;
+
+        SEC("kprobe/foo")
+        int lib_prog(u64 *ctx)
+        {{
+                lib_counter += lib_config;
+                return 0;
+        }}
+        "#,
+    )
+    .expect("failed to write lib.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    // Generate subskeleton file
+    let skel = NamedTempFile::new().unwrap();
+    let mut builder = SkeletonBuilder::new();
+    builder
+        .source(proj_dir.join("src/bpf/lib.bpf.c"))
+        .debug(true)
+        .build()
+        .unwrap();
+    builder.generate_subskel(skel.path()).unwrap();
+
+    let mut cargo = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+
+    // Make test project use our development libbpf-rs version
+    writeln!(
+        cargo,
+        r#"
+        libbpf-rs = {{ path = "{}" }}
+        "#,
+        get_libbpf_rs_path().as_path().display()
+    )
+    .expect("failed to write to Cargo.toml");
+
+    let mut source = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(proj_dir.join("src/main.rs"))
+        .expect("failed to open main.rs");
+
+    write!(
+        source,
+        r#"
+        #[path = "{skel_path}"]
+        mod skel;
+        use skel::*;
+
+        fn main() {{
+            let mut obj = libbpf_rs::ObjectBuilder::default()
+                .open_file("lib.bpf.o")
+                .expect("failed to open object")
+                .load()
+                .expect("failed to load object");
+            let mut subskel = LibSubskel::open(&mut obj).expect("failed to open subskel");
+
+            // Check that we can grab handles to maps/progs
+            let _map = subskel.maps().libmap().expect("failed to find map");
+            let _prog = subskel.progs().lib_prog().expect("failed to find program");
+            let _map_mut = subskel.maps_mut().libmap().expect("failed to find map");
+            let _prog_mut = subskel.progs_mut().lib_prog().expect("failed to find program");
+
+            // Check that we can access global variables
+            *subskel.lib_counter_mut().expect("failed to access variable") += 1;
+            let _config: i32 = *subskel.lib_config().expect("failed to access variable");
+        }}
+        "#,
+        skel_path = skel.path().display(),
+    )
+    .expect("failed to write to main.rs");
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(cargo_toml.into_os_string())
+        .env("RUSTFLAGS", "-Dwarnings")
+        .status()
+        .expect("failed to spawn cargo-build");
+    assert!(status.success());
+}
+
+#[test]
+fn test_skeleton_builder_light() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        struct {{
+                __uint(type, BPF_MAP_TYPE_HASH);
+                __uint(max_entries, 1024);
+                __type(key, u32);
+                __type(value, u64);
+        }} mymap SEC(".maps");
+
+        int counter = 0;
+
+        SEC("kprobe/foo")
+        int this_is_my_prog(u64 *ctx)
+        {{
+                counter++;
+                return 0;
+        }}
+
+        SEC("?kprobe/bar")
+        int not_autoloaded(u64 *ctx)
+        {{
+                return 0;
+        }}
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    // Lay down the necessary header files
+    add_vmlinux_header(&proj_dir);
+
+    // Generate light skeleton file
+    let skel = NamedTempFile::new().unwrap();
+    let mut builder = SkeletonBuilder::new();
+    builder
+        .source(proj_dir.join("src/bpf/prog.bpf.c"))
+        .debug(true)
+        .build()
+        .unwrap();
+    builder.generate_light(skel.path()).unwrap();
+
+    let mut cargo = OpenOptions::new()
+        .append(true)
+        .open(&cargo_toml)
+        .expect("failed to open Cargo.toml");
+
+    // Make test project use our development libbpf-rs version
+    writeln!(
+        cargo,
+        r#"
+        libbpf-rs = {{ path = "{}" }}
+        "#,
+        get_libbpf_rs_path().as_path().display()
+    )
+    .expect("failed to write to Cargo.toml");
+
+    let mut source = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(proj_dir.join("src/main.rs"))
+        .expect("failed to open main.rs");
+
+    write!(
+        source,
+        r#"
+        #[path = "{skel_path}"]
+        mod skel;
+        use skel::*;
+
+        fn main() {{
+            let skel = ProgLightSkel::load().expect("failed to load light skel");
+
+            // Check that we can grab handles to maps/progs
+            let _map: &libbpf_rs::MapHandle = skel.maps().mymap();
+            let _bss: &libbpf_rs::MapHandle = skel.maps().bss();
+            let _prog: std::os::unix::io::BorrowedFd<'_> = skel.progs().this_is_my_prog();
+            let _obj: &libbpf_rs::LightObject = skel.object();
+        }}
+        "#,
+        skel_path = skel.path().display(),
+    )
+    .expect("failed to write to main.rs");
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(cargo_toml.into_os_string())
+        .env("RUSTFLAGS", "-Dwarnings")
+        .status()
+        .expect("failed to spawn cargo-build");
+    assert!(status.success());
+}
+
+#[test]
+fn test_skeleton_builder_clang_opts() {
+    let (_dir, proj_dir, _cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog
+    let mut prog = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(proj_dir.join("src/bpf/prog.bpf.c"))
+        .expect("failed to open prog.bpf.c");
+
+    write!(
+        prog,
+        r#"
+        #ifndef PURPOSE
+        #error "what is my purpose?"
+        #endif
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    let skel = NamedTempFile::new().unwrap();
+
+    // Should fail b/c `PURPOSE` not defined
+    SkeletonBuilder::new()
+        .source(proj_dir.join("src/bpf/prog.bpf.c"))
+        .debug(true)
+        .clang("clang")
+        .build_and_generate(skel.path())
+        .unwrap_err();
+
+    // Should succeed b/c we defined the macro
+    SkeletonBuilder::new()
+        .source(proj_dir.join("src/bpf/prog.bpf.c"))
+        .debug(true)
+        .clang("clang")
+        .clang_args(["-DPURPOSE=you_pass_the_butter"])
+        .build_and_generate(skel.path())
+        .unwrap();
+}
+
+#[test]
+fn test_skeleton_builder_deps() {
+    let (_dir, proj_dir, _cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+    create_dir(proj_dir.join("include")).expect("failed to create include dir");
+
+    // Add a prog including a header that includes another one
+    write(proj_dir.join("include/common.h"), "#include \"nested.h\"\n")
+        .expect("failed to write common.h");
+    write(proj_dir.join("include/nested.h"), "#define NESTED 1\n")
+        .expect("failed to write nested.h");
+    let source = proj_dir.join("src/bpf/prog.bpf.c");
+    write(&source, "#include \"common.h\"\n").expect("failed to write prog.bpf.c");
+
+    let obj = NamedTempFile::new().unwrap();
+    let include = proj_dir.join("include");
+    let deps = build_single(
+        true,
+        slice::from_ref(&source),
+        obj.path(),
+        None,
+        true,
+        vec!["-I".into(), include.clone().into_os_string()],
+        &HashMap::new(),
+        Endianness::default(),
+        Compiler::default(),
+    )
+    .unwrap();
+
+    assert!(deps.contains(&source), "{deps:?}");
+    assert!(deps.contains(&include.join("common.h")), "{deps:?}");
+    assert!(deps.contains(&include.join("nested.h")), "{deps:?}");
+
+    // Generating an unchanged skeleton should leave the file untouched.
+    let skel = proj_dir.join("src/bpf/prog.skel.rs");
+    let mut builder = SkeletonBuilder::new();
+    builder
+        .source(&source)
+        .clang_args(["-I", include.to_str().unwrap()]);
+    builder.build_and_generate(&skel).unwrap();
+    let modified = skel.metadata().unwrap().modified().unwrap();
+    builder.build_and_generate(&skel).unwrap();
+    assert_eq!(skel.metadata().unwrap().modified().unwrap(), modified);
+}
+
+#[test]
+fn test_skeleton_builder_gcc() {
+    let dir = tempdir().expect("failed to create temporary directory");
+    let source = dir.path().join("prog.bpf.c");
+    write(&source, "").expect("failed to write prog.bpf.c");
+
+    // A fake compiler recording the arguments it got invoked with.
+    let args = dir.path().join("args");
+    let gcc = dir.path().join("bpf-gcc");
+    write(
+        &gcc,
+        format!(
+            "#!/bin/sh\n[ \"$1\" = --version ] && exit 0\necho \"$@\" > {}\nexit 1\n",
+            args.display()
+        ),
+    )
+    .expect("failed to write bpf-gcc");
+    set_permissions(&gcc, Permissions::from_mode(0o755))
+        .expect("failed to make bpf-gcc executable");
 
-    let status = Command::new("cargo")
-        .arg("build")
-        .arg("--quiet")
-        .arg("--manifest-path")
-        .arg(cargo_toml.into_os_string())
-        .env("RUSTFLAGS", "-Dwarnings")
-        .status()
-        .expect("failed to spawn cargo-build");
-    assert!(status.success());
+    let obj = NamedTempFile::new().unwrap();
+    SkeletonBuilder::new()
+        .source(&source)
+        .obj(obj.path())
+        .clang(&gcc)
+        .compiler(Compiler::Gcc)
+        .endianness(Endianness::Big)
+        .skip_clang_version_check(true)
+        .build()
+        .unwrap_err();
+
+    let args = read_to_string(&args).expect("compiler was not invoked");
+    let args = args.split_whitespace().collect::<Vec<_>>();
+    for arg in ["-gbtf", "-mco-re", "-mbig-endian", "-O2", "-c"] {
+        assert!(args.contains(&arg), "{arg} missing in {args:?}");
+    }
+    assert!(!args.contains(&"-target"), "{args:?}");
 }
 
+/// Check that we report outdated GCC versions without referring to clang.
 #[test]
-fn test_skeleton_builder_clang_opts() {
+fn test_skeleton_builder_gcc_too_old() {
+    let dir = tempdir().expect("failed to create temporary directory");
+    let source = dir.path().join("prog.bpf.c");
+    write(&source, "").expect("failed to write prog.bpf.c");
+
+    let gcc = dir.path().join("bpf-gcc");
+    write(&gcc, "#!/bin/sh\necho 'bpf-gcc (GCC) 12.1.0'\n").expect("failed to write bpf-gcc");
+    set_permissions(&gcc, Permissions::from_mode(0o755))
+        .expect("failed to make bpf-gcc executable");
+
+    let obj = NamedTempFile::new().unwrap();
+    let err = SkeletonBuilder::new()
+        .source(&source)
+        .obj(obj.path())
+        .clang(&gcc)
+        .compiler(Compiler::Gcc)
+        .build()
+        .unwrap_err();
+    let err = format!("{err:#}");
+    assert!(err.contains("13.0.0 or newer is required"), "{err}");
+    assert!(!err.contains("clang"), "{err}");
+}
+
+#[test]
+fn test_skeleton_builder_source_opts() {
     let (_dir, proj_dir, _cargo_toml) = setup_temp_project();
 
     // Add prog dir
     create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+    create_dir(proj_dir.join("include")).expect("failed to create include dir");
 
-    // Add a prog
-    let mut prog = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(proj_dir.join("src/bpf/prog.bpf.c"))
-        .expect("failed to open prog.bpf.c");
+    write(
+        proj_dir.join("include/purpose.h"),
+        "#define PURPOSE you_pass_the_butter\n",
+    )
+    .expect("failed to write purpose.h");
 
-    write!(
-        prog,
+    // Add progs
+    write(
+        proj_dir.join("src/bpf/prog.bpf.c"),
         r#"
-        #ifndef PURPOSE
+        #include "purpose.h"
+        #if !defined(PURPOSE) || !defined(ANSWER) || ANSWER != 42
         #error "what is my purpose?"
         #endif
         "#,
     )
     .expect("failed to write prog.bpf.c");
+    write(
+        proj_dir.join("src/bpf/lib.bpf.c"),
+        r#"
+        #ifndef LIB
+        #error "not a library"
+        #endif
+        "#,
+    )
+    .expect("failed to write lib.bpf.c");
 
-    let skel = NamedTempFile::new().unwrap();
+    let obj = NamedTempFile::new().unwrap();
 
-    // Should fail b/c `PURPOSE` not defined
+    // Should fail b/c `lib.bpf.c` lacks its macro
+    SkeletonBuilder::new()
+        .sources([
+            proj_dir.join("src/bpf/prog.bpf.c"),
+            proj_dir.join("src/bpf/lib.bpf.c"),
+        ])
+        .obj(obj.path())
+        .include_dir(proj_dir.join("include"))
+        .define("ANSWER", Some("42"))
+        .build()
+        .unwrap_err();
+
+    // Should fail b/c arguments are provided for an unknown source
     SkeletonBuilder::new()
         .source(proj_dir.join("src/bpf/prog.bpf.c"))
-        .debug(true)
-        .clang("clang")
-        .build_and_generate(skel.path())
+        .obj(obj.path())
+        .include_dir(proj_dir.join("include"))
+        .define("ANSWER", Some("42"))
+        .source_clang_args(proj_dir.join("src/bpf/lib.bpf.c"), ["-DLIB"])
+        .build()
         .unwrap_err();
 
-    // Should succeed b/c we defined the macro
+    SkeletonBuilder::new()
+        .sources([
+            proj_dir.join("src/bpf/prog.bpf.c"),
+            proj_dir.join("src/bpf/lib.bpf.c"),
+        ])
+        .obj(obj.path())
+        .include_dir(proj_dir.join("include"))
+        .define("ANSWER", Some("42"))
+        .source_clang_args(proj_dir.join("src/bpf/lib.bpf.c"), ["-DLIB"])
+        .endianness(Endianness::Big)
+        .build()
+        .unwrap();
+
+    let buffer = read(obj.path()).expect("failed to read object file");
+    match Object::parse(&buffer).expect("failed to parse object file") {
+        Object::Elf(elf) => assert!(!elf.little_endian),
+        _ => panic!("wrong object file format"),
+    }
+}
+
+#[test]
+fn test_gen_vmlinux_h() {
+    let dir = tempdir().expect("failed to create temporary directory");
+    let btf = Path::new("/sys/kernel/btf/vmlinux");
+
+    let header = gen_vmlinux_h(true, Some(btf), dir.path()).unwrap();
+    assert_eq!(header.file_name().unwrap(), "vmlinux.h");
+    let contents = read_to_string(&header).unwrap();
+    assert!(contents.starts_with("#ifndef __VMLINUX_H__"));
+    assert!(contents.contains("struct task_struct {"));
+
+    // The cached header should be used the second time around.
+    let modified = header.metadata().unwrap().modified().unwrap();
+    let cached = gen_vmlinux_h(true, Some(btf), dir.path()).unwrap();
+    assert_eq!(cached, header);
+    assert_eq!(cached.metadata().unwrap().modified().unwrap(), modified);
+
+    // Headers are cached by contents, not by the path of the BTF file.
+    let copy = dir.path().join("copy").join("vmlinux");
+    create_dir(copy.parent().unwrap()).unwrap();
+    let _bytes = copy_file(btf, &copy).unwrap();
+    let cached = gen_vmlinux_h(true, Some(&copy), dir.path()).unwrap();
+    assert_eq!(cached, header);
+}
+
+#[test]
+fn test_skeleton_builder_vmlinux_h() {
+    let (_dir, proj_dir, _cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog, but don't lay down vmlinux.h
+    write(
+        proj_dir.join("src/bpf/prog.bpf.c"),
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        SEC("tp_btf/sched_switch")
+        int handle_sched_switch(u64 *ctx)
+        {
+                struct task_struct *prev = (struct task_struct *)ctx[1];
+                return prev->pid == 0;
+        }
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    let skel = NamedTempFile::new().unwrap();
     SkeletonBuilder::new()
         .source(proj_dir.join("src/bpf/prog.bpf.c"))
         .debug(true)
-        .clang("clang")
-        .clang_args(["-DPURPOSE=you_pass_the_butter"])
+        .vmlinux_btf("/sys/kernel/btf/vmlinux")
         .build_and_generate(skel.path())
         .unwrap();
 }
@@ -1417,6 +3208,170 @@ impl Default for Foo {
     assert_definition(&btf, &struct_foo, expected_output);
 }
 
+/// Check that we can generate definitions for selected types, including
+/// typedefs, via the public code generation API.
+#[test]
+fn test_btf_codegen() {
+    let prog_text = r#"
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+
+struct Foo {
+    int x;
+    void *z;
+};
+
+typedef struct Foo foo_t;
+
+foo_t foo = {0};
+"#;
+
+    let expected_output = r#"
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct Foo {
+    pub x: i32,
+    pub z: *mut std::ffi::c_void,
+}
+impl Default for Foo {
+    fn default() -> Self {
+        Foo {
+            x: i32::default(),
+            z: std::ptr::null_mut(),
+        }
+    }
+}
+pub type foo_t = Foo;
+"#;
+
+    let mmap = build_btf_mmap(prog_text);
+    let btf = Btf::from_raw("prog", &mmap)
+        .expect("Failed to initialize Btf")
+        .expect("Did not find .BTF section");
+    let output = codegen(btf, &["foo_t"]).expect("failed to generate code");
+    assert_output(&output, expected_output);
+
+    let btf = Btf::from_raw("prog", &mmap).unwrap().unwrap();
+    let err = codegen(btf, &["does_not_exist"]).unwrap_err();
+    assert!(err.to_string().contains("not found"), "{err}");
+}
+
+#[test]
+fn test_btf_exported_types() {
+    let prog_text = r#"
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+
+struct event {
+    u64 ts;
+    u32 pid;
+    u32 cpu;
+} __attribute__((btf_decl_tag("libbpf_rs.export")));
+
+struct other {
+    int x;
+};
+
+struct event *unused_event;
+struct other *unused_other;
+"#;
+
+    let expected_output = r#"
+#[derive(Debug, Default, Copy, Clone)]
+#[repr(C)]
+pub struct event {
+    pub ts: u64,
+    pub pid: u32,
+    pub cpu: u32,
+}
+"#;
+
+    let mmap = build_btf_mmap(prog_text);
+    let btf = btf_from_mmap(&mmap);
+    let mut processed = HashSet::new();
+    let def = btf.exported_type_definitions(&mut processed).unwrap();
+
+    assert_output(&def, expected_output);
+}
+
+#[test]
+fn test_btf_enum_definitions() {
+    let prog_text = r#"
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+
+enum event_type {
+    EVENT_EXEC = 1,
+    EVENT_EXIT = 2,
+};
+
+enum dup {
+    DUP_A = 0,
+    DUP_B = 0,
+};
+
+enum big {
+    BIG_NEG = -0x100000000LL,
+    BIG_POS = 1,
+};
+
+enum unused {
+    UNUSED_A = 7,
+};
+
+struct event {
+    enum event_type type;
+    enum {
+        FLAG_NONE = -1,
+        FLAG_SOME = 4,
+    } flag;
+    enum dup dup;
+    enum big big;
+};
+
+struct event *unused_event;
+
+int not_a_variable(enum unused unused)
+{
+    return unused;
+}
+"#;
+
+    let expected_enum = r#"
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[repr(u32)]
+pub enum event_type {
+    #[default]
+    EVENT_EXEC = 1,
+    EVENT_EXIT = 2,
+}
+"#;
+
+    let mmap = build_btf_mmap(prog_text);
+    let btf = btf_from_mmap(&mmap);
+    let mut processed = HashSet::new();
+    let def = btf.enum_definitions(&mut processed).unwrap();
+
+    assert!(def.contains(expected_enum.trim_start()), "{def}");
+    for line in [
+        "pub const EVENT_EXEC: u32 = 1;",
+        "pub const EVENT_EXIT: u32 = 2;",
+        "pub const FLAG_NONE: i32 = -1;",
+        "pub const FLAG_SOME: i32 = 4;",
+        "pub const DUP_A: u32 = 0;",
+        "pub const DUP_B: u32 = 0;",
+        "pub const BIG_NEG: i64 = -4294967296;",
+        "pub const BIG_POS: i64 = 1;",
+    ] {
+        assert!(def.contains(line), "{line} missing in {def}");
+    }
+    // Enums not referenced by variables, maps, or exported types are
+    // skipped.
+    assert!(!def.contains("UNUSED_A"), "{def}");
+    // Enums with duplicate values are not mirrored by a Rust enum.
+    assert!(!def.contains("pub enum dup"), "{def}");
+}
+
 #[test]
 fn test_btf_dump_fwd() {
     let prog_text = r#"
@@ -2596,6 +4551,67 @@ impl Default for __anon_1 {
     assert_definition(&btf, &struct_foo, expected_output);
 }
 
+#[test]
+fn test_btf_dump_no_std() {
+    let prog_text = r#"
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+
+struct Foo {
+    union {
+        char *name;
+        void *tp;
+    };
+    bool flag;
+};
+
+struct Foo foo = {{0}};
+"#;
+
+    let expected_output = r#"
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct Foo {
+    pub __anon_1: __anon_1,
+    pub flag: core::mem::MaybeUninit<bool>,
+    pub __pad_9: [u8; 7],
+}
+impl Default for Foo {
+    fn default() -> Self {
+        Foo {
+            __anon_1: __anon_1::default(),
+            flag: core::mem::MaybeUninit::new(bool::default()),
+            __pad_9: [u8::default(); 7],
+        }
+    }
+}
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub union __anon_1 {
+    pub name: *mut i8,
+    pub tp: *mut core::ffi::c_void,
+}
+impl core::fmt::Debug for __anon_1 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "(???)")
+    }
+}
+impl Default for __anon_1 {
+    fn default() -> Self {
+        __anon_1 {
+            name: core::ptr::null_mut(),
+        }
+    }
+}
+"#;
+
+    let mmap = build_btf_mmap(prog_text);
+    let btf = btf_from_mmap(&mmap).no_std(true);
+    let struct_foo = find_type_in_btf!(btf, types::Struct<'_>, "Foo");
+
+    assert_definition(&btf, &struct_foo, expected_output);
+}
+
 #[test]
 fn test_btf_dump_definition_anon_enum() {
     let prog_text = r#"
@@ -2896,3 +4912,95 @@ double d = 12.15;
         btf.type_declaration(d).expect("Failed to generate d decl")
     );
 }
+
+#[test]
+fn test_check_object() {
+    let (_dir, proj_dir, cargo_toml) = setup_temp_project();
+
+    // Add prog dir
+    create_dir(proj_dir.join("src/bpf")).expect("failed to create prog dir");
+
+    // Add a prog with a program passing and one failing verification
+    write(
+        proj_dir.join("src/bpf/prog.bpf.c"),
+        r#"
+        #include "vmlinux.h"
+        #include <bpf/bpf_helpers.h>
+
+        SEC("syscall")
+        int good(void *ctx)
+        {
+                return 42;
+        }
+
+        SEC("syscall")
+        int bad(void *ctx)
+        {
+                return *(int *)(long)bpf_get_prandom_u32();
+        }
+
+        char LICENSE[] SEC("license") = "GPL";
+        "#,
+    )
+    .expect("failed to write prog.bpf.c");
+
+    add_vmlinux_header(&proj_dir);
+
+    build(true, Some(&cargo_toml), None, Vec::new(), true).unwrap();
+
+    let obj = proj_dir.join("target/bpf/prog.bpf.o");
+    let report = check_object(&obj, &["good".to_string()]).unwrap();
+    assert_eq!(report.programs.len(), 2);
+
+    let good = report
+        .programs
+        .iter()
+        .find(|prog| prog.name == "good")
+        .unwrap();
+    assert!(good.loaded, "{:?}", good.error);
+    assert_eq!(good.section, "syscall");
+    let test_run = good.test_run.as_ref().unwrap();
+    assert_eq!(test_run.return_value, Some(42));
+
+    let bad = report
+        .programs
+        .iter()
+        .find(|prog| prog.name == "bad")
+        .unwrap();
+    assert!(!bad.loaded);
+    assert!(bad.error.is_some());
+    assert!(bad.verifier_log.is_some());
+    assert!(bad.test_run.is_none());
+}
+
+#[test]
+fn test_watcher_changes() {
+    let dir = tempdir().expect("failed to create temporary directory");
+    let nested = dir.path().join("include");
+    create_dir(&nested).expect("failed to create nested dir");
+    let source = dir.path().join("prog.bpf.c");
+    let header = nested.join("prog.h");
+    write(&source, "int x;\n").expect("failed to write prog.bpf.c");
+    write(&header, "").expect("failed to write prog.h");
+
+    let mut watcher = Watcher::new([dir.path().to_path_buf()]).unwrap();
+    assert_eq!(watcher.changes().unwrap(), Vec::<PathBuf>::new());
+
+    // Files without a C extension are not watched.
+    write(dir.path().join("README"), "foo").expect("failed to write README");
+    assert_eq!(watcher.changes().unwrap(), Vec::<PathBuf>::new());
+
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(&header)
+        .expect("failed to open prog.h");
+    writeln!(file, "#define FOO 1").expect("failed to write prog.h");
+    assert_eq!(watcher.changes().unwrap(), vec![header.clone()]);
+
+    let added = nested.join("other.h");
+    write(&added, "").expect("failed to write other.h");
+    assert_eq!(watcher.changes().unwrap(), vec![added.clone()]);
+
+    remove_file(&source).expect("failed to remove prog.bpf.c");
+    assert_eq!(watcher.changes().unwrap(), vec![source]);
+}
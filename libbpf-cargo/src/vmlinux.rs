@@ -0,0 +1,150 @@
+//! Generation of `vmlinux.h` headers from kernel BTF.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hash as _;
+use std::hash::Hasher as _;
+use std::io::stdout;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use anyhow::Result;
+
+use libbpf_rs::btf::dump::CDump;
+use libbpf_rs::Btf;
+
+use tempfile::NamedTempFile;
+
+use crate::metadata;
+
+/// The path the kernel exposes its release under.
+const OSRELEASE: &str = "/proc/sys/kernel/osrelease";
+
+/// Render all types of `btf` as a C header, in the same form as
+/// `bpftool btf dump file <btf> format c` does.
+fn render(btf: &Btf<'_>) -> Result<String> {
+    let mut dump = CDump::new(btf).context("failed to create BTF dumper")?;
+    let () = dump.dump_all().context("failed to dump BTF types")?;
+
+    let header = format!(
+        r#"#ifndef __VMLINUX_H__
+#define __VMLINUX_H__
+
+#ifndef BPF_NO_PRESERVE_ACCESS_INDEX
+#pragma clang attribute push (__attribute__((preserve_access_index)), apply_to = record)
+#endif
+
+{types}
+#ifndef BPF_NO_PRESERVE_ACCESS_INDEX
+#pragma clang attribute pop
+#endif
+
+#endif /* __VMLINUX_H__ */
+"#,
+        types = dump.output()
+    );
+    Ok(header)
+}
+
+/// Write `contents` to `path` such that concurrent readers never observe a
+/// partially written file.
+fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let () = fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create directory `{}`", dir.display()))?;
+
+    let mut file = NamedTempFile::new_in(dir)
+        .with_context(|| format!("failed to create temporary file in `{}`", dir.display()))?;
+    let () = file.write_all(contents.as_bytes())?;
+    let _file = file
+        .persist(path)
+        .with_context(|| format!("failed to write `{}`", path.display()))?;
+    Ok(())
+}
+
+/// Generate a `vmlinux.h` header inside of `cache_dir` and return its
+/// path.
+///
+/// If `btf` is provided, the header is generated from the BTF in this file
+/// and cached by the file's contents, so that it is regenerated only when
+/// they change. Otherwise it is generated from the BTF of the running
+/// kernel and cached per kernel release.
+///
+/// The header is always named `vmlinux.h`, so that its parent directory
+/// can be used as include directory.
+pub fn gen_vmlinux_h(debug: bool, btf: Option<&Path>, cache_dir: &Path) -> Result<PathBuf> {
+    let header = if let Some(btf_path) = btf {
+        let data = fs::read(btf_path)
+            .with_context(|| format!("failed to read `{}`", btf_path.display()))?;
+        let mut hasher = DefaultHasher::new();
+        let () = data.hash(&mut hasher);
+        let key = format!("{:016x}", hasher.finish());
+        let header = cache_dir.join("file").join(key).join("vmlinux.h");
+        if header.exists() {
+            return Ok(header);
+        }
+
+        if debug {
+            println!(
+                "Generating {} from {}",
+                header.display(),
+                btf_path.display()
+            );
+        }
+
+        let btf = Btf::from_path(btf_path)
+            .with_context(|| format!("failed to load BTF from `{}`", btf_path.display()))?;
+        let () = write_atomic(&header, &render(&btf)?)?;
+        header
+    } else {
+        let release = fs::read_to_string(OSRELEASE)
+            .with_context(|| format!("failed to read kernel release from `{OSRELEASE}`"))?;
+        let release = release.trim();
+        let header = cache_dir.join("kernel").join(release).join("vmlinux.h");
+        if header.exists() {
+            return Ok(header);
+        }
+
+        if debug {
+            println!("Generating {} for kernel {release}", header.display());
+        }
+
+        let btf = Btf::from_vmlinux().context("failed to load kernel BTF")?;
+        let () = write_atomic(&header, &render(&btf)?)?;
+        header
+    };
+
+    Ok(header)
+}
+
+/// Generate a `vmlinux.h` header for the project at `manifest_path` and
+/// copy it to `output` or, if not provided, print it to stdout.
+///
+/// Headers are cached in the workspace's target directory.
+pub fn gen_vmlinux(
+    debug: bool,
+    manifest_path: Option<&PathBuf>,
+    btf: Option<&PathBuf>,
+    output: Option<&PathBuf>,
+) -> Result<()> {
+    let (target_dir, _) = metadata::get(debug, manifest_path)?;
+    let cache_dir = target_dir.join("bpf").join("vmlinux");
+    let header = gen_vmlinux_h(debug, btf.map(PathBuf::as_path), &cache_dir)?;
+
+    if let Some(output) = output {
+        let _bytes = fs::copy(&header, output).with_context(|| {
+            format!(
+                "failed to copy `{}` to `{}`",
+                header.display(),
+                output.display()
+            )
+        })?;
+    } else {
+        let contents =
+            fs::read(&header).with_context(|| format!("failed to read `{}`", header.display()))?;
+        let () = stdout().write_all(&contents)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,177 @@
+//! Rebuilding of BPF objects when their sources change.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context as _;
+use anyhow::Result;
+
+use crate::build;
+use crate::gen;
+use crate::metadata;
+
+/// The interval in which watched directories are scanned for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The extensions of files that are watched.
+const EXTENSIONS: [&str; 2] = ["c", "h"];
+
+/// The state of a watched file, used to detect modifications.
+type FileState = (SystemTime, u64);
+
+/// A watcher detecting changes to C source and header files inside a set
+/// of directories.
+///
+/// Changes are detected by periodically comparing the modification time
+/// and size of files, which does not require any operating system specific
+/// notification mechanism.
+#[derive(Debug)]
+pub struct Watcher {
+    dirs: BTreeSet<PathBuf>,
+    files: BTreeMap<PathBuf, FileState>,
+}
+
+impl Watcher {
+    /// Create a watcher for the files inside `dirs` and their
+    /// subdirectories.
+    pub fn new<I>(dirs: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        let dirs = dirs.into_iter().collect();
+        let files = scan(&dirs)?;
+        Ok(Self { dirs, files })
+    }
+
+    /// Retrieve the files that were added, modified, or removed since the
+    /// last call.
+    pub fn changes(&mut self) -> Result<Vec<PathBuf>> {
+        let files = scan(&self.dirs)?;
+        let changed = files
+            .iter()
+            .filter(|(path, state)| self.files.get(*path) != Some(state))
+            .map(|(path, _)| path)
+            .chain(self.files.keys().filter(|path| !files.contains_key(*path)))
+            .cloned()
+            .collect();
+        self.files = files;
+        Ok(changed)
+    }
+
+    /// Block until any watched file changes and return the changed files.
+    pub fn wait(&mut self) -> Result<Vec<PathBuf>> {
+        loop {
+            let changed = self.changes()?;
+            if !changed.is_empty() {
+                break Ok(changed);
+            }
+            let () = sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// Record the state of all watched files inside `dirs`.
+fn scan(dirs: &BTreeSet<PathBuf>) -> Result<BTreeMap<PathBuf, FileState>> {
+    fn scan_dir(dir: &Path, files: &mut BTreeMap<PathBuf, FileState>) -> Result<()> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            // The directory may legitimately be removed and recreated by
+            // editors or build tools; treat it as empty in the meantime.
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read `{}`", dir.display()))
+            }
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            // Files may vanish while we scan, which we will pick up on the
+            // next scan.
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+
+            if metadata.is_dir() {
+                let () = scan_dir(&path, files)?;
+            } else if path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| EXTENSIONS.contains(&ext))
+            {
+                let _state = files.insert(path, (metadata.modified()?, metadata.len()));
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = BTreeMap::new();
+    for dir in dirs {
+        let () = scan_dir(dir, &mut files)?;
+    }
+    Ok(files)
+}
+
+/// Build the objects of the project at `manifest_path` and generate their
+/// skeletons, then do so again whenever a source or header file in any of
+/// the projects' source directories changes.
+///
+/// Build failures are reported but do not stop watching. This function
+/// only returns on error setting up the watch.
+pub fn watch(
+    debug: bool,
+    manifest_path: Option<&PathBuf>,
+    clang: Option<&PathBuf>,
+    clang_args: Vec<OsString>,
+    skip_clang_version_checks: bool,
+) -> Result<()> {
+    let (_target_dir, objs) = metadata::get(debug, manifest_path)?;
+    let dirs = objs
+        .iter()
+        .filter_map(|obj| obj.path.parent())
+        .map(Path::to_path_buf)
+        .collect::<Vec<_>>();
+    let mut watcher = Watcher::new(dirs)?;
+
+    loop {
+        let result = build::build(
+            debug,
+            manifest_path,
+            clang,
+            clang_args.clone(),
+            skip_clang_version_checks,
+        )
+        .and_then(|()| {
+            gen::gen(
+                debug,
+                manifest_path,
+                None,
+                None,
+                false,
+                false,
+                false,
+                false,
+                false,
+            )
+        });
+        match result {
+            Ok(()) => println!("Build succeeded, watching for changes..."),
+            Err(err) => eprintln!("Build failed: {err:?}\nWatching for changes..."),
+        }
+
+        let changed = watcher.wait()?;
+        if debug {
+            for path in &changed {
+                println!("Changed: {}", path.display());
+            }
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Locate, set up, and inspect BPF file system (bpffs) instances.
+//!
+//! Maps, programs, and links pinned to a bpffs instance outlive the process
+//! that created them. The helpers in this module take care of the plumbing
+//! surrounding that: finding (or mounting) the file system, creating pin
+//! directories, and enumerating what is pinned where.
+//! ```no_run
+//! use libbpf_rs::bpffs;
+//!
+//! let root = bpffs::find().unwrap().expect("no BPF file system mounted");
+//! let dir = root.join("myapp");
+//! let () = bpffs::create_pin_dir(&dir, 0o700).unwrap();
+//! for obj in bpffs::pinned_objects(&dir).unwrap() {
+//!     println!("{}: {:?}", obj.path.display(), obj.ty);
+//! }
+//! ```
+
+use std::fs;
+use std::fs::DirBuilder;
+use std::fs::Permissions;
+use std::io;
+use std::mem::size_of;
+use std::mem::MaybeUninit;
+use std::os::unix::fs::DirBuilderExt as _;
+use std::os::unix::fs::PermissionsExt as _;
+use std::os::unix::io::AsRawFd as _;
+use std::os::unix::io::FromRawFd as _;
+use std::os::unix::io::OwnedFd;
+use std::path::Path;
+use std::path::PathBuf;
+use std::ptr;
+
+use crate::util;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::ErrorKind;
+use crate::Result;
+
+/// The conventional mount point of the BPF file system.
+pub const DEFAULT_PATH: &str = "/sys/fs/bpf";
+
+/// The type of a BPF object pinned to a bpffs instance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PinnedObjectType {
+    /// A pinned map.
+    Map,
+    /// A pinned program.
+    Program,
+    /// A pinned link.
+    Link,
+}
+
+/// A BPF object pinned to a bpffs instance, as reported by
+/// [`pinned_objects`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PinnedObject {
+    /// The path the object is pinned at.
+    pub path: PathBuf,
+    /// The type of the object.
+    pub ty: PinnedObjectType,
+}
+
+/// Retrieve the mount points of all bpffs instances visible to the
+/// process, in the order they were mounted.
+pub fn mount_points() -> Result<Vec<PathBuf>> {
+    util::mount_points("bpf")
+}
+
+/// Find a bpffs instance to pin objects to.
+///
+/// The instance at [`DEFAULT_PATH`] is preferred, if mounted. Otherwise
+/// the first one found is reported. `Ok(None)` indicates that no bpffs
+/// instance is mounted at all, in which case one can be created with
+/// [`mount`].
+pub fn find() -> Result<Option<PathBuf>> {
+    let mut mount_points = mount_points()?;
+    if let Some(idx) = mount_points
+        .iter()
+        .position(|path| path == Path::new(DEFAULT_PATH))
+    {
+        return Ok(Some(mount_points.swap_remove(idx)));
+    }
+    Ok(mount_points.into_iter().next())
+}
+
+/// Check whether `path` resides on a bpffs instance.
+pub fn is_bpffs<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let path = path.as_ref();
+    let c_path = util::path_to_cstring(path)?;
+    let mut buf = MaybeUninit::<libc::statfs>::uninit();
+    let rc = unsafe { libc::statfs(c_path.as_ptr(), buf.as_mut_ptr()) };
+    if rc != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("failed to stat file system of `{}`", path.display()));
+    }
+    // SAFETY: `statfs` succeeded and initialized the buffer.
+    let buf = unsafe { buf.assume_init() };
+    Ok(buf.f_type as u32 == libc::BPF_FS_MAGIC as u32)
+}
+
+/// Ensure that `path` resides on a bpffs instance.
+///
+/// An error of kind [`ErrorKind::InvalidInput`]
+/// is reported if it does not.
+pub fn validate<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    if !is_bpffs(path)? {
+        return Err(Error::with_invalid_input(format!(
+            "`{}` is not located on a BPF file system",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Mount a new bpffs instance at `path`, which has to be an existing
+/// directory.
+///
+/// Mounting requires `CAP_SYS_ADMIN`.
+pub fn mount<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    let c_path = util::path_to_cstring(path)?;
+    let fstype = b"bpf\0".as_ptr().cast();
+    let rc = unsafe { libc::mount(fstype, c_path.as_ptr(), fstype, 0, ptr::null()) };
+    if rc != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("failed to mount BPF file system at `{}`", path.display()));
+    }
+    Ok(())
+}
+
+/// Create the directory `path` on a bpffs instance for pinning objects
+/// into, along with all missing parents.
+///
+/// All directories created get their permissions set to `mode`
+/// (e.g., `0o700`), irrespective of the process' umask. Directories that
+/// already exist are left untouched. An error of kind
+/// [`ErrorKind::InvalidInput`] is reported
+/// if `path` would not reside on a bpffs instance.
+pub fn create_pin_dir<P: AsRef<Path>>(path: P, mode: u32) -> Result<()> {
+    let path = path.as_ref();
+    let missing = path
+        .ancestors()
+        .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+        .collect::<Vec<_>>();
+    let existing = path
+        .ancestors()
+        .nth(missing.len())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let () = validate(existing)?;
+
+    for dir in missing.into_iter().rev() {
+        let () = DirBuilder::new()
+            .mode(mode)
+            .create(dir)
+            .with_context(|| format!("failed to create directory `{}`", dir.display()))?;
+        let () = fs::set_permissions(dir, Permissions::from_mode(mode)).with_context(|| {
+            format!("failed to set permissions of directory `{}`", dir.display())
+        })?;
+    }
+    Ok(())
+}
+
+/// Determine the type of the BPF object pinned at `path`.
+fn pinned_object_type(path: &Path) -> Result<PinnedObjectType> {
+    let c_path = util::path_to_cstring(path)?;
+    let obj_get = |file_flags| {
+        let opts = libbpf_sys::bpf_obj_get_opts {
+            sz: size_of::<libbpf_sys::bpf_obj_get_opts>() as _,
+            file_flags,
+            ..Default::default()
+        };
+        util::parse_ret_i32(unsafe { libbpf_sys::bpf_obj_get_opts(c_path.as_ptr(), &opts) })
+    };
+    // Maps can be opened read-only, which only requires read permission on
+    // the pin. Programs and links, however, can only be opened read-write
+    // and the kernel refuses any other flags with `EINVAL`.
+    let fd = match obj_get(libbpf_sys::BPF_F_RDONLY) {
+        Err(err) if err.raw_os_error() == Some(libc::EINVAL) => obj_get(0),
+        result => result,
+    }
+    .with_context(|| format!("failed to open pinned object `{}`", path.display()))?;
+    // SAFETY: `bpf_obj_get_opts` returned a valid file descriptor that
+    //         nobody else owns.
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // The kernel backs each kind of BPF object by an anonymous inode with a
+    // distinct name. Links got renamed from `bpf_link` to `bpf-link` at
+    // some point.
+    let target = fs::read_link(format!("/proc/self/fd/{}", fd.as_raw_fd()))?;
+    let name = target.to_string_lossy();
+    let name = name.strip_prefix("anon_inode:").unwrap_or(&name);
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    match name {
+        "bpf-map" => Ok(PinnedObjectType::Map),
+        "bpf-prog" => Ok(PinnedObjectType::Program),
+        "bpf-link" | "bpf_link" => Ok(PinnedObjectType::Link),
+        _ => Err(Error::with_invalid_data(format!(
+            "object pinned at `{}` is of unknown type `{name}`",
+            path.display()
+        ))),
+    }
+}
+
+/// Recursively collect the objects pinned in directory `dir`.
+fn collect_pinned_objects(dir: &Path, objects: &mut Vec<PinnedObject>) -> Result<()> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("failed to read directory `{}`", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let () = collect_pinned_objects(&path, objects)?;
+        } else if file_type.is_file() {
+            match pinned_object_type(&path) {
+                Ok(ty) => objects.push(PinnedObject { path, ty }),
+                // The entry got removed in the meantime.
+                Err(err) if err.kind() == ErrorKind::NotFound => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Enumerate the BPF objects pinned under the directory `path` and its
+/// sub-directories, which have to reside on a bpffs instance.
+///
+/// Symbolic links are not followed. Objects are reported in no particular
+/// order. Note that the `maps.debug` and `progs.debug` iterators some
+/// kernels preload into each bpffs instance are pinned links and reported
+/// as such.
+pub fn pinned_objects<P: AsRef<Path>>(path: P) -> Result<Vec<PinnedObject>> {
+    let path = path.as_ref();
+    let () = validate(path)?;
+
+    let mut objects = Vec::new();
+    let () = collect_pinned_objects(path, &mut objects)?;
+    Ok(objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that we correctly tell apart bpffs and other file systems.
+    #[test]
+    fn bpffs_validation() {
+        assert!(!is_bpffs("/proc").unwrap());
+
+        let err = validate("/proc").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        let err = is_bpffs("/does-not-exist").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        let err = create_pin_dir("/proc/does-not-exist/foo", 0o700).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(!Path::new("/proc/does-not-exist").exists());
+    }
+}
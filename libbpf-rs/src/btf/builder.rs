@@ -0,0 +1,353 @@
+//! Programmatic construction of BTF.
+
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::ptr;
+
+use crate::util::create_bpf_entity_checked;
+use crate::util::parse_ret;
+use crate::util::parse_ret_i32;
+use crate::util::str_to_cstring;
+use crate::Error;
+use crate::Result;
+
+use super::types::FwdKind;
+use super::types::IntEncoding;
+use super::types::Linkage;
+use super::Btf;
+use super::DropPolicy;
+use super::TypeId;
+
+/// Convert a type id into the representation libbpf expects.
+fn raw_id(id: TypeId) -> c_int {
+    u32::from(id) as c_int
+}
+
+/// Convert the return value of one of libbpf's `btf__add_*` functions into
+/// the id of the added type.
+fn parse_type_id(ret: c_int) -> Result<TypeId> {
+    parse_ret_i32(ret).map(|id| TypeId::from(id as u32))
+}
+
+impl Btf<'static> {
+    /// Create a new, empty BTF object, to which types can be added.
+    ///
+    /// ```no_run
+    /// use libbpf_rs::btf::types::IntEncoding;
+    /// use libbpf_rs::Btf;
+    ///
+    /// let mut btf = Btf::new_empty().unwrap();
+    /// let int = btf.add_int("int", 4, IntEncoding::Signed).unwrap();
+    /// let _key = btf.add_struct("key", 8).unwrap();
+    /// let () = btf.add_field("pid", int, 0, None).unwrap();
+    /// let () = btf.add_field("tid", int, 32, None).unwrap();
+    /// ```
+    pub fn new_empty() -> Result<Self> {
+        let ptr = create_bpf_entity_checked(|| unsafe { libbpf_sys::btf__new_empty() })?;
+
+        Ok(Self {
+            ptr,
+            drop_policy: DropPolicy::SelfPtrOnly,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<'btf> Btf<'btf> {
+    /// Create a new, empty split BTF object on top of `base`, to which
+    /// types can be added.
+    ///
+    /// Added types may reference types of `base`.
+    pub fn new_empty_split(base: &'btf Btf<'_>) -> Result<Self> {
+        let ptr = create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::btf__new_empty_split(base.ptr.as_ptr())
+        })?;
+
+        Ok(Self {
+            ptr,
+            drop_policy: DropPolicy::SelfPtrOnly,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Check that we own the BTF and hence may modify it.
+    fn check_owned(&self) -> Result<()> {
+        match self.drop_policy {
+            DropPolicy::SelfPtrOnly => Ok(()),
+            DropPolicy::Nothing | DropPolicy::ObjPtr(_) => Err(Error::with_invalid_input(
+                "BTF belonging to a BPF object cannot be modified",
+            )),
+        }
+    }
+
+    /// Add a type using `f`, which is provided the raw BTF pointer.
+    fn add(&mut self, f: impl FnOnce(*mut libbpf_sys::btf) -> c_int) -> Result<TypeId> {
+        let () = self.check_owned()?;
+        parse_type_id(f(self.ptr.as_ptr()))
+    }
+
+    /// Add an integer type of `size` bytes.
+    pub fn add_int(&mut self, name: &str, size: usize, encoding: IntEncoding) -> Result<TypeId> {
+        let name = str_to_cstring(name)?;
+        let encoding = match encoding {
+            IntEncoding::None => 0,
+            IntEncoding::Signed => libbpf_sys::BTF_INT_SIGNED,
+            IntEncoding::Char => libbpf_sys::BTF_INT_CHAR,
+            IntEncoding::Bool => libbpf_sys::BTF_INT_BOOL,
+        };
+        self.add(|btf| unsafe {
+            libbpf_sys::btf__add_int(btf, name.as_ptr(), size as _, encoding as _)
+        })
+    }
+
+    /// Add a floating point type of `size` bytes.
+    pub fn add_float(&mut self, name: &str, size: usize) -> Result<TypeId> {
+        let name = str_to_cstring(name)?;
+        self.add(|btf| unsafe { libbpf_sys::btf__add_float(btf, name.as_ptr(), size as _) })
+    }
+
+    /// Add a pointer to the type `ty`.
+    pub fn add_ptr(&mut self, ty: TypeId) -> Result<TypeId> {
+        self.add(|btf| unsafe { libbpf_sys::btf__add_ptr(btf, raw_id(ty)) })
+    }
+
+    /// Add an array of `len` elements of type `elem_ty`, indexed by
+    /// `index_ty`.
+    pub fn add_array(&mut self, index_ty: TypeId, elem_ty: TypeId, len: u32) -> Result<TypeId> {
+        self.add(|btf| unsafe {
+            libbpf_sys::btf__add_array(btf, raw_id(index_ty), raw_id(elem_ty), len)
+        })
+    }
+
+    /// Add a struct of `size` bytes. An empty `name` denotes an anonymous
+    /// struct.
+    ///
+    /// Members are added via subsequent calls to [`Btf::add_field`].
+    pub fn add_struct(&mut self, name: &str, size: u32) -> Result<TypeId> {
+        let name = str_to_cstring(name)?;
+        self.add(|btf| unsafe { libbpf_sys::btf__add_struct(btf, name.as_ptr(), size) })
+    }
+
+    /// Add a union of `size` bytes. An empty `name` denotes an anonymous
+    /// union.
+    ///
+    /// Members are added via subsequent calls to [`Btf::add_field`].
+    pub fn add_union(&mut self, name: &str, size: u32) -> Result<TypeId> {
+        let name = str_to_cstring(name)?;
+        self.add(|btf| unsafe { libbpf_sys::btf__add_union(btf, name.as_ptr(), size) })
+    }
+
+    /// Add a member of type `ty` at `bit_offset` to the struct or union
+    /// added last. `bitfield_size` has to be provided for bitfields.
+    pub fn add_field(
+        &mut self,
+        name: &str,
+        ty: TypeId,
+        bit_offset: u32,
+        bitfield_size: Option<u32>,
+    ) -> Result<()> {
+        let () = self.check_owned()?;
+        let name = str_to_cstring(name)?;
+        let ret = unsafe {
+            libbpf_sys::btf__add_field(
+                self.ptr.as_ptr(),
+                name.as_ptr(),
+                raw_id(ty),
+                bit_offset,
+                bitfield_size.unwrap_or(0),
+            )
+        };
+        parse_ret(ret)
+    }
+
+    /// Add an enum of `size` bytes. An empty `name` denotes an anonymous
+    /// enum.
+    ///
+    /// Values are added via subsequent calls to [`Btf::add_enum_value`].
+    pub fn add_enum(&mut self, name: &str, size: u32) -> Result<TypeId> {
+        let name = str_to_cstring(name)?;
+        self.add(|btf| unsafe { libbpf_sys::btf__add_enum(btf, name.as_ptr(), size) })
+    }
+
+    /// Add a value to the enum added last.
+    pub fn add_enum_value(&mut self, name: &str, value: i64) -> Result<()> {
+        let () = self.check_owned()?;
+        let name = str_to_cstring(name)?;
+        let ret =
+            unsafe { libbpf_sys::btf__add_enum_value(self.ptr.as_ptr(), name.as_ptr(), value) };
+        parse_ret(ret)
+    }
+
+    /// Add a forward declaration of a struct or union.
+    pub fn add_fwd(&mut self, name: &str, kind: FwdKind) -> Result<TypeId> {
+        let name = str_to_cstring(name)?;
+        let kind = match kind {
+            FwdKind::Struct => libbpf_sys::BTF_FWD_STRUCT,
+            FwdKind::Union => libbpf_sys::BTF_FWD_UNION,
+        };
+        self.add(|btf| unsafe { libbpf_sys::btf__add_fwd(btf, name.as_ptr(), kind) })
+    }
+
+    /// Add a typedef `name` for the type `ty`.
+    pub fn add_typedef(&mut self, name: &str, ty: TypeId) -> Result<TypeId> {
+        let name = str_to_cstring(name)?;
+        self.add(|btf| unsafe { libbpf_sys::btf__add_typedef(btf, name.as_ptr(), raw_id(ty)) })
+    }
+
+    /// Add a `volatile` modifier for the type `ty`.
+    pub fn add_volatile(&mut self, ty: TypeId) -> Result<TypeId> {
+        self.add(|btf| unsafe { libbpf_sys::btf__add_volatile(btf, raw_id(ty)) })
+    }
+
+    /// Add a `const` modifier for the type `ty`.
+    pub fn add_const(&mut self, ty: TypeId) -> Result<TypeId> {
+        self.add(|btf| unsafe { libbpf_sys::btf__add_const(btf, raw_id(ty)) })
+    }
+
+    /// Add a `restrict` modifier for the type `ty`.
+    pub fn add_restrict(&mut self, ty: TypeId) -> Result<TypeId> {
+        self.add(|btf| unsafe { libbpf_sys::btf__add_restrict(btf, raw_id(ty)) })
+    }
+
+    /// Add a function prototype returning the type `ret_ty`.
+    ///
+    /// Parameters are added via subsequent calls to
+    /// [`Btf::add_func_param`].
+    pub fn add_func_proto(&mut self, ret_ty: TypeId) -> Result<TypeId> {
+        self.add(|btf| unsafe { libbpf_sys::btf__add_func_proto(btf, raw_id(ret_ty)) })
+    }
+
+    /// Add a parameter of type `ty` to the function prototype added last.
+    pub fn add_func_param(&mut self, name: &str, ty: TypeId) -> Result<()> {
+        let () = self.check_owned()?;
+        let name = str_to_cstring(name)?;
+        let ret = unsafe {
+            libbpf_sys::btf__add_func_param(self.ptr.as_ptr(), name.as_ptr(), raw_id(ty))
+        };
+        parse_ret(ret)
+    }
+
+    /// Add a function with prototype `proto`.
+    pub fn add_func(&mut self, name: &str, linkage: Linkage, proto: TypeId) -> Result<TypeId> {
+        let name = str_to_cstring(name)?;
+        self.add(|btf| unsafe {
+            libbpf_sys::btf__add_func(btf, name.as_ptr(), u32::from(linkage), raw_id(proto))
+        })
+    }
+
+    /// Add a variable of type `ty`.
+    pub fn add_var(&mut self, name: &str, linkage: Linkage, ty: TypeId) -> Result<TypeId> {
+        let name = str_to_cstring(name)?;
+        self.add(|btf| unsafe {
+            libbpf_sys::btf__add_var(btf, name.as_ptr(), u32::from(linkage) as _, raw_id(ty))
+        })
+    }
+
+    /// Add a data section of `size` bytes, such as `.data`.
+    ///
+    /// Variables are added via subsequent calls to
+    /// [`Btf::add_datasec_var_info`].
+    pub fn add_datasec(&mut self, name: &str, size: u32) -> Result<TypeId> {
+        let name = str_to_cstring(name)?;
+        self.add(|btf| unsafe { libbpf_sys::btf__add_datasec(btf, name.as_ptr(), size) })
+    }
+
+    /// Add the variable `var`, of `size` bytes at `offset`, to the data
+    /// section added last.
+    pub fn add_datasec_var_info(&mut self, var: TypeId, offset: u32, size: u32) -> Result<()> {
+        let () = self.check_owned()?;
+        let ret = unsafe {
+            libbpf_sys::btf__add_datasec_var_info(self.ptr.as_ptr(), raw_id(var), offset, size)
+        };
+        parse_ret(ret)
+    }
+
+    /// Deduplicate the types of the BTF, merging identical ones.
+    ///
+    /// Type ids may change as a result.
+    pub fn dedup(&mut self) -> Result<()> {
+        let () = self.check_owned()?;
+        let opts = libbpf_sys::btf_dedup_opts {
+            sz: size_of::<libbpf_sys::btf_dedup_opts>() as libbpf_sys::size_t,
+            btf_ext: ptr::null_mut(),
+            ..Default::default()
+        };
+        let ret = unsafe { libbpf_sys::btf__dedup(self.ptr.as_ptr(), &opts) };
+        parse_ret(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use tempfile::NamedTempFile;
+
+    use crate::btf::types::Struct;
+    use crate::btf::BtfKind;
+    use crate::btf::BtfType;
+    use crate::ErrorKind;
+
+    /// Check that we can build BTF from scratch and look up the added
+    /// types.
+    #[test]
+    fn build() {
+        let mut btf = Btf::new_empty().unwrap();
+        let int = btf.add_int("int", 4, IntEncoding::Signed).unwrap();
+        let ptr = btf.add_ptr(int).unwrap();
+        let key = btf.add_struct("key", 16).unwrap();
+        let () = btf.add_field("pid", int, 0, None).unwrap();
+        let () = btf.add_field("flags", int, 32, Some(3)).unwrap();
+        let () = btf.add_field("ptr", ptr, 64, None).unwrap();
+        // A duplicate of `int`.
+        let _int2 = btf.add_int("int", 4, IntEncoding::Signed).unwrap();
+        assert_eq!(btf.len(), 5);
+
+        let ty = btf.type_by_name::<Struct<'_>>("key").unwrap();
+        assert_eq!(ty.type_id(), key);
+        assert_eq!(ty.len(), 3);
+        assert_eq!(ty.get(1).unwrap().attr.bitfield_size(), Some(3));
+        assert_eq!(ty.get(2).unwrap().attr.bit_offset(), 64);
+        assert_eq!(
+            btf.type_by_id::<BtfType<'_>>(ptr).unwrap().kind(),
+            BtfKind::Ptr
+        );
+
+        let () = btf.dedup().unwrap();
+        assert_eq!(btf.len(), 4);
+    }
+
+    /// Check that BTF we built survives a round trip through a file.
+    #[test]
+    fn write_to() {
+        let mut btf = Btf::new_empty().unwrap();
+        let int = btf.add_int("int", 4, IntEncoding::Signed).unwrap();
+        let _typedef = btf.add_typedef("pid_t", int).unwrap();
+
+        let file = NamedTempFile::new().unwrap();
+        let () = btf.write_to(file.path()).unwrap();
+        assert_eq!(fs::read(file.path()).unwrap(), btf.to_raw().unwrap());
+
+        let parsed = Btf::from_path(file.path()).unwrap();
+        assert_eq!(parsed.len(), btf.len());
+        let pid_t = parsed.type_by_name::<BtfType<'_>>("pid_t").unwrap();
+        assert_eq!(pid_t.kind(), BtfKind::Typedef);
+        assert_eq!(parsed.type_size(pid_t.type_id()).unwrap(), 4);
+    }
+
+    /// Check that we refuse to modify BTF we don't own.
+    #[test]
+    fn modify_unowned() {
+        let btf = Btf::new_empty().unwrap();
+        let mut unowned = Btf {
+            ptr: btf.ptr,
+            drop_policy: DropPolicy::Nothing,
+            _marker: PhantomData,
+        };
+        let err = unowned.add_int("int", 4, IntEncoding::None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,142 @@
+//! Rendering of BTF types as C definitions.
+
+use std::ffi::c_char;
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use std::mem;
+use std::mem::size_of;
+use std::ptr::NonNull;
+
+use crate::util::create_bpf_entity_checked;
+use crate::util::parse_ret;
+use crate::AsRawLibbpf as _;
+use crate::Btf;
+use crate::Result;
+
+use super::TypeId;
+
+extern "C" fn printf_cb(ctx: *mut c_void, fmt: *const c_char, va_list: *mut c_void) {
+    // SAFETY: `ctx` is the `String` owned by the `CDump` object, which
+    //         outlives the libbpf `btf_dump` object calling us.
+    let out = unsafe { &mut *ctx.cast::<String>() };
+    // SAFETY: libbpf provides a valid format string and matching
+    //         arguments.
+    match unsafe { vsprintf::vsprintf_raw(fmt, va_list) } {
+        Ok(s) => out.push_str(&String::from_utf8_lossy(&s)),
+        Err(e) => out.push_str(&format!("/* failed to format BTF dump output: {e} */")),
+    }
+}
+
+/// A renderer of BTF types as compilable C definitions, as used for
+/// generating `vmlinux.h` headers.
+///
+/// Each type is emitted along with all the types it depends on, in an
+/// order suitable for a C compiler. Types are emitted only once, even if
+/// requested (or depended on) multiple times.
+///
+/// ```no_run
+/// use libbpf_rs::btf::dump::CDump;
+/// use libbpf_rs::Btf;
+///
+/// let btf = Btf::from_vmlinux().unwrap();
+/// let task = btf.type_by_name::<libbpf_rs::btf::BtfType>("task_struct").unwrap();
+/// let mut dump = CDump::new(&btf).unwrap();
+/// let () = dump.dump_type(task.type_id()).unwrap();
+/// println!("{}", dump.output());
+/// ```
+#[derive(Debug)]
+pub struct CDump<'btf> {
+    ptr: NonNull<libbpf_sys::btf_dump>,
+    /// The output produced so far. Boxed, because libbpf holds on to its
+    /// address.
+    #[allow(clippy::box_collection)]
+    out: Box<String>,
+    /// The number of types of the BTF, including `void`.
+    type_cnt: u32,
+    _btf: PhantomData<&'btf ()>,
+}
+
+impl<'btf> CDump<'btf> {
+    /// Create a new [`CDump`] object for rendering types of `btf`.
+    pub fn new(btf: &'btf Btf<'_>) -> Result<Self> {
+        let mut out = Box::<String>::default();
+        let opts = libbpf_sys::btf_dump_opts {
+            sz: size_of::<libbpf_sys::btf_dump_opts>() as libbpf_sys::size_t,
+        };
+        // SAFETY: `printf_cb` has the same signature as
+        //         `btf_dump_printf_fn_t`, modulo the `va_list` type, which
+        //         is always passed as a pointer.
+        let printf_fn = unsafe {
+            mem::transmute::<*const (), libbpf_sys::btf_dump_printf_fn_t>(printf_cb as *const ())
+        };
+        let ctx = (&mut *out as *mut String).cast::<c_void>();
+        let ptr = create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::btf_dump__new(btf.as_libbpf_object().as_ptr(), printf_fn, ctx, &opts)
+        })?;
+
+        Ok(Self {
+            ptr,
+            out,
+            type_cnt: btf.len() as u32,
+            _btf: PhantomData,
+        })
+    }
+
+    /// Emit the definition of the type with id `id`, preceded by all the
+    /// types it depends on that have not been emitted yet.
+    pub fn dump_type(&mut self, id: TypeId) -> Result<()> {
+        let ret = unsafe { libbpf_sys::btf_dump__dump_type(self.ptr.as_ptr(), id.into()) };
+        parse_ret(ret)
+    }
+
+    /// Emit the definitions of all types of the BTF.
+    pub fn dump_all(&mut self) -> Result<()> {
+        for id in 1..self.type_cnt {
+            let () = self.dump_type(TypeId::from(id))?;
+        }
+        Ok(())
+    }
+
+    /// Retrieve the output produced so far.
+    pub fn output(&self) -> &str {
+        &self.out
+    }
+
+    /// Take the output produced so far, leaving the output empty.
+    ///
+    /// Types already emitted will not be emitted again.
+    pub fn take_output(&mut self) -> String {
+        // Take the `String` itself, keeping the `Box` libbpf refers to.
+        mem::take(&mut *self.out)
+    }
+}
+
+impl Drop for CDump<'_> {
+    fn drop(&mut self) {
+        unsafe { libbpf_sys::btf_dump__free(self.ptr.as_ptr()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::btf::types::Struct;
+
+    /// Check that we can render a kernel type as C.
+    #[test]
+    fn dump_type() {
+        let btf = Btf::from_vmlinux().unwrap();
+        let list_head = btf.type_by_name::<Struct<'_>>("list_head").unwrap();
+
+        let mut dump = CDump::new(&btf).unwrap();
+        let () = dump.dump_type(list_head.type_id()).unwrap();
+        let output = dump.take_output();
+        assert!(output.contains("struct list_head {"), "{output}");
+        assert!(output.contains("struct list_head *next;"), "{output}");
+
+        // The type has been emitted already.
+        let () = dump.dump_type(list_head.type_id()).unwrap();
+        assert_eq!(dump.output(), "");
+    }
+}
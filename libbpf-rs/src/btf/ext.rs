@@ -0,0 +1,654 @@
+//! Parsing of the `.BTF.ext` section of BPF object files.
+//!
+//! The `.BTF.ext` section complements the `.BTF` section with information
+//! about the programs of an object: which BTF function each subprogram
+//! corresponds to, which source line each instruction originates from, and
+//! which instructions need to be adjusted by means of CO-RE relocations.
+//! The information is available grouped by program, which can be matched
+//! against [`Program::name`][crate::Program::name], as well as by the ELF
+//! section it refers to, which can be matched against
+//! [`Program::section`][crate::Program::section].
+
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::os::unix::prelude::OsStrExt as _;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use num_enum::IntoPrimitive;
+use num_enum::TryFromPrimitive;
+
+use crate::externs::Elf;
+use crate::util::create_bpf_entity_checked;
+use crate::Error;
+use crate::Result;
+
+use super::Btf;
+use super::BtfType;
+use super::DropPolicy;
+use super::TypeId;
+
+/// The magic number of BTF and `.BTF.ext` data, in native endianness.
+const BTF_MAGIC: u16 = 0xeb9f;
+/// The size of a single BPF instruction.
+const INSN_SIZE: u32 = size_of::<libbpf_sys::bpf_insn>() as u32;
+
+/// An entry of the function information, associating a (sub)program with
+/// its BTF function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FuncInfo {
+    /// The index of the first instruction of the function within the
+    /// section.
+    pub insn_idx: u32,
+    /// The id of the BTF function (of kind [`BtfKind::Func`][super::BtfKind::Func]).
+    pub type_id: TypeId,
+}
+
+/// An entry of the line information, associating an instruction with the
+/// source code it was generated from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineInfo {
+    /// The index of the instruction within the section.
+    pub insn_idx: u32,
+    /// The name of the source file.
+    pub file_name: String,
+    /// The source line itself, if available.
+    pub line: String,
+    /// The line number.
+    pub line_num: u32,
+    /// The column number.
+    pub column: u32,
+}
+
+/// The kind of a CO-RE relocation. Maps to `enum bpf_core_relo_kind`.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+pub enum CoreReloKind {
+    /// The byte offset of a field.
+    FieldByteOffset = libbpf_sys::BPF_CORE_FIELD_BYTE_OFFSET,
+    /// The byte size of a field.
+    FieldByteSize = libbpf_sys::BPF_CORE_FIELD_BYTE_SIZE,
+    /// Whether a field exists.
+    FieldExists = libbpf_sys::BPF_CORE_FIELD_EXISTS,
+    /// Whether a field is signed.
+    FieldSigned = libbpf_sys::BPF_CORE_FIELD_SIGNED,
+    /// The left shift needed to extract a bitfield.
+    FieldLshiftU64 = libbpf_sys::BPF_CORE_FIELD_LSHIFT_U64,
+    /// The right shift needed to extract a bitfield.
+    FieldRshiftU64 = libbpf_sys::BPF_CORE_FIELD_RSHIFT_U64,
+    /// The id of a type in the object's BTF.
+    TypeIdLocal = libbpf_sys::BPF_CORE_TYPE_ID_LOCAL,
+    /// The id of a type in the target (kernel) BTF.
+    TypeIdTarget = libbpf_sys::BPF_CORE_TYPE_ID_TARGET,
+    /// Whether a type exists.
+    TypeExists = libbpf_sys::BPF_CORE_TYPE_EXISTS,
+    /// The size of a type.
+    TypeSize = libbpf_sys::BPF_CORE_TYPE_SIZE,
+    /// Whether an enum value exists.
+    EnumvalExists = libbpf_sys::BPF_CORE_ENUMVAL_EXISTS,
+    /// The integer value of an enum value.
+    EnumvalValue = libbpf_sys::BPF_CORE_ENUMVAL_VALUE,
+    /// Whether a type matches.
+    TypeMatches = libbpf_sys::BPF_CORE_TYPE_MATCHES,
+    /// A relocation kind not known to this library.
+    Unknown = u32::MAX,
+}
+
+/// A CO-RE relocation record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoreRelo {
+    /// The index of the instruction to relocate within the section.
+    pub insn_idx: u32,
+    /// The id of the root type of the relocation, in the object's BTF.
+    pub type_id: TypeId,
+    /// The access specification, e.g., `0:1:2` for accessing the third
+    /// member of the second member of the root type.
+    pub access_spec: String,
+    /// The kind of the relocation.
+    pub kind: CoreReloKind,
+}
+
+/// The `.BTF.ext` information concerning a single ELF section.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BtfExtSection {
+    /// The name of the section.
+    pub name: String,
+    /// The function information, ordered by instruction.
+    pub func_info: Vec<FuncInfo>,
+    /// The line information, ordered by instruction.
+    pub line_info: Vec<LineInfo>,
+    /// The CO-RE relocations.
+    pub core_relos: Vec<CoreRelo>,
+}
+
+/// The `.BTF.ext` information concerning a single program.
+///
+/// Instruction indexes are relative to the start of the program, just as
+/// the ones reported by the verifier.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BtfExtProgram {
+    /// The name of the program, i.e., of its BTF function.
+    pub name: String,
+    /// The name of the section containing the program.
+    pub section: String,
+    /// The index of the program's first instruction within the section.
+    pub insn_off: u32,
+    /// The function information, ordered by instruction.
+    pub func_info: Vec<FuncInfo>,
+    /// The line information, ordered by instruction.
+    pub line_info: Vec<LineInfo>,
+    /// The CO-RE relocations.
+    pub core_relos: Vec<CoreRelo>,
+}
+
+impl BtfExtProgram {
+    /// Split the information of `section` into that of the functions
+    /// it contains, as delimited by its function information.
+    fn split(btf: &Btf<'_>, section: &BtfExtSection) -> Vec<Self> {
+        let mut funcs = section.func_info.clone();
+        let () = funcs.sort_by_key(|func| func.insn_idx);
+
+        let mut progs = Vec::with_capacity(funcs.len());
+        for (idx, func) in funcs.iter().enumerate() {
+            let start = func.insn_idx;
+            let end = funcs.get(idx + 1).map_or(u32::MAX, |next| next.insn_idx);
+            let contains = |insn_idx: u32| (start..end).contains(&insn_idx);
+
+            let name = btf
+                .type_by_id::<BtfType<'_>>(func.type_id)
+                .and_then(|ty| ty.name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let prog = Self {
+                name,
+                section: section.name.clone(),
+                insn_off: start,
+                func_info: vec![FuncInfo {
+                    insn_idx: 0,
+                    type_id: func.type_id,
+                }],
+                line_info: section
+                    .line_info
+                    .iter()
+                    .filter(|line| contains(line.insn_idx))
+                    .map(|line| LineInfo {
+                        insn_idx: line.insn_idx - start,
+                        ..line.clone()
+                    })
+                    .collect(),
+                core_relos: section
+                    .core_relos
+                    .iter()
+                    .filter(|relo| contains(relo.insn_idx))
+                    .map(|relo| CoreRelo {
+                        insn_idx: relo.insn_idx - start,
+                        ..relo.clone()
+                    })
+                    .collect(),
+            };
+            let () = progs.push(prog);
+        }
+        progs
+    }
+}
+
+/// The parsed contents of a `.BTF.ext` section.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BtfExt {
+    sections: Vec<BtfExtSection>,
+    programs: Vec<BtfExtProgram>,
+}
+
+impl BtfExt {
+    /// Parse raw `.BTF.ext` data, in either byte order. `btf` has to be the
+    /// BTF the data refers to, i.e., the contents of the `.BTF` section of
+    /// the same object.
+    pub fn parse(btf: &Btf<'_>, data: &[u8]) -> Result<Self> {
+        let magic = data
+            .get(..2)
+            .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(|| Error::with_invalid_data(".BTF.ext data is truncated"))?;
+        let swap = match magic {
+            BTF_MAGIC => false,
+            magic if magic.swap_bytes() == BTF_MAGIC => true,
+            magic => {
+                return Err(Error::with_invalid_data(format!(
+                    "invalid .BTF.ext magic {magic:#x}"
+                )))
+            }
+        };
+
+        let sections = Parser { btf, data, swap }.parse()?;
+        let programs = sections
+            .iter()
+            .flat_map(|section| BtfExtProgram::split(btf, section))
+            .collect();
+        Ok(Self { sections, programs })
+    }
+
+    /// Retrieve the information of all programs, including subprograms
+    /// (contained in the `.text` section), in order of appearance.
+    pub fn programs(&self) -> &[BtfExtProgram] {
+        &self.programs
+    }
+
+    /// Retrieve the information concerning the program `name`, e.g., as
+    /// reported by [`Program::name`][crate::Program::name].
+    pub fn program(&self, name: &str) -> Option<&BtfExtProgram> {
+        self.programs.iter().find(|prog| prog.name == name)
+    }
+
+    /// Retrieve the information of all sections, in order of appearance.
+    pub fn sections(&self) -> &[BtfExtSection] {
+        &self.sections
+    }
+
+    /// Retrieve the information concerning the section `name`, e.g., as
+    /// reported by [`Program::section`][crate::Program::section].
+    pub fn section(&self, name: &str) -> Option<&BtfExtSection> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+}
+
+impl Btf<'static> {
+    /// Load the `.BTF` and `.BTF.ext` sections of the BPF object file at
+    /// `path`.
+    ///
+    /// The returned [`BtfExt`] is `None` if the object has no `.BTF.ext`
+    /// section.
+    pub fn from_path_with_ext<P: AsRef<Path>>(path: P) -> Result<(Self, Option<BtfExt>)> {
+        fn inner(path: &Path) -> Result<(Btf<'static>, Option<BtfExt>)> {
+            let path = CString::new(path.as_os_str().as_bytes()).map_err(|_| {
+                Error::with_invalid_data(format!("invalid path {path:?}, has null bytes"))
+            })?;
+            let mut ext = ptr::null_mut();
+            let ptr = create_bpf_entity_checked(|| unsafe {
+                libbpf_sys::btf__parse_elf(path.as_ptr(), &mut ext)
+            })?;
+            let btf = Btf {
+                ptr,
+                drop_policy: DropPolicy::SelfPtrOnly,
+                _marker: PhantomData,
+            };
+
+            if ext.is_null() {
+                return Ok((btf, None));
+            }
+
+            let mut size = 0;
+            let data = unsafe { libbpf_sys::btf_ext__raw_data(ext, &mut size) };
+            let result = if data.is_null() {
+                Err(Error::with_invalid_data("failed to retrieve .BTF.ext data"))
+            } else {
+                // SAFETY: libbpf guarantees that `data` points to `size`
+                //         bytes that are valid as long as `ext` is.
+                let data = unsafe { slice::from_raw_parts(data.cast::<u8>(), size as usize) };
+                BtfExt::parse(&btf, data)
+            };
+            let () = unsafe { libbpf_sys::btf_ext__free(ext) };
+            Ok((btf, Some(result?)))
+        }
+        inner(path.as_ref())
+    }
+}
+
+/// Remove the function and line information from the `.BTF.ext` section
+/// of the BPF object file `elf`, leaving CO-RE relocations intact.
+///
+/// This is done by marking both information subsections as empty in the
+/// section's header.
+pub(crate) fn strip_func_info(elf: &mut [u8]) -> Result<()> {
+    let section = match Elf::parse(elf)?.section(".BTF.ext")? {
+        Some(section) => section,
+        None => return Ok(()),
+    };
+    let hdr = usize::try_from(section.offset)
+        .ok()
+        .and_then(|offset| elf.get_mut(offset..offset.checked_add(24)?))
+        .ok_or_else(|| Error::with_invalid_data(".BTF.ext header is truncated"))?;
+
+    let magic = u16::from_ne_bytes([hdr[0], hdr[1]]);
+    if magic != BTF_MAGIC && magic.swap_bytes() != BTF_MAGIC {
+        return Err(Error::with_invalid_data(format!(
+            "invalid .BTF.ext magic {magic:#x}"
+        )));
+    }
+
+    // Zero is zero in either byte order.
+    let () = hdr[12..16].fill(0);
+    let () = hdr[20..24].fill(0);
+    Ok(())
+}
+
+/// A parser for raw `.BTF.ext` data.
+struct Parser<'btf, 'data> {
+    btf: &'btf Btf<'btf>,
+    data: &'data [u8],
+    /// Whether the data is in non-native byte order.
+    swap: bool,
+}
+
+impl Parser<'_, '_> {
+    fn u32_at(&self, off: usize) -> Result<u32> {
+        let value = self
+            .data
+            .get(off..off + 4)
+            .map(|bytes| u32::from_ne_bytes(bytes.try_into().unwrap()))
+            .ok_or_else(|| Error::with_invalid_data(".BTF.ext data is truncated"))?;
+        Ok(if self.swap { value.swap_bytes() } else { value })
+    }
+
+    fn str_at(&self, off: u32) -> String {
+        self.btf
+            .name_at(off)
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn section(sections: &mut Vec<BtfExtSection>, name: String) -> &mut BtfExtSection {
+        let idx = match sections.iter().position(|section| section.name == name) {
+            Some(idx) => idx,
+            None => {
+                sections.push(BtfExtSection {
+                    name,
+                    ..Default::default()
+                });
+                sections.len() - 1
+            }
+        };
+        &mut sections[idx]
+    }
+
+    /// Parse the subsection at offset `off` of `len` bytes, with records of
+    /// at least `min_rec_size` bytes, invoking `f` with the section name
+    /// and the offset of each record.
+    fn parse_subsection(
+        &self,
+        off: usize,
+        len: usize,
+        min_rec_size: usize,
+        mut f: impl FnMut(String, usize) -> Result<()>,
+    ) -> Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+
+        let end = off + len;
+        if end > self.data.len() {
+            return Err(Error::with_invalid_data(".BTF.ext data is truncated"));
+        }
+
+        let rec_size = self.u32_at(off)? as usize;
+        if rec_size < min_rec_size {
+            return Err(Error::with_invalid_data(format!(
+                ".BTF.ext record size {rec_size} is too small"
+            )));
+        }
+
+        let mut off = off + 4;
+        while off < end {
+            let name = self.str_at(self.u32_at(off)?);
+            let num_info = self.u32_at(off + 4)? as usize;
+            off += 8;
+
+            let recs_end = off + num_info * rec_size;
+            if recs_end > end {
+                return Err(Error::with_invalid_data(".BTF.ext data is truncated"));
+            }
+            for rec in (off..recs_end).step_by(rec_size) {
+                let () = f(name.clone(), rec)?;
+            }
+            off = recs_end;
+        }
+        Ok(())
+    }
+
+    fn parse(self) -> Result<Vec<BtfExtSection>> {
+        let hdr_len = self.u32_at(4)? as usize;
+        // The CO-RE relocation information is optional.
+        let has_core_relos = hdr_len >= 32;
+        let subsection = |idx: usize| -> Result<(usize, usize)> {
+            let off = self.u32_at(8 + idx * 8)? as usize;
+            let len = self.u32_at(12 + idx * 8)? as usize;
+            Ok((hdr_len + off, len))
+        };
+
+        let mut sections = Vec::new();
+
+        let (off, len) = subsection(0)?;
+        let () = self.parse_subsection(off, len, 8, |name, rec| {
+            let info = FuncInfo {
+                insn_idx: self.u32_at(rec)? / INSN_SIZE,
+                type_id: TypeId::from(self.u32_at(rec + 4)?),
+            };
+            Self::section(&mut sections, name).func_info.push(info);
+            Ok(())
+        })?;
+
+        let (off, len) = subsection(1)?;
+        let () = self.parse_subsection(off, len, 16, |name, rec| {
+            let line_col = self.u32_at(rec + 12)?;
+            let info = LineInfo {
+                insn_idx: self.u32_at(rec)? / INSN_SIZE,
+                file_name: self.str_at(self.u32_at(rec + 4)?),
+                line: self.str_at(self.u32_at(rec + 8)?),
+                line_num: line_col >> 10,
+                column: line_col & 0x3ff,
+            };
+            Self::section(&mut sections, name).line_info.push(info);
+            Ok(())
+        })?;
+
+        if has_core_relos {
+            let (off, len) = subsection(2)?;
+            let () = self.parse_subsection(off, len, 16, |name, rec| {
+                let kind =
+                    CoreReloKind::try_from(self.u32_at(rec + 12)?).unwrap_or(CoreReloKind::Unknown);
+                let relo = CoreRelo {
+                    insn_idx: self.u32_at(rec)? / INSN_SIZE,
+                    type_id: TypeId::from(self.u32_at(rec + 4)?),
+                    access_spec: self.str_at(self.u32_at(rec + 8)?),
+                    kind,
+                };
+                Self::section(&mut sections, name).core_relos.push(relo);
+                Ok(())
+            })?;
+        }
+
+        Ok(sections)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::btf::types::IntEncoding;
+    use crate::btf::types::Linkage;
+    use crate::btf::BtfKind;
+
+    /// Check that we can parse `.BTF.ext` data we assembled by hand, in
+    /// either byte order.
+    #[test]
+    fn parse() {
+        let mut btf = Btf::new_empty().unwrap();
+        let int = btf.add_int("int", 4, IntEncoding::Signed).unwrap();
+        let proto = btf.add_func_proto(int).unwrap();
+        let func = btf.add_func("handler", Linkage::Global, proto).unwrap();
+        let other = btf.add_func("other", Linkage::Global, proto).unwrap();
+        let raw = btf.to_raw().unwrap();
+
+        // Look up the offsets of strings we need in the string table, which
+        // follows the type section.
+        let strings = {
+            let hdr_len = u32::from_ne_bytes(raw[4..8].try_into().unwrap()) as usize;
+            let str_off = u32::from_ne_bytes(raw[16..20].try_into().unwrap()) as usize;
+            &raw[hdr_len + str_off..]
+        };
+        let str_off = |s: &str| {
+            let needle = [s.as_bytes(), b"\0"].concat();
+            strings
+                .windows(needle.len())
+                .position(|w| w == needle)
+                .unwrap() as u32
+        };
+        let sec = str_off("handler");
+        let int_name = str_off("int");
+
+        let words = [
+            // func_info: record size, section header, two records
+            8u32,
+            sec,
+            2,
+            8,
+            u32::from(func),
+            32,
+            u32::from(other),
+            // line_info: record size, section header, two records
+            16,
+            sec,
+            2,
+            16,
+            int_name,
+            0,
+            (42 << 10) | 7,
+            40,
+            int_name,
+            0,
+            43 << 10,
+            // core_relo: record size, section header, one record
+            16,
+            sec,
+            1,
+            24,
+            u32::from(int),
+            int_name,
+            libbpf_sys::BPF_CORE_TYPE_SIZE,
+        ];
+        let func_info_len = 4 + 8 + 2 * 8;
+        let line_info_len = 4 + 8 + 2 * 16;
+        let core_relo_len = 4 + 8 + 16;
+        let hdr = [
+            32u32,
+            0,
+            func_info_len,
+            func_info_len,
+            line_info_len,
+            func_info_len + line_info_len,
+            core_relo_len,
+        ];
+
+        let assemble = |swap: bool| {
+            let to_bytes = |word: u32| {
+                if swap {
+                    word.swap_bytes().to_ne_bytes()
+                } else {
+                    word.to_ne_bytes()
+                }
+            };
+            let magic = if swap {
+                BTF_MAGIC.swap_bytes()
+            } else {
+                BTF_MAGIC
+            };
+
+            let mut data = Vec::new();
+            data.extend_from_slice(&magic.to_ne_bytes());
+            data.extend_from_slice(&[1, 0]);
+            for word in hdr.into_iter().chain(words) {
+                data.extend_from_slice(&to_bytes(word));
+            }
+            data
+        };
+
+        let data = assemble(false);
+        let ext = BtfExt::parse(&btf, &data).unwrap();
+        assert_eq!(ext.sections().len(), 1);
+        let section = ext.section("handler").unwrap();
+        assert_eq!(
+            section.func_info,
+            vec![
+                FuncInfo {
+                    insn_idx: 1,
+                    type_id: func,
+                },
+                FuncInfo {
+                    insn_idx: 4,
+                    type_id: other,
+                },
+            ]
+        );
+        let func_ty = btf.type_by_id::<BtfType<'_>>(func).unwrap();
+        assert_eq!(func_ty.kind(), BtfKind::Func);
+        let line = LineInfo {
+            insn_idx: 2,
+            file_name: "int".to_string(),
+            line: String::new(),
+            line_num: 42,
+            column: 7,
+        };
+        let other_line = LineInfo {
+            insn_idx: 5,
+            file_name: "int".to_string(),
+            line: String::new(),
+            line_num: 43,
+            column: 0,
+        };
+        assert_eq!(section.line_info, vec![line.clone(), other_line.clone()]);
+        let relo = CoreRelo {
+            insn_idx: 3,
+            type_id: int,
+            access_spec: "int".to_string(),
+            kind: CoreReloKind::TypeSize,
+        };
+        assert_eq!(section.core_relos, vec![relo.clone()]);
+
+        // The information is split up by program, with instruction
+        // indexes relative to the program's start.
+        assert_eq!(ext.programs().len(), 2);
+        let prog = ext.program("handler").unwrap();
+        assert_eq!(prog.section, "handler");
+        assert_eq!(prog.insn_off, 1);
+        assert_eq!(
+            prog.func_info,
+            vec![FuncInfo {
+                insn_idx: 0,
+                type_id: func,
+            }]
+        );
+        assert_eq!(
+            prog.line_info,
+            vec![LineInfo {
+                insn_idx: 1,
+                ..line
+            }]
+        );
+        assert_eq!(
+            prog.core_relos,
+            vec![CoreRelo {
+                insn_idx: 2,
+                ..relo
+            }]
+        );
+
+        let prog = ext.program("other").unwrap();
+        assert_eq!(prog.insn_off, 4);
+        assert_eq!(
+            prog.line_info,
+            vec![LineInfo {
+                insn_idx: 1,
+                ..other_line
+            }]
+        );
+        assert_eq!(prog.core_relos, Vec::new());
+
+        // Data in the other byte order is understood as well.
+        assert_eq!(BtfExt::parse(&btf, &assemble(true)).unwrap(), ext);
+
+        // Truncated data is rejected.
+        assert!(BtfExt::parse(&btf, &data[..data.len() - 4]).is_err());
+    }
+}
@@ -12,6 +12,10 @@
 //! [`Btf::type_by_kind`]). If you want to get a type independently of the kind, just make sure `K`
 //! binds to [`BtfType`].
 
+mod builder;
+pub mod dump;
+pub mod ext;
+pub(crate) mod symbols;
 pub mod types;
 
 use std::ffi::CStr;
@@ -19,6 +23,7 @@ use std::ffi::CString;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::fs;
 use std::io;
 use std::marker::PhantomData;
 use std::mem::size_of;
@@ -26,26 +31,34 @@ use std::num::NonZeroUsize;
 use std::ops::Deref;
 use std::os::raw::c_ulong;
 use std::os::raw::c_void;
+use std::os::unix::prelude::AsFd;
 use std::os::unix::prelude::AsRawFd;
+use std::os::unix::prelude::BorrowedFd;
 use std::os::unix::prelude::FromRawFd;
 use std::os::unix::prelude::OsStrExt;
 use std::os::unix::prelude::OwnedFd;
 use std::path::Path;
 use std::ptr;
 use std::ptr::NonNull;
+use std::slice;
 
 use num_enum::IntoPrimitive;
 use num_enum::TryFromPrimitive;
 
 use crate::util::create_bpf_entity_checked;
 use crate::util::create_bpf_entity_checked_opt;
+use crate::util::parse_ret;
 use crate::util::parse_ret_i32;
 use crate::AsRawLibbpf;
 use crate::Error;
+use crate::ErrorExt as _;
 use crate::Result;
 
 use self::types::Composite;
 
+pub use self::symbols::has_kfunc;
+pub use self::symbols::has_ksym;
+
 /// The various btf types.
 #[derive(IntoPrimitive, TryFromPrimitive, Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u32)]
@@ -190,6 +203,20 @@ impl Btf<'static> {
             _marker: PhantomData,
         })
     }
+
+    /// Parse raw BTF data, such as the contents of an object file's
+    /// `.BTF` section, in either byte order.
+    pub(crate) fn from_raw_data(bytes: &[u8]) -> Result<Self> {
+        let ptr = create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::btf__new(bytes.as_ptr() as *const c_void, bytes.len() as u32)
+        })?;
+
+        Ok(Self {
+            ptr,
+            drop_policy: DropPolicy::SelfPtrOnly,
+            _marker: PhantomData,
+        })
+    }
 }
 
 impl<'btf> Btf<'btf> {
@@ -212,6 +239,69 @@ impl<'btf> Btf<'btf> {
         })
     }
 
+    /// Load the btf information of the kernel module with the given name.
+    ///
+    /// Module BTF is split BTF, built on top of the kernel's `vmlinux` BTF,
+    /// which consequently has to be provided as `vmlinux` and outlive the
+    /// returned object.
+    pub fn from_kernel_module(name: &str, vmlinux: &'btf Btf<'static>) -> Result<Self> {
+        let cname = CString::new(name)
+            .map_err(|_| Error::with_invalid_data(format!("invalid module name {name:?}")))?;
+        let ptr = create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::btf__load_module_btf(cname.as_ptr(), vmlinux.ptr.as_ptr())
+        })?;
+
+        Ok(Self {
+            ptr,
+            drop_policy: DropPolicy::SelfPtrOnly,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Parse split BTF from the raw BTF data `bytes`, on top of the BTF
+    /// `base`.
+    ///
+    /// Split BTF, as used for kernel modules, only contains types in
+    /// addition to those of its base, which consequently has to outlive the
+    /// returned object. Types of the base are accessible through the split
+    /// BTF as well. `bytes` is copied.
+    pub fn from_raw_split(base: &'btf Btf<'_>, bytes: &[u8]) -> Result<Self> {
+        let ptr = create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::btf__new_split(
+                bytes.as_ptr() as *const c_void,
+                bytes.len() as u32,
+                base.ptr.as_ptr(),
+            )
+        })?;
+
+        Ok(Self {
+            ptr,
+            drop_policy: DropPolicy::SelfPtrOnly,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Load split BTF from the specified path, on top of the BTF `base`.
+    ///
+    /// `path` may refer to a raw BTF file (such as
+    /// `/sys/kernel/btf/<module>`) or an ELF file containing a `.BTF`
+    /// section. See [`Btf::from_raw_split`] for details on split BTF.
+    pub fn from_path_with_base<P: AsRef<Path>>(path: P, base: &'btf Btf<'_>) -> Result<Self> {
+        let path = path.as_ref();
+        let path = CString::new(path.as_os_str().as_bytes()).map_err(|_| {
+            Error::with_invalid_data(format!("invalid path {path:?}, has null bytes"))
+        })?;
+        let ptr = create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::btf__parse_split(path.as_ptr(), base.ptr.as_ptr())
+        })?;
+
+        Ok(Self {
+            ptr,
+            drop_policy: DropPolicy::SelfPtrOnly,
+            _marker: PhantomData,
+        })
+    }
+
     /// From raw bytes coming from an object file.
     pub fn from_raw(name: &'btf str, object_file: &'btf [u8]) -> Result<Option<Self>> {
         let cname = CString::new(name)
@@ -343,6 +433,16 @@ impl<'btf> Btf<'btf> {
         .ok()
     }
 
+    /// Iterate over all types, in order of their [`TypeId`]s.
+    ///
+    /// The implicit `void` type (with id 0) is not included. Use
+    /// [`types::Type::from`] to work with the concrete type of each.
+    pub fn types(&self) -> impl Iterator<Item = BtfType<'_>> + '_ {
+        (1..self.len() as u32)
+            .map(TypeId::from)
+            .filter_map(|id| self.type_by_id(id))
+    }
+
     /// Find all types of a specific type kind.
     pub fn type_by_kind<'s, K>(&'s self) -> impl Iterator<Item = K> + 's
     where
@@ -353,6 +453,166 @@ impl<'btf> Btf<'btf> {
             .filter_map(|id| self.type_by_id(id))
             .filter_map(|t| K::try_from(t).ok())
     }
+
+    /// Find a btf type by name and kind.
+    ///
+    /// Unlike [`Btf::type_by_name`], this finds the type of kind `kind` even if
+    /// a type of another kind with the same name comes first, e.g., `struct
+    /// foo` in the presence of `typedef struct foo foo`.
+    ///
+    /// # Panics
+    /// If `name` has null bytes.
+    pub fn find_type_by_name_kind(&self, name: &str, kind: BtfKind) -> Option<BtfType<'_>> {
+        let c_string = CString::new(name)
+            .map_err(|_| Error::with_invalid_data(format!("{name:?} contains null bytes")))
+            .unwrap();
+        let ty = unsafe {
+            // SAFETY: the btf pointer is valid and the c_string pointer was created from safe code
+            // therefore it's also valid.
+            libbpf_sys::btf__find_by_name_kind(self.ptr.as_ptr(), c_string.as_ptr(), kind.into())
+        };
+        if ty < 0 {
+            None
+        } else {
+            self.type_by_id(TypeId(ty as _))
+        }
+    }
+
+    /// Resolve the type with id `type_id`, skipping any typedefs and
+    /// modifiers (`volatile`, `const`, `restrict`, and type tags).
+    pub fn resolve_type(&self, type_id: TypeId) -> Result<BtfType<'_>> {
+        let id = parse_ret_i32(unsafe {
+            // SAFETY: the btf pointer is valid.
+            libbpf_sys::btf__resolve_type(self.ptr.as_ptr(), type_id.0)
+        })?;
+        self.type_by_id(TypeId(id as _)).ok_or_else(|| {
+            Error::with_invalid_data(format!("type {type_id} resolved to invalid type {id}"))
+        })
+    }
+
+    /// Retrieve the size of the type with id `type_id` in bytes, as `sizeof`
+    /// would report it in C.
+    ///
+    /// Typedefs and modifiers are skipped and arrays are sized according to
+    /// their number of elements. Types without a size, such as functions or
+    /// forward declarations, result in an error.
+    pub fn type_size(&self, type_id: TypeId) -> Result<usize> {
+        let size = unsafe {
+            // SAFETY: the btf pointer is valid.
+            libbpf_sys::btf__resolve_size(self.ptr.as_ptr(), type_id.0)
+        };
+        if size < 0 {
+            Err(Error::from_raw_os_error(-size as i32))
+        } else {
+            Ok(size as usize)
+        }
+    }
+
+    /// Retrieve the alignment of the type with id `type_id` in bytes.
+    ///
+    /// In contrast to [`BtfType::alignment`], this takes into account that
+    /// structs and unions may be packed.
+    pub fn alignment_of(&self, type_id: TypeId) -> Result<NonZeroUsize> {
+        let align = parse_ret_i32(unsafe {
+            // SAFETY: the btf pointer is valid.
+            libbpf_sys::btf__align_of(self.ptr.as_ptr(), type_id.0)
+        })?;
+        NonZeroUsize::new(align as usize).ok_or_else(|| {
+            Error::with_invalid_data(format!("cannot determine alignment of type {type_id}"))
+        })
+    }
+
+    /// Serialize this BTF into its raw binary representation, as found in
+    /// the `.BTF` section of an ELF file or in `/sys/kernel/btf`.
+    ///
+    /// The result can be parsed again with [`Btf::from_raw_split`] or
+    /// [`Btf::from_path`] (for split and non-split BTF, respectively).
+    pub fn to_raw(&self) -> Result<Vec<u8>> {
+        let mut size = 0;
+        let data = unsafe {
+            // SAFETY: the btf pointer is valid.
+            libbpf_sys::btf__raw_data(self.ptr.as_ptr(), &mut size)
+        };
+        if data.is_null() {
+            return Err(Error::with_io_error(
+                io::ErrorKind::OutOfMemory,
+                "failed to serialize BTF",
+            ));
+        }
+
+        let data = unsafe {
+            // SAFETY: libbpf guarantees that `data` points to `size` bytes,
+            //         which stay valid until the BTF is modified or freed.
+            slice::from_raw_parts(data.cast::<u8>(), size as usize)
+        };
+        Ok(data.to_vec())
+    }
+
+    /// Load this BTF into the kernel, returning a file descriptor referring
+    /// to the loaded BTF object.
+    ///
+    /// The file descriptor can be used for creating maps with BTF type
+    /// information, by setting `btf_fd` (as well as the key and value type
+    /// ids) in the options passed to [`MapHandle::create`][crate::MapHandle::create].
+    /// If the kernel rejects the BTF, the returned error contains the
+    /// kernel's log.
+    pub fn load_into_kernel(&self) -> Result<OwnedFd> {
+        /// The size of the buffer for the kernel's log, as used by libbpf.
+        const LOG_BUF_SIZE: usize = (u32::MAX >> 8) as usize;
+
+        let data = self.to_raw()?;
+        let load = |opts: &mut libbpf_sys::bpf_btf_load_opts| {
+            let fd = unsafe {
+                // SAFETY: `data` is valid for the duration of the call and
+                //         `opts` is a valid options object.
+                libbpf_sys::bpf_btf_load(
+                    data.as_ptr().cast::<c_void>(),
+                    data.len() as libbpf_sys::size_t,
+                    opts,
+                )
+            };
+            parse_ret_i32(fd).map(|fd| unsafe {
+                // SAFETY: parse_ret_i32 will check that this fd is above -1
+                OwnedFd::from_raw_fd(fd)
+            })
+        };
+
+        let mut opts = libbpf_sys::bpf_btf_load_opts {
+            sz: size_of::<libbpf_sys::bpf_btf_load_opts>() as _,
+            ..Default::default()
+        };
+        let err = match load(&mut opts) {
+            Ok(fd) => return Ok(fd),
+            Err(err) => err,
+        };
+
+        // Loading failed. Retry with logging enabled, to report why.
+        let mut log = vec![0u8; LOG_BUF_SIZE];
+        opts.log_buf = log.as_mut_ptr().cast();
+        opts.log_size = log.len() as _;
+        opts.log_level = 1;
+        let err = match load(&mut opts) {
+            // We didn't expect the second attempt to succeed, but there is
+            // no reason to report an error then.
+            Ok(fd) => return Ok(fd),
+            Err(_) => err,
+        };
+
+        let log = CStr::from_bytes_until_nul(&log)
+            .map(|log| log.to_string_lossy())
+            .unwrap_or_default();
+        Err(err).with_context(|| format!("failed to load BTF into kernel:\n{}", log.trim_end()))
+    }
+
+    /// Serialize this BTF into its raw binary representation and write it
+    /// to the file at `path`.
+    ///
+    /// See [`Btf::to_raw`].
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let data = self.to_raw()?;
+        let () = fs::write(path, data)?;
+        Ok(())
+    }
 }
 
 impl AsRawLibbpf for Btf<'_> {
@@ -385,6 +645,101 @@ impl Drop for Btf<'_> {
     }
 }
 
+/// The btf information of a loaded kernel module, as known to the kernel.
+#[derive(Debug)]
+pub struct ModuleBtf {
+    name: String,
+    id: u32,
+    fd: OwnedFd,
+}
+
+impl ModuleBtf {
+    /// List the btf information of all loaded kernel modules that have any.
+    pub fn list() -> Result<Vec<Self>> {
+        let mut modules = Vec::new();
+        let mut id = 0;
+        loop {
+            let ret = unsafe { libbpf_sys::bpf_btf_get_next_id(id, &mut id) };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::NotFound {
+                    break;
+                }
+                return Err(Error::from(err));
+            }
+
+            let fd = unsafe { libbpf_sys::bpf_btf_get_fd_by_id(id) };
+            if fd < 0 {
+                let err = io::Error::last_os_error();
+                // The btf object may have vanished in the meantime.
+                if err.kind() == io::ErrorKind::NotFound {
+                    continue;
+                }
+                return Err(Error::from(err));
+            }
+            let fd = unsafe {
+                // SAFETY: we checked that this fd is above -1
+                OwnedFd::from_raw_fd(fd)
+            };
+
+            // Module names are at most 56 bytes long, including the NUL
+            // terminator.
+            let mut name = [0u8; 64];
+            let mut info = libbpf_sys::bpf_btf_info {
+                name: name.as_mut_ptr() as u64,
+                name_len: name.len() as u32,
+                ..Default::default()
+            };
+            let () = parse_ret(unsafe {
+                libbpf_sys::bpf_obj_get_info_by_fd(
+                    fd.as_raw_fd(),
+                    (&mut info as *mut libbpf_sys::bpf_btf_info).cast::<c_void>(),
+                    &mut (size_of::<libbpf_sys::bpf_btf_info>() as u32),
+                )
+            })?;
+
+            let name = CStr::from_bytes_until_nul(&name)
+                .map_err(|_| Error::with_invalid_data("btf name is not NUL terminated"))?
+                .to_string_lossy()
+                .into_owned();
+            if info.kernel_btf != 0 && name != "vmlinux" {
+                modules.push(Self { name, id, fd });
+            }
+        }
+        Ok(modules)
+    }
+
+    /// The name of the kernel module.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The id of the btf object in the kernel.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Parse the btf information, which is split btf on top of the kernel's
+    /// `vmlinux` btf, as provided by `vmlinux`.
+    pub fn btf<'btf>(&self, vmlinux: &'btf Btf<'static>) -> Result<Btf<'btf>> {
+        let ptr = create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::btf__load_from_kernel_by_id_split(self.id, vmlinux.ptr.as_ptr())
+        })?;
+
+        Ok(Btf {
+            ptr,
+            drop_policy: DropPolicy::SelfPtrOnly,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl AsFd for ModuleBtf {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
 /// An undiscriminated btf type
 ///
 /// The [`btf_type_match`](crate::btf_type_match) can be used to match on the variants of this type
@@ -683,4 +1038,95 @@ mod tests {
     fn from_vmlinux() {
         assert!(Btf::from_vmlinux().is_ok());
     }
+
+    /// Check that we can walk all types of a BTF and convert them into
+    /// their concrete types.
+    #[test]
+    fn types() {
+        let btf = Btf::from_vmlinux().unwrap();
+        assert_eq!(btf.types().count(), btf.len() - 1);
+
+        for ty in btf.types() {
+            let kind = ty.kind();
+            let ty = types::Type::from(ty);
+            assert_eq!(ty.kind(), kind);
+        }
+
+        let pid_t = btf.type_by_name::<types::Typedef<'_>>("pid_t").unwrap();
+        assert_eq!(pid_t.resolve().kind(), BtfKind::Int);
+
+        let task = btf
+            .type_by_name::<types::Struct<'_>>("task_struct")
+            .unwrap();
+        let offsets = task
+            .iter()
+            .map(|member| member.attr.bit_offset())
+            .collect::<Vec<_>>();
+        assert!(offsets.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    /// Check that we can parse module BTF as split BTF on top of vmlinux.
+    #[test]
+    fn split() {
+        let vmlinux = Btf::from_vmlinux().unwrap();
+        let module = fs::read_dir("/sys/kernel/btf")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.file_name().unwrap() != "vmlinux");
+        let module = match module {
+            Some(module) => module,
+            // No modules with BTF are loaded.
+            None => return,
+        };
+
+        let from_path = Btf::from_path_with_base(&module, &vmlinux).unwrap();
+        assert!(from_path.len() > vmlinux.len());
+        // Types of the base are accessible as well.
+        assert!(from_path
+            .type_by_name::<types::Struct<'_>>("task_struct")
+            .is_some());
+
+        let bytes = fs::read(&module).unwrap();
+        let from_raw = Btf::from_raw_split(&vmlinux, &bytes).unwrap();
+        assert_eq!(from_raw.len(), from_path.len());
+    }
+
+    /// Check that we can resolve types and determine their sizes and
+    /// alignments.
+    #[test]
+    fn resolution() {
+        let btf = Btf::from_vmlinux().unwrap();
+
+        let pid_t = btf
+            .find_type_by_name_kind("pid_t", BtfKind::Typedef)
+            .unwrap();
+        assert!(btf
+            .find_type_by_name_kind("pid_t", BtfKind::Struct)
+            .is_none());
+
+        let resolved = btf.resolve_type(pid_t.type_id()).unwrap();
+        assert_eq!(resolved.kind(), BtfKind::Int);
+        assert_eq!(resolved.name().unwrap().to_str().unwrap(), "int");
+        assert_eq!(btf.type_size(pid_t.type_id()).unwrap(), 4);
+        assert_eq!(btf.alignment_of(pid_t.type_id()).unwrap().get(), 4);
+
+        let list_head = btf
+            .find_type_by_name_kind("list_head", BtfKind::Struct)
+            .unwrap();
+        let ptr_size = btf.ptr_size().unwrap().get();
+        assert_eq!(btf.type_size(list_head.type_id()).unwrap(), 2 * ptr_size);
+        assert_eq!(
+            btf.alignment_of(list_head.type_id()).unwrap().get(),
+            ptr_size
+        );
+
+        let func = btf.type_by_kind::<types::Func<'_>>().next().unwrap();
+        assert!(btf.type_size(func.type_id()).is_err());
+    }
+
+    #[test]
+    fn from_kernel_module_nonexistent() {
+        let vmlinux = Btf::from_vmlinux().unwrap();
+        assert!(Btf::from_kernel_module("does-not-exist", &vmlinux).is_err());
+    }
 }
@@ -0,0 +1,164 @@
+//! Probing for the availability of kernel symbols.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::Error;
+use crate::Result;
+
+use super::Btf;
+use super::BtfKind;
+use super::TypeId;
+
+/// The directory the kernel exposes its btf information in.
+const SYS_KERNEL_BTF: &str = "/sys/kernel/btf";
+
+/// The path of the kernel symbol table.
+pub(crate) const KALLSYMS: &str = "/proc/kallsyms";
+
+/// Look up a type `name` of kind `kind` in the btf of the kernel, as
+/// provided by `vmlinux`, or of any loaded kernel module.
+///
+/// On success, the name of the module providing the type (`None` for
+/// the kernel itself) and the type's id are returned.
+pub(crate) fn find_in_kernel_btf(
+    vmlinux: &Btf<'static>,
+    name: &str,
+    kind: BtfKind,
+) -> Result<Option<(Option<String>, TypeId)>> {
+    if name.contains('\0') {
+        return Err(Error::with_invalid_input(format!(
+            "{name:?} contains null bytes"
+        )));
+    }
+
+    if let Some(ty) = vmlinux.find_type_by_name_kind(name, kind) {
+        return Ok(Some((None, ty.type_id())));
+    }
+
+    let dir = match fs::read_dir(SYS_KERNEL_BTF) {
+        Ok(dir) => dir,
+        // Without the directory there is no module btf information.
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    for entry in dir {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_name() == "vmlinux" {
+            continue;
+        }
+
+        let module = match Btf::from_path_with_base(&path, vmlinux) {
+            Ok(module) => module,
+            // The module may have been unloaded in the meantime.
+            Err(_) if !path.exists() => continue,
+            Err(err) => return Err(err),
+        };
+        if let Some(ty) = module.find_type_by_name_kind(name, kind) {
+            let module = entry.file_name().to_string_lossy().into_owned();
+            return Ok(Some((Some(module), ty.type_id())));
+        }
+    }
+    Ok(None)
+}
+
+/// Read the kernel symbol table at `path`, treating a missing file as an
+/// empty table.
+pub(crate) fn read_kallsyms(path: &Path) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(kallsyms) => Ok(kallsyms),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Look up the address of `name` in the kernel symbol table `kallsyms`.
+///
+/// Addresses are reported as zero to users lacking the privileges to see
+/// them.
+pub(crate) fn find_in_kallsyms(kallsyms: &str, name: &str) -> Option<u64> {
+    // Lines are of the form `<addr> <type> <name> [<module>]`.
+    kallsyms.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let addr = fields.next()?;
+        let sym = fields.nth(1)?;
+        if sym != name {
+            return None;
+        }
+        u64::from_str_radix(addr, 16).ok()
+    })
+}
+
+/// Check whether the running kernel provides the kfunc `name`, by
+/// looking it up in the btf of the kernel and of all loaded kernel
+/// modules.
+///
+/// This allows for choosing between alternative programs, e.g., by means
+/// of [`OpenProgram::set_autoload`][crate::OpenProgram::set_autoload],
+/// before the verifier rejects a program referencing an unknown kfunc.
+///
+/// Note that the presence of a function in the kernel's btf does not
+/// guarantee that it is callable from every program type.
+pub fn has_kfunc(name: &str) -> Result<bool> {
+    let vmlinux = Btf::from_vmlinux()?;
+    let found = find_in_kernel_btf(&vmlinux, name, BtfKind::Func)?.is_some();
+    Ok(found)
+}
+
+/// Check whether the running kernel provides the symbol `name`, as it
+/// could be referenced via a `__ksym` extern.
+///
+/// Typed ksyms are looked up as variables in the btf of the kernel and of
+/// all loaded kernel modules, while any other symbol is looked up in
+/// `/proc/kallsyms`.
+pub fn has_ksym(name: &str) -> Result<bool> {
+    let vmlinux = Btf::from_vmlinux()?;
+    if find_in_kernel_btf(&vmlinux, name, BtfKind::Var)?.is_some() {
+        return Ok(true);
+    }
+    let kallsyms = read_kallsyms(Path::new(KALLSYMS))?;
+    Ok(find_in_kallsyms(&kallsyms, name).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::NamedTempFile;
+
+    use crate::btf::types::Func;
+
+    /// Check that we can find kernel functions in btf.
+    #[test]
+    fn kfunc() {
+        let vmlinux = Btf::from_vmlinux().unwrap();
+        let func = vmlinux.type_by_kind::<Func<'_>>().next().unwrap();
+        let name = func.name().unwrap().to_str().unwrap();
+        assert!(has_kfunc(name).unwrap());
+        assert!(!has_kfunc("libbpf_rs_does_not_exist").unwrap());
+        assert!(has_kfunc("nul\0byte").is_err());
+    }
+
+    /// Check that we parse the kernel symbol table correctly.
+    #[test]
+    fn kallsyms() {
+        let file = NamedTempFile::new().unwrap();
+        let () = fs::write(
+            file.path(),
+            "ffffffff81000000 T schedule\n0000000000000000 t foo_init\t[foo]\n",
+        )
+        .unwrap();
+
+        let kallsyms = read_kallsyms(file.path()).unwrap();
+        assert_eq!(
+            find_in_kallsyms(&kallsyms, "schedule"),
+            Some(0xffffffff81000000)
+        );
+        assert_eq!(find_in_kallsyms(&kallsyms, "foo_init"), Some(0));
+        assert_eq!(find_in_kallsyms(&kallsyms, "foo"), None);
+        assert_eq!(read_kallsyms(Path::new("/does/not/exist")).unwrap(), "");
+    }
+}
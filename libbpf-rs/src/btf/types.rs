@@ -258,6 +258,24 @@ pub enum MemberAttr {
 }
 
 impl MemberAttr {
+    /// The offset of the member from the start of the struct/union, in
+    /// bits.
+    #[inline]
+    pub fn bit_offset(&self) -> u32 {
+        match self {
+            Self::Normal { offset } | Self::BitField { offset, .. } => *offset,
+        }
+    }
+
+    /// The size of the member in bits, if it is a bitfield.
+    #[inline]
+    pub fn bitfield_size(&self) -> Option<u8> {
+        match self {
+            Self::Normal { .. } => None,
+            Self::BitField { size, .. } => Some(*size),
+        }
+    }
+
     #[inline]
     fn new(kflag: bool, offset: u32) -> Self {
         if kflag {
@@ -605,6 +623,17 @@ gen_collection_concrete_type! {
     }
 }
 
+impl Enum<'_> {
+    /// Check whether the enum's values are signed.
+    ///
+    /// Note that older compilers do not record signedness, in which case
+    /// values are reported as unsigned.
+    #[inline]
+    pub fn is_signed(&self) -> bool {
+        self.source.kind_flag()
+    }
+}
+
 // Fwd
 gen_fieldless_concrete_type! {
     /// A forward declared C type.
@@ -643,6 +672,22 @@ gen_fieldless_concrete_type! {
     Typedef with ReferencesType
 }
 
+impl<'btf> Typedef<'btf> {
+    /// Follow the chain of typedefs starting at this one (e.g., `pid_t` ->
+    /// `__kernel_pid_t` -> `int`), returning the first type that is not a
+    /// typedef.
+    ///
+    /// Modifiers such as `const` are not skipped, see
+    /// [`BtfType::skip_mods_and_typedefs`] for that.
+    pub fn resolve(&self) -> BtfType<'btf> {
+        let mut ty = self.referenced_type();
+        while let Ok(typedef) = Typedef::try_from(ty) {
+            ty = typedef.referenced_type();
+        }
+        ty
+    }
+}
+
 // Volatile
 gen_fieldless_concrete_type! {
     /// The volatile modifier.
@@ -704,6 +749,19 @@ gen_collection_concrete_type! {
     }
 }
 
+impl FuncProto<'_> {
+    /// Whether the function takes a variable number of arguments.
+    ///
+    /// Variadic functions are denoted by a final parameter without name
+    /// and type, which is included in the parameters reported.
+    pub fn is_variadic(&self) -> bool {
+        self.iter()
+            .last()
+            .map(|param| param.name.is_none() && param.ty == TypeId::from(0))
+            .unwrap_or(false)
+    }
+}
+
 // Var
 gen_concrete_type! {
     /// A global variable.
@@ -809,6 +867,75 @@ gen_collection_concrete_type! {
     }
 }
 
+impl Enum64<'_> {
+    /// Check whether the enum's values are signed, in which case they have
+    /// to be interpreted as `i64`.
+    ///
+    /// Note that older compilers do not record signedness, in which case
+    /// values are reported as unsigned.
+    #[inline]
+    pub fn is_signed(&self) -> bool {
+        self.source.kind_flag()
+    }
+}
+
+macro_rules! gen_type_enum {
+    ($($name:ident),* $(,)?) => {
+        /// A [`BtfType`] converted into the concrete type of its kind.
+        ///
+        /// In contrast to [`btf_type_match!`](crate::btf_type_match), this
+        /// allows for working with types of all kinds using regular Rust
+        /// `match` expressions.
+        ///
+        /// ```no_run
+        /// use libbpf_rs::btf::types::Type;
+        /// use libbpf_rs::Btf;
+        ///
+        /// let btf = Btf::from_vmlinux().unwrap();
+        /// for ty in btf.types() {
+        ///     match Type::from(ty) {
+        ///         Type::Struct(s) => println!("struct {:?}: {} members", s.name(), s.len()),
+        ///         Type::Enum(e) => println!("enum {:?}: {} values", e.name(), e.len()),
+        ///         _ => (),
+        ///     }
+        /// }
+        /// ```
+        #[derive(Debug)]
+        pub enum Type<'btf> {
+            $(
+                #[doc = concat!("A [`", stringify!($name), "`].")]
+                $name($name<'btf>),
+            )*
+        }
+
+        impl<'btf> From<BtfType<'btf>> for Type<'btf> {
+            fn from(ty: BtfType<'btf>) -> Self {
+                match ty.kind() {
+                    $(
+                        // SANITY: We checked the kind.
+                        BtfKind::$name => Self::$name($name::try_from(ty).unwrap()),
+                    )*
+                }
+            }
+        }
+
+        impl<'btf> Deref for Type<'btf> {
+            type Target = BtfType<'btf>;
+
+            fn deref(&self) -> &Self::Target {
+                match self {
+                    $(Self::$name(ty) => ty,)*
+                }
+            }
+        }
+    };
+}
+
+gen_type_enum!(
+    Void, Int, Ptr, Array, Struct, Union, Enum, Fwd, Typedef, Volatile, Const, Restrict, Func,
+    FuncProto, Var, DataSec, Float, DeclTag, TypeTag, Enum64,
+);
+
 /// A macro that allows matching on the type of a [`BtfType`] as if it was an enum.
 ///
 /// Each pattern can be of two types.
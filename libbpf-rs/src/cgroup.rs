@@ -0,0 +1,307 @@
+//! Resolve and inspect cgroups, as needed for attaching BPF programs to
+//! them or keying cgroup storage.
+//!
+//! BPF operates exclusively on the unified (v2) cgroup hierarchy. On
+//! systems that additionally mount legacy (v1) hierarchies, paths and IDs
+//! from those are rejected instead of being silently misinterpreted.
+//! ```no_run
+//! use std::process;
+//!
+//! use libbpf_rs::cgroup;
+//!
+//! let path = cgroup::of_pid(process::id()).unwrap();
+//! println!("cgroup {}: id {}", path.display(), cgroup::id(&path).unwrap());
+//! for child in cgroup::descendants(&path).unwrap() {
+//!     println!("descendant {}", child.display());
+//! }
+//! ```
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::mem::size_of;
+use std::mem::MaybeUninit;
+use std::os::unix::io::AsFd as _;
+use std::os::unix::io::AsRawFd as _;
+use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::OwnedFd;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::util;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::Result;
+
+/// The conventional mount point of the unified (v2) cgroup hierarchy.
+const DEFAULT_ROOT: &str = "/sys/fs/cgroup";
+
+/// A file handle as reported by `name_to_handle_at` for cgroups, which
+/// encodes the cgroup's ID.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct CgroupFileHandle {
+    handle_bytes: u32,
+    handle_type: i32,
+    id: u64,
+}
+
+/// Open the cgroup directory at `path` (e.g., `/sys/fs/cgroup/foo`).
+pub fn open<P: AsRef<Path>>(path: P) -> Result<OwnedFd> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|err| {
+        Error::with_io_error(
+            err.kind(),
+            format!("failed to open cgroup `{}`: {err}", path.display()),
+        )
+    })?;
+    Ok(OwnedFd::from(file))
+}
+
+/// Ensure that `fd` refers to a cgroup of the unified (v2) hierarchy.
+fn ensure_cgroup2(fd: BorrowedFd<'_>) -> Result<()> {
+    let mut buf = MaybeUninit::<libc::statfs>::uninit();
+    let rc = unsafe { libc::fstatfs(fd.as_raw_fd(), buf.as_mut_ptr()) };
+    if rc != 0 {
+        return Err(io::Error::last_os_error()).context("failed to stat cgroup file system");
+    }
+    // SAFETY: `fstatfs` succeeded and initialized the buffer.
+    let buf = unsafe { buf.assume_init() };
+    if buf.f_type as u32 != libc::CGROUP2_SUPER_MAGIC as u32 {
+        return Err(Error::with_invalid_input(
+            "cgroup is not part of the unified (v2) cgroup hierarchy",
+        ));
+    }
+    Ok(())
+}
+
+/// Retrieve the mount point of the unified (v2) cgroup hierarchy.
+///
+/// `/sys/fs/cgroup` is preferred if the hierarchy is mounted there.
+/// Otherwise the first mount point found (e.g., `/sys/fs/cgroup/unified`
+/// on systems also using v1 hierarchies) is reported. An error of kind
+/// [`ErrorKind::NotFound`][crate::ErrorKind::NotFound] indicates that the
+/// hierarchy is not mounted.
+pub fn root() -> Result<PathBuf> {
+    let mut mount_points = util::mount_points("cgroup2")?;
+    if let Some(idx) = mount_points
+        .iter()
+        .position(|path| path == Path::new(DEFAULT_ROOT))
+    {
+        return Ok(mount_points.swap_remove(idx));
+    }
+    mount_points.into_iter().next().ok_or_else(|| {
+        Error::with_io_error(
+            io::ErrorKind::NotFound,
+            "unified (v2) cgroup hierarchy is not mounted",
+        )
+    })
+}
+
+/// Extract the path of a process' cgroup in the unified hierarchy,
+/// relative to the hierarchy's root, from the contents of its
+/// `/proc/<pid>/cgroup` file.
+fn parse_proc_cgroup(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(|path| path.trim_start_matches('/'))
+}
+
+/// Retrieve the path of the cgroup the process `pid` belongs to in the
+/// unified (v2) hierarchy.
+///
+/// Memberships in legacy (v1) hierarchies are ignored.
+pub fn of_pid(pid: u32) -> Result<PathBuf> {
+    let path = format!("/proc/{pid}/cgroup");
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read cgroup membership of process {pid}"))?;
+    let cgroup = parse_proc_cgroup(&content).ok_or_else(|| {
+        Error::with_io_error(
+            io::ErrorKind::NotFound,
+            format!("process {pid} is not a member of the unified (v2) cgroup hierarchy"),
+        )
+    })?;
+    Ok(root()?.join(cgroup))
+}
+
+/// Retrieve the ID of a cgroup, as reported by, e.g., the
+/// `bpf_get_current_cgroup_id` helper and used for keying by cgroup.
+///
+/// An error of kind [`ErrorKind::InvalidInput`][crate::ErrorKind::InvalidInput]
+/// is reported for cgroups of legacy (v1) hierarchies.
+pub fn id<C: IntoCgroupFd>(cgroup: C) -> Result<u64> {
+    let fd = cgroup.into_cgroup_fd()?;
+    let () = ensure_cgroup2(fd.as_fd())?;
+
+    let mut handle = CgroupFileHandle {
+        handle_bytes: size_of::<u64>() as _,
+        ..Default::default()
+    };
+    let mut mount_id = 0;
+    let rc = unsafe {
+        libc::syscall(
+            libc::SYS_name_to_handle_at,
+            fd.as_raw_fd(),
+            b"\0".as_ptr(),
+            &mut handle as *mut CgroupFileHandle,
+            &mut mount_id as *mut libc::c_int,
+            libc::AT_EMPTY_PATH,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error()).context("failed to retrieve cgroup ID");
+    }
+    Ok(handle.id)
+}
+
+/// Recursively collect the cgroups below directory `dir`.
+fn collect_descendants(dir: &Path, descendants: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // The cgroup may have been removed since we encountered it.
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read cgroup `{}`", dir.display()))
+        }
+    };
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            let path = entry.path();
+            descendants.push(path.clone());
+            let () = collect_descendants(&path, descendants)?;
+        }
+    }
+    Ok(())
+}
+
+/// Retrieve the paths of all cgroups below the cgroup at `path`, with
+/// each cgroup preceding its own descendants.
+///
+/// An error of kind [`ErrorKind::InvalidInput`][crate::ErrorKind::InvalidInput]
+/// is reported for cgroups of legacy (v1) hierarchies.
+pub fn descendants<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let () = ensure_cgroup2(open(path)?.as_fd())?;
+
+    let mut descendants = Vec::new();
+    let () = collect_descendants(path, &mut descendants)?;
+    Ok(descendants)
+}
+
+/// A type identifying a cgroup, either by file descriptor or by the path
+/// of its directory in the cgroup file system.
+pub trait IntoCgroupFd {
+    /// Retrieve a file descriptor referring to the identified cgroup.
+    ///
+    /// File descriptors get duplicated, paths get opened.
+    fn into_cgroup_fd(self) -> Result<OwnedFd>;
+}
+
+impl IntoCgroupFd for BorrowedFd<'_> {
+    fn into_cgroup_fd(self) -> Result<OwnedFd> {
+        self.try_clone_to_owned().map_err(Error::from)
+    }
+}
+
+impl IntoCgroupFd for &OwnedFd {
+    fn into_cgroup_fd(self) -> Result<OwnedFd> {
+        self.try_clone().map_err(Error::from)
+    }
+}
+
+impl IntoCgroupFd for &File {
+    fn into_cgroup_fd(self) -> Result<OwnedFd> {
+        self.try_clone().map(OwnedFd::from).map_err(Error::from)
+    }
+}
+
+impl IntoCgroupFd for &Path {
+    fn into_cgroup_fd(self) -> Result<OwnedFd> {
+        open(self)
+    }
+}
+
+impl IntoCgroupFd for &PathBuf {
+    fn into_cgroup_fd(self) -> Result<OwnedFd> {
+        open(self)
+    }
+}
+
+impl IntoCgroupFd for &str {
+    fn into_cgroup_fd(self) -> Result<OwnedFd> {
+        open(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process;
+
+    use crate::ErrorKind;
+
+    /// Check that we can resolve cgroups by path and file descriptor.
+    #[test]
+    fn test_sudo_cgroup_resolution() {
+        let fd = "/sys/fs/cgroup".into_cgroup_fd().unwrap();
+        let _fd = fd.as_fd().into_cgroup_fd().unwrap();
+        let _fd = (&fd).into_cgroup_fd().unwrap();
+
+        let err = Path::new("/sys/fs/cgroup/does-not-exist")
+            .into_cgroup_fd()
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.to_string().contains("does-not-exist"), "{err}");
+    }
+
+    /// Check that we can extract a process' cgroup from its
+    /// `/proc/<pid>/cgroup` file.
+    #[test]
+    fn proc_cgroup_parsing() {
+        let content =
+            "4:memory:/user.slice\n1:name=systemd:/init.scope\n0::/system.slice/foo.service\n";
+        assert_eq!(parse_proc_cgroup(content), Some("system.slice/foo.service"));
+        assert_eq!(parse_proc_cgroup("0::/\n"), Some(""));
+        assert_eq!(parse_proc_cgroup("4:memory:/user.slice\n"), None);
+    }
+
+    /// Check that cgroups removed while collecting descendants are
+    /// skipped.
+    #[test]
+    fn removed_cgroup_skipping() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("removed");
+        let mut descendants = Vec::new();
+        let () = collect_descendants(&path, &mut descendants).unwrap();
+        assert!(descendants.is_empty());
+    }
+
+    /// Check that we can look up the cgroup of a process along with its ID
+    /// and descendants.
+    #[test]
+    fn test_sudo_cgroup_lookup() {
+        let root = root().unwrap();
+        let path = of_pid(process::id()).unwrap();
+        assert!(path.starts_with(&root), "{}", path.display());
+
+        let cgroup_id = id(&path).unwrap();
+        assert_ne!(cgroup_id, 0);
+        let fd = open(&path).unwrap();
+        assert_eq!(id(fd.as_fd()).unwrap(), cgroup_id);
+
+        let children = descendants(&root).unwrap();
+        assert!(children.iter().all(|child| child.starts_with(&root)));
+        assert!(path == root || children.contains(&path));
+        if path != root {
+            assert_ne!(id(&root).unwrap(), cgroup_id);
+        }
+
+        let err = id("/proc").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let err = descendants("/proc").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
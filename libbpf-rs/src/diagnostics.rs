@@ -0,0 +1,303 @@
+use std::mem;
+use std::os::unix::io::AsFd as _;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::print::CapturedMessage;
+use crate::query::ProgInfoQueryOptions;
+use crate::query::ProgramInfo;
+use crate::Object;
+use crate::PrintLevel;
+
+/// The kind of a [`LoadEvent`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadEventKind {
+    /// A CO-RE relocation of the program `prog` was processed, e.g., a
+    /// candidate type was matched or an instruction was patched.
+    CoreRelocation {
+        /// The name of the program.
+        prog: String,
+        /// The index of the relocation within the program.
+        index: usize,
+    },
+    /// The map `map` was created.
+    MapCreated {
+        /// The name of the map.
+        map: String,
+    },
+    /// Any other event concerning the map `map`.
+    Map {
+        /// The name of the map.
+        map: String,
+    },
+    /// Any other event concerning the program `prog`.
+    Program {
+        /// The name of the program.
+        prog: String,
+    },
+    /// libbpf worked around a feature not supported by the running kernel,
+    /// e.g., by not loading BTF or by poisoning instructions referencing
+    /// maps or kfuncs that are unavailable.
+    FeatureFallback,
+    /// Any other event.
+    Other,
+}
+
+/// A single event reported by libbpf while loading an object.
+#[derive(Clone, Debug)]
+pub struct LoadEvent {
+    /// The time elapsed since loading started.
+    pub elapsed: Duration,
+    /// The level libbpf reported the event at.
+    pub level: PrintLevel,
+    /// The kind of the event.
+    pub kind: LoadEventKind,
+    /// The message libbpf reported, without `libbpf: ` prefix and trailing
+    /// newline.
+    pub message: String,
+}
+
+/// Timing information about the loading of a single program.
+#[derive(Clone, Debug)]
+pub struct ProgramLoad {
+    /// The name of the program.
+    pub name: String,
+    /// The time elapsed since loading started when the kernel received the
+    /// program.
+    pub started: Duration,
+    /// The time it took to load the program, including verification.
+    ///
+    /// As libbpf loads programs one after the other, this is approximated
+    /// as the time until the next program was received by the kernel (or
+    /// loading finished, for the last one).
+    pub duration: Duration,
+}
+
+/// A structured report of the loading of an object, as created by
+/// [`OpenObject::load_with_report`][crate::OpenObject::load_with_report].
+///
+/// The report is based on the messages libbpf emits, at all levels, on
+/// the thread loading the object.
+#[derive(Clone, Debug, Default)]
+pub struct LoadReport {
+    /// The time it took to load the object.
+    pub duration: Duration,
+    /// The events libbpf reported, in order.
+    pub events: Vec<LoadEvent>,
+    /// Timing information about programs, in load order. Only available if
+    /// loading succeeded.
+    pub programs: Vec<ProgramLoad>,
+}
+
+impl LoadReport {
+    /// Create a report from the messages captured while loading.
+    pub(crate) fn new(start: Instant, duration: Duration, msgs: Vec<CapturedMessage>) -> Self {
+        let events = msgs
+            .into_iter()
+            .map(|(time, level, msg)| {
+                let msg = msg.strip_prefix("libbpf: ").unwrap_or(&msg);
+                let msg = msg.trim_end().to_string();
+                LoadEvent {
+                    elapsed: time.saturating_duration_since(start),
+                    level,
+                    kind: classify(&msg),
+                    message: msg,
+                }
+            })
+            .collect();
+
+        Self {
+            duration,
+            events,
+            programs: Vec::new(),
+        }
+    }
+
+    /// Fill in timing information for the programs of the loaded object
+    /// `obj`, based on boot time clock readings `start` and `end` taken
+    /// before and after loading it.
+    pub(crate) fn collect_programs(&mut self, obj: &Object, start: Duration, end: Duration) {
+        let opts = ProgInfoQueryOptions::default();
+        let mut programs = obj
+            .progs_iter()
+            .filter_map(|prog| {
+                let info = ProgramInfo::load_from_fd(prog.as_fd(), &opts).ok()?;
+                Some(ProgramLoad {
+                    name: prog.name().to_string(),
+                    started: info.load_time.saturating_sub(start),
+                    duration: Duration::ZERO,
+                })
+            })
+            .collect::<Vec<_>>();
+        programs.sort_by_key(|prog| prog.started);
+
+        let end = end.saturating_sub(start);
+        let mut next_started = end;
+        for prog in programs.iter_mut().rev() {
+            prog.duration = next_started.saturating_sub(prog.started);
+            next_started = prog.started;
+        }
+        self.programs = programs;
+    }
+
+    /// Get the events concerning the CO-RE relocations of program `prog`.
+    pub fn core_relocations<'report>(
+        &'report self,
+        prog: &'report str,
+    ) -> impl Iterator<Item = &'report LoadEvent> {
+        self.events.iter().filter(move |event| {
+            matches!(&event.kind, LoadEventKind::CoreRelocation { prog: p, .. } if p == prog)
+        })
+    }
+}
+
+/// Read the boot time clock, which the kernel uses to timestamp loaded
+/// programs.
+pub(crate) fn boot_time() -> Duration {
+    let mut ts = unsafe { mem::zeroed::<libc::timespec>() };
+    let _ret = unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut ts) };
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+/// Split a message of the form `<kind> '<name>': <rest>` into name and rest.
+fn split_named<'msg>(msg: &'msg str, kind: &str) -> Option<(&'msg str, &'msg str)> {
+    let msg = msg.strip_prefix(kind)?.strip_prefix(" '")?;
+    let (name, rest) = msg.split_once("': ")?;
+    Some((name, rest))
+}
+
+/// Find the program that failed to load, along with the log of the
+/// verifier rejecting it (if available), in the messages captured while
+/// loading an object.
+pub(crate) fn failed_program(msgs: &[CapturedMessage]) -> Option<(&str, Option<&str>)> {
+    let mut failed = None;
+    for (_time, _level, msg) in msgs {
+        let msg = msg.strip_prefix("libbpf: ").unwrap_or(msg);
+        let Some((prog, rest)) = split_named(msg, "prog") else {
+            continue;
+        };
+
+        match failed {
+            None if rest.starts_with("BPF program load failed") => failed = Some((prog, None)),
+            Some((failed_prog, None)) if failed_prog == prog => {
+                if let Some(log) = rest.strip_prefix("-- BEGIN PROG LOAD LOG --\n") {
+                    let log = log.split("-- END PROG LOAD LOG --").next().unwrap_or(log);
+                    failed = Some((prog, Some(log)));
+                }
+            }
+            _ => (),
+        }
+    }
+    failed
+}
+
+/// Classify a libbpf message.
+fn classify(msg: &str) -> LoadEventKind {
+    const FALLBACK_PATTERNS: [&str; 3] = [
+        "BTF is optional, ignoring",
+        "Kernel doesn't support BTF",
+        ": poisoning insn #",
+    ];
+
+    if FALLBACK_PATTERNS
+        .iter()
+        .any(|pattern| msg.contains(pattern))
+    {
+        return LoadEventKind::FeatureFallback;
+    }
+
+    if let Some((prog, rest)) = split_named(msg, "prog") {
+        let index = rest
+            .strip_prefix("relo #")
+            .and_then(|rest| rest.split_once(':'))
+            .and_then(|(index, _)| index.parse().ok());
+        match index {
+            Some(index) => LoadEventKind::CoreRelocation {
+                prog: prog.to_string(),
+                index,
+            },
+            None => LoadEventKind::Program {
+                prog: prog.to_string(),
+            },
+        }
+    } else if let Some((map, rest)) = split_named(msg, "map") {
+        if rest.starts_with("created successfully") {
+            LoadEventKind::MapCreated {
+                map: map.to_string(),
+            }
+        } else {
+            LoadEventKind::Map {
+                map: map.to_string(),
+            }
+        }
+    } else {
+        LoadEventKind::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that we classify libbpf messages correctly.
+    #[test]
+    fn message_classification() {
+        assert_eq!(
+            classify("prog 'handle__sched_wakeup': relo #3: patched insn #5 (LDX/ST/STX) off 1324 -> 1324"),
+            LoadEventKind::CoreRelocation {
+                prog: "handle__sched_wakeup".to_string(),
+                index: 3,
+            }
+        );
+        assert_eq!(
+            classify("prog 'foo': BPF program load failed: Permission denied"),
+            LoadEventKind::Program {
+                prog: "foo".to_string(),
+            }
+        );
+        assert_eq!(
+            classify("map 'start': created successfully, fd=4"),
+            LoadEventKind::MapCreated {
+                map: "start".to_string(),
+            }
+        );
+        assert_eq!(
+            classify("map 'start': skipping creation (preset fd=4)"),
+            LoadEventKind::Map {
+                map: "start".to_string(),
+            }
+        );
+        assert_eq!(
+            classify("prog 'foo': relo #1: poisoning insn #2 that loads map #0 'bar'"),
+            LoadEventKind::FeatureFallback
+        );
+        assert_eq!(
+            classify("Error loading .BTF into kernel: -22. BTF is optional, ignoring."),
+            LoadEventKind::FeatureFallback
+        );
+        assert_eq!(classify("something else"), LoadEventKind::Other);
+    }
+
+    /// Check that we can find the program that failed to load and its
+    /// verifier log.
+    #[test]
+    fn failed_program_detection() {
+        let msg = |msg: &str| (Instant::now(), PrintLevel::Warn, msg.to_string());
+        let mut msgs = vec![
+            msg("libbpf: prog 'bar': -- BEGIN PROG LOAD LOG --\nok\n-- END PROG LOAD LOG --\n"),
+            msg("libbpf: prog 'foo': BPF program load failed: Permission denied\n"),
+        ];
+        assert_eq!(failed_program(&msgs), Some(("foo", None)));
+
+        let () = msgs.push(msg(
+            "libbpf: prog 'foo': -- BEGIN PROG LOAD LOG --\n0: (bf) r0 = r2\nR2 !read_ok\n-- END PROG LOAD LOG --\n",
+        ));
+        let () = msgs.push(msg("libbpf: prog 'foo': failed to load: -13\n"));
+        assert_eq!(
+            failed_program(&msgs),
+            Some(("foo", Some("0: (bf) r0 = r2\nR2 !read_ok\n")))
+        );
+
+        assert_eq!(failed_program(&msgs[..1]), None);
+    }
+}
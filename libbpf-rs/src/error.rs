@@ -92,6 +92,34 @@ impl IntoCowStr for String {
     }
 }
 
+/// An invocation of a libbpf function that failed, as retrieved via
+/// [`Error::libbpf_call`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LibbpfCall {
+    function: &'static str,
+    name: Box<str>,
+}
+
+impl LibbpfCall {
+    /// The name of the libbpf function, e.g., `bpf_program__attach_kprobe`.
+    #[inline]
+    pub fn function(&self) -> &str {
+        self.function
+    }
+
+    /// The name of the object, program, or map the function operated on.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Display for LibbpfCall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}({})", self.function, self.name)
+    }
+}
+
 // TODO: We may want to support optionally storing a backtrace in
 //       terminal variants.
 enum ErrorImpl {
@@ -110,29 +138,114 @@ enum ErrorImpl {
         context: &'static str,
         source: Box<ErrorImpl>,
     },
+    /// The libbpf function invocation that failed with `source`, acting
+    /// as context. The immediate `source` is included in the `Display`
+    /// representation of this layer, i.e., it is not reported separately.
+    Call {
+        call: Box<LibbpfCall>,
+        source: Box<ErrorImpl>,
+    },
+    /// The output libbpf emitted while the operation failing with `source`
+    /// was performed. This layer is transparent, i.e., it is represented
+    /// as `source` (except for being included in the `Debug`
+    /// representation).
+    Output {
+        output: Box<str>,
+        source: Box<ErrorImpl>,
+    },
+    /// A classification of `source` derived from the context in which it
+    /// occurred, overriding the one based on `source` itself. This layer
+    /// is transparent as well.
+    Kind {
+        kind: ErrorKind,
+        source: Box<ErrorImpl>,
+    },
+    /// The log of the BPF verifier rejecting a program, which caused
+    /// `source`. This layer is transparent as well.
+    VerifierLog {
+        log: Box<str>,
+        source: Box<ErrorImpl>,
+    },
 }
 
 impl ErrorImpl {
     fn kind(&self) -> ErrorKind {
         match self {
-            Self::Io(error) => match error.kind() {
-                io::ErrorKind::NotFound => ErrorKind::NotFound,
-                io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
-                io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
-                io::ErrorKind::WouldBlock => ErrorKind::WouldBlock,
-                io::ErrorKind::InvalidInput => ErrorKind::InvalidInput,
-                io::ErrorKind::InvalidData => ErrorKind::InvalidData,
-                io::ErrorKind::TimedOut => ErrorKind::TimedOut,
-                io::ErrorKind::WriteZero => ErrorKind::WriteZero,
-                io::ErrorKind::Interrupted => ErrorKind::Interrupted,
-                io::ErrorKind::Unsupported => ErrorKind::Unsupported,
-                io::ErrorKind::UnexpectedEof => ErrorKind::UnexpectedEof,
-                io::ErrorKind::OutOfMemory => ErrorKind::OutOfMemory,
-                _ => ErrorKind::Other,
-            },
-            Self::ContextOwned { source, .. } | Self::ContextStatic { source, .. } => {
-                source.deref().kind()
+            Self::Io(error) => {
+                if let Some(kind) = error.raw_os_error().and_then(errno_kind) {
+                    return kind;
+                }
+
+                match error.kind() {
+                    io::ErrorKind::NotFound => ErrorKind::NotFound,
+                    io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+                    io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+                    io::ErrorKind::WouldBlock => ErrorKind::WouldBlock,
+                    io::ErrorKind::InvalidInput => ErrorKind::InvalidInput,
+                    io::ErrorKind::InvalidData => ErrorKind::InvalidData,
+                    io::ErrorKind::TimedOut => ErrorKind::TimedOut,
+                    io::ErrorKind::WriteZero => ErrorKind::WriteZero,
+                    io::ErrorKind::Interrupted => ErrorKind::Interrupted,
+                    io::ErrorKind::Unsupported => ErrorKind::Unsupported,
+                    io::ErrorKind::UnexpectedEof => ErrorKind::UnexpectedEof,
+                    io::ErrorKind::OutOfMemory => ErrorKind::OutOfMemory,
+                    _ => ErrorKind::Other,
+                }
             }
+            Self::ContextOwned { source, .. }
+            | Self::ContextStatic { source, .. }
+            | Self::Call { source, .. }
+            | Self::Output { source, .. }
+            | Self::VerifierLog { source, .. } => source.deref().kind(),
+            Self::Kind { kind, .. } => *kind,
+        }
+    }
+
+    fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            Self::Io(error) => error.raw_os_error(),
+            Self::ContextOwned { source, .. }
+            | Self::ContextStatic { source, .. }
+            | Self::Call { source, .. }
+            | Self::Output { source, .. }
+            | Self::Kind { source, .. }
+            | Self::VerifierLog { source, .. } => source.raw_os_error(),
+        }
+    }
+
+    fn libbpf_output(&self) -> Option<&str> {
+        match self {
+            Self::Io(..) => None,
+            Self::ContextOwned { source, .. }
+            | Self::ContextStatic { source, .. }
+            | Self::Call { source, .. }
+            | Self::Kind { source, .. }
+            | Self::VerifierLog { source, .. } => source.libbpf_output(),
+            Self::Output { output, .. } => Some(output),
+        }
+    }
+
+    fn verifier_log(&self) -> Option<&str> {
+        match self {
+            Self::Io(..) => None,
+            Self::ContextOwned { source, .. }
+            | Self::ContextStatic { source, .. }
+            | Self::Call { source, .. }
+            | Self::Output { source, .. }
+            | Self::Kind { source, .. } => source.verifier_log(),
+            Self::VerifierLog { log, .. } => Some(log),
+        }
+    }
+
+    fn libbpf_call(&self) -> Option<&LibbpfCall> {
+        match self {
+            Self::Io(..) => None,
+            Self::ContextOwned { source, .. }
+            | Self::ContextStatic { source, .. }
+            | Self::Output { source, .. }
+            | Self::Kind { source, .. }
+            | Self::VerifierLog { source, .. } => source.libbpf_call(),
+            Self::Call { call, .. } => Some(call),
         }
     }
 
@@ -166,6 +279,22 @@ impl Debug for ErrorImpl {
                     dbg = f.debug_tuple(stringify!(ContextStatic));
                     dbg.field(context)
                 }
+                Self::Call { call, .. } => {
+                    dbg = f.debug_tuple(stringify!(Call));
+                    dbg.field(call)
+                }
+                Self::Output { output, .. } => {
+                    dbg = f.debug_tuple(stringify!(Output));
+                    dbg.field(output)
+                }
+                Self::Kind { kind, .. } => {
+                    dbg = f.debug_tuple(stringify!(Kind));
+                    dbg.field(kind)
+                }
+                Self::VerifierLog { log, .. } => {
+                    dbg = f.debug_tuple(stringify!(VerifierLog));
+                    dbg.field(log)
+                }
             }
             .finish()
         } else {
@@ -173,6 +302,10 @@ impl Debug for ErrorImpl {
                 Self::Io(error) => write!(f, "Error: {error}")?,
                 Self::ContextOwned { context, .. } => write!(f, "Error: {context}")?,
                 Self::ContextStatic { context, .. } => write!(f, "Error: {context}")?,
+                Self::Call { call, source } => write!(f, "Error: {call} failed: {source}")?,
+                Self::Output { source, .. }
+                | Self::Kind { source, .. }
+                | Self::VerifierLog { source, .. } => write!(f, "Error: {source}")?,
             };
 
             if let Some(source) = self.source() {
@@ -184,6 +317,20 @@ impl Debug for ErrorImpl {
                     error = err.source();
                 }
             }
+
+            // The verifier log is part of libbpf's output, so only print
+            // it separately if the latter is not available.
+            if let Some(output) = self.libbpf_output() {
+                let () = f.write_str("\n\nlibbpf output:")?;
+                for line in output.lines() {
+                    let () = write!(f, "\n    {line}")?;
+                }
+            } else if let Some(log) = self.verifier_log() {
+                let () = f.write_str("\n\nverifier log:")?;
+                for line in log.lines() {
+                    let () = write!(f, "\n    {line}")?;
+                }
+            }
             Ok(())
         }
     }
@@ -195,6 +342,10 @@ impl Display for ErrorImpl {
             Self::Io(error) => Display::fmt(error, f)?,
             Self::ContextOwned { context, .. } => Display::fmt(context, f)?,
             Self::ContextStatic { context, .. } => Display::fmt(context, f)?,
+            Self::Call { call, source } => write!(f, "{call} failed: {source}")?,
+            Self::Output { source, .. }
+            | Self::Kind { source, .. }
+            | Self::VerifierLog { source, .. } => return Display::fmt(source, f),
         };
 
         if f.alternate() {
@@ -213,10 +364,33 @@ impl error::Error for ErrorImpl {
         match self {
             Self::Io(error) => error.source(),
             Self::ContextOwned { source, .. } | Self::ContextStatic { source, .. } => Some(source),
+            // The immediate source is part of the call's `Display`
+            // representation.
+            Self::Call { source, .. }
+            | Self::Output { source, .. }
+            | Self::Kind { source, .. }
+            | Self::VerifierLog { source, .. } => source.source(),
         }
     }
 }
 
+/// `ENOTSUPP`, a kernel internal error code that nevertheless is reported
+/// by some BPF operations.
+const ENOTSUPP: i32 = 524;
+
+/// Classify OS error codes with a BPF specific meaning that
+/// [`io::ErrorKind`] does not capture.
+fn errno_kind(errno: i32) -> Option<ErrorKind> {
+    match errno {
+        // The kernel reports `ESRCH` for non-existent BPF objects and
+        // symbols, and `ENODEV` for non-existent devices.
+        libc::ESRCH | libc::ENODEV => Some(ErrorKind::NotFound),
+        libc::EBUSY => Some(ErrorKind::ResourceBusy),
+        ENOTSUPP => Some(ErrorKind::Unsupported),
+        _ => None,
+    }
+}
+
 /// An enum providing a rough classification of errors.
 ///
 /// The variants of this type partly resemble those of
@@ -247,7 +421,8 @@ pub enum ErrorKind {
     ///
     /// Interrupted operations can typically be retried.
     Interrupted,
-    /// This operation is unsupported on this platform.
+    /// This operation is unsupported on this platform, e.g., because
+    /// the kernel lacks a required feature.
     Unsupported,
     /// An error returned when an operation could not be completed
     /// because an "end of file" was reached prematurely.
@@ -255,6 +430,14 @@ pub enum ErrorKind {
     /// An operation could not be completed, because it failed
     /// to allocate enough memory.
     OutOfMemory,
+    /// A resource is busy, e.g., because it is already in use and cannot
+    /// be shared.
+    ResourceBusy,
+    /// The kernel's BPF verifier refused to load a program.
+    ///
+    /// The verifier log, which is part of libbpf's output, typically
+    /// explains the reason.
+    VerifierRejected,
     /// A custom error that does not fall under any other I/O error
     /// kind.
     Other,
@@ -354,6 +537,14 @@ impl Error {
         Self::with_io_error(io::ErrorKind::InvalidData, error)
     }
 
+    #[inline]
+    pub(crate) fn with_invalid_input<E>(error: E) -> Self
+    where
+        E: ToString,
+    {
+        Self::with_io_error(io::ErrorKind::InvalidInput, error)
+    }
+
     /// Retrieve a rough error classification in the form of an
     /// [`ErrorKind`].
     #[inline]
@@ -361,6 +552,95 @@ impl Error {
         self.error.kind()
     }
 
+    /// Retrieve the OS error code (typically `errno`) this error
+    /// originated from, if any.
+    ///
+    /// Prefer matching on [`kind`][Self::kind] where possible.
+    #[inline]
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.error.raw_os_error()
+    }
+
+    /// Classify this `Error` as being of kind `kind`, irrespective of its
+    /// source.
+    pub(crate) fn with_kind(self, kind: ErrorKind) -> Self {
+        Self {
+            error: Box::new(ErrorImpl::Kind {
+                kind,
+                source: self.error,
+            }),
+        }
+    }
+
+    /// Retrieve the libbpf output captured while the operation that failed
+    /// with this error was performed, if any.
+    ///
+    /// Output is only captured for operations run via
+    /// [`capture_output`][crate::capture_output].
+    #[inline]
+    pub fn libbpf_output(&self) -> Option<&str> {
+        self.error.libbpf_output()
+    }
+
+    /// Retrieve the libbpf function invocation that failed, if known.
+    ///
+    /// The invocation is also reported as part of the error's
+    /// [`Display`] representation, e.g., as
+    /// `bpf_program__attach_kprobe(handle_exec) failed: Operation not
+    /// permitted (os error 1)`.
+    #[inline]
+    pub fn libbpf_call(&self) -> Option<&LibbpfCall> {
+        self.error.libbpf_call()
+    }
+
+    /// Attribute this `Error` to an invocation of the libbpf function
+    /// `function` on the object, program, or map `name`.
+    pub(crate) fn with_libbpf_call(self, function: &'static str, name: &str) -> Self {
+        let call = LibbpfCall {
+            function,
+            name: name.into(),
+        };
+        Self {
+            error: Box::new(ErrorImpl::Call {
+                call: Box::new(call),
+                source: self.error,
+            }),
+        }
+    }
+
+    /// Retrieve the log of the BPF verifier, if this error was caused by
+    /// the verifier rejecting a program.
+    ///
+    /// The log is available for failures to load an
+    /// [`OpenObject`][crate::OpenObject] (or a skeleton). In that case,
+    /// the error's context identifies the program that got rejected.
+    #[inline]
+    pub fn verifier_log(&self) -> Option<&str> {
+        self.error.verifier_log()
+    }
+
+    /// Attach the log of the BPF verifier rejecting a program, which
+    /// caused this `Error`.
+    pub(crate) fn with_verifier_log(self, log: String) -> Self {
+        Self {
+            error: Box::new(ErrorImpl::VerifierLog {
+                log: log.into_boxed_str(),
+                source: self.error,
+            }),
+        }
+    }
+
+    /// Attach the libbpf output emitted while performing the operation
+    /// that failed with this `Error`.
+    pub(crate) fn with_libbpf_output(self, output: String) -> Self {
+        Self {
+            error: Box::new(ErrorImpl::Output {
+                output: output.into_boxed_str(),
+                source: self.error,
+            }),
+        }
+    }
+
     /// Layer the provided context on top of this `Error`, creating a
     /// new one in the process.
     fn layer_context(self, context: Cow<'static, Str>) -> Self {
@@ -497,6 +777,21 @@ impl ErrorExt for io::Error {
     }
 }
 
+/// A trait for attributing errors to the libbpf function invocation
+/// causing them.
+pub(crate) trait LibbpfCallExt {
+    /// Attribute the error to an invocation of the libbpf function
+    /// `function` on the object, program, or map `name`.
+    fn libbpf_call(self, function: &'static str, name: &str) -> Self;
+}
+
+impl<T> LibbpfCallExt for Result<T> {
+    #[inline]
+    fn libbpf_call(self, function: &'static str, name: &str) -> Self {
+        self.map_err(|err| err.with_libbpf_call(function, name))
+    }
+}
+
 /// A trait providing conversion shortcuts for creating `Error`
 /// instances.
 pub trait IntoError<T>: private::Sealed
@@ -610,4 +905,126 @@ Caused by:
         assert_eq!(format!("{err:?}"), expected);
         assert_ne!(format!("{err:#?}"), "");
     }
+
+    /// Check that errors are classified as expected.
+    #[test]
+    fn error_kinds() {
+        let err = Error::from_raw_os_error(libc::ENOENT);
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+        let err = Error::from_raw_os_error(libc::ESRCH);
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        let err = Error::from_raw_os_error(libc::EBUSY);
+        assert_eq!(err.kind(), ErrorKind::ResourceBusy);
+        let err = Error::from_raw_os_error(ENOTSUPP);
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let err = Error::from_raw_os_error(libc::E2BIG);
+        assert_eq!(err.kind(), ErrorKind::Other);
+
+        let err = Error::with_invalid_input("bogus");
+        assert_eq!(err.raw_os_error(), None);
+
+        let err = Error::from_raw_os_error(libc::EACCES);
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        let err = err
+            .with_kind(ErrorKind::VerifierRejected)
+            .context("failed to load object");
+        assert_eq!(err.kind(), ErrorKind::VerifierRejected);
+        assert_eq!(err.raw_os_error(), Some(libc::EACCES));
+        assert_eq!(
+            format!("{err:#}"),
+            "failed to load object: Permission denied (os error 13)"
+        );
+    }
+
+    /// Check that the libbpf call an error is attributed to is retrievable
+    /// and formatted as expected.
+    #[test]
+    fn error_libbpf_call() {
+        let err = Error::from_raw_os_error(libc::EPERM);
+        assert_eq!(err.libbpf_call(), None);
+
+        let err = err.with_libbpf_call("bpf_program__attach_kprobe", "handle_exec");
+        let call = err.libbpf_call().unwrap();
+        assert_eq!(call.function(), "bpf_program__attach_kprobe");
+        assert_eq!(call.name(), "handle_exec");
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+        let expected =
+            "bpf_program__attach_kprobe(handle_exec) failed: Operation not permitted (os error 1)";
+        assert_eq!(format!("{err}"), expected);
+        assert_eq!(format!("{err:#}"), expected);
+        assert_eq!(format!("{err:?}"), format!("Error: {expected}"));
+
+        let err = err.context("failed to attach");
+        assert_eq!(err.libbpf_call().unwrap().name(), "handle_exec");
+        assert_eq!(format!("{err:#}"), format!("failed to attach: {expected}"));
+        let expected = format!("Error: failed to attach\n\nCaused by:\n    {expected}");
+        assert_eq!(format!("{err:?}"), expected);
+    }
+
+    /// Check that a verifier log attached to an error is retrievable and
+    /// formatted as expected.
+    #[test]
+    fn error_verifier_log() {
+        let err = Error::from_raw_os_error(libc::EACCES)
+            .with_verifier_log("0: (bf) r0 = r2\nR2 !read_ok\n".to_string())
+            .context("failed to load BPF program `foo`");
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(err.verifier_log(), Some("0: (bf) r0 = r2\nR2 !read_ok\n"));
+        assert_eq!(err.libbpf_output(), None);
+
+        let expected = r#"Error: failed to load BPF program `foo`
+
+Caused by:
+    Permission denied (os error 13)
+
+verifier log:
+    0: (bf) r0 = r2
+    R2 !read_ok"#;
+        assert_eq!(format!("{err:?}"), expected);
+
+        // The log is part of libbpf's output, if present.
+        let err = err.with_libbpf_output("libbpf: output\n".to_string());
+        assert!(err.verifier_log().is_some());
+        assert!(!format!("{err:?}").contains("verifier log:"));
+    }
+
+    /// Check that libbpf output attached to an error is retrievable and
+    /// formatted as expected.
+    #[test]
+    fn error_libbpf_output() {
+        let err = Error::from(io::Error::new(io::ErrorKind::NotFound, "no such map"));
+        assert_eq!(err.libbpf_output(), None);
+
+        let err = err.with_libbpf_output("libbpf: first\nlibbpf: second\n".to_string());
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(err.libbpf_output(), Some("libbpf: first\nlibbpf: second\n"));
+        assert_eq!(format!("{err}"), "no such map");
+        assert_eq!(format!("{err:#}"), "no such map");
+        let src = err.source();
+        assert!(src.is_none(), "{src:?}");
+
+        let expected = r#"Error: no such map
+
+libbpf output:
+    libbpf: first
+    libbpf: second"#;
+        assert_eq!(format!("{err:?}"), expected);
+
+        let err = err.context("failed to load");
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.libbpf_output().is_some());
+        assert_eq!(format!("{err:#}"), "failed to load: no such map");
+
+        let expected = r#"Error: failed to load
+
+Caused by:
+    no such map
+
+libbpf output:
+    libbpf: first
+    libbpf: second"#;
+        assert_eq!(format!("{err:?}"), expected);
+    }
 }
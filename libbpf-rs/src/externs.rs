@@ -0,0 +1,455 @@
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::btf::symbols::find_in_kallsyms;
+use crate::btf::symbols::find_in_kernel_btf;
+use crate::btf::symbols::read_kallsyms;
+use crate::btf::symbols::KALLSYMS;
+use crate::btf::types::DataSec;
+use crate::btf::types::Func;
+use crate::btf::types::Var;
+use crate::btf::BtfKind;
+use crate::btf::BtfType;
+use crate::btf::ReferencesType as _;
+use crate::btf::TypeId;
+use crate::Btf;
+use crate::Error;
+use crate::Result;
+
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ELFDATA2MSB: u8 = 2;
+const SHT_SYMTAB: u32 = 2;
+const SHN_UNDEF: u16 = 0;
+const STB_WEAK: u8 = 2;
+const STT_NOTYPE: u8 = 0;
+
+/// The size of a symbol table entry in a 64 bit ELF file.
+const SYM_SIZE: usize = 24;
+
+/// The kind of an [`Extern`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExternKind {
+    /// A kernel configuration value, declared with `__kconfig`.
+    Kconfig,
+    /// A kernel variable, declared with `__ksym`.
+    Ksym,
+    /// A kernel function (kfunc), declared with `__ksym`.
+    Kfunc,
+}
+
+/// What an [`Extern`] was resolved to when loading its object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExternResolution {
+    /// The extern is weak and the kernel does not provide it, so that it
+    /// reads as zero.
+    Unresolved,
+    /// The address of a kernel variable declared without type
+    /// (`extern const void sym __ksym`), as listed in `/proc/kallsyms`.
+    Address(u64),
+    /// The kernel BTF type a typed kernel variable or a kfunc resolved
+    /// to.
+    BtfId {
+        /// The kernel module providing the type, or `None` if it is
+        /// provided by the kernel itself.
+        module: Option<String>,
+        /// The id of the type.
+        type_id: TypeId,
+    },
+}
+
+/// The externs of an object, collected when first requested.
+#[derive(Debug, Default)]
+pub(crate) struct Externs {
+    /// The file the object was opened from, if any.
+    path: Option<PathBuf>,
+    /// The collected externs or the reason they could not be collected.
+    externs: OnceCell<Result<Vec<Extern>, String>>,
+}
+
+impl Externs {
+    /// Create an `Externs` object collecting the externs of the ELF file
+    /// at `path` once they are requested.
+    pub(crate) fn from_file(path: &Path) -> Self {
+        Self {
+            path: Some(path.to_path_buf()),
+            externs: OnceCell::new(),
+        }
+    }
+
+    /// Create an `Externs` object for the ELF file `elf`, which is only
+    /// borrowed and so has to be parsed right away.
+    pub(crate) fn from_memory(elf: &[u8]) -> Self {
+        Self {
+            path: None,
+            externs: OnceCell::from(Extern::collect(elf).map_err(|err| format!("{err:#}"))),
+        }
+    }
+
+    /// Retrieve the externs, collecting them if necessary.
+    pub(crate) fn get(&self) -> Result<&[Extern]> {
+        self.externs
+            .get_or_init(|| {
+                let externs = match &self.path {
+                    Some(path) => fs::read(path)
+                        .map_err(Error::from)
+                        .and_then(|elf| Extern::collect(&elf)),
+                    None => Ok(Vec::new()),
+                };
+                externs.map_err(|err| format!("{err:#}"))
+            })
+            .as_deref()
+            .map_err(|err| {
+                Error::with_invalid_data(format!("failed to collect object externs: {err}"))
+            })
+    }
+}
+
+/// An extern symbol referenced by a BPF object, which libbpf resolves
+/// against the running kernel when the object is loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Extern {
+    name: String,
+    kind: ExternKind,
+    weak: bool,
+    /// Whether the extern is a kernel variable declared without type.
+    typeless: bool,
+    resolution: Option<ExternResolution>,
+}
+
+impl Extern {
+    /// Collect the externs declared in the ELF file `elf`.
+    ///
+    /// libbpf rewrites the `.ksyms` section's BTF when opening an object,
+    /// so externs are taken from the unmodified BTF of the file instead.
+    pub(crate) fn collect(elf: &[u8]) -> Result<Vec<Self>> {
+        let elf = Elf::parse(elf)?;
+        let btf = match elf.section_data(".BTF")? {
+            Some(data) => Btf::from_raw_data(data)?,
+            // libbpf refuses to open objects with externs but without BTF.
+            None => return Ok(Vec::new()),
+        };
+        let weak = elf.weak_externs()?;
+
+        let mut externs = Vec::new();
+        for datasec in btf.type_by_kind::<DataSec<'_>>() {
+            let kconfig = match datasec.name().map(CStr::to_bytes) {
+                Some(b".kconfig") => true,
+                Some(b".ksyms") => false,
+                _ => continue,
+            };
+
+            for info in datasec.iter() {
+                let ty = btf
+                    .type_by_id::<BtfType<'_>>(info.ty)
+                    .ok_or_else(|| Error::with_invalid_data("invalid extern BTF type id"))?;
+                let name = ty
+                    .name()
+                    .ok_or_else(|| Error::with_invalid_data("extern has no name"))?
+                    .to_str()
+                    .map_err(Error::with_invalid_data)?
+                    .to_string();
+
+                let (kind, typeless) = if let Ok(var) = Var::try_from(ty) {
+                    let kind = if kconfig {
+                        ExternKind::Kconfig
+                    } else {
+                        ExternKind::Ksym
+                    };
+                    let typeless =
+                        var.referenced_type().skip_mods_and_typedefs().kind() == BtfKind::Void;
+                    (kind, typeless && !kconfig)
+                } else if Func::try_from(ty).is_ok() && !kconfig {
+                    (ExternKind::Kfunc, false)
+                } else {
+                    return Err(Error::with_invalid_data(format!(
+                        "extern `{name}` has unsupported BTF kind {:?}",
+                        ty.kind()
+                    )));
+                };
+
+                externs.push(Self {
+                    weak: weak.contains(name.as_str()),
+                    name,
+                    kind,
+                    typeless,
+                    resolution: None,
+                });
+            }
+        }
+        Ok(externs)
+    }
+
+    /// Determine what the externs `externs` of a loaded object were
+    /// resolved to, by looking them up the way libbpf does.
+    pub(crate) fn resolve_all(externs: &mut [Self]) -> Result<()> {
+        let mut vmlinux = None;
+        let mut kallsyms = None;
+
+        for ext in externs {
+            let resolution = match ext.kind {
+                ExternKind::Kconfig => continue,
+                ExternKind::Ksym if ext.typeless => {
+                    let kallsyms = match &mut kallsyms {
+                        Some(kallsyms) => kallsyms,
+                        kallsyms @ None => kallsyms.insert(read_kallsyms(Path::new(KALLSYMS))?),
+                    };
+                    find_in_kallsyms(kallsyms, &ext.name).map(ExternResolution::Address)
+                }
+                ExternKind::Ksym | ExternKind::Kfunc => {
+                    let vmlinux = match &mut vmlinux {
+                        Some(vmlinux) => vmlinux,
+                        vmlinux @ None => vmlinux.insert(Btf::from_vmlinux()?),
+                    };
+                    // Only kfuncs are looked up without their flavor suffix.
+                    let (name, kind) = if ext.kind == ExternKind::Kfunc {
+                        (&ext.name[..essential_name_len(&ext.name)], BtfKind::Func)
+                    } else {
+                        (ext.name.as_str(), BtfKind::Var)
+                    };
+                    find_in_kernel_btf(vmlinux, name, kind)?
+                        .map(|(module, type_id)| ExternResolution::BtfId { module, type_id })
+                }
+            };
+            ext.resolution = Some(resolution.unwrap_or(ExternResolution::Unresolved));
+        }
+        Ok(())
+    }
+
+    /// The name of the extern, as declared by the BPF program.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The kind of the extern.
+    #[inline]
+    pub fn kind(&self) -> ExternKind {
+        self.kind
+    }
+
+    /// Whether the extern is declared `__weak`, in which case the object
+    /// loads even if it can't be resolved.
+    #[inline]
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// What the extern was resolved to when loading the object, as
+    /// determined by [`Object::resolve_externs`][crate::Object::resolve_externs].
+    ///
+    /// `None` is returned for externs that have not been resolved that way
+    /// as well as for kconfig externs, the values of which can be read
+    /// via [`Object::kconfig`][crate::Object::kconfig]. libbpf leaves weak
+    /// kfuncs unresolved if their prototype doesn't match the kernel's,
+    /// which is not reflected here.
+    #[inline]
+    pub fn resolution(&self) -> Option<&ExternResolution> {
+        self.resolution.as_ref()
+    }
+
+    /// Look up the extern in kernel BTF, such as retrieved via
+    /// [`Btf::from_vmlinux`].
+    ///
+    /// This is the BTF id kfuncs and typed kernel variables are resolved
+    /// to when loading the object. `None` is returned if `btf` doesn't
+    /// provide the extern, which will make loading fail for non-weak
+    /// externs. Kconfig externs and kernel variables declared without a
+    /// type (`extern const void sym __ksym`) are not described by kernel
+    /// BTF and always result in `None`.
+    pub fn kernel_btf_id(&self, btf: &Btf<'_>) -> Option<TypeId> {
+        match self.kind {
+            ExternKind::Kconfig => None,
+            ExternKind::Ksym if self.typeless => None,
+            ExternKind::Ksym => btf
+                .type_by_name::<Var<'_>>(&self.name)
+                .map(|var| var.type_id()),
+            // Kfuncs may carry a "flavor" suffix, e.g., `foo___v2`, that
+            // is ignored when resolving them.
+            ExternKind::Kfunc => btf
+                .type_by_name::<Func<'_>>(&self.name[..essential_name_len(&self.name)])
+                .map(|func| func.type_id()),
+        }
+    }
+}
+
+/// Determine the length of `name` without a `___` flavor suffix.
+fn essential_name_len(name: &str) -> usize {
+    let bytes = name.as_bytes();
+    (0..bytes.len().saturating_sub(4))
+        .rev()
+        .find(|&i| bytes[i] != b'_' && bytes[i + 1..i + 4] == *b"___" && bytes[i + 4] != b'_')
+        .map_or(bytes.len(), |i| i + 1)
+}
+
+/// A section header of an ELF file.
+#[derive(Clone, Copy)]
+pub(crate) struct Section {
+    name: u32,
+    ty: u32,
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+    link: u32,
+}
+
+/// A minimal reader for the 64 bit ELF files of either byte order BPF
+/// objects are stored in.
+pub(crate) struct Elf<'elf> {
+    data: &'elf [u8],
+    big_endian: bool,
+}
+
+impl<'elf> Elf<'elf> {
+    pub(crate) fn parse(data: &'elf [u8]) -> Result<Self> {
+        let ident = data
+            .get(..6)
+            .ok_or_else(|| Error::with_invalid_data("ELF file is truncated"))?;
+        if ident[..4] != *b"\x7fELF" || ident[4] != ELFCLASS64 {
+            return Err(Error::with_invalid_data("not a 64 bit ELF file"));
+        }
+        let big_endian = match ident[5] {
+            ELFDATA2LSB => false,
+            ELFDATA2MSB => true,
+            _ => return Err(Error::with_invalid_data("invalid ELF byte order")),
+        };
+        Ok(Self { data, big_endian })
+    }
+
+    /// Retrieve the `N` bytes at `offset`.
+    fn bytes<const N: usize>(&self, offset: u64) -> Result<[u8; N]> {
+        let bytes = self.slice(offset, N as u64)?;
+        // The conversion can't fail, as the slice has length `N`.
+        Ok(bytes.try_into().unwrap())
+    }
+
+    /// Retrieve the `size` bytes at `offset`.
+    fn slice(&self, offset: u64, size: u64) -> Result<&'elf [u8]> {
+        usize::try_from(offset)
+            .ok()
+            .zip(usize::try_from(size).ok())
+            .and_then(|(offset, size)| self.data.get(offset..offset.checked_add(size)?))
+            .ok_or_else(|| Error::with_invalid_data("ELF file is truncated"))
+    }
+
+    fn u16(&self, offset: u64) -> Result<u16> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: u64) -> Result<u32> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn u64(&self, offset: u64) -> Result<u64> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        })
+    }
+
+    /// Retrieve the headers of all sections.
+    fn sections(&self) -> Result<Vec<Section>> {
+        let shoff = self.u64(0x28)?;
+        let shentsize = u64::from(self.u16(0x3a)?);
+        let shnum = self.u16(0x3c)?;
+
+        (0..u64::from(shnum))
+            .map(|idx| {
+                let hdr = shoff + idx * shentsize;
+                Ok(Section {
+                    name: self.u32(hdr)?,
+                    ty: self.u32(hdr + 0x4)?,
+                    offset: self.u64(hdr + 0x18)?,
+                    size: self.u64(hdr + 0x20)?,
+                    link: self.u32(hdr + 0x28)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Retrieve the NUL terminated string at `offset` in string table
+    /// `strtab`.
+    fn str(&self, strtab: &Section, offset: u32) -> Result<&'elf str> {
+        self.slice(strtab.offset, strtab.size)?
+            .get(offset as usize..)
+            .and_then(|s| CStr::from_bytes_until_nul(s).ok())
+            .ok_or_else(|| Error::with_invalid_data("invalid ELF string table offset"))?
+            .to_str()
+            .map_err(Error::with_invalid_data)
+    }
+
+    /// Retrieve the header of the section called `name`, if any.
+    pub(crate) fn section(&self, name: &str) -> Result<Option<Section>> {
+        let sections = self.sections()?;
+        let shstrndx = usize::from(self.u16(0x3e)?);
+        let shstrtab = sections
+            .get(shstrndx)
+            .ok_or_else(|| Error::with_invalid_data("invalid ELF section name table"))?;
+
+        for section in &sections {
+            if self.str(shstrtab, section.name)? == name {
+                return Ok(Some(*section));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Retrieve the contents of the section called `name`, if any.
+    fn section_data(&self, name: &str) -> Result<Option<&'elf [u8]>> {
+        self.section(name)?
+            .map(|section| self.slice(section.offset, section.size))
+            .transpose()
+    }
+
+    /// Find the names of the weak, undefined symbols.
+    fn weak_externs(&self) -> Result<HashSet<&'elf str>> {
+        let sections = self.sections()?;
+        let mut externs = HashSet::new();
+        for symtab in sections.iter().filter(|section| section.ty == SHT_SYMTAB) {
+            let strtab = sections
+                .get(symtab.link as usize)
+                .ok_or_else(|| Error::with_invalid_data("invalid ELF string table"))?;
+
+            for idx in 0..symtab.size / SYM_SIZE as u64 {
+                let sym = symtab.offset + idx * SYM_SIZE as u64;
+                let [info] = self.bytes(sym + 0x4)?;
+                let shndx = self.u16(sym + 0x6)?;
+                if shndx != SHN_UNDEF || info >> 4 != STB_WEAK || info & 0xf != STT_NOTYPE {
+                    continue;
+                }
+                externs.insert(self.str(strtab, self.u32(sym)?)?);
+            }
+        }
+        Ok(externs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that we correctly strip flavor suffixes from extern names.
+    #[test]
+    fn essential_name() {
+        assert_eq!(essential_name_len("bpf_obj_new_impl"), 16);
+        assert_eq!(essential_name_len("foo___v2"), 3);
+        assert_eq!(essential_name_len("foo____v2"), 9);
+        assert_eq!(essential_name_len("___foo"), 6);
+        assert_eq!(essential_name_len(""), 0);
+    }
+}
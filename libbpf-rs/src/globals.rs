@@ -0,0 +1,287 @@
+use std::ffi::CStr;
+use std::io;
+use std::marker::PhantomData;
+use std::mem::align_of;
+use std::mem::size_of;
+use std::ptr;
+use std::ptr::NonNull;
+
+use crate::btf::types::DataSec;
+use crate::btf::types::Var;
+use crate::Btf;
+use crate::Error;
+use crate::Result;
+
+/// A type that can be used to access a global variable of a BPF object.
+///
+/// # Safety
+/// Implementors have to be valid for any bit pattern (i.e., be "plain old
+/// data"), because the memory backing a global variable may be changed
+/// arbitrarily by the BPF program.
+pub unsafe trait GlobalValue: Copy {}
+
+macro_rules! impl_global_value {
+    ($($ty:ty),*) => {
+        $(
+            // SAFETY: Primitive integer and floating point types are valid
+            //         for any bit pattern.
+            unsafe impl GlobalValue for $ty {}
+        )*
+    };
+}
+
+impl_global_value!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+// SAFETY: An array of plain old data is plain old data itself.
+unsafe impl<T, const N: usize> GlobalValue for [T; N] where T: GlobalValue {}
+
+/// The memory backing one of an object's data sections.
+#[derive(Debug)]
+struct Section {
+    /// The section's name, e.g., `.rodata`.
+    name: String,
+    data: NonNull<u8>,
+    size: usize,
+    writable: bool,
+}
+
+/// Typed access to the global variables of a BPF object, by name.
+///
+/// Variables are looked up in the `.data`, `.rodata`, and `.bss` sections
+/// (including custom ones such as `.data.foo`) and validated against the
+/// object's BTF. Before the object is loaded, accesses refer to the initial
+/// values of variables. Afterwards, they refer to the memory mapped maps
+/// shared with the BPF program, in which case variables in `.rodata` can
+/// only be read.
+///
+/// ```no_run
+/// # use libbpf_rs::Object;
+/// # let get_object = || -> Object { todo!() };
+/// let mut object: Object = get_object();
+/// let mut globals = object.globals().unwrap();
+/// *globals.get_mut::<u64>("sample_rate").unwrap() = 100;
+/// ```
+#[derive(Debug)]
+pub struct Globals<'obj> {
+    btf: Btf<'obj>,
+    sections: Vec<Section>,
+    _obj: PhantomData<&'obj mut libbpf_sys::bpf_object>,
+}
+
+/// Collect the memory mapped data sections of `obj` whose names start with
+/// one of `prefixes`.
+///
+/// # Safety
+/// `obj` must point to a valid `bpf_object`.
+unsafe fn collect_sections(
+    obj: NonNull<libbpf_sys::bpf_object>,
+    btf: &Btf<'_>,
+    prefixes: &[&str],
+    writable: impl Fn(&str) -> bool,
+) -> Result<Vec<Section>> {
+    let mut sections = Vec::new();
+    let mut map: *mut libbpf_sys::bpf_map = ptr::null_mut();
+    loop {
+        map = unsafe { libbpf_sys::bpf_object__next_map(obj.as_ptr(), map) };
+        if map.is_null() {
+            break;
+        }
+
+        if !unsafe { libbpf_sys::bpf_map__is_internal(map) } {
+            continue;
+        }
+
+        // The names of internal maps are derived from the section name in
+        // ways that can't be reversed reliably (e.g., `runqslo.rodata` for
+        // the `.rodata` section of object `runqslower`), but libbpf
+        // associates each with its section's DATASEC type.
+        let type_id = unsafe { libbpf_sys::bpf_map__btf_value_type_id(map) };
+        let name = match btf
+            .type_by_id::<DataSec<'_>>(type_id.into())
+            .and_then(|datasec| datasec.name())
+        {
+            Some(name) => name.to_str().map_err(Error::with_invalid_data)?.to_string(),
+            None => continue,
+        };
+        if !prefixes.iter().any(|prefix| name.starts_with(prefix)) {
+            continue;
+        }
+
+        let mut size = 0;
+        let data = unsafe { libbpf_sys::bpf_map__initial_value(map, &mut size) };
+        // The map may not be memory mapped, e.g., because the kernel
+        // does not support doing so.
+        let data = match NonNull::new(data.cast::<u8>()) {
+            Some(data) => data,
+            None => continue,
+        };
+
+        sections.push(Section {
+            writable: writable(&name),
+            name,
+            data,
+            size: size as usize,
+        });
+    }
+    Ok(sections)
+}
+
+/// Find the variable `name` in `sections` and check that it can be
+/// accessed as a `T`.
+fn find<T: GlobalValue>(
+    btf: &Btf<'_>,
+    sections: &[Section],
+    name: &str,
+    write: bool,
+) -> Result<NonNull<T>> {
+    for section in sections {
+        let datasec = match btf.type_by_name::<DataSec<'_>>(&section.name) {
+            Some(datasec) => datasec,
+            None => continue,
+        };
+
+        for info in datasec.iter() {
+            let var = match btf.type_by_id::<Var<'_>>(info.ty) {
+                Some(var) => var,
+                None => continue,
+            };
+            if var.name().map(CStr::to_bytes) != Some(name.as_bytes()) {
+                continue;
+            }
+
+            if info.size != size_of::<T>() {
+                return Err(Error::with_invalid_input(format!(
+                    "global variable `{name}` has size {}, but {} was requested",
+                    info.size,
+                    size_of::<T>()
+                )));
+            }
+
+            let offset = info.offset as usize;
+            if offset + info.size > section.size {
+                return Err(Error::with_invalid_data(format!(
+                    "global variable `{name}` exceeds section `{}`",
+                    section.name
+                )));
+            }
+
+            let data = unsafe { section.data.as_ptr().add(offset) };
+            if data as usize % align_of::<T>() != 0 {
+                return Err(Error::with_invalid_input(format!(
+                    "global variable `{name}` is not suitably aligned"
+                )));
+            }
+
+            if write && !section.writable {
+                return Err(Error::with_io_error(
+                    io::ErrorKind::PermissionDenied,
+                    format!("global variable `{name}` is read-only"),
+                ));
+            }
+
+            // SAFETY: `data` points into a section and is non-null.
+            return Ok(unsafe { NonNull::new_unchecked(data.cast::<T>()) });
+        }
+    }
+
+    Err(Error::with_io_error(
+        io::ErrorKind::NotFound,
+        format!("global variable `{name}` not found"),
+    ))
+}
+
+impl Globals<'_> {
+    /// Create a [`Globals`] object for the provided `bpf_object`.
+    ///
+    /// # Safety
+    /// `obj` must point to a valid `bpf_object` that is borrowed mutably
+    /// for `'obj`. `loaded` has to reflect whether it has been loaded.
+    pub(crate) unsafe fn new(obj: NonNull<libbpf_sys::bpf_object>, loaded: bool) -> Result<Self> {
+        let btf = Btf::from_bpf_object(unsafe { &*obj.as_ptr() })?
+            .ok_or_else(|| Error::with_invalid_data("object does not contain BTF"))?;
+        let sections = unsafe {
+            collect_sections(obj, &btf, &[".data", ".rodata", ".bss"], |name| {
+                !(loaded && name.starts_with(".rodata"))
+            })
+        }?;
+
+        Ok(Self {
+            btf,
+            sections,
+            _obj: PhantomData,
+        })
+    }
+
+    /// Retrieve a reference to the global variable `name`.
+    pub fn get<T: GlobalValue>(&self, name: &str) -> Result<&T> {
+        let ptr = find::<T>(&self.btf, &self.sections, name, false)?;
+        // SAFETY: `find` checked size and alignment of the variable and `T`
+        //         is valid for any bit pattern. The backing memory lives at
+        //         least as long as the object we borrow.
+        Ok(unsafe { ptr.as_ref() })
+    }
+
+    /// Retrieve a mutable reference to the global variable `name`.
+    pub fn get_mut<T: GlobalValue>(&mut self, name: &str) -> Result<&mut T> {
+        let mut ptr = find::<T>(&self.btf, &self.sections, name, true)?;
+        // SAFETY: `find` checked size, alignment, and writability of the
+        //         variable and `T` is valid for any bit pattern. We borrow
+        //         the object mutably, so no other references exist.
+        Ok(unsafe { ptr.as_mut() })
+    }
+}
+
+/// Read access to the kernel configuration values of a loaded BPF object,
+/// by name.
+///
+/// When loading an object, libbpf resolves the `__kconfig` externs it
+/// declares (e.g., `extern unsigned int CONFIG_HZ __kconfig;`) against the
+/// running kernel and stores them in the `.kconfig` map. Values are read
+/// from this map and thus are exactly what the BPF programs see. Types
+/// are validated by size, meaning that `bool` and tristate values should
+/// be read as `u8` and `u32`, respectively.
+///
+/// ```no_run
+/// # use libbpf_rs::Object;
+/// # let get_object = || -> Object { todo!() };
+/// let object: Object = get_object();
+/// let kconfig = object.kconfig().unwrap();
+/// let hz = *kconfig.get::<u32>("CONFIG_HZ").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Kconfig<'obj> {
+    btf: Btf<'obj>,
+    sections: Vec<Section>,
+    _obj: PhantomData<&'obj libbpf_sys::bpf_object>,
+}
+
+impl Kconfig<'_> {
+    /// Create a [`Kconfig`] object for the provided `bpf_object`.
+    ///
+    /// # Safety
+    /// `obj` must point to a valid and loaded `bpf_object` that is borrowed
+    /// for `'obj`.
+    pub(crate) unsafe fn new(obj: NonNull<libbpf_sys::bpf_object>) -> Result<Self> {
+        let btf = Btf::from_bpf_object(unsafe { &*obj.as_ptr() })?
+            .ok_or_else(|| Error::with_invalid_data("object does not contain BTF"))?;
+        let sections = unsafe { collect_sections(obj, &btf, &[".kconfig"], |_name| false) }?;
+
+        Ok(Self {
+            btf,
+            sections,
+            _obj: PhantomData,
+        })
+    }
+
+    /// Retrieve a reference to the kernel configuration value `name`,
+    /// e.g., `CONFIG_HZ`.
+    ///
+    /// Only values declared by the object can be retrieved.
+    pub fn get<T: GlobalValue>(&self, name: &str) -> Result<&T> {
+        let ptr = find::<T>(&self.btf, &self.sections, name, false)?;
+        // SAFETY: `find` checked size and alignment of the value and `T`
+        //         is valid for any bit pattern. The backing memory lives at
+        //         least as long as the object we borrow.
+        Ok(unsafe { ptr.as_ref() })
+    }
+}
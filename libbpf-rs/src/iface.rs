@@ -0,0 +1,77 @@
+use std::io;
+
+use crate::util;
+use crate::Error;
+use crate::Result;
+
+/// Resolve the name of a network interface (e.g., `eth0`) into its index.
+///
+/// An error of kind [`ErrorKind::NotFound`][crate::ErrorKind::NotFound]
+/// is returned if no interface with the given name exists.
+pub fn ifindex_from_name(name: &str) -> Result<i32> {
+    let c_name = util::str_to_cstring(name)?;
+    let ifindex = unsafe { libc::if_nametoindex(c_name.as_ptr()) };
+    if ifindex == 0 {
+        let err = io::Error::last_os_error();
+        return match err.raw_os_error() {
+            Some(libc::ENODEV) | Some(libc::ENXIO) => Err(Error::with_io_error(
+                io::ErrorKind::NotFound,
+                format!("network interface `{name}` not found"),
+            )),
+            _ => Err(Error::from(err)),
+        };
+    }
+    Ok(ifindex as i32)
+}
+
+/// A type identifying a network interface, either by index or by name.
+///
+/// Functions accepting an interface generically resolve names via
+/// [`ifindex_from_name`].
+pub trait IntoIfindex {
+    /// Retrieve the index of the identified interface.
+    fn into_ifindex(self) -> Result<i32>;
+}
+
+impl IntoIfindex for i32 {
+    fn into_ifindex(self) -> Result<i32> {
+        Ok(self)
+    }
+}
+
+impl IntoIfindex for &str {
+    fn into_ifindex(self) -> Result<i32> {
+        ifindex_from_name(self)
+    }
+}
+
+impl IntoIfindex for &String {
+    fn into_ifindex(self) -> Result<i32> {
+        ifindex_from_name(self)
+    }
+}
+
+impl IntoIfindex for String {
+    fn into_ifindex(self) -> Result<i32> {
+        ifindex_from_name(&self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ErrorKind;
+
+    /// Check that we can resolve interface names.
+    #[test]
+    fn ifindex_resolution() {
+        assert_eq!(ifindex_from_name("lo").unwrap(), 1);
+        assert_eq!("lo".into_ifindex().unwrap(), 1);
+        assert_eq!(42.into_ifindex().unwrap(), 42);
+
+        let err = ifindex_from_name("does-not-exist").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.to_string().contains("does-not-exist"), "{err}");
+    }
+}
@@ -78,33 +78,62 @@
 )]
 #![deny(unsafe_op_in_unsafe_fn)]
 
+pub mod bpffs;
 pub mod btf;
+pub mod cgroup;
+mod diagnostics;
 mod error;
+mod externs;
+mod globals;
+mod iface;
 mod iter;
 mod link;
 mod linker;
 mod map;
+mod netkit;
+mod netlink;
 mod object;
 mod perf_buffer;
 mod print;
+pub mod probes;
 mod program;
 pub mod query;
 mod ringbuf;
+mod settings;
 mod skeleton;
+mod stats;
 mod tc;
+mod token;
+mod typed_map;
 mod user_ringbuf;
 mod util;
+mod variant;
 mod xdp;
+pub mod xsk;
 
 pub use libbpf_sys;
 
 pub use crate::btf::Btf;
 pub use crate::btf::HasSize;
 pub use crate::btf::ReferencesType;
+pub use crate::cgroup::IntoCgroupFd;
+pub use crate::diagnostics::LoadEvent;
+pub use crate::diagnostics::LoadEventKind;
+pub use crate::diagnostics::LoadReport;
+pub use crate::diagnostics::ProgramLoad;
 pub use crate::error::Error;
 pub use crate::error::ErrorExt;
 pub use crate::error::ErrorKind;
+pub use crate::error::LibbpfCall;
 pub use crate::error::Result;
+pub use crate::externs::Extern;
+pub use crate::externs::ExternKind;
+pub use crate::externs::ExternResolution;
+pub use crate::globals::GlobalValue;
+pub use crate::globals::Globals;
+pub use crate::globals::Kconfig;
+pub use crate::iface::ifindex_from_name;
+pub use crate::iface::IntoIfindex;
 pub use crate::iter::Iter;
 pub use crate::link::Link;
 pub use crate::linker::Linker;
@@ -114,30 +143,69 @@ pub use crate::map::MapHandle;
 pub use crate::map::MapInfo;
 pub use crate::map::MapType;
 pub use crate::map::OpenMap;
+pub use crate::map::StructOpsState;
+pub use crate::netkit::NetkitInfo;
+pub use crate::netkit::NetkitMode;
+pub use crate::netkit::NetkitPolicy;
 pub use crate::object::AsRawLibbpf;
+pub use crate::object::LightObject;
+pub use crate::object::LoaderProgram;
 pub use crate::object::Object;
 pub use crate::object::ObjectBuilder;
+pub use crate::object::ObjectMaps;
+pub use crate::object::ObjectProgs;
 pub use crate::object::OpenObject;
 pub use crate::perf_buffer::PerfBuffer;
 pub use crate::perf_buffer::PerfBufferBuilder;
+pub use crate::print::capture_output;
 pub use crate::print::get_print;
 pub use crate::print::set_print;
+#[cfg(feature = "log")]
+pub use crate::print::set_print_to_log;
+#[cfg(feature = "tracing")]
+pub use crate::print::set_print_to_tracing;
 pub use crate::print::PrintCallback;
 pub use crate::print::PrintLevel;
+pub use crate::program::BreakpointType;
+pub use crate::program::CgroupIterOrder;
+pub use crate::program::CgroupSockAddrHook;
 pub use crate::program::Input as ProgramInput;
+pub use crate::program::IterOpts;
+pub use crate::program::KprobeMultiOpts;
+pub use crate::program::LinkAnchor;
+pub use crate::program::LinkPosition;
+pub use crate::program::NetfilterHook;
+pub use crate::program::NetfilterOpts;
+pub use crate::program::NetfilterProtocolFamily;
+pub use crate::program::NetkitOpts;
+pub use crate::program::NetkitTarget;
 pub use crate::program::OpenProgram;
 pub use crate::program::Output as ProgramOutput;
 pub use crate::program::Program;
 pub use crate::program::ProgramAttachType;
+pub use crate::program::ProgramHandle;
 pub use crate::program::ProgramType;
+pub use crate::program::TraceOpts;
 pub use crate::program::TracepointOpts;
 pub use crate::program::UprobeOpts;
 pub use crate::program::UsdtOpts;
 pub use crate::ringbuf::RingBuffer;
 pub use crate::ringbuf::RingBufferBuilder;
+pub use crate::settings::raise_memlock_rlimit;
+pub use crate::settings::set_memlock_rlimit;
+pub use crate::settings::set_strict_mode;
+pub use crate::settings::MemlockRlimitGuard;
+pub use crate::settings::StrictMode;
+pub use crate::stats::RunTimeStats;
 pub use crate::tc::TcAttachPoint;
+pub use crate::tc::TcFilter;
+pub use crate::tc::TcFilterInfo;
 pub use crate::tc::TcHook;
 pub use crate::tc::TcHookBuilder;
+pub use crate::tc::TcHookGuard;
+pub use crate::tc::TcQdisc;
+pub use crate::tc::TcQdiscKind;
+pub use crate::tc::ETH_P_ALL;
 pub use crate::tc::TC_CUSTOM;
 pub use crate::tc::TC_EGRESS;
 pub use crate::tc::TC_H_CLSACT;
@@ -145,10 +213,20 @@ pub use crate::tc::TC_H_INGRESS;
 pub use crate::tc::TC_H_MIN_EGRESS;
 pub use crate::tc::TC_H_MIN_INGRESS;
 pub use crate::tc::TC_INGRESS;
+pub use crate::token::Token;
+pub use crate::typed_map::TypedMap;
 pub use crate::user_ringbuf::UserRingBuffer;
 pub use crate::user_ringbuf::UserRingBufferSample;
 pub use crate::util::num_possible_cpus;
+pub use crate::util::percpu_buffer;
+pub use crate::util::percpu_buffer_size;
+pub use crate::util::percpu_value_stride;
+pub use crate::variant::ProgramVariant;
+pub use crate::variant::ProgramVariants;
+pub use crate::variant::VariantRequirement;
 pub use crate::xdp::Xdp;
+pub use crate::xdp::XdpAttached;
+pub use crate::xdp::XdpFeatures;
 pub use crate::xdp::XdpFlags;
 
 /// Used for skeleton -- an end user may not consider this API stable
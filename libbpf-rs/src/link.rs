@@ -1,15 +1,57 @@
+use std::ffi::c_char;
+use std::ffi::c_int;
 use std::fmt::Debug;
+use std::mem::size_of;
 use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
 use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::io::OwnedFd;
 use std::path::Path;
 use std::path::PathBuf;
+use std::ptr;
 use std::ptr::NonNull;
 
+use crate::error::LibbpfCallExt as _;
+use crate::query::LinkInfo;
 use crate::util;
 use crate::AsRawLibbpf;
+use crate::Error;
+use crate::Map;
+use crate::MapType;
 use crate::Program;
 use crate::Result;
 
+/// The layout of libbpf's `struct bpf_link` (see `libbpf_internal.h`).
+///
+/// libbpf does not provide a way to wrap a link file descriptor created
+/// via `bpf_link_create` into a `bpf_link` object, so we create such
+/// objects ourselves, the same way libbpf does for the links it creates.
+#[repr(C)]
+struct RawLink {
+    detach: Option<unsafe extern "C" fn(*mut libbpf_sys::bpf_link) -> c_int>,
+    dealloc: Option<unsafe extern "C" fn(*mut libbpf_sys::bpf_link)>,
+    pin_path: *mut c_char,
+    fd: c_int,
+    disconnected: bool,
+}
+
+unsafe extern "C" fn raw_link_detach(link: *mut libbpf_sys::bpf_link) -> c_int {
+    // SAFETY: `link` was created by `Link::from_fd` and, hence, is a
+    //         `RawLink`.
+    let link = unsafe { &mut *link.cast::<RawLink>() };
+    // SAFETY: the link owns its file descriptor.
+    let () = drop(unsafe { OwnedFd::from_raw_fd(link.fd) });
+    0
+}
+
+unsafe extern "C" fn raw_link_dealloc(link: *mut libbpf_sys::bpf_link) {
+    // SAFETY: `link` was allocated by `Link::from_fd` and libbpf is done
+    //         with it.
+    let _link = unsafe { Box::from_raw(link.cast::<RawLink>()) };
+}
+
 /// Represents an attached [`Program`].
 ///
 /// This struct is used to model ownership. The underlying program will be detached
@@ -29,6 +71,24 @@ impl Link {
         Link { ptr }
     }
 
+    /// Create a new [`Link`] taking ownership of the link file descriptor
+    /// `fd`, as created by `bpf_link_create`.
+    ///
+    /// Dropping the returned object closes `fd`, which detaches the link
+    /// unless other references to it exist.
+    pub fn from_fd(fd: OwnedFd) -> Self {
+        let link = Box::new(RawLink {
+            detach: Some(raw_link_detach),
+            dealloc: Some(raw_link_dealloc),
+            pin_path: ptr::null_mut(),
+            fd: fd.into_raw_fd(),
+            disconnected: false,
+        });
+        // SAFETY: `RawLink` has the same layout as `bpf_link` and
+        //         `bpf_link__destroy` releases it via `raw_link_dealloc`.
+        unsafe { Self::new(NonNull::from(Box::leak(link)).cast()) }
+    }
+
     /// Create link from BPF FS file.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path_c = util::path_to_cstring(path)?;
@@ -37,6 +97,32 @@ impl Link {
             .map(|ptr| unsafe { Self::new(ptr) })
     }
 
+    /// Create a [`Link`] referring to the existing link with id `id`.
+    ///
+    /// The returned object holds a reference to the link, which allows for
+    /// inspecting, updating, pinning, or detaching it. Dropping it merely
+    /// releases that reference, while the link stays attached for as long as
+    /// its creator (or a pin in bpffs) keeps it alive.
+    pub fn from_id(id: u32) -> Result<Self> {
+        let ret = unsafe { libbpf_sys::bpf_link_get_fd_by_id(id) };
+        let fd = util::parse_ret_i32(ret)?;
+        // SAFETY
+        // A file descriptor coming from the bpf_link_get_fd_by_id function is always suitable for
+        // ownership and can be cleaned up with close.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        Ok(Self::from_fd(fd))
+    }
+
+    /// Returns link id by fd.
+    pub fn get_id_by_fd(fd: BorrowedFd<'_>) -> Result<u32> {
+        LinkInfo::load_from_fd(fd).map(|info| info.id)
+    }
+
+    /// Returns the id of this link.
+    pub fn id(&self) -> Result<u32> {
+        Self::get_id_by_fd(self.as_fd())
+    }
+
     /// Takes ownership from pointer.
     ///
     /// # Safety
@@ -50,7 +136,54 @@ impl Link {
     pub fn update_prog(&mut self, prog: &Program) -> Result<()> {
         let ret =
             unsafe { libbpf_sys::bpf_link__update_program(self.ptr.as_ptr(), prog.ptr.as_ptr()) };
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_link__update_program", prog.name())
+    }
+
+    /// Replace the `struct_ops` map backing this link with `map`.
+    ///
+    /// The kernel switches over to the new implementation atomically, without
+    /// a window in which neither is registered. This allows for swapping a
+    /// `struct_ops` implementation, such as a `sched_ext` scheduler or a TCP
+    /// congestion control algorithm, at runtime.
+    ///
+    /// The link has to have been created via
+    /// [`Map::attach_struct_ops`][crate::Map::attach_struct_ops] and `map`
+    /// has to be a `struct_ops` map defined in a `SEC(".struct_ops.link")`
+    /// section.
+    pub fn update_map(&mut self, map: &Map) -> Result<()> {
+        if map.map_type() != MapType::StructOps {
+            return Err(Error::with_invalid_data(format!(
+                "Invalid map type ({}) for update_map()",
+                map.map_type(),
+            )));
+        }
+
+        let ret = unsafe {
+            libbpf_sys::bpf_link__update_map(self.ptr.as_ptr(), map.as_libbpf_object().as_ptr())
+        };
+        util::parse_ret(ret).libbpf_call("bpf_link__update_map", map.name())
+    }
+
+    /// Atomically replace the underlying prog with `prog`, but only if it
+    /// currently is `old_prog`.
+    ///
+    /// If the link was updated concurrently and no longer refers to
+    /// `old_prog`, the update fails and the link is left untouched.
+    pub fn replace_prog(&mut self, old_prog: &Program, prog: &Program) -> Result<()> {
+        let opts = libbpf_sys::bpf_link_update_opts {
+            sz: size_of::<libbpf_sys::bpf_link_update_opts>() as _,
+            flags: libbpf_sys::BPF_F_REPLACE,
+            old_prog_fd: old_prog.as_fd().as_raw_fd() as _,
+            ..Default::default()
+        };
+        let ret = unsafe {
+            libbpf_sys::bpf_link_update(
+                self.as_fd().as_raw_fd(),
+                prog.as_fd().as_raw_fd(),
+                &opts as *const _,
+            )
+        };
+        util::parse_ret(ret).libbpf_call("bpf_link_update", prog.name())
     }
 
     /// Release "ownership" of underlying BPF resource (typically, a BPF program
@@ -66,6 +199,20 @@ impl Link {
         unsafe { libbpf_sys::bpf_link__disconnect(self.ptr.as_ptr()) }
     }
 
+    /// Convert this link into an owned file descriptor referring to it.
+    ///
+    /// The link is [disconnected][Self::disconnect] and its libbpf object is
+    /// released, while the attachment stays alive for as long as the returned
+    /// file descriptor (or any other reference to the link, e.g., one held by
+    /// another process it got passed to) exists.
+    pub fn into_fd(mut self) -> Result<OwnedFd> {
+        // Duplicate first, so that on failure the link is still
+        // cleaned up as usual.
+        let fd = self.as_fd().try_clone_to_owned()?;
+        let () = self.disconnect();
+        Ok(fd)
+    }
+
     /// [Pin](https://facebookmicrosites.github.io/bpf/blog/2018/08/31/object-lifetime.html#bpffs)
     /// this link to bpffs.
     pub fn pin<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
@@ -98,8 +245,18 @@ impl Link {
         Some(PathBuf::from(path.as_str()))
     }
 
+    /// Retrieve information about this link, including type specific
+    /// information about where it is attached.
+    pub fn info(&self) -> Result<LinkInfo> {
+        LinkInfo::load_from_fd(self.as_fd())
+    }
+
     /// Detach the link.
-    pub fn detach(&self) -> Result<()> {
+    ///
+    /// The program is detached from its hook right away, but the link object
+    /// itself stays around (as does its pin in bpffs, if any) until it is
+    /// dropped or unpinned, respectively.
+    pub fn detach(&mut self) -> Result<()> {
         let ret = unsafe { libbpf_sys::bpf_link__detach(self.ptr.as_ptr()) };
         util::parse_ret(ret)
     }
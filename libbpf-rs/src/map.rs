@@ -27,9 +27,13 @@ use num_enum::IntoPrimitive;
 use num_enum::TryFromPrimitive;
 use strum_macros::Display;
 
+use crate::btf::BtfType;
+use crate::btf::TypeId;
+use crate::error::LibbpfCallExt as _;
 use crate::util;
 use crate::util::parse_ret_i32;
 use crate::AsRawLibbpf;
+use crate::Btf;
 use crate::Error;
 use crate::ErrorExt as _;
 use crate::Link;
@@ -103,6 +107,17 @@ impl OpenMap {
         }
     }
 
+    /// Retrieve the index of the network interface the map is offloaded to,
+    /// if any (`0` means no offloading).
+    pub fn map_ifindex(&self) -> u32 {
+        unsafe { libbpf_sys::bpf_map__ifindex(self.ptr.as_ptr()) }
+    }
+
+    /// Set the index of the network interface to offload the map to.
+    ///
+    /// Maps used by programs offloaded via
+    /// [`OpenProgram::set_ifindex`][crate::OpenProgram::set_ifindex] have to be
+    /// offloaded to the same device.
     pub fn set_map_ifindex(&mut self, idx: u32) {
         unsafe { libbpf_sys::bpf_map__set_ifindex(self.ptr.as_ptr(), idx) };
     }
@@ -306,7 +321,7 @@ impl Map {
         let path_ptr = path_c.as_ptr();
 
         let ret = unsafe { libbpf_sys::bpf_map__pin(self.ptr.as_ptr(), path_ptr) };
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_map__pin", self.name())
     }
 
     /// [Unpin](https://facebookmicrosites.github.io/bpf/blog/2018/08/31/object-lifetime.html#bpffs)
@@ -315,7 +330,37 @@ impl Map {
         let path_c = util::path_to_cstring(path)?;
         let path_ptr = path_c.as_ptr();
         let ret = unsafe { libbpf_sys::bpf_map__unpin(self.ptr.as_ptr(), path_ptr) };
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_map__unpin", self.name())
+    }
+
+    /// Retrieve the id of the map's key type in the BTF of the object the
+    /// map belongs to, if the map was declared with BTF key information.
+    pub fn btf_key_type_id(&self) -> Option<TypeId> {
+        let id = unsafe { libbpf_sys::bpf_map__btf_key_type_id(self.ptr.as_ptr()) };
+        (id != 0).then(|| TypeId::from(id))
+    }
+
+    /// Retrieve the id of the map's value type in the BTF of the object the
+    /// map belongs to, if the map was declared with BTF value information.
+    pub fn btf_value_type_id(&self) -> Option<TypeId> {
+        let id = unsafe { libbpf_sys::bpf_map__btf_value_type_id(self.ptr.as_ptr()) };
+        (id != 0).then(|| TypeId::from(id))
+    }
+
+    /// Retrieve the map's key type, with typedefs and modifiers skipped.
+    ///
+    /// `btf` has to be the BTF of the object the map belongs to, as
+    /// retrieved via [`Object::btf`][crate::Object::btf].
+    pub fn btf_key_type<'btf>(&self, btf: &'btf Btf<'_>) -> Option<BtfType<'btf>> {
+        btf.resolve_type(self.btf_key_type_id()?).ok()
+    }
+
+    /// Retrieve the map's value type, with typedefs and modifiers skipped.
+    ///
+    /// `btf` has to be the BTF of the object the map belongs to, as
+    /// retrieved via [`Object::btf`][crate::Object::btf].
+    pub fn btf_value_type<'btf>(&self, btf: &'btf Btf<'_>) -> Option<BtfType<'btf>> {
+        btf.resolve_type(self.btf_value_type_id()?).ok()
     }
 
     /// Attach a struct ops map
@@ -330,6 +375,7 @@ impl Map {
         util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_map__attach_struct_ops(self.ptr.as_ptr())
         })
+        .libbpf_call("bpf_map__attach_struct_ops", self.name())
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
@@ -397,6 +443,13 @@ pub struct MapHandle {
 
 impl MapHandle {
     /// Create a bpf map whose data is not managed by libbpf.
+    ///
+    /// To create the map using a [BPF token][crate::Token], set `token_fd`
+    /// in `opts` and include `BPF_F_TOKEN_FD` in its `map_flags`.
+    ///
+    /// To attach BTF type information to the map, set `btf_fd` in `opts` to
+    /// a file descriptor as returned by [`Btf::load_into_kernel`], along
+    /// with `btf_key_type_id` and `btf_value_type_id`.
     pub fn create<T: AsRef<str>>(
         map_type: MapType,
         name: Option<T>,
@@ -433,7 +486,7 @@ impl MapHandle {
                 opts,
             )
         };
-        let () = util::parse_ret(fd)?;
+        let () = util::parse_ret(fd).libbpf_call("bpf_map_create", &map_name)?;
 
         Ok(MapHandle {
             fd: MapFd::Owned(unsafe {
@@ -488,7 +541,7 @@ impl MapHandle {
         .and_then(Self::from_fd)
     }
 
-    fn from_fd(fd: OwnedFd) -> Result<Self> {
+    pub(crate) fn from_fd(fd: OwnedFd) -> Result<Self> {
         let info = MapInfo::new(fd.as_fd())?;
         Ok(Self {
             fd: MapFd::Owned(fd),
@@ -542,20 +595,6 @@ impl MapHandle {
         self.value_size
     }
 
-    /// Return the size of one value including padding for interacting with per-cpu
-    /// maps. The values are aligned to 8 bytes.
-    fn percpu_aligned_value_size(&self) -> usize {
-        let val_size = self.value_size() as usize;
-        util::roundup(val_size, 8)
-    }
-
-    /// Returns the size of the buffer needed for a lookup/update of a per-cpu map.
-    fn percpu_buffer_size(&self) -> Result<usize> {
-        let aligned_val_size = self.percpu_aligned_value_size();
-        let ncpu = crate::num_possible_cpus()?;
-        Ok(ncpu * aligned_val_size)
-    }
-
     /// Apply a key check and return a null pointer in case of dealing with queue/stack/bloom-filter map,
     /// before passing the key to the bpf functions that support the map of type queue/stack/bloom-filter.
     fn map_key(&self, key: &[u8]) -> *const c_void {
@@ -587,20 +626,15 @@ impl MapHandle {
                 flags.bits(),
             )
         };
+        if ret == -libc::ENOENT {
+            return Ok(None);
+        }
 
-        if ret == 0 {
-            unsafe {
-                out.set_len(out_size);
-            }
-            Ok(Some(out))
-        } else {
-            let err = io::Error::last_os_error();
-            if err.kind() == io::ErrorKind::NotFound {
-                Ok(None)
-            } else {
-                Err(Error::from(err))
-            }
+        let () = util::parse_ret(ret).libbpf_call("bpf_map_lookup_elem_flags", self.name())?;
+        unsafe {
+            out.set_len(out_size);
         }
+        Ok(Some(out))
     }
 
     /// Internal function to update a map. This does not check the length of the
@@ -623,7 +657,7 @@ impl MapHandle {
             )
         };
 
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_map_update_elem", self.name())
     }
 
     /// Returns map value as `Vec` of `u8`.
@@ -650,6 +684,33 @@ impl MapHandle {
         self.lookup_raw(key, flags, out_size)
     }
 
+    /// Retrieve the state the kernel reports for this `struct_ops` map.
+    ///
+    /// This can be used, for instance, to check whether a `struct_ops`
+    /// implementation (e.g., a `sched_ext` scheduler) is still registered
+    /// or got unregistered by the kernel.
+    pub fn struct_ops_state(&self) -> Result<StructOpsState> {
+        if self.map_type() != MapType::StructOps {
+            return Err(Error::with_invalid_data(format!(
+                "Invalid map type ({}) for struct_ops_state()",
+                self.map_type(),
+            )));
+        }
+
+        let value = self
+            .lookup(&0u32.to_ne_bytes(), MapFlags::ANY)?
+            .ok_or_else(|| Error::with_invalid_data("kernel did not report struct_ops value"))?;
+        // The value starts with a `struct bpf_struct_ops_common_value`,
+        // containing the reference count followed by the state.
+        let state = value
+            .get(4..8)
+            .and_then(|state| state.try_into().ok())
+            .map(u32::from_ne_bytes)
+            .ok_or_else(|| Error::with_invalid_data("struct_ops value is too small"))?;
+        StructOpsState::try_from(state)
+            .map_err(|_| Error::with_invalid_data(format!("unknown struct_ops state {state}")))
+    }
+
     /// Returns if the given value is likely present in bloom_filter as `bool`.
     ///
     /// `value` must have exactly [`MapHandle::value_size()`] elements.
@@ -661,17 +722,12 @@ impl MapHandle {
                 value.to_vec().as_mut_ptr() as *mut c_void,
             )
         };
-
-        if ret == 0 {
-            Ok(true)
-        } else {
-            let err = io::Error::last_os_error();
-            if err.kind() == io::ErrorKind::NotFound {
-                Ok(false)
-            } else {
-                Err(Error::from(err))
-            }
+        if ret == -libc::ENOENT {
+            return Ok(false);
         }
+
+        let () = util::parse_ret(ret).libbpf_call("bpf_map_lookup_elem", self.name())?;
+        Ok(true)
     }
 
     /// Returns one value per cpu as `Vec` of `Vec` of `u8` for per per-cpu maps.
@@ -686,8 +742,8 @@ impl MapHandle {
         }
 
         let val_size = self.value_size() as usize;
-        let aligned_val_size = self.percpu_aligned_value_size();
-        let out_size = self.percpu_buffer_size()?;
+        let aligned_val_size = util::percpu_value_stride(val_size);
+        let out_size = util::percpu_buffer_size(val_size)?;
 
         let raw_res = self.lookup_raw(key, flags, out_size)?;
         if let Some(raw_vals) = raw_res {
@@ -716,7 +772,7 @@ impl MapHandle {
         let ret = unsafe {
             libbpf_sys::bpf_map_delete_elem(self.fd.as_raw_fd(), key.as_ptr() as *const c_void)
         };
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_map_delete_elem", self.name())
     }
 
     /// Deletes many elements in batch mode from the map.
@@ -756,7 +812,7 @@ impl MapHandle {
                 &opts as *const libbpf_sys::bpf_map_batch_opts,
             )
         };
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_map_delete_batch", self.name())
     }
 
     /// Same as [`MapHandle::lookup()`] except this also deletes the key from the map.
@@ -783,20 +839,15 @@ impl MapHandle {
                 out.as_mut_ptr() as *mut c_void,
             )
         };
+        if ret == -libc::ENOENT {
+            return Ok(None);
+        }
 
-        if ret == 0 {
-            unsafe {
-                out.set_len(self.value_size() as usize);
-            }
-            Ok(Some(out))
-        } else {
-            let err = io::Error::last_os_error();
-            if err.kind() == io::ErrorKind::NotFound {
-                Ok(None)
-            } else {
-                Err(Error::from(err))
-            }
+        let () = util::parse_ret(ret).libbpf_call("bpf_map_lookup_and_delete_elem", self.name())?;
+        unsafe {
+            out.set_len(self.value_size() as usize);
         }
+        Ok(Some(out))
     }
 
     /// Update an element.
@@ -874,7 +925,7 @@ impl MapHandle {
             )
         };
 
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_map_update_batch", self.name())
     }
 
     /// Update an element in an per-cpu map with one value per cpu.
@@ -901,10 +952,8 @@ impl MapHandle {
         };
 
         let val_size = self.value_size() as usize;
-        let aligned_val_size = self.percpu_aligned_value_size();
-        let buf_size = self.percpu_buffer_size()?;
-
-        let mut value_buf = vec![0; buf_size];
+        let aligned_val_size = util::percpu_value_stride(val_size);
+        let mut value_buf = util::percpu_buffer(val_size)?;
 
         for (i, val) in values.iter().enumerate() {
             if val.len() != val_size {
@@ -932,7 +981,7 @@ impl MapHandle {
     pub fn freeze(&self) -> Result<()> {
         let ret = unsafe { libbpf_sys::bpf_map_freeze(self.fd.as_raw_fd()) };
 
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_map_freeze", self.name())
     }
 
     /// [Pin](https://facebookmicrosites.github.io/bpf/blog/2018/08/31/object-lifetime.html#bpffs)
@@ -942,7 +991,7 @@ impl MapHandle {
         let path_ptr = path_c.as_ptr();
 
         let ret = unsafe { libbpf_sys::bpf_obj_pin(self.fd.as_raw_fd(), path_ptr) };
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_obj_pin", self.name())
     }
 
     /// [Unpin](https://facebookmicrosites.github.io/bpf/blog/2018/08/31/object-lifetime.html#bpffs)
@@ -988,6 +1037,7 @@ bitflags! {
 #[non_exhaustive]
 #[repr(u32)]
 #[derive(Copy, Clone, TryFromPrimitive, IntoPrimitive, PartialEq, Eq, Display, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 // TODO: Document members.
 #[allow(missing_docs)]
 pub enum MapType {
@@ -1029,6 +1079,22 @@ pub enum MapType {
     Unknown = u32::MAX,
 }
 
+/// The state of a `struct_ops` map, as reported by the kernel. Maps to
+/// `enum bpf_struct_ops_state` in the kernel.
+#[non_exhaustive]
+#[repr(u32)]
+#[derive(Copy, Clone, TryFromPrimitive, PartialEq, Eq, Display, Debug)]
+pub enum StructOpsState {
+    /// The map has not been registered with the kernel yet.
+    Init = 0,
+    /// The map is registered, without a link.
+    InUse,
+    /// The map got unregistered and is about to be freed.
+    ToBeFree,
+    /// The map is ready to be registered via a link.
+    Ready,
+}
+
 impl MapType {
     /// Returns if the map is of one of the per-cpu types.
     pub fn is_percpu(&self) -> bool {
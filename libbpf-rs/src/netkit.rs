@@ -0,0 +1,262 @@
+use std::mem::size_of;
+
+use crate::netlink;
+use crate::netlink::nl_align;
+use crate::netlink::nl_attributes;
+use crate::netlink::read_u32;
+use crate::Error;
+use crate::IntoIfindex;
+use crate::Result;
+
+/// Netlink attribute type of a link's kind specific information
+/// (`IFLA_LINKINFO`).
+const IFLA_LINKINFO: u16 = 18;
+/// Netlink attribute type of a link's kind (`IFLA_INFO_KIND`).
+const IFLA_INFO_KIND: u16 = 1;
+/// Netlink attribute type of a link's kind specific data (`IFLA_INFO_DATA`).
+const IFLA_INFO_DATA: u16 = 2;
+/// Netlink attribute type indicating a netkit primary device
+/// (`IFLA_NETKIT_PRIMARY`).
+const IFLA_NETKIT_PRIMARY: u16 = 2;
+/// Netlink attribute type of a netkit device's policy (`IFLA_NETKIT_POLICY`).
+const IFLA_NETKIT_POLICY: u16 = 3;
+/// Netlink attribute type of a netkit device's peer's policy
+/// (`IFLA_NETKIT_PEER_POLICY`).
+const IFLA_NETKIT_PEER_POLICY: u16 = 4;
+/// Netlink attribute type of a netkit device's mode (`IFLA_NETKIT_MODE`).
+const IFLA_NETKIT_MODE: u16 = 5;
+
+/// The verdict a netkit device applies to packets when no BPF program is
+/// attached to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NetkitPolicy {
+    /// Packets are passed on (`NETKIT_PASS`).
+    Pass,
+    /// Packets are dropped (`NETKIT_DROP`).
+    Drop,
+}
+
+impl TryFrom<u32> for NetkitPolicy {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Pass),
+            2 => Ok(Self::Drop),
+            _ => Err(Error::with_invalid_data(format!(
+                "unknown netkit policy {value}"
+            ))),
+        }
+    }
+}
+
+/// The mode of operation of a netkit device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NetkitMode {
+    /// The device operates on layer 2, i.e., with Ethernet headers
+    /// (`NETKIT_L2`).
+    L2,
+    /// The device operates on layer 3 (`NETKIT_L3`).
+    L3,
+}
+
+impl TryFrom<u32> for NetkitMode {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        match value {
+            0 => Ok(Self::L2),
+            1 => Ok(Self::L3),
+            _ => Err(Error::with_invalid_data(format!(
+                "unknown netkit mode {value}"
+            ))),
+        }
+    }
+}
+
+/// Information about a netkit device, as retrieved via
+/// [`NetkitInfo::query`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetkitInfo {
+    /// Whether the device is the primary device of its pair, as opposed to
+    /// the peer.
+    pub primary: bool,
+    /// The policy of the device.
+    pub policy: NetkitPolicy,
+    /// The policy of the device's peer, if the peer still exists.
+    pub peer_policy: Option<NetkitPolicy>,
+    /// The mode of operation of the device pair.
+    pub mode: NetkitMode,
+}
+
+/// The `ifinfomsg` header of link related netlink messages.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct IfInfoMsg {
+    family: u8,
+    _pad: u8,
+    ty: u16,
+    index: i32,
+    flags: u32,
+    change: u32,
+}
+
+/// A netlink request for retrieving a link.
+#[repr(C)]
+struct LinkRequest {
+    header: libc::nlmsghdr,
+    msg: IfInfoMsg,
+}
+
+/// Parse the payload of an `RTM_NEWLINK` message, returning `Ok(None)` for
+/// anything but a netkit device.
+fn parse_link(payload: &[u8]) -> Result<Option<NetkitInfo>> {
+    let attrs = payload
+        .get(nl_align(size_of::<IfInfoMsg>())..)
+        .ok_or_else(|| Error::with_invalid_data("received truncated link message"))?;
+
+    let mut is_netkit = false;
+    let mut primary = false;
+    let mut policy = None;
+    let mut peer_policy = None;
+    let mut mode = None;
+    for (_, data) in nl_attributes(attrs).filter(|(ty, _)| *ty == IFLA_LINKINFO) {
+        for (ty, data) in nl_attributes(data) {
+            match ty {
+                IFLA_INFO_KIND => is_netkit = data.split(|b| *b == 0).next() == Some(b"netkit"),
+                IFLA_INFO_DATA => {
+                    for (ty, data) in nl_attributes(data) {
+                        match ty {
+                            IFLA_NETKIT_PRIMARY => primary = data.first() == Some(&1),
+                            IFLA_NETKIT_POLICY => policy = read_u32(data),
+                            IFLA_NETKIT_PEER_POLICY => peer_policy = read_u32(data),
+                            IFLA_NETKIT_MODE => mode = read_u32(data),
+                            _ => (),
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    if !is_netkit {
+        return Ok(None);
+    }
+
+    let info = NetkitInfo {
+        primary,
+        policy: NetkitPolicy::try_from(
+            policy.ok_or_else(|| Error::with_invalid_data("netkit device lacks policy"))?,
+        )?,
+        peer_policy: peer_policy.map(NetkitPolicy::try_from).transpose()?,
+        mode: NetkitMode::try_from(
+            mode.ok_or_else(|| Error::with_invalid_data("netkit device lacks mode"))?,
+        )?,
+    };
+    Ok(Some(info))
+}
+
+impl NetkitInfo {
+    /// Retrieve information about the netkit device `ifindex`.
+    ///
+    /// An error of kind [`ErrorKind::InvalidInput`][crate::ErrorKind::InvalidInput]
+    /// is reported if the interface is not a netkit device.
+    pub fn query<I: IntoIfindex>(ifindex: I) -> Result<Self> {
+        let ifindex = ifindex.into_ifindex()?;
+        let req = LinkRequest {
+            header: libc::nlmsghdr {
+                nlmsg_len: size_of::<LinkRequest>() as u32,
+                nlmsg_type: libc::RTM_GETLINK,
+                nlmsg_flags: (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16,
+                nlmsg_seq: 1,
+                nlmsg_pid: 0,
+            },
+            msg: IfInfoMsg {
+                family: libc::AF_UNSPEC as u8,
+                index: ifindex,
+                ..Default::default()
+            },
+        };
+
+        let mut result = None;
+        let () = netlink::transact(&req, |msg_type, payload| {
+            if msg_type == libc::RTM_NEWLINK && result.is_none() {
+                result = Some(parse_link(payload));
+            }
+        })?;
+
+        result
+            .ok_or_else(|| Error::with_invalid_data("kernel did not report link"))??
+            .ok_or_else(|| {
+                Error::with_invalid_input(format!(
+                    "network interface {ifindex} is not a netkit device"
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ErrorKind;
+
+    /// Append a netlink attribute of type `ty` with payload `data` to `buf`.
+    fn push_attr(buf: &mut Vec<u8>, ty: u16, data: &[u8]) {
+        let len = 4 + data.len();
+        buf.extend_from_slice(&(len as u16).to_ne_bytes());
+        buf.extend_from_slice(&ty.to_ne_bytes());
+        buf.extend_from_slice(data);
+        buf.resize(nl_align(buf.len()), 0);
+    }
+
+    /// Create the payload of an `RTM_NEWLINK` message for a link of kind
+    /// `kind` with kind specific data `data`.
+    fn link_msg(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut info = Vec::new();
+        push_attr(&mut info, IFLA_INFO_KIND, kind);
+        push_attr(&mut info, IFLA_INFO_DATA | 1 << 15, data);
+
+        let mut buf = vec![0; size_of::<IfInfoMsg>()];
+        push_attr(&mut buf, IFLA_LINKINFO | 1 << 15, &info);
+        buf
+    }
+
+    /// Check that we can parse netkit device information from netlink
+    /// messages.
+    #[test]
+    fn link_parsing() {
+        let mut data = Vec::new();
+        push_attr(&mut data, IFLA_NETKIT_PRIMARY, &[1]);
+        push_attr(&mut data, IFLA_NETKIT_POLICY, &2u32.to_ne_bytes());
+        push_attr(&mut data, IFLA_NETKIT_MODE, &1u32.to_ne_bytes());
+
+        let info = parse_link(&link_msg(b"netkit\0", &data)).unwrap();
+        assert_eq!(
+            info,
+            Some(NetkitInfo {
+                primary: true,
+                policy: NetkitPolicy::Drop,
+                peer_policy: None,
+                mode: NetkitMode::L3,
+            })
+        );
+
+        push_attr(&mut data, IFLA_NETKIT_PEER_POLICY, &0u32.to_ne_bytes());
+        let info = parse_link(&link_msg(b"netkit\0", &data)).unwrap().unwrap();
+        assert_eq!(info.peer_policy, Some(NetkitPolicy::Pass));
+
+        // Links of other kinds are skipped.
+        assert_eq!(parse_link(&link_msg(b"veth\0", &[])).unwrap(), None);
+        assert_eq!(parse_link(&[0; size_of::<IfInfoMsg>()]).unwrap(), None);
+
+        let mut data = Vec::new();
+        push_attr(&mut data, IFLA_NETKIT_POLICY, &42u32.to_ne_bytes());
+        push_attr(&mut data, IFLA_NETKIT_MODE, &0u32.to_ne_bytes());
+        let err = parse_link(&link_msg(b"netkit\0", &data)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,179 @@
+//! Minimal helpers for querying the kernel via `NETLINK_ROUTE` sockets.
+
+use std::io;
+use std::iter;
+use std::mem::size_of;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::FromRawFd as _;
+use std::os::unix::io::OwnedFd;
+use std::slice;
+
+use crate::Error;
+use crate::Result;
+
+/// Flag marking nested netlink attributes (`NLA_F_NESTED`).
+const NLA_F_NESTED: u16 = 1 << 15;
+/// Mask stripping flags from netlink attribute types (`NLA_TYPE_MASK`).
+const NLA_TYPE_MASK: u16 = !(1 << 15 | 1 << 14);
+/// The size of the buffer to receive netlink messages into.
+const NETLINK_RECV_SIZE: usize = 32 * 1024;
+
+/// Round `len` up to the netlink alignment of four bytes.
+pub(crate) fn nl_align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+pub(crate) fn read_u16(data: &[u8]) -> Option<u16> {
+    Some(u16::from_ne_bytes(data.get(..2)?.try_into().ok()?))
+}
+
+pub(crate) fn read_u32(data: &[u8]) -> Option<u32> {
+    Some(u32::from_ne_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+/// Iterate over the netlink attributes in `data`, as pairs of type and payload.
+pub(crate) fn nl_attributes(mut data: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    iter::from_fn(move || {
+        let len = usize::from(read_u16(data)?);
+        let ty = read_u16(&data[2..])? & NLA_TYPE_MASK;
+        if len < 4 || len > data.len() {
+            return None;
+        }
+        let payload = &data[4..len];
+        data = &data[nl_align(len).min(data.len())..];
+        Some((ty, payload))
+    })
+}
+
+/// Append a netlink attribute of type `ty` with payload `data` to `buf`.
+pub(crate) fn push_attr(buf: &mut Vec<u8>, ty: u16, data: &[u8]) {
+    let len = 4 + data.len();
+    let () = buf.extend_from_slice(&(len as u16).to_ne_bytes());
+    let () = buf.extend_from_slice(&ty.to_ne_bytes());
+    let () = buf.extend_from_slice(data);
+    let () = buf.resize(nl_align(buf.len()), 0);
+}
+
+/// Append a nested netlink attribute of type `ty` to `buf`, with `nest`
+/// appending the attributes contained in it.
+pub(crate) fn push_nested<F>(buf: &mut Vec<u8>, ty: u16, nest: F)
+where
+    F: FnOnce(&mut Vec<u8>),
+{
+    let start = buf.len();
+    let () = push_attr(buf, ty | NLA_F_NESTED, &[]);
+    let () = nest(buf);
+    let len = (buf.len() - start) as u16;
+    let () = buf[start..start + 2].copy_from_slice(&len.to_ne_bytes());
+}
+
+/// Create a netlink message of type `msg_type` with flags `flags`, the
+/// payload of which starts with `header` and is followed by the
+/// attributes `attrs` appends.
+pub(crate) fn message<H, F>(msg_type: u16, flags: u16, header: &H, attrs: F) -> Vec<u8>
+where
+    F: FnOnce(&mut Vec<u8>),
+{
+    let header_len = size_of::<libc::nlmsghdr>();
+    let mut buf = vec![0u8; header_len];
+    let header =
+        unsafe { slice::from_raw_parts((header as *const H).cast::<u8>(), size_of::<H>()) };
+    let () = buf.extend_from_slice(header);
+    let () = buf.resize(nl_align(buf.len()), 0);
+    let () = attrs(&mut buf);
+
+    let len = buf.len() as u32;
+    let () = buf[0..4].copy_from_slice(&len.to_ne_bytes());
+    let () = buf[4..6].copy_from_slice(&msg_type.to_ne_bytes());
+    let () = buf[6..8].copy_from_slice(&flags.to_ne_bytes());
+    // Sequence number 1 and port ID 0, for the kernel to fill in.
+    let () = buf[8..12].copy_from_slice(&1u32.to_ne_bytes());
+    buf
+}
+
+/// Send the request `req` and invoke `handle` with the type and payload of
+/// each message received in response, until the kernel signals completion
+/// (via `NLMSG_DONE` or an acknowledgement) or reports an error.
+///
+/// `req` has to be a `#[repr(C)]` type starting with a `libc::nlmsghdr`
+/// that describes the request.
+pub(crate) fn transact<R, F>(req: &R, handle: F) -> Result<()>
+where
+    F: FnMut(u16, &[u8]),
+{
+    let req = unsafe { slice::from_raw_parts((req as *const R).cast::<u8>(), size_of::<R>()) };
+    transact_raw(req, handle)
+}
+
+/// Send the request `req`, a complete netlink message (as created by
+/// [`message`]), and handle the response as [`transact`] does.
+pub(crate) fn transact_raw<F>(req: &[u8], mut handle: F) -> Result<()>
+where
+    F: FnMut(u16, &[u8]),
+{
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        )
+    };
+    if fd < 0 {
+        return Err(Error::from(io::Error::last_os_error()));
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let rc = unsafe { libc::send(fd.as_raw_fd(), req.as_ptr().cast(), req.len(), 0) };
+    if rc < 0 {
+        return Err(Error::from(io::Error::last_os_error()));
+    }
+
+    let header_len = size_of::<libc::nlmsghdr>();
+    let mut buf = vec![0u8; NETLINK_RECV_SIZE];
+    loop {
+        let rc = unsafe {
+            libc::recv(
+                fd.as_raw_fd(),
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                libc::MSG_TRUNC,
+            )
+        };
+        if rc < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(Error::from(err));
+        }
+        let len = rc as usize;
+        if len > buf.len() {
+            return Err(Error::with_invalid_data("netlink message got truncated"));
+        }
+
+        let mut data = &buf[..len];
+        while data.len() >= header_len {
+            // Unwraps are safe here since we checked the length above.
+            let msg_len = read_u32(data).unwrap() as usize;
+            let msg_type = read_u16(&data[4..]).unwrap();
+            if msg_len < header_len || msg_len > data.len() {
+                return Err(Error::with_invalid_data(
+                    "received malformed netlink message",
+                ));
+            }
+            let payload = &data[header_len..msg_len];
+
+            match i32::from(msg_type) {
+                libc::NLMSG_DONE | libc::NLMSG_ERROR => {
+                    let err = read_u32(payload).unwrap_or(0) as i32;
+                    if err < 0 {
+                        return Err(Error::from_raw_os_error(-err));
+                    }
+                    return Ok(());
+                }
+                _ => handle(msg_type, payload),
+            }
+            data = &data[nl_align(msg_len).min(data.len())..];
+        }
+    }
+}
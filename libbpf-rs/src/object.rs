@@ -2,22 +2,77 @@ use core::ffi::c_void;
 use std::collections::HashMap;
 use std::ffi::CStr;
 use std::ffi::CString;
+use std::fs;
+use std::io;
 use std::mem;
+use std::os::raw::c_char;
+use std::os::unix::io::AsFd as _;
+use std::os::unix::io::AsRawFd as _;
+use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::FromRawFd as _;
+use std::os::unix::io::OwnedFd;
 use std::path::Path;
 use std::ptr;
 use std::ptr::NonNull;
+use std::slice;
+use std::time::Instant;
 
+use crate::btf::ext::strip_func_info;
+use crate::diagnostics;
+use crate::error::LibbpfCallExt as _;
+use crate::externs::Externs;
+use crate::print;
 use crate::set_print;
 use crate::util;
 use crate::Btf;
 use crate::Error;
+use crate::ErrorExt as _;
+use crate::Extern;
+use crate::Globals;
+use crate::Kconfig;
+use crate::Link;
+use crate::LoadReport;
 use crate::Map;
+use crate::MapHandle;
 use crate::OpenMap;
 use crate::OpenProgram;
 use crate::PrintLevel;
 use crate::Program;
 use crate::Result;
 
+/// The directory below an object's pin directory that links are pinned in
+/// by [`Object::pin_all`].
+const LINK_PIN_DIR: &str = "links";
+
+/// Pin each of `links` under its name in the not yet existing directory
+/// `dir`, removing all pins and the directory again on failure.
+fn pin_links<'link, L>(dir: &Path, links: L) -> Result<()>
+where
+    L: IntoIterator<Item = (&'link str, &'link mut Link)>,
+{
+    let mut links = links.into_iter().peekable();
+    if links.peek().is_none() {
+        return Ok(());
+    }
+
+    let () = fs::create_dir(dir)
+        .map_err(Error::from)
+        .with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let mut pinned = Vec::new();
+    for (name, link) in links {
+        if let Err(err) = link.pin(dir.join(name)) {
+            for link in pinned {
+                let _result = Link::unpin(link);
+            }
+            let _result = fs::remove_dir(dir);
+            return Err(err).with_context(|| format!("failed to pin link `{name}`"));
+        }
+        pinned.push(link);
+    }
+    Ok(())
+}
+
 /// A trait implemented for types that are thin wrappers around `libbpf` types.
 ///
 /// The trait provides access to the underlying `libbpf` (or `libbpf-sys`)
@@ -43,6 +98,10 @@ pub trait AsRawLibbpf {
 pub struct ObjectBuilder {
     name: Option<CString>,
     pin_root_path: Option<CString>,
+    btf_custom_path: Option<CString>,
+    kconfig: Option<CString>,
+    bpf_token_path: Option<CString>,
+    func_info: bool,
 
     opts: libbpf_sys::bpf_object_open_opts,
 }
@@ -64,6 +123,10 @@ impl Default for ObjectBuilder {
         Self {
             name: None,
             pin_root_path: None,
+            btf_custom_path: None,
+            kconfig: None,
+            bpf_token_path: None,
+            func_info: true,
             opts,
         }
     }
@@ -71,6 +134,15 @@ impl Default for ObjectBuilder {
 
 impl ObjectBuilder {
     /// Override the generated name that would have been inferred from the constructor.
+    ///
+    /// Besides being reported by [`OpenObject::name`] and [`Object::name`],
+    /// the name's prefix is used for the names of the maps libbpf creates
+    /// for global data (`.data`, `.rodata`, `.bss`, ...), e.g.,
+    /// `inst1.bss`. Giving each instance of an object a distinct name thus
+    /// makes these maps distinguishable in `bpftool` output. Note that map
+    /// names are limited to 15 characters, so libbpf truncates the object
+    /// name to whatever room the section suffix leaves, e.g., eleven
+    /// characters for `.bss` but only eight for `.rodata`.
     pub fn name<T: AsRef<str>>(&mut self, name: T) -> Result<&mut Self> {
         self.name = Some(util::str_to_cstring(name.as_ref())?);
         self.opts.object_name = self.name.as_ref().map_or(ptr::null(), |p| p.as_ptr());
@@ -79,6 +151,11 @@ impl ObjectBuilder {
 
     /// Set the pin_root_path for maps that are pinned by name.
     ///
+    /// Maps declared with `pinning = LIBBPF_PIN_BY_NAME` are pinned as (or
+    /// reused from) `<path>/<map name>`. Using a distinct directory per
+    /// instance allows for running multiple instances of the same object
+    /// without their pinned maps colliding.
+    ///
     /// By default, this is NULL which bpf translates to /sys/fs/bpf
     pub fn pin_root_path<T: AsRef<Path>>(&mut self, path: T) -> Result<&mut Self> {
         self.pin_root_path = Some(util::path_to_cstring(path)?);
@@ -89,12 +166,99 @@ impl ObjectBuilder {
         Ok(self)
     }
 
+    /// Set the path of a BTF file to use for CO-RE relocations instead of
+    /// the kernel's own BTF.
+    ///
+    /// This is useful on kernels that don't expose their BTF via
+    /// `/sys/kernel/btf/vmlinux`, for example by supplying a file from
+    /// BTFHub. The file is read when the object is loaded.
+    pub fn btf_custom_path<T: AsRef<Path>>(&mut self, path: T) -> Result<&mut Self> {
+        self.btf_custom_path = Some(util::path_to_cstring(path)?);
+        self.opts.btf_custom_path = self
+            .btf_custom_path
+            .as_ref()
+            .map_or(ptr::null(), |p| p.as_ptr());
+        Ok(self)
+    }
+
+    /// Supply values for `CONFIG_*` externs, augmenting and overriding the
+    /// kernel configuration of the system.
+    ///
+    /// Each item is a pair of an extern's name and its value, in the format
+    /// used by kernel config files, e.g., `("CONFIG_FOO", "y")`,
+    /// `("CONFIG_BAR", "42")`, or `("CONFIG_BAZ", "\"text\"")`. Values are
+    /// written into the `.kconfig` map before the object is loaded, which
+    /// makes them usable as load time constants by the BPF program.
+    /// Calling this method again replaces all previously supplied values.
+    pub fn kconfig<I, K, V>(&mut self, values: I) -> Result<&mut Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut kconfig = String::new();
+        for (name, value) in values {
+            let name = name.as_ref();
+            let value = value.as_ref();
+            if !name.starts_with("CONFIG_") {
+                return Err(Error::with_invalid_input(format!(
+                    "kconfig extern `{name}` does not start with `CONFIG_`"
+                )));
+            }
+            if value.contains('\n') {
+                return Err(Error::with_invalid_input(format!(
+                    "value for kconfig extern `{name}` contains a newline"
+                )));
+            }
+            kconfig.push_str(&format!("{name}={value}\n"));
+        }
+
+        self.kconfig = Some(util::str_to_cstring(&kconfig)?);
+        self.opts.kconfig = self.kconfig.as_ref().map_or(ptr::null(), |p| p.as_ptr());
+        Ok(self)
+    }
+
+    /// Set the path of a BPF file system instance to derive a
+    /// [BPF token][crate::Token] from.
+    ///
+    /// If set, libbpf creates a token from the file system, which has to be
+    /// mounted with delegation options, and uses it for all BPF operations
+    /// performed on behalf of the object. That allows for loading objects
+    /// without elevated privileges, e.g., in containers. By default, libbpf
+    /// attempts to use `/sys/fs/bpf` (as well as the path in the
+    /// `LIBBPF_BPF_TOKEN_PATH` environment variable), but doesn't fail if no
+    /// token can be created.
+    pub fn bpf_token_path<T: AsRef<Path>>(&mut self, path: T) -> Result<&mut Self> {
+        self.bpf_token_path = Some(util::path_to_cstring(path)?);
+        self.opts.bpf_token_path = self
+            .bpf_token_path
+            .as_ref()
+            .map_or(ptr::null(), |p| p.as_ptr());
+        Ok(self)
+    }
+
     /// Option to parse map definitions non-strictly, allowing extra attributes/data
     pub fn relaxed_maps(&mut self, relaxed_maps: bool) -> &mut Self {
         self.opts.relaxed_maps = relaxed_maps;
         self
     }
 
+    /// Option to provide the kernel with the BTF function and line
+    /// information of programs, contained in the object's `.BTF.ext`
+    /// section.
+    ///
+    /// Disabling it mimics how libbpf loads programs on kernels lacking
+    /// support for BTF function information, allowing for exercising this
+    /// code path on any kernel. Note that libbpf performs its remaining
+    /// kernel feature detection internally, without a way to override the
+    /// outcome.
+    ///
+    /// By default, the information is provided.
+    pub fn func_info(&mut self, func_info: bool) -> &mut Self {
+        self.func_info = func_info;
+        self
+    }
+
     /// Option to print debug output to stderr.
     ///
     /// Note: This function uses [`set_print`] internally and will overwrite any callbacks
@@ -119,29 +283,72 @@ impl ObjectBuilder {
 
     /// Open an object using the provided path on the file system.
     pub fn open_file<P: AsRef<Path>>(&mut self, path: P) -> Result<OpenObject> {
+        let path = path.as_ref();
+        if !self.func_info {
+            // We have to modify the object and, hence, open it from
+            // memory, using the name libbpf would infer from the path.
+            let mem = fs::read(path)?;
+            let name = match &self.name {
+                Some(name) => name.clone(),
+                None => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    util::str_to_cstring(name.split('.').next().unwrap_or_default())?
+                }
+            };
+            return self.open_memory_impl(&mem, &name);
+        }
+
         let path_c = util::path_to_cstring(path)?;
         let path_ptr = path_c.as_ptr();
 
         let opts = self.opts();
 
-        util::create_bpf_entity_checked(|| unsafe {
+        let mut obj = util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_object__open_file(path_ptr, opts)
         })
-        .and_then(|ptr| unsafe { OpenObject::new(ptr) })
+        .libbpf_call("bpf_object__open_file", &path.to_string_lossy())
+        .and_then(|ptr| unsafe { OpenObject::new(ptr) })?;
+
+        // libbpf does not expose information about externs, so we parse
+        // the file ourselves, but only once they are asked for.
+        obj.externs = Externs::from_file(path);
+        Ok(obj)
     }
 
     /// Open an object from memory.
     pub fn open_memory(&mut self, mem: &[u8]) -> Result<OpenObject> {
-        let opts = self.opts();
+        let name = self.name.clone().unwrap_or_default();
+        self.open_memory_impl(mem, &name)
+    }
+
+    fn open_memory_impl(&self, mem: &[u8], name: &CStr) -> Result<OpenObject> {
+        let mut opts = self.opts;
+        if !name.is_empty() {
+            opts.object_name = name.as_ptr();
+        }
+
+        let stripped;
+        let mem = if self.func_info {
+            mem
+        } else {
+            let mut elf = mem.to_vec();
+            let () = strip_func_info(&mut elf)?;
+            stripped = elf;
+            &stripped
+        };
 
-        util::create_bpf_entity_checked(|| unsafe {
+        let mut obj = util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_object__open_mem(
                 mem.as_ptr() as *const c_void,
                 mem.len() as libbpf_sys::size_t,
-                opts,
+                &opts,
             )
         })
-        .and_then(|ptr| unsafe { OpenObject::new(ptr) })
+        .libbpf_call("bpf_object__open_mem", &name.to_string_lossy())
+        .and_then(|ptr| unsafe { OpenObject::new(ptr) })?;
+
+        obj.externs = Externs::from_memory(mem);
+        Ok(obj)
     }
 }
 
@@ -153,6 +360,7 @@ pub struct OpenObject {
     ptr: NonNull<libbpf_sys::bpf_object>,
     maps: HashMap<String, OpenMap>,
     progs: HashMap<String, OpenProgram>,
+    externs: Externs,
 }
 
 impl OpenObject {
@@ -167,6 +375,7 @@ impl OpenObject {
             ptr,
             maps: HashMap::new(),
             progs: HashMap::new(),
+            externs: Externs::default(),
         };
 
         // Populate obj.maps
@@ -231,9 +440,15 @@ impl OpenObject {
             // manually free the internal state.
             // using destructuring we make sure we'll get a compiler error if anything in
             // Self changes, which will alert us to change this function as well
-            let Self { ptr, maps, progs } = &mut self;
+            let Self {
+                ptr,
+                maps,
+                progs,
+                externs,
+            } = &mut self;
             mem::take(maps);
             mem::take(progs);
+            mem::take(externs);
             *ptr
         };
         // avoid double free of self.ptr
@@ -256,6 +471,22 @@ impl OpenObject {
         }
     }
 
+    /// Retrieve the kernel version the object's programs will be loaded
+    /// with, as taken from the object's `version` section.
+    pub fn kversion(&self) -> u32 {
+        unsafe { libbpf_sys::bpf_object__kversion(self.ptr.as_ptr()) }
+    }
+
+    /// Override the kernel version the object's programs are loaded with.
+    ///
+    /// The version is passed as `kern_version` attribute when loading
+    /// programs and is encoded as by the `KERNEL_VERSION` macro. Only very
+    /// old kernels check it, and only for kprobe programs.
+    pub fn set_kversion(&mut self, version: u32) -> Result<()> {
+        let ret = unsafe { libbpf_sys::bpf_object__set_kversion(self.ptr.as_ptr(), version) };
+        util::parse_ret(ret)
+    }
+
     /// Get a reference to `OpenMap` with the name `name`, if one exists.
     pub fn map<T: AsRef<str>>(&self, name: T) -> Option<&OpenMap> {
         self.maps.get(name.as_ref())
@@ -300,14 +531,361 @@ impl OpenObject {
         self.progs.values_mut()
     }
 
+    /// Get the extern symbols (kconfig values, kernel variables, and
+    /// kfuncs) the object references.
+    ///
+    /// This information is only available for objects opened via
+    /// [`ObjectBuilder`]. It is not needed for opening or loading the
+    /// object, so objects opened from a file are only parsed for it once
+    /// requested. Checking the externs against kernel BTF, see
+    /// [`Extern::kernel_btf_id`], allows for determining which of them
+    /// would fail to resolve before attempting to load the object:
+    ///
+    /// ```no_run
+    /// # use libbpf_rs::Btf;
+    /// # use libbpf_rs::OpenObject;
+    /// # use libbpf_rs::ExternKind;
+    /// # let get_open_object = || -> OpenObject { todo!() };
+    /// let open_object: OpenObject = get_open_object();
+    /// let vmlinux = Btf::from_vmlinux().unwrap();
+    /// for ext in open_object.externs().unwrap() {
+    ///     let missing = ext.kind() == ExternKind::Kfunc
+    ///         && !ext.is_weak()
+    ///         && ext.kernel_btf_id(&vmlinux).is_none();
+    ///     if missing {
+    ///         println!("kfunc {} is not available", ext.name());
+    ///     }
+    /// }
+    /// ```
+    pub fn externs(&self) -> Result<&[Extern]> {
+        self.externs.get()
+    }
+
+    /// Get typed access to the initial values of the object's global
+    /// variables.
+    ///
+    /// This is commonly used to configure `.rodata` constants before the
+    /// object is loaded.
+    pub fn globals(&mut self) -> Result<Globals<'_>> {
+        // SAFETY: Our object pointer is valid and borrowed mutably, and the
+        //         object has not been loaded yet.
+        unsafe { Globals::new(self.ptr, false) }
+    }
+
     /// Load the maps and programs contained in this BPF object into the system.
-    pub fn load(self) -> Result<Object> {
+    ///
+    /// If a program fails to load, the error reported names it and, if
+    /// the verifier rejected it, provides the verifier log via
+    /// [`Error::verifier_log`][crate::Error::verifier_log].
+    pub fn load(mut self) -> Result<Object> {
+        let name = self.name().unwrap_or_default().to_string();
+        let () = util::load_object("bpf_object__load", &name, || unsafe {
+            libbpf_sys::bpf_object__load(self.ptr.as_ptr())
+        })?;
+
+        let externs = mem::take(&mut self.externs);
+        let mut obj = unsafe { Object::from_ptr(self.take_ptr())? };
+        obj.externs = externs;
+
+        Ok(obj)
+    }
+
+    /// Load the maps and programs contained in this BPF object into the
+    /// system, reporting on what libbpf did in the process.
+    ///
+    /// The report details CO-RE relocation decisions, map creations, and
+    /// kernel features libbpf had to work around, and it is provided
+    /// irrespective of whether loading succeeded. This can help in
+    /// understanding why an object behaves differently across kernels.
+    /// Libbpf messages are still passed on to the callback set up via
+    /// [`set_print`][crate::set_print].
+    ///
+    /// ```no_run
+    /// # use libbpf_rs::OpenObject;
+    /// # let get_open_object = || -> OpenObject { todo!() };
+    /// let open_object: OpenObject = get_open_object();
+    /// let (result, report) = open_object.load_with_report();
+    /// for event in report.core_relocations("handle__sched_wakeup") {
+    ///     println!("{:?}: {}", event.elapsed, event.message);
+    /// }
+    /// let object = result.unwrap();
+    /// ```
+    pub fn load_with_report(self) -> (Result<Object>, LoadReport) {
+        let boot_start = diagnostics::boot_time();
+        let start = Instant::now();
+        let (result, msgs) = print::capture(|| self.load());
+        let duration = start.elapsed();
+        let boot_end = diagnostics::boot_time();
+
+        let mut report = LoadReport::new(start, duration, msgs);
+        if let Ok(obj) = &result {
+            let () = report.collect_programs(obj, boot_start, boot_end);
+        }
+        (result, report)
+    }
+
+    /// Generate a loader program for this BPF object instead of loading it.
+    ///
+    /// Rather than performing the load steps directly, libbpf records them
+    /// into a `BPF_PROG_TYPE_SYSCALL` program and an accompanying data blob.
+    /// Running this program (see [`LoaderProgram::run`], as done by "light
+    /// skeletons") loads the object, without requiring libbpf or the
+    /// object file at that point. That makes it suitable for environments
+    /// such as early boot or signed loaders.
+    pub fn gen_loader(self) -> Result<LoaderProgram> {
+        // The loader program refers to maps and programs by their index
+        // in libbpf's lists, so we walk those instead of our name indices.
+        let name = |name: *const c_char| {
+            // SAFETY: libbpf returns valid NUL terminated names.
+            unsafe { CStr::from_ptr(name) }
+                .to_str()
+                .map(str::to_string)
+                .map_err(Error::with_invalid_data)
+        };
+        let mut maps = Vec::new();
+        let mut map = ptr::null_mut();
+        loop {
+            map = unsafe { libbpf_sys::bpf_object__next_map(self.ptr.as_ptr(), map) };
+            if map.is_null() {
+                break;
+            }
+            let () = maps.push(name(unsafe { libbpf_sys::bpf_map__name(map) })?);
+        }
+        let mut progs = Vec::new();
+        let mut prog = ptr::null_mut();
+        loop {
+            prog = unsafe { libbpf_sys::bpf_object__next_program(self.ptr.as_ptr(), prog) };
+            if prog.is_null() {
+                break;
+            }
+            let () = progs.push(name(unsafe { libbpf_sys::bpf_program__name(prog) })?);
+        }
+
+        let mut opts = libbpf_sys::gen_loader_opts {
+            sz: mem::size_of::<libbpf_sys::gen_loader_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+        let ret = unsafe { libbpf_sys::bpf_object__gen_loader(self.ptr.as_ptr(), &mut opts) };
+        let () = util::parse_ret(ret)?;
+
+        // libbpf keeps a reference to `opts` and fills in the generated
+        // program and data as part of the "load".
         let ret = unsafe { libbpf_sys::bpf_object__load(self.ptr.as_ptr()) };
         let () = util::parse_ret(ret)?;
 
-        let obj = unsafe { Object::from_ptr(self.take_ptr())? };
+        let copy = |ptr: *const c_char, size: u32| {
+            if ptr.is_null() {
+                Vec::new()
+            } else {
+                // SAFETY: libbpf reported the size of the buffer it
+                //         generated, which lives as long as the object.
+                unsafe { slice::from_raw_parts(ptr.cast::<u8>(), size as usize) }.to_vec()
+            }
+        };
 
-        Ok(obj)
+        Ok(LoaderProgram {
+            insns: copy(opts.insns, opts.insns_sz),
+            data: copy(opts.data, opts.data_sz),
+            maps,
+            progs,
+        })
+    }
+}
+
+/// A loader program generated by [`OpenObject::gen_loader`].
+#[derive(Clone, Debug)]
+pub struct LoaderProgram {
+    insns: Vec<u8>,
+    data: Vec<u8>,
+    maps: Vec<String>,
+    progs: Vec<String>,
+}
+
+impl LoaderProgram {
+    /// Create a loader program from its previously generated parts, e.g.,
+    /// as embedded in a light skeleton.
+    ///
+    /// `maps` and `progs` are the names of the object's maps and programs,
+    /// in the order in which libbpf enumerates them.
+    pub fn from_parts(
+        insns: Vec<u8>,
+        data: Vec<u8>,
+        maps: Vec<String>,
+        progs: Vec<String>,
+    ) -> Self {
+        Self {
+            insns,
+            data,
+            maps,
+            progs,
+        }
+    }
+
+    /// The raw instructions of the loader program, an array of `struct
+    /// bpf_insn`.
+    #[inline]
+    pub fn insns(&self) -> &[u8] {
+        &self.insns
+    }
+
+    /// The data blob the loader program operates on, containing the
+    /// object's BTF, map definitions, and program instructions.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The names of the maps the loader program creates.
+    #[inline]
+    pub fn maps(&self) -> &[String] {
+        &self.maps
+    }
+
+    /// The names of the programs the loader program loads.
+    #[inline]
+    pub fn progs(&self) -> &[String] {
+        &self.progs
+    }
+
+    /// Load the object the loader program was generated for by running
+    /// it, the way light skeletons do.
+    ///
+    /// The data blob gets stored in a frozen array map, which the loader
+    /// program (of type `BPF_PROG_TYPE_SYSCALL`) then reads from when
+    /// creating the object's maps and loading its programs.
+    pub fn run(&self) -> Result<LightObject> {
+        let name = CString::new("__loader.map").unwrap();
+        let ret = unsafe {
+            libbpf_sys::bpf_map_create(
+                libbpf_sys::BPF_MAP_TYPE_ARRAY,
+                name.as_ptr(),
+                mem::size_of::<u32>() as _,
+                self.data.len() as _,
+                1,
+                ptr::null(),
+            )
+        };
+        let fd = util::parse_ret_i32(ret).context("failed to create loader map")?;
+        // SAFETY: `bpf_map_create` returned a valid file descriptor that
+        //         nobody else owns.
+        let map = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let key = 0u32;
+        let ret = unsafe {
+            libbpf_sys::bpf_map_update_elem(
+                map.as_raw_fd(),
+                (&key as *const u32).cast(),
+                self.data.as_ptr().cast(),
+                0,
+            )
+        };
+        let () = util::parse_ret(ret).context("failed to update loader map")?;
+        let ret = unsafe { libbpf_sys::bpf_map_freeze(map.as_raw_fd()) };
+        let () = util::parse_ret(ret).context("failed to freeze loader map")?;
+
+        let fd_array = [map.as_raw_fd()];
+        let mut opts = libbpf_sys::bpf_prog_load_opts {
+            sz: mem::size_of::<libbpf_sys::bpf_prog_load_opts>() as _,
+            fd_array: fd_array.as_ptr(),
+            prog_flags: libbpf_sys::BPF_F_SLEEPABLE,
+            ..Default::default()
+        };
+        let name = CString::new("__loader.prog").unwrap();
+        let ret = unsafe {
+            libbpf_sys::bpf_prog_load(
+                libbpf_sys::BPF_PROG_TYPE_SYSCALL,
+                name.as_ptr(),
+                b"Dual BSD/GPL\0".as_ptr().cast(),
+                self.insns.as_ptr().cast(),
+                (self.insns.len() / mem::size_of::<libbpf_sys::bpf_insn>()) as _,
+                &mut opts,
+            )
+        };
+        let fd = util::parse_ret_i32(ret).context("failed to load loader program")?;
+        // SAFETY: `bpf_prog_load` returned a valid file descriptor that
+        //         nobody else owns.
+        let prog = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // The context is a `struct bpf_loader_ctx`, followed by a `struct
+        // bpf_map_desc` for each map and a `struct bpf_prog_desc` for each
+        // program. The loader program stores the file descriptors it
+        // created in the latter.
+        const CTX_SIZE: usize = 24;
+        const MAP_DESC_SIZE: usize = 16;
+        const PROG_DESC_SIZE: usize = 4;
+        let progs_off = CTX_SIZE + self.maps.len() * MAP_DESC_SIZE;
+        let mut ctx = vec![0u8; progs_off + self.progs.len() * PROG_DESC_SIZE];
+        let ctx_len = ctx.len() as u32;
+        let () = ctx[0..4].copy_from_slice(&ctx_len.to_ne_bytes());
+
+        let mut opts = libbpf_sys::bpf_test_run_opts {
+            sz: mem::size_of::<libbpf_sys::bpf_test_run_opts>() as _,
+            ctx_in: ctx.as_mut_ptr().cast(),
+            ctx_size_in: ctx_len,
+            ..Default::default()
+        };
+        let ret = unsafe { libbpf_sys::bpf_prog_test_run_opts(prog.as_raw_fd(), &mut opts) };
+        let () = util::parse_ret(ret).context("failed to run loader program")?;
+
+        // The kernel copies the context back to where it got it from.
+        let read_fd = |off: usize| {
+            let fd = i32::from_ne_bytes(ctx[off..off + 4].try_into().unwrap());
+            // SAFETY: The loader program stored a file descriptor it
+            //         created on our behalf, or left the slot untouched.
+            (fd > 0).then(|| unsafe { OwnedFd::from_raw_fd(fd) })
+        };
+        let maps = self
+            .maps
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, name)| Some((name, read_fd(CTX_SIZE + idx * MAP_DESC_SIZE)?)))
+            .collect::<Vec<_>>();
+        let progs = self
+            .progs
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, name)| {
+                Some((name.clone(), read_fd(progs_off + idx * PROG_DESC_SIZE)?))
+            })
+            .collect::<Vec<_>>();
+
+        let ret = opts.retval as i32;
+        let () = util::parse_ret(ret).context("loader program failed")?;
+
+        let maps = maps
+            .into_iter()
+            .map(|(name, fd)| Ok((name.clone(), MapHandle::from_fd(fd)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(LightObject { maps, progs })
+    }
+}
+
+/// The maps and programs of an object loaded by running its
+/// [`LoaderProgram`].
+///
+/// Dropping it closes the file descriptors of all maps and programs.
+#[derive(Debug)]
+pub struct LightObject {
+    maps: Vec<(String, MapHandle)>,
+    progs: Vec<(String, OwnedFd)>,
+}
+
+impl LightObject {
+    /// Retrieve the map with the given name, as known to libbpf.
+    pub fn map(&self, name: &str) -> Option<&MapHandle> {
+        self.maps
+            .iter()
+            .find_map(|(map_name, map)| (map_name == name).then_some(map))
+    }
+
+    /// Retrieve the file descriptor of the program with the given name.
+    ///
+    /// Programs with autoload disabled are not loaded and hence not found.
+    pub fn prog_fd(&self, name: &str) -> Option<BorrowedFd<'_>> {
+        self.progs
+            .iter()
+            .find_map(|(prog_name, fd)| (prog_name == name).then(|| fd.as_fd()))
     }
 }
 
@@ -329,11 +907,16 @@ impl Drop for OpenObject {
 ///
 /// Note that this is an explanation of the motivation -- Rust's lifetime system should already be
 /// enforcing this invariant.
+///
+/// Maps and programs are indexed by name when the `Object` is created, so
+/// looking them up via [`Object::map`] or [`Object::prog`] (and their
+/// mutable counterparts) does not involve walking libbpf's internal lists.
 #[derive(Debug)]
 pub struct Object {
     ptr: NonNull<libbpf_sys::bpf_object>,
     maps: HashMap<String, Map>,
     progs: HashMap<String, Program>,
+    externs: Externs,
 }
 
 impl Object {
@@ -350,6 +933,7 @@ impl Object {
             ptr,
             maps: HashMap::new(),
             progs: HashMap::new(),
+            externs: Externs::default(),
         };
 
         // Populate obj.maps
@@ -395,11 +979,106 @@ impl Object {
         Ok(obj)
     }
 
+    /// Retrieve the object's name.
+    pub fn name(&self) -> Result<&str> {
+        let ptr = unsafe { libbpf_sys::bpf_object__name(self.ptr.as_ptr()) };
+        let name = unsafe { CStr::from_ptr(ptr) };
+        name.to_str().map_err(Error::with_invalid_data)
+    }
+
     /// Parse the btf information associated with this bpf object.
     pub fn btf(&self) -> Result<Option<Btf<'_>>> {
         Btf::from_bpf_object(unsafe { &*self.ptr.as_ptr() })
     }
 
+    /// Get the extern symbols (kconfig values, kernel variables, and
+    /// kfuncs) the object references.
+    ///
+    /// See [`OpenObject::externs`] for details. Use
+    /// [`Object::resolve_externs`] for finding out what they resolved to.
+    pub fn externs(&self) -> Result<&[Extern]> {
+        self.externs.get()
+    }
+
+    /// Determine what the extern symbols the object references resolved
+    /// to, as reported by [`Extern::resolution`].
+    ///
+    /// Having been loaded successfully, all non-weak externs have been
+    /// resolved. The externs are looked up the way libbpf does when
+    /// loading, which involves parsing kernel BTF and, for kernel
+    /// variables declared without type, `/proc/kallsyms`. Hence, the
+    /// result is not cached.
+    pub fn resolve_externs(&self) -> Result<Vec<Extern>> {
+        let mut externs = self.externs()?.to_vec();
+        let () = Extern::resolve_all(&mut externs)
+            .context("failed to determine what externs resolved to")?;
+        Ok(externs)
+    }
+
+    /// Get typed access to the object's global variables, as shared with
+    /// its BPF programs.
+    ///
+    /// Variables in `.rodata` can be read but not modified.
+    pub fn globals(&mut self) -> Result<Globals<'_>> {
+        // SAFETY: Our object pointer is valid and borrowed mutably, and the
+        //         object is loaded.
+        unsafe { Globals::new(self.ptr, true) }
+    }
+
+    /// Get read access to the kernel configuration values declared by the
+    /// object's BPF programs, as resolved when loading it.
+    pub fn kconfig(&self) -> Result<Kconfig<'_>> {
+        // SAFETY: Our object pointer is valid and borrowed, and the object
+        //         is loaded.
+        unsafe { Kconfig::new(self.ptr) }
+    }
+
+    /// Pin all maps and programs of this object as well as the provided
+    /// links under the directory `path` on a bpffs.
+    ///
+    /// Each map and program is pinned as `<path>/<name>`. Links are not
+    /// owned by the object and so are provided alongside the name to pin
+    /// them under, as `<path>/links/<name>`. Should pinning any of them
+    /// fail, all pins created up to that point are removed again, leaving
+    /// the file system unchanged.
+    pub fn pin_all<'link, P, L>(&mut self, path: P, links: L) -> Result<()>
+    where
+        P: AsRef<Path>,
+        L: IntoIterator<Item = (&'link str, &'link mut Link)>,
+    {
+        let path = path.as_ref();
+        let path_c = util::path_to_cstring(path)?;
+        let path_ptr = path_c.as_ptr();
+
+        let ret = unsafe { libbpf_sys::bpf_object__pin(self.ptr.as_ptr(), path_ptr) };
+        let () = util::parse_ret(ret)?;
+
+        let result = pin_links(&path.join(LINK_PIN_DIR), links);
+        if result.is_err() {
+            let _ret = unsafe { libbpf_sys::bpf_object__unpin(self.ptr.as_ptr(), path_ptr) };
+        }
+        result
+    }
+
+    /// Unpin all maps, programs, and links previously pinned under the
+    /// directory `path` via [`Object::pin_all`].
+    pub fn unpin_all<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let path_c = util::path_to_cstring(path)?;
+        let path_ptr = path_c.as_ptr();
+
+        let ret = unsafe { libbpf_sys::bpf_object__unpin(self.ptr.as_ptr(), path_ptr) };
+        let () = util::parse_ret(ret)?;
+
+        let link_dir = path.join(LINK_PIN_DIR);
+        match fs::remove_dir_all(&link_dir) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Error::from(err))
+                .with_context(|| format!("failed to unpin links in {}", link_dir.display())),
+        }
+    }
+
     /// Get a reference to `Map` with the name `name`, if one exists.
     pub fn map<T: AsRef<str>>(&self, name: T) -> Option<&Map> {
         self.maps.get(name.as_ref())
@@ -443,6 +1122,91 @@ impl Object {
     pub fn progs_iter_mut(&mut self) -> impl Iterator<Item = &mut Program> {
         self.progs.values_mut()
     }
+
+    /// Split the object into separately borrowed views of its maps and
+    /// programs.
+    ///
+    /// This allows for holding on to a mutable reference to a map while
+    /// working with the object's programs, and vice versa.
+    ///
+    /// ```no_run
+    /// # use libbpf_rs::Object;
+    /// # let get_object = || -> Object { todo!() };
+    /// let mut object: Object = get_object();
+    /// let (mut maps, mut progs) = object.parts();
+    /// let config = maps.get_mut("config").unwrap();
+    /// for prog in progs.iter_mut() {
+    ///     let _link = prog.attach().unwrap();
+    ///     config.update(&[0; 4], &[1; 4], libbpf_rs::MapFlags::ANY).unwrap();
+    /// }
+    /// ```
+    pub fn parts(&mut self) -> (ObjectMaps<'_>, ObjectProgs<'_>) {
+        let maps = ObjectMaps {
+            maps: &mut self.maps,
+        };
+        let progs = ObjectProgs {
+            progs: &mut self.progs,
+        };
+        (maps, progs)
+    }
+}
+
+/// A mutable view of the maps of an [`Object`], as returned by
+/// [`Object::parts`].
+#[derive(Debug)]
+pub struct ObjectMaps<'obj> {
+    maps: &'obj mut HashMap<String, Map>,
+}
+
+impl ObjectMaps<'_> {
+    /// Get a reference to `Map` with the name `name`, if one exists.
+    pub fn get<T: AsRef<str>>(&self, name: T) -> Option<&Map> {
+        self.maps.get(name.as_ref())
+    }
+
+    /// Get a mutable reference to `Map` with the name `name`, if one exists.
+    pub fn get_mut<T: AsRef<str>>(&mut self, name: T) -> Option<&mut Map> {
+        self.maps.get_mut(name.as_ref())
+    }
+
+    /// Get an iterator over references to all `Map`s.
+    pub fn iter(&self) -> impl Iterator<Item = &Map> {
+        self.maps.values()
+    }
+
+    /// Get an iterator over mutable references to all `Map`s.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Map> {
+        self.maps.values_mut()
+    }
+}
+
+/// A mutable view of the programs of an [`Object`], as returned by
+/// [`Object::parts`].
+#[derive(Debug)]
+pub struct ObjectProgs<'obj> {
+    progs: &'obj mut HashMap<String, Program>,
+}
+
+impl ObjectProgs<'_> {
+    /// Get a reference to `Program` with the name `name`, if one exists.
+    pub fn get<T: AsRef<str>>(&self, name: T) -> Option<&Program> {
+        self.progs.get(name.as_ref())
+    }
+
+    /// Get a mutable reference to `Program` with the name `name`, if one exists.
+    pub fn get_mut<T: AsRef<str>>(&mut self, name: T) -> Option<&mut Program> {
+        self.progs.get_mut(name.as_ref())
+    }
+
+    /// Get an iterator over references to all `Program`s.
+    pub fn iter(&self) -> impl Iterator<Item = &Program> {
+        self.progs.values()
+    }
+
+    /// Get an iterator over mutable references to all `Program`s.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Program> {
+        self.progs.values_mut()
+    }
 }
 
 impl AsRawLibbpf for Object {
@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::ffi::c_char;
 use std::ffi::c_int;
 use std::ffi::c_void;
@@ -5,8 +6,10 @@ use std::io;
 use std::io::Write;
 use std::mem;
 use std::sync::Mutex;
+use std::time::Instant;
 
 use crate::util::LazyLock;
+use crate::Result;
 
 /// An enum representing the different supported print levels.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
@@ -42,16 +45,56 @@ fn default_callback(_lvl: PrintLevel, msg: String) {
     let _ = io::stderr().write(msg.as_bytes());
 }
 
+/// The print related state of the process.
+struct PrintState {
+    /// The user's callback along with the lowest level it is interested
+    /// in, if any.
+    callback: Option<(PrintLevel, PrintCallback)>,
+    /// The number of [`capture`]s currently in progress, across all
+    /// threads.
+    captures: usize,
+}
+
+impl PrintState {
+    /// Install our callback with libbpf if anybody is interested in its
+    /// output, or disable printing otherwise.
+    fn install(&self) {
+        let real_cb = if self.callback.is_some() || self.captures > 0 {
+            // # Safety
+            // outer_print_cb has the same function signature as libbpf_print_fn_t
+            unsafe {
+                mem::transmute::<*const (), libbpf_sys::libbpf_print_fn_t>(
+                    outer_print_cb as *const (),
+                )
+            }
+        } else {
+            None
+        };
+        let _prev = unsafe { libbpf_sys::libbpf_set_print(real_cb) };
+    }
+}
+
 // While we can't say that set_print is thread-safe, because we shouldn't assume that of
 // libbpf_set_print, we should still make sure that things are sane on the rust side of things.
-// Therefore we are using a lock to keep the log level and the callback in sync.
+// Therefore we are using a lock to keep the log level, the callback, and what is installed with
+// libbpf in sync.
 //
 // We don't do anything that can panic with the lock held, so we'll unconditionally unwrap() when
 // locking the mutex.
 //
 // Note that default print behavior ignores debug messages.
-static PRINT_CB: LazyLock<Mutex<Option<(PrintLevel, PrintCallback)>>> =
-    LazyLock::new(|| Mutex::new(Some((PrintLevel::Info, default_callback))));
+static PRINT_STATE: LazyLock<Mutex<PrintState>> = LazyLock::new(|| {
+    Mutex::new(PrintState {
+        callback: Some((PrintLevel::Info, default_callback)),
+        captures: 0,
+    })
+});
+
+thread_local! {
+    /// The message buffers of the captures in progress on the current
+    /// thread, innermost last.
+    static THREAD_CAPTURES: RefCell<Vec<Vec<CapturedMessage>>> = const { RefCell::new(Vec::new()) };
+}
 
 extern "C" fn outer_print_cb(
     level: libbpf_sys::libbpf_print_level,
@@ -63,14 +106,33 @@ extern "C" fn outer_print_cb(
     va_list: *mut c_void,
 ) -> c_int {
     let level = level.into();
-    if let Some((min_level, func)) = { *PRINT_CB.lock().unwrap() } {
-        if level <= min_level {
-            let msg = match unsafe { vsprintf::vsprintf(fmtstr, va_list) } {
-                Ok(s) => s,
-                Err(e) => format!("Failed to parse libbpf output: {e}"),
-            };
-            func(level, msg);
-        }
+    let callback = PRINT_STATE
+        .lock()
+        .unwrap()
+        .callback
+        .filter(|(min_level, _func)| level <= *min_level);
+    // libbpf invokes the callback on the thread performing the operation,
+    // which allows us to attribute messages to the captures of said
+    // thread.
+    let capturing = THREAD_CAPTURES.with(|captures| !captures.borrow().is_empty());
+    if callback.is_none() && !capturing {
+        return 0;
+    }
+
+    let msg = match unsafe { vsprintf::vsprintf(fmtstr, va_list) } {
+        Ok(s) => s,
+        Err(e) => format!("Failed to parse libbpf output: {e}"),
+    };
+    if capturing {
+        let () = THREAD_CAPTURES.with(|captures| {
+            let now = Instant::now();
+            for msgs in captures.borrow_mut().iter_mut() {
+                let () = msgs.push((now, level, msg.clone()));
+            }
+        });
+    }
+    if let Some((_min_level, func)) = callback {
+        func(level, msg);
     }
     0 // return value is ignored by libbpf
 }
@@ -102,6 +164,9 @@ extern "C" fn outer_print_cb(
 /// set_print(Some((PrintLevel::Debug, print_to_log)));
 /// ```
 ///
+/// With the `log` feature enabled, `set_print_to_log` does the same (and
+/// `set_print_to_tracing` likewise for the `tracing` feature).
+///
 /// To disable printing completely:
 ///
 /// ```
@@ -121,13 +186,9 @@ extern "C" fn outer_print_cb(
 pub fn set_print(
     mut callback: Option<(PrintLevel, PrintCallback)>,
 ) -> Option<(PrintLevel, PrintCallback)> {
-    // # Safety
-    // outer_print_cb has the same function signature as libbpf_print_fn_t
-    let real_cb: libbpf_sys::libbpf_print_fn_t =
-        unsafe { Some(mem::transmute(outer_print_cb as *const ())) };
-    let real_cb: libbpf_sys::libbpf_print_fn_t = callback.as_ref().and(real_cb);
-    mem::swap(&mut callback, &mut *PRINT_CB.lock().unwrap());
-    unsafe { libbpf_sys::libbpf_set_print(real_cb) };
+    let mut state = PRINT_STATE.lock().unwrap();
+    mem::swap(&mut callback, &mut state.callback);
+    let () = state.install();
     callback
 }
 
@@ -146,5 +207,167 @@ pub fn set_print(
 /// set_print(prev);
 /// ```
 pub fn get_print() -> Option<(PrintLevel, PrintCallback)> {
-    *PRINT_CB.lock().unwrap()
+    PRINT_STATE.lock().unwrap().callback
+}
+
+/// Strip the `libbpf: ` prefix and the trailing newline off a libbpf message,
+/// as the log target conveys the origin already.
+#[cfg(any(feature = "log", feature = "tracing"))]
+fn trim_message(msg: &str) -> &str {
+    let msg = msg.strip_prefix("libbpf: ").unwrap_or(msg);
+    msg.trim_end_matches('\n')
+}
+
+#[cfg(feature = "log")]
+fn log_callback(level: PrintLevel, msg: String) {
+    let level = match level {
+        PrintLevel::Debug => log::Level::Debug,
+        PrintLevel::Info => log::Level::Info,
+        PrintLevel::Warn => log::Level::Warn,
+    };
+    log::log!(target: "libbpf", level, "{}", trim_message(&msg));
+}
+
+/// Forward all libbpf output to the [`log`](https://crates.io/crates/log)
+/// crate, using the `libbpf` target.
+///
+/// libbpf's debug, info, and warning messages are logged with the
+/// corresponding [`log::Level`]. Filtering is left to the logger. The
+/// previously set callback is returned, as for [`set_print`].
+#[cfg(feature = "log")]
+pub fn set_print_to_log() -> Option<(PrintLevel, PrintCallback)> {
+    set_print(Some((PrintLevel::Debug, log_callback)))
+}
+
+#[cfg(feature = "tracing")]
+fn tracing_callback(level: PrintLevel, msg: String) {
+    let msg = trim_message(&msg);
+    // `tracing` requires levels to be known at compile time.
+    match level {
+        PrintLevel::Debug => tracing::debug!(target: "libbpf", "{msg}"),
+        PrintLevel::Info => tracing::info!(target: "libbpf", "{msg}"),
+        PrintLevel::Warn => tracing::warn!(target: "libbpf", "{msg}"),
+    }
+}
+
+/// Forward all libbpf output to the
+/// [`tracing`](https://crates.io/crates/tracing) crate, as events with the
+/// `libbpf` target.
+///
+/// libbpf's debug, info, and warning messages are emitted with the
+/// corresponding [`tracing::Level`]. Filtering is left to the subscriber.
+/// The previously set callback is returned, as for [`set_print`].
+#[cfg(feature = "tracing")]
+pub fn set_print_to_tracing() -> Option<(PrintLevel, PrintCallback)> {
+    set_print(Some((PrintLevel::Debug, tracing_callback)))
+}
+
+/// A libbpf message captured by [`capture`], along with the time it was
+/// emitted.
+pub(crate) type CapturedMessage = (Instant, PrintLevel, String);
+
+/// A guard ending a [`capture`] on drop, even if the captured operation
+/// panicked.
+struct CaptureGuard;
+
+impl CaptureGuard {
+    fn new() -> Self {
+        {
+            let mut state = PRINT_STATE.lock().unwrap();
+            state.captures += 1;
+            if state.captures == 1 {
+                let () = state.install();
+            }
+        }
+
+        let () = THREAD_CAPTURES.with(|captures| captures.borrow_mut().push(Vec::new()));
+        Self
+    }
+
+    fn finish(self) -> Vec<CapturedMessage> {
+        // The buffer itself is removed once the guard is dropped.
+        // SANITY: The guard pushed a buffer on creation, which only it
+        //         removes.
+        THREAD_CAPTURES.with(|captures| mem::take(captures.borrow_mut().last_mut().unwrap()))
+    }
+}
+
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        let _msgs = THREAD_CAPTURES.with(|captures| captures.borrow_mut().pop());
+
+        let mut state = PRINT_STATE.lock().unwrap();
+        state.captures -= 1;
+        if state.captures == 0 {
+            let () = state.install();
+        }
+    }
+}
+
+/// Run `f`, capturing all libbpf messages (including debug ones) emitted by
+/// the calling thread in the meantime.
+///
+/// Messages are still passed on to the print callback that is currently
+/// set up, which is not affected by the capture. Captures may be nested and
+/// may happen concurrently on multiple threads.
+pub(crate) fn capture<R>(f: impl FnOnce() -> R) -> (R, Vec<CapturedMessage>) {
+    let guard = CaptureGuard::new();
+    let result = f();
+    let msgs = guard.finish();
+    (result, msgs)
+}
+
+/// Run `f`, attaching the libbpf output it caused to the error it returns,
+/// if any.
+///
+/// libbpf reports details about failures (e.g., which relocation could not
+/// be applied or why the kernel rejected a map) only via its print
+/// callback, which is global to the process. This function captures the
+/// messages (at all levels) emitted by the calling thread while `f` runs,
+/// so that they can be attributed to the failing operation even if other
+/// threads use libbpf concurrently. The output can be retrieved via
+/// [`Error::libbpf_output`][crate::Error::libbpf_output] and is included in the error's
+/// [`Debug`][std::fmt::Debug] representation.
+///
+/// Messages are still passed on to the callback set up via [`set_print`].
+///
+/// ```no_run
+/// # use libbpf_rs::capture_output;
+/// # use libbpf_rs::ObjectBuilder;
+/// let result = capture_output(|| ObjectBuilder::default().open_file("prog.bpf.o"));
+/// if let Err(err) = result {
+///     eprintln!("{}", err.libbpf_output().unwrap_or_default());
+/// }
+/// ```
+pub fn capture_output<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    let (result, msgs) = capture(f);
+    result.map_err(|err| {
+        let output = msgs
+            .iter()
+            .map(|(_time, _level, msg)| msg.as_str())
+            .collect::<String>();
+        err.with_libbpf_output(output)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::panic;
+
+    /// Check that a capture ends when the captured operation panics.
+    #[test]
+    fn capture_panic() {
+        let result = panic::catch_unwind(|| capture(|| panic!("captured operation failed")));
+        assert!(result.is_err());
+        assert!(THREAD_CAPTURES.with(|captures| captures.borrow().is_empty()));
+
+        let ((), msgs) = capture(|| ());
+        assert!(msgs.is_empty());
+        assert!(THREAD_CAPTURES.with(|captures| captures.borrow().is_empty()));
+    }
 }
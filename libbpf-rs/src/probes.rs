@@ -0,0 +1,357 @@
+//! Probe the running kernel for BPF feature support.
+//!
+//! Probing allows for branching on kernel support up front, e.g., to pick
+//! an alternative implementation, instead of having to interpret load
+//! failures after the fact:
+//! ```no_run
+//! use libbpf_rs::probes;
+//! use libbpf_rs::ProgramType;
+//!
+//! if probes::prog_type_supported(ProgramType::Tracing).unwrap() {
+//!     // Use fentry/fexit programs.
+//! } else {
+//!     // Fall back to kprobes.
+//! }
+//! ```
+//!
+//! Make sure the process has the required set of `CAP_*` permissions (or
+//! runs as root) when probing.
+
+use std::ffi::c_char;
+use std::ffi::CStr;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs;
+use std::io;
+use std::mem;
+use std::mem::size_of;
+use std::os::unix::io::AsRawFd as _;
+use std::os::unix::io::FromRawFd as _;
+use std::os::unix::io::OwnedFd;
+use std::path::Path;
+use std::ptr;
+
+use libbpf_sys::bpf_func_id;
+
+use crate::util;
+use crate::Error;
+use crate::ErrorKind;
+use crate::MapType;
+use crate::ProgramType;
+use crate::Result;
+
+/// Check whether the kernel supports programs of type `prog_type`.
+///
+/// This is equivalent to [`ProgramType::is_supported`].
+pub fn prog_type_supported(prog_type: ProgramType) -> Result<bool> {
+    prog_type.is_supported()
+}
+
+/// Check whether the kernel supports maps of type `map_type`.
+///
+/// This is equivalent to [`MapType::is_supported`].
+pub fn map_type_supported(map_type: MapType) -> Result<bool> {
+    map_type.is_supported()
+}
+
+/// Check whether the kernel supports the use of the BPF helper `helper_id`
+/// (e.g., [`libbpf_sys::BPF_FUNC_map_lookup_elem`]) from programs of type
+/// `prog_type`.
+///
+/// This is equivalent to [`ProgramType::is_helper_supported`].
+pub fn helper_supported(prog_type: ProgramType, helper_id: bpf_func_id) -> Result<bool> {
+    prog_type.is_helper_supported(helper_id)
+}
+
+/// Check whether the kernel accounts the memory of BPF maps and programs
+/// to memory cgroups (Linux 5.11+), instead of against the
+/// `RLIMIT_MEMLOCK` resource limit.
+pub fn memcg_accounting_supported() -> Result<bool> {
+    // Memory cgroup based accounting was introduced together with the
+    // `bpf_ktime_get_coarse_ns` helper, which libbpf uses for detecting it
+    // as well.
+    helper_supported(
+        ProgramType::SocketFilter,
+        libbpf_sys::BPF_FUNC_ktime_get_coarse_ns,
+    )
+}
+
+/// The version of a Linux kernel, as in `6.8.12`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KernelVersion {
+    /// The major version.
+    pub major: u32,
+    /// The minor version.
+    pub minor: u32,
+    /// The patch level.
+    pub patch: u32,
+}
+
+impl KernelVersion {
+    /// Parse the version from a kernel release string, as reported by
+    /// `uname -r` (e.g., `6.8.0-45-generic`).
+    pub fn from_release(release: &str) -> Option<Self> {
+        let version = release
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()?;
+        let mut parts = version.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        let patch = parts
+            .next()
+            .and_then(|patch| patch.parse().ok())
+            .unwrap_or(0);
+        Some(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl Display for KernelVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A report of the BPF related capabilities of the running kernel, as
+/// gathered by [`SystemCapabilities::detect`].
+///
+/// The report is meant for feature gating at startup as well as for
+/// inclusion in bug reports. With the `serde` feature enabled, it can be
+/// serialized.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SystemCapabilities {
+    /// The kernel release, as reported by `uname -r`.
+    pub kernel_release: String,
+    /// The kernel version, if it could be parsed from the release.
+    pub kernel_version: Option<KernelVersion>,
+    /// Whether kernel BTF is available at `/sys/kernel/btf/vmlinux`.
+    pub vmlinux_btf: bool,
+    /// The supported program types.
+    pub prog_types: Vec<ProgramType>,
+    /// The supported map types.
+    pub map_types: Vec<MapType>,
+    /// Whether BPF ring buffers are supported.
+    pub ringbuf: bool,
+    /// Whether BPF cookies (`bpf_get_attach_cookie`) are supported.
+    pub bpf_cookie: bool,
+    /// Whether kprobe-multi links are supported.
+    pub kprobe_multi: bool,
+    /// Whether tcx attachments are supported.
+    pub tcx: bool,
+    /// Whether the BPF JIT compiler is enabled, if it could be determined.
+    pub jit_enabled: Option<bool>,
+}
+
+/// Probe a feature via `probe`, treating lack of support by libbpf as the
+/// feature being unsupported.
+fn probe_supported<F>(probe: F) -> Result<bool>
+where
+    F: FnOnce() -> Result<bool>,
+{
+    match probe() {
+        Err(err) if err.kind() == ErrorKind::Unsupported => Ok(false),
+        result => result,
+    }
+}
+
+/// Check whether the kernel supports kprobe-multi links.
+fn kprobe_multi_supported() -> Result<bool> {
+    // A program doing nothing but `return 0`.
+    let insns = [
+        libbpf_sys::bpf_insn {
+            code: (libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K) as _,
+            ..Default::default()
+        },
+        libbpf_sys::bpf_insn {
+            code: (libbpf_sys::BPF_JMP | libbpf_sys::BPF_EXIT) as _,
+            ..Default::default()
+        },
+    ];
+    let mut opts = libbpf_sys::bpf_prog_load_opts {
+        sz: size_of::<libbpf_sys::bpf_prog_load_opts>() as _,
+        expected_attach_type: libbpf_sys::BPF_TRACE_KPROBE_MULTI,
+        ..Default::default()
+    };
+    let ret = unsafe {
+        libbpf_sys::bpf_prog_load(
+            libbpf_sys::BPF_PROG_TYPE_KPROBE,
+            ptr::null(),
+            b"GPL\0".as_ptr().cast(),
+            insns.as_ptr(),
+            insns.len() as _,
+            &mut opts,
+        )
+    };
+    let fd = util::parse_ret_i32(ret)?;
+    // SAFETY: `bpf_prog_load` returned a valid file descriptor that nobody
+    //         else owns.
+    let prog = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // Attach to a symbol that does not exist: kernels supporting
+    // kprobe-multi links fail the symbol lookup, while all others reject
+    // the attach type or the request as a whole.
+    let mut syms = [b"libbpf_rs_nonexistent_symbol\0".as_ptr().cast::<c_char>()];
+    let mut opts = libbpf_sys::bpf_link_create_opts {
+        sz: size_of::<libbpf_sys::bpf_link_create_opts>() as _,
+        ..Default::default()
+    };
+    opts.__bindgen_anon_1.kprobe_multi =
+        libbpf_sys::bpf_link_create_opts__bindgen_ty_1__bindgen_ty_2 {
+            cnt: syms.len() as _,
+            syms: syms.as_mut_ptr(),
+            ..Default::default()
+        };
+    let ret = unsafe {
+        libbpf_sys::bpf_link_create(
+            prog.as_raw_fd(),
+            0,
+            libbpf_sys::BPF_TRACE_KPROBE_MULTI,
+            &opts,
+        )
+    };
+    if ret >= 0 {
+        // SAFETY: `bpf_link_create` returned a valid file descriptor that
+        //         nobody else owns.
+        let _link = unsafe { OwnedFd::from_raw_fd(ret) };
+        return Ok(true);
+    }
+    Ok(matches!(-ret, libc::ESRCH | libc::ENOENT))
+}
+
+/// Check whether the kernel supports tcx attachments.
+fn tcx_supported() -> Result<bool> {
+    let mut attach_flags = 0;
+    let mut prog_cnt = 0;
+    // Query the programs attached to the loopback device, which exists in
+    // every network namespace. Kernels not supporting tcx reject the attach
+    // type.
+    let ret = unsafe {
+        libbpf_sys::bpf_prog_query(
+            1,
+            libbpf_sys::BPF_TCX_INGRESS,
+            0,
+            &mut attach_flags,
+            ptr::null_mut(),
+            &mut prog_cnt,
+        )
+    };
+    match ret {
+        0 => Ok(true),
+        _ if -ret == libc::EINVAL => Ok(false),
+        _ => Err(Error::from_raw_os_error(-ret)),
+    }
+}
+
+/// Retrieve the release of the running kernel.
+fn kernel_release() -> Result<String> {
+    let mut uts = unsafe { mem::zeroed::<libc::utsname>() };
+    let rc = unsafe { libc::uname(&mut uts) };
+    if rc != 0 {
+        return Err(Error::from(io::Error::last_os_error()));
+    }
+    // SAFETY: `uname` NUL terminates the release.
+    let release = unsafe { CStr::from_ptr(uts.release.as_ptr()) };
+    Ok(release.to_string_lossy().into_owned())
+}
+
+impl SystemCapabilities {
+    /// Detect the capabilities of the running kernel.
+    ///
+    /// Make sure the process has the required set of `CAP_*` permissions
+    /// (or runs as root), as detection involves loading programs and
+    /// creating maps.
+    pub fn detect() -> Result<Self> {
+        let kernel_release = kernel_release()?;
+        let kernel_version = KernelVersion::from_release(&kernel_release);
+
+        let mut prog_types = Vec::new();
+        for prog_type in
+            (1..=ProgramType::Syscall as u32).filter_map(|ty| ProgramType::try_from(ty).ok())
+        {
+            if probe_supported(|| prog_type_supported(prog_type))? {
+                let () = prog_types.push(prog_type);
+            }
+        }
+
+        let mut map_types = Vec::new();
+        for map_type in
+            (1..=MapType::UserRingBuf as u32).filter_map(|ty| MapType::try_from(ty).ok())
+        {
+            if probe_supported(|| map_type_supported(map_type))? {
+                let () = map_types.push(map_type);
+            }
+        }
+
+        let ringbuf = map_types.contains(&MapType::RingBuf);
+        let bpf_cookie = probe_supported(|| {
+            helper_supported(ProgramType::Kprobe, libbpf_sys::BPF_FUNC_get_attach_cookie)
+        })?;
+        let jit_enabled = fs::read_to_string("/proc/sys/net/core/bpf_jit_enable")
+            .ok()
+            .and_then(|value| value.trim().parse::<u32>().ok())
+            .map(|value| value != 0);
+
+        let slf = Self {
+            kernel_release,
+            kernel_version,
+            vmlinux_btf: Path::new("/sys/kernel/btf/vmlinux").exists(),
+            prog_types,
+            map_types,
+            ringbuf,
+            bpf_cookie,
+            kprobe_multi: kprobe_multi_supported()?,
+            tcx: tcx_supported()?,
+            jit_enabled,
+        };
+        Ok(slf)
+    }
+
+    /// Check whether programs of type `prog_type` are supported.
+    pub fn supports_prog_type(&self, prog_type: ProgramType) -> bool {
+        self.prog_types
+            .iter()
+            .any(|ty| *ty as u32 == prog_type as u32)
+    }
+
+    /// Check whether maps of type `map_type` are supported.
+    pub fn supports_map_type(&self, map_type: MapType) -> bool {
+        self.map_types.contains(&map_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that we can parse kernel versions from release strings.
+    #[test]
+    fn kernel_version_parsing() {
+        let version = |major, minor, patch| KernelVersion {
+            major,
+            minor,
+            patch,
+        };
+
+        assert_eq!(
+            KernelVersion::from_release("6.8.0-45-generic"),
+            Some(version(6, 8, 0))
+        );
+        assert_eq!(
+            KernelVersion::from_release("5.15.167.4-microsoft-standard-WSL2"),
+            Some(version(5, 15, 167))
+        );
+        assert_eq!(
+            KernelVersion::from_release("6.12-rc1"),
+            Some(version(6, 12, 0))
+        );
+        assert_eq!(KernelVersion::from_release("foo"), None);
+        assert!(version(5, 15, 0) < version(6, 1, 0));
+        assert_eq!(version(6, 1, 2).to_string(), "6.1.2");
+    }
+}
@@ -1,5 +1,6 @@
 use std::ffi::c_void;
 use std::ffi::CStr;
+use std::io;
 use std::mem;
 use std::mem::size_of;
 use std::mem::size_of_val;
@@ -7,6 +8,7 @@ use std::os::unix::io::AsFd;
 use std::os::unix::io::AsRawFd;
 use std::os::unix::io::BorrowedFd;
 use std::os::unix::io::FromRawFd;
+use std::os::unix::io::IntoRawFd;
 use std::os::unix::io::OwnedFd;
 use std::path::Path;
 use std::ptr;
@@ -17,11 +19,20 @@ use libbpf_sys::bpf_func_id;
 use num_enum::TryFromPrimitive;
 use strum_macros::Display;
 
+use crate::btf::BtfType;
+use crate::btf::TypeId;
+use crate::error::LibbpfCallExt as _;
+use crate::query::ProgInfoQueryOptions;
+use crate::query::ProgramInfo;
 use crate::util;
 use crate::AsRawLibbpf;
+use crate::Btf;
 use crate::Error;
+use crate::IntoCgroupFd;
+use crate::IntoIfindex;
 use crate::Link;
 use crate::Result;
+use crate::XdpFlags;
 
 /// Options to optionally be provided when attaching to a uprobe.
 #[derive(Clone, Debug, Default)]
@@ -45,6 +56,331 @@ pub struct UprobeOpts {
     pub _non_exhaustive: (),
 }
 
+/// Options to optionally be provided when attaching to multiple kprobes at
+/// once.
+#[derive(Clone, Debug, Default)]
+pub struct KprobeMultiOpts {
+    /// List of symbol names to attach to.
+    pub symbols: Vec<String>,
+    /// Array of custom user-provided values accessible through
+    /// `bpf_get_attach_cookie`. If provided, it has to contain one entry per
+    /// symbol.
+    pub cookies: Vec<u64>,
+    /// kprobes are return probes, invoked at function return time.
+    pub retprobe: bool,
+    /// Attach in "session" mode, in which the program is invoked both at
+    /// function entry and return. The return value of the entry invocation
+    /// decides whether the return invocation happens, and both invocations
+    /// share the same cookie. Requires a `SEC("kprobe.session")` program
+    /// and cannot be combined with `retprobe`.
+    pub session: bool,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+/// A socket address hook of a cgroup, as attached to by
+/// `SEC("cgroup/<hook>")` programs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CgroupSockAddrHook {
+    /// `connect(2)` on IPv4 sockets (`cgroup/connect4`).
+    Connect4,
+    /// `connect(2)` on IPv6 sockets (`cgroup/connect6`).
+    Connect6,
+    /// `bind(2)` on IPv4 sockets (`cgroup/bind4`).
+    Bind4,
+    /// `bind(2)` on IPv6 sockets (`cgroup/bind6`).
+    Bind6,
+    /// `sendmsg(2)` on unconnected IPv4 UDP sockets (`cgroup/sendmsg4`).
+    Sendmsg4,
+    /// `sendmsg(2)` on unconnected IPv6 UDP sockets (`cgroup/sendmsg6`).
+    Sendmsg6,
+    /// `recvmsg(2)` on IPv4 UDP sockets (`cgroup/recvmsg4`).
+    Recvmsg4,
+    /// `recvmsg(2)` on IPv6 UDP sockets (`cgroup/recvmsg6`).
+    Recvmsg6,
+    /// `getpeername(2)` on IPv4 sockets (`cgroup/getpeername4`).
+    Getpeername4,
+    /// `getpeername(2)` on IPv6 sockets (`cgroup/getpeername6`).
+    Getpeername6,
+    /// `getsockname(2)` on IPv4 sockets (`cgroup/getsockname4`).
+    Getsockname4,
+    /// `getsockname(2)` on IPv6 sockets (`cgroup/getsockname6`).
+    Getsockname6,
+}
+
+impl CgroupSockAddrHook {
+    /// The attach type programs for this hook have to be loaded with.
+    pub fn attach_type(&self) -> ProgramAttachType {
+        match self {
+            Self::Connect4 => ProgramAttachType::CgroupInet4Connect,
+            Self::Connect6 => ProgramAttachType::CgroupInet6Connect,
+            Self::Bind4 => ProgramAttachType::CgroupInet4Bind,
+            Self::Bind6 => ProgramAttachType::CgroupInet6Bind,
+            Self::Sendmsg4 => ProgramAttachType::CgroupUdp4Sendmsg,
+            Self::Sendmsg6 => ProgramAttachType::CgroupUdp6Sendmsg,
+            Self::Recvmsg4 => ProgramAttachType::CgroupUdp4Recvmsg,
+            Self::Recvmsg6 => ProgramAttachType::CgroupUdp6Recvmsg,
+            Self::Getpeername4 => ProgramAttachType::CgroupInet4Getpeername,
+            Self::Getpeername6 => ProgramAttachType::CgroupInet6Getpeername,
+            Self::Getsockname4 => ProgramAttachType::CgroupInet4Getsockname,
+            Self::Getsockname6 => ProgramAttachType::CgroupInet6Getsockname,
+        }
+    }
+
+    /// Map an attach type to the corresponding hook, if any.
+    fn from_attach_type(attach_type: &ProgramAttachType) -> Option<Self> {
+        let hook = match attach_type {
+            ProgramAttachType::CgroupInet4Connect => Self::Connect4,
+            ProgramAttachType::CgroupInet6Connect => Self::Connect6,
+            ProgramAttachType::CgroupInet4Bind => Self::Bind4,
+            ProgramAttachType::CgroupInet6Bind => Self::Bind6,
+            ProgramAttachType::CgroupUdp4Sendmsg => Self::Sendmsg4,
+            ProgramAttachType::CgroupUdp6Sendmsg => Self::Sendmsg6,
+            ProgramAttachType::CgroupUdp4Recvmsg => Self::Recvmsg4,
+            ProgramAttachType::CgroupUdp6Recvmsg => Self::Recvmsg6,
+            ProgramAttachType::CgroupInet4Getpeername => Self::Getpeername4,
+            ProgramAttachType::CgroupInet6Getpeername => Self::Getpeername6,
+            ProgramAttachType::CgroupInet4Getsockname => Self::Getsockname4,
+            ProgramAttachType::CgroupInet6Getsockname => Self::Getsockname6,
+            _ => return None,
+        };
+        Some(hook)
+    }
+}
+
+/// The order in which a cgroup iterator traverses cgroups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum CgroupIterOrder {
+    /// Only visit the provided cgroup itself.
+    SelfOnly = libbpf_sys::BPF_CGROUP_ITER_SELF_ONLY,
+    /// Visit the provided cgroup and its descendants, parents first.
+    DescendantsPre = libbpf_sys::BPF_CGROUP_ITER_DESCENDANTS_PRE,
+    /// Visit the provided cgroup and its descendants, children first.
+    DescendantsPost = libbpf_sys::BPF_CGROUP_ITER_DESCENDANTS_POST,
+    /// Visit the provided cgroup and its ancestors, up to the root.
+    AncestorsUp = libbpf_sys::BPF_CGROUP_ITER_ANCESTORS_UP,
+}
+
+/// The entity a [BPF
+/// Iterator](https://www.kernel.org/doc/html/latest/bpf/bpf_iterators.html)
+/// iterates over.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum IterOpts<'fd> {
+    /// Iterate over the elements of the map referred to by the file
+    /// descriptor, as done by `SEC("iter/bpf_map_elem")` programs.
+    Map(BorrowedFd<'fd>),
+    /// Iterate over the cgroups reached from the cgroup referred to by the
+    /// file descriptor in the given order, as done by `SEC("iter/cgroup")`
+    /// programs.
+    Cgroup {
+        /// A file descriptor referring to the cgroup to start at.
+        cgroup_fd: BorrowedFd<'fd>,
+        /// The traversal order.
+        order: CgroupIterOrder,
+    },
+}
+
+impl From<IterOpts<'_>> for libbpf_sys::bpf_iter_link_info {
+    fn from(opts: IterOpts<'_>) -> Self {
+        let mut linkinfo = libbpf_sys::bpf_iter_link_info::default();
+        match opts {
+            IterOpts::Map(map_fd) => {
+                linkinfo.map.map_fd = map_fd.as_raw_fd() as _;
+            }
+            IterOpts::Cgroup { cgroup_fd, order } => {
+                linkinfo.cgroup.cgroup_fd = cgroup_fd.as_raw_fd() as _;
+                linkinfo.cgroup.order = order as _;
+            }
+        }
+        linkinfo
+    }
+}
+
+/// The side of a netkit device pair a program is attached to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetkitTarget {
+    /// The primary device, i.e., the one residing in the host namespace.
+    Primary,
+    /// The peer device, e.g., the one residing in a container's namespace.
+    Peer,
+}
+
+impl NetkitTarget {
+    /// The attach type programs for this target have to be loaded with.
+    pub fn attach_type(&self) -> ProgramAttachType {
+        match self {
+            Self::Primary => ProgramAttachType::NetkitPrimary,
+            Self::Peer => ProgramAttachType::NetkitPeer,
+        }
+    }
+}
+
+/// An existing program or link to position a new attachment relative to,
+/// for attachment types that support multiple ordered programs (such as
+/// netkit).
+#[derive(Clone, Copy, Debug)]
+pub enum LinkAnchor<'fd> {
+    /// The program referred to by the file descriptor.
+    Program(BorrowedFd<'fd>),
+    /// The program with the given ID.
+    ProgramId(u32),
+    /// The link referred to by the file descriptor.
+    Link(BorrowedFd<'fd>),
+    /// The link with the given ID.
+    LinkId(u32),
+}
+
+impl LinkAnchor<'_> {
+    /// Retrieve the flags, relative file descriptor, and relative ID
+    /// describing the anchor to the kernel.
+    fn to_raw(self) -> (u32, u32, u32) {
+        let link = libbpf_sys::BPF_F_LINK;
+        match self {
+            Self::Program(fd) => (0, fd.as_raw_fd() as _, 0),
+            Self::ProgramId(id) => (libbpf_sys::BPF_F_ID, 0, id),
+            Self::Link(fd) => (link, fd.as_raw_fd() as _, 0),
+            Self::LinkId(id) => (link | libbpf_sys::BPF_F_ID, 0, id),
+        }
+    }
+}
+
+/// The position of a new attachment among the programs already attached,
+/// for attachment types that support multiple ordered programs (such as
+/// netkit).
+#[derive(Clone, Copy, Debug, Default)]
+pub enum LinkPosition<'fd> {
+    /// Run before all programs attached so far.
+    First,
+    /// Run after all programs attached so far.
+    #[default]
+    Last,
+    /// Run directly before the anchor.
+    Before(LinkAnchor<'fd>),
+    /// Run directly after the anchor.
+    After(LinkAnchor<'fd>),
+}
+
+impl LinkPosition<'_> {
+    /// Retrieve the flags, relative file descriptor, and relative ID
+    /// describing the position to the kernel.
+    fn to_raw(self) -> (u32, u32, u32) {
+        match self {
+            Self::First => (libbpf_sys::BPF_F_BEFORE, 0, 0),
+            Self::Last => (0, 0, 0),
+            Self::Before(anchor) => {
+                let (flags, fd, id) = anchor.to_raw();
+                (libbpf_sys::BPF_F_BEFORE | flags, fd, id)
+            }
+            Self::After(anchor) => {
+                let (flags, fd, id) = anchor.to_raw();
+                (libbpf_sys::BPF_F_AFTER | flags, fd, id)
+            }
+        }
+    }
+}
+
+/// Options to optionally be provided when attaching to a netkit device.
+#[derive(Clone, Debug, Default)]
+pub struct NetkitOpts<'fd> {
+    /// The position among the programs already attached to the device.
+    pub position: LinkPosition<'fd>,
+    /// The revision the set of programs attached to the device is expected
+    /// to have, or `0` to not check it. If another party changed the set in
+    /// the meantime, attachment fails with `ESTALE`.
+    pub expected_revision: u64,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl From<NetkitOpts<'_>> for libbpf_sys::bpf_netkit_opts {
+    fn from(opts: NetkitOpts<'_>) -> Self {
+        let NetkitOpts {
+            position,
+            expected_revision,
+            _non_exhaustive,
+        } = opts;
+
+        let (flags, relative_fd, relative_id) = position.to_raw();
+        libbpf_sys::bpf_netkit_opts {
+            sz: size_of::<Self>() as _,
+            flags,
+            relative_fd,
+            relative_id,
+            expected_revision,
+            ..Default::default()
+        }
+    }
+}
+
+/// The protocol family of the packets a netfilter program processes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u32)]
+pub enum NetfilterProtocolFamily {
+    /// IPv4 packets (`NFPROTO_IPV4`).
+    #[default]
+    Ipv4 = 2,
+    /// IPv6 packets (`NFPROTO_IPV6`).
+    Ipv6 = 10,
+}
+
+/// The netfilter hook a netfilter program is attached to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u32)]
+pub enum NetfilterHook {
+    /// Packets before routing (`NF_INET_PRE_ROUTING`).
+    #[default]
+    PreRouting = 0,
+    /// Packets destined for the local host (`NF_INET_LOCAL_IN`).
+    LocalIn = 1,
+    /// Packets being forwarded (`NF_INET_FORWARD`).
+    Forward = 2,
+    /// Packets originating from the local host (`NF_INET_LOCAL_OUT`).
+    LocalOut = 3,
+    /// Packets after routing (`NF_INET_POST_ROUTING`).
+    PostRouting = 4,
+}
+
+/// The priority of netfilter's defragmentation hook
+/// (`NF_IP_PRI_CONNTRACK_DEFRAG`).
+const NF_IP_PRI_CONNTRACK_DEFRAG: i32 = -400;
+
+/// Options to be provided when attaching to a netfilter hook.
+#[derive(Clone, Debug, Default)]
+pub struct NetfilterOpts {
+    /// The protocol family of the packets to process.
+    pub protocol_family: NetfilterProtocolFamily,
+    /// The hook to attach to.
+    pub hook: NetfilterHook,
+    /// The priority of the program among all functions registered for the
+    /// hook, with lower values running first. The extreme values
+    /// `i32::MIN` and `i32::MAX` are reserved.
+    pub priority: i32,
+    /// Have packets defragmented before the program processes them
+    /// (`BPF_F_NETFILTER_IP_DEFRAG`).
+    ///
+    /// Defragmentation happens at priority `-400`, so `priority` has to be
+    /// greater than that.
+    pub ip_defrag: bool,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+/// The kind of memory access a hardware breakpoint triggers on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum BreakpointType {
+    /// Trigger on reads of the watched memory.
+    Read = 1,
+    /// Trigger on writes to the watched memory.
+    Write = 2,
+    /// Trigger on reads of or writes to the watched memory.
+    ReadWrite = 3,
+    /// Trigger on execution of the instruction at the watched address.
+    Execute = 4,
+}
+
 /// Options to optionally be provided when attaching to a USDT.
 #[derive(Clone, Debug, Default)]
 pub struct UsdtOpts {
@@ -96,6 +432,33 @@ impl From<TracepointOpts> for libbpf_sys::bpf_tracepoint_opts {
     }
 }
 
+/// Options to optionally be provided when attaching to a fentry/fexit
+/// trampoline.
+#[derive(Clone, Debug, Default)]
+pub struct TraceOpts {
+    /// Custom user-provided value accessible through `bpf_get_attach_cookie`.
+    pub cookie: u64,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl From<TraceOpts> for libbpf_sys::bpf_trace_opts {
+    fn from(opts: TraceOpts) -> Self {
+        let TraceOpts {
+            cookie,
+            _non_exhaustive,
+        } = opts;
+
+        #[allow(clippy::needless_update)]
+        libbpf_sys::bpf_trace_opts {
+            sz: size_of::<Self>() as _,
+            cookie,
+            // bpf_trace_opts might have padding fields on some platform
+            ..Default::default()
+        }
+    }
+}
+
 /// Represents a parsed but not yet loaded BPF program.
 ///
 /// This object exposes operations that need to happen before the program is loaded.
@@ -141,6 +504,37 @@ impl OpenProgram {
         }
     }
 
+    /// Set up this program to be attached to the cgroup socket address hook
+    /// `hook`, by setting its type and expected attach type accordingly.
+    ///
+    /// This is only necessary for programs not already defined with a
+    /// matching `SEC("cgroup/<hook>")`.
+    pub fn set_cgroup_sock_addr_hook(&mut self, hook: CgroupSockAddrHook) {
+        self.set_prog_type(ProgramType::CgroupSockAddr);
+        self.set_attach_type(hook.attach_type());
+    }
+
+    /// Set up this program to be attached to the `target` side of a netkit
+    /// device pair, by setting its type and expected attach type
+    /// accordingly.
+    ///
+    /// This is only necessary for programs not already defined with a
+    /// matching `SEC("netkit/primary")` or `SEC("netkit/peer")`.
+    pub fn set_netkit_target(&mut self, target: NetkitTarget) {
+        self.set_prog_type(ProgramType::SchedCls);
+        self.set_attach_type(target.attach_type());
+    }
+
+    /// Set the index of the network interface to offload this program to.
+    ///
+    /// The program will be loaded onto the network device instead of being
+    /// run by the kernel. All maps used by the program have to be offloaded
+    /// to the same device, using [`OpenMap::set_map_ifindex`][crate::OpenMap::set_map_ifindex].
+    ///
+    /// To instead load an XDP program that runs in the kernel but is bound
+    /// to the device (to make use of device specific kfuncs), additionally
+    /// set the `BPF_F_XDP_DEV_BOUND_ONLY` flag using
+    /// [`OpenProgram::set_flags`].
     pub fn set_ifindex(&mut self, idx: u32) {
         unsafe {
             libbpf_sys::bpf_program__set_ifindex(self.ptr.as_ptr(), idx);
@@ -179,6 +573,29 @@ impl OpenProgram {
         util::parse_ret(ret)
     }
 
+    /// Set whether a bpf program should be attached by a skeleton's
+    /// `attach` method.
+    ///
+    /// Programs are attached automatically by default, if their section
+    /// describes the attach target.
+    pub fn set_autoattach(&mut self, autoattach: bool) {
+        unsafe { libbpf_sys::bpf_program__set_autoattach(self.ptr.as_ptr(), autoattach) }
+    }
+
+    /// Return `true` if the bpf program is set to be attached automatically,
+    /// `false` otherwise.
+    pub fn autoattach(&self) -> bool {
+        unsafe { libbpf_sys::bpf_program__autoattach(self.ptr.as_ptr()) }
+    }
+
+    /// Set the target this program will attach to.
+    ///
+    /// If `attach_prog_fd` is non-zero, the program is attached to the BPF
+    /// program it refers to (as is the case for `freplace` programs).
+    /// Otherwise `attach_func_name` is looked up in kernel BTF. Functions
+    /// defined by kernel modules are found as well, and can be referenced
+    /// unambiguously in the form `<module>:<function>` (e.g.,
+    /// `nf_conntrack:nf_confirm`).
     pub fn set_attach_target(
         &mut self,
         attach_prog_fd: i32,
@@ -250,6 +667,7 @@ impl AsRawLibbpf for OpenProgram {
 #[non_exhaustive]
 #[repr(u32)]
 #[derive(Copy, Clone, TryFromPrimitive, Display, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 // TODO: Document variants.
 #[allow(missing_docs)]
 pub enum ProgramType {
@@ -368,6 +786,21 @@ pub enum ProgramAttachType {
     SkReuseportSelect,
     SkReuseportSelectOrMigrate,
     PerfEvent,
+    TraceKprobeMulti,
+    LsmCgroup,
+    StructOps,
+    Netfilter,
+    TcxIngress,
+    TcxEgress,
+    TraceUprobeMulti,
+    CgroupUnixConnect,
+    CgroupUnixSendmsg,
+    CgroupUnixRecvmsg,
+    CgroupUnixGetpeername,
+    CgroupUnixGetsockname,
+    NetkitPrimary,
+    NetkitPeer,
+    TraceKprobeSession,
     /// See [`MapType::Unknown`][crate::MapType::Unknown]
     Unknown = u32::MAX,
 }
@@ -412,6 +845,63 @@ pub struct Output<'dat> {
     pub _non_exhaustive: (),
 }
 
+/// Test run the program referred to by `prog_fd` with the given input data.
+fn test_run<'dat>(prog_fd: BorrowedFd<'_>, input: Input<'dat>) -> Result<Output<'dat>> {
+    pub(crate) unsafe fn slice_from_array<'t, T>(
+        items: *mut T,
+        num_items: usize,
+    ) -> Option<&'t mut [T]> {
+        if items.is_null() {
+            None
+        } else {
+            Some(unsafe { slice::from_raw_parts_mut(items, num_items) })
+        }
+    }
+
+    let Input {
+        context_in,
+        mut context_out,
+        data_in,
+        mut data_out,
+        cpu,
+        flags,
+        _non_exhaustive: (),
+    } = input;
+
+    let mut opts = unsafe { mem::zeroed::<libbpf_sys::bpf_test_run_opts>() };
+    opts.sz = size_of_val(&opts) as _;
+    opts.ctx_in = context_in
+        .map(|data| data.as_ptr().cast())
+        .unwrap_or_else(ptr::null);
+    opts.ctx_size_in = context_in.map(|data| data.len() as _).unwrap_or(0);
+    opts.ctx_out = context_out
+        .as_mut()
+        .map(|data| data.as_mut_ptr().cast())
+        .unwrap_or_else(ptr::null_mut);
+    opts.ctx_size_out = context_out.map(|data| data.len() as _).unwrap_or(0);
+    opts.data_in = data_in
+        .map(|data| data.as_ptr().cast())
+        .unwrap_or_else(ptr::null);
+    opts.data_size_in = data_in.map(|data| data.len() as _).unwrap_or(0);
+    opts.data_out = data_out
+        .as_mut()
+        .map(|data| data.as_mut_ptr().cast())
+        .unwrap_or_else(ptr::null_mut);
+    opts.data_size_out = data_out.map(|data| data.len() as _).unwrap_or(0);
+    opts.cpu = cpu;
+    opts.flags = flags;
+
+    let rc = unsafe { libbpf_sys::bpf_prog_test_run_opts(prog_fd.as_raw_fd(), &mut opts) };
+    let () = util::parse_ret(rc)?;
+    let output = Output {
+        return_value: opts.retval,
+        context: unsafe { slice_from_array(opts.ctx_out.cast(), opts.ctx_size_out as _) },
+        data: unsafe { slice_from_array(opts.data_out.cast(), opts.data_size_out as _) },
+        _non_exhaustive: (),
+    };
+    Ok(output)
+}
+
 /// Represents a loaded [`Program`].
 ///
 /// This struct is not safe to clone because the underlying libbpf resource cannot currently
@@ -516,11 +1006,35 @@ impl Program {
         unsafe { libbpf_sys::bpf_program__autoload(self.ptr.as_ptr()) }
     }
 
+    /// Set whether the bpf program should be attached by a skeleton's
+    /// `attach` method.
+    pub fn set_autoattach(&mut self, autoattach: bool) {
+        unsafe { libbpf_sys::bpf_program__set_autoattach(self.ptr.as_ptr(), autoattach) }
+    }
+
+    /// Return `true` if the bpf program is set to be attached automatically,
+    /// `false` otherwise.
+    pub fn autoattach(&self) -> bool {
+        unsafe { libbpf_sys::bpf_program__autoattach(self.ptr.as_ptr()) }
+    }
+
     /// Return the bpf program's log level.
     pub fn log_level(&self) -> u32 {
         unsafe { libbpf_sys::bpf_program__log_level(self.ptr.as_ptr()) }
     }
 
+    /// Bind a map to this program, even if the program does not reference
+    /// it.
+    ///
+    /// The kernel keeps the map alive for as long as the program exists. This
+    /// is useful, for example, for maps storing metadata about the program.
+    pub fn bind_map(&self, map_fd: BorrowedFd<'_>) -> Result<()> {
+        let ret = unsafe {
+            libbpf_sys::bpf_prog_bind_map(self.as_fd().as_raw_fd(), map_fd.as_raw_fd(), ptr::null())
+        };
+        util::parse_ret(ret)
+    }
+
     /// [Pin](https://facebookmicrosites.github.io/bpf/blog/2018/08/31/object-lifetime.html#bpffs)
     /// this program to bpffs.
     pub fn pin<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
@@ -528,7 +1042,7 @@ impl Program {
         let path_ptr = path_c.as_ptr();
 
         let ret = unsafe { libbpf_sys::bpf_program__pin(self.ptr.as_ptr(), path_ptr) };
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_program__pin", &self.name)
     }
 
     /// [Unpin](https://facebookmicrosites.github.io/bpf/blog/2018/08/31/object-lifetime.html#bpffs)
@@ -538,7 +1052,7 @@ impl Program {
         let path_ptr = path_c.as_ptr();
 
         let ret = unsafe { libbpf_sys::bpf_program__unpin(self.ptr.as_ptr(), path_ptr) };
-        util::parse_ret(ret)
+        util::parse_ret(ret).libbpf_call("bpf_program__unpin", &self.name)
     }
 
     /// Auto-attach based on prog section
@@ -546,6 +1060,7 @@ impl Program {
         util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_program__attach(self.ptr.as_ptr())
         })
+        .libbpf_call("bpf_program__attach", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
@@ -558,23 +1073,135 @@ impl Program {
         util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_program__attach_cgroup(self.ptr.as_ptr(), cgroup_fd)
         })
+        .libbpf_call("bpf_program__attach_cgroup", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
         })
     }
 
+    /// Attach this program to a
+    /// [cgroup](https://www.kernel.org/doc/html/latest/admin-guide/cgroup-v2.html)
+    /// as a per-cgroup [LSM](https://en.wikipedia.org/wiki/Linux_Security_Modules)
+    /// hook.
+    ///
+    /// The program has to have been loaded with an expected attach type of
+    /// [`ProgramAttachType::LsmCgroup`], which is the case for programs
+    /// defined with `SEC("lsm_cgroup/<hook>")`.
+    pub fn attach_lsm_cgroup(&mut self, cgroup_fd: i32) -> Result<Link> {
+        let attach_type = self.attach_type();
+        if !matches!(attach_type, ProgramAttachType::LsmCgroup) {
+            return Err(Error::with_invalid_input(format!(
+                "program {} has attach type {attach_type}, but lsm_cgroup attachment requires {}",
+                self.name,
+                ProgramAttachType::LsmCgroup,
+            )));
+        }
+
+        util::create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::bpf_program__attach_cgroup(self.ptr.as_ptr(), cgroup_fd)
+        })
+        .libbpf_call("bpf_program__attach_cgroup", &self.name)
+        .map(|ptr| unsafe {
+            // SAFETY: the pointer came from libbpf and has been checked for errors
+            Link::new(ptr)
+        })
+    }
+
+    /// Attach this program to the socket address hook of a
+    /// [cgroup](https://www.kernel.org/doc/html/latest/admin-guide/cgroup-v2.html)
+    /// it was loaded for, returning the hook along with the link.
+    ///
+    /// The cgroup can be provided as a file descriptor or as the path to its
+    /// directory (e.g., `/sys/fs/cgroup/foo`). The program has to have been
+    /// loaded with the attach type of one of the [`CgroupSockAddrHook`]s,
+    /// which is the case for programs defined with, e.g.,
+    /// `SEC("cgroup/connect4")` or set up via
+    /// [`OpenProgram::set_cgroup_sock_addr_hook`].
+    pub fn attach_cgroup_sock_addr<C: IntoCgroupFd>(
+        &mut self,
+        cgroup: C,
+    ) -> Result<(CgroupSockAddrHook, Link)> {
+        let attach_type = self.attach_type();
+        let hook = CgroupSockAddrHook::from_attach_type(&attach_type).ok_or_else(|| {
+            Error::with_invalid_input(format!(
+                "program {} has attach type {attach_type}, which is not a cgroup socket address hook",
+                self.name,
+            ))
+        })?;
+        let cgroup_fd = cgroup.into_cgroup_fd()?;
+
+        util::create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::bpf_program__attach_cgroup(self.ptr.as_ptr(), cgroup_fd.as_raw_fd())
+        })
+        .libbpf_call("bpf_program__attach_cgroup", &self.name)
+        .map(|ptr| {
+            // SAFETY: the pointer came from libbpf and has been checked for errors
+            let link = unsafe { Link::new(ptr) };
+            (hook, link)
+        })
+    }
+
     /// Attach this program to a [perf event](https://linux.die.net/man/2/perf_event_open).
     pub fn attach_perf_event(&mut self, pfd: i32) -> Result<Link> {
         util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_program__attach_perf_event(self.ptr.as_ptr(), pfd)
         })
+        .libbpf_call("bpf_program__attach_perf_event", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
         })
     }
 
+    /// Attach this program to a hardware breakpoint, triggering on accesses
+    /// of type `bp_type` to the `len` bytes at `addr`.
+    ///
+    /// `pid` and `cpu` scope the breakpoint as described for
+    /// [`perf_event_open`](https://man7.org/linux/man-pages/man2/perf_event_open.2.html).
+    /// The underlying perf event is owned by the returned [`Link`] and closed
+    /// together with it.
+    pub fn attach_breakpoint(
+        &mut self,
+        bp_type: BreakpointType,
+        addr: u64,
+        len: u64,
+        pid: i32,
+        cpu: i32,
+    ) -> Result<Link> {
+        let mut attr = libbpf_sys::perf_event_attr {
+            type_: libbpf_sys::PERF_TYPE_BREAKPOINT,
+            size: size_of::<libbpf_sys::perf_event_attr>() as _,
+            bp_type: bp_type as u32,
+            ..Default::default()
+        };
+        attr.__bindgen_anon_1.sample_period = 1;
+        attr.__bindgen_anon_3.bp_addr = addr;
+        attr.__bindgen_anon_4.bp_len = len;
+
+        let pfd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                &attr as *const _,
+                pid,
+                cpu,
+                -1,
+                libbpf_sys::PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if pfd < 0 {
+            return Err(Error::from(io::Error::last_os_error()));
+        }
+        // SAFETY: `perf_event_open` returned a valid file descriptor that
+        //         nobody else owns.
+        let pfd = unsafe { OwnedFd::from_raw_fd(pfd as _) };
+
+        let link = self.attach_perf_event(pfd.as_raw_fd())?;
+        // The link took over ownership of the perf event file descriptor.
+        let _fd = pfd.into_raw_fd();
+        Ok(link)
+    }
+
     /// Attach this program to a [userspace
     /// probe](https://www.kernel.org/doc/html/latest/trace/uprobetracer.html).
     pub fn attach_uprobe<T: AsRef<Path>>(
@@ -595,6 +1222,7 @@ impl Program {
                 func_offset as libbpf_sys::size_t,
             )
         })
+        .libbpf_call("bpf_program__attach_uprobe", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
@@ -640,6 +1268,7 @@ impl Program {
                 &opts as *const _,
             )
         })
+        .libbpf_call("bpf_program__attach_uprobe_opts", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
@@ -654,12 +1283,96 @@ impl Program {
         util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_program__attach_kprobe(self.ptr.as_ptr(), retprobe, func_name_ptr)
         })
+        .libbpf_call("bpf_program__attach_kprobe", &self.name)
+        .map(|ptr| unsafe {
+            // SAFETY: the pointer came from libbpf and has been checked for errors
+            Link::new(ptr)
+        })
+    }
+
+    fn attach_kprobe_multi_impl(&mut self, opts: KprobeMultiOpts) -> Result<Link> {
+        let KprobeMultiOpts {
+            symbols,
+            cookies,
+            retprobe,
+            session,
+            _non_exhaustive,
+        } = opts;
+
+        if !cookies.is_empty() && cookies.len() != symbols.len() {
+            return Err(Error::with_invalid_input(format!(
+                "number of cookies ({}) does not match number of symbols ({})",
+                cookies.len(),
+                symbols.len()
+            )));
+        }
+
+        if retprobe && session {
+            return Err(Error::with_invalid_input(
+                "kprobe sessions cannot be attached as return probes",
+            ));
+        }
+
+        let syms = symbols
+            .iter()
+            .map(|name| util::str_to_cstring(name))
+            .collect::<Result<Vec<_>>>()?;
+        let mut sym_ptrs = syms.iter().map(|sym| sym.as_ptr()).collect::<Vec<_>>();
+
+        let opts = libbpf_sys::bpf_kprobe_multi_opts {
+            sz: size_of::<libbpf_sys::bpf_kprobe_multi_opts>() as _,
+            syms: sym_ptrs.as_mut_ptr(),
+            cookies: if cookies.is_empty() {
+                ptr::null()
+            } else {
+                cookies.as_ptr()
+            },
+            cnt: sym_ptrs.len() as libbpf_sys::size_t,
+            retprobe,
+            session,
+            ..Default::default()
+        };
+
+        util::create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::bpf_program__attach_kprobe_multi_opts(
+                self.ptr.as_ptr(),
+                ptr::null(),
+                &opts as *const _,
+            )
+        })
+        .libbpf_call("bpf_program__attach_kprobe_multi_opts", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
         })
     }
 
+    /// Attach this program to multiple [kernel
+    /// probes](https://www.kernel.org/doc/html/latest/trace/kprobetrace.html)
+    /// at once, using a single link.
+    pub fn attach_kprobe_multi<T: AsRef<str>>(
+        &mut self,
+        retprobe: bool,
+        symbols: impl IntoIterator<Item = T>,
+    ) -> Result<Link> {
+        let opts = KprobeMultiOpts {
+            symbols: symbols
+                .into_iter()
+                .map(|sym| sym.as_ref().to_string())
+                .collect(),
+            retprobe,
+            ..Default::default()
+        };
+        self.attach_kprobe_multi_impl(opts)
+    }
+
+    /// Attach this program to multiple [kernel
+    /// probes](https://www.kernel.org/doc/html/latest/trace/kprobetrace.html)
+    /// at once, providing additional options.
+    pub fn attach_kprobe_multi_with_opts(&mut self, opts: KprobeMultiOpts) -> Result<Link> {
+        self.attach_kprobe_multi_impl(opts)
+    }
+
     /// Attach this program to the specified syscall
     pub fn attach_ksyscall<T: AsRef<str>>(
         &mut self,
@@ -677,6 +1390,7 @@ impl Program {
         util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_program__attach_ksyscall(self.ptr.as_ptr(), syscall_name_ptr, &opts)
         })
+        .libbpf_call("bpf_program__attach_ksyscall", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
@@ -693,6 +1407,11 @@ impl Program {
         let tp_category_ptr = tp_category.as_ptr();
         let tp_name = util::str_to_cstring(tp_name)?;
         let tp_name_ptr = tp_name.as_ptr();
+        let function = if tp_opts.is_some() {
+            "bpf_program__attach_tracepoint_opts"
+        } else {
+            "bpf_program__attach_tracepoint"
+        };
 
         util::create_bpf_entity_checked(|| {
             if let Some(tp_opts) = tp_opts {
@@ -715,6 +1434,7 @@ impl Program {
                 }
             }
         })
+        .libbpf_call(function, &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
@@ -751,34 +1471,87 @@ impl Program {
         util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_program__attach_raw_tracepoint(self.ptr.as_ptr(), tp_name_ptr)
         })
+        .libbpf_call("bpf_program__attach_raw_tracepoint", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
         })
     }
 
+    /// Attach this program to a writable [raw kernel
+    /// tracepoint](https://lwn.net/Articles/748352/).
+    ///
+    /// Writable raw tracepoint programs are allowed to modify the tracepoint's
+    /// arguments. The program has to be of type
+    /// [`ProgramType::RawTracepointWritable`], which is the case for programs
+    /// defined with `SEC("raw_tp.w")` or `SEC("raw_tracepoint.w")`, or
+    /// can be set using [`OpenProgram::set_prog_type`].
+    pub fn attach_raw_tracepoint_writable<T: AsRef<str>>(&mut self, tp_name: T) -> Result<Link> {
+        let prog_type = self.prog_type();
+        if !matches!(prog_type, ProgramType::RawTracepointWritable) {
+            return Err(Error::with_invalid_input(format!(
+                "program {} has type {prog_type}, but writable raw tracepoint attachment requires {}",
+                self.name,
+                ProgramType::RawTracepointWritable,
+            )));
+        }
+
+        self.attach_raw_tracepoint(tp_name)
+    }
+
     /// Attach to an [LSM](https://en.wikipedia.org/wiki/Linux_Security_Modules) hook
     pub fn attach_lsm(&mut self) -> Result<Link> {
         util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_program__attach_lsm(self.ptr.as_ptr())
         })
+        .libbpf_call("bpf_program__attach_lsm", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
         })
     }
 
-    /// Attach to a [fentry/fexit kernel probe](https://lwn.net/Articles/801479/)
-    pub fn attach_trace(&mut self) -> Result<Link> {
-        util::create_bpf_entity_checked(|| unsafe {
-            libbpf_sys::bpf_program__attach_trace(self.ptr.as_ptr())
+    fn attach_trace_impl(&mut self, trace_opts: Option<TraceOpts>) -> Result<Link> {
+        let function = if trace_opts.is_some() {
+            "bpf_program__attach_trace_opts"
+        } else {
+            "bpf_program__attach_trace"
+        };
+
+        util::create_bpf_entity_checked(|| {
+            if let Some(trace_opts) = trace_opts {
+                let trace_opts = libbpf_sys::bpf_trace_opts::from(trace_opts);
+                unsafe {
+                    libbpf_sys::bpf_program__attach_trace_opts(
+                        self.ptr.as_ptr(),
+                        &trace_opts as *const _,
+                    )
+                }
+            } else {
+                unsafe { libbpf_sys::bpf_program__attach_trace(self.ptr.as_ptr()) }
+            }
         })
+        .libbpf_call(function, &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
         })
     }
 
+    /// Attach to a [fentry/fexit kernel probe](https://lwn.net/Articles/801479/)
+    pub fn attach_trace(&mut self) -> Result<Link> {
+        self.attach_trace_impl(None)
+    }
+
+    /// Attach to a [fentry/fexit kernel probe](https://lwn.net/Articles/801479/),
+    /// providing additional options.
+    ///
+    /// Besides fentry/fexit programs, this works for all trampoline based
+    /// attachments, such as `fmod_ret` programs.
+    pub fn attach_trace_with_opts(&mut self, trace_opts: TraceOpts) -> Result<Link> {
+        self.attach_trace_impl(Some(trace_opts))
+    }
+
     /// Attach a verdict/parser to a [sockmap/sockhash](https://lwn.net/Articles/731133/)
     pub fn attach_sockmap(&self, map_fd: i32) -> Result<()> {
         let err = unsafe {
@@ -789,7 +1562,7 @@ impl Program {
                 0,
             )
         };
-        util::parse_ret(err)
+        util::parse_ret(err).libbpf_call("bpf_prog_attach", &self.name)
     }
 
     /// Attach this program to [XDP](https://lwn.net/Articles/825998/)
@@ -797,6 +1570,138 @@ impl Program {
         util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_program__attach_xdp(self.ptr.as_ptr(), ifindex)
         })
+        .libbpf_call("bpf_program__attach_xdp", &self.name)
+        .map(|ptr| unsafe {
+            // SAFETY: the pointer came from libbpf and has been checked for errors
+            Link::new(ptr)
+        })
+    }
+
+    /// Attach this program to [XDP](https://lwn.net/Articles/825998/) of
+    /// the interface `ifindex`, provided by index or by name, in the mode
+    /// selected by `flags`
+    ///
+    /// Only the mode flags ([`XdpFlags::SKB_MODE`],
+    /// [`XdpFlags::DRV_MODE`], and [`XdpFlags::HW_MODE`]) are supported. If
+    /// none is provided, the kernel picks driver mode if supported by the
+    /// device and generic mode otherwise, just as for
+    /// [`Program::attach_xdp`].
+    pub fn attach_xdp_with_flags<I: IntoIfindex>(
+        &mut self,
+        ifindex: I,
+        flags: XdpFlags,
+    ) -> Result<Link> {
+        let ifindex = ifindex.into_ifindex()?;
+        let flags = flags.bits();
+        if flags & !XdpFlags::MODES.bits() != 0 {
+            return Err(Error::with_invalid_input(
+                "only XDP mode flags are supported for XDP links",
+            ));
+        }
+
+        let opts = libbpf_sys::bpf_link_create_opts {
+            sz: size_of::<libbpf_sys::bpf_link_create_opts>() as _,
+            flags,
+            ..Default::default()
+        };
+        let ret = unsafe {
+            libbpf_sys::bpf_link_create(
+                self.as_fd().as_raw_fd(),
+                ifindex,
+                libbpf_sys::BPF_XDP,
+                &opts,
+            )
+        };
+        let fd = util::parse_ret_i32(ret).libbpf_call("bpf_link_create", &self.name)?;
+        // SAFETY: `bpf_link_create` returned a valid file descriptor that
+        //         nobody else owns.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        Ok(Link::from_fd(fd))
+    }
+
+    /// Attach this program to the netkit device `ifindex`, after all
+    /// programs already attached.
+    ///
+    /// See [`Program::attach_netkit_with_opts`] for details.
+    pub fn attach_netkit<I: IntoIfindex>(&mut self, ifindex: I) -> Result<Link> {
+        self.attach_netkit_with_opts(ifindex, NetkitOpts::default())
+    }
+
+    /// Attach this program to the netkit device `ifindex`, as described by
+    /// `opts`.
+    ///
+    /// Whether the program runs on the primary or the peer side of the
+    /// device pair is determined by the attach type the program was loaded
+    /// with, as set by `SEC("netkit/primary")` or `SEC("netkit/peer")` or
+    /// via [`OpenProgram::set_netkit_target`].
+    pub fn attach_netkit_with_opts<I: IntoIfindex>(
+        &mut self,
+        ifindex: I,
+        opts: NetkitOpts<'_>,
+    ) -> Result<Link> {
+        let attach_type = self.attach_type();
+        if !matches!(
+            attach_type,
+            ProgramAttachType::NetkitPrimary | ProgramAttachType::NetkitPeer
+        ) {
+            return Err(Error::with_invalid_input(format!(
+                "program {} has attach type {attach_type}, but netkit attachment requires {} or {}",
+                self.name,
+                ProgramAttachType::NetkitPrimary,
+                ProgramAttachType::NetkitPeer,
+            )));
+        }
+
+        let ifindex = ifindex.into_ifindex()?;
+        let opts = libbpf_sys::bpf_netkit_opts::from(opts);
+        util::create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::bpf_program__attach_netkit(self.ptr.as_ptr(), ifindex, &opts as *const _)
+        })
+        .libbpf_call("bpf_program__attach_netkit", &self.name)
+        .map(|ptr| unsafe {
+            // SAFETY: the pointer came from libbpf and has been checked for errors
+            Link::new(ptr)
+        })
+    }
+
+    /// Attach this program to a
+    /// [netfilter](https://www.netfilter.org/) hook, as described by `opts`.
+    pub fn attach_netfilter_with_opts(&mut self, opts: NetfilterOpts) -> Result<Link> {
+        let NetfilterOpts {
+            protocol_family,
+            hook,
+            priority,
+            ip_defrag,
+            _non_exhaustive,
+        } = opts;
+
+        if priority == i32::MIN || priority == i32::MAX {
+            return Err(Error::with_invalid_input(format!(
+                "netfilter priority {priority} is reserved"
+            )));
+        }
+        if ip_defrag && priority <= NF_IP_PRI_CONNTRACK_DEFRAG {
+            return Err(Error::with_invalid_input(format!(
+                "netfilter priority {priority} has to be greater than {NF_IP_PRI_CONNTRACK_DEFRAG} to defragment packets"
+            )));
+        }
+
+        let opts = libbpf_sys::bpf_netfilter_opts {
+            sz: size_of::<libbpf_sys::bpf_netfilter_opts>() as _,
+            pf: protocol_family as u32,
+            hooknum: hook as u32,
+            priority,
+            flags: if ip_defrag {
+                libbpf_sys::BPF_F_NETFILTER_IP_DEFRAG
+            } else {
+                0
+            },
+        };
+
+        util::create_bpf_entity_checked(|| unsafe {
+            libbpf_sys::bpf_program__attach_netfilter(self.ptr.as_ptr(), &opts as *const _)
+        })
+        .libbpf_call("bpf_program__attach_netfilter", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
@@ -808,6 +1713,7 @@ impl Program {
         util::create_bpf_entity_checked(|| unsafe {
             libbpf_sys::bpf_program__attach_netns(self.ptr.as_ptr(), netns_fd)
         })
+        .libbpf_call("bpf_program__attach_netns", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
@@ -844,6 +1750,7 @@ impl Program {
                 usdt_opts_ptr,
             )
         })
+        .libbpf_call("bpf_program__attach_usdt", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
@@ -893,9 +1800,19 @@ impl Program {
     /// [BPF Iterator](https://www.kernel.org/doc/html/latest/bpf/bpf_iterators.html).
     /// The entry point of the program must be defined with `SEC("iter")` or `SEC("iter.s")`.
     pub fn attach_iter(&mut self, map_fd: BorrowedFd<'_>) -> Result<Link> {
+        self.attach_iter_with_opts(IterOpts::Map(map_fd))
+    }
+
+    /// Attach this program to a
+    /// [BPF Iterator](https://www.kernel.org/doc/html/latest/bpf/bpf_iterators.html)
+    /// over the entity described by `opts`.
+    ///
+    /// The entry point of the program must be defined with a `SEC("iter/...")`
+    /// or `SEC("iter.s/...")` matching the kind of entity, e.g.,
+    /// `SEC("iter/cgroup")` for [`IterOpts::Cgroup`].
+    pub fn attach_iter_with_opts(&mut self, opts: IterOpts<'_>) -> Result<Link> {
         util::create_bpf_entity_checked(|| unsafe {
-            let mut linkinfo = libbpf_sys::bpf_iter_link_info::default();
-            linkinfo.map.map_fd = map_fd.as_raw_fd() as _;
+            let mut linkinfo = libbpf_sys::bpf_iter_link_info::from(opts);
             let attach_opt = libbpf_sys::bpf_iter_attach_opts {
                 link_info: &mut linkinfo as *mut libbpf_sys::bpf_iter_link_info,
                 link_info_len: size_of::<libbpf_sys::bpf_iter_link_info>() as _,
@@ -908,6 +1825,7 @@ impl Program {
                 &attach_opt as *const libbpf_sys::bpf_iter_attach_opts,
             )
         })
+        .libbpf_call("bpf_program__attach_iter", &self.name)
         .map(|ptr| unsafe {
             // SAFETY: the pointer came from libbpf and has been checked for errors
             Link::new(ptr)
@@ -920,59 +1838,7 @@ impl Program {
     /// [BPF_PROG_RUN](https://www.kernel.org/doc/html/latest/bpf/bpf_prog_run.html)
     /// facility.
     pub fn test_run<'dat>(&mut self, input: Input<'dat>) -> Result<Output<'dat>> {
-        pub(crate) unsafe fn slice_from_array<'t, T>(
-            items: *mut T,
-            num_items: usize,
-        ) -> Option<&'t mut [T]> {
-            if items.is_null() {
-                None
-            } else {
-                Some(unsafe { slice::from_raw_parts_mut(items, num_items) })
-            }
-        }
-
-        let Input {
-            context_in,
-            mut context_out,
-            data_in,
-            mut data_out,
-            cpu,
-            flags,
-            _non_exhaustive: (),
-        } = input;
-
-        let mut opts = unsafe { mem::zeroed::<libbpf_sys::bpf_test_run_opts>() };
-        opts.sz = size_of_val(&opts) as _;
-        opts.ctx_in = context_in
-            .map(|data| data.as_ptr().cast())
-            .unwrap_or_else(ptr::null);
-        opts.ctx_size_in = context_in.map(|data| data.len() as _).unwrap_or(0);
-        opts.ctx_out = context_out
-            .as_mut()
-            .map(|data| data.as_mut_ptr().cast())
-            .unwrap_or_else(ptr::null_mut);
-        opts.ctx_size_out = context_out.map(|data| data.len() as _).unwrap_or(0);
-        opts.data_in = data_in
-            .map(|data| data.as_ptr().cast())
-            .unwrap_or_else(ptr::null);
-        opts.data_size_in = data_in.map(|data| data.len() as _).unwrap_or(0);
-        opts.data_out = data_out
-            .as_mut()
-            .map(|data| data.as_mut_ptr().cast())
-            .unwrap_or_else(ptr::null_mut);
-        opts.data_size_out = data_out.map(|data| data.len() as _).unwrap_or(0);
-        opts.cpu = cpu;
-        opts.flags = flags;
-
-        let rc = unsafe { libbpf_sys::bpf_prog_test_run_opts(self.as_fd().as_raw_fd(), &mut opts) };
-        let () = util::parse_ret(rc)?;
-        let output = Output {
-            return_value: opts.retval,
-            context: unsafe { slice_from_array(opts.ctx_out.cast(), opts.ctx_size_out as _) },
-            data: unsafe { slice_from_array(opts.data_out.cast(), opts.data_size_out as _) },
-            _non_exhaustive: (),
-        };
-        Ok(output)
+        test_run(self.as_fd(), input)
     }
 
     /// Returns the number of instructions that form the program.
@@ -1001,3 +1867,136 @@ impl AsRawLibbpf for Program {
         self.ptr
     }
 }
+
+/// A handle to a loaded program, not managed by libbpf. Handles can be
+/// duplicated and dropped.
+///
+/// Compared to a [`Program`], a handle can be created for any program loaded
+/// on the system, e.g., by [id][ProgramHandle::from_prog_id] or from a
+/// [pinned path][ProgramHandle::from_pinned_path]. It supports operations
+/// that don't require a libbpf object, but can't be attached.
+#[derive(Debug)]
+pub struct ProgramHandle {
+    fd: OwnedFd,
+    name: String,
+    ty: ProgramType,
+}
+
+impl ProgramHandle {
+    /// Open a previously pinned program from its path.
+    pub fn from_pinned_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = util::path_to_cstring(path)?;
+        let fd = util::parse_ret_i32(unsafe { libbpf_sys::bpf_obj_get(path.as_ptr()) })?;
+        // SAFETY
+        // A file descriptor coming from the bpf_obj_get function is always suitable for
+        // ownership and can be cleaned up with close.
+        Self::from_fd(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+
+    /// Open a loaded program from its program id.
+    pub fn from_prog_id(id: u32) -> Result<Self> {
+        Program::get_fd_by_id(id).and_then(Self::from_fd)
+    }
+
+    /// Create a handle from the file descriptor `fd` referring to a
+    /// loaded program.
+    pub fn from_fd(fd: OwnedFd) -> Result<Self> {
+        let opts = ProgInfoQueryOptions::default().include_func_info(true);
+        let info = ProgramInfo::load_from_fd(fd.as_fd(), &opts)?;
+        Ok(Self {
+            fd,
+            name: full_prog_name(&info)?,
+            ty: info.ty,
+        })
+    }
+
+    /// Try cloning this handle by duplicating its underlying file descriptor.
+    pub fn try_clone(this: &ProgramHandle) -> Result<Self> {
+        let fd = this.fd.try_clone()?;
+        Ok(Self {
+            fd,
+            name: this.name.clone(),
+            ty: this.ty,
+        })
+    }
+
+    /// Create a handle for the provided [`Program`].
+    pub fn from_program(prog: &Program) -> Result<Self> {
+        let fd = prog.as_fd().try_clone_to_owned()?;
+        Ok(Self {
+            fd,
+            name: prog.name().to_string(),
+            ty: prog.prog_type(),
+        })
+    }
+
+    /// Retrieve the program's name.
+    ///
+    /// The kernel truncates program names to 15 characters. For programs
+    /// not created from a [`Program`], the full name is recovered from the
+    /// program's BTF information, if available.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Retrieve the program's type.
+    #[inline]
+    pub fn prog_type(&self) -> ProgramType {
+        self.ty
+    }
+
+    /// Fetch information about the program, including the optional parts
+    /// requested by `opts`.
+    pub fn info(&self, opts: &ProgInfoQueryOptions) -> Result<ProgramInfo> {
+        ProgramInfo::load_from_fd(self.fd.as_fd(), opts)
+    }
+
+    /// Bind a map to this program, as done by [`Program::bind_map`].
+    pub fn bind_map(&self, map_fd: BorrowedFd<'_>) -> Result<()> {
+        let ret = unsafe {
+            libbpf_sys::bpf_prog_bind_map(self.fd.as_raw_fd(), map_fd.as_raw_fd(), ptr::null())
+        };
+        util::parse_ret(ret)
+    }
+
+    /// Test run the program with the given input data, as done by
+    /// [`Program::test_run`].
+    pub fn test_run<'dat>(&mut self, input: Input<'dat>) -> Result<Output<'dat>> {
+        test_run(self.fd.as_fd(), input)
+    }
+}
+
+/// Retrieve the full name of the program described by `info`.
+///
+/// The kernel reports program names truncated to 15 characters. Just like
+/// `bpftool`, we look up the name of the program's BTF function instead,
+/// if the kernel provides it.
+fn full_prog_name(info: &ProgramInfo) -> Result<String> {
+    let name = info.name.to_str().map_err(Error::with_invalid_data)?;
+    let full_name = || -> Option<String> {
+        let func_info = info.func_info.first()?;
+        let btf = Btf::from_prog_id(info.id).ok()?;
+        let func = btf.type_by_id::<BtfType<'_>>(TypeId::from(func_info.type_id))?;
+        let full_name = func.name()?.to_str().ok()?;
+        full_name.starts_with(name).then(|| full_name.to_string())
+    };
+
+    if name.len() < libbpf_sys::BPF_OBJ_NAME_LEN as usize - 1 || info.btf_id == 0 {
+        return Ok(name.to_string());
+    }
+    Ok(full_name().unwrap_or_else(|| name.to_string()))
+}
+
+impl AsFd for ProgramHandle {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl From<ProgramHandle> for OwnedFd {
+    fn from(handle: ProgramHandle) -> Self {
+        handle.fd
+    }
+}
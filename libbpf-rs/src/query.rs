@@ -9,10 +9,25 @@
 //!     println!("{}", prog.name.to_string_lossy());
 //! }
 //! ```
+//!
+//! Similarly, to list every bpf link along with the program it refers to:
+//! ```
+//! use libbpf_rs::query::LinkInfoIter;
+//!
+//! for link in LinkInfoIter::default() {
+//!     println!("link {}: prog {}: {:?}", link.id, link.prog_id, link.info);
+//! }
+//! ```
 
 use std::ffi::c_void;
+use std::ffi::CStr;
 use std::ffi::CString;
+use std::fs;
+use std::fs::File;
 use std::io;
+use std::io::Read as _;
+use std::mem;
+use std::mem::size_of;
 use std::mem::size_of_val;
 use std::os::fd::AsFd;
 use std::os::fd::AsRawFd;
@@ -23,8 +38,15 @@ use std::os::raw::c_char;
 use std::ptr;
 use std::time::Duration;
 
+use crate::btf::types::Composite;
+use crate::btf::types::Struct;
+use crate::btf::BtfKind;
+use crate::btf::BtfType;
 use crate::util;
+use crate::Btf;
+use crate::Error;
 use crate::MapType;
+use crate::NetkitInfo;
 use crate::ProgramAttachType;
 use crate::ProgramType;
 use crate::Result;
@@ -81,7 +103,7 @@ macro_rules! gen_info_impl {
                 let parsed_uapi = if ret != 0 {
                     None
                 } else {
-                    <$info_ty>::from_uapi(fd.as_fd(), item)
+                    <$info_ty>::from_uapi(fd.as_fd(), item).ok()
                 };
 
                 parsed_uapi
@@ -90,6 +112,78 @@ macro_rules! gen_info_impl {
     };
 }
 
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A type describing information about a BPF object, as retrieved via
+/// `bpf_obj_get_info_by_fd`.
+///
+/// This trait is sealed and implemented for [`ProgramInfo`], [`MapInfo`],
+/// [`LinkInfo`], and [`BtfInfo`]. Use [`object_info`] to retrieve any of
+/// them.
+pub trait ObjInfo: Sized + sealed::Sealed {
+    /// The type of object this information describes.
+    const OBJECT_TYPE: FdObjectType;
+
+    #[doc(hidden)]
+    fn load(fd: BorrowedFd<'_>) -> Result<Self>;
+}
+
+/// Retrieve information about the BPF object referred to by `fd`.
+///
+/// This works for any program, map, link, or btf file descriptor, including
+/// ones not created through this crate (e.g., received from another
+/// process). It is an error if `fd` does not refer to an object of the kind
+/// requested.
+///
+/// ```no_run
+/// # use std::os::fd::AsFd;
+/// # use std::os::fd::OwnedFd;
+/// use libbpf_rs::query::object_info;
+/// use libbpf_rs::query::MapInfo;
+///
+/// # fn f(fd: OwnedFd) -> libbpf_rs::Result<()> {
+/// let info = object_info::<MapInfo>(fd.as_fd())?;
+/// println!("map {}: {}", info.id, info.name.to_string_lossy());
+/// # Ok(())
+/// # }
+/// ```
+pub fn object_info<T: ObjInfo>(fd: BorrowedFd<'_>) -> Result<T> {
+    // The kernel fills in the info matching the object type, irrespective of
+    // what we asked for. Make sure to not interpret one as the other.
+    let path = format!("/proc/self/fd/{}", fd.as_raw_fd());
+    let target = fs::read_link(path)?;
+    let expected = T::OBJECT_TYPE.anon_inode_name();
+    if target.as_os_str() != expected {
+        return Err(Error::with_invalid_input(format!(
+            "file descriptor {} does not refer to a `{expected}` object",
+            fd.as_raw_fd()
+        )));
+    }
+    T::load(fd)
+}
+
+/// Retrieve the raw kernel provided information for the object referred to
+/// by `fd`.
+///
+/// # Safety
+/// `T` has to be one of the `bpf_*_info` types understood by the kernel.
+unsafe fn load_raw_info<T>(fd: BorrowedFd<'_>) -> Result<T> {
+    // Padding bytes need to be zero, see `gen_info_impl`. Because the kernel
+    // never writes more than the size we pass in, fields unknown to an older
+    // kernel stay zeroed.
+    let mut item: T = unsafe { mem::zeroed() };
+    let item_ptr: *mut T = &mut item;
+    let mut len = size_of_val(&item) as u32;
+
+    let ret = unsafe {
+        libbpf_sys::bpf_obj_get_info_by_fd(fd.as_raw_fd(), item_ptr as *mut c_void, &mut len)
+    };
+    util::parse_ret(ret)?;
+    Ok(item)
+}
+
 /// BTF Line information
 #[derive(Clone, Debug)]
 pub struct LineInfo {
@@ -118,7 +212,7 @@ impl From<&libbpf_sys::bpf_line_info> for LineInfo {
 }
 
 /// Bpf identifier tag
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 #[repr(C)]
 pub struct Tag([u8; 8]);
 
@@ -155,6 +249,14 @@ pub struct ProgramInfo {
     pub run_cnt: u64,
     /// Skipped BPF executions due to recursion or concurrent execution prevention.
     pub recursion_misses: u64,
+    /// The number of instructions processed by the verifier.
+    pub verified_insns: u32,
+    /// The id of the BTF object (e.g., of a kernel module or BPF program)
+    /// containing the type the program is attached to, or `0` if it refers
+    /// to vmlinux BTF.
+    pub attach_btf_obj_id: u32,
+    /// The BTF type id of the function the program is attached to.
+    pub attach_btf_id: u32,
 }
 
 /// An iterator for the information of loaded bpf programs
@@ -185,6 +287,12 @@ pub struct ProgInfoQueryOptions {
     include_prog_tags: bool,
     /// Include the jited kernel symbols
     include_jited_ksyms: bool,
+    /// Only report programs of this type
+    prog_type: Option<ProgramType>,
+    /// Only report programs whose name starts with this prefix
+    name_prefix: Option<Vec<u8>>,
+    /// Only report programs with this tag
+    tag: Option<Tag>,
 }
 
 impl ProgInfoIter {
@@ -264,13 +372,64 @@ impl ProgInfoQueryOptions {
             include_jited_func_lens: true,
             include_prog_tags: true,
             include_jited_ksyms: true,
+            ..self
         }
     }
+
+    /// Only report programs of the given type.
+    ///
+    /// Filters are evaluated before any of the optional parts of the
+    /// program information are retrieved.
+    pub fn prog_type(mut self, prog_type: ProgramType) -> Self {
+        self.prog_type = Some(prog_type);
+        self
+    }
+
+    /// Only report programs whose name starts with `prefix`.
+    ///
+    /// Note that the kernel truncates program names to 15 characters.
+    pub fn name_prefix(mut self, prefix: impl AsRef<str>) -> Self {
+        self.name_prefix = Some(prefix.as_ref().as_bytes().to_vec());
+        self
+    }
+
+    /// Only report programs with the given tag.
+    pub fn tag(mut self, tag: Tag) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Check whether a program described by `name`, `prog_type`, and `tag`
+    /// passes all filters.
+    fn matches(&self, name: &CStr, prog_type: u32, tag: &Tag) -> bool {
+        self.prog_type.map_or(true, |ty| ty as u32 == prog_type)
+            && self
+                .name_prefix
+                .as_ref()
+                .map_or(true, |prefix| name.to_bytes().starts_with(prefix))
+            && self.tag.as_ref().map_or(true, |t| t == tag)
+    }
 }
 
 impl ProgramInfo {
-    fn load_from_fd(fd: BorrowedFd<'_>, opts: &ProgInfoQueryOptions) -> Result<Self> {
-        let mut item = libbpf_sys::bpf_prog_info::default();
+    /// Load information about the program referred to by the provided file
+    /// descriptor, including the optional parts requested by `opts`.
+    ///
+    /// Filters set on `opts` are ignored.
+    pub fn load_from_fd(fd: BorrowedFd<'_>, opts: &ProgInfoQueryOptions) -> Result<Self> {
+        // SANITY: Without filtering we always produce a result.
+        Self::load_from_fd_impl(fd, opts, false).map(Option::unwrap)
+    }
+
+    /// Load information about a program, returning `None` if `filter` is
+    /// set and the program does not pass the filters set on `opts`.
+    fn load_from_fd_impl(
+        fd: BorrowedFd<'_>,
+        opts: &ProgInfoQueryOptions,
+        filter: bool,
+    ) -> Result<Option<Self>> {
+        // Padding bytes need to be zero, see `gen_info_impl`.
+        let mut item: libbpf_sys::bpf_prog_info = unsafe { mem::zeroed() };
 
         let mut xlated_prog_insns: Vec<u8> = Vec::new();
         let mut jited_prog_insns: Vec<u8> = Vec::new();
@@ -297,6 +456,10 @@ impl ProgramInfo {
             Err(_) => ProgramType::Unknown,
         };
 
+        if filter && !opts.matches(name, item.type_, &Tag(item.tag)) {
+            return Ok(None);
+        }
+
         if opts.include_xlated_prog_insns {
             xlated_prog_insns.resize(item.xlated_prog_len as usize, 0u8);
             item.xlated_prog_insns = xlated_prog_insns.as_mut_ptr() as *mut c_void as u64;
@@ -371,7 +534,7 @@ impl ProgramInfo {
         };
         util::parse_ret(ret)?;
 
-        return Ok(ProgramInfo {
+        Ok(Some(ProgramInfo {
             name: name.to_owned(),
             ty,
             tag: Tag(item.tag),
@@ -398,7 +561,10 @@ impl ProgramInfo {
             run_time_ns: item.run_time_ns,
             run_cnt: item.run_cnt,
             recursion_misses: item.recursion_misses,
-        });
+            verified_insns: item.verified_insns,
+            attach_btf_obj_id: item.attach_btf_obj_id,
+            attach_btf_id: item.attach_btf_id,
+        }))
     }
 }
 
@@ -427,14 +593,17 @@ impl Iterator for ProgInfoIter {
     type Item = ProgramInfo;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let fd = self.next_valid_fd()?;
+        loop {
+            let fd = self.next_valid_fd()?;
 
-        let prog = ProgramInfo::load_from_fd(fd.as_fd(), &self.opts);
+            let prog = ProgramInfo::load_from_fd_impl(fd.as_fd(), &self.opts, true);
 
-        match prog {
-            Ok(p) => Some(p),
-            // TODO: We should consider bubbling up errors properly.
-            Err(_err) => None,
+            match prog {
+                Ok(Some(p)) => break Some(p),
+                Ok(None) => continue,
+                // TODO: We should consider bubbling up errors properly.
+                Err(_err) => break None,
+            }
         }
     }
 }
@@ -461,7 +630,15 @@ pub struct MapInfo {
 }
 
 impl MapInfo {
-    fn from_uapi(_fd: BorrowedFd<'_>, s: libbpf_sys::bpf_map_info) -> Option<Self> {
+    /// Load information about the map referred to by the provided file
+    /// descriptor.
+    pub fn load_from_fd(fd: BorrowedFd<'_>) -> Result<Self> {
+        // SAFETY: `bpf_map_info` is a kernel info type.
+        let item = unsafe { load_raw_info::<libbpf_sys::bpf_map_info>(fd) }?;
+        Self::from_uapi(fd, item)
+    }
+
+    fn from_uapi(_fd: BorrowedFd<'_>, s: libbpf_sys::bpf_map_info) -> Result<Self> {
         // SANITY: `libbpf` should guarantee NUL termination.
         let name = util::c_char_slice_to_cstr(&s.name).unwrap();
         let ty = match MapType::try_from(s.type_) {
@@ -469,7 +646,7 @@ impl MapInfo {
             Err(_) => MapType::Unknown,
         };
 
-        Some(Self {
+        Ok(Self {
             name: name.to_owned(),
             ty,
             id: s.id,
@@ -488,14 +665,94 @@ impl MapInfo {
     }
 }
 
-gen_info_impl!(
-    /// Iterator that returns [`MapInfo`]s.
-    MapInfoIter,
-    MapInfo,
-    libbpf_sys::bpf_map_info,
-    libbpf_sys::bpf_map_get_next_id,
-    libbpf_sys::bpf_map_get_fd_by_id
-);
+/// Options to filter the maps reported by [`MapInfoIter`]
+#[derive(Clone, Default, Debug)]
+pub struct MapInfoQueryOptions {
+    /// Only report maps of this type
+    map_type: Option<MapType>,
+    /// Only report maps whose name starts with this prefix
+    name_prefix: Option<Vec<u8>>,
+}
+
+impl MapInfoQueryOptions {
+    /// Only report maps of the given type.
+    pub fn map_type(mut self, map_type: MapType) -> Self {
+        self.map_type = Some(map_type);
+        self
+    }
+
+    /// Only report maps whose name starts with `prefix`.
+    ///
+    /// Note that the kernel truncates map names to 15 characters.
+    pub fn name_prefix(mut self, prefix: impl AsRef<str>) -> Self {
+        self.name_prefix = Some(prefix.as_ref().as_bytes().to_vec());
+        self
+    }
+
+    /// Check whether `info` passes all filters.
+    fn matches(&self, info: &MapInfo) -> bool {
+        self.map_type.map_or(true, |ty| ty == info.ty)
+            && self
+                .name_prefix
+                .as_ref()
+                .map_or(true, |prefix| info.name.to_bytes().starts_with(prefix))
+    }
+}
+
+/// Iterator that returns [`MapInfo`]s.
+#[derive(Default, Debug)]
+pub struct MapInfoIter {
+    cur_id: u32,
+    opts: MapInfoQueryOptions,
+}
+
+impl MapInfoIter {
+    /// Generate an iter reporting only the maps passing the filters set on
+    /// `opts`.
+    pub fn with_query_opts(opts: MapInfoQueryOptions) -> Self {
+        Self {
+            opts,
+            ..Self::default()
+        }
+    }
+
+    // Returns Some(next_valid_fd), None on none left
+    fn next_valid_fd(&mut self) -> Option<OwnedFd> {
+        loop {
+            if unsafe { libbpf_sys::bpf_map_get_next_id(self.cur_id, &mut self.cur_id) } != 0 {
+                return None;
+            }
+
+            let fd = unsafe { libbpf_sys::bpf_map_get_fd_by_id(self.cur_id) };
+            if fd < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::NotFound {
+                    continue;
+                }
+                return None;
+            }
+
+            return Some(unsafe { OwnedFd::from_raw_fd(fd) });
+        }
+    }
+}
+
+impl Iterator for MapInfoIter {
+    type Item = MapInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let fd = self.next_valid_fd()?;
+
+            match MapInfo::load_from_fd(fd.as_fd()) {
+                Ok(map) if self.opts.matches(&map) => break Some(map),
+                Ok(_) => continue,
+                // TODO: We should consider bubbling up errors properly.
+                Err(_err) => break None,
+            }
+        }
+    }
+}
 
 /// Information about BPF type format
 #[derive(Debug, Clone)]
@@ -506,11 +763,21 @@ pub struct BtfInfo {
     pub btf: Vec<u8>,
     /// The btf id associated with this btf information in the kernel
     pub id: u32,
+    /// Whether this is btf information of the kernel itself, as opposed to
+    /// btf information loaded alongside a bpf program.
+    ///
+    /// Kernel btf information is named `vmlinux` for the kernel proper and
+    /// after the respective module for kernel modules. Module btf
+    /// information is split btf information, built on top of `vmlinux`.
+    pub kernel_btf: bool,
 }
 
 impl BtfInfo {
-    fn load_from_fd(fd: BorrowedFd<'_>) -> Result<Self> {
-        let mut item = libbpf_sys::bpf_btf_info::default();
+    /// Load information about the btf object referred to by the provided
+    /// file descriptor.
+    pub fn load_from_fd(fd: BorrowedFd<'_>) -> Result<Self> {
+        // Padding bytes need to be zero, see `gen_info_impl`.
+        let mut item: libbpf_sys::bpf_btf_info = unsafe { mem::zeroed() };
         let mut btf: Vec<u8> = Vec::new();
         let mut name: Vec<u8> = Vec::new();
 
@@ -543,6 +810,7 @@ impl BtfInfo {
             name: CString::from_vec_with_nul(name).unwrap(),
             btf,
             id: item.id,
+            kernel_btf: item.kernel_btf != 0,
         })
     }
 }
@@ -582,70 +850,192 @@ impl Iterator for BtfInfoIter {
     fn next(&mut self) -> Option<Self::Item> {
         let fd = self.next_valid_fd()?;
 
-        let info = BtfInfo::load_from_fd(fd.as_fd());
-
-        match info {
-            Ok(i) => Some(i),
-            // TODO: We should consider bubbling up errors properly.
-            Err(_err) => None,
-        }
+        // TODO: We should consider bubbling up errors properly.
+        BtfInfo::load_from_fd(fd.as_fd()).ok()
     }
 }
 
+/// Information about a raw tracepoint link.
 #[derive(Debug, Clone)]
-// TODO: Document members.
-#[allow(missing_docs)]
 pub struct RawTracepointLinkInfo {
+    /// The name of the tracepoint the program is attached to.
     pub name: String,
 }
 
+/// Information about a tracing (fentry/fexit/...) link.
 #[derive(Debug, Clone)]
-// TODO: Document members.
-#[allow(missing_docs)]
 pub struct TracingLinkInfo {
+    /// The kind of tracing attachment.
     pub attach_type: ProgramAttachType,
+    /// The id of the BPF program or module BTF object the link is attached
+    /// to, or `0` if it is attached to vmlinux.
+    pub target_obj_id: u32,
+    /// The BTF type id of the function the link is attached to.
+    pub target_btf_id: u32,
 }
 
+/// Information about a cgroup link.
 #[derive(Debug, Clone)]
-// TODO: Document members.
-#[allow(missing_docs)]
 pub struct CgroupLinkInfo {
+    /// The id of the cgroup the program is attached to.
     pub cgroup_id: u64,
+    /// The cgroup hook the program is attached to.
     pub attach_type: ProgramAttachType,
 }
 
+/// Information about a network namespace link.
 #[derive(Debug, Clone)]
-// TODO: Document members.
-#[allow(missing_docs)]
 pub struct NetNsLinkInfo {
+    /// The inode number of the network namespace the program is attached to.
     pub ino: u32,
+    /// The hook the program is attached to.
     pub attach_type: ProgramAttachType,
 }
 
+/// Information about an XDP link.
+#[derive(Debug, Clone)]
+pub struct XdpLinkInfo {
+    /// The index of the network interface the program is attached to.
+    pub ifindex: u32,
+}
+
+/// Information about a `struct_ops` link.
+#[derive(Debug, Clone)]
+pub struct StructOpsLinkInfo {
+    /// The id of the `struct_ops` map backing the link.
+    pub map_id: u32,
+}
+
+/// Information about a netfilter link.
+#[derive(Debug, Clone)]
+pub struct NetfilterLinkInfo {
+    /// The protocol family of the hook (e.g., `NFPROTO_IPV4`).
+    pub protocol_family: u32,
+    /// The netfilter hook the program is attached to (e.g.,
+    /// `NF_INET_LOCAL_IN`).
+    pub hooknum: u32,
+    /// The priority of the program within the hook.
+    pub priority: i32,
+    /// Flags provided when attaching.
+    pub flags: u32,
+}
+
+/// Information about a kprobe-multi link.
+#[derive(Debug, Clone)]
+pub struct KprobeMultiLinkInfo {
+    /// The number of functions the link is attached to.
+    pub count: u32,
+    /// Flags provided when attaching (e.g., `BPF_F_KPROBE_MULTI_RETURN`).
+    pub flags: u32,
+    /// The number of times the program was not invoked, because of
+    /// recursion or similar reasons.
+    pub missed: u64,
+}
+
+/// Information about a uprobe-multi link.
+#[derive(Debug, Clone)]
+pub struct UprobeMultiLinkInfo {
+    /// The number of probes the link is attached to.
+    pub count: u32,
+    /// Flags provided when attaching (e.g., `BPF_F_UPROBE_MULTI_RETURN`).
+    pub flags: u32,
+    /// The id of the process the probes are restricted to, or `0` if they
+    /// are not.
+    pub pid: u32,
+}
+
+/// Information about a tcx link.
+#[derive(Debug, Clone)]
+pub struct TcxLinkInfo {
+    /// The index of the network interface the program is attached to.
+    pub ifindex: u32,
+    /// Whether the program is attached on ingress or egress.
+    pub attach_type: ProgramAttachType,
+}
+
+/// Information about a netkit link.
+#[derive(Debug, Clone)]
+pub struct NetkitLinkInfo {
+    /// The index of the netkit device the program is attached to.
+    pub ifindex: u32,
+    /// Whether the program is attached to the primary or peer device.
+    pub attach_type: ProgramAttachType,
+}
+
+impl NetkitLinkInfo {
+    /// Retrieve information about the netkit device the program is attached
+    /// to, such as its policy.
+    pub fn device_info(&self) -> Result<NetkitInfo> {
+        NetkitInfo::query(self.ifindex as i32)
+    }
+}
+
+/// Information about a sockmap link.
+#[derive(Debug, Clone)]
+pub struct SockMapLinkInfo {
+    /// The id of the sockmap or sockhash the program is attached to.
+    pub map_id: u32,
+    /// The role of the program, e.g., whether it is a stream verdict or
+    /// parser program.
+    pub attach_type: ProgramAttachType,
+}
+
+/// Type specific information about a BPF link.
 #[derive(Debug, Clone)]
-// TODO: Document variants.
-#[allow(missing_docs)]
 pub enum LinkTypeInfo {
+    /// A raw tracepoint link.
     RawTracepoint(RawTracepointLinkInfo),
+    /// A tracing (fentry/fexit/...) link.
     Tracing(TracingLinkInfo),
+    /// A cgroup link.
     Cgroup(CgroupLinkInfo),
+    /// A BPF iterator link.
     Iter,
+    /// A network namespace link.
     NetNs(NetNsLinkInfo),
+    /// An XDP link.
+    Xdp(XdpLinkInfo),
+    /// A perf event (kprobe/uprobe/tracepoint/...) link.
+    PerfEvent,
+    /// A kprobe-multi link.
+    KprobeMulti(KprobeMultiLinkInfo),
+    /// A `struct_ops` link.
+    StructOps(StructOpsLinkInfo),
+    /// A netfilter link.
+    Netfilter(NetfilterLinkInfo),
+    /// A tcx link.
+    Tcx(TcxLinkInfo),
+    /// A uprobe-multi link.
+    UprobeMulti(UprobeMultiLinkInfo),
+    /// A netkit link.
+    Netkit(NetkitLinkInfo),
+    /// A sockmap link.
+    SockMap(SockMapLinkInfo),
+    /// A link of a type unknown to this library.
     Unknown,
 }
 
 /// Information about a BPF link
 #[derive(Debug, Clone)]
-// TODO: Document members.
-#[allow(missing_docs)]
 pub struct LinkInfo {
+    /// Type specific information about the link.
     pub info: LinkTypeInfo,
+    /// The id of the link.
     pub id: u32,
+    /// The id of the program the link refers to.
     pub prog_id: u32,
 }
 
 impl LinkInfo {
-    fn from_uapi(fd: BorrowedFd<'_>, mut s: libbpf_sys::bpf_link_info) -> Option<Self> {
+    /// Load information about the link referred to by the provided file
+    /// descriptor.
+    pub fn load_from_fd(fd: BorrowedFd<'_>) -> Result<Self> {
+        // SAFETY: `bpf_link_info` is a kernel info type.
+        let item = unsafe { load_raw_info::<libbpf_sys::bpf_link_info>(fd) }?;
+        Self::from_uapi(fd, item)
+    }
+
+    fn from_uapi(fd: BorrowedFd<'_>, mut s: libbpf_sys::bpf_link_info) -> Result<Self> {
         let type_info = match s.type_ {
             libbpf_sys::BPF_LINK_TYPE_RAW_TRACEPOINT => {
                 let mut buf = [0; 256];
@@ -661,9 +1051,7 @@ impl LinkInfo {
                         &mut len,
                     )
                 };
-                if ret != 0 {
-                    return None;
-                }
+                let () = util::parse_ret(ret)?;
 
                 LinkTypeInfo::RawTracepoint(RawTracepointLinkInfo {
                     name: util::c_ptr_to_string(
@@ -677,6 +1065,8 @@ impl LinkInfo {
                     s.__bindgen_anon_1.tracing.attach_type
                 })
                 .unwrap_or(ProgramAttachType::Unknown),
+                target_obj_id: unsafe { s.__bindgen_anon_1.tracing.target_obj_id },
+                target_btf_id: unsafe { s.__bindgen_anon_1.tracing.target_btf_id },
             }),
             libbpf_sys::BPF_LINK_TYPE_CGROUP => LinkTypeInfo::Cgroup(CgroupLinkInfo {
                 cgroup_id: unsafe { s.__bindgen_anon_1.cgroup.cgroup_id },
@@ -693,10 +1083,58 @@ impl LinkInfo {
                 })
                 .unwrap_or(ProgramAttachType::Unknown),
             }),
+            libbpf_sys::BPF_LINK_TYPE_XDP => LinkTypeInfo::Xdp(XdpLinkInfo {
+                ifindex: unsafe { s.__bindgen_anon_1.xdp.ifindex },
+            }),
+            libbpf_sys::BPF_LINK_TYPE_PERF_EVENT => LinkTypeInfo::PerfEvent,
+            libbpf_sys::BPF_LINK_TYPE_KPROBE_MULTI => {
+                LinkTypeInfo::KprobeMulti(KprobeMultiLinkInfo {
+                    count: unsafe { s.__bindgen_anon_1.kprobe_multi.count },
+                    flags: unsafe { s.__bindgen_anon_1.kprobe_multi.flags },
+                    missed: unsafe { s.__bindgen_anon_1.kprobe_multi.missed },
+                })
+            }
+            libbpf_sys::BPF_LINK_TYPE_STRUCT_OPS => LinkTypeInfo::StructOps(StructOpsLinkInfo {
+                map_id: unsafe { s.__bindgen_anon_1.struct_ops.map_id },
+            }),
+            libbpf_sys::BPF_LINK_TYPE_NETFILTER => LinkTypeInfo::Netfilter(NetfilterLinkInfo {
+                protocol_family: unsafe { s.__bindgen_anon_1.netfilter.pf },
+                hooknum: unsafe { s.__bindgen_anon_1.netfilter.hooknum },
+                priority: unsafe { s.__bindgen_anon_1.netfilter.priority },
+                flags: unsafe { s.__bindgen_anon_1.netfilter.flags },
+            }),
+            libbpf_sys::BPF_LINK_TYPE_TCX => LinkTypeInfo::Tcx(TcxLinkInfo {
+                ifindex: unsafe { s.__bindgen_anon_1.tcx.ifindex },
+                attach_type: ProgramAttachType::try_from(unsafe {
+                    s.__bindgen_anon_1.tcx.attach_type
+                })
+                .unwrap_or(ProgramAttachType::Unknown),
+            }),
+            libbpf_sys::BPF_LINK_TYPE_UPROBE_MULTI => {
+                LinkTypeInfo::UprobeMulti(UprobeMultiLinkInfo {
+                    count: unsafe { s.__bindgen_anon_1.uprobe_multi.count },
+                    flags: unsafe { s.__bindgen_anon_1.uprobe_multi.flags },
+                    pid: unsafe { s.__bindgen_anon_1.uprobe_multi.pid },
+                })
+            }
+            libbpf_sys::BPF_LINK_TYPE_NETKIT => LinkTypeInfo::Netkit(NetkitLinkInfo {
+                ifindex: unsafe { s.__bindgen_anon_1.netkit.ifindex },
+                attach_type: ProgramAttachType::try_from(unsafe {
+                    s.__bindgen_anon_1.netkit.attach_type
+                })
+                .unwrap_or(ProgramAttachType::Unknown),
+            }),
+            libbpf_sys::BPF_LINK_TYPE_SOCKMAP => LinkTypeInfo::SockMap(SockMapLinkInfo {
+                map_id: unsafe { s.__bindgen_anon_1.sockmap.map_id },
+                attach_type: ProgramAttachType::try_from(unsafe {
+                    s.__bindgen_anon_1.sockmap.attach_type
+                })
+                .unwrap_or(ProgramAttachType::Unknown),
+            }),
             _ => LinkTypeInfo::Unknown,
         };
 
-        Some(Self {
+        Ok(Self {
             info: type_info,
             id: s.id,
             prog_id: s.prog_id,
@@ -712,3 +1150,647 @@ gen_info_impl!(
     libbpf_sys::bpf_link_get_next_id,
     libbpf_sys::bpf_link_get_fd_by_id
 );
+
+impl sealed::Sealed for ProgramInfo {}
+impl sealed::Sealed for MapInfo {}
+impl sealed::Sealed for LinkInfo {}
+impl sealed::Sealed for BtfInfo {}
+
+impl ObjInfo for ProgramInfo {
+    const OBJECT_TYPE: FdObjectType = FdObjectType::Program;
+
+    fn load(fd: BorrowedFd<'_>) -> Result<Self> {
+        Self::load_from_fd(fd, &ProgInfoQueryOptions::default())
+    }
+}
+
+impl ObjInfo for MapInfo {
+    const OBJECT_TYPE: FdObjectType = FdObjectType::Map;
+
+    fn load(fd: BorrowedFd<'_>) -> Result<Self> {
+        Self::load_from_fd(fd)
+    }
+}
+
+impl ObjInfo for LinkInfo {
+    const OBJECT_TYPE: FdObjectType = FdObjectType::Link;
+
+    fn load(fd: BorrowedFd<'_>) -> Result<Self> {
+        Self::load_from_fd(fd)
+    }
+}
+
+impl ObjInfo for BtfInfo {
+    const OBJECT_TYPE: FdObjectType = FdObjectType::Btf;
+
+    fn load(fd: BorrowedFd<'_>) -> Result<Self> {
+        Self::load_from_fd(fd)
+    }
+}
+
+/// Information about the programs attached to a hook.
+#[derive(Clone, Debug)]
+pub struct AttachedProgs {
+    /// The flags the programs were attached with (e.g.,
+    /// `BPF_F_ALLOW_MULTI`).
+    pub attach_flags: u32,
+    /// The ids of the attached programs, in execution order.
+    pub prog_ids: Vec<u32>,
+    /// The flags each individual program was attached with, in the same
+    /// order as `prog_ids`. Only reported by some kernels, for some hooks,
+    /// and never for effective queries; otherwise this is empty.
+    pub prog_attach_flags: Vec<u32>,
+}
+
+/// Query the programs attached to hook `attach_type` of the object
+/// referred to by `target_fd`.
+fn query_attached_progs(
+    target_fd: BorrowedFd<'_>,
+    attach_type: ProgramAttachType,
+    query_flags: u32,
+) -> Result<AttachedProgs> {
+    let attach_type = attach_type as u32;
+    loop {
+        let mut opts = libbpf_sys::bpf_prog_query_opts {
+            sz: size_of::<libbpf_sys::bpf_prog_query_opts>() as _,
+            query_flags,
+            ..Default::default()
+        };
+        // First figure out how many programs are attached.
+        let ret = unsafe {
+            libbpf_sys::bpf_prog_query_opts(target_fd.as_raw_fd(), attach_type, &mut opts)
+        };
+        util::parse_ret(ret)?;
+
+        let count = unsafe { opts.__bindgen_anon_1.prog_cnt };
+        let mut prog_ids = vec![0u32; count as usize];
+        // The kernel reports per-program flags only for programs
+        // attached directly and rejects the request for effective
+        // queries.
+        let effective = query_flags & libbpf_sys::BPF_F_QUERY_EFFECTIVE != 0;
+        let mut prog_attach_flags = if effective {
+            Vec::new()
+        } else {
+            vec![0u32; count as usize]
+        };
+        opts.prog_ids = prog_ids.as_mut_ptr();
+        if !effective {
+            opts.prog_attach_flags = prog_attach_flags.as_mut_ptr();
+        }
+
+        let ret = unsafe {
+            libbpf_sys::bpf_prog_query_opts(target_fd.as_raw_fd(), attach_type, &mut opts)
+        };
+        match util::parse_ret(ret) {
+            Ok(()) => (),
+            // More programs got attached in the meantime. Just try again.
+            Err(_) if ret == -libc::ENOSPC => continue,
+            Err(err) => return Err(err),
+        }
+
+        let count = unsafe { opts.__bindgen_anon_1.prog_cnt } as usize;
+        prog_ids.truncate(count);
+        prog_attach_flags.truncate(count);
+        if prog_attach_flags.iter().all(|flags| *flags == 0) {
+            prog_attach_flags.clear();
+        }
+
+        break Ok(AttachedProgs {
+            attach_flags: opts.attach_flags,
+            prog_ids,
+            prog_attach_flags,
+        });
+    }
+}
+
+/// Query the programs attached to the cgroup referred to by `cgroup_fd` at
+/// hook `attach_type`.
+///
+/// If `effective` is `true`, all programs effectively run for the
+/// cgroup are reported, including those inherited from ancestor cgroups.
+/// Otherwise only programs attached directly to this cgroup are included.
+pub fn cgroup_progs(
+    cgroup_fd: BorrowedFd<'_>,
+    attach_type: ProgramAttachType,
+    effective: bool,
+) -> Result<AttachedProgs> {
+    let query_flags = if effective {
+        libbpf_sys::BPF_F_QUERY_EFFECTIVE
+    } else {
+        0
+    };
+    query_attached_progs(cgroup_fd, attach_type, query_flags)
+}
+
+/// Query the programs attached to the network namespace referred to by
+/// `netns_fd` at hook `attach_type`.
+///
+/// This is useful for discovering, for instance, flow dissector
+/// ([`ProgramAttachType::FlowDissector`]) or socket lookup
+/// ([`ProgramAttachType::SkLookup`]) programs.
+pub fn netns_progs(
+    netns_fd: BorrowedFd<'_>,
+    attach_type: ProgramAttachType,
+) -> Result<AttachedProgs> {
+    query_attached_progs(netns_fd, attach_type, 0)
+}
+
+/// The type of a BPF object referred to by a file descriptor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FdObjectType {
+    /// A BPF program.
+    Program,
+    /// A BPF map.
+    Map,
+    /// A BPF link.
+    Link,
+    /// A BTF object.
+    Btf,
+}
+
+impl FdObjectType {
+    /// The name of the anonymous inode backing file descriptors of this
+    /// type, as reported by `readlink` on `/proc/<pid>/fd/<fd>`.
+    fn anon_inode_name(&self) -> &'static str {
+        match self {
+            Self::Program => "anon_inode:bpf-prog",
+            Self::Map => "anon_inode:bpf-map",
+            Self::Link => "anon_inode:bpf_link",
+            Self::Btf => "anon_inode:btf",
+        }
+    }
+
+    /// The `/proc/<pid>/fdinfo/<fd>` key reporting the object's id.
+    fn fdinfo_id_key(&self) -> &'static str {
+        match self {
+            Self::Program => "prog_id:",
+            Self::Map => "map_id:",
+            Self::Link => "link_id:",
+            Self::Btf => "btf_id:",
+        }
+    }
+
+    /// The pointers (as struct and member name) to follow from a file's
+    /// private data to the object's id, followed by the member holding the
+    /// id itself.
+    fn id_path(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Program => &[("bpf_prog", "aux"), ("bpf_prog_aux", "id")],
+            Self::Map => &[("bpf_map", "id")],
+            Self::Link => &[("bpf_link", "id")],
+            Self::Btf => &[("btf", "id")],
+        }
+    }
+}
+
+/// A process holding a file descriptor referring to a BPF object.
+#[derive(Clone, Debug)]
+pub struct FdOwner {
+    /// The id of the process.
+    pub pid: u32,
+    /// The command name of the process.
+    pub comm: String,
+    /// The file descriptor referring to the object, in the process' file
+    /// descriptor table.
+    pub fd: i32,
+}
+
+/// The size of the records emitted by the program generated by
+/// [`fd_owners_insns`]: the process id, the file descriptor, the object id,
+/// four bytes of padding, and the command name of the process.
+const FD_OWNER_RECORD_SIZE: usize = 32;
+
+/// Encode a BPF instruction.
+fn insn(code: u32, dst: u8, src: u8, off: i16, imm: i32) -> libbpf_sys::bpf_insn {
+    let mut insn = libbpf_sys::bpf_insn {
+        code: code as u8,
+        off,
+        imm,
+        ..Default::default()
+    };
+    let () = insn.set_dst_reg(dst);
+    let () = insn.set_src_reg(src);
+    insn
+}
+
+/// Find the offset in bits of member `member` of `composite`, descending
+/// into anonymous structs and unions.
+fn composite_member_offset(btf: &Btf<'_>, composite: Composite<'_>, member: &str) -> Option<u32> {
+    composite.iter().find_map(|m| match m.name {
+        Some(name) => (name.to_bytes() == member.as_bytes()).then(|| m.attr.bit_offset()),
+        None => {
+            let ty = btf
+                .type_by_id::<BtfType<'_>>(m.ty)?
+                .skip_mods_and_typedefs();
+            let inner = Composite::try_from(ty).ok()?;
+            composite_member_offset(btf, inner, member).map(|offset| m.attr.bit_offset() + offset)
+        }
+    })
+}
+
+/// Find the offset in bytes of member `member` of struct `name` in `btf`.
+fn member_offset(btf: &Btf<'_>, name: &str, member: &str) -> Result<i16> {
+    btf.find_type_by_name_kind(name, BtfKind::Struct)
+        .and_then(|ty| Struct::try_from(ty).ok())
+        .and_then(|ty| composite_member_offset(btf, Composite::from(ty), member))
+        .and_then(|offset| i16::try_from(offset / 8).ok())
+        .ok_or_else(|| Error::with_invalid_data(format!("failed to find `{name}::{member}`")))
+}
+
+/// Generate a `task_file` iterator program emitting a record (see
+/// [`FD_OWNER_RECORD_SIZE`]) for each file descriptor referring to the
+/// object of type `ty` with id `id`, given kernel BTF `btf`.
+fn fd_owners_insns(btf: &Btf<'_>, ty: FdObjectType, id: u32) -> Result<Vec<libbpf_sys::bpf_insn>> {
+    use libbpf_sys::BPF_FUNC_probe_read_kernel;
+    use libbpf_sys::BPF_FUNC_seq_write;
+    use libbpf_sys::BPF_ADD;
+    use libbpf_sys::BPF_ALU64;
+    use libbpf_sys::BPF_B;
+    use libbpf_sys::BPF_CALL;
+    use libbpf_sys::BPF_DW;
+    use libbpf_sys::BPF_EXIT;
+    use libbpf_sys::BPF_H;
+    use libbpf_sys::BPF_IMM;
+    use libbpf_sys::BPF_JEQ;
+    use libbpf_sys::BPF_JMP;
+    use libbpf_sys::BPF_JMP32;
+    use libbpf_sys::BPF_JNE;
+    use libbpf_sys::BPF_K;
+    use libbpf_sys::BPF_LD;
+    use libbpf_sys::BPF_LDX;
+    use libbpf_sys::BPF_MEM;
+    use libbpf_sys::BPF_MOV;
+    use libbpf_sys::BPF_ST;
+    use libbpf_sys::BPF_STX;
+    use libbpf_sys::BPF_W;
+    use libbpf_sys::BPF_X;
+
+    let ctx_meta = member_offset(btf, "bpf_iter__task_file", "meta")?;
+    let ctx_task = member_offset(btf, "bpf_iter__task_file", "task")?;
+    let ctx_fd = member_offset(btf, "bpf_iter__task_file", "fd")?;
+    let ctx_file = member_offset(btf, "bpf_iter__task_file", "file")?;
+    let meta_seq = member_offset(btf, "bpf_iter_meta", "seq")?;
+    let task_tgid = member_offset(btf, "task_struct", "tgid")?;
+    let task_leader = member_offset(btf, "task_struct", "group_leader")?;
+    let task_comm = member_offset(btf, "task_struct", "comm")?;
+    let file_dentry = member_offset(btf, "file", "f_path")? + member_offset(btf, "path", "dentry")?;
+    let file_data = member_offset(btf, "file", "private_data")?;
+    let dentry_name = member_offset(btf, "dentry", "d_name")? + member_offset(btf, "qstr", "name")?;
+
+    // The record is assembled on the stack, with the command name making
+    // up its last 16 bytes. Its space doubles as scratch space for
+    // reading pointers.
+    let record = -(FD_OWNER_RECORD_SIZE as i16);
+    let comm = -16;
+    // Instructions to jump to the end from, to be patched once known.
+    let mut exits = Vec::new();
+
+    let mut insns = vec![
+        // r6 = ctx
+        insn(BPF_ALU64 | BPF_MOV | BPF_X, 6, 1, 0, 0),
+        // r7 = ctx->task
+        insn(BPF_LDX | BPF_MEM | BPF_DW, 7, 6, ctx_task, 0),
+        // r8 = ctx->file
+        insn(BPF_LDX | BPF_MEM | BPF_DW, 8, 6, ctx_file, 0),
+    ];
+    for reg in [7, 8] {
+        // if rX == 0 goto exit
+        exits.push(insns.len());
+        insns.push(insn(BPF_JMP | BPF_JEQ | BPF_K, reg, 0, 0, 0));
+    }
+    // Files referring to BPF objects are backed by anonymous inodes, which
+    // are named after the object type, just as reported via `/proc`.
+    // SANITY: All names carry the prefix.
+    let name = ty.anon_inode_name().strip_prefix("anon_inode:").unwrap();
+    let name = [name.as_bytes(), b"\0"].concat();
+    insns.extend([
+        // bpf_probe_read_kernel(r10 + <comm>, 16, file->f_path.dentry->d_name.name)
+        insn(BPF_LDX | BPF_MEM | BPF_DW, 1, 8, file_dentry, 0),
+        insn(BPF_LDX | BPF_MEM | BPF_DW, 3, 1, dentry_name, 0),
+        insn(BPF_ALU64 | BPF_MOV | BPF_X, 1, 10, 0, 0),
+        insn(BPF_ALU64 | BPF_ADD | BPF_K, 1, 0, 0, comm.into()),
+        insn(BPF_ALU64 | BPF_MOV | BPF_K, 2, 0, 0, -i32::from(comm)),
+        insn(BPF_JMP | BPF_CALL, 0, 0, 0, BPF_FUNC_probe_read_kernel as _),
+    ]);
+    let mut offset = 0;
+    while offset < name.len() {
+        let (size, code) = match name.len() - offset {
+            8.. => (8, BPF_DW),
+            4.. => (4, BPF_W),
+            2.. => (2, BPF_H),
+            _ => (1, BPF_B),
+        };
+        let mut value = [0; 8];
+        let () = value[..size].copy_from_slice(&name[offset..offset + size]);
+        let value = if cfg!(target_endian = "little") {
+            u64::from_le_bytes(value)
+        } else {
+            u64::from_be_bytes(value) >> (64 - 8 * size)
+        };
+
+        // r1 = *(<size> *)(r10 + <comm> + <offset>)
+        insns.push(insn(
+            BPF_LDX | BPF_MEM | code,
+            1,
+            10,
+            comm + offset as i16,
+            0,
+        ));
+        if size == 8 {
+            insns.extend([
+                // r2 = <value>
+                insn(BPF_LD | BPF_IMM | BPF_DW, 2, 0, 0, value as i32),
+                insn(0, 0, 0, 0, (value >> 32) as i32),
+            ]);
+            // if r1 != r2 goto exit
+            exits.push(insns.len());
+            insns.push(insn(BPF_JMP | BPF_JNE | BPF_X, 1, 2, 0, 0));
+        } else {
+            // if w1 != <value> goto exit
+            exits.push(insns.len());
+            insns.push(insn(BPF_JMP32 | BPF_JNE | BPF_K, 1, 0, 0, value as i32));
+        }
+        offset += size;
+    }
+
+    // r3 = file->private_data
+    insns.push(insn(BPF_LDX | BPF_MEM | BPF_DW, 3, 8, file_data, 0));
+    let id_path = ty.id_path();
+    for (idx, (name, member)) in id_path.iter().enumerate() {
+        let last = idx == id_path.len() - 1;
+        let (dst, size) = if last { (record + 8, 4) } else { (comm, 8) };
+        insns.extend([
+            // r3 += offsetof(<name>, <member>)
+            insn(
+                BPF_ALU64 | BPF_ADD | BPF_K,
+                3,
+                0,
+                0,
+                member_offset(btf, name, member)?.into(),
+            ),
+            // bpf_probe_read_kernel(r10 + <dst>, <size>, r3)
+            insn(BPF_ALU64 | BPF_MOV | BPF_X, 1, 10, 0, 0),
+            insn(BPF_ALU64 | BPF_ADD | BPF_K, 1, 0, 0, dst.into()),
+            insn(BPF_ALU64 | BPF_MOV | BPF_K, 2, 0, 0, size),
+            insn(BPF_JMP | BPF_CALL, 0, 0, 0, BPF_FUNC_probe_read_kernel as _),
+        ]);
+        if !last {
+            // r3 = *(u64 *)(r10 + <dst>)
+            insns.push(insn(BPF_LDX | BPF_MEM | BPF_DW, 3, 10, dst, 0));
+        }
+    }
+    // r1 = *(u32 *)(r10 + <id>)
+    insns.push(insn(BPF_LDX | BPF_MEM | BPF_W, 1, 10, record + 8, 0));
+    // if w1 != <id> goto exit
+    exits.push(insns.len());
+    insns.push(insn(BPF_JMP32 | BPF_JNE | BPF_K, 1, 0, 0, id as i32));
+
+    insns.extend([
+        // *(u32 *)(r10 + <pid>) = task->tgid
+        insn(BPF_LDX | BPF_MEM | BPF_W, 1, 7, task_tgid, 0),
+        insn(BPF_STX | BPF_MEM | BPF_W, 10, 1, record, 0),
+        // *(u32 *)(r10 + <fd>) = ctx->fd
+        insn(BPF_LDX | BPF_MEM | BPF_W, 1, 6, ctx_fd, 0),
+        insn(BPF_STX | BPF_MEM | BPF_W, 10, 1, record + 4, 0),
+        // *(u32 *)(r10 + <padding>) = 0
+        insn(BPF_ST | BPF_MEM | BPF_W, 10, 0, record + 12, 0),
+        // bpf_probe_read_kernel(r10 + <comm>, 16, task->group_leader->comm)
+        insn(BPF_LDX | BPF_MEM | BPF_DW, 3, 7, task_leader, 0),
+        insn(BPF_ALU64 | BPF_ADD | BPF_K, 3, 0, 0, task_comm.into()),
+        insn(BPF_ALU64 | BPF_MOV | BPF_X, 1, 10, 0, 0),
+        insn(BPF_ALU64 | BPF_ADD | BPF_K, 1, 0, 0, comm.into()),
+        insn(BPF_ALU64 | BPF_MOV | BPF_K, 2, 0, 0, -i32::from(comm)),
+        insn(BPF_JMP | BPF_CALL, 0, 0, 0, BPF_FUNC_probe_read_kernel as _),
+        // bpf_seq_write(ctx->meta->seq, r10 + <record>, <record size>)
+        insn(BPF_LDX | BPF_MEM | BPF_DW, 1, 6, ctx_meta, 0),
+        insn(BPF_LDX | BPF_MEM | BPF_DW, 1, 1, meta_seq, 0),
+        insn(BPF_ALU64 | BPF_MOV | BPF_X, 2, 10, 0, 0),
+        insn(BPF_ALU64 | BPF_ADD | BPF_K, 2, 0, 0, record.into()),
+        insn(
+            BPF_ALU64 | BPF_MOV | BPF_K,
+            3,
+            0,
+            0,
+            FD_OWNER_RECORD_SIZE as _,
+        ),
+        insn(BPF_JMP | BPF_CALL, 0, 0, 0, BPF_FUNC_seq_write as _),
+    ]);
+
+    // exit: return 0
+    let exit = insns.len();
+    for idx in exits {
+        insns[idx].off = (exit - idx - 1) as i16;
+    }
+    insns.extend([
+        insn(BPF_ALU64 | BPF_MOV | BPF_K, 0, 0, 0, 0),
+        insn(BPF_JMP | BPF_EXIT, 0, 0, 0, 0),
+    ]);
+    Ok(insns)
+}
+
+/// Find the processes holding file descriptors referring to the object of
+/// type `ty` with id `id` by means of a `task_file` BPF iterator.
+fn fd_owners_iter(ty: FdObjectType, id: u32) -> Result<Vec<FdOwner>> {
+    let btf = Btf::from_vmlinux()?;
+    let insns = fd_owners_insns(&btf, ty, id)?;
+
+    let attach_btf_id = btf
+        .find_type_by_name_kind("bpf_iter_task_file", BtfKind::Func)
+        .ok_or_else(|| Error::with_invalid_data("kernel lacks the `task_file` iterator"))?
+        .type_id();
+    let mut opts = libbpf_sys::bpf_prog_load_opts {
+        sz: size_of::<libbpf_sys::bpf_prog_load_opts>() as _,
+        expected_attach_type: libbpf_sys::BPF_TRACE_ITER,
+        attach_btf_id: attach_btf_id.into(),
+        ..Default::default()
+    };
+    let ret = unsafe {
+        libbpf_sys::bpf_prog_load(
+            libbpf_sys::BPF_PROG_TYPE_TRACING,
+            ptr::null(),
+            b"GPL\0".as_ptr().cast(),
+            insns.as_ptr(),
+            insns.len() as _,
+            &mut opts,
+        )
+    };
+    // SAFETY: `bpf_prog_load` returned a valid file descriptor that nobody
+    //         else owns.
+    let prog = unsafe { OwnedFd::from_raw_fd(util::parse_ret_i32(ret)?) };
+
+    let ret = unsafe {
+        libbpf_sys::bpf_link_create(prog.as_raw_fd(), 0, libbpf_sys::BPF_TRACE_ITER, ptr::null())
+    };
+    // SAFETY: `bpf_link_create` returned a valid file descriptor that
+    //         nobody else owns.
+    let link = unsafe { OwnedFd::from_raw_fd(util::parse_ret_i32(ret)?) };
+
+    let ret = unsafe { libbpf_sys::bpf_iter_create(link.as_raw_fd()) };
+    // SAFETY: `bpf_iter_create` returned a valid file descriptor that
+    //         nobody else owns.
+    let iter = unsafe { OwnedFd::from_raw_fd(util::parse_ret_i32(ret)?) };
+    let mut records = Vec::new();
+    let _count = File::from(iter).read_to_end(&mut records)?;
+
+    let owners = records
+        .chunks_exact(FD_OWNER_RECORD_SIZE)
+        .map(|record| {
+            let u32_at = |offset: usize| {
+                // SANITY: The record is large enough for all fields.
+                u32::from_ne_bytes(record[offset..offset + 4].try_into().unwrap())
+            };
+            let comm = &record[16..];
+            let comm = comm.split(|byte| *byte == 0).next().unwrap_or_default();
+            FdOwner {
+                pid: u32_at(0),
+                comm: String::from_utf8_lossy(comm).into_owned(),
+                fd: u32_at(4) as i32,
+            }
+        })
+        .collect();
+    Ok(owners)
+}
+
+/// Find the processes holding file descriptors referring to the object of
+/// type `ty` with id `id` by scanning `/proc`.
+fn fd_owners_proc(ty: FdObjectType, id: u32) -> Result<Vec<FdOwner>> {
+    let mut owners = Vec::new();
+
+    for entry in fs::read_dir("/proc")? {
+        let entry = entry?;
+        let pid = match entry.file_name().to_str().map(str::parse::<u32>) {
+            Some(Ok(pid)) => pid,
+            _ => continue,
+        };
+        let proc_dir = entry.path();
+        // The process may exit at any point, in which case we just skip
+        // it.
+        let fds = match fs::read_dir(proc_dir.join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+
+        for fd_entry in fds.flatten() {
+            let fd = match fd_entry.file_name().to_str().map(str::parse::<i32>) {
+                Some(Ok(fd)) => fd,
+                _ => continue,
+            };
+            match fs::read_link(fd_entry.path()) {
+                Ok(target) if target.as_os_str() == ty.anon_inode_name() => (),
+                _ => continue,
+            }
+
+            let fdinfo = match fs::read_to_string(proc_dir.join("fdinfo").join(fd.to_string())) {
+                Ok(fdinfo) => fdinfo,
+                Err(_) => continue,
+            };
+            let fd_id = fdinfo.lines().find_map(|line| {
+                line.strip_prefix(ty.fdinfo_id_key())
+                    .and_then(|value| value.trim().parse::<u32>().ok())
+            });
+            if fd_id != Some(id) {
+                continue;
+            }
+
+            let comm = fs::read_to_string(proc_dir.join("comm"))
+                .map(|comm| comm.trim_end().to_string())
+                .unwrap_or_default();
+            owners.push(FdOwner { pid, comm, fd });
+        }
+    }
+    Ok(owners)
+}
+
+/// Find all processes holding file descriptors referring to the BPF object
+/// of type `ty` with the given `id`.
+///
+/// Like `bpftool`, this function walks the file descriptors of all
+/// processes using a `task_file` BPF iterator, which requires kernel BTF
+/// and the privileges for loading tracing programs. Process ids reported
+/// this way are the ones of the initial PID namespace.
+///
+/// If the iterator can't be used, `/proc` is scanned instead, which
+/// requires sufficient privileges for inspecting other processes' file
+/// descriptors. Processes that can't be inspected are silently skipped.
+/// Only processes visible in the `/proc` instance of the caller's mount
+/// namespace are reported then (e.g., processes in other PID namespaces or
+/// hidden by `hidepid` are not), and ids are the ones of the PID namespace
+/// `/proc` belongs to. Scanning is considerably slower than iterating,
+/// which matters on systems with many processes or file descriptors.
+pub fn fd_owners(ty: FdObjectType, id: u32) -> Result<Vec<FdOwner>> {
+    fd_owners_iter(ty, id).or_else(|_err| fd_owners_proc(ty, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process;
+
+    use crate::MapHandle;
+
+    /// Check that we can find the processes holding a map via a
+    /// `task_file` iterator.
+    #[test]
+    fn test_sudo_fd_owners_iter() {
+        let opts = libbpf_sys::bpf_map_create_opts {
+            sz: size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+        let map = MapHandle::create(MapType::Array, Some("owned_map"), 4, 4, 1, &opts).unwrap();
+        let map_id = map.info().unwrap().info.id;
+
+        let owners = fd_owners_iter(FdObjectType::Map, map_id).unwrap();
+        let owner = owners
+            .iter()
+            .find(|owner| owner.pid == process::id())
+            .expect("failed to find own process");
+        assert_eq!(owner.fd, map.as_fd().as_raw_fd());
+        assert_eq!(
+            owner.comm,
+            fs::read_to_string("/proc/self/comm").unwrap().trim_end()
+        );
+
+        // The map id is not the id of any program.
+        let owners = fd_owners_iter(FdObjectType::Program, map_id).unwrap();
+        assert!(owners
+            .iter()
+            .all(|owner| owner.pid != process::id() || owner.fd != map.as_fd().as_raw_fd()));
+    }
+
+    /// Check that we can find the processes holding a program via a
+    /// `task_file` iterator.
+    #[test]
+    fn test_sudo_fd_owners_iter_prog() {
+        // A program doing nothing but `return 0`.
+        let insns = [
+            insn(
+                libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K,
+                0,
+                0,
+                0,
+                0,
+            ),
+            insn(libbpf_sys::BPF_JMP | libbpf_sys::BPF_EXIT, 0, 0, 0, 0),
+        ];
+        let ret = unsafe {
+            libbpf_sys::bpf_prog_load(
+                libbpf_sys::BPF_PROG_TYPE_SOCKET_FILTER,
+                ptr::null(),
+                b"GPL\0".as_ptr().cast(),
+                insns.as_ptr(),
+                insns.len() as _,
+                ptr::null_mut(),
+            )
+        };
+        let prog = unsafe { OwnedFd::from_raw_fd(util::parse_ret_i32(ret).unwrap()) };
+        let prog_id = ProgramInfo::load_from_fd(prog.as_fd(), &ProgInfoQueryOptions::default())
+            .unwrap()
+            .id;
+
+        let owners = fd_owners_iter(FdObjectType::Program, prog_id).unwrap();
+        assert!(owners
+            .iter()
+            .any(|owner| owner.pid == process::id() && owner.fd == prog.as_raw_fd()));
+    }
+}
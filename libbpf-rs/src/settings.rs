@@ -0,0 +1,173 @@
+use std::io;
+
+use bitflags::bitflags;
+
+use crate::probes;
+use crate::util;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::Result;
+
+bitflags! {
+    /// The set of "strict" behaviors libbpf should adhere to, as opposed to
+    /// the legacy behaviors of libbpf versions prior to 1.0 (see
+    /// `enum libbpf_strict_mode`).
+    ///
+    /// A set flag opts into the modern behavior, a cleared one requests the
+    /// legacy one.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct StrictMode: u32 {
+        /// Return `NULL` from pointer returning APIs on error, instead of an
+        /// error encoded in the pointer value.
+        const CLEAN_PTRS          = libbpf_sys::LIBBPF_STRICT_CLEAN_PTRS as _;
+        /// Return errors directly as negative error codes, instead of `-1`
+        /// with `errno` set.
+        const DIRECT_ERRS         = libbpf_sys::LIBBPF_STRICT_DIRECT_ERRS as _;
+        /// Enforce the strict set of program section names.
+        const SEC_NAME            = libbpf_sys::LIBBPF_STRICT_SEC_NAME as _;
+        /// Do not keep a global list of opened BPF objects.
+        const NO_OBJECT_LIST      = libbpf_sys::LIBBPF_STRICT_NO_OBJECT_LIST as _;
+        /// Automatically raise `RLIMIT_MEMLOCK` on kernels without memory
+        /// cgroup based accounting of BPF memory.
+        const AUTO_RLIMIT_MEMLOCK = libbpf_sys::LIBBPF_STRICT_AUTO_RLIMIT_MEMLOCK as _;
+        /// Reject legacy `SEC("maps")` map definitions in favor of BTF
+        /// defined ones.
+        const MAP_DEFINITIONS     = libbpf_sys::LIBBPF_STRICT_MAP_DEFINITIONS as _;
+    }
+}
+
+/// Configure the strict mode libbpf operates in.
+///
+/// Starting with version 1.0, libbpf always behaves strictly and
+/// `libbpf_set_strict_mode` has no effect. The only legacy behavior that
+/// can still be requested is opting out of automatic `RLIMIT_MEMLOCK`
+/// adjustments, which is done by clearing
+/// [`StrictMode::AUTO_RLIMIT_MEMLOCK`] (equivalent to
+/// [`set_memlock_rlimit(0)`][set_memlock_rlimit]). Requesting any other
+/// legacy behavior results in an error of kind
+/// [`ErrorKind::Unsupported`][crate::ErrorKind::Unsupported], instead of
+/// being silently ignored.
+pub fn set_strict_mode(mode: StrictMode) -> Result<()> {
+    let legacy = StrictMode::all().difference(mode) - StrictMode::AUTO_RLIMIT_MEMLOCK;
+    if !legacy.is_empty() {
+        return Err(Error::with_io_error(
+            io::ErrorKind::Unsupported,
+            format!("libbpf does not support legacy behavior {legacy:?}"),
+        ));
+    }
+
+    let ret = unsafe { libbpf_sys::libbpf_set_strict_mode(mode.bits()) };
+    let () = util::parse_ret(ret)?;
+
+    if !mode.contains(StrictMode::AUTO_RLIMIT_MEMLOCK) {
+        let () = set_memlock_rlimit(0)?;
+    }
+    Ok(())
+}
+
+/// Set the `RLIMIT_MEMLOCK` value (in bytes) libbpf raises the process
+/// limit to before creating its first BPF map or program, on kernels that
+/// still account BPF memory against it.
+///
+/// By default, libbpf raises the limit to `RLIM_INFINITY`.
+///
+/// A value of zero disables the adjustment altogether, leaving the limit
+/// as it is. This setting has to be changed before libbpf creates any BPF
+/// objects; afterwards the call fails with `EBUSY`.
+pub fn set_memlock_rlimit(bytes: usize) -> Result<()> {
+    let ret = unsafe { libbpf_sys::libbpf_set_memlock_rlim(bytes as _) };
+    util::parse_ret(ret)
+}
+
+/// A guard restoring the `RLIMIT_MEMLOCK` resource limit of the process
+/// to its previous value when dropped, as returned by
+/// [`raise_memlock_rlimit`].
+///
+/// Memory already charged against the limit stays with the BPF maps and
+/// programs created, so the guard can be dropped once all of them got
+/// loaded. Use [`mem::forget`][std::mem::forget] to keep the limit raised
+/// for the lifetime of the process.
+#[derive(Debug)]
+#[must_use = "the previous RLIMIT_MEMLOCK is restored when the guard is dropped"]
+pub struct MemlockRlimitGuard {
+    /// The previous limit, if it got raised.
+    prev: Option<libc::rlimit>,
+}
+
+impl MemlockRlimitGuard {
+    /// Check whether the limit was raised, as opposed to being left alone
+    /// because the kernel uses memory cgroup based accounting.
+    #[inline]
+    pub fn raised(&self) -> bool {
+        self.prev.is_some()
+    }
+}
+
+impl Drop for MemlockRlimitGuard {
+    fn drop(&mut self) {
+        if let Some(prev) = &self.prev {
+            let _ret = unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, prev) };
+        }
+    }
+}
+
+/// Lift the `RLIMIT_MEMLOCK` resource limit of the process, if the kernel
+/// charges the memory of BPF maps and programs against it.
+///
+/// Kernels before 5.11 account BPF memory against `RLIMIT_MEMLOCK`, the
+/// default of which is too low for all but the smallest BPF objects. libbpf
+/// raises the limit on such kernels before creating its first BPF map or
+/// program (to `RLIM_INFINITY`, unless configured otherwise via
+/// [`set_memlock_rlimit`]), but keeps it raised for the remainder of the
+/// process' lifetime. This function removes the limit only until the
+/// returned guard is dropped, which restores the limit the process had
+/// before. On kernels using memory cgroup based accounting (see
+/// [`probes::memcg_accounting_supported`]) the limit is left untouched.
+///
+/// Raising the hard limit requires `CAP_SYS_RESOURCE`. Without it, an
+/// error of kind [`ErrorKind::PermissionDenied`][crate::ErrorKind::PermissionDenied]
+/// is reported.
+///
+/// ```no_run
+/// # use libbpf_rs::raise_memlock_rlimit;
+/// # use libbpf_rs::ObjectBuilder;
+/// let _guard = raise_memlock_rlimit().unwrap();
+/// let obj = ObjectBuilder::default()
+///     .open_file("prog.bpf.o")
+///     .unwrap()
+///     .load()
+///     .unwrap();
+/// ```
+pub fn raise_memlock_rlimit() -> Result<MemlockRlimitGuard> {
+    // Probing loads a BPF program, for which libbpf may already raise the
+    // limit, so retrieve it beforehand.
+    let mut prev = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    let rc = unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut prev) };
+    if rc != 0 {
+        return Err(io::Error::last_os_error()).context("failed to retrieve RLIMIT_MEMLOCK");
+    }
+
+    if probes::memcg_accounting_supported()? {
+        return Ok(MemlockRlimitGuard { prev: None });
+    }
+
+    let rlimit = libc::rlimit {
+        rlim_cur: libc::RLIM_INFINITY,
+        rlim_max: libc::RLIM_INFINITY,
+    };
+    let rc = unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &rlimit) };
+    if rc != 0 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() == Some(libc::EPERM) {
+            return Err(err).context(
+                "failed to raise RLIMIT_MEMLOCK: raising the hard limit requires CAP_SYS_RESOURCE",
+            );
+        }
+        return Err(err).context("failed to raise RLIMIT_MEMLOCK");
+    }
+
+    Ok(MemlockRlimitGuard { prev: Some(prev) })
+}
@@ -3,33 +3,47 @@ use std::alloc::alloc_zeroed;
 use std::alloc::dealloc;
 use std::alloc::Layout;
 use std::ffi::CString;
+use std::io;
 use std::mem::size_of;
 use std::os::raw::c_char;
 use std::os::raw::c_ulong;
+use std::path::Path;
 use std::ptr;
 use std::ptr::NonNull;
+#[cfg(feature = "zstd")]
+use std::sync::OnceLock;
 
 use libbpf_sys::bpf_link;
 use libbpf_sys::bpf_map;
 use libbpf_sys::bpf_map_skeleton;
 use libbpf_sys::bpf_object;
 use libbpf_sys::bpf_object_skeleton;
+use libbpf_sys::bpf_object_subskeleton;
 use libbpf_sys::bpf_prog_skeleton;
 use libbpf_sys::bpf_program;
+use libbpf_sys::bpf_var_skeleton;
+#[cfg(feature = "zstd")]
+use zstd::stream::decode_all;
 
 use crate::error::IntoError as _;
+use crate::error::LibbpfCallExt as _;
 use crate::util;
 use crate::Error;
+use crate::Link;
 use crate::Object;
 use crate::ObjectBuilder;
 use crate::OpenObject;
 use crate::Result;
 
+pub use crate::util::load_object;
+
 #[derive(Debug)]
 struct MapSkelConfig {
     name: String,
     p: Box<*mut bpf_map>,
     mmaped: Option<Box<*mut c_void>>,
+    /// Link of the map, if it is a `struct_ops` map that got attached.
+    link: Box<*mut bpf_link>,
 }
 
 #[derive(Debug)]
@@ -39,6 +53,101 @@ struct ProgSkelConfig {
     link: Box<*mut bpf_link>,
 }
 
+#[derive(Debug)]
+struct VarSkelConfig {
+    name: String,
+    /// Index of the map containing the variable.
+    map_idx: usize,
+    addr: Box<*mut c_void>,
+}
+
+/// Decompress zstd compressed object data, as embedded by skeletons
+/// generated with compression enabled.
+#[cfg(feature = "zstd")]
+pub fn decompress_object(data: &[u8]) -> Result<Vec<u8>> {
+    decode_all(data).map_err(Error::from)
+}
+
+/// A zstd compressed object embedded in a skeleton, which gets
+/// decompressed on first use.
+#[cfg(feature = "zstd")]
+#[derive(Debug)]
+pub struct CompressedObject {
+    data: &'static [u8],
+    object: OnceLock<Vec<u8>>,
+}
+
+#[cfg(feature = "zstd")]
+impl CompressedObject {
+    /// Create a [`CompressedObject`] for the compressed object `data`.
+    pub const fn new(data: &'static [u8]) -> Self {
+        Self {
+            data,
+            object: OnceLock::new(),
+        }
+    }
+
+    /// Retrieve the decompressed object, decompressing it if necessary.
+    pub fn data(&'static self) -> Result<&'static [u8]> {
+        if let Some(object) = self.object.get() {
+            return Ok(object);
+        }
+        let object = decompress_object(self.data)?;
+        Ok(self.object.get_or_init(|| object))
+    }
+}
+
+/// Create the error reported when attaching a program that a skeleton does
+/// not contain by name.
+pub fn prog_not_found(name: &str) -> Error {
+    Error::with_io_error(
+        io::ErrorKind::NotFound,
+        format!("program `{name}` not found"),
+    )
+}
+
+/// Check the result `ret` of invoking the libbpf function `function` on
+/// the object `name`, attributing a failure to said invocation.
+pub fn check_call(function: &'static str, name: &str, ret: i32) -> Result<()> {
+    util::parse_ret(ret).libbpf_call(function, name)
+}
+
+/// Pin each of `links`, named after the program (or `struct_ops` map) it
+/// belongs to, as `<path>/<name>` and disconnect them, so that they stay
+/// in place once dropped.
+///
+/// Links that are pinned already are only disconnected. Should pinning any
+/// link fail, pins created up to that point are removed again and no link
+/// gets disconnected.
+pub fn persist_links(path: &Path, links: &mut [(&str, Option<&mut Link>)]) -> Result<()> {
+    let mut pinned = Vec::<usize>::with_capacity(links.len());
+    for idx in 0..links.len() {
+        let (name, Some(link)) = &mut links[idx] else {
+            continue;
+        };
+        if link.pin_path().is_some() {
+            continue;
+        }
+
+        if let Err(err) = link.pin(path.join(name)) {
+            for idx in pinned {
+                if let (_, Some(link)) = &mut links[idx] {
+                    let _ = link.unpin();
+                }
+            }
+            return Err(err);
+        }
+        let () = pinned.push(idx);
+    }
+
+    for (_, link) in links.iter_mut() {
+        if let Some(link) = link {
+            let () = link.disconnect();
+        }
+    }
+    Ok(())
+}
+
 #[allow(missing_docs)]
 #[derive(Debug)]
 pub struct ObjectSkeletonConfigBuilder<'a> {
@@ -57,6 +166,68 @@ fn str_to_cstring_and_pool(s: &str, pool: &mut Vec<CString>) -> Result<*const c_
     Ok(p)
 }
 
+/// Allocate and fill an array of `bpf_map_skeleton` objects describing
+/// `maps`.
+fn build_map_skels(
+    maps: &mut [MapSkelConfig],
+    string_pool: &mut Vec<CString>,
+) -> Option<(*mut bpf_map_skeleton, Layout)> {
+    if maps.is_empty() {
+        return None;
+    }
+
+    let layout = Layout::array::<bpf_map_skeleton>(maps.len())
+        .expect("Failed to allocate memory for maps skeleton");
+
+    unsafe {
+        let skels = alloc_zeroed(layout) as *mut bpf_map_skeleton;
+        for (i, map) in maps.iter_mut().enumerate() {
+            let current_map = skels.add(i);
+
+            // Opt to panic on error here. We've already allocated memory and we'd rather not
+            // leak. Extremely unlikely to have invalid unicode anyways.
+            (*current_map).name = str_to_cstring_and_pool(&map.name, string_pool)
+                .expect("Invalid unicode in map name");
+            (*current_map).map = &mut *map.p;
+            (*current_map).mmaped = if let Some(ref mut mmaped) = map.mmaped {
+                &mut **mmaped
+            } else {
+                ptr::null_mut()
+            };
+            (*current_map).link = &mut *map.link;
+        }
+        Some((skels, layout))
+    }
+}
+
+/// Allocate and fill an array of `bpf_prog_skeleton` objects describing
+/// `progs`.
+fn build_prog_skels(
+    progs: &mut [ProgSkelConfig],
+    string_pool: &mut Vec<CString>,
+) -> Option<(*mut bpf_prog_skeleton, Layout)> {
+    if progs.is_empty() {
+        return None;
+    }
+
+    let layout = Layout::array::<bpf_prog_skeleton>(progs.len())
+        .expect("Failed to allocate memory for progs skeleton");
+
+    unsafe {
+        let skels = alloc_zeroed(layout) as *mut bpf_prog_skeleton;
+        for (i, prog) in progs.iter_mut().enumerate() {
+            let current_prog = skels.add(i);
+
+            // See above for `expect()` rationale
+            (*current_prog).name = str_to_cstring_and_pool(&prog.name, string_pool)
+                .expect("Invalid unicode in prog name");
+            (*current_prog).prog = &mut *prog.p;
+            (*current_prog).link = &mut *prog.link;
+        }
+        Some((skels, layout))
+    }
+}
+
 impl<'a> ObjectSkeletonConfigBuilder<'a> {
     /// Construct a new instance
     ///
@@ -94,6 +265,7 @@ impl<'a> ObjectSkeletonConfigBuilder<'a> {
             name: name.as_ref().to_string(),
             p: Box::new(ptr::null_mut()),
             mmaped: m,
+            link: Box::new(ptr::null_mut()),
         });
 
         self
@@ -115,34 +287,10 @@ impl<'a> ObjectSkeletonConfigBuilder<'a> {
         s: &mut bpf_object_skeleton,
         string_pool: &mut Vec<CString>,
     ) -> Option<Layout> {
-        if maps.is_empty() {
-            return None;
-        }
-
+        let (skels, layout) = build_map_skels(maps, string_pool)?;
         s.map_cnt = maps.len() as i32;
         s.map_skel_sz = size_of::<bpf_map_skeleton>() as i32;
-
-        let layout = Layout::array::<bpf_map_skeleton>(maps.len())
-            .expect("Failed to allocate memory for maps skeleton");
-
-        unsafe {
-            s.maps = alloc_zeroed(layout) as *mut bpf_map_skeleton;
-            for (i, map) in maps.iter_mut().enumerate() {
-                let current_map = s.maps.add(i);
-
-                // Opt to panic on error here. We've already allocated memory and we'd rather not
-                // leak. Extremely unlikely to have invalid unicode anyways.
-                (*current_map).name = str_to_cstring_and_pool(&map.name, string_pool)
-                    .expect("Invalid unicode in map name");
-                (*current_map).map = &mut *map.p;
-                (*current_map).mmaped = if let Some(ref mut mmaped) = map.mmaped {
-                    &mut **mmaped
-                } else {
-                    ptr::null_mut()
-                };
-            }
-        }
-
+        s.maps = skels;
         Some(layout)
     }
 
@@ -151,29 +299,10 @@ impl<'a> ObjectSkeletonConfigBuilder<'a> {
         s: &mut bpf_object_skeleton,
         string_pool: &mut Vec<CString>,
     ) -> Option<Layout> {
-        if progs.is_empty() {
-            return None;
-        }
-
+        let (skels, layout) = build_prog_skels(progs, string_pool)?;
         s.prog_cnt = progs.len() as i32;
         s.prog_skel_sz = size_of::<bpf_prog_skeleton>() as i32;
-
-        let layout = Layout::array::<bpf_prog_skeleton>(progs.len())
-            .expect("Failed to allocate memory for progs skeleton");
-
-        unsafe {
-            s.progs = alloc_zeroed(layout) as *mut bpf_prog_skeleton;
-            for (i, prog) in progs.iter_mut().enumerate() {
-                let current_prog = s.progs.add(i);
-
-                // See above for `expect()` rationale
-                (*current_prog).name = str_to_cstring_and_pool(&prog.name, string_pool)
-                    .expect("Invalid unicode in prog name");
-                (*current_prog).prog = &mut *prog.p;
-                (*current_prog).link = &mut *prog.link;
-            }
-        }
-
+        s.progs = skels;
         Some(layout)
     }
 
@@ -288,6 +417,25 @@ impl ObjectSkeletonConfig<'_> {
         self.map_mmap_ptr(index).map(|p| p.cast_mut())
     }
 
+    /// Returns the link pointer for a map at the specified `index`.
+    ///
+    /// Only `struct_ops` maps get attached and, hence, may have a non-NULL
+    /// link.
+    ///
+    /// The index is determined by the order in which the map was passed to
+    /// `ObjectSkeletonConfigBuilder::map`. Index starts at 0.
+    ///
+    /// Warning: the returned pointer is only valid while the `ObjectSkeletonConfig` is alive.
+    pub fn map_link_ptr(&mut self, index: usize) -> Result<*mut bpf_link> {
+        if index >= self.maps.len() {
+            return Err(Error::with_invalid_data(format!(
+                "Invalid map index: {index}"
+            )));
+        }
+
+        Ok(*self.maps[index].link)
+    }
+
     /// Returns the link pointer for a prog at the specified `index`.
     ///
     /// The index is determined by the order in which the prog was passed to
@@ -303,6 +451,36 @@ impl ObjectSkeletonConfig<'_> {
 
         Ok(*self.progs[index].link)
     }
+
+    /// Set the link pointer for a map at the specified `index`.
+    ///
+    /// libbpf does not attach maps that already have a link when attaching
+    /// the skeleton. The link remains owned by the caller.
+    pub fn set_map_link_ptr(&mut self, index: usize, link: *mut bpf_link) -> Result<()> {
+        if index >= self.maps.len() {
+            return Err(Error::with_invalid_data(format!(
+                "Invalid map index: {index}"
+            )));
+        }
+
+        *self.maps[index].link = link;
+        Ok(())
+    }
+
+    /// Set the link pointer for a prog at the specified `index`.
+    ///
+    /// libbpf does not attach programs that already have a link when
+    /// attaching the skeleton. The link remains owned by the caller.
+    pub fn set_prog_link_ptr(&mut self, index: usize, link: *mut bpf_link) -> Result<()> {
+        if index >= self.progs.len() {
+            return Err(Error::with_invalid_data(format!(
+                "Invalid prog index: {index}"
+            )));
+        }
+
+        *self.progs[index].link = link;
+        Ok(())
+    }
 }
 
 impl Drop for ObjectSkeletonConfig<'_> {
@@ -337,12 +515,254 @@ impl Drop for ObjectSkeletonConfig<'_> {
     }
 }
 
+/// Builder for an [`ObjectSubskeletonConfig`], describing the maps,
+/// programs, and global variables of a BPF library that has been linked
+/// into an existing object.
+#[derive(Debug)]
+pub struct ObjectSubskeletonConfigBuilder {
+    obj: NonNull<bpf_object>,
+    maps: Vec<MapSkelConfig>,
+    progs: Vec<ProgSkelConfig>,
+    vars: Vec<VarSkelConfig>,
+}
+
+impl ObjectSubskeletonConfigBuilder {
+    /// Construct a new instance for the object `obj`.
+    pub fn new(obj: NonNull<bpf_object>) -> Self {
+        Self {
+            obj,
+            maps: Vec::new(),
+            progs: Vec::new(),
+            vars: Vec::new(),
+        }
+    }
+
+    /// Adds a map to the config
+    ///
+    /// Maps backing global data have to be referenced by their section
+    /// name, e.g., `.bss`.
+    pub fn map<T: AsRef<str>>(&mut self, name: T) -> &mut Self {
+        self.maps.push(MapSkelConfig {
+            name: name.as_ref().to_string(),
+            p: Box::new(ptr::null_mut()),
+            mmaped: None,
+            link: Box::new(ptr::null_mut()),
+        });
+
+        self
+    }
+
+    /// Adds a prog to the config
+    pub fn prog<T: AsRef<str>>(&mut self, name: T) -> &mut Self {
+        self.progs.push(ProgSkelConfig {
+            name: name.as_ref().to_string(),
+            p: Box::new(ptr::null_mut()),
+            link: Box::new(ptr::null_mut()),
+        });
+
+        self
+    }
+
+    /// Adds a global variable to the config
+    ///
+    /// `map` is the name of the map containing the variable, which has to
+    /// have been added via [`Self::map`] already.
+    pub fn var<T: AsRef<str>, M: AsRef<str>>(&mut self, name: T, map: M) -> Result<&mut Self> {
+        let map = map.as_ref();
+        let map_idx = self
+            .maps
+            .iter()
+            .position(|config| config.name == map)
+            .ok_or_else(|| {
+                Error::with_invalid_input(format!("map `{map}` was not added to subskeleton"))
+            })?;
+
+        self.vars.push(VarSkelConfig {
+            name: name.as_ref().to_string(),
+            map_idx,
+            addr: Box::new(ptr::null_mut()),
+        });
+
+        Ok(self)
+    }
+
+    fn build_vars(
+        vars: &mut [VarSkelConfig],
+        maps: &mut [MapSkelConfig],
+        s: &mut bpf_object_subskeleton,
+        string_pool: &mut Vec<CString>,
+    ) -> Option<Layout> {
+        if vars.is_empty() {
+            return None;
+        }
+
+        s.var_cnt = vars.len() as i32;
+        s.var_skel_sz = size_of::<bpf_var_skeleton>() as i32;
+
+        let layout = Layout::array::<bpf_var_skeleton>(vars.len())
+            .expect("Failed to allocate memory for vars skeleton");
+
+        unsafe {
+            s.vars = alloc_zeroed(layout) as *mut bpf_var_skeleton;
+            for (i, var) in vars.iter_mut().enumerate() {
+                let current_var = s.vars.add(i);
+
+                // See `build_map_skels` for `expect()` rationale
+                (*current_var).name = str_to_cstring_and_pool(&var.name, string_pool)
+                    .expect("Invalid unicode in var name");
+                (*current_var).map = &mut *maps[var.map_idx].p;
+                (*current_var).addr = &mut *var.addr;
+            }
+        }
+
+        Some(layout)
+    }
+
+    #[allow(missing_docs)]
+    pub fn build(mut self) -> Result<ObjectSubskeletonConfig> {
+        // Holds `CString`s alive so pointers to them stay valid
+        let mut string_pool = Vec::new();
+
+        let mut s = bpf_object_subskeleton {
+            sz: size_of::<bpf_object_subskeleton>() as c_ulong,
+            obj: self.obj.as_ptr(),
+            ..Default::default()
+        };
+
+        let maps_layout =
+            build_map_skels(&mut self.maps, &mut string_pool).map(|(skels, layout)| {
+                s.map_cnt = self.maps.len() as i32;
+                s.map_skel_sz = size_of::<bpf_map_skeleton>() as i32;
+                s.maps = skels;
+                layout
+            });
+        let progs_layout =
+            build_prog_skels(&mut self.progs, &mut string_pool).map(|(skels, layout)| {
+                s.prog_cnt = self.progs.len() as i32;
+                s.prog_skel_sz = size_of::<bpf_prog_skeleton>() as i32;
+                s.progs = skels;
+                layout
+            });
+        let vars_layout =
+            Self::build_vars(&mut self.vars, &mut self.maps, &mut s, &mut string_pool);
+
+        Ok(ObjectSubskeletonConfig {
+            inner: s,
+            _maps: self.maps,
+            _progs: self.progs,
+            vars: self.vars,
+            maps_layout,
+            progs_layout,
+            vars_layout,
+            _string_pool: string_pool,
+        })
+    }
+}
+
+/// Helper struct that wraps a `libbpf_sys::bpf_object_subskeleton`.
+///
+/// Similar to [`ObjectSkeletonConfig`], this struct keeps all pointers
+/// referenced by the subskeleton valid and frees allocated memory on drop.
+/// It does not own the object the subskeleton refers to.
+#[derive(Debug)]
+pub struct ObjectSubskeletonConfig {
+    inner: bpf_object_subskeleton,
+    /// Hold map and program configs alive so that libbpf can write to them
+    _maps: Vec<MapSkelConfig>,
+    _progs: Vec<ProgSkelConfig>,
+    vars: Vec<VarSkelConfig>,
+    /// Layout necessary to `dealloc` memory
+    maps_layout: Option<Layout>,
+    /// Same as above
+    progs_layout: Option<Layout>,
+    /// Same as above
+    vars_layout: Option<Layout>,
+    /// Hold strings alive so pointers to them stay valid
+    _string_pool: Vec<CString>,
+}
+
+impl ObjectSubskeletonConfig {
+    #[allow(missing_docs)]
+    pub fn get(&mut self) -> &mut bpf_object_subskeleton {
+        &mut self.inner
+    }
+
+    /// Returns the address of a global variable at the specified `index`.
+    ///
+    /// The index is determined by the order in which the variable was
+    /// passed to `ObjectSubskeletonConfigBuilder::var`. Index starts at 0.
+    /// The address is only populated once the subskeleton has been opened
+    /// via `bpf_object__open_subskeleton`.
+    ///
+    /// Warning: the returned pointer is only valid while the object the
+    /// subskeleton refers to is alive.
+    pub fn var_ptr(&self, index: usize) -> Result<*mut c_void> {
+        let var = self
+            .vars
+            .get(index)
+            .ok_or_invalid_data(|| format!("Invalid var index: {index}"))?;
+        let addr = *var.addr;
+        if addr.is_null() {
+            return Err(Error::with_invalid_data(format!(
+                "Var `{}` has no address",
+                var.name
+            )));
+        }
+        Ok(addr)
+    }
+}
+
+/// Create the error reported by a subskeleton accessor when the object
+/// lacks the library's map or program `name`.
+pub fn subskel_not_found(kind: &str, name: &str) -> Error {
+    Error::with_io_error(
+        io::ErrorKind::NotFound,
+        format!("{kind} `{name}` not found in object"),
+    )
+}
+
+impl Drop for ObjectSubskeletonConfig {
+    // Similar to `ObjectSkeletonConfig`, we do *not* run
+    // `libbpf_sys::bpf_object__destroy_subskeleton` here, as we did not
+    // allocate the `bpf_object_subskeleton` on the heap.
+    fn drop(&mut self) {
+        assert_eq!(self.maps_layout.is_none(), self.inner.maps.is_null());
+        assert_eq!(self.progs_layout.is_none(), self.inner.progs.is_null());
+        assert_eq!(self.vars_layout.is_none(), self.inner.vars.is_null());
+
+        if let Some(layout) = self.maps_layout {
+            unsafe {
+                dealloc(self.inner.maps as _, layout);
+            }
+        }
+
+        if let Some(layout) = self.progs_layout {
+            unsafe {
+                dealloc(self.inner.progs as _, layout);
+            }
+        }
+
+        if let Some(layout) = self.vars_layout {
+            unsafe {
+                dealloc(self.inner.vars as _, layout);
+            }
+        }
+    }
+}
+
 /// A trait for skeleton builder.
+///
+/// Generated skeleton builders can be created from an already configured
+/// [`ObjectBuilder`] via their `From<ObjectBuilder>` implementation, e.g.,
+/// to set a custom BTF path, kconfig values, or a pin root path.
 pub trait SkelBuilder<'a> {
     /// Define that when BPF object is opened, the returned type should implement the [`OpenSkel`] trait
     type Output: OpenSkel;
 
     /// Open eBPF object and return [`OpenSkel`]
+    ///
+    /// The object is opened with the options configured on the builder's
+    /// [`ObjectBuilder`].
     fn open(self) -> Result<Self::Output>;
 
     /// Open eBPF object with [`libbpf_sys::bpf_object_open_opts`] and return [`OpenSkel`]
@@ -402,6 +822,9 @@ pub trait OpenSkel {
 /// A trait for loaded skeleton.
 pub trait Skel {
     /// Attach BPF object.
+    ///
+    /// Programs that are not loaded or whose autoattach setting is disabled
+    /// are skipped.
     fn attach(&mut self) -> Result<()> {
         unimplemented!()
     }
@@ -0,0 +1,41 @@
+use std::os::fd::AsFd;
+use std::os::fd::BorrowedFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+
+use crate::util;
+use crate::Result;
+
+/// A guard keeping the collection of BPF program run time statistics
+/// enabled.
+///
+/// While statistics are enabled, the kernel accounts the time spent in and
+/// the number of invocations of every BPF program, as reported by
+/// [`ProgramInfo::run_time_ns`][crate::query::ProgramInfo::run_time_ns] and
+/// [`ProgramInfo::run_cnt`][crate::query::ProgramInfo::run_cnt]. Because
+/// this accounting incurs overhead, it is disabled by default. It is
+/// disabled again once the last guard on the system is dropped.
+#[derive(Debug)]
+pub struct RunTimeStats {
+    fd: OwnedFd,
+}
+
+impl RunTimeStats {
+    /// Enable the collection of run time statistics.
+    pub fn enable() -> Result<Self> {
+        let ret = unsafe { libbpf_sys::bpf_enable_stats(libbpf_sys::BPF_STATS_RUN_TIME) };
+        let fd = util::parse_ret_i32(ret)?;
+        Ok(Self {
+            // SAFETY: `bpf_enable_stats` returned a valid file descriptor that
+            //         nobody else owns.
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+}
+
+impl AsFd for RunTimeStats {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
@@ -1,9 +1,21 @@
 use std::io;
 use std::mem::size_of;
+use std::os::unix::io::AsFd as _;
 use std::os::unix::io::AsRawFd;
 use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::OwnedFd;
 
+use crate::iface::ifindex_from_name;
+use crate::netlink;
+use crate::netlink::nl_align;
+use crate::netlink::nl_attributes;
+use crate::netlink::read_u32;
+use crate::query::ProgInfoQueryOptions;
+use crate::query::ProgramInfo;
 use crate::Error;
+use crate::ErrorKind;
+use crate::IntoIfindex;
+use crate::Program;
 use crate::Result;
 
 /// See [`libbpf_sys::bpf_tc_attach_point`].
@@ -33,6 +45,174 @@ pub const TC_H_MAJ_MASK: u32 = 0xFFFF0000;
 #[allow(missing_docs)]
 pub const TC_H_MIN_MASK: u32 = 0x0000FFFF;
 
+/// Information about a TC-BPF filter attached at a [`TcHook`], as retrieved via
+/// [`TcHook::query()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TcFilter {
+    /// The identifier of the filter's program.
+    pub prog_id: u32,
+    /// The handle of the filter.
+    pub handle: u32,
+    /// The priority of the filter.
+    pub priority: u32,
+}
+
+impl TcFilter {
+    /// Open a file descriptor referring to the filter's program.
+    ///
+    /// Make sure the process has the required set of `CAP_*` permissions (or runs as root).
+    pub fn prog_fd(&self) -> Result<OwnedFd> {
+        Program::get_fd_by_id(self.prog_id)
+    }
+}
+
+/// Information about a TC-BPF filter, as retrieved via [`TcHook::list_filters()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TcFilterInfo {
+    /// The handle of the filter.
+    pub handle: u32,
+    /// The priority of the filter.
+    pub priority: u32,
+    /// The protocol the filter applies to (e.g., `ETH_P_ALL`).
+    pub protocol: u16,
+    /// The index of the chain the filter is part of.
+    pub chain: u32,
+    /// The identifier of the filter's program.
+    pub prog_id: u32,
+    /// The name of the filter's program.
+    ///
+    /// The name is looked up via the program's identifier, which requires the process to have the
+    /// required set of `CAP_*` permissions (or to run as root). It is `None` if the lookup failed.
+    pub name: Option<String>,
+    /// The name of the filter, if any.
+    ///
+    /// This name is chosen by whoever attached the filter. Filters attached via libbpf (and,
+    /// hence, [`TcHook::attach()`]) are named `<program name>:[<program id>]`.
+    pub filter_name: Option<String>,
+}
+
+/// The `tcmsg` header of TC related netlink messages.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct TcMsg {
+    family: u8,
+    _pad1: u8,
+    _pad2: u16,
+    ifindex: i32,
+    handle: u32,
+    parent: u32,
+    info: u32,
+}
+
+/// A netlink request for dumping TC filters.
+#[repr(C)]
+struct TcDumpRequest {
+    header: libc::nlmsghdr,
+    msg: TcMsg,
+}
+
+/// Netlink attribute type of a filter's chain index (`TCA_CHAIN`).
+const TCA_CHAIN: u16 = 11;
+/// Netlink attribute type of a BPF filter's program file descriptor (`TCA_BPF_FD`).
+const TCA_BPF_FD: u16 = 6;
+/// Netlink attribute type of a BPF filter's name (`TCA_BPF_NAME`).
+const TCA_BPF_NAME: u16 = 7;
+/// Netlink attribute type of a BPF filter's flags (`TCA_BPF_FLAGS`).
+const TCA_BPF_FLAGS: u16 = 8;
+/// Netlink attribute type of a BPF filter's program ID (`TCA_BPF_ID`).
+const TCA_BPF_ID: u16 = 11;
+/// Flag making a BPF filter's program decide on the action (`TCA_BPF_FLAG_ACT_DIRECT`).
+const TCA_BPF_FLAG_ACT_DIRECT: u32 = 1 << 0;
+/// The protocol matching all packets (`ETH_P_ALL`), which filters apply to by default.
+pub const ETH_P_ALL: u16 = libc::ETH_P_ALL as u16;
+/// Parse the payload of an `RTM_NEWTFILTER` message, returning `None` for anything but a BPF
+/// filter.
+fn parse_filter(payload: &[u8]) -> Option<TcFilterInfo> {
+    let msg = payload.get(..size_of::<TcMsg>())?;
+    let handle = read_u32(&msg[8..])?;
+    let info = read_u32(&msg[16..])?;
+
+    let mut is_bpf = false;
+    let mut chain = 0;
+    let mut prog_id = None;
+    let mut filter_name = None;
+    for (ty, data) in nl_attributes(payload.get(nl_align(size_of::<TcMsg>())..)?) {
+        match ty {
+            libc::TCA_KIND => is_bpf = data.split(|b| *b == 0).next() == Some(b"bpf"),
+            TCA_CHAIN => chain = read_u32(data)?,
+            libc::TCA_OPTIONS => {
+                for (ty, data) in nl_attributes(data) {
+                    match ty {
+                        TCA_BPF_ID => prog_id = read_u32(data),
+                        TCA_BPF_NAME => {
+                            let data = data.split(|b| *b == 0).next().unwrap_or(data);
+                            filter_name = Some(String::from_utf8_lossy(data).into_owned())
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if !is_bpf {
+        return None;
+    }
+
+    Some(TcFilterInfo {
+        handle,
+        priority: (info & TC_H_MAJ_MASK) >> 16,
+        protocol: u16::from_be((info & TC_H_MIN_MASK) as u16),
+        chain,
+        // The kernel reports a filter without program once per priority, which we skip.
+        prog_id: prog_id?,
+        name: None,
+        filter_name,
+    })
+}
+
+/// Look up the name of the program with ID `prog_id`.
+fn prog_name(prog_id: u32) -> Option<String> {
+    let fd = Program::get_fd_by_id(prog_id).ok()?;
+    let info = ProgramInfo::load_from_fd(fd.as_fd(), &ProgInfoQueryOptions::default()).ok()?;
+    Some(info.name.to_string_lossy().into_owned())
+}
+
+/// Dump the TC filters with parent `parent` on the interface with index `ifindex` via
+/// netlink.
+fn dump_filters(ifindex: i32, parent: u32) -> Result<Vec<TcFilterInfo>> {
+    let req = TcDumpRequest {
+        header: libc::nlmsghdr {
+            nlmsg_len: size_of::<TcDumpRequest>() as u32,
+            nlmsg_type: libc::RTM_GETTFILTER,
+            nlmsg_flags: (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16,
+            nlmsg_seq: 1,
+            nlmsg_pid: 0,
+        },
+        msg: TcMsg {
+            family: libc::AF_UNSPEC as u8,
+            ifindex,
+            parent,
+            ..Default::default()
+        },
+    };
+
+    let mut filters = Vec::new();
+    let () = netlink::transact(&req, |msg_type, payload| {
+        if msg_type == libc::RTM_NEWTFILTER {
+            if let Some(filter) = parse_filter(payload) {
+                let () = filters.push(filter);
+            }
+        }
+    })?;
+
+    for filter in &mut filters {
+        filter.name = prog_name(filter.prog_id);
+    }
+    Ok(filters)
+}
+
 /// Represents a location where a TC-BPF filter can be attached.
 ///
 /// The BPF TC subsystem has different control paths from other BPF programs.
@@ -51,6 +231,8 @@ pub const TC_H_MIN_MASK: u32 = 0x0000FFFF;
 pub struct TcHook {
     hook: libbpf_sys::bpf_tc_hook,
     opts: libbpf_sys::bpf_tc_opts,
+    chain: u32,
+    protocol: u16,
 }
 
 impl TcHook {
@@ -60,6 +242,8 @@ impl TcHook {
         let mut tc_hook = TcHook {
             hook: libbpf_sys::bpf_tc_hook::default(),
             opts: libbpf_sys::bpf_tc_opts::default(),
+            chain: 0,
+            protocol: ETH_P_ALL,
         };
 
         tc_hook.hook.sz = size_of::<libbpf_sys::bpf_tc_hook>() as libbpf_sys::size_t;
@@ -75,6 +259,8 @@ impl TcHook {
     /// [`Self::create()`], this function will still succeed.
     ///
     /// Will always fail on a `TC_CUSTOM` hook
+    ///
+    /// Use [`TcQdisc`] to keep track of whether the qdisc got created.
     pub fn create(&mut self) -> Result<Self> {
         let err = unsafe { libbpf_sys::bpf_tc_hook_create(&mut self.hook as *mut _) };
         if err != 0 {
@@ -98,6 +284,20 @@ impl TcHook {
         self
     }
 
+    /// Set the interface to attach to by name (e.g., `eth0`)
+    ///
+    /// Fails with an error of kind [`ErrorKind::NotFound`][crate::ErrorKind::NotFound] if the
+    /// interface does not exist
+    pub fn ifname(&mut self, name: &str) -> Result<&mut Self> {
+        let idx = ifindex_from_name(name)?;
+        Ok(self.ifindex(idx))
+    }
+
+    /// Get the index of the interface to attach to
+    pub fn get_ifindex(&self) -> i32 {
+        self.hook.ifindex
+    }
+
     /// Set what type of TC point to attach onto
     ///
     /// `TC_EGRESS`, `TC_INGRESS`, or `TC_CUSTOM`
@@ -130,6 +330,10 @@ impl TcHook {
     ///
     /// If replace is not true upon attach, and a hook already exists
     /// an EEXIST error will be returned from [`Self::attach()`]
+    ///
+    /// If replace is true, the filter with the hook's handle and priority is replaced if it
+    /// exists and created otherwise. As the filter to replace is identified by them, both the
+    /// handle and the priority have to be set in this case.
     pub fn replace(&mut self, replace: bool) -> &mut Self {
         if replace {
             self.opts.flags = BPF_TC_F_REPLACE;
@@ -148,6 +352,8 @@ impl TcHook {
 
     /// Get the handle of a hook.
     /// Only has meaning after hook is attached
+    ///
+    /// If no handle was set, this is the handle the kernel assigned upon attach
     pub fn get_handle(&self) -> u32 {
         self.opts.handle
     }
@@ -161,12 +367,182 @@ impl TcHook {
 
     /// Get the priority of a hook
     /// Only has meaning after hook is attached
+    ///
+    /// If no priority was set, this is the priority the kernel assigned upon attach
     pub fn get_priority(&self) -> u32 {
         self.opts.priority
     }
 
-    /// Query a hook to inspect the program identifier (prog_id)
-    pub fn query(&mut self) -> Result<u32> {
+    /// Set the index of the filter chain the hook's filter is part of
+    ///
+    /// Defaults to chain 0. Chains other than 0 get created by the kernel as necessary.
+    pub fn chain(&mut self, chain: u32) -> &mut Self {
+        self.chain = chain;
+        self
+    }
+
+    /// Get the index of the filter chain the hook's filter is part of
+    pub fn get_chain(&self) -> u32 {
+        self.chain
+    }
+
+    /// Set the protocol the hook's filter applies to, e.g., `ETH_P_IP`, in host byte order
+    ///
+    /// Defaults to [`ETH_P_ALL`], which matches all packets.
+    pub fn protocol(&mut self, protocol: u16) -> &mut Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Get the protocol the hook's filter applies to
+    pub fn get_protocol(&self) -> u16 {
+        self.protocol
+    }
+
+    /// Check whether the hook's filter can be managed via libbpf, which only supports
+    /// filters in chain 0 that apply to all protocols.
+    fn is_libbpf_filter(&self) -> bool {
+        self.chain == 0 && self.protocol == ETH_P_ALL
+    }
+
+    /// Create the `tcmsg` header identifying the hook's filter.
+    fn filter_msg(&self) -> Result<TcMsg> {
+        let parent = match self.hook.attach_point {
+            TC_INGRESS | TC_EGRESS if self.hook.parent != 0 => {
+                return Err(Error::with_invalid_input(
+                    "a parent can only be set on a TC_CUSTOM hook",
+                ))
+            }
+            TC_INGRESS => (TC_H_CLSACT & TC_H_MAJ_MASK) | TC_H_MIN_INGRESS,
+            TC_EGRESS => (TC_H_CLSACT & TC_H_MAJ_MASK) | TC_H_MIN_EGRESS,
+            TC_CUSTOM => self.hook.parent,
+            _ => {
+                return Err(Error::with_invalid_input(
+                    "filters can only be managed for TC_INGRESS, TC_EGRESS, or TC_CUSTOM hooks",
+                ))
+            }
+        };
+
+        Ok(TcMsg {
+            family: libc::AF_UNSPEC as u8,
+            ifindex: self.hook.ifindex,
+            handle: self.opts.handle,
+            parent,
+            info: (self.opts.priority << 16) | u32::from(self.protocol.to_be()),
+            ..Default::default()
+        })
+    }
+
+    /// Send a netlink request of type `msg_type` for the hook's filter, with `attrs` appending
+    /// attributes beyond the filter's kind and chain, and return the filter reported back, if
+    /// any.
+    fn filter_request<F>(&self, msg_type: u16, flags: i32, attrs: F) -> Result<Option<TcFilterInfo>>
+    where
+        F: FnOnce(&mut Vec<u8>),
+    {
+        let msg = self.filter_msg()?;
+        let flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK | flags) as u16;
+        let req = netlink::message(msg_type, flags, &msg, |buf| {
+            let () = netlink::push_attr(buf, libc::TCA_KIND, b"bpf\0");
+            let () = netlink::push_attr(buf, TCA_CHAIN, &self.chain.to_ne_bytes());
+            let () = attrs(buf);
+        });
+
+        let mut filter = None;
+        let () = netlink::transact_raw(&req, |msg_type, payload| {
+            if msg_type == libc::RTM_NEWTFILTER {
+                filter = filter.take().or_else(|| parse_filter(payload));
+            }
+        })?;
+        Ok(filter)
+    }
+
+    /// Attach the hook's filter via netlink, for filters libbpf does not support.
+    fn attach_netlink(&mut self) -> Result<()> {
+        let prog_fd = unsafe { BorrowedFd::borrow_raw(self.opts.prog_fd) };
+        let info = ProgramInfo::load_from_fd(prog_fd, &ProgInfoQueryOptions::default())?;
+        // Name the filter the way libbpf does.
+        let name = format!("{}:[{}]\0", info.name.to_string_lossy(), info.id);
+
+        let flags = if self.opts.flags & BPF_TC_F_REPLACE != 0 {
+            libc::NLM_F_REPLACE
+        } else {
+            libc::NLM_F_EXCL
+        };
+        let filter = self.filter_request(
+            libc::RTM_NEWTFILTER,
+            libc::NLM_F_CREATE | libc::NLM_F_ECHO | flags,
+            |buf| {
+                netlink::push_nested(buf, libc::TCA_OPTIONS, |buf| {
+                    let fd = self.opts.prog_fd as u32;
+                    let () = netlink::push_attr(buf, TCA_BPF_FD, &fd.to_ne_bytes());
+                    let () = netlink::push_attr(buf, TCA_BPF_NAME, name.as_bytes());
+                    let () = netlink::push_attr(
+                        buf,
+                        TCA_BPF_FLAGS,
+                        &TCA_BPF_FLAG_ACT_DIRECT.to_ne_bytes(),
+                    );
+                })
+            },
+        )?;
+        let filter = filter.ok_or_else(|| {
+            Error::with_invalid_data("kernel did not report back the attached filter")
+        })?;
+
+        self.opts.handle = filter.handle;
+        self.opts.priority = filter.priority;
+        self.opts.prog_id = filter.prog_id;
+        Ok(())
+    }
+
+    /// Query the filter currently attached at the hook
+    ///
+    /// The filter is looked up by the hook's handle and priority, which both have to be set.
+    /// If no filter is attached, an error of kind
+    /// [`ErrorKind::NotFound`][crate::ErrorKind::NotFound] is returned.
+    ///
+    /// This allows for detecting filters left behind by a previous run (which would cause
+    /// [`Self::attach()`] to fail with EEXIST) and deciding whether to reuse or replace them.
+    pub fn query(&mut self) -> Result<TcFilter> {
+        if self.opts.handle == 0 || self.opts.priority == 0 {
+            return Err(Error::with_invalid_input(
+                "querying a filter requires both handle and priority to be set",
+            ));
+        }
+
+        match self.query_filter() {
+            // The kernel removes a chain along with its last filter and
+            // rejects looking up filters in chains that do not exist.
+            Err(err) if err.kind() == ErrorKind::InvalidInput => {
+                let msg = self.filter_msg()?;
+                let filters = dump_filters(msg.ifindex, msg.parent)?;
+                if filters.iter().any(|filter| filter.chain == self.chain) {
+                    Err(err)
+                } else {
+                    Err(Error::with_io_error(
+                        io::ErrorKind::NotFound,
+                        "no BPF filter found at hook",
+                    ))
+                }
+            }
+            result => result,
+        }
+    }
+
+    fn query_filter(&self) -> Result<TcFilter> {
+        if !self.is_libbpf_filter() {
+            let filter = self
+                .filter_request(libc::RTM_GETTFILTER, 0, |_| ())?
+                .ok_or_else(|| {
+                    Error::with_io_error(io::ErrorKind::NotFound, "no BPF filter found at hook")
+                })?;
+            return Ok(TcFilter {
+                prog_id: filter.prog_id,
+                handle: filter.handle,
+                priority: filter.priority,
+            });
+        }
+
         let mut opts = self.opts;
         opts.prog_id = 0;
         opts.prog_fd = 0;
@@ -174,12 +550,46 @@ impl TcHook {
 
         let err = unsafe { libbpf_sys::bpf_tc_query(&self.hook as *const _, &mut opts as *mut _) };
         if err != 0 {
-            Err(Error::from(io::Error::last_os_error()))
+            Err(Error::from_raw_os_error(-err))
         } else {
-            Ok(opts.prog_id)
+            Ok(TcFilter {
+                prog_id: opts.prog_id,
+                handle: opts.handle,
+                priority: opts.priority,
+            })
         }
     }
 
+    /// List all BPF filters attached at `attach_point` of the interface `ifindex`, provided by
+    /// index or by name
+    ///
+    /// In contrast to [`Self::query()`], this reports every BPF filter present, not just the one
+    /// with a given handle and priority, allowing for an inventory of filters attached by other
+    /// users of TC. Filters are reported in the order the kernel provides them, which is by
+    /// priority.
+    ///
+    /// `attach_point` has to be either `TC_INGRESS` or `TC_EGRESS`. If the clsact qdisc does not
+    /// exist, no filters are reported.
+    pub fn list_filters<I: IntoIfindex>(
+        ifindex: I,
+        attach_point: TcAttachPoint,
+    ) -> Result<Vec<TcFilterInfo>> {
+        let ifindex = ifindex.into_ifindex()?;
+        let min = match attach_point {
+            TC_INGRESS => TC_H_MIN_INGRESS,
+            TC_EGRESS => TC_H_MIN_EGRESS,
+            _ => {
+                return Err(Error::with_invalid_input(
+                    "filters can only be listed for TC_INGRESS or TC_EGRESS",
+                ));
+            }
+        };
+        dump_filters(
+            ifindex,
+            (TC_H_CLSACT & TC_H_MAJ_MASK) | (min & TC_H_MIN_MASK),
+        )
+    }
+
     /// Attach a filter to the TcHook so that the program starts processing
     ///
     /// Once the hook is processing, changing the values will have no effect unless the hook is
@@ -192,7 +602,20 @@ impl TcHook {
     /// NOTE: Once a [`TcHook`] is attached, it, and the maps it uses, will outlive the userspace
     /// application that spawned them Make sure to detach if this is not desired
     pub fn attach(&mut self) -> Result<Self> {
+        if self.opts.flags & BPF_TC_F_REPLACE != 0
+            && (self.opts.handle == 0 || self.opts.priority == 0)
+        {
+            return Err(Error::with_invalid_input(
+                "replacing a filter requires both handle and priority to be set",
+            ));
+        }
+
         self.opts.prog_id = 0;
+        if !self.is_libbpf_filter() {
+            let () = self.attach_netlink()?;
+            return Ok(*self);
+        }
+
         let err =
             unsafe { libbpf_sys::bpf_tc_attach(&self.hook as *const _, &mut self.opts as *mut _) };
         if err != 0 {
@@ -202,8 +625,26 @@ impl TcHook {
         }
     }
 
+    /// Attach a filter to the TcHook, as [`Self::attach()`] does, returning a guard detaching
+    /// it again when dropped
+    pub fn attach_guarded(&mut self) -> Result<TcHookGuard> {
+        let hook = self.attach()?;
+        Ok(TcHookGuard::new(vec![hook], None))
+    }
+
     /// Detach a filter from a [`TcHook`]
     pub fn detach(&mut self) -> Result<()> {
+        if !self.is_libbpf_filter() {
+            if self.opts.handle == 0 || self.opts.priority == 0 {
+                return Err(Error::with_invalid_input(
+                    "detaching a filter requires both handle and priority to be set",
+                ));
+            }
+            let _filter = self.filter_request(libc::RTM_DELTFILTER, 0, |_| ())?;
+            self.opts.prog_id = 0;
+            return Ok(());
+        }
+
         let mut opts = self.opts;
         opts.prog_id = 0;
         opts.prog_fd = 0;
@@ -230,7 +671,7 @@ impl TcHook {
     /// Will error with EOPNOTSUPP if attach_point is `TC_CUSTOM`
     ///
     /// It is good practice to query before destroying as the tc qdisc may be used by multiple
-    /// programs
+    /// programs. [`TcQdisc::release()`] only removes the qdisc if it got created by us.
     pub fn destroy(&mut self) -> Result<()> {
         let err = unsafe { libbpf_sys::bpf_tc_hook_destroy(&mut self.hook as *mut _) };
         if err != 0 {
@@ -241,6 +682,273 @@ impl TcHook {
     }
 }
 
+/// The kind of a [`TcQdisc`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TcQdiscKind {
+    /// The `clsact` qdisc, which filters attached at `TC_INGRESS` and `TC_EGRESS` hooks belong
+    /// to.
+    Clsact,
+    /// The legacy `ingress` qdisc, which only supports filters attached at `TC_INGRESS` hooks.
+    Ingress,
+}
+
+impl TcQdiscKind {
+    /// The kind's name as used by the kernel, NUL terminated.
+    fn name(&self) -> &'static [u8] {
+        match self {
+            Self::Clsact => b"clsact\0",
+            Self::Ingress => b"ingress\0",
+        }
+    }
+
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name.split(|b| *b == 0).next()? {
+            b"clsact" => Some(Self::Clsact),
+            b"ingress" => Some(Self::Ingress),
+            _ => None,
+        }
+    }
+}
+
+/// Create the `tcmsg` header addressing the `clsact` or `ingress` qdisc of the interface with
+/// index `ifindex`, which share the same handle and parent.
+fn qdisc_msg(ifindex: i32) -> TcMsg {
+    TcMsg {
+        family: libc::AF_UNSPEC as u8,
+        ifindex,
+        handle: TC_H_CLSACT & TC_H_MAJ_MASK,
+        parent: TC_H_CLSACT,
+        ..Default::default()
+    }
+}
+
+/// Parse the kind of the qdisc described by an `RTM_NEWQDISC` message, if it is the `clsact` or
+/// `ingress` qdisc of the interface with index `ifindex`.
+fn parse_qdisc(payload: &[u8], ifindex: i32) -> Option<TcQdiscKind> {
+    let msg = payload.get(..size_of::<TcMsg>())?;
+    if read_u32(&msg[4..])? as i32 != ifindex || read_u32(&msg[12..])? != TC_H_CLSACT {
+        return None;
+    }
+
+    nl_attributes(payload.get(nl_align(size_of::<TcMsg>())..)?)
+        .find(|(ty, _data)| *ty == libc::TCA_KIND)
+        .and_then(|(_ty, data)| TcQdiscKind::from_name(data))
+}
+
+/// Retrieve the kind of the `clsact` or `ingress` qdisc on the interface with index `ifindex`, if
+/// any.
+fn find_qdisc(ifindex: i32) -> Result<Option<TcQdiscKind>> {
+    let req = TcDumpRequest {
+        header: libc::nlmsghdr {
+            nlmsg_len: size_of::<TcDumpRequest>() as u32,
+            nlmsg_type: libc::RTM_GETQDISC,
+            nlmsg_flags: (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16,
+            nlmsg_seq: 1,
+            nlmsg_pid: 0,
+        },
+        msg: TcMsg {
+            family: libc::AF_UNSPEC as u8,
+            ifindex,
+            ..Default::default()
+        },
+    };
+
+    let mut kind = None;
+    let () = netlink::transact(&req, |msg_type, payload| {
+        if msg_type == libc::RTM_NEWQDISC {
+            kind = kind.or_else(|| parse_qdisc(payload, ifindex));
+        }
+    })?;
+    Ok(kind)
+}
+
+/// Send a request of type `msg_type` for the qdisc of kind `kind` on the interface with index
+/// `ifindex`.
+fn qdisc_request(msg_type: u16, flags: i32, ifindex: i32, kind: TcQdiscKind) -> Result<()> {
+    let flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK | flags) as u16;
+    let req = netlink::message(msg_type, flags, &qdisc_msg(ifindex), |buf| {
+        let () = netlink::push_attr(buf, libc::TCA_KIND, kind.name());
+    });
+    netlink::transact_raw(&req, |_msg_type, _payload| ())
+}
+
+/// The qdisc of an interface which TC-BPF filters attached at `TC_INGRESS` (and, for the `clsact`
+/// qdisc, `TC_EGRESS`) hooks belong to.
+///
+/// In contrast to [`TcHook::create()`] and [`TcHook::destroy()`], a [`TcQdisc`] keeps track of
+/// whether it created the qdisc or found it already in place. That allows for removing the qdisc
+/// only if it was created by us via [`Self::release()`], without tearing down a qdisc (and all
+/// filters) that another tool installed and may still be using.
+///
+/// The qdisc is not removed when the object is dropped.
+#[derive(Debug)]
+pub struct TcQdisc {
+    ifindex: i32,
+    kind: TcQdiscKind,
+    created: bool,
+}
+
+impl TcQdisc {
+    /// Make sure the `clsact` qdisc exists on the interface `ifindex`, provided by index or by
+    /// name, creating it if it does not.
+    ///
+    /// An `ingress` qdisc already in place can't be used for filters attached at `TC_EGRESS`
+    /// hooks and results in an error of kind [`ErrorKind::AlreadyExists`].
+    pub fn clsact<I: IntoIfindex>(ifindex: I) -> Result<Self> {
+        let ifindex = ifindex.into_ifindex()?;
+        let qdisc = Self::create(ifindex, TcQdiscKind::Clsact)?;
+        if qdisc.kind != TcQdiscKind::Clsact {
+            return Err(Error::with_io_error(
+                io::ErrorKind::AlreadyExists,
+                format!("interface {ifindex} already has an ingress qdisc"),
+            ));
+        }
+        Ok(qdisc)
+    }
+
+    /// Make sure a qdisc supporting filters attached at `TC_INGRESS` hooks exists on the
+    /// interface `ifindex`, provided by index or by name, creating an `ingress` qdisc if there
+    /// is none.
+    ///
+    /// A `clsact` qdisc already in place is used as is.
+    pub fn ingress<I: IntoIfindex>(ifindex: I) -> Result<Self> {
+        let ifindex = ifindex.into_ifindex()?;
+        Self::create(ifindex, TcQdiscKind::Ingress)
+    }
+
+    /// Create the qdisc of kind `kind` on the interface with index `ifindex`, unless a `clsact`
+    /// or `ingress` qdisc exists already.
+    fn create(ifindex: i32, kind: TcQdiscKind) -> Result<Self> {
+        let result = qdisc_request(
+            libc::RTM_NEWQDISC,
+            libc::NLM_F_CREATE | libc::NLM_F_EXCL,
+            ifindex,
+            kind,
+        );
+        match result {
+            Ok(()) => Ok(Self {
+                ifindex,
+                kind,
+                created: true,
+            }),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                let kind = find_qdisc(ifindex)?.ok_or_else(|| {
+                    Error::with_invalid_data(format!(
+                        "interface {ifindex} has a qdisc other than clsact or ingress in place"
+                    ))
+                })?;
+                Ok(Self {
+                    ifindex,
+                    kind,
+                    created: false,
+                })
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Retrieve the index of the interface the qdisc belongs to.
+    pub fn ifindex(&self) -> i32 {
+        self.ifindex
+    }
+
+    /// Retrieve the kind of the qdisc.
+    pub fn kind(&self) -> TcQdiscKind {
+        self.kind
+    }
+
+    /// Check whether the qdisc was created by us, as opposed to having existed already.
+    pub fn created(&self) -> bool {
+        self.created
+    }
+
+    /// Remove the qdisc if it was created by us, detaching all filters attached to it.
+    ///
+    /// A qdisc that existed already is left in place. Returns whether the qdisc was removed.
+    pub fn release(self) -> Result<bool> {
+        if !self.created {
+            return Ok(false);
+        }
+        let () = self.destroy()?;
+        Ok(true)
+    }
+
+    /// Remove the qdisc, detaching all filters attached to it, regardless of who created it.
+    pub fn destroy(self) -> Result<()> {
+        qdisc_request(libc::RTM_DELQDISC, 0, self.ifindex, self.kind)
+    }
+}
+
+/// A guard detaching a set of attached [`TcHook`]s when dropped.
+///
+/// Optionally, the guard also owns a [`TcQdisc`], which is
+/// [released][TcQdisc::release()] (i.e., removed if it was created by us) after detaching.
+///
+/// Errors during teardown on drop are ignored. Use [`Self::detach()`] to learn about them.
+#[derive(Debug)]
+pub struct TcHookGuard {
+    hooks: Vec<TcHook>,
+    qdisc: Option<TcQdisc>,
+    disconnected: bool,
+}
+
+impl TcHookGuard {
+    fn new(hooks: Vec<TcHook>, qdisc: Option<TcQdisc>) -> Self {
+        Self {
+            hooks,
+            qdisc,
+            disconnected: false,
+        }
+    }
+
+    /// Make the guard release `qdisc` after detaching its hooks.
+    pub fn with_qdisc(mut self, qdisc: TcQdisc) -> Self {
+        self.qdisc = Some(qdisc);
+        self
+    }
+
+    /// Retrieve the attached hooks.
+    pub fn hooks(&self) -> &[TcHook] {
+        &self.hooks
+    }
+
+    /// Disconnect the guard from its hooks, which will then stay attached (and the qdisc in
+    /// place) when the guard is dropped.
+    pub fn disconnect(&mut self) {
+        self.disconnected = true;
+    }
+
+    /// Detach all hooks and release the qdisc, if any, reporting the first error encountered.
+    pub fn detach(mut self) -> Result<()> {
+        let result = self.teardown();
+        self.disconnected = true;
+        result
+    }
+
+    fn teardown(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for hook in &mut self.hooks {
+            if let Err(err) = hook.detach() {
+                result = result.and(Err(err));
+            }
+        }
+        if let Some(qdisc) = self.qdisc.take() {
+            if let Err(err) = qdisc.release() {
+                result = result.and(Err(err));
+            }
+        }
+        result
+    }
+}
+
+impl Drop for TcHookGuard {
+    fn drop(&mut self) {
+        if !self.disconnected {
+            let _result = self.teardown();
+        }
+    }
+}
+
 /// Builds [`TcHook`] instances.
 ///
 /// [`TcHookBuilder`] is a way to ergonomically create multiple `TcHook`s,
@@ -257,6 +965,8 @@ pub struct TcHookBuilder<'fd> {
     replace: bool,
     handle: u32,
     priority: u32,
+    chain: u32,
+    protocol: u16,
 }
 
 impl<'fd> TcHookBuilder<'fd> {
@@ -271,6 +981,8 @@ impl<'fd> TcHookBuilder<'fd> {
             replace: false,
             handle: 0,
             priority: 0,
+            chain: 0,
+            protocol: ETH_P_ALL,
         }
     }
 
@@ -280,6 +992,16 @@ impl<'fd> TcHookBuilder<'fd> {
         self
     }
 
+    /// Set the initial interface to attach the hook on by name (e.g., `eth0`)
+    ///
+    /// Fails with an error of kind [`ErrorKind::NotFound`][crate::ErrorKind::NotFound] if the
+    /// interface does not exist. The resolved index is available via [`TcHook::get_ifindex()`]
+    /// on created hooks.
+    pub fn ifname(&mut self, name: &str) -> Result<&mut Self> {
+        let ifindex = ifindex_from_name(name)?;
+        Ok(self.ifindex(ifindex))
+    }
+
     /// Set the initial parent of a hook
     pub fn parent(&mut self, maj: u32, min: u32) -> &mut Self {
         self.parent_maj = maj;
@@ -288,23 +1010,47 @@ impl<'fd> TcHookBuilder<'fd> {
     }
 
     /// Set whether created hooks should replace existing hooks
+    ///
+    /// See [`TcHook::replace()`] for details.
     pub fn replace(&mut self, replace: bool) -> &mut Self {
         self.replace = replace;
         self
     }
 
     /// Set the initial handle for a hook
+    ///
+    /// If unset, the kernel will assign a handle upon attach, which can be retrieved via
+    /// [`TcHook::get_handle()`]
     pub fn handle(&mut self, handle: u32) -> &mut Self {
         self.handle = handle;
         self
     }
 
     /// Set the initial priority for a hook
+    ///
+    /// If unset, the kernel will assign a priority upon attach, which can be retrieved via
+    /// [`TcHook::get_priority()`]
     pub fn priority(&mut self, priority: u32) -> &mut Self {
         self.priority = priority;
         self
     }
 
+    /// Set the initial filter chain index for a hook
+    ///
+    /// See [`TcHook::chain()`] for details.
+    pub fn chain(&mut self, chain: u32) -> &mut Self {
+        self.chain = chain;
+        self
+    }
+
+    /// Set the initial protocol for a hook
+    ///
+    /// See [`TcHook::protocol()`] for details.
+    pub fn protocol(&mut self, protocol: u16) -> &mut Self {
+        self.protocol = protocol;
+        self
+    }
+
     /// Create a [`TcHook`] given the values previously set
     ///
     /// Once a hook is created, the values can still be changed on the `TcHook`
@@ -316,8 +1062,103 @@ impl<'fd> TcHookBuilder<'fd> {
             .priority(self.priority)
             .parent(self.parent_maj, self.parent_min)
             .replace(self.replace)
+            .chain(self.chain)
+            .protocol(self.protocol)
             .attach_point(attach_point);
 
         hook
     }
+
+    /// Attach the program to both `TC_INGRESS` and `TC_EGRESS`, creating the clsact qdisc if
+    /// necessary
+    ///
+    /// The returned guard detaches both filters when dropped and removes the qdisc, if it got
+    /// created by this call. If attaching fails, already attached filters are detached again.
+    pub fn attach_ingress_egress(&self) -> Result<TcHookGuard> {
+        let qdisc = TcQdisc::clsact(self.ifindex)?;
+        let mut guard = TcHookGuard::new(Vec::with_capacity(2), Some(qdisc));
+        for attach_point in [TC_INGRESS, TC_EGRESS] {
+            let hook = self.hook(attach_point).attach()?;
+            let () = guard.hooks.push(hook);
+        }
+        Ok(guard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::netlink::push_attr;
+
+    /// Create the payload of an `RTM_NEWTFILTER` message.
+    fn filter_msg(kind: &[u8], options: Option<&[u8]>) -> Vec<u8> {
+        let mut buf = Vec::new();
+        // family, padding, and ifindex
+        buf.extend_from_slice(&[0; 8]);
+        buf.extend_from_slice(&0x10002u32.to_ne_bytes());
+        buf.extend_from_slice(&0xfffffff2u32.to_ne_bytes());
+        // priority 3 and protocol ETH_P_ALL (in network byte order)
+        buf.extend_from_slice(&((3u32 << 16) | u32::from(0x0003u16.to_be())).to_ne_bytes());
+        push_attr(&mut buf, libc::TCA_KIND, kind);
+        push_attr(&mut buf, TCA_CHAIN, &2u32.to_ne_bytes());
+        if let Some(options) = options {
+            push_attr(&mut buf, libc::TCA_OPTIONS | 1 << 15, options);
+        }
+        buf
+    }
+
+    /// Check that we can parse BPF filters from netlink messages.
+    #[test]
+    fn filter_parsing() {
+        let mut options = Vec::new();
+        push_attr(&mut options, TCA_BPF_NAME, b"handle_tc:[42]\0");
+        push_attr(&mut options, TCA_BPF_ID, &42u32.to_ne_bytes());
+
+        let filter = parse_filter(&filter_msg(b"bpf\0", Some(&options))).unwrap();
+        assert_eq!(
+            filter,
+            TcFilterInfo {
+                handle: 0x10002,
+                priority: 3,
+                protocol: 0x0003,
+                chain: 2,
+                prog_id: 42,
+                name: None,
+                filter_name: Some("handle_tc:[42]".to_string()),
+            }
+        );
+
+        // Filters of other kinds are skipped.
+        assert_eq!(parse_filter(&filter_msg(b"u32\0", Some(&options))), None);
+        // As are the per-priority messages without options.
+        assert_eq!(parse_filter(&filter_msg(b"bpf\0", None)), None);
+        assert_eq!(parse_filter(&[0; 4]), None);
+    }
+
+    /// Check that we can parse the kind of qdiscs from netlink messages.
+    #[test]
+    fn qdisc_parsing() {
+        let qdisc_msg = |ifindex: i32, parent: u32, kind: &[u8]| {
+            let mut buf = Vec::new();
+            // family and padding
+            buf.extend_from_slice(&[0; 4]);
+            buf.extend_from_slice(&ifindex.to_ne_bytes());
+            buf.extend_from_slice(&0xffff0000u32.to_ne_bytes());
+            buf.extend_from_slice(&parent.to_ne_bytes());
+            buf.extend_from_slice(&[0; 4]);
+            push_attr(&mut buf, libc::TCA_KIND, kind);
+            buf
+        };
+
+        let msg = qdisc_msg(1, TC_H_CLSACT, b"clsact\0");
+        assert_eq!(parse_qdisc(&msg, 1), Some(TcQdiscKind::Clsact));
+        // Qdiscs of other interfaces are skipped.
+        assert_eq!(parse_qdisc(&msg, 2), None);
+        let msg = qdisc_msg(1, TC_H_INGRESS, b"ingress\0");
+        assert_eq!(parse_qdisc(&msg, 1), Some(TcQdiscKind::Ingress));
+        // As are root qdiscs.
+        let msg = qdisc_msg(1, 0xffffffff, b"noqueue\0");
+        assert_eq!(parse_qdisc(&msg, 1), None);
+    }
 }
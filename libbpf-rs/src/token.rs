@@ -0,0 +1,51 @@
+use std::mem::size_of;
+use std::os::fd::AsFd;
+use std::os::fd::AsRawFd;
+use std::os::fd::BorrowedFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+
+use crate::util;
+use crate::Result;
+
+/// A BPF token, granting a subset of BPF functionality to otherwise
+/// unprivileged processes.
+///
+/// Tokens are derived from a BPF file system instance that has been mounted
+/// with delegation options (`delegate_cmds`, `delegate_maps`,
+/// `delegate_progs`, and `delegate_attachs`), typically by a privileged
+/// process on behalf of a container. The token's file descriptor can be
+/// provided when creating maps via
+/// [`MapHandle::create`][crate::MapHandle::create] (`token_fd` and
+/// `BPF_F_TOKEN_FD` in the creation options). For objects, use
+/// [`ObjectBuilder::bpf_token_path`][crate::ObjectBuilder::bpf_token_path]
+/// instead, which makes libbpf create and use a token for all operations.
+#[derive(Debug)]
+pub struct Token {
+    fd: OwnedFd,
+}
+
+impl Token {
+    /// Create a token from the BPF file system instance referred to by
+    /// `bpffs_fd`, i.e., a file descriptor to its root directory.
+    pub fn create(bpffs_fd: BorrowedFd<'_>) -> Result<Self> {
+        let mut opts = libbpf_sys::bpf_token_create_opts {
+            sz: size_of::<libbpf_sys::bpf_token_create_opts>() as _,
+            ..Default::default()
+        };
+        let ret = unsafe { libbpf_sys::bpf_token_create(bpffs_fd.as_raw_fd(), &mut opts) };
+        let fd = util::parse_ret_i32(ret)?;
+        Ok(Self {
+            // SAFETY: `bpf_token_create` returned a valid file descriptor that
+            //         nobody else owns.
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+}
+
+impl AsFd for Token {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
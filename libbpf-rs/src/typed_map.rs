@@ -0,0 +1,141 @@
+use std::fmt;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::ptr;
+use std::slice;
+
+use crate::Error;
+use crate::MapFlags;
+use crate::MapHandle;
+use crate::Result;
+
+/// View `value` as its raw bytes.
+fn as_bytes<T>(value: &T) -> &[u8] {
+    // SAFETY: `TypedMap::new` requires that types it is used with don't
+    //         have any implicit padding, so all bytes are initialized.
+    unsafe { slice::from_raw_parts((value as *const T).cast::<u8>(), size_of::<T>()) }
+}
+
+/// Create a `T` from its raw bytes.
+fn from_bytes<T>(bytes: &[u8]) -> T {
+    debug_assert_eq!(bytes.len(), size_of::<T>());
+    // SAFETY: `TypedMap::new` requires that any bit pattern is a valid `T`
+    //         and we checked that map values have the size of `T`.
+    unsafe { ptr::read_unaligned(bytes.as_ptr().cast::<T>()) }
+}
+
+/// A view of a map that works with keys of type `K` and values of type `V`,
+/// instead of raw bytes.
+///
+/// Typed maps are generated for maps of skeletons whose keys and values are
+/// described by BTF, but can also be created manually using
+/// [`TypedMap::new`].
+pub struct TypedMap<'map, K, V> {
+    map: &'map MapHandle,
+    _types: PhantomData<(K, V)>,
+}
+
+impl<'map, K, V> TypedMap<'map, K, V>
+where
+    K: Copy,
+    V: Copy,
+{
+    /// Create a typed view of `map`.
+    ///
+    /// An error is reported if the sizes of `K` and `V` don't match the key
+    /// and value size of the map, respectively, or if the map is a per-CPU
+    /// map.
+    ///
+    /// # Safety
+    /// `K` and `V` must have the same layout as the map's key and value
+    /// types (i.e., be `#[repr(C)]` mirrors thereof), must not contain any
+    /// implicit padding, and any bit pattern must be a valid value of them.
+    pub unsafe fn new(map: &'map MapHandle) -> Result<Self> {
+        if map.map_type().is_percpu() {
+            return Err(Error::with_invalid_input(format!(
+                "map `{}` is a per-CPU map",
+                map.name()
+            )));
+        }
+
+        if map.key_size() as usize != size_of::<K>() {
+            return Err(Error::with_invalid_input(format!(
+                "key size of map `{}` ({}) does not match size of key type ({})",
+                map.name(),
+                map.key_size(),
+                size_of::<K>()
+            )));
+        }
+
+        if map.value_size() as usize != size_of::<V>() {
+            return Err(Error::with_invalid_input(format!(
+                "value size of map `{}` ({}) does not match size of value type ({})",
+                map.name(),
+                map.value_size(),
+                size_of::<V>()
+            )));
+        }
+
+        Ok(Self {
+            map,
+            _types: PhantomData,
+        })
+    }
+
+    /// Retrieve the underlying map.
+    pub fn map(&self) -> &'map MapHandle {
+        self.map
+    }
+
+    /// Look up the value stored for `key`.
+    ///
+    /// See [`MapHandle::lookup`].
+    pub fn lookup(&self, key: &K, flags: MapFlags) -> Result<Option<V>> {
+        let value = self.map.lookup(as_bytes(key), flags)?;
+        Ok(value.map(|value| from_bytes(&value)))
+    }
+
+    /// Store `value` for `key`.
+    ///
+    /// See [`MapHandle::update`].
+    pub fn update(&self, key: &K, value: &V, flags: MapFlags) -> Result<()> {
+        self.map.update(as_bytes(key), as_bytes(value), flags)
+    }
+
+    /// Delete the entry for `key`.
+    ///
+    /// See [`MapHandle::delete`].
+    pub fn delete(&self, key: &K) -> Result<()> {
+        self.map.delete(as_bytes(key))
+    }
+
+    /// Look up and delete the entry for `key`, returning its value.
+    ///
+    /// See [`MapHandle::lookup_and_delete`].
+    pub fn lookup_and_delete(&self, key: &K) -> Result<Option<V>> {
+        let value = self.map.lookup_and_delete(as_bytes(key))?;
+        Ok(value.map(|value| from_bytes(&value)))
+    }
+
+    /// Iterate over the keys of the map.
+    ///
+    /// See [`MapHandle::keys`] for caveats.
+    pub fn keys(&self) -> impl Iterator<Item = K> + 'map {
+        self.map.keys().map(|key| from_bytes(&key))
+    }
+}
+
+impl<K, V> Debug for TypedMap<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedMap").field("map", &self.map).finish()
+    }
+}
+
+impl<K, V> Clone for TypedMap<'_, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for TypedMap<'_, K, V> {}
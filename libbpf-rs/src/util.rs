@@ -1,15 +1,25 @@
 use std::any::type_name;
 use std::ffi::CStr;
 use std::ffi::CString;
+use std::ffi::OsString;
+use std::fs;
 use std::io;
 use std::mem::transmute;
 use std::ops::Deref;
 use std::os::raw::c_char;
+use std::os::unix::ffi::OsStringExt as _;
 use std::path::Path;
+use std::path::PathBuf;
 use std::ptr::NonNull;
+use std::str;
 use std::sync::OnceLock;
 
+use crate::diagnostics;
+use crate::error::LibbpfCallExt as _;
+use crate::print;
 use crate::Error;
+use crate::ErrorExt as _;
+use crate::ErrorKind;
 use crate::Result;
 
 pub fn str_to_cstring(s: &str) -> Result<CString> {
@@ -58,11 +68,45 @@ pub fn roundup(num: usize, r: usize) -> usize {
 }
 
 /// Get the number of CPUs in the system, e.g., to interact with per-cpu maps.
+///
+/// This is the number of *possible* CPUs, for each of which per-cpu maps
+/// hold a value. On systems with offline or hot-pluggable CPUs it exceeds
+/// the number of online CPUs (as reported by, e.g., `nproc`).
 pub fn num_possible_cpus() -> Result<usize> {
     let ret = unsafe { libbpf_sys::libbpf_num_possible_cpus() };
     parse_ret(ret).map(|()| ret as usize)
 }
 
+/// Get the distance between the values of consecutive CPUs in the buffers
+/// used for interacting with per-cpu maps with values of `value_size`
+/// bytes.
+///
+/// The kernel aligns each CPU's value to eight bytes.
+pub fn percpu_value_stride(value_size: usize) -> usize {
+    roundup(value_size, 8)
+}
+
+/// Get the size of the buffer used for looking up or updating an element
+/// of a per-cpu map with values of `value_size` bytes.
+///
+/// The buffer holds one value for each possible CPU (see
+/// [`num_possible_cpus`]), spaced [`percpu_value_stride`] bytes apart.
+pub fn percpu_buffer_size(value_size: usize) -> Result<usize> {
+    let ncpu = num_possible_cpus()?;
+    Ok(ncpu * percpu_value_stride(value_size))
+}
+
+/// Allocate a zeroed buffer of [`percpu_buffer_size`] bytes for looking up
+/// or updating an element of a per-cpu map with values of `value_size`
+/// bytes.
+///
+/// The value of CPU `i` is located at offset
+/// `i * percpu_value_stride(value_size)`.
+pub fn percpu_buffer(value_size: usize) -> Result<Vec<u8>> {
+    let size = percpu_buffer_size(value_size)?;
+    Ok(vec![0; size])
+}
+
 pub fn parse_ret(ret: i32) -> Result<()> {
     if ret < 0 {
         // Error code is returned negative, flip to positive to match errno
@@ -72,6 +116,37 @@ pub fn parse_ret(ret: i32) -> Result<()> {
     }
 }
 
+/// Load the BPF object (or skeleton) `name` via `load`, which returns the
+/// result of invoking the libbpf function `function`.
+///
+/// If one of the object's programs failed to load, the error reported
+/// names it and carries the verifier log, if available. Such errors are
+/// reported as [`ErrorKind::VerifierRejected`] if the kernel refused the
+/// program as invalid (as opposed to, say, lacking permissions).
+pub fn load_object<F>(function: &'static str, name: &str, load: F) -> Result<()>
+where
+    F: FnOnce() -> i32,
+{
+    let (ret, msgs) = print::capture(load);
+    parse_ret(ret)
+        .map_err(|err| match diagnostics::failed_program(&msgs) {
+            Some((prog, log)) => {
+                let err = if ret == -libc::EACCES || ret == -libc::EINVAL {
+                    err.with_kind(ErrorKind::VerifierRejected)
+                } else {
+                    err
+                };
+                let err = match log {
+                    Some(log) => err.with_verifier_log(log.to_string()),
+                    None => err,
+                };
+                err.context(format!("failed to load BPF program `{prog}`"))
+            }
+            None => err,
+        })
+        .libbpf_call(function, name)
+}
+
 pub fn parse_ret_i32(ret: i32) -> Result<i32> {
     parse_ret(ret).map(|()| ret)
 }
@@ -135,6 +210,52 @@ impl<T> Deref for LazyLock<T> {
     }
 }
 
+/// Unescape a path as found in `/proc/self/mounts`, in which the kernel
+/// encodes spaces, tabs, newlines, and backslashes as octal escapes.
+fn unescape_mount_path(path: &str) -> PathBuf {
+    let bytes = path.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|digits| str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        if let Some(byte) = octal {
+            result.push(byte);
+            i += 4;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+    PathBuf::from(OsString::from_vec(result))
+}
+
+/// Extract the mount points of all file systems of type `fstype` from the
+/// contents of a `mounts` file.
+fn parse_mounts(mounts: &str, fstype: &str) -> Vec<PathBuf> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _source = fields.next()?;
+            let target = fields.next()?;
+            let ty = fields.next()?;
+            (ty == fstype).then(|| unescape_mount_path(target))
+        })
+        .collect()
+}
+
+/// Retrieve the mount points of all file systems of type `fstype` (e.g.,
+/// `bpf`) visible to the process, in the order they were mounted.
+pub(crate) fn mount_points(fstype: &str) -> Result<Vec<PathBuf>> {
+    let mounts =
+        fs::read_to_string("/proc/self/mounts").context("failed to read /proc/self/mounts")?;
+    Ok(parse_mounts(&mounts, fstype))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,12 +277,46 @@ mod tests {
         }
     }
 
+    /// Check that we can extract mount points from `mounts` files.
+    #[test]
+    fn mounts_parsing() {
+        let mounts = r#"sysfs /sys sysfs rw,nosuid,nodev,noexec,relatime 0 0
+bpf /sys/fs/bpf bpf rw,nosuid,nodev,noexec,relatime,mode=700 0 0
+tmpfs /tmp tmpfs rw,nosuid,nodev 0 0
+bpf /run/my\040bpf\134fs bpf rw,relatime 0 0
+"#;
+        assert_eq!(
+            parse_mounts(mounts, "bpf"),
+            vec![
+                PathBuf::from("/sys/fs/bpf"),
+                PathBuf::from("/run/my bpf\\fs")
+            ]
+        );
+        assert_eq!(parse_mounts(mounts, "tmpfs"), vec![PathBuf::from("/tmp")]);
+        assert_eq!(parse_mounts("", "bpf"), Vec::<PathBuf>::new());
+    }
+
     #[test]
     fn test_num_possible_cpus() {
         let num = num_possible_cpus().unwrap();
         assert!(num > 0);
     }
 
+    /// Check that per-cpu values and buffers are sized as expected.
+    #[test]
+    fn percpu_sizing() {
+        assert_eq!(percpu_value_stride(0), 0);
+        assert_eq!(percpu_value_stride(1), 8);
+        assert_eq!(percpu_value_stride(8), 8);
+        assert_eq!(percpu_value_stride(12), 16);
+
+        let ncpu = num_possible_cpus().unwrap();
+        assert_eq!(percpu_buffer_size(12).unwrap(), ncpu * 16);
+        let buf = percpu_buffer(12).unwrap();
+        assert_eq!(buf.len(), ncpu * 16);
+        assert!(buf.iter().all(|b| *b == 0));
+    }
+
     /// Check that we can convert a `[c_char]` into a `CStr`.
     #[test]
     fn c_char_slice_conversion() {
@@ -0,0 +1,244 @@
+//! Selection among alternative versions of a program.
+
+use std::collections::HashSet;
+use std::io;
+
+use crate::btf::has_kfunc;
+use crate::btf::has_ksym;
+use crate::Error;
+use crate::OpenObject;
+use crate::OpenProgram;
+use crate::ProgramType;
+use crate::Result;
+
+/// A requirement a program variant has on the running kernel.
+#[derive(Clone, Debug)]
+pub enum VariantRequirement {
+    /// The program type has to be supported (see
+    /// [`ProgramType::is_supported`]).
+    ProgType(ProgramType),
+    /// The kernel function has to be present in the kernel's btf, as is
+    /// required for `fentry`/`fexit` programs attaching to it.
+    KernelFunction(String),
+    /// The kernel symbol has to be present (see [`has_ksym`]).
+    Ksym(String),
+    /// The provided function has to return `true`.
+    Check(fn() -> Result<bool>),
+}
+
+impl VariantRequirement {
+    fn is_met(&self) -> Result<bool> {
+        match self {
+            Self::ProgType(prog_type) => prog_type.is_supported(),
+            Self::KernelFunction(name) => has_kfunc(name),
+            Self::Ksym(name) => has_ksym(name),
+            Self::Check(check) => check(),
+        }
+    }
+}
+
+/// Derive the requirement that the attach target of a program defined in
+/// section `section` exists, if the section names one.
+fn target_requirement(section: &str) -> Option<VariantRequirement> {
+    let (kind, target) = section.split_once('/')?;
+    match kind {
+        "fentry" | "fentry.s" | "fexit" | "fexit.s" | "fmod_ret" | "fmod_ret.s" => {
+            Some(VariantRequirement::KernelFunction(target.to_string()))
+        }
+        "kprobe" | "kretprobe" => {
+            // Strip an offset into the function, as in `kprobe/func+0x10`.
+            let target = target.split('+').next().unwrap_or(target);
+            Some(VariantRequirement::Ksym(target.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// A program usable as one alternative of a [`ProgramVariants`] group.
+#[derive(Clone, Debug)]
+pub struct ProgramVariant {
+    prog: String,
+    requirements: Vec<VariantRequirement>,
+}
+
+impl ProgramVariant {
+    /// Create a variant using the program named `prog`.
+    ///
+    /// The variant implicitly requires the program's type to be supported
+    /// and, for `fentry`, `fexit`, `fmod_ret`, `kprobe`, and `kretprobe`
+    /// programs naming their attach target in their section, the target
+    /// to be present.
+    pub fn new<S: Into<String>>(prog: S) -> Self {
+        Self {
+            prog: prog.into(),
+            requirements: Vec::new(),
+        }
+    }
+
+    /// Add a requirement to the variant.
+    pub fn requires(mut self, requirement: VariantRequirement) -> Self {
+        self.requirements.push(requirement);
+        self
+    }
+
+    /// Check whether the variant is usable on the running kernel.
+    fn is_usable(&self, prog: &OpenProgram) -> Result<bool> {
+        if !prog.prog_type().is_supported()? {
+            return Ok(false);
+        }
+
+        for requirement in target_requirement(prog.section())
+            .iter()
+            .chain(&self.requirements)
+        {
+            if !requirement.is_met()? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// A group of alternative programs of which only one gets loaded.
+#[derive(Clone, Debug)]
+struct VariantGroup {
+    name: String,
+    variants: Vec<ProgramVariant>,
+}
+
+/// A facility for selecting, for each of a set of groups of alternative
+/// programs, the most preferred program usable on the running kernel.
+///
+/// Selection is meant to happen on an [`OpenObject`] (e.g., that of an
+/// open skeleton, as retrieved via
+/// [`OpenSkel::open_object_mut`][crate::skel::OpenSkel::open_object_mut]).
+/// The selected program of each group is set to be loaded, all others are
+/// not.
+///
+/// ```no_run
+/// # use libbpf_rs::ProgramVariant;
+/// # use libbpf_rs::ProgramVariants;
+/// # let mut open_obj = libbpf_rs::ObjectBuilder::default().open_file("prog.bpf.o").unwrap();
+/// let selected = ProgramVariants::new()
+///     .group(
+///         "exec",
+///         [
+///             // SEC("fentry/do_execveat_common")
+///             ProgramVariant::new("handle_exec_fentry"),
+///             // SEC("kprobe/do_execveat_common")
+///             ProgramVariant::new("handle_exec_kprobe"),
+///         ],
+///     )
+///     .select(&mut open_obj)
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ProgramVariants {
+    groups: Vec<VariantGroup>,
+}
+
+impl ProgramVariants {
+    /// Create an empty set of groups.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a group named `name` with the provided variants, in order of
+    /// preference.
+    pub fn group<S, I>(&mut self, name: S, variants: I) -> &mut Self
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = ProgramVariant>,
+    {
+        self.groups.push(VariantGroup {
+            name: name.into(),
+            variants: variants.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Select the first usable variant of each group in `obj` and set
+    /// programs to be loaded accordingly.
+    ///
+    /// The names of the selected programs are returned, in the order the
+    /// groups were added. An error is reported if a variant refers to a
+    /// program not present in `obj`, if a program is part of multiple
+    /// groups, or if no variant of a group is usable. In the latter cases,
+    /// `obj` is left unmodified.
+    ///
+    /// Make sure the process has the required set of `CAP_*` permissions
+    /// (or runs as root), as program type support is probed.
+    pub fn select(&self, obj: &mut OpenObject) -> Result<Vec<String>> {
+        let mut seen = HashSet::new();
+        for variant in self.groups.iter().flat_map(|group| &group.variants) {
+            if !seen.insert(variant.prog.as_str()) {
+                return Err(Error::with_invalid_input(format!(
+                    "program `{}` is part of multiple variants",
+                    variant.prog
+                )));
+            }
+            if obj.prog(&variant.prog).is_none() {
+                return Err(Error::with_io_error(
+                    io::ErrorKind::NotFound,
+                    format!("program `{}` not found", variant.prog),
+                ));
+            }
+        }
+
+        let mut selected = Vec::with_capacity(self.groups.len());
+        for group in &self.groups {
+            let mut choice = None;
+            for variant in &group.variants {
+                // Unwrap is safe here since we checked for existence above.
+                let prog = obj.prog(&variant.prog).unwrap();
+                if variant.is_usable(prog)? {
+                    choice = Some(variant.prog.clone());
+                    break;
+                }
+            }
+
+            let choice = choice.ok_or_else(|| {
+                Error::with_io_error(
+                    io::ErrorKind::Unsupported,
+                    format!("no variant of `{}` is usable", group.name),
+                )
+            })?;
+            selected.push(choice);
+        }
+
+        // Only touch the object once we know that selection succeeded.
+        for (group, choice) in self.groups.iter().zip(&selected) {
+            for variant in &group.variants {
+                let prog = obj.prog_mut(&variant.prog).unwrap();
+                let () = prog.set_autoload(variant.prog == *choice)?;
+            }
+        }
+        Ok(selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that we derive requirements for attach targets from program
+    /// sections.
+    #[test]
+    fn section_target_requirement() {
+        assert!(matches!(
+            target_requirement("fentry/do_exit"),
+            Some(VariantRequirement::KernelFunction(name)) if name == "do_exit"
+        ));
+        assert!(matches!(
+            target_requirement("fexit.s/do_exit"),
+            Some(VariantRequirement::KernelFunction(name)) if name == "do_exit"
+        ));
+        assert!(matches!(
+            target_requirement("kprobe/do_exit+0x10"),
+            Some(VariantRequirement::Ksym(name)) if name == "do_exit"
+        ));
+        assert!(target_requirement("kprobe").is_none());
+        assert!(target_requirement("tp/sched/sched_switch").is_none());
+        assert!(target_requirement("xdp").is_none());
+    }
+}
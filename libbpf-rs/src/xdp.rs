@@ -5,6 +5,7 @@ use std::os::unix::io::BorrowedFd;
 use bitflags::bitflags;
 
 use crate::util;
+use crate::IntoIfindex;
 use crate::Result;
 
 bitflags! {
@@ -30,6 +31,49 @@ bitflags! {
 
 }
 
+bitflags! {
+    /// The XDP features supported by a network device (see `enum
+    /// netdev_xdp_act` in `include/uapi/linux/netdev.h`).
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct XdpFeatures: u64 {
+        /// Basic XDP support, i.e., the `XDP_ABORTED`, `XDP_DROP`,
+        /// `XDP_PASS`, and `XDP_TX` actions.
+        const BASIC        = 1 << 0;
+        /// Support for the `XDP_REDIRECT` action.
+        const REDIRECT     = 1 << 1;
+        /// The device can be the target of an `XDP_REDIRECT`.
+        const NDO_XMIT     = 1 << 2;
+        /// Support for AF_XDP sockets in zero-copy mode.
+        const XSK_ZEROCOPY = 1 << 3;
+        /// Support for offloading programs to hardware.
+        const HW_OFFLOAD   = 1 << 4;
+        /// Support for non-linear (multi-buffer) packets on receive.
+        const RX_SG        = 1 << 5;
+        /// Support for non-linear (multi-buffer) packets when being the
+        /// target of an `XDP_REDIRECT`.
+        const NDO_XMIT_SG  = 1 << 6;
+    }
+}
+
+/// The programs attached at the XDP hook of an interface, by mode, as
+/// retrieved via [`Xdp::query_attached()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XdpAttached {
+    /// The ID of the program attached in generic (SKB) mode, if any.
+    pub generic: Option<u32>,
+    /// The ID of the program attached in native (driver) mode, if any.
+    pub driver: Option<u32>,
+    /// The ID of the program offloaded to hardware, if any.
+    pub offload: Option<u32>,
+}
+
+impl XdpAttached {
+    /// Check whether no program is attached in any mode.
+    pub fn is_empty(&self) -> bool {
+        self.generic.is_none() && self.driver.is_none() && self.offload.is_none()
+    }
+}
+
 /// Represents a XDP program.
 ///
 /// This struct exposes operations to attach, detach and query a XDP program
@@ -57,10 +101,14 @@ impl<'fd> Xdp<'fd> {
     /// Attach the XDP program to the given interface to start processing the
     /// packets
     ///
+    /// The interface can be provided by index or by name (see
+    /// [`IntoIfindex`]).
+    ///
     /// # Notes
     /// Once a program is attached, it will outlive the userspace program. Make
     /// sure to detach the program if its not desired.
-    pub fn attach(&self, ifindex: i32, flags: XdpFlags) -> Result<()> {
+    pub fn attach<I: IntoIfindex>(&self, ifindex: I, flags: XdpFlags) -> Result<()> {
+        let ifindex = ifindex.into_ifindex()?;
         let ret = unsafe {
             libbpf_sys::bpf_xdp_attach(
                 ifindex,
@@ -73,35 +121,98 @@ impl<'fd> Xdp<'fd> {
     }
 
     /// Detach the XDP program from the interface
-    pub fn detach(&self, ifindex: i32, flags: XdpFlags) -> Result<()> {
+    pub fn detach<I: IntoIfindex>(&self, ifindex: I, flags: XdpFlags) -> Result<()> {
+        let ifindex = ifindex.into_ifindex()?;
         let ret = unsafe { libbpf_sys::bpf_xdp_detach(ifindex, flags.bits(), &self.attach_opts) };
         util::parse_ret(ret)
     }
 
     /// Query to inspect the program
-    pub fn query(&self, ifindex: i32, flags: XdpFlags) -> Result<libbpf_sys::bpf_xdp_query_opts> {
+    pub fn query<I: IntoIfindex>(
+        &self,
+        ifindex: I,
+        flags: XdpFlags,
+    ) -> Result<libbpf_sys::bpf_xdp_query_opts> {
+        let ifindex = ifindex.into_ifindex()?;
         let mut opts = self.query_opts;
         let err = unsafe { libbpf_sys::bpf_xdp_query(ifindex, flags.bits() as i32, &mut opts) };
         util::parse_ret(err).map(|()| opts)
     }
 
     /// Query to inspect the program identifier (prog_id)
-    pub fn query_id(&self, ifindex: i32, flags: XdpFlags) -> Result<u32> {
+    pub fn query_id<I: IntoIfindex>(&self, ifindex: I, flags: XdpFlags) -> Result<u32> {
+        let ifindex = ifindex.into_ifindex()?;
         let mut prog_id = 0;
         let err =
             unsafe { libbpf_sys::bpf_xdp_query_id(ifindex, flags.bits() as i32, &mut prog_id) };
         util::parse_ret(err).map(|()| prog_id)
     }
 
+    /// Query the programs attached at the XDP hook of the given interface
+    /// in each of the generic, driver, and offload modes
+    ///
+    /// In contrast to [`Self::query()`], this does not require an XDP
+    /// program of our own.
+    pub fn query_attached<I: IntoIfindex>(ifindex: I) -> Result<XdpAttached> {
+        let ifindex = ifindex.into_ifindex()?;
+        let mut opts = libbpf_sys::bpf_xdp_query_opts {
+            sz: size_of::<libbpf_sys::bpf_xdp_query_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+        let err = unsafe { libbpf_sys::bpf_xdp_query(ifindex, 0, &mut opts) };
+        let () = util::parse_ret(err)?;
+
+        let id = |id| Some(id).filter(|id| *id != 0);
+        Ok(XdpAttached {
+            generic: id(opts.skb_prog_id),
+            driver: id(opts.drv_prog_id),
+            offload: id(opts.hw_prog_id),
+        })
+    }
+
+    /// Query the XDP features supported by the given interface
+    ///
+    /// Kernels not able to report XDP features (i.e., before 6.3) report
+    /// none. Unknown features are retained in the result.
+    pub fn query_features<I: IntoIfindex>(ifindex: I) -> Result<XdpFeatures> {
+        let ifindex = ifindex.into_ifindex()?;
+        let mut opts = libbpf_sys::bpf_xdp_query_opts {
+            sz: size_of::<libbpf_sys::bpf_xdp_query_opts>() as libbpf_sys::size_t,
+            ..Default::default()
+        };
+        let err = unsafe { libbpf_sys::bpf_xdp_query(ifindex, 0, &mut opts) };
+        util::parse_ret(err).map(|()| XdpFeatures::from_bits_retain(opts.feature_flags))
+    }
+
     /// Replace an existing xdp program (identified by old_prog_fd) with this xdp program
-    pub fn replace(&self, ifindex: i32, old_prog_fd: BorrowedFd<'_>) -> Result<()> {
+    pub fn replace<I: IntoIfindex>(&self, ifindex: I, old_prog_fd: BorrowedFd<'_>) -> Result<()> {
+        self.attach_replace(ifindex, old_prog_fd, XdpFlags::NONE)
+    }
+
+    /// Atomically replace the xdp program identified by `old_prog_fd` with
+    /// this xdp program, attaching in the mode selected by `flags`
+    ///
+    /// If the program attached at the interface is not the expected one
+    /// (e.g., because another agent replaced it in the meantime), the
+    /// operation fails with an error of kind
+    /// [`ErrorKind::AlreadyExists`][crate::ErrorKind::AlreadyExists] and
+    /// the attached program is left in place.
+    ///
+    /// [`XdpFlags::REPLACE`] is implied.
+    pub fn attach_replace<I: IntoIfindex>(
+        &self,
+        ifindex: I,
+        old_prog_fd: BorrowedFd<'_>,
+        flags: XdpFlags,
+    ) -> Result<()> {
+        let ifindex = ifindex.into_ifindex()?;
         let mut opts = self.attach_opts;
         opts.old_prog_fd = old_prog_fd.as_raw_fd();
         let ret = unsafe {
             libbpf_sys::bpf_xdp_attach(
                 ifindex,
                 self.fd.as_raw_fd(),
-                XdpFlags::REPLACE.bits(),
+                (flags | XdpFlags::REPLACE).bits(),
                 &opts,
             )
         };
@@ -0,0 +1,703 @@
+//! Support for AF_XDP sockets.
+//!
+//! AF_XDP sockets (XSKs) receive packets that an XDP program redirects to
+//! them via an `BPF_MAP_TYPE_XSKMAP` map directly into a memory area shared
+//! with user space, the UMEM, and transmit packets from there. Ownership of
+//! the UMEM's frames is passed between user space and the kernel via four
+//! rings:
+//! - the fill ring, via which user space hands frames to the kernel for
+//!   receiving packets into,
+//! - the rx ring, via which the kernel hands frames containing received
+//!   packets to user space,
+//! - the tx ring, via which user space hands frames containing packets to
+//!   transmit to the kernel, and
+//! - the completion ring, via which the kernel hands frames back to user
+//!   space once their packets got transmitted.
+//!
+//! ```no_run
+//! # use libbpf_rs::MapHandle;
+//! # use libbpf_rs::xsk::Umem;
+//! # use libbpf_rs::xsk::UmemConfig;
+//! # use libbpf_rs::xsk::XdpDesc;
+//! # use libbpf_rs::xsk::XskConfig;
+//! # use libbpf_rs::xsk::XskParts;
+//! # use libbpf_rs::xsk::XskSocket;
+//! # let xsks_map: MapHandle = todo!();
+//! let umem = Umem::new(4096, UmemConfig::default()).unwrap();
+//! let mut xsk = XskSocket::new("eth0", 0, umem, &XskConfig::default()).unwrap();
+//! // Make the socket the target of `bpf_redirect_map(&xsks_map, 0, 0)`.
+//! let () = xsk.register(&xsks_map, 0).unwrap();
+//!
+//! let XskParts { umem, fill, rx, .. } = xsk.parts_mut();
+//! let addrs = (0..2048).map(|idx| umem.frame_addr(idx)).collect::<Vec<_>>();
+//! let _count = fill.produce(&addrs);
+//!
+//! let mut descs = [XdpDesc::default(); 64];
+//! if fill.needs_wakeup() {
+//!     let () = xsk.wakeup_rx().unwrap();
+//! }
+//! let XskParts { umem, rx, .. } = xsk.parts_mut();
+//! let count = rx.consume(&mut descs);
+//! for desc in &descs[..count] {
+//!     let packet = umem.data(desc.addr, desc.len).unwrap();
+//!     println!("received {} bytes", packet.len());
+//! }
+//! ```
+
+use std::cmp::min;
+use std::io;
+use std::mem::size_of;
+use std::mem::MaybeUninit;
+use std::os::raw::c_int;
+use std::os::raw::c_void;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::FromRawFd as _;
+use std::os::unix::io::OwnedFd;
+use std::ptr;
+use std::ptr::NonNull;
+use std::slice;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+
+use bitflags::bitflags;
+
+use crate::error::LibbpfCallExt as _;
+use crate::query::MapInfo;
+use crate::util;
+use crate::Error;
+use crate::IntoIfindex;
+use crate::MapType;
+use crate::Result;
+
+/// The `AF_XDP` address family, which `libc` does not provide for all
+/// targets.
+const AF_XDP: c_int = 44;
+
+/// The default number of entries of each ring.
+pub const DEFAULT_RING_SIZE: u32 = 2048;
+/// The default size of a UMEM frame, in bytes.
+pub const DEFAULT_FRAME_SIZE: u32 = 4096;
+
+/// A memory mapping, unmapped when dropped.
+#[derive(Debug)]
+struct Mmap {
+    ptr: NonNull<c_void>,
+    len: usize,
+}
+
+impl Mmap {
+    fn new(len: usize, flags: c_int, fd: c_int, offset: u64) -> Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                fd,
+                offset as libc::off_t,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(Error::from(io::Error::last_os_error()));
+        }
+        // SAFETY: `mmap` never returns NULL on success when not asked for
+        //         a fixed mapping.
+        let ptr = unsafe { NonNull::new_unchecked(ptr) };
+        Ok(Self { ptr, len })
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        let _ret = unsafe { libc::munmap(self.ptr.as_ptr(), self.len) };
+    }
+}
+
+// SAFETY: The mapping is exclusively owned and can be unmapped from any
+//         thread.
+unsafe impl Send for Mmap {}
+
+/// The configuration of a [`Umem`].
+#[derive(Clone, Copy, Debug)]
+pub struct UmemConfig {
+    /// The number of entries of the fill ring. Has to be a power of two.
+    pub fill_size: u32,
+    /// The number of entries of the completion ring. Has to be a power of
+    /// two.
+    pub comp_size: u32,
+    /// The size of each frame, in bytes. Has to be a power of two between
+    /// 2048 and the page size.
+    pub frame_size: u32,
+    /// The headroom the kernel leaves at the start of each frame before
+    /// packet data, in bytes.
+    pub frame_headroom: u32,
+}
+
+impl Default for UmemConfig {
+    fn default() -> Self {
+        Self {
+            fill_size: DEFAULT_RING_SIZE,
+            comp_size: DEFAULT_RING_SIZE,
+            frame_size: DEFAULT_FRAME_SIZE,
+            frame_headroom: 0,
+        }
+    }
+}
+
+/// A memory area divided into equally sized frames that packets are
+/// received into and transmitted from.
+///
+/// Frames are referred to by their address, i.e., their offset into the
+/// area.
+#[derive(Debug)]
+pub struct Umem {
+    area: Mmap,
+    config: UmemConfig,
+    frame_count: u32,
+}
+
+impl Umem {
+    /// Allocate a UMEM consisting of `frame_count` frames.
+    pub fn new(frame_count: u32, config: UmemConfig) -> Result<Self> {
+        if frame_count == 0 {
+            return Err(Error::with_invalid_input("UMEM needs at least one frame"));
+        }
+        if !config.fill_size.is_power_of_two() || !config.comp_size.is_power_of_two() {
+            return Err(Error::with_invalid_input(
+                "UMEM ring sizes have to be powers of two",
+            ));
+        }
+
+        let len = frame_count as usize * config.frame_size as usize;
+        let area = Mmap::new(len, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1, 0)?;
+        Ok(Self {
+            area,
+            config,
+            frame_count,
+        })
+    }
+
+    /// Retrieve the configuration of the UMEM.
+    pub fn config(&self) -> &UmemConfig {
+        &self.config
+    }
+
+    /// Retrieve the number of frames of the UMEM.
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Retrieve the address of the frame with index `idx`.
+    pub fn frame_addr(&self, idx: u32) -> u64 {
+        u64::from(idx) * u64::from(self.config.frame_size)
+    }
+
+    /// Check that `len` bytes at address `addr` are inside the UMEM.
+    fn check_range(&self, addr: u64, len: u32) -> Option<usize> {
+        let end = addr.checked_add(u64::from(len))?;
+        (end <= self.area.len as u64).then_some(addr as usize)
+    }
+
+    /// Retrieve the `len` bytes of data at address `addr`, e.g., as
+    /// described by an [`XdpDesc`] read from the rx ring.
+    ///
+    /// `None` is returned if the data is not entirely inside the UMEM.
+    pub fn data(&self, addr: u64, len: u32) -> Option<&[u8]> {
+        let offset = self.check_range(addr, len)?;
+        let ptr = unsafe { self.area.ptr.as_ptr().cast::<u8>().add(offset) };
+        Some(unsafe { slice::from_raw_parts(ptr, len as usize) })
+    }
+
+    /// Retrieve the `len` bytes of data at address `addr` mutably, e.g.,
+    /// to write a packet to transmit.
+    ///
+    /// `None` is returned if the data is not entirely inside the UMEM.
+    pub fn data_mut(&mut self, addr: u64, len: u32) -> Option<&mut [u8]> {
+        let offset = self.check_range(addr, len)?;
+        let ptr = unsafe { self.area.ptr.as_ptr().cast::<u8>().add(offset) };
+        Some(unsafe { slice::from_raw_parts_mut(ptr, len as usize) })
+    }
+}
+
+/// A descriptor of a packet in the UMEM, as exchanged via the rx and tx
+/// rings (see `struct xdp_desc`).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XdpDesc {
+    /// The address of the packet data in the UMEM.
+    pub addr: u64,
+    /// The length of the packet data, in bytes.
+    pub len: u32,
+    /// Options, such as `XDP_PKT_CONTD` for packets spanning multiple
+    /// frames.
+    pub options: u32,
+}
+
+/// A ring shared with the kernel.
+#[derive(Debug)]
+struct Ring<T> {
+    _mmap: Mmap,
+    producer: NonNull<AtomicU32>,
+    consumer: NonNull<AtomicU32>,
+    flags: NonNull<AtomicU32>,
+    descs: NonNull<T>,
+    size: u32,
+}
+
+impl<T: Copy> Ring<T> {
+    /// Map the ring with `size` entries at offset `pgoff` of the socket
+    /// `fd`, laid out according to `off`.
+    fn map(fd: BorrowedFd<'_>, size: u32, off: &libc::xdp_ring_offset, pgoff: u64) -> Result<Self> {
+        let len = off.desc as usize + size as usize * size_of::<T>();
+        let mmap = Mmap::new(
+            len,
+            libc::MAP_SHARED | libc::MAP_POPULATE,
+            fd.as_raw_fd(),
+            pgoff,
+        )?;
+        let base = mmap.ptr.as_ptr().cast::<u8>();
+        // SAFETY: The kernel reported offsets inside the mapping, which is
+        //         not NULL.
+        let ptr = |offset: u64| unsafe { NonNull::new_unchecked(base.add(offset as usize)) };
+        Ok(Self {
+            producer: ptr(off.producer).cast(),
+            consumer: ptr(off.consumer).cast(),
+            flags: ptr(off.flags).cast(),
+            descs: ptr(off.desc).cast(),
+            size,
+            _mmap: mmap,
+        })
+    }
+
+    fn producer(&self) -> &AtomicU32 {
+        unsafe { self.producer.as_ref() }
+    }
+
+    fn consumer(&self) -> &AtomicU32 {
+        unsafe { self.consumer.as_ref() }
+    }
+
+    fn desc(&self, idx: u32) -> *mut T {
+        unsafe { self.descs.as_ptr().add((idx & (self.size - 1)) as usize) }
+    }
+}
+
+// SAFETY: Rings are only accessed through exclusive references on the user
+//         space side.
+unsafe impl<T> Send for Ring<T> {}
+
+/// A ring via which user space passes entries to the kernel.
+#[derive(Debug)]
+pub struct ProducerRing<T> {
+    ring: Ring<T>,
+}
+
+impl<T: Copy> ProducerRing<T> {
+    /// Retrieve the number of entries that can currently be produced.
+    pub fn free(&self) -> u32 {
+        let prod = self.ring.producer().load(Ordering::Relaxed);
+        let cons = self.ring.consumer().load(Ordering::Acquire);
+        self.ring.size - prod.wrapping_sub(cons)
+    }
+
+    /// Pass as many of `entries` to the kernel as there is room for in the
+    /// ring, returning the number of entries passed.
+    pub fn produce(&mut self, entries: &[T]) -> usize {
+        let count = min(self.free() as usize, entries.len());
+        let prod = self.ring.producer().load(Ordering::Relaxed);
+        for (idx, entry) in entries[..count].iter().enumerate() {
+            let () = unsafe { self.ring.desc(prod.wrapping_add(idx as u32)).write(*entry) };
+        }
+        let () = self
+            .ring
+            .producer()
+            .store(prod.wrapping_add(count as u32), Ordering::Release);
+        count
+    }
+
+    /// Check whether the kernel has to be woken up to process the ring's
+    /// entries.
+    ///
+    /// This is only ever the case if the socket was bound with
+    /// [`XskBindFlags::USE_NEED_WAKEUP`].
+    pub fn needs_wakeup(&self) -> bool {
+        let flags = unsafe { self.ring.flags.as_ref() }.load(Ordering::Relaxed);
+        flags & libc::XDP_RING_NEED_WAKEUP != 0
+    }
+}
+
+/// A ring via which the kernel passes entries to user space.
+#[derive(Debug)]
+pub struct ConsumerRing<T> {
+    ring: Ring<T>,
+}
+
+impl<T: Copy> ConsumerRing<T> {
+    /// Retrieve the number of entries that can currently be consumed.
+    pub fn available(&self) -> u32 {
+        let prod = self.ring.producer().load(Ordering::Acquire);
+        let cons = self.ring.consumer().load(Ordering::Relaxed);
+        prod.wrapping_sub(cons)
+    }
+
+    /// Consume as many entries as available and fitting into `entries`,
+    /// returning the number of entries consumed.
+    pub fn consume(&mut self, entries: &mut [T]) -> usize {
+        let count = min(self.available() as usize, entries.len());
+        let cons = self.ring.consumer().load(Ordering::Relaxed);
+        for (idx, entry) in entries[..count].iter_mut().enumerate() {
+            *entry = unsafe { self.ring.desc(cons.wrapping_add(idx as u32)).read() };
+        }
+        let () = self
+            .ring
+            .consumer()
+            .store(cons.wrapping_add(count as u32), Ordering::Release);
+        count
+    }
+}
+
+/// The ring via which frames to receive packets into are passed to the
+/// kernel.
+pub type FillRing = ProducerRing<u64>;
+/// The ring via which frames of transmitted packets are passed back to user
+/// space.
+pub type CompletionRing = ConsumerRing<u64>;
+/// The ring via which received packets are passed to user space.
+pub type RxRing = ConsumerRing<XdpDesc>;
+/// The ring via which packets to transmit are passed to the kernel.
+pub type TxRing = ProducerRing<XdpDesc>;
+
+bitflags! {
+    /// Flags to configure the binding of an [`XskSocket`].
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct XskBindFlags: u16 {
+        /// Force copy mode.
+        const COPY            = libc::XDP_COPY;
+        /// Force zero-copy mode.
+        const ZEROCOPY        = libc::XDP_ZEROCOPY;
+        /// Only process the fill and tx rings when woken up (see
+        /// [`ProducerRing::needs_wakeup`]).
+        const USE_NEED_WAKEUP = libc::XDP_USE_NEED_WAKEUP;
+    }
+}
+
+/// The configuration of an [`XskSocket`].
+#[derive(Clone, Copy, Debug)]
+pub struct XskConfig {
+    /// The number of entries of the rx ring. Has to be a power of two.
+    pub rx_size: u32,
+    /// The number of entries of the tx ring. Has to be a power of two.
+    pub tx_size: u32,
+    /// The flags to bind the socket with.
+    pub bind_flags: XskBindFlags,
+}
+
+impl Default for XskConfig {
+    fn default() -> Self {
+        Self {
+            rx_size: DEFAULT_RING_SIZE,
+            tx_size: DEFAULT_RING_SIZE,
+            bind_flags: XskBindFlags::empty(),
+        }
+    }
+}
+
+/// Mutable references to the UMEM and the rings of an [`XskSocket`], as
+/// retrieved via [`XskSocket::parts_mut`].
+#[derive(Debug)]
+pub struct XskParts<'sock> {
+    /// The UMEM.
+    pub umem: &'sock mut Umem,
+    /// The fill ring.
+    pub fill: &'sock mut FillRing,
+    /// The completion ring.
+    pub completion: &'sock mut CompletionRing,
+    /// The rx ring.
+    pub rx: &'sock mut RxRing,
+    /// The tx ring.
+    pub tx: &'sock mut TxRing,
+}
+
+fn setsockopt<T>(fd: BorrowedFd<'_>, name: c_int, value: &T) -> Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            libc::SOL_XDP,
+            name,
+            (value as *const T).cast(),
+            size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(Error::from(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// An AF_XDP socket bound to a queue of a network interface.
+#[derive(Debug)]
+pub struct XskSocket {
+    // Rings are declared before the socket so that they are unmapped
+    // before it is closed.
+    fill: FillRing,
+    completion: CompletionRing,
+    rx: RxRing,
+    tx: TxRing,
+    fd: OwnedFd,
+    umem: Umem,
+    ifindex: i32,
+    queue_id: u32,
+}
+
+impl XskSocket {
+    /// Create a socket using `umem` and bind it to queue `queue_id` of the
+    /// interface `ifindex`, provided by index or by name.
+    ///
+    /// Make sure the process has the required set of `CAP_*` permissions
+    /// (or runs as root).
+    pub fn new<I: IntoIfindex>(
+        ifindex: I,
+        queue_id: u32,
+        umem: Umem,
+        config: &XskConfig,
+    ) -> Result<Self> {
+        let ifindex = ifindex.into_ifindex()?;
+        if !config.rx_size.is_power_of_two() || !config.tx_size.is_power_of_two() {
+            return Err(Error::with_invalid_input(
+                "socket ring sizes have to be powers of two",
+            ));
+        }
+
+        let fd = unsafe { libc::socket(AF_XDP, libc::SOCK_RAW | libc::SOCK_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(Error::from(io::Error::last_os_error()));
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let umem_config = umem.config;
+        let reg = libc::xdp_umem_reg {
+            addr: umem.area.ptr.as_ptr() as u64,
+            len: umem.area.len as u64,
+            chunk_size: umem_config.frame_size,
+            headroom: umem_config.frame_headroom,
+            flags: 0,
+            tx_metadata_len: 0,
+        };
+        let () = setsockopt(fd.as_fd(), libc::XDP_UMEM_REG, &reg)?;
+        let () = setsockopt(fd.as_fd(), libc::XDP_UMEM_FILL_RING, &umem_config.fill_size)?;
+        let () = setsockopt(
+            fd.as_fd(),
+            libc::XDP_UMEM_COMPLETION_RING,
+            &umem_config.comp_size,
+        )?;
+        let () = setsockopt(fd.as_fd(), libc::XDP_RX_RING, &config.rx_size)?;
+        let () = setsockopt(fd.as_fd(), libc::XDP_TX_RING, &config.tx_size)?;
+
+        let mut off = MaybeUninit::<libc::xdp_mmap_offsets>::zeroed();
+        let mut len = size_of::<libc::xdp_mmap_offsets>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                fd.as_raw_fd(),
+                libc::SOL_XDP,
+                libc::XDP_MMAP_OFFSETS,
+                off.as_mut_ptr().cast(),
+                &mut len,
+            )
+        };
+        if ret < 0 {
+            return Err(Error::from(io::Error::last_os_error()));
+        }
+        if len as usize != size_of::<libc::xdp_mmap_offsets>() {
+            return Err(Error::with_io_error(
+                io::ErrorKind::Unsupported,
+                "kernel does not report ring flags offsets",
+            ));
+        }
+        let off = unsafe { off.assume_init() };
+
+        let fill = ProducerRing {
+            ring: Ring::map(
+                fd.as_fd(),
+                umem_config.fill_size,
+                &off.fr,
+                libc::XDP_UMEM_PGOFF_FILL_RING,
+            )?,
+        };
+        let completion = ConsumerRing {
+            ring: Ring::map(
+                fd.as_fd(),
+                umem_config.comp_size,
+                &off.cr,
+                libc::XDP_UMEM_PGOFF_COMPLETION_RING,
+            )?,
+        };
+        let rx = ConsumerRing {
+            ring: Ring::map(
+                fd.as_fd(),
+                config.rx_size,
+                &off.rx,
+                libc::XDP_PGOFF_RX_RING as u64,
+            )?,
+        };
+        let tx = ProducerRing {
+            ring: Ring::map(
+                fd.as_fd(),
+                config.tx_size,
+                &off.tx,
+                libc::XDP_PGOFF_TX_RING as u64,
+            )?,
+        };
+
+        let addr = libc::sockaddr_xdp {
+            sxdp_family: AF_XDP as u16,
+            sxdp_flags: config.bind_flags.bits(),
+            sxdp_ifindex: ifindex as u32,
+            sxdp_queue_id: queue_id,
+            sxdp_shared_umem_fd: 0,
+        };
+        let ret = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                (&addr as *const libc::sockaddr_xdp).cast(),
+                size_of::<libc::sockaddr_xdp>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(Error::from(io::Error::last_os_error()));
+        }
+
+        Ok(Self {
+            fill,
+            completion,
+            rx,
+            tx,
+            fd,
+            umem,
+            ifindex,
+            queue_id,
+        })
+    }
+
+    /// Retrieve the index of the interface the socket is bound to.
+    pub fn ifindex(&self) -> i32 {
+        self.ifindex
+    }
+
+    /// Retrieve the queue the socket is bound to.
+    pub fn queue_id(&self) -> u32 {
+        self.queue_id
+    }
+
+    /// Retrieve the socket's UMEM.
+    pub fn umem(&self) -> &Umem {
+        &self.umem
+    }
+
+    /// Retrieve mutable references to the socket's UMEM and rings, for
+    /// using them simultaneously.
+    pub fn parts_mut(&mut self) -> XskParts<'_> {
+        XskParts {
+            umem: &mut self.umem,
+            fill: &mut self.fill,
+            completion: &mut self.completion,
+            rx: &mut self.rx,
+            tx: &mut self.tx,
+        }
+    }
+
+    /// Make the socket the one at index `index` of the
+    /// `BPF_MAP_TYPE_XSKMAP` map `map`, which XDP programs can redirect
+    /// packets to (via `bpf_redirect_map`).
+    ///
+    /// `map` may be any map representation, e.g., a [`MapHandle`][crate::MapHandle] or a map
+    /// of a loaded object or skeleton.
+    pub fn register<M: AsFd + ?Sized>(&self, map: &M, index: u32) -> Result<()> {
+        let map = map.as_fd();
+        let info = MapInfo::load_from_fd(map)?;
+        let name = info.name.to_string_lossy();
+        if info.ty != MapType::Xskmap {
+            return Err(Error::with_invalid_input(format!(
+                "map `{name}` is not an XSKMAP"
+            )));
+        }
+
+        let key = index;
+        let value = self.fd.as_raw_fd();
+        let ret = unsafe {
+            libbpf_sys::bpf_map_update_elem(
+                map.as_raw_fd(),
+                (&key as *const u32).cast(),
+                (&value as *const c_int).cast(),
+                libbpf_sys::BPF_ANY.into(),
+            )
+        };
+        util::parse_ret(ret).libbpf_call("bpf_map_update_elem", &name)
+    }
+
+    /// Wake up the kernel to process the fill ring and receive packets.
+    ///
+    /// Only necessary if the socket was bound with
+    /// [`XskBindFlags::USE_NEED_WAKEUP`] and the fill ring
+    /// [needs wakeup][ProducerRing::needs_wakeup]. Without the wakeup, the
+    /// kernel does not pick up new frames to receive packets into and the
+    /// rx ring stays empty.
+    pub fn wakeup_rx(&self) -> Result<()> {
+        let ret = unsafe {
+            libc::recvfrom(
+                self.fd.as_raw_fd(),
+                ptr::null_mut(),
+                0,
+                libc::MSG_DONTWAIT,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            // The socket being empty or the kernel being busy are no
+            // errors for our purposes.
+            match err.raw_os_error() {
+                Some(libc::EAGAIN | libc::EBUSY | libc::ENOBUFS | libc::ENETDOWN) => (),
+                _ => return Err(Error::from(err)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Wake up the kernel to transmit the packets on the tx ring.
+    ///
+    /// Only necessary if the socket was bound with
+    /// [`XskBindFlags::USE_NEED_WAKEUP`] and the tx ring
+    /// [needs wakeup][ProducerRing::needs_wakeup] or the socket is in copy
+    /// mode.
+    pub fn wakeup_tx(&self) -> Result<()> {
+        let ret = unsafe {
+            libc::sendto(
+                self.fd.as_raw_fd(),
+                ptr::null(),
+                0,
+                libc::MSG_DONTWAIT,
+                ptr::null(),
+                0,
+            )
+        };
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            // These errors are transient and merely indicate that the
+            // kernel is busy processing the ring already.
+            match err.raw_os_error() {
+                Some(libc::EAGAIN | libc::EBUSY | libc::ENOBUFS | libc::ENETDOWN) => (),
+                _ => return Err(Error::from(err)),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AsFd for XskSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: GPL-2.0
+
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+
+extern unsigned int CONFIG_HZ __kconfig;
+extern bool CONFIG_LIBBPF_RS_MISSING __kconfig __weak;
+
+extern const int bpf_prog_active __ksym;
+extern const void __start_BTF __ksym;
+extern const void libbpf_rs_missing_sym __ksym __weak;
+
+extern void bpf_rcu_read_lock(void) __ksym;
+extern void libbpf_rs_missing_kfunc(void) __ksym __weak;
+
+SEC("socket")
+int handle__externs(void *ctx)
+{
+	unsigned int hz = CONFIG_HZ;
+	bool missing = CONFIG_LIBBPF_RS_MISSING;
+	const void *ptr;
+
+	barrier_var(hz);
+	barrier_var(missing);
+
+	ptr = &__start_BTF;
+	barrier_var(ptr);
+	ptr = &libbpf_rs_missing_sym;
+	barrier_var(ptr);
+
+	ptr = bpf_rcu_read_lock;
+	barrier_var(ptr);
+	ptr = libbpf_rs_missing_kfunc;
+	barrier_var(ptr);
+
+	return 0;
+}
+
+/* Loading typed kernel variables requires CONFIG_KALLSYMS_ALL. */
+SEC("socket")
+int handle__typed_ksym(void *ctx)
+{
+	const void *ptr = &bpf_prog_active;
+
+	barrier_var(ptr);
+	return 0;
+}
+
+char LICENSE[] SEC("license") = "GPL";
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: GPL-2.0
+
+#include "vmlinux.h"
+#include <bpf/bpf_helpers.h>
+#include <bpf/bpf_tracing.h>
+
+char _license[] SEC("license") = "GPL";
+
+SEC("struct_ops")
+__u32 BPF_PROG(ca_ssthresh, struct sock *sk)
+{
+	return 2;
+}
+
+SEC("struct_ops")
+void BPF_PROG(ca_cong_avoid, struct sock *sk, __u32 ack, __u32 acked)
+{
+}
+
+SEC("struct_ops")
+__u32 BPF_PROG(ca_undo_cwnd, struct sock *sk)
+{
+	return 2;
+}
+
+/* Both implementations register under the same name, as the kernel
+ * only allows a link to switch between algorithms of the same name. */
+SEC(".struct_ops.link")
+struct tcp_congestion_ops ca_1 = {
+	.ssthresh = (void *)ca_ssthresh,
+	.cong_avoid = (void *)ca_cong_avoid,
+	.undo_cwnd = (void *)ca_undo_cwnd,
+	.name = "libbpf_rs_test",
+};
+
+SEC(".struct_ops.link")
+struct tcp_congestion_ops ca_2 = {
+	.ssthresh = (void *)ca_ssthresh,
+	.cong_avoid = (void *)ca_cong_avoid,
+	.undo_cwnd = (void *)ca_undo_cwnd,
+	.name = "libbpf_rs_test",
+};
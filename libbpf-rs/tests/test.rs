@@ -5,42 +5,99 @@ use std::collections::HashSet;
 use std::env::current_exe;
 use std::ffi::c_int;
 use std::ffi::c_void;
+use std::ffi::CString;
 use std::fs;
+use std::fs::File;
 use std::hint;
 use std::io;
 use std::io::Read;
+use std::mem;
 use std::mem::size_of;
+use std::net::UdpSocket;
+use std::os::unix::ffi::OsStrExt as _;
+use std::os::unix::fs::PermissionsExt as _;
 use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::FromRawFd as _;
+use std::os::unix::io::OwnedFd;
 use std::path::Path;
 use std::path::PathBuf;
+use std::process;
 use std::ptr;
 use std::ptr::addr_of;
 use std::slice;
 use std::sync::mpsc::channel;
 use std::time::Duration;
 
+use libbpf_rs::__internal_skel::persist_links;
+use libbpf_rs::__internal_skel::ObjectSkeletonConfigBuilder;
+use libbpf_rs::bpffs;
+use libbpf_rs::bpffs::PinnedObject;
+use libbpf_rs::bpffs::PinnedObjectType;
+use libbpf_rs::btf::types;
+use libbpf_rs::btf::BtfKind;
+use libbpf_rs::btf::ModuleBtf;
+use libbpf_rs::btf::TypeId;
+use libbpf_rs::cgroup;
+use libbpf_rs::ifindex_from_name;
 use libbpf_rs::num_possible_cpus;
+use libbpf_rs::probes;
+use libbpf_rs::query;
+use libbpf_rs::raise_memlock_rlimit;
 use libbpf_rs::AsRawLibbpf;
+use libbpf_rs::BreakpointType;
+use libbpf_rs::Btf;
+use libbpf_rs::CgroupIterOrder;
+use libbpf_rs::CgroupSockAddrHook;
+use libbpf_rs::ErrorKind;
+use libbpf_rs::ExternKind;
+use libbpf_rs::ExternResolution;
 use libbpf_rs::Iter;
+use libbpf_rs::IterOpts;
+use libbpf_rs::KprobeMultiOpts;
+use libbpf_rs::Link;
+use libbpf_rs::LinkAnchor;
+use libbpf_rs::LinkPosition;
 use libbpf_rs::Linker;
+use libbpf_rs::LoadEventKind;
+use libbpf_rs::LoaderProgram;
 use libbpf_rs::Map;
 use libbpf_rs::MapFlags;
 use libbpf_rs::MapHandle;
 use libbpf_rs::MapInfo;
 use libbpf_rs::MapType;
+use libbpf_rs::NetfilterHook;
+use libbpf_rs::NetfilterOpts;
+use libbpf_rs::NetfilterProtocolFamily;
+use libbpf_rs::NetkitInfo;
+use libbpf_rs::NetkitOpts;
+use libbpf_rs::NetkitPolicy;
+use libbpf_rs::NetkitTarget;
 use libbpf_rs::Object;
 use libbpf_rs::ObjectBuilder;
 use libbpf_rs::OpenObject;
+use libbpf_rs::OpenProgram;
 use libbpf_rs::Program;
+use libbpf_rs::ProgramAttachType;
+use libbpf_rs::ProgramHandle;
 use libbpf_rs::ProgramInput;
 use libbpf_rs::ProgramType;
+use libbpf_rs::ProgramVariant;
+use libbpf_rs::ProgramVariants;
+use libbpf_rs::RunTimeStats;
+use libbpf_rs::StructOpsState;
+use libbpf_rs::Token;
+use libbpf_rs::TraceOpts;
 use libbpf_rs::TracepointOpts;
+use libbpf_rs::TypedMap;
 use libbpf_rs::UprobeOpts;
 use libbpf_rs::UsdtOpts;
 use libbpf_rs::UserRingBuffer;
+use libbpf_rs::VariantRequirement;
 use plain::Plain;
 use probe::probe;
 use scopeguard::defer;
+use tempfile::tempdir;
 use tempfile::NamedTempFile;
 
 fn get_test_object_path(filename: &str) -> PathBuf {
@@ -111,6 +168,152 @@ where
     value
 }
 
+/// Create a BPF instruction.
+fn insn(code: u32, dst: u8, src: u8, off: i16, imm: i32) -> libbpf_sys::bpf_insn {
+    let mut insn = libbpf_sys::bpf_insn {
+        code: code as u8,
+        off,
+        imm,
+        ..Default::default()
+    };
+    let () = insn.set_dst_reg(dst);
+    let () = insn.set_src_reg(src);
+    insn
+}
+
+/// Create a counter map for use with [`set_counter_insns`].
+fn create_counter_map() -> MapHandle {
+    let opts = libbpf_sys::bpf_map_create_opts {
+        sz: size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+        ..Default::default()
+    };
+    MapHandle::create(MapType::Array, Some("counter"), 4, 8, 1, &opts)
+        .expect("failed to create counter map")
+}
+
+/// Read and reset the value of a counter map.
+fn take_counter(counter: &MapHandle) -> u64 {
+    let key = 0u32.to_ne_bytes();
+    let value = counter
+        .lookup(&key, MapFlags::ANY)
+        .expect("failed to look up counter")
+        .expect("counter not found");
+    let () = counter
+        .update(&key, &0u64.to_ne_bytes(), MapFlags::ANY)
+        .expect("failed to reset counter");
+    u64::from_ne_bytes(value.try_into().unwrap())
+}
+
+/// Replace the instructions of `prog` with `prologue` followed by
+/// instructions adding the value of `r6` to `counter`, if `r6` is not
+/// negative, and returning zero.
+///
+/// The program to replace must not have any relocations.
+fn set_counter_insns(
+    prog: &mut OpenProgram,
+    prologue: &[libbpf_sys::bpf_insn],
+    counter: &MapHandle,
+) {
+    set_counter_insns_with_ret(prog, prologue, counter, 0)
+}
+
+/// Replace the instructions of `prog` as [`set_counter_insns`] does, but
+/// have the program return `ret`.
+fn set_counter_insns_with_ret(
+    prog: &mut OpenProgram,
+    prologue: &[libbpf_sys::bpf_insn],
+    counter: &MapHandle,
+    ret: i32,
+) {
+    let mut insns = prologue.to_vec();
+    insns.extend([
+        //   if r6 s< 0 goto +8
+        insn(
+            libbpf_sys::BPF_JMP | libbpf_sys::BPF_JSLT | libbpf_sys::BPF_K,
+            6,
+            0,
+            8,
+            0,
+        ),
+        //   *(u32 *)(r10 - 4) = 0
+        insn(
+            libbpf_sys::BPF_ST | libbpf_sys::BPF_MEM | libbpf_sys::BPF_W,
+            10,
+            0,
+            -4,
+            0,
+        ),
+        //   r1 = <counter>
+        insn(
+            libbpf_sys::BPF_LD | libbpf_sys::BPF_IMM | libbpf_sys::BPF_DW,
+            1,
+            libbpf_sys::BPF_PSEUDO_MAP_FD as _,
+            0,
+            counter.as_fd().as_raw_fd(),
+        ),
+        insn(0, 0, 0, 0, 0),
+        //   r2 = r10
+        insn(
+            libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_X,
+            2,
+            10,
+            0,
+            0,
+        ),
+        //   r2 += -4
+        insn(
+            libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_ADD | libbpf_sys::BPF_K,
+            2,
+            0,
+            0,
+            -4,
+        ),
+        //   call bpf_map_lookup_elem
+        insn(
+            libbpf_sys::BPF_JMP | libbpf_sys::BPF_CALL,
+            0,
+            0,
+            0,
+            libbpf_sys::BPF_FUNC_map_lookup_elem as _,
+        ),
+        //   if r0 == 0 goto +1
+        insn(
+            libbpf_sys::BPF_JMP | libbpf_sys::BPF_JEQ | libbpf_sys::BPF_K,
+            0,
+            0,
+            1,
+            0,
+        ),
+        //   lock *(u64 *)(r0 + 0) += r6
+        insn(
+            libbpf_sys::BPF_STX | libbpf_sys::BPF_ATOMIC | libbpf_sys::BPF_DW,
+            0,
+            6,
+            0,
+            libbpf_sys::BPF_ADD as _,
+        ),
+        //   r0 = <ret>
+        insn(
+            libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K,
+            0,
+            0,
+            0,
+            ret,
+        ),
+        //   exit
+        insn(libbpf_sys::BPF_JMP | libbpf_sys::BPF_EXIT, 0, 0, 0, 0),
+    ]);
+
+    let ret = unsafe {
+        libbpf_sys::bpf_program__set_insns(
+            prog.as_libbpf_object().as_ptr(),
+            insns.as_mut_ptr(),
+            insns.len() as _,
+        )
+    };
+    assert_eq!(ret, 0);
+}
+
 #[test]
 fn test_sudo_object_build_and_load() {
     bump_rlimit_mlock();
@@ -150,10 +353,13 @@ fn test_object_build_from_memory_empty_name() {
 #[test]
 fn test_sudo_object_load_invalid() {
     let empty_file = NamedTempFile::new().unwrap();
-    let _err = ObjectBuilder::default()
+    let err = ObjectBuilder::default()
         .debug(true)
         .open_file(empty_file.path())
         .unwrap_err();
+    let call = err.libbpf_call().unwrap();
+    assert_eq!(call.function(), "bpf_object__open_file");
+    assert_eq!(call.name(), empty_file.path().to_string_lossy());
 }
 
 #[test]
@@ -166,909 +372,1605 @@ fn test_object_name() {
     assert!(obj_name == "test name");
 }
 
+/// Check that the object name is retained after load and used for naming
+/// global data maps.
 #[test]
-fn test_sudo_object_maps() {
+fn test_sudo_object_name_loaded() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("runqslower.bpf.o");
-    obj.map("start").expect("failed to find map");
-    obj.map("events").expect("failed to find map");
-    assert!(obj.map("asdf").is_none());
+    let obj_path = get_test_object_path("runqslower.bpf.o");
+    let obj = ObjectBuilder::default()
+        .name("inst1")
+        .unwrap()
+        .open_file(obj_path)
+        .expect("failed to open object")
+        .load()
+        .expect("failed to load object");
+    assert_eq!(obj.name().unwrap(), "inst1");
+    assert!(obj.maps_iter().any(|map| map.name() == "inst1.rodata"));
 }
 
+/// Check that we can load an object using a custom BTF file for CO-RE
+/// relocations.
 #[test]
-fn test_sudo_object_maps_iter() {
+fn test_sudo_object_btf_custom_path() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("runqslower.bpf.o");
-    for map in obj.maps_iter() {
-        eprintln!("{}", map.name());
-    }
-    // This will include .rodata and .bss, so our expected count is 4, not 2
-    assert!(obj.maps_iter().count() == 4);
+    let obj_path = get_test_object_path("runqslower.bpf.o");
+    let mut builder = ObjectBuilder::default();
+    let () = builder
+        .btf_custom_path("/sys/kernel/btf/vmlinux")
+        .unwrap()
+        .open_file(&obj_path)
+        .expect("failed to open object")
+        .load()
+        .map(|_obj| ())
+        .expect("failed to load object");
+
+    let mut builder = ObjectBuilder::default();
+    let _err = builder
+        .btf_custom_path("/does-not-exist")
+        .unwrap()
+        .open_file(obj_path)
+        .expect("failed to open object")
+        .load()
+        .unwrap_err();
 }
 
+/// Check that we can supply values for kconfig externs, but only for
+/// properly named ones.
 #[test]
-fn test_sudo_object_map_key_value_size() {
-    bump_rlimit_mlock();
+fn test_object_kconfig() {
+    let obj_path = get_test_object_path("runqslower.bpf.o");
+    let mut builder = ObjectBuilder::default();
+    let _obj = builder
+        .kconfig([("CONFIG_BPF_SYSCALL", "y"), ("CONFIG_HZ", "250")])
+        .unwrap()
+        .open_file(obj_path)
+        .expect("failed to open object");
 
-    let mut obj = get_test_object("runqslower.bpf.o");
-    let start = obj.map_mut("start").expect("failed to find map");
+    let err = ObjectBuilder::default()
+        .kconfig([("HZ", "250")])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
 
-    assert!(start.lookup(&[1, 2, 3, 4, 5], MapFlags::empty()).is_err());
-    assert!(start.delete(&[1]).is_err());
-    assert!(start.lookup_and_delete(&[1, 2, 3, 4, 5]).is_err());
-    assert!(start
-        .update(&[1, 2, 3, 4, 5], &[1], MapFlags::empty())
-        .is_err());
+    let err = ObjectBuilder::default()
+        .kconfig([("CONFIG_HZ", "250\nCONFIG_FOO=y")])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
 }
 
+/// Check that we can access global variables of an open object by name.
 #[test]
-fn test_sudo_object_map_update_batch() {
-    bump_rlimit_mlock();
+fn test_object_globals() {
+    let mut open_obj = open_test_object("runqslower.bpf.o");
+    let mut globals = open_obj.globals().expect("failed to access globals");
+    assert_eq!(*globals.get::<u64>("min_us").unwrap(), 0);
+    *globals.get_mut::<u64>("min_us").unwrap() = 42;
+    *globals.get_mut::<i32>("targ_pid").unwrap() = 1337;
+    assert_eq!(*globals.get::<u64>("min_us").unwrap(), 42);
+    assert_eq!(*globals.get::<i32>("targ_pid").unwrap(), 1337);
+
+    let err = globals.get::<u32>("min_us").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = globals.get::<u64>("does_not_exist").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
 
-    let mut obj = get_test_object("runqslower.bpf.o");
-    let start = obj.map_mut("start").expect("failed to find map");
+/// Check that global variables can be accessed when the object name
+/// contains a dot, which ends up in the names of its internal maps.
+#[test]
+fn test_object_globals_dotted_name() {
+    let path = get_test_object_path("runqslower.bpf.o");
+    let mut open_obj = ObjectBuilder::default()
+        .name("run.qslower")
+        .unwrap()
+        .open_file(path)
+        .expect("failed to open object");
+    let mut globals = open_obj.globals().expect("failed to access globals");
+    *globals.get_mut::<u64>("min_us").unwrap() = 42;
+    assert_eq!(*globals.get::<u64>("min_us").unwrap(), 42);
+}
 
-    let key1 = 1u32.to_ne_bytes();
-    let key2 = 2u32.to_ne_bytes();
-    let key3 = 3u32.to_ne_bytes();
-    let key4 = 4u32.to_ne_bytes();
+/// Check that global variables set before load are visible afterwards and
+/// that `.rodata` can no longer be modified.
+#[test]
+fn test_sudo_object_globals() {
+    bump_rlimit_mlock();
 
-    let value1 = 369u64.to_ne_bytes();
-    let value2 = 258u64.to_ne_bytes();
-    let value3 = 147u64.to_ne_bytes();
-    let value4 = 159u64.to_ne_bytes();
+    let mut open_obj = open_test_object("runqslower.bpf.o");
+    *open_obj
+        .globals()
+        .unwrap()
+        .get_mut::<u64>("min_us")
+        .unwrap() = 42;
+    let mut obj = open_obj.load().expect("failed to load object");
+
+    let mut globals = obj.globals().expect("failed to access globals");
+    assert_eq!(*globals.get::<u64>("min_us").unwrap(), 42);
+    let err = globals.get_mut::<u64>("min_us").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+}
 
-    let batch_key1 = key1.into_iter().chain(key2).collect::<Vec<_>>();
-    let batch_value1 = value1.into_iter().chain(value2).collect::<Vec<_>>();
+/// Check that an object without `__kconfig` externs doesn't provide any
+/// kernel configuration values.
+#[test]
+fn test_sudo_object_kconfig() {
+    bump_rlimit_mlock();
 
-    let batch_key2 = key2.into_iter().chain(key3).chain(key4).collect::<Vec<_>>();
-    let batch_value2 = value2
-        .into_iter()
-        .chain(value3)
-        .chain(value4)
-        .collect::<Vec<_>>();
+    let obj = get_test_object("runqslower.bpf.o");
+    let kconfig = obj.kconfig().expect("failed to access kconfig");
+    let err = kconfig.get::<u32>("CONFIG_HZ").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
 
-    // Update batch with wrong key size
-    assert!(start
-        .update_batch(
-            &[1, 2, 3],
-            &batch_value1,
-            2,
-            MapFlags::ANY,
-            MapFlags::NO_EXIST
-        )
-        .is_err());
+/// Check that we can work with a map using typed keys and values.
+#[test]
+fn test_sudo_typed_map() {
+    bump_rlimit_mlock();
 
-    // Update batch with wrong value size
-    assert!(start
-        .update_batch(
-            &batch_key1,
-            &[1, 2, 3],
-            2,
-            MapFlags::ANY,
-            MapFlags::NO_EXIST
-        )
-        .is_err());
+    let opts = libbpf_sys::bpf_map_create_opts {
+        sz: size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+        ..Default::default()
+    };
+    let map = MapHandle::create(MapType::Hash, Some("typed_map"), 4, 8, 8, &opts).unwrap();
 
-    // Update batch with wrong count.
-    assert!(start
-        .update_batch(
-            &batch_key1,
-            &batch_value1,
-            1,
-            MapFlags::ANY,
-            MapFlags::NO_EXIST
-        )
-        .is_err());
+    let typed = unsafe { TypedMap::<u32, u64>::new(&map) }.unwrap();
+    let () = typed.update(&1, &42, MapFlags::ANY).unwrap();
+    let () = typed.update(&2, &1337, MapFlags::ANY).unwrap();
+    assert_eq!(typed.lookup(&1, MapFlags::ANY).unwrap(), Some(42));
+    assert_eq!(typed.lookup(&3, MapFlags::ANY).unwrap(), None);
 
-    // Update batch with 1 key.
-    assert!(start
-        .update_batch(&key1, &value1, 1, MapFlags::ANY, MapFlags::NO_EXIST)
-        .is_ok());
+    let mut keys = typed.keys().collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, vec![1, 2]);
 
-    // Update batch with multiple keys.
-    assert!(start
-        .update_batch(
-            &batch_key2,
-            &batch_value2,
-            3,
-            MapFlags::ANY,
-            MapFlags::NO_EXIST
-        )
-        .is_ok());
+    let () = typed.delete(&1).unwrap();
+    assert_eq!(typed.lookup(&1, MapFlags::ANY).unwrap(), None);
 
-    // Update batch with existing keys.
-    assert!(start
-        .update_batch(
-            &batch_key2,
-            &batch_value2,
-            3,
-            MapFlags::NO_EXIST,
-            MapFlags::NO_EXIST
-        )
-        .is_err());
+    let err = unsafe { TypedMap::<u64, u64>::new(&map) }.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
 }
 
+/// Check that we can access the BTF key and value types of a map.
 #[test]
-fn test_sudo_object_map_delete_batch() {
+fn test_sudo_map_btf_types() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("runqslower.bpf.o");
-    let start = obj.map_mut("start").expect("failed to find map");
-
-    let key1 = 1u32.to_ne_bytes();
-    assert!(start
-        .update(&key1, &9999u64.to_ne_bytes(), MapFlags::ANY)
-        .is_ok());
-    let key2 = 2u32.to_ne_bytes();
-    assert!(start
-        .update(&key2, &42u64.to_ne_bytes(), MapFlags::ANY)
-        .is_ok());
-    let key3 = 3u32.to_ne_bytes();
-    assert!(start
-        .update(&key3, &18u64.to_ne_bytes(), MapFlags::ANY)
-        .is_ok());
-    let key4 = 4u32.to_ne_bytes();
-    assert!(start
-        .update(&key4, &1337u64.to_ne_bytes(), MapFlags::ANY)
-        .is_ok());
+    let obj = get_test_object("runqslower.bpf.o");
+    let btf = obj.btf().unwrap().unwrap();
 
-    // Delete 1 incomplete key.
-    assert!(start
-        .delete_batch(&[0, 0, 1], 1, MapFlags::empty(), MapFlags::empty())
-        .is_err());
-    // Delete keys with wrong count.
-    assert!(start
-        .delete_batch(&key4, 2, MapFlags::empty(), MapFlags::empty())
-        .is_err());
-    // Delete 1 key successfully.
-    assert!(start
-        .delete_batch(&key4, 1, MapFlags::empty(), MapFlags::empty())
-        .is_ok());
-    // Delete remaining 3 keys.
-    let keys = key1.into_iter().chain(key2).chain(key3).collect::<Vec<_>>();
-    assert!(start
-        .delete_batch(&keys, 3, MapFlags::empty(), MapFlags::empty())
-        .is_ok());
-    // Map should be empty now.
-    assert!(start.keys().collect::<Vec<_>>().is_empty())
+    let start = obj.map("start").expect("failed to find map");
+    let key = start.btf_key_type(&btf).unwrap();
+    assert_eq!(key.kind(), BtfKind::Int);
+    assert_eq!(btf.type_size(key.type_id()).unwrap(), 4);
+    let value = start.btf_value_type(&btf).unwrap();
+    assert_eq!(value.kind(), BtfKind::Int);
+    assert_eq!(btf.type_size(value.type_id()).unwrap(), 8);
+
+    // The map is declared using sizes only.
+    let events = obj.map("events").expect("failed to find map");
+    assert_eq!(events.btf_key_type_id(), None);
+    assert!(events.btf_value_type(&btf).is_none());
 }
 
-/// Test whether `MapInfo` works properly
+/// Check that we can load custom BTF into the kernel and create a map
+/// using it.
 #[test]
-pub fn test_sudo_map_info() {
-    #[allow(clippy::needless_update)]
+fn test_sudo_btf_load_into_kernel() {
+    bump_rlimit_mlock();
+
+    let mut btf = Btf::new_empty().unwrap();
+    let int = btf.add_int("int", 4, types::IntEncoding::Signed).unwrap();
+    let long = btf.add_int("long", 8, types::IntEncoding::Signed).unwrap();
+    let btf_fd = btf.load_into_kernel().expect("failed to load BTF");
+
     let opts = libbpf_sys::bpf_map_create_opts {
         sz: size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
-        map_flags: libbpf_sys::BPF_ANY,
-        btf_fd: 0,
-        btf_key_type_id: 0,
-        btf_value_type_id: 0,
-        btf_vmlinux_value_type_id: 0,
-        inner_map_fd: 0,
-        map_extra: 0,
-        numa_node: 0,
-        map_ifindex: 0,
-        // bpf_map_create_opts might have padding fields on some platform
+        btf_fd: btf_fd.as_raw_fd() as _,
+        btf_key_type_id: int.into(),
+        btf_value_type_id: long.into(),
         ..Default::default()
     };
-
-    let map = MapHandle::create(MapType::Hash, Some("simple_map"), 8, 64, 1024, &opts).unwrap();
-    let map_info = MapInfo::new(map.as_fd()).unwrap();
-    let name_received = map_info.name().unwrap();
-    assert_eq!(name_received, "simple_map");
-    assert_eq!(map_info.map_type(), MapType::Hash);
-    assert_eq!(map_info.flags() & MapFlags::ANY, MapFlags::ANY);
-
-    let map_info = &map_info.info;
-    assert_eq!(map_info.key_size, 8);
-    assert_eq!(map_info.value_size, 64);
-    assert_eq!(map_info.max_entries, 1024);
-    assert_eq!(map_info.btf_id, 0);
-    assert_eq!(map_info.btf_key_type_id, 0);
-    assert_eq!(map_info.btf_value_type_id, 0);
-    assert_eq!(map_info.btf_vmlinux_value_type_id, 0);
-    assert_eq!(map_info.map_extra, 0);
-    assert_eq!(map_info.ifindex, 0);
+    let map = MapHandle::create(MapType::Hash, Some("btf_map"), 4, 8, 16, &opts).unwrap();
+    let info = map.info().unwrap().info;
+    assert_ne!(info.btf_id, 0);
+    assert_eq!(info.btf_key_type_id, u32::from(int));
+    assert_eq!(info.btf_value_type_id, u32::from(long));
+
+    // A pointer to a type that doesn't exist is rejected, along with an
+    // explanation.
+    let mut btf = Btf::new_empty().unwrap();
+    let _ptr = btf.add_ptr(TypeId::from(42)).unwrap();
+    let err = btf.load_into_kernel().unwrap_err();
+    assert!(
+        format!("{err:#}").contains("failed to load BTF into kernel"),
+        "{err:#}"
+    );
 }
 
+/// Check that we can list the BTF of loaded kernel modules and parse it.
 #[test]
-fn test_sudo_object_percpu_lookup() {
-    bump_rlimit_mlock();
+fn test_sudo_module_btfs() {
+    let vmlinux = Btf::from_vmlinux().unwrap();
+    let modules = ModuleBtf::list().expect("failed to list module BTFs");
+    assert!(modules.iter().all(|module| module.name() != "vmlinux"));
+
+    if let Some(module) = modules.first() {
+        let btf = module.btf(&vmlinux).unwrap();
+        assert!(btf.len() > vmlinux.len());
+        let by_name = Btf::from_kernel_module(module.name(), &vmlinux).unwrap();
+        assert_eq!(by_name.len(), btf.len());
+    }
+}
 
-    let mut obj = get_test_object("percpu_map.bpf.o");
-    let map = obj.map_mut("percpu_map").expect("failed to find map");
+/// Check that we can parse the `.BTF.ext` section of an object file.
+#[test]
+fn test_btf_ext() {
+    let path = get_test_object_path("runqslower.bpf.o");
+    let (btf, ext) = Btf::from_path_with_ext(path).expect("failed to parse BTF");
+    let ext = ext.expect("object has no .BTF.ext section");
+
+    let section = ext
+        .section("tp_btf/sched_switch")
+        .expect("failed to find section");
+    let func = section.func_info.first().expect("no function information");
+    assert_eq!(func.insn_idx, 0);
+    let func = btf.type_by_id::<types::Func<'_>>(func.type_id).unwrap();
+    assert_eq!(
+        func.name().unwrap().to_str().unwrap(),
+        "handle__sched_switch"
+    );
 
-    let res = map
-        .lookup_percpu(&(0_u32).to_ne_bytes(), MapFlags::ANY)
-        .expect("failed to lookup")
-        .expect("failed to find value for key");
+    assert!(!section.line_info.is_empty());
+    assert!(section
+        .line_info
+        .iter()
+        .all(|info| info.file_name.ends_with("runqslower.bpf.c")));
+    // The program reads task fields in CO-RE fashion.
+    assert!(!section.core_relos.is_empty());
 
-    assert_eq!(
-        res.len(),
-        num_possible_cpus().expect("must be one value per cpu")
-    );
-    assert_eq!(res[0].len(), size_of::<u32>());
+    let prog = ext
+        .program("handle__sched_switch")
+        .expect("failed to find program");
+    assert_eq!(prog.section, "tp_btf/sched_switch");
+    assert_eq!(prog.line_info, section.line_info);
+    assert_eq!(prog.core_relos, section.core_relos);
 }
 
+/// Check that we can open an object with a BPF token path set.
 #[test]
-fn test_sudo_object_percpu_invalid_lookup_fn() {
-    bump_rlimit_mlock();
-
-    let mut obj = get_test_object("percpu_map.bpf.o");
-    let map = obj.map_mut("percpu_map").expect("failed to find map");
+fn test_object_bpf_token_path() {
+    let obj_path = get_test_object_path("runqslower.bpf.o");
+    let mut builder = ObjectBuilder::default();
+    let _obj = builder
+        .bpf_token_path("/sys/fs/bpf")
+        .unwrap()
+        .open_file(obj_path)
+        .expect("failed to open object");
+}
 
-    assert!(map.lookup(&(0_u32).to_ne_bytes(), MapFlags::ANY).is_err());
+/// Check that we fail to create a BPF token from something that is not a
+/// BPF file system.
+#[test]
+fn test_sudo_token_create_invalid() {
+    let dir = File::open("/proc").expect("failed to open /proc");
+    let _err = Token::create(dir.as_fd()).unwrap_err();
 }
 
+/// Check that we can generate a loader program for an object.
 #[test]
-fn test_sudo_object_percpu_update() {
+fn test_sudo_object_gen_loader() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("percpu_map.bpf.o");
-    let map = obj.map_mut("percpu_map").expect("failed to find map");
-    let key = (0_u32).to_ne_bytes();
+    let open_obj = open_test_object("runqslower.bpf.o");
+    let loader = open_obj
+        .gen_loader()
+        .expect("failed to generate loader program");
+    assert!(!loader.insns().is_empty());
+    // Each instruction is eight bytes in size.
+    assert_eq!(loader.insns().len() % 8, 0);
+    assert!(!loader.data().is_empty());
+    assert!(loader.maps().iter().any(|map| map == "start"));
+    assert!(loader
+        .progs()
+        .iter()
+        .any(|prog| prog == "handle__sched_wakeup"));
+
+    let obj = loader.run().expect("failed to run loader program");
+    let map = obj.map("start").expect("failed to find map");
+    assert_eq!(map.name(), "start");
+    assert_eq!(map.map_type(), MapType::Hash);
+
+    let fd = obj
+        .prog_fd("handle__sched_wakeup")
+        .expect("failed to find program");
+    let info = query::ProgramInfo::load_from_fd(fd, &query::ProgInfoQueryOptions::default())
+        .expect("failed to query program info");
+    assert_eq!(info.name.to_str().unwrap(), "handle__sched_w");
+    assert!(obj.prog_fd("nonexistent").is_none());
+
+    // The generated parts suffice for loading the object anew.
+    let loader = LoaderProgram::from_parts(
+        loader.insns().to_vec(),
+        loader.data().to_vec(),
+        loader.maps().to_vec(),
+        loader.progs().to_vec(),
+    );
+    let _obj = loader.run().expect("failed to run loader program");
+}
 
-    let mut vals: Vec<Vec<u8>> = Vec::new();
-    for i in 0..num_possible_cpus().unwrap() {
-        vals.push((i as u32).to_ne_bytes().to_vec());
+/// Check that we report the externs an object references.
+#[test]
+fn test_object_externs() {
+    let open_obj = open_test_object("runqslower.bpf.o");
+    assert!(open_obj.externs().unwrap().is_empty());
+
+    // The big endian object is the same as the native one, but compiled
+    // for `bpfeb`.
+    for file in ["externs.bpf.o", "externs.bpfeb.o"] {
+        let open_obj = open_test_object(file);
+        let externs = open_obj
+            .externs()
+            .unwrap()
+            .iter()
+            .map(|ext| (ext.name(), ext.kind(), ext.is_weak()))
+            .collect::<HashSet<_>>();
+        let expected = HashSet::from([
+            ("CONFIG_HZ", ExternKind::Kconfig, false),
+            ("CONFIG_LIBBPF_RS_MISSING", ExternKind::Kconfig, true),
+            ("bpf_prog_active", ExternKind::Ksym, false),
+            ("__start_BTF", ExternKind::Ksym, false),
+            ("libbpf_rs_missing_sym", ExternKind::Ksym, true),
+            ("bpf_rcu_read_lock", ExternKind::Kfunc, false),
+            ("libbpf_rs_missing_kfunc", ExternKind::Kfunc, true),
+        ]);
+        assert_eq!(externs, expected, "{file}");
+        assert!(open_obj
+            .externs()
+            .unwrap()
+            .iter()
+            .all(|ext| ext.resolution().is_none()));
     }
 
-    map.update_percpu(&key, &vals, MapFlags::ANY)
-        .expect("failed to update map");
-
-    let res = map
-        .lookup_percpu(&key, MapFlags::ANY)
-        .expect("failed to lookup")
-        .expect("failed to find value for key");
+    let open_obj = open_test_object("externs.bpf.o");
+    let vmlinux = Btf::from_vmlinux().unwrap();
+    let find = |name| {
+        open_obj
+            .externs()
+            .unwrap()
+            .iter()
+            .find(|ext| ext.name() == name)
+            .unwrap()
+    };
+    assert!(find("bpf_rcu_read_lock").kernel_btf_id(&vmlinux).is_some());
+    assert!(find("bpf_prog_active").kernel_btf_id(&vmlinux).is_some());
+    assert_eq!(find("__start_BTF").kernel_btf_id(&vmlinux), None);
+    assert_eq!(
+        find("libbpf_rs_missing_kfunc").kernel_btf_id(&vmlinux),
+        None
+    );
+}
 
-    assert_eq!(vals, res);
+/// Check that an object's externs are only collected once requested.
+#[test]
+fn test_object_externs_lazy() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("externs.bpf.o");
+    let _bytes = fs::copy(get_test_object_path("externs.bpf.o"), &path).unwrap();
+
+    let open_obj = ObjectBuilder::default()
+        .open_file(&path)
+        .expect("failed to open object");
+    let () = fs::remove_file(&path).unwrap();
+    let err = open_obj.externs().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
 }
 
+/// Check that we report what an object's externs resolved to when
+/// loading it.
 #[test]
-fn test_sudo_object_percpu_invalid_update_fn() {
+fn test_sudo_object_extern_resolution() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("percpu_map.bpf.o");
-    let map = obj.map_mut("percpu_map").expect("failed to find map");
-    let key = (0_u32).to_ne_bytes();
+    let mut open_obj = ObjectBuilder::default()
+        .kconfig([("CONFIG_HZ", "250")])
+        .unwrap()
+        .open_file(get_test_object_path("externs.bpf.o"))
+        .expect("failed to open object");
+    // Referencing typed kernel variables requires the kernel to list them
+    // in kallsyms, which is not a given. libbpf resolves externs for all
+    // programs, though.
+    let () = open_obj
+        .prog_mut("handle__typed_ksym")
+        .unwrap()
+        .set_autoload(false)
+        .unwrap();
+    let obj = open_obj.load().expect("failed to load object");
+    assert!(obj
+        .externs()
+        .unwrap()
+        .iter()
+        .all(|ext| ext.resolution().is_none()));
+
+    let externs = obj.resolve_externs().expect("failed to resolve externs");
+    let resolution = |name| {
+        externs
+            .iter()
+            .find(|ext| ext.name() == name)
+            .unwrap()
+            .resolution()
+            .cloned()
+    };
 
-    let val = (1_u32).to_ne_bytes().to_vec();
+    let vmlinux = Btf::from_vmlinux().unwrap();
+    let rcu_read_lock = vmlinux
+        .type_by_name::<types::Func<'_>>("bpf_rcu_read_lock")
+        .unwrap();
+    assert_eq!(
+        resolution("bpf_rcu_read_lock"),
+        Some(ExternResolution::BtfId {
+            module: None,
+            type_id: rcu_read_lock.type_id(),
+        })
+    );
+    assert!(matches!(
+        resolution("bpf_prog_active"),
+        Some(ExternResolution::BtfId { module: None, .. })
+    ));
+    assert!(matches!(
+        resolution("__start_BTF"),
+        Some(ExternResolution::Address(_))
+    ));
+    assert_eq!(
+        resolution("libbpf_rs_missing_sym"),
+        Some(ExternResolution::Unresolved)
+    );
+    assert_eq!(
+        resolution("libbpf_rs_missing_kfunc"),
+        Some(ExternResolution::Unresolved)
+    );
+    assert_eq!(resolution("CONFIG_HZ"), None);
 
-    assert!(map.update(&key, &val, MapFlags::ANY).is_err());
+    let kconfig = obj.kconfig().unwrap();
+    assert_eq!(*kconfig.get::<u32>("CONFIG_HZ").unwrap(), 250);
+    assert_eq!(*kconfig.get::<u8>("CONFIG_LIBBPF_RS_MISSING").unwrap(), 0);
 }
 
+/// Check that we can override the kernel version of an open object.
 #[test]
-fn test_sudo_object_percpu_lookup_update() {
-    bump_rlimit_mlock();
+fn test_object_kversion() {
+    let mut open_obj = open_test_object("runqslower.bpf.o");
+    // KERNEL_VERSION(4, 19, 0)
+    let version = (4 << 16) + (19 << 8);
+    open_obj.set_kversion(version).unwrap();
+    assert_eq!(open_obj.kversion(), version);
+}
 
-    let mut obj = get_test_object("percpu_map.bpf.o");
-    let map = obj.map_mut("percpu_map").expect("failed to find map");
-    let key = (0_u32).to_ne_bytes();
+/// Check that we can configure whether programs get attached automatically.
+#[test]
+fn test_object_prog_autoattach() {
+    let mut open_obj = open_test_object("runqslower.bpf.o");
+    let prog = open_obj
+        .prog_mut("handle__sched_wakeup")
+        .expect("failed to find program");
+    assert!(prog.autoattach());
 
-    let mut res = map
-        .lookup_percpu(&key, MapFlags::ANY)
-        .expect("failed to lookup")
-        .expect("failed to find value for key");
+    let () = prog.set_autoattach(false);
+    assert!(!prog.autoattach());
+}
 
-    for e in res.iter_mut() {
-        e[0] &= 0xf0;
-    }
+/// Check that we select the first usable variant of a group of programs.
+#[test]
+fn test_sudo_object_program_variants() {
+    bump_rlimit_mlock();
 
-    map.update_percpu(&key, &res, MapFlags::ANY)
-        .expect("failed to update after first lookup");
+    let mut open_obj = open_test_object("runqslower.bpf.o");
+    let selected = ProgramVariants::new()
+        .group(
+            "wakeup",
+            [
+                ProgramVariant::new("handle__sched_wakeup")
+                    .requires(VariantRequirement::Check(|| Ok(false))),
+                ProgramVariant::new("handle__sched_wakeup_new"),
+            ],
+        )
+        .select(&mut open_obj)
+        .unwrap();
+    assert_eq!(selected, ["handle__sched_wakeup_new"]);
 
-    let res2 = map
-        .lookup_percpu(&key, MapFlags::ANY)
-        .expect("failed to lookup")
-        .expect("failed to find value for key");
+    let obj = open_obj.load().expect("failed to load object");
+    assert!(!obj.prog("handle__sched_wakeup").unwrap().autoload());
+    assert!(obj.prog("handle__sched_wakeup_new").unwrap().autoload());
+}
 
-    assert_eq!(res, res2);
+/// Check that variant selection fails for unknown programs and groups
+/// without usable variants, without modifying the object.
+#[test]
+fn test_object_program_variants_errors() {
+    let mut open_obj = open_test_object("runqslower.bpf.o");
+    let err = ProgramVariants::new()
+        .group("missing", [ProgramVariant::new("does_not_exist")])
+        .select(&mut open_obj)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    let err = ProgramVariants::new()
+        .group(
+            "twice",
+            [
+                ProgramVariant::new("handle__sched_wakeup"),
+                ProgramVariant::new("handle__sched_wakeup"),
+            ],
+        )
+        .select(&mut open_obj)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
 }
 
+/// Check that attaching a skeleton leaves programs alone that already
+/// have a link in their skeleton slot.
 #[test]
-fn test_sudo_object_map_empty_lookup() {
+fn test_sudo_skeleton_attach_keeps_links() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("runqslower.bpf.o");
-    let start = obj.map("start").expect("failed to find map");
+    let data = fs::read(get_test_object_path("tracepoint.bpf.o")).unwrap();
+    let mut builder = ObjectSkeletonConfigBuilder::new(&data);
+    let _builder = builder.name("tracepoint_bpf").prog("handle__tracepoint");
+    let mut config = builder.build().unwrap();
 
-    assert!(start
-        .lookup(&[1, 2, 3, 4], MapFlags::empty())
-        .expect("err in map lookup")
-        .is_none());
+    let ret = unsafe { libbpf_sys::bpf_object__open_skeleton(config.get(), ptr::null()) };
+    assert_eq!(ret, 0);
+    let mut open_obj = unsafe { OpenObject::from_ptr(config.object_ptr()) }.unwrap();
+    for prog in open_obj.progs_iter_mut() {
+        let () = prog.set_autoattach(false);
+    }
+    let () = open_obj
+        .prog_mut("handle__tracepoint")
+        .unwrap()
+        .set_autoattach(true);
+    let ret = unsafe { libbpf_sys::bpf_object__load_skeleton(config.get()) };
+    assert_eq!(ret, 0);
+    let mut obj = unsafe { Object::from_ptr(open_obj.take_ptr()) }.unwrap();
+
+    let link = obj
+        .prog_mut("handle__tracepoint")
+        .unwrap()
+        .attach()
+        .unwrap();
+    let link_ptr = link.as_libbpf_object().as_ptr();
+    let () = config.set_prog_link_ptr(0, link_ptr).unwrap();
+    let ret = unsafe { libbpf_sys::bpf_object__attach_skeleton(config.get()) };
+    assert_eq!(ret, 0);
+    assert_eq!(config.prog_link_ptr(0).unwrap(), link_ptr);
+
+    // With the link gone and the slot cleared, libbpf attaches the
+    // program anew.
+    drop(link);
+    let () = config.set_prog_link_ptr(0, ptr::null_mut()).unwrap();
+    let ret = unsafe { libbpf_sys::bpf_object__attach_skeleton(config.get()) };
+    assert_eq!(ret, 0);
+    let ptr = config.prog_link_ptr(0).unwrap();
+    assert!(!ptr.is_null());
+    assert_ne!(ptr, link_ptr);
+    let _link = unsafe { Link::from_ptr(ptr::NonNull::new(ptr).unwrap()) };
 }
 
-/// Test CRUD operations on map of type queue.
+/// Check that persisting skeleton links pins them and keeps them in place
+/// once dropped, and that a failure to pin leaves all links untouched.
 #[test]
-fn test_sudo_object_map_queue_crud() {
+fn test_sudo_skeleton_persist_links() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("tracepoint.bpf.o");
-    let queue = obj.map("queue").expect("failed to find queue map");
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let mut wakeup = obj
+        .prog_mut("handle__sched_wakeup")
+        .unwrap()
+        .attach()
+        .unwrap();
+    let mut switch = obj
+        .prog_mut("handle__sched_switch")
+        .unwrap()
+        .attach()
+        .unwrap();
+    let link_id = query::LinkInfo::load_from_fd(wakeup.as_fd()).unwrap().id;
 
-    let key: [u8; 0] = [];
-    let value1 = 42u32.to_ne_bytes();
-    let value2 = 43u32.to_ne_bytes();
+    let dir = Path::new("/sys/fs/bpf/test_skeleton_persist_links");
+    let () = fs::create_dir(dir).unwrap();
+    defer! {
+        let _ = fs::remove_dir_all(dir);
+    }
 
-    // Test queue, FIFO expected
-    queue
-        .update(&key, &value1, MapFlags::ANY)
-        .expect("failed to update in queue");
-    queue
-        .update(&key, &value2, MapFlags::ANY)
-        .expect("failed to update in queue");
+    // A conflicting entry makes pinning `handle__sched_switch` fail, in
+    // which case the already pinned link gets unpinned again.
+    let () = fs::create_dir(dir.join("handle__sched_switch")).unwrap();
+    let err = persist_links(
+        dir,
+        &mut [
+            ("handle__sched_wakeup", Some(&mut wakeup)),
+            ("handle__sched_switch", Some(&mut switch)),
+        ],
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    assert_eq!(wakeup.pin_path(), None);
+    assert!(!dir.join("handle__sched_wakeup").exists());
+
+    let () = fs::remove_dir(dir.join("handle__sched_switch")).unwrap();
+    let () = persist_links(
+        dir,
+        &mut [
+            ("handle__sched_wakeup", Some(&mut wakeup)),
+            ("handle__sched_switch", Some(&mut switch)),
+            ("unattached", None),
+        ],
+    )
+    .unwrap();
+    drop(wakeup);
+    drop(switch);
+    drop(obj);
+
+    let link = Link::open(dir.join("handle__sched_wakeup")).unwrap();
+    let info = query::LinkInfo::load_from_fd(link.as_fd()).unwrap();
+    assert_eq!(info.id, link_id);
+    let _link = Link::open(dir.join("handle__sched_switch")).unwrap();
+}
 
-    let mut val = queue
-        .lookup(&key, MapFlags::ANY)
-        .expect("failed to peek the queue")
-        .expect("failed to retrieve value");
-    assert_eq!(val.len(), 4);
-    assert_eq!(&val, &value1);
+/// Check that we can configure map offloading on an open object.
+#[test]
+fn test_object_map_ifindex() {
+    let mut open_obj = open_test_object("runqslower.bpf.o");
+    let map = open_obj.map_mut("start").expect("failed to find map");
+    assert_eq!(map.map_ifindex(), 0);
 
-    val = queue
-        .lookup_and_delete(&key)
-        .expect("failed to pop from queue")
-        .expect("failed to retrieve value");
-    assert_eq!(val.len(), 4);
-    assert_eq!(&val, &value1);
+    let () = map.set_map_ifindex(1);
+    assert_eq!(map.map_ifindex(), 1);
+}
 
-    val = queue
-        .lookup_and_delete(&key)
-        .expect("failed to pop from queue")
-        .expect("failed to retrieve value");
-    assert_eq!(val.len(), 4);
-    assert_eq!(&val, &value2);
+#[test]
+fn test_sudo_object_maps() {
+    bump_rlimit_mlock();
 
-    assert!(queue
-        .lookup_and_delete(&key)
-        .expect("failed to pop from queue")
-        .is_none());
+    let obj = get_test_object("runqslower.bpf.o");
+    obj.map("start").expect("failed to find map");
+    obj.map("events").expect("failed to find map");
+    assert!(obj.map("asdf").is_none());
 }
 
-/// Test CRUD operations on map of type bloomfilter.
+/// Check that we can use a map mutably while working with an object's
+/// programs.
 #[test]
-fn test_sudo_object_map_bloom_filter_crud() {
+fn test_sudo_object_parts() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("tracepoint.bpf.o");
-    let bloom_filter = obj
-        .map("bloom_filter")
-        .expect("failed to find bloom_filter map");
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let (mut maps, mut progs) = obj.parts();
+    let start = maps.get_mut("start").expect("failed to find map");
+    for (i, prog) in progs.iter_mut().enumerate() {
+        let _link = prog.attach().expect("failed to attach prog");
+        let key = u32::try_from(i).unwrap().to_ne_bytes();
+        start
+            .update(&key, &[0; 8], MapFlags::ANY)
+            .expect("failed to update map");
+    }
+    assert!(progs.get("handle__sched_wakeup").is_some());
+    assert!(maps.get("asdf").is_none());
+}
 
-    let key: [u8; 0] = [];
-    let value1 = 1337u32.to_ne_bytes();
-    let value2 = 2674u32.to_ne_bytes();
+/// Check that loading an object with a report provides information about
+/// map creation and program loading.
+#[test]
+fn test_sudo_object_load_with_report() {
+    bump_rlimit_mlock();
 
-    bloom_filter
-        .update(&key, &value1, MapFlags::ANY)
-        .expect("failed to add entry value1 to bloom filter");
+    let open_obj = open_test_object("runqslower.bpf.o");
+    let (result, report) = open_obj.load_with_report();
+    let _obj = result.expect("failed to load object");
+
+    assert!(report.events.iter().any(|event| event.kind
+        == LoadEventKind::MapCreated {
+            map: "start".to_string()
+        }));
+    assert!(report
+        .programs
+        .iter()
+        .any(|prog| prog.name == "handle__sched_wakeup"));
+}
 
-    bloom_filter
-        .update(&key, &value2, MapFlags::ANY)
-        .expect("failed to add entry value2 in bloom filter");
+/// Check that a failure to load an object because of the verifier
+/// rejecting a program identifies the program and carries the verifier
+/// log.
+#[test]
+fn test_sudo_object_load_verifier_log() {
+    bump_rlimit_mlock();
 
-    // Non empty keys should result in an error
-    bloom_filter
-        .update(&value1, &value1, MapFlags::ANY)
-        .expect_err("Non empty key should return an error");
+    let mut open_obj = open_test_object("xdp.bpf.o");
+    let prog = open_obj
+        .prog_mut("xdp_filter")
+        .expect("failed to find program");
 
-    for inserted_value in [value1, value2] {
-        let val = bloom_filter
-            .lookup_bloom_filter(&inserted_value)
-            .expect("failed retrieve item from bloom filter");
+    // `r0 = r2; exit`, reading the uninitialized register `r2`.
+    let mut mov = libbpf_sys::bpf_insn {
+        code: (libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_X) as u8,
+        ..Default::default()
+    };
+    let () = mov.set_dst_reg(0);
+    let () = mov.set_src_reg(2);
+    let exit = libbpf_sys::bpf_insn {
+        code: (libbpf_sys::BPF_JMP | libbpf_sys::BPF_EXIT) as u8,
+        ..Default::default()
+    };
+    let mut insns = [mov, exit];
+    let ret = unsafe {
+        libbpf_sys::bpf_program__set_insns(
+            prog.as_libbpf_object().as_ptr(),
+            insns.as_mut_ptr(),
+            insns.len() as _,
+        )
+    };
+    assert_eq!(ret, 0);
+
+    let err = open_obj.load().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::VerifierRejected);
+    assert!(err.to_string().contains("xdp_filter"), "{err}");
+    assert_eq!(err.libbpf_call().unwrap().function(), "bpf_object__load");
+    let log = err.verifier_log().expect("no verifier log present");
+    assert!(log.contains("R2 !read_ok"), "{log}");
+}
 
-        assert!(val);
+#[test]
+fn test_sudo_object_maps_iter() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("runqslower.bpf.o");
+    for map in obj.maps_iter() {
+        eprintln!("{}", map.name());
     }
-    // Test non existing element
-    let enoent_found = bloom_filter
-        .lookup_bloom_filter(&[1, 2, 3, 4])
-        .expect("failed retrieve item from bloom filter");
+    // This will include .rodata and .bss, so our expected count is 4, not 2
+    assert!(obj.maps_iter().count() == 4);
+}
 
-    assert!(!enoent_found);
+#[test]
+fn test_sudo_object_map_key_value_size() {
+    bump_rlimit_mlock();
 
-    // Calling lookup should result in an error
-    bloom_filter
-        .lookup(&[1, 2, 3, 4], MapFlags::ANY)
-        .expect_err("lookup should fail since we should use lookup_bloom_filter");
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let start = obj.map_mut("start").expect("failed to find map");
 
-    // Deleting should not be possible
-    bloom_filter
-        .lookup_and_delete(&key)
-        .expect_err("Expect delete to fail");
+    assert!(start.lookup(&[1, 2, 3, 4, 5], MapFlags::empty()).is_err());
+    assert!(start.delete(&[1]).is_err());
+    assert!(start.lookup_and_delete(&[1, 2, 3, 4, 5]).is_err());
+    assert!(start
+        .update(&[1, 2, 3, 4, 5], &[1], MapFlags::empty())
+        .is_err());
 }
 
-/// Test CRUD operations on map of type stack.
 #[test]
-fn test_sudo_object_map_stack_crud() {
+fn test_sudo_object_map_update_batch() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("tracepoint.bpf.o");
-    let stack = obj.map("stack").expect("failed to find stack map");
-
-    let key: [u8; 0] = [];
-    let value1 = 1337u32.to_ne_bytes();
-    let value2 = 2674u32.to_ne_bytes();
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let start = obj.map_mut("start").expect("failed to find map");
 
-    stack
-        .update(&key, &value1, MapFlags::ANY)
-        .expect("failed to update in stack");
-    stack
-        .update(&key, &value2, MapFlags::ANY)
-        .expect("failed to update in stack");
+    let key1 = 1u32.to_ne_bytes();
+    let key2 = 2u32.to_ne_bytes();
+    let key3 = 3u32.to_ne_bytes();
+    let key4 = 4u32.to_ne_bytes();
 
-    let mut val = stack
-        .lookup(&key, MapFlags::ANY)
-        .expect("failed to pop from stack")
-        .expect("failed to retrieve value");
-
-    assert_eq!(val.len(), 4);
-    assert_eq!(&val, &value2);
+    let value1 = 369u64.to_ne_bytes();
+    let value2 = 258u64.to_ne_bytes();
+    let value3 = 147u64.to_ne_bytes();
+    let value4 = 159u64.to_ne_bytes();
 
-    val = stack
-        .lookup_and_delete(&key)
-        .expect("failed to pop from stack")
-        .expect("failed to retrieve value");
-    assert_eq!(val.len(), 4);
-    assert_eq!(&val, &value2);
+    let batch_key1 = key1.into_iter().chain(key2).collect::<Vec<_>>();
+    let batch_value1 = value1.into_iter().chain(value2).collect::<Vec<_>>();
 
-    val = stack
-        .lookup_and_delete(&key)
-        .expect("failed to pop from stack")
-        .expect("failed to retrieve value");
-    assert_eq!(val.len(), 4);
-    assert_eq!(&val, &value1);
+    let batch_key2 = key2.into_iter().chain(key3).chain(key4).collect::<Vec<_>>();
+    let batch_value2 = value2
+        .into_iter()
+        .chain(value3)
+        .chain(value4)
+        .collect::<Vec<_>>();
 
-    assert!(stack
-        .lookup_and_delete(&key)
-        .expect("failed to pop from stack")
-        .is_none());
-}
+    // Update batch with wrong key size
+    assert!(start
+        .update_batch(
+            &[1, 2, 3],
+            &batch_value1,
+            2,
+            MapFlags::ANY,
+            MapFlags::NO_EXIST
+        )
+        .is_err());
 
-#[test]
-fn test_sudo_object_map_mutation() {
-    bump_rlimit_mlock();
+    // Update batch with wrong value size
+    assert!(start
+        .update_batch(
+            &batch_key1,
+            &[1, 2, 3],
+            2,
+            MapFlags::ANY,
+            MapFlags::NO_EXIST
+        )
+        .is_err());
 
-    let mut obj = get_test_object("runqslower.bpf.o");
-    let start = obj.map_mut("start").expect("failed to find map");
+    // Update batch with wrong count.
+    assert!(start
+        .update_batch(
+            &batch_key1,
+            &batch_value1,
+            1,
+            MapFlags::ANY,
+            MapFlags::NO_EXIST
+        )
+        .is_err());
 
-    start
-        .update(&[1, 2, 3, 4], &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::empty())
-        .expect("failed to write");
-    let val = start
-        .lookup(&[1, 2, 3, 4], MapFlags::empty())
-        .expect("failed to read map")
-        .expect("failed to find key");
-    assert_eq!(val.len(), 8);
-    assert_eq!(val, &[1, 2, 3, 4, 5, 6, 7, 8]);
+    // Update batch with 1 key.
+    assert!(start
+        .update_batch(&key1, &value1, 1, MapFlags::ANY, MapFlags::NO_EXIST)
+        .is_ok());
 
-    start.delete(&[1, 2, 3, 4]).expect("failed to delete key");
+    // Update batch with multiple keys.
+    assert!(start
+        .update_batch(
+            &batch_key2,
+            &batch_value2,
+            3,
+            MapFlags::ANY,
+            MapFlags::NO_EXIST
+        )
+        .is_ok());
 
+    // Update batch with existing keys.
     assert!(start
-        .lookup(&[1, 2, 3, 4], MapFlags::empty())
-        .expect("failed to read map")
-        .is_none());
+        .update_batch(
+            &batch_key2,
+            &batch_value2,
+            3,
+            MapFlags::NO_EXIST,
+            MapFlags::NO_EXIST
+        )
+        .is_err());
 }
 
 #[test]
-fn test_sudo_object_map_lookup_flags() {
+fn test_sudo_object_map_delete_batch() {
     bump_rlimit_mlock();
 
     let mut obj = get_test_object("runqslower.bpf.o");
     let start = obj.map_mut("start").expect("failed to find map");
 
-    start
-        .update(&[1, 2, 3, 4], &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::NO_EXIST)
-        .expect("failed to write");
+    let key1 = 1u32.to_ne_bytes();
     assert!(start
-        .update(&[1, 2, 3, 4], &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::NO_EXIST)
+        .update(&key1, &9999u64.to_ne_bytes(), MapFlags::ANY)
+        .is_ok());
+    let key2 = 2u32.to_ne_bytes();
+    assert!(start
+        .update(&key2, &42u64.to_ne_bytes(), MapFlags::ANY)
+        .is_ok());
+    let key3 = 3u32.to_ne_bytes();
+    assert!(start
+        .update(&key3, &18u64.to_ne_bytes(), MapFlags::ANY)
+        .is_ok());
+    let key4 = 4u32.to_ne_bytes();
+    assert!(start
+        .update(&key4, &1337u64.to_ne_bytes(), MapFlags::ANY)
+        .is_ok());
+
+    // Delete 1 incomplete key.
+    assert!(start
+        .delete_batch(&[0, 0, 1], 1, MapFlags::empty(), MapFlags::empty())
+        .is_err());
+    // Delete keys with wrong count.
+    assert!(start
+        .delete_batch(&key4, 2, MapFlags::empty(), MapFlags::empty())
         .is_err());
+    // Delete 1 key successfully.
+    assert!(start
+        .delete_batch(&key4, 1, MapFlags::empty(), MapFlags::empty())
+        .is_ok());
+    // Delete remaining 3 keys.
+    let keys = key1.into_iter().chain(key2).chain(key3).collect::<Vec<_>>();
+    assert!(start
+        .delete_batch(&keys, 3, MapFlags::empty(), MapFlags::empty())
+        .is_ok());
+    // Map should be empty now.
+    assert!(start.keys().collect::<Vec<_>>().is_empty())
 }
 
+/// Test whether `MapInfo` works properly
 #[test]
-fn test_sudo_object_map_key_iter() {
-    bump_rlimit_mlock();
-
-    let mut obj = get_test_object("runqslower.bpf.o");
-
-    let start = obj.map_mut("start").expect("failed to find map");
-
-    let key1 = vec![1, 2, 3, 4];
-    let key2 = vec![1, 2, 3, 5];
-    let key3 = vec![1, 2, 3, 6];
+pub fn test_sudo_map_info() {
+    #[allow(clippy::needless_update)]
+    let opts = libbpf_sys::bpf_map_create_opts {
+        sz: size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+        map_flags: libbpf_sys::BPF_ANY,
+        btf_fd: 0,
+        btf_key_type_id: 0,
+        btf_value_type_id: 0,
+        btf_vmlinux_value_type_id: 0,
+        inner_map_fd: 0,
+        map_extra: 0,
+        numa_node: 0,
+        map_ifindex: 0,
+        // bpf_map_create_opts might have padding fields on some platform
+        ..Default::default()
+    };
 
-    start
-        .update(&key1, &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::empty())
-        .expect("failed to write");
-    start
-        .update(&key2, &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::empty())
-        .expect("failed to write");
-    start
-        .update(&key3, &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::empty())
-        .expect("failed to write");
+    let map = MapHandle::create(MapType::Hash, Some("simple_map"), 8, 64, 1024, &opts).unwrap();
+    let map_info = MapInfo::new(map.as_fd()).unwrap();
+    let name_received = map_info.name().unwrap();
+    assert_eq!(name_received, "simple_map");
+    assert_eq!(map_info.map_type(), MapType::Hash);
+    assert_eq!(map_info.flags() & MapFlags::ANY, MapFlags::ANY);
 
-    let mut keys = HashSet::new();
-    for key in start.keys() {
-        keys.insert(key);
-    }
-    assert_eq!(keys.len(), 3);
-    assert!(keys.contains(&key1));
-    assert!(keys.contains(&key2));
-    assert!(keys.contains(&key3));
+    let map_info = &map_info.info;
+    assert_eq!(map_info.key_size, 8);
+    assert_eq!(map_info.value_size, 64);
+    assert_eq!(map_info.max_entries, 1024);
+    assert_eq!(map_info.btf_id, 0);
+    assert_eq!(map_info.btf_key_type_id, 0);
+    assert_eq!(map_info.btf_value_type_id, 0);
+    assert_eq!(map_info.btf_vmlinux_value_type_id, 0);
+    assert_eq!(map_info.map_extra, 0);
+    assert_eq!(map_info.ifindex, 0);
 }
 
 #[test]
-fn test_sudo_object_map_key_iter_empty() {
+fn test_sudo_object_percpu_lookup() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("runqslower.bpf.o");
-    let start = obj.map("start").expect("failed to find map");
+    let mut obj = get_test_object("percpu_map.bpf.o");
+    let map = obj.map_mut("percpu_map").expect("failed to find map");
 
-    let mut count = 0;
-    for _ in start.keys() {
-        count += 1;
-    }
-    assert_eq!(count, 0);
+    let res = map
+        .lookup_percpu(&(0_u32).to_ne_bytes(), MapFlags::ANY)
+        .expect("failed to lookup")
+        .expect("failed to find value for key");
+
+    assert_eq!(
+        res.len(),
+        num_possible_cpus().expect("must be one value per cpu")
+    );
+    assert_eq!(res[0].len(), size_of::<u32>());
 }
 
 #[test]
-fn test_sudo_object_map_pin() {
+fn test_sudo_object_percpu_invalid_lookup_fn() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("runqslower.bpf.o");
-    let map = obj.map_mut("start").expect("failed to find map");
-
-    let path = "/sys/fs/bpf/mymap_test_object_map_pin";
-
-    // Unpinning a unpinned map should be an error
-    assert!(map.unpin(path).is_err());
-    assert!(!Path::new(path).exists());
+    let mut obj = get_test_object("percpu_map.bpf.o");
+    let map = obj.map_mut("percpu_map").expect("failed to find map");
 
-    // Pin and unpin should be successful
-    map.pin(path).expect("failed to pin map");
-    assert!(Path::new(path).exists());
-    map.unpin(path).expect("failed to unpin map");
-    assert!(!Path::new(path).exists());
+    assert!(map.lookup(&(0_u32).to_ne_bytes(), MapFlags::ANY).is_err());
 }
 
 #[test]
-fn test_sudo_object_loading_pinned_map_from_path() {
+fn test_sudo_object_percpu_update() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("runqslower.bpf.o");
-    let map = obj.map_mut("start").expect("failed to find map 'start'");
-
-    let path = "/sys/fs/bpf/mymap_test_pin_to_load_from_path";
-
-    map.pin(path).expect("pinning map failed");
+    let mut obj = get_test_object("percpu_map.bpf.o");
+    let map = obj.map_mut("percpu_map").expect("failed to find map");
+    let key = (0_u32).to_ne_bytes();
 
-    let pinned_map = MapHandle::from_pinned_path(path).expect("loading a map from a path failed");
-    map.unpin(path).expect("unpinning map failed");
+    let mut vals: Vec<Vec<u8>> = Vec::new();
+    for i in 0..num_possible_cpus().unwrap() {
+        vals.push((i as u32).to_ne_bytes().to_vec());
+    }
 
-    assert_eq!(map.name(), pinned_map.name());
-    assert_eq!(
-        map.info().unwrap().info.id,
-        pinned_map.info().unwrap().info.id
-    );
+    map.update_percpu(&key, &vals, MapFlags::ANY)
+        .expect("failed to update map");
+
+    let res = map
+        .lookup_percpu(&key, MapFlags::ANY)
+        .expect("failed to lookup")
+        .expect("failed to find value for key");
+
+    assert_eq!(vals, res);
 }
 
 #[test]
-fn test_sudo_object_loading_loaded_map_from_id() {
+fn test_sudo_object_percpu_invalid_update_fn() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("runqslower.bpf.o");
-    let map = obj.map_mut("start").expect("failed to find map 'start'");
-
-    let id = map.info().expect("to get info from map 'start'").info.id;
+    let mut obj = get_test_object("percpu_map.bpf.o");
+    let map = obj.map_mut("percpu_map").expect("failed to find map");
+    let key = (0_u32).to_ne_bytes();
 
-    let map_by_id = MapHandle::from_map_id(id).expect("map to load from id");
+    let val = (1_u32).to_ne_bytes().to_vec();
 
-    assert_eq!(map.name(), map_by_id.name());
-    assert_eq!(
-        map.info().unwrap().info.id,
-        map_by_id.info().unwrap().info.id
-    );
+    assert!(map.update(&key, &val, MapFlags::ANY).is_err());
 }
 
 #[test]
-fn test_sudo_object_programs() {
+fn test_sudo_object_percpu_lookup_update() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("runqslower.bpf.o");
-    obj.prog("handle__sched_wakeup")
-        .expect("failed to find program");
-    obj.prog("handle__sched_wakeup_new")
-        .expect("failed to find program");
-    obj.prog("handle__sched_switch")
-        .expect("failed to find program");
-    assert!(obj.prog("asdf").is_none());
-}
-
-#[test]
-fn test_sudo_object_programs_iter_mut() {
-    bump_rlimit_mlock();
+    let mut obj = get_test_object("percpu_map.bpf.o");
+    let map = obj.map_mut("percpu_map").expect("failed to find map");
+    let key = (0_u32).to_ne_bytes();
 
-    let obj = get_test_object("runqslower.bpf.o");
-    assert!(obj.progs_iter().count() == 3);
-}
+    let mut res = map
+        .lookup_percpu(&key, MapFlags::ANY)
+        .expect("failed to lookup")
+        .expect("failed to find value for key");
 
-#[test]
-fn test_sudo_object_program_pin() {
-    bump_rlimit_mlock();
+    for e in res.iter_mut() {
+        e[0] &= 0xf0;
+    }
 
-    let mut obj = get_test_object("runqslower.bpf.o");
-    let prog = obj
-        .prog_mut("handle__sched_wakeup")
-        .expect("failed to find program");
+    map.update_percpu(&key, &res, MapFlags::ANY)
+        .expect("failed to update after first lookup");
 
-    let path = "/sys/fs/bpf/myprog";
+    let res2 = map
+        .lookup_percpu(&key, MapFlags::ANY)
+        .expect("failed to lookup")
+        .expect("failed to find value for key");
 
-    // Unpinning a unpinned prog should be an error
-    assert!(prog.unpin(path).is_err());
-    assert!(!Path::new(path).exists());
+    assert_eq!(res, res2);
+}
 
-    // Pin should be successful
-    prog.pin(path).expect("failed to pin prog");
-    assert!(Path::new(path).exists());
+#[test]
+fn test_sudo_object_map_empty_lookup() {
+    bump_rlimit_mlock();
 
-    // Backup cleanup method in case test errors
-    defer! {
-        let _ = fs::remove_file(path);
-    }
+    let obj = get_test_object("runqslower.bpf.o");
+    let start = obj.map("start").expect("failed to find map");
 
-    // Unpin should be successful
-    prog.unpin(path).expect("failed to unpin prog");
-    assert!(!Path::new(path).exists());
+    assert!(start
+        .lookup(&[1, 2, 3, 4], MapFlags::empty())
+        .expect("err in map lookup")
+        .is_none());
 }
 
+/// Test CRUD operations on map of type queue.
 #[test]
-fn test_sudo_object_link_pin() {
+fn test_sudo_object_map_queue_crud() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("runqslower.bpf.o");
-    let prog = obj
-        .prog_mut("handle__sched_wakeup")
-        .expect("failed to find program");
-    let mut link = prog.attach().expect("failed to attach prog");
+    let obj = get_test_object("tracepoint.bpf.o");
+    let queue = obj.map("queue").expect("failed to find queue map");
 
-    let path = "/sys/fs/bpf/mylink";
+    let key: [u8; 0] = [];
+    let value1 = 42u32.to_ne_bytes();
+    let value2 = 43u32.to_ne_bytes();
 
-    // Unpinning a unpinned prog should be an error
-    assert!(link.unpin().is_err());
-    assert!(!Path::new(path).exists());
+    // Test queue, FIFO expected
+    queue
+        .update(&key, &value1, MapFlags::ANY)
+        .expect("failed to update in queue");
+    queue
+        .update(&key, &value2, MapFlags::ANY)
+        .expect("failed to update in queue");
 
-    // Pin should be successful
-    link.pin(path).expect("failed to pin prog");
-    assert!(Path::new(path).exists());
+    let mut val = queue
+        .lookup(&key, MapFlags::ANY)
+        .expect("failed to peek the queue")
+        .expect("failed to retrieve value");
+    assert_eq!(val.len(), 4);
+    assert_eq!(&val, &value1);
 
-    // Backup cleanup method in case test errors
-    defer! {
-        let _ = fs::remove_file(path);
-    }
+    val = queue
+        .lookup_and_delete(&key)
+        .expect("failed to pop from queue")
+        .expect("failed to retrieve value");
+    assert_eq!(val.len(), 4);
+    assert_eq!(&val, &value1);
 
-    // Unpin should be successful
-    link.unpin().expect("failed to unpin prog");
-    assert!(!Path::new(path).exists());
+    val = queue
+        .lookup_and_delete(&key)
+        .expect("failed to pop from queue")
+        .expect("failed to retrieve value");
+    assert_eq!(val.len(), 4);
+    assert_eq!(&val, &value2);
+
+    assert!(queue
+        .lookup_and_delete(&key)
+        .expect("failed to pop from queue")
+        .is_none());
 }
 
+/// Test CRUD operations on map of type bloomfilter.
 #[test]
-fn test_sudo_object_reuse_pined_map() {
+fn test_sudo_object_map_bloom_filter_crud() {
     bump_rlimit_mlock();
 
-    let path = "/sys/fs/bpf/mymap_test_object_reuse_pined_map";
-    let key = vec![1, 2, 3, 4];
-    let val = vec![1, 2, 3, 4, 5, 6, 7, 8];
+    let obj = get_test_object("tracepoint.bpf.o");
+    let bloom_filter = obj
+        .map("bloom_filter")
+        .expect("failed to find bloom_filter map");
 
-    // Pin a map
-    {
-        let mut obj = get_test_object("runqslower.bpf.o");
-        let map = obj.map_mut("start").expect("failed to find map");
+    let key: [u8; 0] = [];
+    let value1 = 1337u32.to_ne_bytes();
+    let value2 = 2674u32.to_ne_bytes();
 
-        map.update(&key, &val, MapFlags::empty())
-            .expect("failed to write");
+    bloom_filter
+        .update(&key, &value1, MapFlags::ANY)
+        .expect("failed to add entry value1 to bloom filter");
 
-        // Pin map
-        map.pin(path).expect("failed to pin map");
-        assert!(Path::new(path).exists());
-    }
+    bloom_filter
+        .update(&key, &value2, MapFlags::ANY)
+        .expect("failed to add entry value2 in bloom filter");
 
-    // Backup cleanup method in case test errors somewhere
-    defer! {
-        let _ = fs::remove_file(path);
-    }
+    // Non empty keys should result in an error
+    bloom_filter
+        .update(&value1, &value1, MapFlags::ANY)
+        .expect_err("Non empty key should return an error");
 
-    // Reuse the pinned map
-    let obj_path = get_test_object_path("runqslower.bpf.o");
-    let mut builder = ObjectBuilder::default();
-    builder.debug(true);
-    let mut open_obj = builder.open_file(obj_path).expect("failed to open object");
+    for inserted_value in [value1, value2] {
+        let val = bloom_filter
+            .lookup_bloom_filter(&inserted_value)
+            .expect("failed retrieve item from bloom filter");
 
-    let start = open_obj.map_mut("start").expect("failed to find map");
-    assert!(start.reuse_pinned_map("/asdf").is_err());
-    start.reuse_pinned_map(path).expect("failed to reuse map");
+        assert!(val);
+    }
+    // Test non existing element
+    let enoent_found = bloom_filter
+        .lookup_bloom_filter(&[1, 2, 3, 4])
+        .expect("failed retrieve item from bloom filter");
 
-    let mut obj = open_obj.load().expect("Failed to load object");
-    let reused_map = obj.map_mut("start").expect("failed to find map");
+    assert!(!enoent_found);
 
-    let found_val = reused_map
-        .lookup(&key, MapFlags::empty())
-        .expect("failed to read map")
-        .expect("failed to find key");
-    assert_eq!(&found_val, &val);
+    // Calling lookup should result in an error
+    bloom_filter
+        .lookup(&[1, 2, 3, 4], MapFlags::ANY)
+        .expect_err("lookup should fail since we should use lookup_bloom_filter");
 
-    // Cleanup
-    reused_map.unpin(path).expect("failed to unpin map");
-    assert!(!Path::new(path).exists());
+    // Deleting should not be possible
+    bloom_filter
+        .lookup_and_delete(&key)
+        .expect_err("Expect delete to fail");
 }
 
+/// Test CRUD operations on map of type stack.
 #[test]
-fn test_sudo_object_ringbuf_raw() {
+fn test_sudo_object_map_stack_crud() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("ringbuf.bpf.o");
-    let prog = obj
-        .prog_mut("handle__sys_enter_getpid")
-        .expect("failed to find program");
-    let _link = prog.attach().expect("failed to attach prog");
-
-    static mut V1: i32 = 0;
-    static mut V2: i32 = 0;
+    let obj = get_test_object("tracepoint.bpf.o");
+    let stack = obj.map("stack").expect("failed to find stack map");
 
-    fn callback1(data: &[u8]) -> i32 {
-        let mut value: i32 = 0;
-        plain::copy_from_bytes(&mut value, data).expect("Wrong size");
+    let key: [u8; 0] = [];
+    let value1 = 1337u32.to_ne_bytes();
+    let value2 = 2674u32.to_ne_bytes();
 
-        unsafe {
-            V1 = value;
-        }
+    stack
+        .update(&key, &value1, MapFlags::ANY)
+        .expect("failed to update in stack");
+    stack
+        .update(&key, &value2, MapFlags::ANY)
+        .expect("failed to update in stack");
 
-        0
-    }
+    let mut val = stack
+        .lookup(&key, MapFlags::ANY)
+        .expect("failed to pop from stack")
+        .expect("failed to retrieve value");
 
-    fn callback2(data: &[u8]) -> i32 {
-        let mut value: i32 = 0;
-        plain::copy_from_bytes(&mut value, data).expect("Wrong size");
+    assert_eq!(val.len(), 4);
+    assert_eq!(&val, &value2);
 
-        unsafe {
-            V2 = value;
-        }
+    val = stack
+        .lookup_and_delete(&key)
+        .expect("failed to pop from stack")
+        .expect("failed to retrieve value");
+    assert_eq!(val.len(), 4);
+    assert_eq!(&val, &value2);
 
-        0
-    }
+    val = stack
+        .lookup_and_delete(&key)
+        .expect("failed to pop from stack")
+        .expect("failed to retrieve value");
+    assert_eq!(val.len(), 4);
+    assert_eq!(&val, &value1);
 
-    // Test trying to build without adding any ringbufs
-    // Can't use expect_err here since RingBuffer does not implement Debug
-    let builder = libbpf_rs::RingBufferBuilder::new();
-    assert!(
-        builder.build().is_err(),
-        "Should not be able to build without adding at least one ringbuf"
-    );
+    assert!(stack
+        .lookup_and_delete(&key)
+        .expect("failed to pop from stack")
+        .is_none());
+}
 
-    // Test building with multiple map objects
-    let mut builder = libbpf_rs::RingBufferBuilder::new();
+#[test]
+fn test_sudo_object_map_mutation() {
+    bump_rlimit_mlock();
 
-    // Add a first map and callback
-    let map1 = obj.map("ringbuf1").expect("Failed to get ringbuf1 map");
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let start = obj.map_mut("start").expect("failed to find map");
 
-    builder.add(map1, callback1).expect("Failed to add ringbuf");
+    start
+        .update(&[1, 2, 3, 4], &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::empty())
+        .expect("failed to write");
+    let val = start
+        .lookup(&[1, 2, 3, 4], MapFlags::empty())
+        .expect("failed to read map")
+        .expect("failed to find key");
+    assert_eq!(val.len(), 8);
+    assert_eq!(val, &[1, 2, 3, 4, 5, 6, 7, 8]);
 
-    // Add a second map and callback
-    let map2 = obj.map("ringbuf2").expect("Failed to get ringbuf2 map");
+    start.delete(&[1, 2, 3, 4]).expect("failed to delete key");
 
-    builder.add(map2, callback2).expect("Failed to add ringbuf");
+    assert!(start
+        .lookup(&[1, 2, 3, 4], MapFlags::empty())
+        .expect("failed to read map")
+        .is_none());
+}
 
-    let mgr = builder.build().expect("Failed to build");
+#[test]
+fn test_sudo_object_map_lookup_flags() {
+    bump_rlimit_mlock();
 
-    // Call getpid to ensure the BPF program runs
-    unsafe { libc::getpid() };
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let start = obj.map_mut("start").expect("failed to find map");
 
-    // Test raw primitives
-    let ret = mgr.consume_raw();
+    start
+        .update(&[1, 2, 3, 4], &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::NO_EXIST)
+        .expect("failed to write");
+    assert!(start
+        .update(&[1, 2, 3, 4], &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::NO_EXIST)
+        .is_err());
+}
 
-    // We can't check for exact return values, since other tasks in the system may call getpid(),
-    // triggering the BPF program
-    assert!(ret >= 2);
+#[test]
+fn test_sudo_object_map_key_iter() {
+    bump_rlimit_mlock();
 
-    unsafe { assert_eq!(V1, 1) };
-    unsafe { assert_eq!(V2, 2) };
+    let mut obj = get_test_object("runqslower.bpf.o");
 
-    // Consume from a (potentially) empty ring buffer
-    let ret = mgr.consume_raw();
-    assert!(ret >= 0);
+    let start = obj.map_mut("start").expect("failed to find map");
 
-    // Consume from a (potentially) empty ring buffer using poll()
-    let ret = mgr.poll_raw(Duration::from_millis(100));
-    assert!(ret >= 0);
+    let key1 = vec![1, 2, 3, 4];
+    let key2 = vec![1, 2, 3, 5];
+    let key3 = vec![1, 2, 3, 6];
+
+    start
+        .update(&key1, &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::empty())
+        .expect("failed to write");
+    start
+        .update(&key2, &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::empty())
+        .expect("failed to write");
+    start
+        .update(&key3, &[1, 2, 3, 4, 5, 6, 7, 8], MapFlags::empty())
+        .expect("failed to write");
+
+    let mut keys = HashSet::new();
+    for key in start.keys() {
+        keys.insert(key);
+    }
+    assert_eq!(keys.len(), 3);
+    assert!(keys.contains(&key1));
+    assert!(keys.contains(&key2));
+    assert!(keys.contains(&key3));
 }
 
 #[test]
-fn test_sudo_object_ringbuf_err_callback() {
+fn test_sudo_object_map_key_iter_empty() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("ringbuf.bpf.o");
-    let prog = obj
-        .prog_mut("handle__sys_enter_getpid")
-        .expect("failed to find program");
-    let _link = prog.attach().expect("failed to attach prog");
+    let obj = get_test_object("runqslower.bpf.o");
+    let start = obj.map("start").expect("failed to find map");
 
-    // Immediately trigger an error that should be reported back to the consume_raw() or poll_raw()
-    fn callback1(_data: &[u8]) -> i32 {
-        -libc::ENOENT
+    let mut count = 0;
+    for _ in start.keys() {
+        count += 1;
     }
+    assert_eq!(count, 0);
+}
 
-    // Immediately trigger an error that should be reported back to the consume_raw() or poll_raw()
-    fn callback2(_data: &[u8]) -> i32 {
-        -libc::EPERM
-    }
+#[test]
+fn test_sudo_object_map_pin() {
+    bump_rlimit_mlock();
 
-    // Test trying to build without adding any ringbufs
-    // Can't use expect_err here since RingBuffer does not implement Debug
-    let builder = libbpf_rs::RingBufferBuilder::new();
-    assert!(
-        builder.build().is_err(),
-        "Should not be able to build without adding at least one ringbuf"
-    );
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let map = obj.map_mut("start").expect("failed to find map");
 
-    // Test building with multiple map objects
-    let mut builder = libbpf_rs::RingBufferBuilder::new();
+    let path = "/sys/fs/bpf/mymap_test_object_map_pin";
 
-    // Add a first map and callback
-    let map1 = obj.map("ringbuf1").expect("Failed to get ringbuf1 map");
+    // Unpinning a unpinned map should be an error
+    assert!(map.unpin(path).is_err());
+    assert!(!Path::new(path).exists());
 
-    builder.add(map1, callback1).expect("Failed to add ringbuf");
+    // Pin and unpin should be successful
+    map.pin(path).expect("failed to pin map");
+    assert!(Path::new(path).exists());
+    map.unpin(path).expect("failed to unpin map");
+    assert!(!Path::new(path).exists());
+}
 
-    // Add a second map and callback
-    let map2 = obj.map("ringbuf2").expect("Failed to get ringbuf2 map");
+#[test]
+fn test_sudo_object_loading_pinned_map_from_path() {
+    bump_rlimit_mlock();
 
-    builder.add(map2, callback2).expect("Failed to add ringbuf");
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let map = obj.map_mut("start").expect("failed to find map 'start'");
 
-    let mgr = builder.build().expect("Failed to build");
+    let path = "/sys/fs/bpf/mymap_test_pin_to_load_from_path";
 
-    // Call getpid to ensure the BPF program runs
-    unsafe { libc::getpid() };
+    map.pin(path).expect("pinning map failed");
 
-    // Test raw primitives
-    let ret = mgr.consume_raw();
+    let pinned_map = MapHandle::from_pinned_path(path).expect("loading a map from a path failed");
+    map.unpin(path).expect("unpinning map failed");
 
-    // The error originated from the first callback executed should be reported here, either
-    // from callback1() or callback2()
-    assert!(ret == -libc::ENOENT || ret == -libc::EPERM);
+    assert_eq!(map.name(), pinned_map.name());
+    assert_eq!(
+        map.info().unwrap().info.id,
+        pinned_map.info().unwrap().info.id
+    );
+}
 
-    unsafe { libc::getpid() };
+#[test]
+fn test_sudo_object_loading_loaded_map_from_id() {
+    bump_rlimit_mlock();
 
-    // The same behavior should happen with poll_raw()
-    let ret = mgr.poll_raw(Duration::from_millis(100));
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let map = obj.map_mut("start").expect("failed to find map 'start'");
 
-    assert!(ret == -libc::ENOENT || ret == -libc::EPERM);
+    let id = map.info().expect("to get info from map 'start'").info.id;
+
+    let map_by_id = MapHandle::from_map_id(id).expect("map to load from id");
+
+    assert_eq!(map.name(), map_by_id.name());
+    assert_eq!(
+        map.info().unwrap().info.id,
+        map_by_id.info().unwrap().info.id
+    );
 }
 
 #[test]
-fn test_sudo_object_ringbuf() {
+fn test_sudo_object_programs() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("ringbuf.bpf.o");
-    let prog = obj
-        .prog_mut("handle__sys_enter_getpid")
+    let obj = get_test_object("runqslower.bpf.o");
+    obj.prog("handle__sched_wakeup")
         .expect("failed to find program");
-    let _link = prog.attach().expect("failed to attach prog");
-
-    static mut V1: i32 = 0;
-    static mut V2: i32 = 0;
+    obj.prog("handle__sched_wakeup_new")
+        .expect("failed to find program");
+    obj.prog("handle__sched_switch")
+        .expect("failed to find program");
+    assert!(obj.prog("asdf").is_none());
+}
 
-    fn callback1(data: &[u8]) -> i32 {
-        let mut value: i32 = 0;
-        plain::copy_from_bytes(&mut value, data).expect("Wrong size");
+#[test]
+fn test_sudo_object_programs_iter_mut() {
+    bump_rlimit_mlock();
 
-        unsafe {
-            V1 = value;
-        }
+    let obj = get_test_object("runqslower.bpf.o");
+    assert!(obj.progs_iter().count() == 3);
+}
 
-        0
-    }
+#[test]
+fn test_sudo_object_program_pin() {
+    bump_rlimit_mlock();
 
-    fn callback2(data: &[u8]) -> i32 {
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let prog = obj
+        .prog_mut("handle__sched_wakeup")
+        .expect("failed to find program");
+
+    let path = "/sys/fs/bpf/myprog";
+
+    // Unpinning a unpinned prog should be an error
+    assert!(prog.unpin(path).is_err());
+    assert!(!Path::new(path).exists());
+
+    // Pin should be successful
+    prog.pin(path).expect("failed to pin prog");
+    assert!(Path::new(path).exists());
+
+    // Backup cleanup method in case test errors
+    defer! {
+        let _ = fs::remove_file(path);
+    }
+
+    // Unpin should be successful
+    prog.unpin(path).expect("failed to unpin prog");
+    assert!(!Path::new(path).exists());
+}
+
+#[test]
+fn test_sudo_object_link_pin() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let prog = obj
+        .prog_mut("handle__sched_wakeup")
+        .expect("failed to find program");
+    let mut link = prog.attach().expect("failed to attach prog");
+
+    let path = "/sys/fs/bpf/mylink";
+
+    // Unpinning a unpinned prog should be an error
+    assert!(link.unpin().is_err());
+    assert!(!Path::new(path).exists());
+
+    // Pin should be successful
+    link.pin(path).expect("failed to pin prog");
+    assert!(Path::new(path).exists());
+
+    // Backup cleanup method in case test errors
+    defer! {
+        let _ = fs::remove_file(path);
+    }
+
+    // Unpin should be successful
+    link.unpin().expect("failed to unpin prog");
+    assert!(!Path::new(path).exists());
+}
+
+/// Check that we can mount a bpffs instance, create pin directories on it,
+/// and enumerate the objects pinned there.
+#[test]
+fn test_sudo_bpffs_pinned_objects() {
+    bump_rlimit_mlock();
+
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    assert!(!bpffs::is_bpffs(root).unwrap());
+    let () = bpffs::mount(root).unwrap();
+    defer! {
+        let path = CString::new(root.as_os_str().as_bytes()).unwrap();
+        let _ret = unsafe { libc::umount(path.as_ptr()) };
+    }
+    assert!(bpffs::is_bpffs(root).unwrap());
+    assert!(bpffs::mount_points()
+        .unwrap()
+        .iter()
+        .any(|path| path == root));
+
+    let app_dir = root.join("app");
+    let pin_dir = app_dir.join("pins");
+    let () = bpffs::create_pin_dir(&pin_dir, 0o700).unwrap();
+    let mode = fs::metadata(&pin_dir).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o700);
+    assert_eq!(bpffs::pinned_objects(&app_dir).unwrap(), Vec::new());
+
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let map = obj.map_mut("start").expect("failed to find map");
+    let () = map.pin(pin_dir.join("map")).unwrap();
+    let prog = obj
+        .prog_mut("handle__sched_wakeup")
+        .expect("failed to find program");
+    let () = prog.pin(pin_dir.join("prog")).unwrap();
+    let mut link = prog.attach().expect("failed to attach prog");
+    let () = link.pin(app_dir.join("link")).unwrap();
+
+    let mut objects = bpffs::pinned_objects(&app_dir).unwrap();
+    let () = objects.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(
+        objects,
+        vec![
+            PinnedObject {
+                path: app_dir.join("link"),
+                ty: PinnedObjectType::Link,
+            },
+            PinnedObject {
+                path: pin_dir.join("map"),
+                ty: PinnedObjectType::Map,
+            },
+            PinnedObject {
+                path: pin_dir.join("prog"),
+                ty: PinnedObjectType::Program,
+            },
+        ]
+    );
+
+    // Depending on the kernel configuration, a new bpffs instance may come
+    // with preloaded iterator links at its root.
+    let all = bpffs::pinned_objects(root).unwrap();
+    assert!(objects.iter().all(|obj| all.contains(obj)));
+}
+
+#[test]
+fn test_sudo_object_pin_all() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let path = Path::new("/sys/fs/bpf/test_object_pin_all");
+    fs::create_dir(path).expect("failed to create pin directory");
+
+    // Backup cleanup method in case test errors
+    defer! {
+        let _ = fs::remove_dir_all(path);
+    }
+
+    let mut link = obj
+        .prog_mut("handle__sched_wakeup")
+        .expect("failed to find program")
+        .attach()
+        .expect("failed to attach program");
+
+    obj.pin_all(path, [("wakeup", &mut link)])
+        .expect("failed to pin object");
+    assert!(path.join("start").exists());
+    assert!(path.join("handle__sched_wakeup").exists());
+    assert!(path.join("links/wakeup").exists());
+
+    obj.unpin_all(path).expect("failed to unpin object");
+    assert!(!path.join("start").exists());
+    assert!(!path.join("handle__sched_wakeup").exists());
+    assert!(!path.join("links").exists());
+
+    // A failure to pin a link leaves no pins behind.
+    let result = obj.pin_all(path, [("invalid/name", &mut link)]);
+    assert!(result.is_err());
+    assert!(!path.join("start").exists());
+    assert!(!path.join("links").exists());
+}
+
+#[test]
+fn test_sudo_object_reuse_pined_map() {
+    bump_rlimit_mlock();
+
+    let path = "/sys/fs/bpf/mymap_test_object_reuse_pined_map";
+    let key = vec![1, 2, 3, 4];
+    let val = vec![1, 2, 3, 4, 5, 6, 7, 8];
+
+    // Pin a map
+    {
+        let mut obj = get_test_object("runqslower.bpf.o");
+        let map = obj.map_mut("start").expect("failed to find map");
+
+        map.update(&key, &val, MapFlags::empty())
+            .expect("failed to write");
+
+        // Pin map
+        map.pin(path).expect("failed to pin map");
+        assert!(Path::new(path).exists());
+    }
+
+    // Backup cleanup method in case test errors somewhere
+    defer! {
+        let _ = fs::remove_file(path);
+    }
+
+    // Reuse the pinned map
+    let obj_path = get_test_object_path("runqslower.bpf.o");
+    let mut builder = ObjectBuilder::default();
+    builder.debug(true);
+    let mut open_obj = builder.open_file(obj_path).expect("failed to open object");
+
+    let start = open_obj.map_mut("start").expect("failed to find map");
+    assert!(start.reuse_pinned_map("/asdf").is_err());
+    start.reuse_pinned_map(path).expect("failed to reuse map");
+
+    let mut obj = open_obj.load().expect("Failed to load object");
+    let reused_map = obj.map_mut("start").expect("failed to find map");
+
+    let found_val = reused_map
+        .lookup(&key, MapFlags::empty())
+        .expect("failed to read map")
+        .expect("failed to find key");
+    assert_eq!(&found_val, &val);
+
+    // Cleanup
+    reused_map.unpin(path).expect("failed to unpin map");
+    assert!(!Path::new(path).exists());
+}
+
+#[test]
+#[allow(static_mut_refs)]
+fn test_sudo_object_ringbuf_raw() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("ringbuf.bpf.o");
+    let prog = obj
+        .prog_mut("handle__sys_enter_getpid")
+        .expect("failed to find program");
+    let _link = prog.attach().expect("failed to attach prog");
+
+    static mut V1: i32 = 0;
+    static mut V2: i32 = 0;
+
+    fn callback1(data: &[u8]) -> i32 {
+        let mut value: i32 = 0;
+        plain::copy_from_bytes(&mut value, data).expect("Wrong size");
+
+        unsafe {
+            V1 = value;
+        }
+
+        0
+    }
+
+    fn callback2(data: &[u8]) -> i32 {
         let mut value: i32 = 0;
         plain::copy_from_bytes(&mut value, data).expect("Wrong size");
 
@@ -1105,31 +2007,27 @@ fn test_sudo_object_ringbuf() {
     // Call getpid to ensure the BPF program runs
     unsafe { libc::getpid() };
 
-    // This should result in both callbacks being called
-    mgr.consume().expect("Failed to consume ringbuf");
+    // Test raw primitives
+    let ret = mgr.consume_raw();
+
+    // We can't check for exact return values, since other tasks in the system may call getpid(),
+    // triggering the BPF program
+    assert!(ret >= 2);
 
-    // Our values should both reflect that the callbacks have been called
     unsafe { assert_eq!(V1, 1) };
     unsafe { assert_eq!(V2, 2) };
 
-    // Reset both values
-    unsafe { V1 = 0 };
-    unsafe { V2 = 0 };
-
-    // Call getpid to ensure the BPF program runs
-    unsafe { libc::getpid() };
-
-    // This should result in both callbacks being called
-    mgr.poll(Duration::from_millis(100))
-        .expect("Failed to poll ringbuf");
+    // Consume from a (potentially) empty ring buffer
+    let ret = mgr.consume_raw();
+    assert!(ret >= 0);
 
-    // Our values should both reflect that the callbacks have been called
-    unsafe { assert_eq!(V1, 1) };
-    unsafe { assert_eq!(V2, 2) };
+    // Consume from a (potentially) empty ring buffer using poll()
+    let ret = mgr.poll_raw(Duration::from_millis(100));
+    assert!(ret >= 0);
 }
 
 #[test]
-fn test_sudo_object_ringbuf_closure() {
+fn test_sudo_object_ringbuf_err_callback() {
     bump_rlimit_mlock();
 
     let mut obj = get_test_object("ringbuf.bpf.o");
@@ -1138,25 +2036,15 @@ fn test_sudo_object_ringbuf_closure() {
         .expect("failed to find program");
     let _link = prog.attach().expect("failed to attach prog");
 
-    let (sender1, receiver1) = channel();
-    let callback1 = move |data: &[u8]| -> i32 {
-        let mut value: i32 = 0;
-        plain::copy_from_bytes(&mut value, data).expect("Wrong size");
-
-        sender1.send(value).expect("Failed to send value");
-
-        0
-    };
-
-    let (sender2, receiver2) = channel();
-    let callback2 = move |data: &[u8]| -> i32 {
-        let mut value: i32 = 0;
-        plain::copy_from_bytes(&mut value, data).expect("Wrong size");
-
-        sender2.send(value).expect("Failed to send value");
+    // Immediately trigger an error that should be reported back to the consume_raw() or poll_raw()
+    fn callback1(_data: &[u8]) -> i32 {
+        -libc::ENOENT
+    }
 
-        0
-    };
+    // Immediately trigger an error that should be reported back to the consume_raw() or poll_raw()
+    fn callback2(_data: &[u8]) -> i32 {
+        -libc::EPERM
+    }
 
     // Test trying to build without adding any ringbufs
     // Can't use expect_err here since RingBuffer does not implement Debug
@@ -1184,31 +2072,187 @@ fn test_sudo_object_ringbuf_closure() {
     // Call getpid to ensure the BPF program runs
     unsafe { libc::getpid() };
 
-    // This should result in both callbacks being called
-    mgr.consume().expect("Failed to consume ringbuf");
+    // Test raw primitives
+    let ret = mgr.consume_raw();
 
-    let v1 = receiver1.recv().expect("Failed to receive value");
-    let v2 = receiver2.recv().expect("Failed to receive value");
+    // The error originated from the first callback executed should be reported here, either
+    // from callback1() or callback2()
+    assert!(ret == -libc::ENOENT || ret == -libc::EPERM);
 
-    assert_eq!(v1, 1);
-    assert_eq!(v2, 2);
+    unsafe { libc::getpid() };
+
+    // The same behavior should happen with poll_raw()
+    let ret = mgr.poll_raw(Duration::from_millis(100));
+
+    assert!(ret == -libc::ENOENT || ret == -libc::EPERM);
 }
 
-/// Check that `RingBuffer` works correctly even if the map file descriptors
-/// provided during construction are closed. This test validates that `libbpf`'s
-/// refcount behavior is correctly reflected in our `RingBuffer` lifetimes.
 #[test]
-fn test_sudo_object_ringbuf_with_closed_map() {
+#[allow(static_mut_refs)]
+fn test_sudo_object_ringbuf() {
     bump_rlimit_mlock();
 
-    fn test(poll_fn: impl FnOnce(&libbpf_rs::RingBuffer)) {
-        let mut value = 0i32;
-
-        {
-            let mut obj = get_test_object("tracepoint.bpf.o");
-            let prog = obj
-                .prog_mut("handle__tracepoint")
-                .expect("Failed to find program");
+    let mut obj = get_test_object("ringbuf.bpf.o");
+    let prog = obj
+        .prog_mut("handle__sys_enter_getpid")
+        .expect("failed to find program");
+    let _link = prog.attach().expect("failed to attach prog");
+
+    static mut V1: i32 = 0;
+    static mut V2: i32 = 0;
+
+    fn callback1(data: &[u8]) -> i32 {
+        let mut value: i32 = 0;
+        plain::copy_from_bytes(&mut value, data).expect("Wrong size");
+
+        unsafe {
+            V1 = value;
+        }
+
+        0
+    }
+
+    fn callback2(data: &[u8]) -> i32 {
+        let mut value: i32 = 0;
+        plain::copy_from_bytes(&mut value, data).expect("Wrong size");
+
+        unsafe {
+            V2 = value;
+        }
+
+        0
+    }
+
+    // Test trying to build without adding any ringbufs
+    // Can't use expect_err here since RingBuffer does not implement Debug
+    let builder = libbpf_rs::RingBufferBuilder::new();
+    assert!(
+        builder.build().is_err(),
+        "Should not be able to build without adding at least one ringbuf"
+    );
+
+    // Test building with multiple map objects
+    let mut builder = libbpf_rs::RingBufferBuilder::new();
+
+    // Add a first map and callback
+    let map1 = obj.map("ringbuf1").expect("Failed to get ringbuf1 map");
+
+    builder.add(map1, callback1).expect("Failed to add ringbuf");
+
+    // Add a second map and callback
+    let map2 = obj.map("ringbuf2").expect("Failed to get ringbuf2 map");
+
+    builder.add(map2, callback2).expect("Failed to add ringbuf");
+
+    let mgr = builder.build().expect("Failed to build");
+
+    // Call getpid to ensure the BPF program runs
+    unsafe { libc::getpid() };
+
+    // This should result in both callbacks being called
+    mgr.consume().expect("Failed to consume ringbuf");
+
+    // Our values should both reflect that the callbacks have been called
+    unsafe { assert_eq!(V1, 1) };
+    unsafe { assert_eq!(V2, 2) };
+
+    // Reset both values
+    unsafe { V1 = 0 };
+    unsafe { V2 = 0 };
+
+    // Call getpid to ensure the BPF program runs
+    unsafe { libc::getpid() };
+
+    // This should result in both callbacks being called
+    mgr.poll(Duration::from_millis(100))
+        .expect("Failed to poll ringbuf");
+
+    // Our values should both reflect that the callbacks have been called
+    unsafe { assert_eq!(V1, 1) };
+    unsafe { assert_eq!(V2, 2) };
+}
+
+#[test]
+fn test_sudo_object_ringbuf_closure() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("ringbuf.bpf.o");
+    let prog = obj
+        .prog_mut("handle__sys_enter_getpid")
+        .expect("failed to find program");
+    let _link = prog.attach().expect("failed to attach prog");
+
+    let (sender1, receiver1) = channel();
+    let callback1 = move |data: &[u8]| -> i32 {
+        let mut value: i32 = 0;
+        plain::copy_from_bytes(&mut value, data).expect("Wrong size");
+
+        sender1.send(value).expect("Failed to send value");
+
+        0
+    };
+
+    let (sender2, receiver2) = channel();
+    let callback2 = move |data: &[u8]| -> i32 {
+        let mut value: i32 = 0;
+        plain::copy_from_bytes(&mut value, data).expect("Wrong size");
+
+        sender2.send(value).expect("Failed to send value");
+
+        0
+    };
+
+    // Test trying to build without adding any ringbufs
+    // Can't use expect_err here since RingBuffer does not implement Debug
+    let builder = libbpf_rs::RingBufferBuilder::new();
+    assert!(
+        builder.build().is_err(),
+        "Should not be able to build without adding at least one ringbuf"
+    );
+
+    // Test building with multiple map objects
+    let mut builder = libbpf_rs::RingBufferBuilder::new();
+
+    // Add a first map and callback
+    let map1 = obj.map("ringbuf1").expect("Failed to get ringbuf1 map");
+
+    builder.add(map1, callback1).expect("Failed to add ringbuf");
+
+    // Add a second map and callback
+    let map2 = obj.map("ringbuf2").expect("Failed to get ringbuf2 map");
+
+    builder.add(map2, callback2).expect("Failed to add ringbuf");
+
+    let mgr = builder.build().expect("Failed to build");
+
+    // Call getpid to ensure the BPF program runs
+    unsafe { libc::getpid() };
+
+    // This should result in both callbacks being called
+    mgr.consume().expect("Failed to consume ringbuf");
+
+    let v1 = receiver1.recv().expect("Failed to receive value");
+    let v2 = receiver2.recv().expect("Failed to receive value");
+
+    assert_eq!(v1, 1);
+    assert_eq!(v2, 2);
+}
+
+/// Check that `RingBuffer` works correctly even if the map file descriptors
+/// provided during construction are closed. This test validates that `libbpf`'s
+/// refcount behavior is correctly reflected in our `RingBuffer` lifetimes.
+#[test]
+fn test_sudo_object_ringbuf_with_closed_map() {
+    bump_rlimit_mlock();
+
+    fn test(poll_fn: impl FnOnce(&libbpf_rs::RingBuffer)) {
+        let mut value = 0i32;
+
+        {
+            let mut obj = get_test_object("tracepoint.bpf.o");
+            let prog = obj
+                .prog_mut("handle__tracepoint")
+                .expect("Failed to find program");
 
             let _link = prog
                 .attach_tracepoint("syscalls", "sys_enter_getpid")
@@ -1376,6 +2420,151 @@ fn test_sudo_object_task_iter() {
     assert!(items.iter().any(|&item| item.pid == 1));
 }
 
+/// Check that we can iterate over cgroups in the various supported orders.
+#[test]
+fn test_sudo_object_cgroup_iter() {
+    bump_rlimit_mlock();
+
+    let root = cgroup::root().expect("failed to find cgroup hierarchy");
+    let child = root.join("libbpf-rs-test-cgroup-iter");
+    fs::create_dir(&child).expect("failed to create cgroup");
+    defer! {
+        let _ = fs::remove_dir(&child);
+    }
+
+    let counter = create_counter_map();
+
+    // Repurpose a program without any relocations, so that we can freely
+    // replace its instructions.
+    let mut open_obj = open_test_object("xdp.bpf.o");
+    let prog = open_obj
+        .prog_mut("xdp_filter")
+        .expect("failed to find program");
+    let () = prog.set_prog_type(ProgramType::Tracing);
+    let () = prog.set_attach_type(ProgramAttachType::TraceIter);
+    let () = prog
+        .set_attach_target(0, Some("cgroup".to_string()))
+        .expect("failed to set attach target");
+    // Count each cgroup visited, skipping the final invocation without
+    // one.
+    let prologue = [
+        //   r6 = *(u64 *)(r1 + 8)    ; ctx->cgroup
+        insn(
+            libbpf_sys::BPF_LDX | libbpf_sys::BPF_MEM | libbpf_sys::BPF_DW,
+            6,
+            1,
+            8,
+            0,
+        ),
+        //   if r6 != 0 goto +2
+        insn(
+            libbpf_sys::BPF_JMP | libbpf_sys::BPF_JNE | libbpf_sys::BPF_K,
+            6,
+            0,
+            2,
+            0,
+        ),
+        //   r6 = -1
+        insn(
+            libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K,
+            6,
+            0,
+            0,
+            -1,
+        ),
+        //   goto +1
+        insn(libbpf_sys::BPF_JMP | libbpf_sys::BPF_JA, 0, 0, 1, 0),
+        //   r6 = 1
+        insn(
+            libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K,
+            6,
+            0,
+            0,
+            1,
+        ),
+    ];
+    let () = set_counter_insns(prog, &prologue, &counter);
+
+    let mut obj = open_obj.load().expect("failed to load object");
+    let prog = obj.prog_mut("xdp_filter").expect("failed to find program");
+
+    let mut visited = |path: &Path, order| {
+        let cgroup = cgroup::open(path).expect("failed to open cgroup");
+        let opts = IterOpts::Cgroup {
+            cgroup_fd: cgroup.as_fd(),
+            order,
+        };
+        let link = prog
+            .attach_iter_with_opts(opts)
+            .expect("failed to attach iterator");
+        let mut iter = Iter::new(&link).expect("failed to create iterator");
+        let mut buf = Vec::new();
+        let _cnt = iter
+            .read_to_end(&mut buf)
+            .expect("failed to read from iterator");
+        take_counter(&counter)
+    };
+
+    assert_eq!(visited(&child, CgroupIterOrder::SelfOnly), 1);
+    assert_eq!(visited(&child, CgroupIterOrder::DescendantsPre), 1);
+    assert_eq!(visited(&child, CgroupIterOrder::AncestorsUp), 2);
+    assert!(visited(&root, CgroupIterOrder::DescendantsPost) >= 2);
+}
+
+/// Check that a program attached to a hardware breakpoint runs on writes
+/// to the watched memory.
+#[test]
+fn test_sudo_attach_breakpoint() {
+    bump_rlimit_mlock();
+
+    let counter = create_counter_map();
+    let mut open_obj = open_test_object("xdp.bpf.o");
+    let prog = open_obj
+        .prog_mut("xdp_filter")
+        .expect("failed to find program");
+    let () = prog.set_prog_type(ProgramType::PerfEvent);
+    // Count each hit.
+    let prologue = [
+        //   r6 = 1
+        insn(
+            libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K,
+            6,
+            0,
+            0,
+            1,
+        ),
+    ];
+    let () = set_counter_insns(prog, &prologue, &counter);
+
+    let mut obj = open_obj.load().expect("failed to load object");
+    let prog = obj.prog_mut("xdp_filter").expect("failed to find program");
+
+    let mut watched = Box::new(0u64);
+    let addr = ptr::addr_of_mut!(*watched);
+    let link = prog
+        .attach_breakpoint(
+            BreakpointType::Write,
+            addr as u64,
+            size_of::<u64>() as u64,
+            0,
+            -1,
+        )
+        .expect("failed to attach breakpoint");
+
+    for i in 1..=3 {
+        let () = unsafe { ptr::write_volatile(addr, i) };
+    }
+    assert_eq!(take_counter(&counter), 3);
+
+    // Reads do not trigger the breakpoint.
+    let _value = unsafe { ptr::read_volatile(addr) };
+    assert_eq!(take_counter(&counter), 0);
+
+    drop(link);
+    let () = unsafe { ptr::write_volatile(addr, 4) };
+    assert_eq!(take_counter(&counter), 0);
+}
+
 #[test]
 fn test_sudo_object_map_iter() {
     bump_rlimit_mlock();
@@ -1508,529 +2697,1569 @@ fn test_sudo_object_map_create_without_name() {
     assert_eq!(val, res);
 }
 
-/// Test whether we can obtain multiple `MapHandle`s from a `Map
+/// Test whether we can obtain multiple `MapHandle`s from a `Map
+#[test]
+fn test_sudo_object_map_handle_clone() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("runqslower.bpf.o");
+    let map = obj.map("events").expect("failed to find map");
+    let handle1 = MapHandle::try_clone(map).expect("Failed to create handle from Map");
+    assert_eq!(map.name(), handle1.name());
+    assert_eq!(map.map_type(), handle1.map_type());
+    assert_eq!(map.key_size(), handle1.key_size());
+    assert_eq!(map.value_size(), handle1.value_size());
+
+    let handle2 = MapHandle::try_clone(&handle1).expect("Failed to duplicate existing handle");
+    assert_eq!(handle1.name(), handle2.name());
+    assert_eq!(handle1.map_type(), handle2.map_type());
+    assert_eq!(handle1.key_size(), handle2.key_size());
+    assert_eq!(handle1.value_size(), handle2.value_size());
+
+    let info1 = map.info().expect("Failed to get map info from map");
+    let info2 = handle2.info().expect("Failed to get map info from handle");
+    assert_eq!(
+        info1.info.id, info2.info.id,
+        "Map and MapHandle have different IDs"
+    );
+}
+
+#[test]
+fn test_sudo_object_usdt() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("usdt.bpf.o");
+    let prog = obj
+        .prog_mut("handle__usdt")
+        .expect("Failed to find program");
+
+    let path = current_exe().expect("Failed to find executable name");
+    let _link = prog
+        .attach_usdt(
+            unsafe { libc::getpid() },
+            &path,
+            "test_provider",
+            "test_function",
+        )
+        .expect("Failed to attach prog");
+
+    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
+    let action = || {
+        // Define a USDT probe point and exercise it as we are attaching to self.
+        probe!(test_provider, test_function, 1);
+    };
+    let result = with_ringbuffer(map, action);
+
+    assert_eq!(result, 1);
+}
+
+#[test]
+fn test_sudo_object_usdt_cookie() {
+    bump_rlimit_mlock();
+
+    let cookie_val = 1337u16;
+    let mut obj = get_test_object("usdt.bpf.o");
+    let prog = obj
+        .prog_mut("handle__usdt_with_cookie")
+        .expect("Failed to find program");
+
+    let path = current_exe().expect("Failed to find executable name");
+    let _link = prog
+        .attach_usdt_with_opts(
+            unsafe { libc::getpid() },
+            &path,
+            "test_provider",
+            "test_function",
+            UsdtOpts {
+                cookie: cookie_val.into(),
+                ..UsdtOpts::default()
+            },
+        )
+        .expect("Failed to attach prog");
+
+    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
+    let action = || {
+        // Define a USDT probe point and exercise it as we are attaching to self.
+        probe!(test_provider, test_function, 1);
+    };
+    let result = with_ringbuffer(map, action);
+
+    assert_eq!(result, cookie_val.into());
+}
+
+#[test]
+fn test_sudo_map_probes() {
+    bump_rlimit_mlock();
+
+    let supported = MapType::Array
+        .is_supported()
+        .expect("Failed to query if Array map is supported");
+    assert!(supported);
+    let supported_res = MapType::Unknown.is_supported();
+    assert!(supported_res.is_err());
+}
+
+#[test]
+fn test_sudo_program_probes() {
+    bump_rlimit_mlock();
+
+    let supported = ProgramType::SocketFilter
+        .is_supported()
+        .expect("Failed to query if SocketFilter program is supported");
+    assert!(supported);
+    let supported_res = ProgramType::Unknown.is_supported();
+    assert!(supported_res.is_err());
+}
+
+/// Check that the `probes` module reports kernel feature support.
+#[test]
+fn test_sudo_probes_module() {
+    bump_rlimit_mlock();
+
+    assert!(probes::prog_type_supported(ProgramType::SocketFilter).unwrap());
+    assert!(probes::map_type_supported(MapType::Array).unwrap());
+    assert!(probes::helper_supported(
+        ProgramType::SocketFilter,
+        libbpf_sys::BPF_FUNC_map_lookup_elem
+    )
+    .unwrap());
+    assert!(
+        !probes::helper_supported(ProgramType::SocketFilter, libbpf_sys::BPF_FUNC_redirect)
+            .unwrap()
+    );
+    assert!(probes::map_type_supported(MapType::Unknown).is_err());
+}
+
+/// Check that we only raise `RLIMIT_MEMLOCK` on kernels charging BPF
+/// memory against it.
+#[test]
+fn test_sudo_raise_memlock_rlimit() {
+    let memcg = probes::memcg_accounting_supported().unwrap();
+    let guard = raise_memlock_rlimit().unwrap();
+    assert_eq!(guard.raised(), !memcg);
+    // Keep the limit raised, as concurrently running tests may rely on
+    // it.
+    let () = mem::forget(guard);
+}
+
+/// Check that we can detect the BPF capabilities of the running kernel.
+#[test]
+fn test_sudo_system_capabilities() {
+    bump_rlimit_mlock();
+
+    let caps = probes::SystemCapabilities::detect().expect("Failed to detect capabilities");
+    assert!(!caps.kernel_release.is_empty());
+    assert!(caps.kernel_version.is_some(), "{caps:?}");
+    assert!(caps.supports_prog_type(ProgramType::SocketFilter));
+    assert!(caps.supports_map_type(MapType::Array));
+    assert_eq!(caps.ringbuf, caps.supports_map_type(MapType::RingBuf));
+}
+
+#[test]
+fn test_sudo_program_helper_probes() {
+    bump_rlimit_mlock();
+
+    let supported = ProgramType::SocketFilter
+        .is_helper_supported(libbpf_sys::BPF_FUNC_map_lookup_elem)
+        .expect("Failed to query if helper supported");
+    assert!(supported);
+    // redirect should not be supported from socket filter, as it is only used in TC/XDP.
+    let supported = ProgramType::SocketFilter
+        .is_helper_supported(libbpf_sys::BPF_FUNC_redirect)
+        .expect("Failed to query if helper supported");
+    assert!(!supported);
+    let supported_res = MapType::Unknown.is_supported();
+    assert!(supported_res.is_err());
+}
+
+#[test]
+fn test_sudo_object_open_program_insns() {
+    bump_rlimit_mlock();
+
+    let open_obj = open_test_object("usdt.bpf.o");
+    let prog = open_obj
+        .prog("handle__usdt")
+        .expect("Failed to find program");
+
+    let insns = prog.insns();
+    assert!(!insns.is_empty());
+}
+
+#[test]
+fn test_sudo_object_program_insns() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("usdt.bpf.o");
+    let prog = obj.prog("handle__usdt").expect("Failed to find program");
+
+    let insns = prog.insns();
+    assert!(!insns.is_empty());
+}
+
+/// Check that we can attach a BPF program to a kernel tracepoint.
+#[test]
+fn test_sudo_object_tracepoint() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("tracepoint.bpf.o");
+    let prog = obj
+        .prog_mut("handle__tracepoint")
+        .expect("Failed to find program");
+
+    let _link = prog
+        .attach_tracepoint("syscalls", "sys_enter_getpid")
+        .expect("Failed to attach prog");
+
+    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
+    let action = || {
+        let _pid = unsafe { libc::getpid() };
+    };
+    let result = with_ringbuffer(map, action);
+
+    assert_eq!(result, 1);
+}
+
+/// Check that we can attach a BPF program to a kernel tracepoint, providing
+/// additional options.
+#[test]
+fn test_sudo_object_tracepoint_with_opts() {
+    bump_rlimit_mlock();
+
+    let cookie_val = 42u16;
+    let mut obj = get_test_object("tracepoint.bpf.o");
+    let prog = obj
+        .prog_mut("handle__tracepoint_with_cookie")
+        .expect("Failed to find program");
+
+    let opts = TracepointOpts {
+        cookie: cookie_val.into(),
+        ..TracepointOpts::default()
+    };
+    let _link = prog
+        .attach_tracepoint_with_opts("syscalls", "sys_enter_getpid", opts)
+        .expect("Failed to attach prog");
+
+    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
+    let action = || {
+        let _pid = unsafe { libc::getpid() };
+    };
+    let result = with_ringbuffer(map, action);
+
+    assert_eq!(result, cookie_val.into());
+}
+
+#[inline(never)]
+#[no_mangle]
+extern "C" fn uprobe_target() -> usize {
+    // Use `black_box` here as an additional barrier to inlining.
+    hint::black_box(42)
+}
+
+/// Check that we can attach a BPF program to a uprobe.
+#[test]
+fn test_sudo_object_uprobe_with_opts() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("uprobe.bpf.o");
+    let prog = obj
+        .prog_mut("handle__uprobe")
+        .expect("Failed to find program");
+
+    let pid = unsafe { libc::getpid() };
+    let path = current_exe().expect("Failed to find executable name");
+    let func_offset = 0;
+    let opts = UprobeOpts {
+        func_name: "uprobe_target".to_string(),
+        ..Default::default()
+    };
+    let _link = prog
+        .attach_uprobe_with_opts(pid, path, func_offset, opts)
+        .expect("Failed to attach prog");
+
+    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
+    let action = || {
+        let _ = uprobe_target();
+    };
+    let result = with_ringbuffer(map, action);
+
+    assert_eq!(result, 1);
+}
+
+/// Check that we can attach a BPF program to a uprobe and access the cookie
+/// provided during attach.
+#[test]
+fn test_sudo_object_uprobe_with_cookie() {
+    bump_rlimit_mlock();
+
+    let cookie_val = 5u16;
+    let mut obj = get_test_object("uprobe.bpf.o");
+    let prog = obj
+        .prog_mut("handle__uprobe_with_cookie")
+        .expect("Failed to find program");
+
+    let pid = unsafe { libc::getpid() };
+    let path = current_exe().expect("Failed to find executable name");
+    let func_offset = 0;
+    let opts = UprobeOpts {
+        func_name: "uprobe_target".to_string(),
+        cookie: cookie_val.into(),
+        ..Default::default()
+    };
+    let _link = prog
+        .attach_uprobe_with_opts(pid, path, func_offset, opts)
+        .expect("Failed to attach prog");
+
+    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
+    let action = || {
+        let _ = uprobe_target();
+    };
+    let result = with_ringbuffer(map, action);
+
+    assert_eq!(result, cookie_val.into());
+}
+
+/// Check that we can link multiple object files.
+#[test]
+fn test_object_link_files() {
+    fn test(files: Vec<PathBuf>) {
+        let output_file = NamedTempFile::new().unwrap();
+
+        let mut linker = Linker::new(output_file.path()).unwrap();
+        let () = files
+            .into_iter()
+            .try_for_each(|file| linker.add_file(file))
+            .unwrap();
+        let () = linker.link().unwrap();
+
+        // Check that we can load the resulting object file.
+        let _object = ObjectBuilder::default()
+            .debug(true)
+            .open_file(output_file.path())
+            .unwrap();
+    }
+
+    let obj_path1 = get_test_object_path("usdt.bpf.o");
+    let obj_path2 = get_test_object_path("ringbuf.bpf.o");
+
+    test(vec![obj_path1.clone()]);
+    test(vec![obj_path1, obj_path2]);
+}
+
+/// Get access to the underlying per-cpu ring buffer data.
+fn buffer<'a>(perf: &'a libbpf_rs::PerfBuffer, buf_idx: usize) -> &'a [u8] {
+    let perf_buff_ptr = perf.as_libbpf_object();
+    let mut buffer_data_ptr: *mut c_void = ptr::null_mut();
+    let mut buffer_size: usize = 0;
+    let ret = unsafe {
+        libbpf_sys::perf_buffer__buffer(
+            perf_buff_ptr.as_ptr(),
+            buf_idx as i32,
+            ptr::addr_of_mut!(buffer_data_ptr),
+            ptr::addr_of_mut!(buffer_size) as *mut libbpf_sys::size_t,
+        )
+    };
+    assert!(ret >= 0);
+    unsafe { slice::from_raw_parts(buffer_data_ptr as *const u8, buffer_size) }
+}
+
+/// Check that we can see the raw ring buffer of the perf buffer and find a
+/// value we have sent.
+#[test]
+fn test_sudo_object_perf_buffer_raw() {
+    use memmem::Searcher;
+    use memmem::TwoWaySearcher;
+
+    bump_rlimit_mlock();
+
+    let cookie_val = 42u16;
+    let mut obj = get_test_object("tracepoint.bpf.o");
+    let prog = obj
+        .prog_mut("handle__tracepoint_with_cookie_pb")
+        .expect("Failed to find program");
+
+    let opts = TracepointOpts {
+        cookie: cookie_val.into(),
+        ..TracepointOpts::default()
+    };
+    let _link = prog
+        .attach_tracepoint_with_opts("syscalls", "sys_enter_getpid", opts)
+        .expect("Failed to attach prog");
+
+    let map = obj.map("pb").expect("Failed to get perf-buffer map");
+
+    let cookie_bytes = cookie_val.to_ne_bytes();
+    let searcher = TwoWaySearcher::new(&cookie_bytes[..]);
+
+    let perf = libbpf_rs::PerfBufferBuilder::new(map)
+        .build()
+        .expect("Failed to build");
+
+    // Make an action that the tracepoint will see
+    let _pid = unsafe { libc::getpid() };
+
+    let found_cookie = (0..perf.buffer_cnt()).any(|buf_idx| {
+        let buf = buffer(&perf, buf_idx);
+        searcher.search_in(buf).is_some()
+    });
+
+    assert!(found_cookie);
+}
+
+/// Check that we can get map pin status and map pin path
+#[test]
+fn test_sudo_map_pinned_status() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("map_auto_pin.bpf.o");
+    let map = obj
+        .map("auto_pin_map")
+        .expect("failed to find map 'auto_pin_map'");
+
+    let is_pinned = map.is_pinned();
+    assert!(is_pinned);
+    let expected_path = "/sys/fs/bpf/auto_pin_map";
+    let get_path = map.get_pin_path().expect("get map pin path failed");
+    assert_eq!(expected_path, get_path.to_str().unwrap());
+    // cleanup
+    let _ = fs::remove_file(expected_path);
+}
+
+/// Change the root_pin_path and see if it works.
+#[test]
+fn test_sudo_map_pinned_status_with_pin_root_path() {
+    bump_rlimit_mlock();
+
+    let obj_path = get_test_object_path("map_auto_pin.bpf.o");
+    let obj = ObjectBuilder::default()
+        .debug(true)
+        .pin_root_path("/sys/fs/bpf/test_namespace")
+        .expect("root_pin_path failed")
+        .open_file(obj_path)
+        .expect("failed to open object")
+        .load()
+        .expect("failed to load object");
+
+    let map = obj
+        .map("auto_pin_map")
+        .expect("failed to find map 'auto_pin_map'");
+
+    let is_pinned = map.is_pinned();
+    assert!(is_pinned);
+    let expected_path = "/sys/fs/bpf/test_namespace/auto_pin_map";
+    let get_path = map.get_pin_path().expect("get map pin path failed");
+    assert_eq!(expected_path, get_path.to_str().unwrap());
+    // cleanup
+    let _ = fs::remove_file(expected_path);
+    let _ = fs::remove_dir("/sys/fs/bpf/test_namespace");
+}
+
+/// Check that we can get program fd by id and vice versa.
+#[test]
+fn test_sudo_program_get_fd_and_id() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("runqslower.bpf.o");
+    let prog = obj
+        .prog("handle__sched_wakeup")
+        .expect("failed to find program");
+
+    let prog_fd = prog.as_fd();
+    let prog_id = Program::get_id_by_fd(prog_fd).expect("failed to get program id by fd");
+    let _owned_prog_fd = Program::get_fd_by_id(prog_id).expect("failed to get program fd by id");
+}
+
+/// Check that autocreate disabled maps don't prevent object loading
+#[test]
+fn test_sudo_map_autocreate_disable() {
+    bump_rlimit_mlock();
+
+    let mut open_obj = open_test_object("map_auto_pin.bpf.o");
+
+    open_obj
+        .map_mut("auto_pin_map")
+        .expect("map wasn't found")
+        .set_autocreate(false)
+        .expect("set_autocreate() failed");
+
+    open_obj.load().expect("failed to load object");
+}
+
+/// Check that we can resize a map.
+#[test]
+fn test_sudo_map_resize() {
+    bump_rlimit_mlock();
+
+    let mut open_obj = open_test_object("map_auto_pin.bpf.o");
+
+    let resizable = open_obj
+        .map_mut(".data.resizable_data")
+        .expect("map wasn't found");
+
+    let len = resizable.initial_value().unwrap().len();
+    assert_eq!(len, size_of::<u64>());
+
+    let () = resizable
+        .set_value_size(len as u32 * 2)
+        .expect("failed to set value size");
+    let new_len = resizable.initial_value().unwrap().len();
+    assert_eq!(new_len, len * 2);
+}
+
+/// Check that we are able to attach using ksyscall
+#[test]
+fn test_sudo_attach_ksyscall() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("ksyscall.bpf.o");
+    let prog = obj
+        .prog_mut("handle__ksyscall")
+        .expect("Failed to find program");
+
+    let _link = prog
+        .attach_ksyscall(false, "kill")
+        .expect("Failed to attach prog");
+
+    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
+    let action = || {
+        // Send `SIGCHLD`, which is ignored by default, to our process.
+        let ret = unsafe { libc::kill(libc::getpid(), libc::SIGCHLD) };
+        if ret < 0 {
+            panic!("kill failed: {}", io::Error::last_os_error());
+        }
+    };
+    let result = with_ringbuffer(map, action);
+
+    assert_eq!(result, 1);
+}
+
+/// Check that we can invoke a program directly.
+#[test]
+fn test_sudo_run_prog_success() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("run_prog.bpf.o");
+    let prog = obj.prog_mut("test_1").expect("Failed to find program");
+
+    #[repr(C)]
+    struct bpf_dummy_ops_state {
+        val: c_int,
+    }
+
+    let value = 42;
+    let state = bpf_dummy_ops_state { val: value };
+    let args = [addr_of!(state) as u64];
+    let input = ProgramInput {
+        context_in: Some(unsafe { plain::as_bytes(&args) }),
+        ..Default::default()
+    };
+    let output = prog.test_run(input).unwrap();
+    assert_eq!(output.return_value, value as _);
+}
+
+/// Check that we fail program invocation when providing insufficient arguments.
+#[test]
+fn test_sudo_run_prog_fail() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("run_prog.bpf.o");
+    let prog = obj.prog_mut("test_2").expect("Failed to find program");
+
+    let input = ProgramInput::default();
+    let _err = prog.test_run(input).unwrap_err();
+}
+
+/// Check that we refuse to attach a program as a per-cgroup LSM hook if it
+/// was not defined as one.
 #[test]
-fn test_sudo_object_map_handle_clone() {
+fn test_sudo_attach_lsm_cgroup_invalid_attach_type() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("runqslower.bpf.o");
-    let map = obj.map("events").expect("failed to find map");
-    let handle1 = MapHandle::try_clone(map).expect("Failed to create handle from Map");
-    assert_eq!(map.name(), handle1.name());
-    assert_eq!(map.map_type(), handle1.map_type());
-    assert_eq!(map.key_size(), handle1.key_size());
-    assert_eq!(map.value_size(), handle1.value_size());
-
-    let handle2 = MapHandle::try_clone(&handle1).expect("Failed to duplicate existing handle");
-    assert_eq!(handle1.name(), handle2.name());
-    assert_eq!(handle1.map_type(), handle2.map_type());
-    assert_eq!(handle1.key_size(), handle2.key_size());
-    assert_eq!(handle1.value_size(), handle2.value_size());
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let prog = obj
+        .prog_mut("handle__sched_wakeup")
+        .expect("Failed to find program");
 
-    let info1 = map.info().expect("Failed to get map info from map");
-    let info2 = handle2.info().expect("Failed to get map info from handle");
-    assert_eq!(
-        info1.info.id, info2.info.id,
-        "Map and MapHandle have different IDs"
-    );
+    let err = prog.attach_lsm_cgroup(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
 }
 
+/// Check that we refuse to attach a program to a cgroup socket address hook
+/// if it was not defined for one.
 #[test]
-fn test_sudo_object_usdt() {
+fn test_sudo_attach_cgroup_sock_addr_invalid_attach_type() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("usdt.bpf.o");
+    let mut obj = get_test_object("runqslower.bpf.o");
     let prog = obj
-        .prog_mut("handle__usdt")
+        .prog_mut("handle__sched_wakeup")
         .expect("Failed to find program");
 
-    let path = current_exe().expect("Failed to find executable name");
-    let _link = prog
-        .attach_usdt(
-            unsafe { libc::getpid() },
-            &path,
-            "test_provider",
-            "test_function",
-        )
-        .expect("Failed to attach prog");
-
-    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
-    let action = || {
-        // Define a USDT probe point and exercise it as we are attaching to self.
-        probe!(test_provider, test_function, 1);
-    };
-    let result = with_ringbuffer(map, action);
-
-    assert_eq!(result, 1);
+    let err = prog.attach_cgroup_sock_addr("/sys/fs/cgroup").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
 }
 
+/// Check that we refuse to attach a program to a netkit device if it was not
+/// defined for one.
 #[test]
-fn test_sudo_object_usdt_cookie() {
+fn test_sudo_attach_netkit_invalid_attach_type() {
     bump_rlimit_mlock();
 
-    let cookie_val = 1337u16;
-    let mut obj = get_test_object("usdt.bpf.o");
+    let mut obj = get_test_object("runqslower.bpf.o");
     let prog = obj
-        .prog_mut("handle__usdt_with_cookie")
+        .prog_mut("handle__sched_wakeup")
         .expect("Failed to find program");
 
-    let path = current_exe().expect("Failed to find executable name");
-    let _link = prog
-        .attach_usdt_with_opts(
-            unsafe { libc::getpid() },
-            &path,
-            "test_provider",
-            "test_function",
-            UsdtOpts {
-                cookie: cookie_val.into(),
-                ..UsdtOpts::default()
-            },
-        )
-        .expect("Failed to attach prog");
+    let opts = NetkitOpts {
+        position: LinkPosition::Before(LinkAnchor::ProgramId(1)),
+        expected_revision: 1,
+        ..Default::default()
+    };
+    let err = prog.attach_netkit_with_opts(1, opts).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
 
-    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
-    let action = || {
-        // Define a USDT probe point and exercise it as we are attaching to self.
-        probe!(test_provider, test_function, 1);
+/// Check that we refuse to query netkit information of other devices.
+#[test]
+fn test_netkit_query_non_netkit() {
+    let err = NetkitInfo::query("lo").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+/// Send a netlink request of type `msg_type` for the link `name`, of kind
+/// `kind` if provided, and wait for the acknowledgement.
+fn link_request(msg_type: u16, flags: c_int, name: &str, kind: Option<&str>) -> io::Result<()> {
+    fn push_attr(buf: &mut Vec<u8>, ty: u16, data: &[u8]) {
+        let len = 4 + data.len();
+        buf.extend_from_slice(&(len as u16).to_ne_bytes());
+        buf.extend_from_slice(&ty.to_ne_bytes());
+        buf.extend_from_slice(data);
+        buf.resize(buf.len() + (4 - len % 4) % 4, 0);
+    }
+
+    // The netlink message header, filled in below, and the `ifinfomsg`.
+    let mut buf = vec![0u8; 16 + 16];
+    push_attr(&mut buf, libc::IFLA_IFNAME, format!("{name}\0").as_bytes());
+    if let Some(kind) = kind {
+        let mut info = Vec::new();
+        push_attr(
+            &mut info,
+            libc::IFLA_INFO_KIND,
+            format!("{kind}\0").as_bytes(),
+        );
+        push_attr(&mut buf, libc::IFLA_LINKINFO, &info);
+    }
+    let len = buf.len() as u32;
+    buf[0..4].copy_from_slice(&len.to_ne_bytes());
+    buf[4..6].copy_from_slice(&msg_type.to_ne_bytes());
+    let flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK | flags) as u16;
+    buf[6..8].copy_from_slice(&flags.to_ne_bytes());
+
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        )
     };
-    let result = with_ringbuffer(map, action);
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    if unsafe { libc::send(fd.as_raw_fd(), buf.as_ptr().cast(), buf.len(), 0) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
 
-    assert_eq!(result, cookie_val.into());
+    let mut ack = [0u8; 1024];
+    let rc = unsafe { libc::recv(fd.as_raw_fd(), ack.as_mut_ptr().cast(), ack.len(), 0) };
+    if rc < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // The acknowledgement is an `NLMSG_ERROR` message carrying the
+    // (negative) error code, if any.
+    let err = i32::from_ne_bytes(ack[16..20].try_into().unwrap());
+    if err != 0 {
+        return Err(io::Error::from_raw_os_error(-err));
+    }
+    Ok(())
 }
 
-#[test]
-fn test_sudo_map_probes() {
-    bump_rlimit_mlock();
+/// A netkit device pair, removed when dropped.
+struct NetkitPair {
+    name: &'static str,
+}
 
-    let supported = MapType::Array
-        .is_supported()
-        .expect("Failed to query if Array map is supported");
-    assert!(supported);
-    let supported_res = MapType::Unknown.is_supported();
-    assert!(supported_res.is_err());
+impl NetkitPair {
+    /// Create a netkit device pair with primary device `name`, returning
+    /// `None` if the kernel does not support netkit devices.
+    fn create(name: &'static str) -> Option<Self> {
+        let flags = libc::NLM_F_CREATE | libc::NLM_F_EXCL;
+        match link_request(libc::RTM_NEWLINK, flags, name, Some("netkit")) {
+            Ok(()) => Some(Self { name }),
+            Err(err) if err.raw_os_error() == Some(libc::EOPNOTSUPP) => None,
+            Err(err) => panic!("failed to create netkit device: {err}"),
+        }
+    }
 }
 
+impl Drop for NetkitPair {
+    fn drop(&mut self) {
+        let _result = link_request(libc::RTM_DELLINK, 0, self.name, None);
+    }
+}
+
+/// Check that we can attach programs to a netkit device in a given order.
 #[test]
-fn test_sudo_program_probes() {
+fn test_sudo_attach_netkit() {
     bump_rlimit_mlock();
 
-    let supported = ProgramType::SocketFilter
-        .is_supported()
-        .expect("Failed to query if SocketFilter program is supported");
-    assert!(supported);
-    let supported_res = ProgramType::Unknown.is_supported();
-    assert!(supported_res.is_err());
+    let Some(netkit) = NetkitPair::create("nk-libbpf-rs") else {
+        eprintln!("netkit devices are not supported; skipping");
+        return;
+    };
+
+    let counter = create_counter_map();
+    let load = || {
+        let mut open_obj = open_test_object("xdp.bpf.o");
+        let prog = open_obj
+            .prog_mut("xdp_filter")
+            .expect("failed to find program");
+        let () = prog.set_netkit_target(NetkitTarget::Primary);
+        // Count each packet, passing it on (`NETKIT_PASS`).
+        let prologue = [
+            //   r6 = 1
+            insn(
+                libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K,
+                6,
+                0,
+                0,
+                1,
+            ),
+        ];
+        let () = set_counter_insns(prog, &prologue, &counter);
+        open_obj.load().expect("failed to load object")
+    };
+
+    let mut obj1 = load();
+    let prog1 = obj1.prog_mut("xdp_filter").expect("failed to find program");
+    let link1 = prog1
+        .attach_netkit(netkit.name)
+        .expect("failed to attach program");
+
+    let info = query::LinkInfo::load_from_fd(link1.as_fd()).unwrap();
+    let info = match info.info {
+        query::LinkTypeInfo::Netkit(info) => info,
+        other => panic!("unexpected link info: {other:?}"),
+    };
+    assert_eq!(info.ifindex as i32, ifindex_from_name(netkit.name).unwrap());
+    assert!(matches!(info.attach_type, ProgramAttachType::NetkitPrimary));
+    let device = info.device_info().unwrap();
+    assert!(device.primary);
+    assert_eq!(device.policy, NetkitPolicy::Pass);
+
+    let mut obj2 = load();
+    let prog2 = obj2.prog_mut("xdp_filter").expect("failed to find program");
+    // A stale revision is rejected.
+    let opts = NetkitOpts {
+        position: LinkPosition::Before(LinkAnchor::Link(link1.as_fd())),
+        expected_revision: u64::MAX,
+        ..Default::default()
+    };
+    assert!(prog2.attach_netkit_with_opts(netkit.name, opts).is_err());
+
+    let opts = NetkitOpts {
+        position: LinkPosition::Before(LinkAnchor::Link(link1.as_fd())),
+        ..Default::default()
+    };
+    let _link2 = prog2
+        .attach_netkit_with_opts(netkit.name, opts)
+        .expect("failed to attach program");
 }
 
+/// Check that a cookie provided when attaching a trampoline based program
+/// is reported to it.
 #[test]
-fn test_sudo_program_helper_probes() {
+fn test_sudo_attach_trace_with_opts_cookie() {
     bump_rlimit_mlock();
 
-    let supported = ProgramType::SocketFilter
-        .is_helper_supported(libbpf_sys::BPF_FUNC_map_lookup_elem)
-        .expect("Failed to query if helper supported");
-    assert!(supported);
-    // redirect should not be supported from socket filter, as it is only used in TC/XDP.
-    let supported = ProgramType::SocketFilter
-        .is_helper_supported(libbpf_sys::BPF_FUNC_redirect)
-        .expect("Failed to query if helper supported");
-    assert!(!supported);
-    let supported_res = MapType::Unknown.is_supported();
-    assert!(supported_res.is_err());
+    // Tracing programs have to be GPL compatible, so link our program
+    // without relocations with an object declaring the license.
+    let obj_file = NamedTempFile::new().unwrap();
+    let mut linker = Linker::new(obj_file.path()).unwrap();
+    let () = linker.add_file(get_test_object_path("xdp.bpf.o")).unwrap();
+    let () = linker
+        .add_file(get_test_object_path("percpu_map.bpf.o"))
+        .unwrap();
+    let () = linker.link().unwrap();
+
+    let counter = create_counter_map();
+    let mut open_obj = ObjectBuilder::default()
+        .debug(true)
+        .open_file(obj_file.path())
+        .expect("failed to open object");
+    let prog = open_obj
+        .prog_mut("xdp_filter")
+        .expect("failed to find program");
+    let () = prog.set_prog_type(ProgramType::Tracing);
+    // Use a BTF-enabled tracepoint, as not all kernels support attaching
+    // fentry programs.
+    let () = prog.set_attach_type(ProgramAttachType::TraceRawTp);
+    let () = prog
+        .set_attach_target(0, Some("sys_enter".to_string()))
+        .expect("failed to set attach target");
+    // Add the cookie for each invocation.
+    let prologue = [
+        //   call bpf_get_attach_cookie
+        insn(
+            libbpf_sys::BPF_JMP | libbpf_sys::BPF_CALL,
+            0,
+            0,
+            0,
+            libbpf_sys::BPF_FUNC_get_attach_cookie as _,
+        ),
+        //   r6 = r0
+        insn(
+            libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_X,
+            6,
+            0,
+            0,
+            0,
+        ),
+    ];
+    let () = set_counter_insns(prog, &prologue, &counter);
+
+    let mut obj = open_obj.load().expect("failed to load object");
+    let prog = obj.prog_mut("xdp_filter").expect("failed to find program");
+
+    let cookie = 1337;
+    let opts = TraceOpts {
+        cookie,
+        ..TraceOpts::default()
+    };
+    let _link = prog
+        .attach_trace_with_opts(opts)
+        .expect("failed to attach program");
+
+    let _pid = unsafe { libc::getpid() };
+    // Other tasks in the system enter system calls as well.
+    let value = take_counter(&counter);
+    assert_ne!(value, 0);
+    assert_eq!(value % cookie, 0);
 }
 
+/// Check that a program attached to a cgroup socket address hook runs on
+/// the corresponding socket operations.
 #[test]
-fn test_sudo_object_open_program_insns() {
+fn test_sudo_attach_cgroup_sock_addr() {
     bump_rlimit_mlock();
 
-    let open_obj = open_test_object("usdt.bpf.o");
+    let counter = create_counter_map();
+    let mut open_obj = open_test_object("xdp.bpf.o");
     let prog = open_obj
-        .prog("handle__usdt")
-        .expect("Failed to find program");
+        .prog_mut("xdp_filter")
+        .expect("failed to find program");
+    let () = prog.set_cgroup_sock_addr_hook(CgroupSockAddrHook::Connect4);
+    // Count each connect, allowing it.
+    let prologue = [
+        //   r6 = 1
+        insn(
+            libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K,
+            6,
+            0,
+            0,
+            1,
+        ),
+    ];
+    let () = set_counter_insns_with_ret(prog, &prologue, &counter, 1);
+
+    let mut obj = open_obj.load().expect("failed to load object");
+    let prog = obj.prog_mut("xdp_filter").expect("failed to find program");
+
+    let path = cgroup::of_pid(process::id()).expect("failed to find cgroup");
+    let (hook, link) = prog
+        .attach_cgroup_sock_addr(&path)
+        .expect("failed to attach program");
+    assert_eq!(hook, CgroupSockAddrHook::Connect4);
+
+    let connect = || {
+        let socket = UdpSocket::bind("127.0.0.1:0").expect("failed to bind socket");
+        let () = socket
+            .connect("127.0.0.1:9")
+            .expect("failed to connect socket");
+    };
 
-    let insns = prog.insns();
-    assert!(!insns.is_empty());
+    let () = connect();
+    // Other processes in the cgroup may connect sockets as well.
+    assert!(take_counter(&counter) >= 1);
+
+    drop(link);
+    let () = connect();
+    assert_eq!(take_counter(&counter), 0);
 }
 
+/// Check that we reject kprobe-multi attachment requests in which the number
+/// of cookies does not match the number of symbols.
 #[test]
-fn test_sudo_object_program_insns() {
+fn test_sudo_attach_kprobe_multi_cookie_mismatch() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("usdt.bpf.o");
-    let prog = obj.prog("handle__usdt").expect("Failed to find program");
+    let mut obj = get_test_object("ksyscall.bpf.o");
+    let prog = obj
+        .prog_mut("handle__ksyscall")
+        .expect("Failed to find program");
 
-    let insns = prog.insns();
-    assert!(!insns.is_empty());
+    let opts = KprobeMultiOpts {
+        symbols: vec!["__x64_sys_kill".to_string(), "__x64_sys_getpid".to_string()],
+        cookies: vec![42],
+        session: true,
+        ..Default::default()
+    };
+    let err = prog.attach_kprobe_multi_with_opts(opts).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
 }
 
-/// Check that we can attach a BPF program to a kernel tracepoint.
+/// Check that we reject kprobe-multi attachment requests asking for a
+/// session of return probes.
 #[test]
-fn test_sudo_object_tracepoint() {
+fn test_sudo_attach_kprobe_multi_session_retprobe() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("tracepoint.bpf.o");
+    let mut obj = get_test_object("ksyscall.bpf.o");
     let prog = obj
-        .prog_mut("handle__tracepoint")
+        .prog_mut("handle__ksyscall")
         .expect("Failed to find program");
 
-    let _link = prog
-        .attach_tracepoint("syscalls", "sys_enter_getpid")
-        .expect("Failed to attach prog");
+    let opts = KprobeMultiOpts {
+        symbols: vec!["__x64_sys_getpid".to_string()],
+        retprobe: true,
+        session: true,
+        ..Default::default()
+    };
+    let err = prog.attach_kprobe_multi_with_opts(opts).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+/// Check that a kprobe session program is invoked at function entry and
+/// return, unless its entry invocation returns non-zero.
+#[test]
+fn test_sudo_attach_kprobe_multi_session() {
+    bump_rlimit_mlock();
+
+    // Count the invocations of a session program returning `ret` across
+    // a single `getpid` call of ours.
+    let count_invocations = |ret| {
+        let counter = create_counter_map();
+        let mut open_obj = open_test_object("xdp.bpf.o");
+        let prog = open_obj
+            .prog_mut("xdp_filter")
+            .expect("failed to find program");
+        let () = prog.set_prog_type(ProgramType::Kprobe);
+        let () = prog.set_attach_type(ProgramAttachType::TraceKprobeSession);
+        // Count invocations in the context of this process only.
+        let prologue = [
+            //   call bpf_get_current_pid_tgid
+            insn(
+                libbpf_sys::BPF_JMP | libbpf_sys::BPF_CALL,
+                0,
+                0,
+                0,
+                libbpf_sys::BPF_FUNC_get_current_pid_tgid as _,
+            ),
+            //   r0 >>= 32
+            insn(
+                libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_RSH | libbpf_sys::BPF_K,
+                0,
+                0,
+                0,
+                32,
+            ),
+            //   r6 = -1
+            insn(
+                libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K,
+                6,
+                0,
+                0,
+                -1,
+            ),
+            //   if r0 != <pid> goto +1
+            insn(
+                libbpf_sys::BPF_JMP | libbpf_sys::BPF_JNE | libbpf_sys::BPF_K,
+                0,
+                0,
+                1,
+                process::id() as _,
+            ),
+            //   r6 = 1
+            insn(
+                libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K,
+                6,
+                0,
+                0,
+                1,
+            ),
+        ];
+        let () = set_counter_insns_with_ret(prog, &prologue, &counter, ret);
+
+        let mut obj = open_obj.load().expect("failed to load object");
+        let prog = obj.prog_mut("xdp_filter").expect("failed to find program");
+
+        let opts = KprobeMultiOpts {
+            symbols: vec!["__x64_sys_getpid".to_string()],
+            session: true,
+            ..Default::default()
+        };
+        let link = prog
+            .attach_kprobe_multi_with_opts(opts)
+            .expect("failed to attach program");
 
-    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
-    let action = || {
         let _pid = unsafe { libc::getpid() };
+        drop(link);
+        take_counter(&counter)
     };
-    let result = with_ringbuffer(map, action);
 
-    assert_eq!(result, 1);
+    // A zero return value of the entry invocation requests the return
+    // invocation...
+    assert_eq!(count_invocations(0), 2);
+    // ... while a non-zero one skips it.
+    assert_eq!(count_invocations(1), 1);
 }
 
-/// Check that we can attach a BPF program to a kernel tracepoint, providing
-/// additional options.
+/// Check that we reject netfilter attachment requests with invalid
+/// priorities.
 #[test]
-fn test_sudo_object_tracepoint_with_opts() {
+fn test_sudo_attach_netfilter_invalid_priority() {
     bump_rlimit_mlock();
 
-    let cookie_val = 42u16;
-    let mut obj = get_test_object("tracepoint.bpf.o");
+    let mut obj = get_test_object("ksyscall.bpf.o");
     let prog = obj
-        .prog_mut("handle__tracepoint_with_cookie")
+        .prog_mut("handle__ksyscall")
         .expect("Failed to find program");
 
-    let opts = TracepointOpts {
-        cookie: cookie_val.into(),
-        ..TracepointOpts::default()
+    let opts = NetfilterOpts {
+        protocol_family: NetfilterProtocolFamily::Ipv6,
+        hook: NetfilterHook::LocalIn,
+        priority: i32::MIN,
+        ..Default::default()
     };
-    let _link = prog
-        .attach_tracepoint_with_opts("syscalls", "sys_enter_getpid", opts)
-        .expect("Failed to attach prog");
+    let err = prog.attach_netfilter_with_opts(opts).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
 
-    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
-    let action = || {
-        let _pid = unsafe { libc::getpid() };
+    let opts = NetfilterOpts {
+        priority: -400,
+        ip_defrag: true,
+        ..Default::default()
     };
-    let result = with_ringbuffer(map, action);
-
-    assert_eq!(result, cookie_val.into());
+    let err = prog.attach_netfilter_with_opts(opts).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
 }
 
-#[inline(never)]
-#[no_mangle]
-extern "C" fn uprobe_target() -> usize {
-    // Use `black_box` here as an additional barrier to inlining.
-    hint::black_box(42)
+/// Check that a program attached to a netfilter hook processes packets.
+#[test]
+fn test_sudo_attach_netfilter() {
+    bump_rlimit_mlock();
+
+    let counter = create_counter_map();
+    let mut open_obj = open_test_object("xdp.bpf.o");
+    let prog = open_obj
+        .prog_mut("xdp_filter")
+        .expect("failed to find program");
+    let ret = unsafe {
+        libbpf_sys::bpf_program__set_type(
+            prog.as_libbpf_object().as_ptr(),
+            libbpf_sys::BPF_PROG_TYPE_NETFILTER,
+        )
+    };
+    assert_eq!(ret, 0);
+    let () = prog.set_attach_type(ProgramAttachType::Netfilter);
+    // Count each packet, accepting it (`NF_ACCEPT`).
+    let prologue = [
+        //   r6 = 1
+        insn(
+            libbpf_sys::BPF_ALU64 | libbpf_sys::BPF_MOV | libbpf_sys::BPF_K,
+            6,
+            0,
+            0,
+            1,
+        ),
+    ];
+    let () = set_counter_insns_with_ret(prog, &prologue, &counter, 1);
+
+    let mut obj = open_obj.load().expect("failed to load object");
+    let prog = obj.prog_mut("xdp_filter").expect("failed to find program");
+
+    let opts = NetfilterOpts {
+        protocol_family: NetfilterProtocolFamily::Ipv4,
+        hook: NetfilterHook::LocalIn,
+        priority: -128,
+        ip_defrag: true,
+        ..Default::default()
+    };
+    let link = prog
+        .attach_netfilter_with_opts(opts)
+        .expect("failed to attach netfilter program");
+
+    let info = query::LinkInfo::load_from_fd(link.as_fd()).unwrap();
+    match info.info {
+        query::LinkTypeInfo::Netfilter(info) => {
+            assert_eq!(info.protocol_family, libc::NFPROTO_IPV4 as u32);
+            assert_eq!(info.hooknum, libc::NF_INET_LOCAL_IN as u32);
+            assert_eq!(info.priority, -128);
+            assert_eq!(info.flags, libbpf_sys::BPF_F_NETFILTER_IP_DEFRAG);
+        }
+        other => panic!("unexpected link info: {other:?}"),
+    }
+
+    let send = || {
+        let socket = UdpSocket::bind("127.0.0.1:0").expect("failed to bind socket");
+        let addr = socket.local_addr().unwrap();
+        let _cnt = socket
+            .send_to(b"packet", addr)
+            .expect("failed to send packet");
+        let mut buf = [0; 16];
+        let _cnt = socket
+            .recv_from(&mut buf)
+            .expect("failed to receive packet");
+    };
+
+    let () = send();
+    // Other local traffic may be counted as well.
+    assert!(take_counter(&counter) >= 1);
+
+    drop(link);
+    let () = send();
+    assert_eq!(take_counter(&counter), 0);
 }
 
-/// Check that we can attach a BPF program to a uprobe.
+/// Check that we can bind a map to a program that does not reference it.
 #[test]
-fn test_sudo_object_uprobe_with_opts() {
+fn test_sudo_program_bind_map() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("uprobe.bpf.o");
+    let obj = get_test_object("tracepoint.bpf.o");
     let prog = obj
-        .prog_mut("handle__uprobe")
+        .prog("handle__tracepoint")
         .expect("Failed to find program");
+    let map = obj.map("queue").expect("Failed to find map");
 
-    let pid = unsafe { libc::getpid() };
-    let path = current_exe().expect("Failed to find executable name");
-    let func_offset = 0;
-    let opts = UprobeOpts {
-        func_name: "uprobe_target".to_string(),
-        ..Default::default()
-    };
-    let _link = prog
-        .attach_uprobe_with_opts(pid, path, func_offset, opts)
-        .expect("Failed to attach prog");
+    let () = prog.bind_map(map.as_fd()).expect("failed to bind map");
+}
 
-    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
-    let action = || {
-        let _ = uprobe_target();
-    };
-    let result = with_ringbuffer(map, action);
+/// Check that we can create a `ProgramHandle` for a loaded program from its
+/// id.
+#[test]
+fn test_sudo_program_handle_from_prog_id() {
+    bump_rlimit_mlock();
 
-    assert_eq!(result, 1);
+    let obj = get_test_object("tracepoint.bpf.o");
+    let prog = obj
+        .prog("handle__tracepoint")
+        .expect("Failed to find program");
+    let id = Program::get_id_by_fd(prog.as_fd()).expect("failed to get program id");
+
+    let handle = ProgramHandle::from_prog_id(id).expect("failed to create program handle");
+    // The full name is reported, just as for handles created from a
+    // `Program`.
+    assert_eq!(handle.name(), "handle__tracepoint");
+    assert!(matches!(handle.prog_type(), ProgramType::Tracepoint));
+    let from_prog = ProgramHandle::from_program(prog).expect("failed to create program handle");
+    assert_eq!(from_prog.name(), handle.name());
+
+    let fd = prog.as_fd().try_clone_to_owned().unwrap();
+    let from_fd = ProgramHandle::from_fd(fd).expect("failed to create program handle");
+    assert_eq!(from_fd.name(), handle.name());
+
+    let info = handle
+        .info(&query::ProgInfoQueryOptions::default())
+        .expect("failed to query program info");
+    assert_eq!(info.id, id);
+
+    let clone = ProgramHandle::try_clone(&handle).expect("failed to clone handle");
+    assert_eq!(clone.name(), handle.name());
+    let map = obj.map("queue").expect("Failed to find map");
+    let () = clone.bind_map(map.as_fd()).expect("failed to bind map");
 }
 
-/// Check that we can attach a BPF program to a uprobe and access the cookie
-/// provided during attach.
+/// Check that we refuse to attach a program to a writable raw tracepoint if
+/// it is not of the corresponding type.
 #[test]
-fn test_sudo_object_uprobe_with_cookie() {
+fn test_sudo_attach_raw_tracepoint_writable_invalid_type() {
     bump_rlimit_mlock();
 
-    let cookie_val = 5u16;
-    let mut obj = get_test_object("uprobe.bpf.o");
+    let mut obj = get_test_object("runqslower.bpf.o");
     let prog = obj
-        .prog_mut("handle__uprobe_with_cookie")
+        .prog_mut("handle__sched_wakeup")
         .expect("Failed to find program");
 
-    let pid = unsafe { libc::getpid() };
-    let path = current_exe().expect("Failed to find executable name");
-    let func_offset = 0;
-    let opts = UprobeOpts {
-        func_name: "uprobe_target".to_string(),
-        cookie: cookie_val.into(),
-        ..Default::default()
-    };
-    let _link = prog
-        .attach_uprobe_with_opts(pid, path, func_offset, opts)
-        .expect("Failed to attach prog");
+    let err = prog
+        .attach_raw_tracepoint_writable("sched_wakeup")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
 
-    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
-    let action = || {
-        let _ = uprobe_target();
-    };
-    let result = with_ringbuffer(map, action);
+/// Check that attachment failures name the libbpf function and program
+/// involved.
+#[test]
+fn test_sudo_attach_error_libbpf_call() {
+    bump_rlimit_mlock();
 
-    assert_eq!(result, cookie_val.into());
+    let mut obj = get_test_object("runqslower.bpf.o");
+    let prog = obj
+        .prog_mut("handle__sched_wakeup")
+        .expect("Failed to find program");
+
+    let err = prog
+        .attach_kprobe(false, "libbpf_rs_does_not_exist")
+        .unwrap_err();
+    let call = err.libbpf_call().unwrap();
+    assert_eq!(call.function(), "bpf_program__attach_kprobe");
+    assert_eq!(call.name(), "handle__sched_wakeup");
+    assert!(
+        err.to_string()
+            .starts_with("bpf_program__attach_kprobe(handle__sched_wakeup) failed: "),
+        "{err}"
+    );
 }
 
-/// Check that we can link multiple object files.
+/// Check that we can atomically replace a link's program, but only if the
+/// link currently refers to the expected one.
 #[test]
-fn test_object_link_files() {
-    fn test(files: Vec<PathBuf>) {
-        let output_file = NamedTempFile::new().unwrap();
+fn test_sudo_link_replace_prog() {
+    bump_rlimit_mlock();
 
-        let mut linker = Linker::new(output_file.path()).unwrap();
-        let () = files
-            .into_iter()
-            .try_for_each(|file| linker.add_file(file))
-            .unwrap();
-        let () = linker.link().unwrap();
+    let mut obj = get_test_object("xdp.bpf.o");
+    let obj1 = get_test_object("xdp.bpf.o");
+    let obj2 = get_test_object("xdp.bpf.o");
+    let prog1 = obj1.prog("xdp_filter").expect("Failed to find program");
+    let prog2 = obj2.prog("xdp_filter").expect("Failed to find program");
+
+    let prog = obj.prog_mut("xdp_filter").expect("Failed to find program");
+    // Attach to the loopback device.
+    let mut link = prog.attach_xdp(1).expect("Failed to attach prog");
+
+    let () = link
+        .replace_prog(prog, prog1)
+        .expect("Failed to replace prog");
+    // `prog` is no longer attached, so it can't be replaced.
+    let _err = link.replace_prog(prog, prog2).unwrap_err();
+    let () = link
+        .replace_prog(prog1, prog2)
+        .expect("Failed to replace prog");
+}
 
-        // Check that we can load the resulting object file.
-        let _object = ObjectBuilder::default()
-            .debug(true)
-            .open_file(output_file.path())
-            .unwrap();
-    }
+/// Check that we refuse to update a link or query the `struct_ops` state
+/// with a map that is not a `struct_ops` map.
+#[test]
+fn test_sudo_link_update_map_invalid_type() {
+    bump_rlimit_mlock();
 
-    let obj_path1 = get_test_object_path("usdt.bpf.o");
-    let obj_path2 = get_test_object_path("ringbuf.bpf.o");
+    let mut obj = get_test_object("xdp.bpf.o");
+    let prog = obj.prog_mut("xdp_filter").expect("Failed to find program");
+    // Attach to the loopback device.
+    let mut link = prog.attach_xdp(1).expect("Failed to attach prog");
 
-    test(vec![obj_path1.clone()]);
-    test(vec![obj_path1, obj_path2]);
-}
+    let obj = get_test_object("runqslower.bpf.o");
+    let map = obj.map("start").expect("failed to find map");
+    let err = link.update_map(map).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
 
-/// Get access to the underlying per-cpu ring buffer data.
-fn buffer<'a>(perf: &'a libbpf_rs::PerfBuffer, buf_idx: usize) -> &'a [u8] {
-    let perf_buff_ptr = perf.as_libbpf_object();
-    let mut buffer_data_ptr: *mut c_void = ptr::null_mut();
-    let mut buffer_size: usize = 0;
-    let ret = unsafe {
-        libbpf_sys::perf_buffer__buffer(
-            perf_buff_ptr.as_ptr(),
-            buf_idx as i32,
-            ptr::addr_of_mut!(buffer_data_ptr),
-            ptr::addr_of_mut!(buffer_size) as *mut libbpf_sys::size_t,
-        )
-    };
-    assert!(ret >= 0);
-    unsafe { slice::from_raw_parts(buffer_data_ptr as *const u8, buffer_size) }
+    let err = map.struct_ops_state().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
 }
 
-/// Check that we can see the raw ring buffer of the perf buffer and find a
-/// value we have sent.
+/// Check that we can switch the `struct_ops` map backing a link.
 #[test]
-fn test_sudo_object_perf_buffer_raw() {
-    use memmem::Searcher;
-    use memmem::TwoWaySearcher;
-
+fn test_sudo_link_update_map() {
     bump_rlimit_mlock();
 
-    let cookie_val = 42u16;
-    let mut obj = get_test_object("tracepoint.bpf.o");
-    let prog = obj
-        .prog_mut("handle__tracepoint_with_cookie_pb")
-        .expect("Failed to find program");
-
-    let opts = TracepointOpts {
-        cookie: cookie_val.into(),
-        ..TracepointOpts::default()
+    let obj = get_test_object("struct_ops.bpf.o");
+    let map1 = obj.map("ca_1").expect("failed to find map");
+    let map2 = obj.map("ca_2").expect("failed to find map");
+    let map_id = |link: &Link| match link.info().expect("failed to query link").info {
+        query::LinkTypeInfo::StructOps(info) => info.map_id,
+        info => panic!("unexpected link info: {info:?}"),
     };
-    let _link = prog
-        .attach_tracepoint_with_opts("syscalls", "sys_enter_getpid", opts)
-        .expect("Failed to attach prog");
 
-    let map = obj.map("pb").expect("Failed to get perf-buffer map");
+    let mut link = map1.attach_struct_ops().expect("failed to attach map");
+    assert_eq!(map_id(&link), map1.info().unwrap().info.id);
+    assert_eq!(map1.struct_ops_state().unwrap(), StructOpsState::Ready);
 
-    let cookie_bytes = cookie_val.to_ne_bytes();
-    let searcher = TwoWaySearcher::new(&cookie_bytes[..]);
+    let () = link.update_map(map2).expect("failed to update link");
+    assert_eq!(map_id(&link), map2.info().unwrap().info.id);
+    assert_eq!(map2.struct_ops_state().unwrap(), StructOpsState::Ready);
+}
 
-    let perf = libbpf_rs::PerfBufferBuilder::new(map)
-        .build()
-        .expect("Failed to build");
+/// Check that a link can be detached while the link object itself stays
+/// valid.
+#[test]
+fn test_sudo_link_detach() {
+    bump_rlimit_mlock();
 
-    // Make an action that the tracepoint will see
-    let _pid = unsafe { libc::getpid() };
+    let mut obj = get_test_object("xdp.bpf.o");
+    let prog = obj.prog_mut("xdp_filter").expect("Failed to find program");
+    // Attach to the loopback device.
+    let mut link = prog.attach_xdp(1).expect("Failed to attach prog");
 
-    let found_cookie = (0..perf.buffer_cnt()).any(|buf_idx| {
-        let buf = buffer(&perf, buf_idx);
-        searcher.search_in(buf).is_some()
-    });
+    // The device is occupied as long as the program is attached.
+    let _err = prog.attach_xdp(1).unwrap_err();
 
-    assert!(found_cookie);
+    let () = link.detach().expect("Failed to detach link");
+    // Once detached, the device is free again, even though `link` still
+    // exists.
+    let _link = prog.attach_xdp(1).expect("Failed to attach prog");
+    drop(link);
 }
 
-/// Check that we can get map pin status and map pin path
+/// Check that we can query information about a link.
 #[test]
-fn test_sudo_map_pinned_status() {
+fn test_sudo_link_info() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("map_auto_pin.bpf.o");
-    let map = obj
-        .map("auto_pin_map")
-        .expect("failed to find map 'auto_pin_map'");
+    let mut obj = get_test_object("xdp.bpf.o");
+    let prog = obj.prog_mut("xdp_filter").expect("Failed to find program");
+    // Attach to the loopback device.
+    let link = prog.attach_xdp(1).expect("Failed to attach prog");
 
-    let is_pinned = map.is_pinned();
-    assert!(is_pinned);
-    let expected_path = "/sys/fs/bpf/auto_pin_map";
-    let get_path = map.get_pin_path().expect("get map pin path failed");
-    assert_eq!(expected_path, get_path.to_str().unwrap());
-    // cleanup
-    let _ = fs::remove_file(expected_path);
+    let info = link.info().expect("Failed to query link info");
+    assert!(
+        matches!(
+            info.info,
+            query::LinkTypeInfo::Xdp(query::XdpLinkInfo { ifindex: 1 })
+        ),
+        "{info:?}"
+    );
 }
 
-/// Change the root_pin_path and see if it works.
+/// Check that converting a link into a file descriptor keeps the program
+/// attached for as long as the file descriptor is alive.
 #[test]
-fn test_sudo_map_pinned_status_with_pin_root_path() {
+fn test_sudo_link_into_fd() {
     bump_rlimit_mlock();
 
-    let obj_path = get_test_object_path("map_auto_pin.bpf.o");
-    let obj = ObjectBuilder::default()
-        .debug(true)
-        .pin_root_path("/sys/fs/bpf/test_namespace")
-        .expect("root_pin_path failed")
-        .open_file(obj_path)
-        .expect("failed to open object")
-        .load()
-        .expect("failed to load object");
+    let mut obj = get_test_object("xdp.bpf.o");
+    let prog = obj.prog_mut("xdp_filter").expect("Failed to find program");
+    // Attach to the loopback device.
+    let link = prog.attach_xdp(1).expect("Failed to attach prog");
 
-    let map = obj
-        .map("auto_pin_map")
-        .expect("failed to find map 'auto_pin_map'");
+    let fd = link.into_fd().expect("Failed to convert link");
+    // The device is still occupied.
+    let _err = prog.attach_xdp(1).unwrap_err();
 
-    let is_pinned = map.is_pinned();
-    assert!(is_pinned);
-    let expected_path = "/sys/fs/bpf/test_namespace/auto_pin_map";
-    let get_path = map.get_pin_path().expect("get map pin path failed");
-    assert_eq!(expected_path, get_path.to_str().unwrap());
-    // cleanup
-    let _ = fs::remove_file(expected_path);
-    let _ = fs::remove_dir("/sys/fs/bpf/test_namespace");
+    drop(fd);
+    let _link = prog.attach_xdp(1).expect("Failed to attach prog");
 }
 
-/// Check that we can get program fd by id and vice versa.
+/// Check that we can look up a link by its id.
 #[test]
-fn test_sudo_program_get_fd_and_id() {
+fn test_sudo_link_from_id() {
     bump_rlimit_mlock();
 
-    let obj = get_test_object("runqslower.bpf.o");
-    let prog = obj
-        .prog("handle__sched_wakeup")
-        .expect("failed to find program");
-
-    let prog_fd = prog.as_fd();
-    let prog_id = Program::get_id_by_fd(prog_fd).expect("failed to get program id by fd");
-    let _owned_prog_fd = Program::get_fd_by_id(prog_id).expect("failed to get program fd by id");
+    let mut obj = get_test_object("xdp.bpf.o");
+    let prog = obj.prog_mut("xdp_filter").expect("Failed to find program");
+    // Attach to the loopback device.
+    let link = prog.attach_xdp(1).expect("Failed to attach prog");
+
+    let id = link.id().expect("Failed to get link id");
+    let mut link_by_id = Link::from_id(id).expect("Failed to get link by id");
+    assert_eq!(link_by_id.id().unwrap(), id);
+
+    let info = link_by_id.info().expect("Failed to query link info");
+    assert_eq!(info.prog_id, Program::get_id_by_fd(prog.as_fd()).unwrap());
+
+    // Detaching through the link looked up by id detaches the original
+    // link as well.
+    let () = link_by_id.detach().expect("Failed to detach link");
+    drop(link_by_id);
+    let info = link.info().expect("Failed to query link info");
+    assert!(matches!(
+        info.info,
+        query::LinkTypeInfo::Xdp(query::XdpLinkInfo { ifindex: 0 })
+    ));
 }
 
-/// Check that autocreate disabled maps don't prevent object loading
+/// Check that we can query detailed information about a single program.
 #[test]
-fn test_sudo_map_autocreate_disable() {
+fn test_sudo_program_info() {
     bump_rlimit_mlock();
 
-    let mut open_obj = open_test_object("map_auto_pin.bpf.o");
-
-    open_obj
-        .map_mut("auto_pin_map")
-        .expect("map wasn't found")
-        .set_autocreate(false)
-        .expect("set_autocreate() failed");
+    let obj = get_test_object("tracepoint.bpf.o");
+    let prog = obj
+        .prog("handle__tracepoint")
+        .expect("Failed to find program");
 
-    open_obj.load().expect("failed to load object");
+    let opts = query::ProgInfoQueryOptions::default().include_all();
+    let info = query::ProgramInfo::load_from_fd(prog.as_fd(), &opts)
+        .expect("Failed to query program info");
+    assert_eq!(info.id, Program::get_id_by_fd(prog.as_fd()).unwrap());
+    assert!(!info.xlated_prog_insns.is_empty());
+    assert!(!info.map_ids.is_empty());
+    assert!(info.verified_insns > 0);
 }
 
-/// Check that we can resize a map.
+/// Check that we can withhold BTF function and line information from the
+/// kernel when loading programs.
 #[test]
-fn test_sudo_map_resize() {
+fn test_sudo_object_func_info_disabled() {
     bump_rlimit_mlock();
 
-    let mut open_obj = open_test_object("map_auto_pin.bpf.o");
+    let func_info = |enabled: bool| {
+        let obj = ObjectBuilder::default()
+            .func_info(enabled)
+            .open_file(get_test_object_path("tracepoint.bpf.o"))
+            .expect("failed to open object")
+            .load()
+            .expect("failed to load object");
+        assert_eq!(obj.name().unwrap(), "tracepoint");
+        let prog = obj
+            .prog("handle__tracepoint")
+            .expect("Failed to find program");
+        let opts = query::ProgInfoQueryOptions::default().include_all();
+        let info = query::ProgramInfo::load_from_fd(prog.as_fd(), &opts)
+            .expect("Failed to query program info");
+        (info.func_info.len(), info.line_info.len())
+    };
 
-    let resizable = open_obj
-        .map_mut(".data.resizable_data")
-        .expect("map wasn't found");
+    let (funcs, lines) = func_info(true);
+    assert_ne!(funcs, 0);
+    assert_ne!(lines, 0);
+    assert_eq!(func_info(false), (0, 0));
+}
 
-    let len = resizable.initial_value().unwrap().len();
-    assert_eq!(len, size_of::<u64>());
+/// Check that attached links show up when iterating over all links.
+#[test]
+fn test_sudo_link_info_iter() {
+    bump_rlimit_mlock();
 
-    let () = resizable
-        .set_value_size(len as u32 * 2)
-        .expect("failed to set value size");
-    let new_len = resizable.initial_value().unwrap().len();
-    assert_eq!(new_len, len * 2);
+    let mut obj = get_test_object("xdp.bpf.o");
+    let prog = obj.prog_mut("xdp_filter").expect("Failed to find program");
+    let prog_id = Program::get_id_by_fd(prog.as_fd()).unwrap();
+    // Attach to the loopback device.
+    let link = prog.attach_xdp(1).expect("Failed to attach prog");
+    let link_id = link.id().unwrap();
+
+    let info = query::LinkInfoIter::default()
+        .find(|info| info.id == link_id)
+        .expect("Failed to find link");
+    assert_eq!(info.prog_id, prog_id);
+    assert!(matches!(info.info, query::LinkTypeInfo::Xdp(_)), "{info:?}");
 }
 
-/// Check that we are able to attach using ksyscall
+/// Check that the kernel's own BTF shows up when iterating over BTF objects.
 #[test]
-fn test_sudo_attach_ksyscall() {
+fn test_sudo_btf_info_iter() {
+    let info = query::BtfInfoIter::default()
+        .find(|info| info.name.as_bytes() == b"vmlinux")
+        .expect("Failed to find vmlinux BTF");
+    assert!(info.kernel_btf);
+    assert!(!info.btf.is_empty());
+}
+
+/// Check that we can retrieve information about arbitrary BPF object file
+/// descriptors, but only when asking for the proper kind.
+#[test]
+fn test_sudo_object_info() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("ksyscall.bpf.o");
+    let obj = get_test_object("tracepoint.bpf.o");
+    let map = obj.map("queue").expect("Failed to find map");
     let prog = obj
-        .prog_mut("handle__ksyscall")
+        .prog("handle__tracepoint")
         .expect("Failed to find program");
 
-    let _link = prog
-        .attach_ksyscall(false, "kill")
-        .expect("Failed to attach prog");
+    let info = query::object_info::<query::MapInfo>(map.as_fd()).expect("failed to get map info");
+    assert_eq!(info.name.to_bytes(), b"queue");
+    let info =
+        query::object_info::<query::ProgramInfo>(prog.as_fd()).expect("failed to get program info");
+    assert_eq!(info.name.to_bytes(), b"handle__tracepo");
 
-    let map = obj.map("ringbuf").expect("Failed to get ringbuf map");
-    let action = || {
-        // Send `SIGCHLD`, which is ignored by default, to our process.
-        let ret = unsafe { libc::kill(libc::getpid(), libc::SIGCHLD) };
-        if ret < 0 {
-            panic!("kill failed: {}", io::Error::last_os_error());
-        }
-    };
-    let result = with_ringbuffer(map, action);
+    let err = query::object_info::<query::ProgramInfo>(map.as_fd()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
 
-    assert_eq!(result, 1);
+/// Check that we can enable the collection of run time statistics.
+#[test]
+fn test_sudo_run_time_stats() {
+    let stats = RunTimeStats::enable().expect("Failed to enable stats");
+    drop(stats);
 }
 
-/// Check that we can invoke a program directly.
+/// Check that we can find the processes holding a map.
 #[test]
-fn test_sudo_run_prog_success() {
+fn test_sudo_fd_owners() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("run_prog.bpf.o");
-    let prog = obj.prog_mut("test_1").expect("Failed to find program");
-
-    #[repr(C)]
-    struct bpf_dummy_ops_state {
-        val: c_int,
-    }
-
-    let value = 42;
-    let state = bpf_dummy_ops_state { val: value };
-    let args = [addr_of!(state) as u64];
-    let input = ProgramInput {
-        context_in: Some(unsafe { plain::as_bytes(&args) }),
+    let opts = libbpf_sys::bpf_map_create_opts {
+        sz: size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
         ..Default::default()
     };
-    let output = prog.test_run(input).unwrap();
-    assert_eq!(output.return_value, value as _);
+    let map = MapHandle::create(MapType::Hash, Some("owned_map"), 4, 4, 8, &opts).unwrap();
+    let map_id = MapInfo::new(map.as_fd()).unwrap().info.id;
+
+    let owners = query::fd_owners(query::FdObjectType::Map, map_id).unwrap();
+    let owner = owners
+        .iter()
+        .find(|owner| owner.pid == process::id())
+        .expect("Failed to find own process");
+    assert_eq!(owner.fd, map.as_fd().as_raw_fd());
 }
 
-/// Check that we fail program invocation when providing insufficient arguments.
+/// Check that we can query the programs attached to a cgroup.
 #[test]
-fn test_sudo_run_prog_fail() {
+fn test_sudo_cgroup_progs() {
+    let root = cgroup::root().expect("Failed to find cgroup hierarchy");
+    let cgroup = cgroup::open(root).expect("Failed to open root cgroup");
+    let progs = query::cgroup_progs(cgroup.as_fd(), ProgramAttachType::CgroupInetIngress, true)
+        .expect("Failed to query effective cgroup programs");
+    assert!(progs.prog_attach_flags.is_empty());
+    let _progs = query::cgroup_progs(cgroup.as_fd(), ProgramAttachType::CgroupInetIngress, false)
+        .expect("Failed to query cgroup programs");
+}
+
+/// Check that we can query the programs attached to a network namespace.
+#[test]
+fn test_sudo_netns_progs() {
+    let netns = fs::File::open("/proc/self/ns/net").expect("Failed to open netns");
+    let progs = query::netns_progs(netns.as_fd(), ProgramAttachType::SkLookup)
+        .expect("Failed to query netns programs");
+    assert!(progs.prog_attach_flags.is_empty());
+}
+
+/// Check that we can filter maps while iterating over them.
+#[test]
+fn test_sudo_map_info_iter_filter() {
     bump_rlimit_mlock();
 
-    let mut obj = get_test_object("run_prog.bpf.o");
-    let prog = obj.prog_mut("test_2").expect("Failed to find program");
+    let obj = get_test_object("runqslower.bpf.o");
+    let map = obj.map("start").expect("failed to find map");
+    let map_id = map.info().unwrap().info.id;
+
+    let opts = query::MapInfoQueryOptions::default()
+        .map_type(MapType::Hash)
+        .name_prefix("sta");
+    let mut iter = query::MapInfoIter::with_query_opts(opts);
+    assert!(iter.any(|info| info.id == map_id));
+
+    let opts = query::MapInfoQueryOptions::default().map_type(MapType::Array);
+    let mut iter = query::MapInfoIter::with_query_opts(opts);
+    assert!(iter.all(|info| info.id != map_id));
+}
 
-    let input = ProgramInput::default();
-    let _err = prog.test_run(input).unwrap_err();
+/// Check that we can filter programs while iterating over them.
+#[test]
+fn test_sudo_prog_info_iter_filter() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("tracepoint.bpf.o");
+    let prog = obj
+        .prog("handle__tracepoint")
+        .expect("Failed to find program");
+    let prog_id = Program::get_id_by_fd(prog.as_fd()).unwrap();
+
+    let opts = query::ProgInfoQueryOptions::default()
+        .prog_type(ProgramType::Tracepoint)
+        .name_prefix("handle__tracep");
+    let mut iter = query::ProgInfoIter::with_query_opts(opts);
+    assert!(iter.any(|info| info.id == prog_id));
+
+    let opts = query::ProgInfoQueryOptions::default().prog_type(ProgramType::Xdp);
+    let mut iter = query::ProgInfoIter::with_query_opts(opts);
+    assert!(iter.all(|info| info.id != prog_id));
 }
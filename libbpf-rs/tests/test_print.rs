@@ -7,7 +7,9 @@
 
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
+use std::thread;
 
+use libbpf_rs::capture_output;
 use libbpf_rs::get_print;
 use libbpf_rs::set_print;
 use libbpf_rs::ObjectBuilder;
@@ -78,3 +80,111 @@ fn test_set_and_save_print() {
     let prev = set_print(None);
     assert_eq!(prev, Some((PrintLevel::Debug, callback2 as PrintCallback)));
 }
+
+/// Check that libbpf output gets attributed to the failing operation, even
+/// if multiple threads use libbpf concurrently.
+#[test]
+#[serial]
+fn test_capture_output() {
+    let _prev = set_print(None);
+
+    let threads = (0..4)
+        .map(|i| {
+            thread::spawn(move || {
+                let path = format!("/does-not-exist-{i}");
+                let err = capture_output(|| ObjectBuilder::default().open_file(&path))
+                    .map(|_obj| ())
+                    .unwrap_err();
+                (i, err)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        let (i, err) = thread.join().unwrap();
+        let output = err.libbpf_output().expect("no libbpf output captured");
+        assert!(output.contains(&format!("/does-not-exist-{i}")), "{output}");
+        for j in (0..4).filter(|j| *j != i) {
+            assert!(
+                !output.contains(&format!("/does-not-exist-{j}")),
+                "{output}"
+            );
+        }
+        assert!(format!("{err:?}").contains("libbpf output:"), "{err:?}");
+    }
+
+    // Capturing is transparent: the callback gets restored.
+    assert_eq!(get_print(), None);
+}
+
+/// Check that capturing output does not interfere with setting and
+/// retrieving the print callback.
+#[test]
+#[serial]
+fn test_capture_output_set_print() {
+    fn callback(_: PrintLevel, _: String) {}
+
+    let _prev = set_print(None);
+    let result = capture_output(|| {
+        assert_eq!(get_print(), None);
+        let prev = set_print(Some((PrintLevel::Warn, callback as PrintCallback)));
+        assert_eq!(prev, None);
+        ObjectBuilder::default().open_file("/does-not-exist")
+    });
+    assert!(result.is_err());
+
+    // The callback set during the capture stays in place.
+    assert_eq!(
+        get_print(),
+        Some((PrintLevel::Warn, callback as PrintCallback))
+    );
+    let _prev = set_print(None);
+}
+
+#[cfg(feature = "log")]
+#[test]
+#[serial]
+fn test_set_print_to_log() {
+    use std::sync::Mutex;
+
+    use log::Log;
+    use log::Metadata;
+    use log::Record;
+
+    static RECORDS: Mutex<Vec<(log::Level, String, String)>> = Mutex::new(Vec::new());
+
+    struct Logger;
+
+    impl Log for Logger {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            RECORDS.lock().unwrap().push((
+                record.level(),
+                record.target().to_string(),
+                record.args().to_string(),
+            ));
+        }
+
+        fn flush(&self) {}
+    }
+
+    let () = log::set_logger(&Logger).unwrap();
+    let () = log::set_max_level(log::LevelFilter::Debug);
+
+    let _prev = libbpf_rs::set_print_to_log();
+    let obj = ObjectBuilder::default().open_file("/dev/null");
+    assert!(obj.is_err(), "Successfully loaded /dev/null?");
+    let _prev = set_print(None);
+
+    let records = RECORDS.lock().unwrap();
+    let (_level, target, msg) = records
+        .iter()
+        .find(|(level, _, _)| *level == log::Level::Warn)
+        .expect("Did not log a warning");
+    assert_eq!(target, "libbpf");
+    assert!(!msg.starts_with("libbpf: "), "{msg}");
+    assert!(!msg.ends_with('\n'), "{msg}");
+}
@@ -0,0 +1,22 @@
+//! Tests for libbpf's global behavior settings. These live in their own file
+//! because the settings are process wide and some of them can only be changed
+//! before libbpf created any BPF objects.
+
+use libbpf_rs::set_memlock_rlimit;
+use libbpf_rs::set_strict_mode;
+use libbpf_rs::ErrorKind;
+use libbpf_rs::StrictMode;
+
+/// Check that we can configure libbpf's strict mode and that requesting
+/// unsupported legacy behavior is reported.
+#[test]
+fn test_set_strict_mode() {
+    let () = set_strict_mode(StrictMode::all()).unwrap();
+    let () = set_strict_mode(StrictMode::all() - StrictMode::AUTO_RLIMIT_MEMLOCK).unwrap();
+    let () = set_memlock_rlimit(16 << 20).unwrap();
+
+    let err = set_strict_mode(StrictMode::all() - StrictMode::CLEAN_PTRS).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+    let err = set_strict_mode(StrictMode::empty()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
@@ -8,9 +8,14 @@ use test::bump_rlimit_mlock;
 use test::get_test_object;
 
 use libbpf_rs::ErrorKind;
+use libbpf_rs::Program;
 use libbpf_rs::Result;
+use libbpf_rs::TcFilter;
 use libbpf_rs::TcHook;
 use libbpf_rs::TcHookBuilder;
+use libbpf_rs::TcQdisc;
+use libbpf_rs::TcQdiscKind;
+use libbpf_rs::ETH_P_ALL;
 use libbpf_rs::TC_CUSTOM;
 use libbpf_rs::TC_EGRESS;
 use libbpf_rs::TC_H_CLSACT;
@@ -344,6 +349,318 @@ fn test_sudo_tc_query() {
     assert!(custom.query().is_err());
 }
 
+#[test]
+#[serial]
+fn test_sudo_tc_query_filter() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("tc-unit.bpf.o");
+    let fd = obj.prog("handle_tc").unwrap().as_fd();
+    let prog_id = Program::get_id_by_fd(fd).unwrap();
+
+    let mut tc_builder = TcHookBuilder::new(fd);
+    tc_builder.ifindex(LO_IFINDEX).handle(42).priority(7);
+    assert!(clear_clsact(fd).is_ok());
+
+    let mut egress = tc_builder.hook(TC_EGRESS);
+    assert!(egress.create().is_ok());
+    assert!(egress.attach().is_ok());
+
+    // A second, non-replacing attach fails, but the existing filter can be
+    // discovered.
+    let mut other = tc_builder.hook(TC_EGRESS);
+    let err = other.attach().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+    let filter = other.query().unwrap();
+    assert_eq!(
+        filter,
+        TcFilter {
+            prog_id,
+            handle: 42,
+            priority: 7,
+        }
+    );
+    let prog_fd = filter.prog_fd().unwrap();
+    assert_eq!(Program::get_id_by_fd(prog_fd.as_fd()).unwrap(), prog_id);
+
+    assert!(egress.detach().is_ok());
+    let err = other.query().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    assert!(clear_clsact(fd).is_ok());
+}
+
+#[test]
+#[serial]
+fn test_sudo_tc_attach_auto_handle_priority() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("tc-unit.bpf.o");
+    let fd = obj.prog("handle_tc").unwrap().as_fd();
+
+    let mut tc_builder = TcHookBuilder::new(fd);
+    tc_builder.ifindex(LO_IFINDEX);
+    assert!(clear_clsact(fd).is_ok());
+
+    let mut ingress = tc_builder.hook(TC_INGRESS);
+    assert!(ingress.create().is_ok());
+    assert!(ingress.attach().is_ok());
+    // The kernel assigned handle and priority are reported back.
+    assert_ne!(ingress.get_handle(), 0);
+    assert_ne!(ingress.get_priority(), 0);
+
+    let filter = ingress.query().unwrap();
+    assert_eq!(filter.handle, ingress.get_handle());
+    assert_eq!(filter.priority, ingress.get_priority());
+
+    // Replacing requires the filter to be identified.
+    let mut replacer = tc_builder.hook(TC_INGRESS);
+    replacer.replace(true);
+    let err = replacer.attach().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    replacer
+        .handle(ingress.get_handle())
+        .priority(ingress.get_priority());
+    assert!(replacer.attach().is_ok());
+    assert_eq!(replacer.get_handle(), ingress.get_handle());
+    assert_eq!(replacer.get_priority(), ingress.get_priority());
+
+    assert!(ingress.detach().is_ok());
+    assert!(clear_clsact(fd).is_ok());
+}
+
+/// Check that we can attach, query, and detach filters in a chain other than 0 that apply to a
+/// single protocol.
+#[test]
+#[serial]
+fn test_sudo_tc_attach_chain_protocol() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("tc-unit.bpf.o");
+    let fd = obj.prog("handle_tc").unwrap().as_fd();
+    let prog_id = Program::get_id_by_fd(fd).unwrap();
+
+    let mut tc_builder = TcHookBuilder::new(fd);
+    tc_builder
+        .ifindex(LO_IFINDEX)
+        .chain(3)
+        .protocol(libc::ETH_P_IP as u16);
+    assert!(clear_clsact(fd).is_ok());
+
+    let mut ingress = tc_builder.hook(TC_INGRESS);
+    assert_eq!(ingress.get_chain(), 3);
+    assert_eq!(ingress.get_protocol(), libc::ETH_P_IP as u16);
+    assert!(ingress.create().is_ok());
+    assert!(ingress.attach().is_ok());
+    assert_ne!(ingress.get_handle(), 0);
+    assert_ne!(ingress.get_priority(), 0);
+
+    let filter = ingress.query().unwrap();
+    assert_eq!(filter.prog_id, prog_id);
+    assert_eq!(filter.handle, ingress.get_handle());
+    assert_eq!(filter.priority, ingress.get_priority());
+
+    let filters = TcHook::list_filters(LO_IFINDEX, TC_INGRESS).unwrap();
+    assert_eq!(filters.len(), 1);
+    assert_eq!(filters[0].chain, 3);
+    assert_eq!(filters[0].protocol, libc::ETH_P_IP as u16);
+    assert_eq!(filters[0].prog_id, prog_id);
+    assert_eq!(filters[0].name.as_deref(), Some("handle_tc"));
+    assert_eq!(
+        filters[0].filter_name,
+        Some(format!("handle_tc:[{prog_id}]"))
+    );
+
+    // The filter is not found in chain 0.
+    let mut other = tc_builder.hook(TC_INGRESS);
+    other
+        .chain(0)
+        .protocol(ETH_P_ALL)
+        .handle(ingress.get_handle())
+        .priority(ingress.get_priority());
+    assert!(other.query().is_err());
+
+    // Attaching the same filter again fails, unless replacing it.
+    let mut replacer = tc_builder.hook(TC_INGRESS);
+    replacer
+        .handle(ingress.get_handle())
+        .priority(ingress.get_priority());
+    let err = replacer.attach().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    assert!(replacer.replace(true).attach().is_ok());
+
+    assert!(ingress.detach().is_ok());
+    let err = ingress.query().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    let filters = TcHook::list_filters(LO_IFINDEX, TC_INGRESS).unwrap();
+    assert!(filters.is_empty());
+
+    assert!(clear_clsact(fd).is_ok());
+}
+
+#[test]
+#[serial]
+fn test_sudo_tc_list_filters() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("tc-unit.bpf.o");
+    let fd = obj.prog("handle_tc").unwrap().as_fd();
+    let prog_id = Program::get_id_by_fd(fd).unwrap();
+
+    assert!(clear_clsact(fd).is_ok());
+    let filters = TcHook::list_filters(LO_IFINDEX, TC_INGRESS).unwrap();
+    assert!(filters.is_empty());
+
+    let mut tc_builder = TcHookBuilder::new(fd);
+    tc_builder.ifindex(LO_IFINDEX).handle(1);
+
+    let mut ingress1 = tc_builder.hook(TC_INGRESS);
+    assert!(ingress1.create().is_ok());
+    assert!(ingress1.priority(1).attach().is_ok());
+    let mut ingress2 = tc_builder.hook(TC_INGRESS);
+    assert!(ingress2.priority(2).attach().is_ok());
+
+    let filters = TcHook::list_filters(LO_IFINDEX, TC_INGRESS).unwrap();
+    assert_eq!(filters.len(), 2);
+    for (filter, priority) in filters.iter().zip([1, 2]) {
+        assert_eq!(filter.handle, 1);
+        assert_eq!(filter.priority, priority);
+        assert_eq!(filter.chain, 0);
+        assert_eq!(filter.prog_id, prog_id);
+        assert_eq!(filter.name.as_deref(), Some("handle_tc"));
+        assert_eq!(filter.filter_name, Some(format!("handle_tc:[{prog_id}]")));
+    }
+
+    let filters = TcHook::list_filters(LO_IFINDEX, TC_EGRESS).unwrap();
+    assert!(filters.is_empty());
+
+    let err = TcHook::list_filters(LO_IFINDEX, TC_CUSTOM).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    assert!(clear_clsact(fd).is_ok());
+}
+
+#[test]
+#[serial]
+fn test_sudo_tc_qdisc_ownership() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("tc-unit.bpf.o");
+    let fd = obj.prog("handle_tc").unwrap().as_fd();
+    assert!(clear_clsact(fd).is_ok());
+
+    let qdisc = TcQdisc::clsact("lo").unwrap();
+    assert!(qdisc.created());
+    assert_eq!(qdisc.ifindex(), LO_IFINDEX);
+
+    // Another user finds the qdisc in place and does not remove it.
+    let other = TcQdisc::clsact(LO_IFINDEX).unwrap();
+    assert!(!other.created());
+
+    let mut ingress = TcHookBuilder::new(fd)
+        .ifname("lo")
+        .unwrap()
+        .handle(1)
+        .priority(1)
+        .hook(TC_INGRESS);
+    assert_eq!(ingress.get_ifindex(), LO_IFINDEX);
+    assert!(ingress.attach().is_ok());
+
+    assert!(!other.release().unwrap());
+    assert!(ingress.query().is_ok());
+
+    assert!(qdisc.release().unwrap());
+    assert!(ingress.query().is_err());
+    assert!(ingress.attach().is_err());
+}
+
+#[test]
+#[serial]
+fn test_sudo_tc_qdisc_ingress() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("tc-unit.bpf.o");
+    let fd = obj.prog("handle_tc").unwrap().as_fd();
+    assert!(clear_clsact(fd).is_ok());
+
+    let qdisc = TcQdisc::ingress(LO_IFINDEX).unwrap();
+    assert!(qdisc.created());
+    assert_eq!(qdisc.kind(), TcQdiscKind::Ingress);
+
+    // The ingress qdisc is found in place, but it is not a clsact one.
+    let other = TcQdisc::ingress(LO_IFINDEX).unwrap();
+    assert!(!other.created());
+    assert_eq!(other.kind(), TcQdiscKind::Ingress);
+    let err = TcQdisc::clsact(LO_IFINDEX).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+    // Filters can be attached at the ingress hook only.
+    let mut tc_builder = TcHookBuilder::new(fd);
+    tc_builder.ifindex(LO_IFINDEX).handle(1).priority(1);
+    let mut ingress = tc_builder.hook(TC_INGRESS);
+    assert!(ingress.attach().is_ok());
+    assert!(ingress.query().is_ok());
+    let mut egress = tc_builder.hook(TC_EGRESS);
+    assert!(egress.attach().is_err());
+
+    assert!(qdisc.release().unwrap());
+    assert!(ingress.query().is_err());
+
+    // An existing clsact qdisc gets used for ingress filters.
+    let clsact = TcQdisc::clsact(LO_IFINDEX).unwrap();
+    let qdisc = TcQdisc::ingress(LO_IFINDEX).unwrap();
+    assert!(!qdisc.created());
+    assert_eq!(qdisc.kind(), TcQdiscKind::Clsact);
+    assert!(clsact.release().unwrap());
+}
+
+#[test]
+#[serial]
+fn test_sudo_tc_hook_guard() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("tc-unit.bpf.o");
+    let fd = obj.prog("handle_tc").unwrap().as_fd();
+    assert!(clear_clsact(fd).is_ok());
+
+    let mut tc_builder = TcHookBuilder::new(fd);
+    tc_builder.ifindex(LO_IFINDEX).handle(1).priority(1);
+
+    {
+        let guard = tc_builder.attach_ingress_egress().unwrap();
+        assert_eq!(guard.hooks().len(), 2);
+        for hook in guard.hooks() {
+            let mut hook = *hook;
+            assert!(hook.query().is_ok());
+        }
+    }
+
+    // Both filters are detached and the qdisc we created got removed.
+    let qdisc = TcQdisc::clsact(LO_IFINDEX).unwrap();
+    assert!(qdisc.created());
+
+    let mut ingress = tc_builder.hook(TC_INGRESS);
+    {
+        let _guard = ingress.attach_guarded().unwrap();
+        assert!(ingress.query().is_ok());
+    }
+    assert!(ingress.query().is_err());
+
+    let mut guard = ingress.attach_guarded().unwrap().with_qdisc(qdisc);
+    guard.disconnect();
+    drop(guard);
+    assert!(ingress.query().is_ok());
+    assert!(ingress.detach().is_ok());
+
+    let guard = ingress.attach_guarded().unwrap();
+    assert!(guard.detach().is_ok());
+    assert!(ingress.query().is_err());
+
+    assert!(clear_clsact(fd).is_ok());
+}
+
 #[test]
 #[serial]
 fn test_sudo_tc_double_create() {
@@ -1,17 +1,23 @@
 use std::os::fd::AsFd;
 
 use scopeguard::defer;
+use serial_test::serial;
 
 mod test;
 use test::bump_rlimit_mlock;
 use test::get_test_object;
 
+use libbpf_rs::ErrorKind;
+use libbpf_rs::Program;
 use libbpf_rs::Xdp;
+use libbpf_rs::XdpAttached;
+use libbpf_rs::XdpFeatures;
 use libbpf_rs::XdpFlags;
 
 const LO_IFINDEX: i32 = 1;
 
 #[test]
+#[serial]
 fn test_sudo_xdp() {
     bump_rlimit_mlock();
 
@@ -59,3 +65,149 @@ fn test_sudo_xdp() {
         .detach(LO_IFINDEX, XdpFlags::UPDATE_IF_NOEXIST)
         .is_ok());
 }
+
+#[test]
+#[serial]
+fn test_sudo_xdp_ifname() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("xdp.bpf.o");
+    let fd = obj.prog("xdp_filter").unwrap().as_fd();
+    let xdp_prog = Xdp::new(fd);
+
+    assert!(xdp_prog.attach("lo", XdpFlags::UPDATE_IF_NOEXIST).is_ok());
+    defer! {
+        xdp_prog.detach("lo", XdpFlags::UPDATE_IF_NOEXIST).unwrap();
+    }
+
+    let prog_id = xdp_prog
+        .query_id("lo", XdpFlags::UPDATE_IF_NOEXIST)
+        .unwrap();
+    assert_eq!(
+        xdp_prog
+            .query_id(LO_IFINDEX, XdpFlags::UPDATE_IF_NOEXIST)
+            .unwrap(),
+        prog_id
+    );
+
+    let err = xdp_prog
+        .attach("does-not-exist", XdpFlags::UPDATE_IF_NOEXIST)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+#[serial]
+fn test_sudo_xdp_query_attached() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("xdp.bpf.o");
+    let fd = obj.prog("xdp_filter").unwrap().as_fd();
+    let xdp_prog = Xdp::new(fd);
+
+    assert!(Xdp::query_attached(LO_IFINDEX).unwrap().is_empty());
+
+    assert!(xdp_prog
+        .attach(LO_IFINDEX, XdpFlags::UPDATE_IF_NOEXIST | XdpFlags::SKB_MODE)
+        .is_ok());
+    defer! {
+        xdp_prog.detach(LO_IFINDEX, XdpFlags::SKB_MODE).unwrap();
+    }
+
+    let prog_id = xdp_prog
+        .query_id(LO_IFINDEX, XdpFlags::UPDATE_IF_NOEXIST)
+        .unwrap();
+    let attached = Xdp::query_attached("lo").unwrap();
+    assert_eq!(
+        attached,
+        XdpAttached {
+            generic: Some(prog_id),
+            driver: None,
+            offload: None,
+        }
+    );
+}
+
+#[test]
+#[serial]
+fn test_sudo_xdp_query_features() {
+    let features = Xdp::query_features(LO_IFINDEX).unwrap();
+    // The loopback device does not support offloading or zero-copy.
+    assert!(!features.contains(XdpFeatures::HW_OFFLOAD));
+    assert!(!features.contains(XdpFeatures::XSK_ZEROCOPY));
+    assert_eq!(Xdp::query_features("lo").unwrap(), features);
+}
+
+#[test]
+#[serial]
+fn test_sudo_xdp_attach_replace() {
+    bump_rlimit_mlock();
+
+    let obj = get_test_object("xdp.bpf.o");
+    let fd = obj.prog("xdp_filter").unwrap().as_fd();
+    let obj1 = get_test_object("xdp.bpf.o");
+    let fd1 = obj1.prog("xdp_filter").unwrap().as_fd();
+
+    let xdp_prog = Xdp::new(fd);
+    let xdp_prog1 = Xdp::new(fd1);
+
+    assert!(xdp_prog
+        .attach(LO_IFINDEX, XdpFlags::UPDATE_IF_NOEXIST | XdpFlags::SKB_MODE)
+        .is_ok());
+    defer! {
+        xdp_prog.detach(LO_IFINDEX, XdpFlags::SKB_MODE).unwrap();
+    }
+
+    assert!(xdp_prog1
+        .attach_replace(LO_IFINDEX, fd, XdpFlags::SKB_MODE)
+        .is_ok());
+    let attached = Xdp::query_attached(LO_IFINDEX).unwrap();
+    assert_eq!(attached.generic, Some(Program::get_id_by_fd(fd1).unwrap()));
+
+    // `fd` is no longer attached, so replacing it has to fail.
+    let err = xdp_prog
+        .attach_replace(LO_IFINDEX, fd, XdpFlags::SKB_MODE)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    let attached = Xdp::query_attached(LO_IFINDEX).unwrap();
+    assert_eq!(attached.generic, Some(Program::get_id_by_fd(fd1).unwrap()));
+}
+
+#[test]
+#[serial]
+fn test_sudo_xdp_link() {
+    bump_rlimit_mlock();
+
+    let mut obj = get_test_object("xdp.bpf.o");
+    let prog = obj.prog_mut("xdp_filter").unwrap();
+    let obj1 = get_test_object("xdp.bpf.o");
+    let prog1 = obj1.prog("xdp_filter").unwrap();
+    let prog_id = Program::get_id_by_fd(prog.as_fd()).unwrap();
+    let prog1_id = Program::get_id_by_fd(prog1.as_fd()).unwrap();
+
+    let err = prog
+        .attach_xdp_with_flags(LO_IFINDEX, XdpFlags::UPDATE_IF_NOEXIST)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let mut link = prog
+        .attach_xdp_with_flags("lo", XdpFlags::SKB_MODE)
+        .unwrap();
+    let attached = Xdp::query_attached(LO_IFINDEX).unwrap();
+    assert_eq!(attached.generic, Some(prog_id));
+
+    // The link prevents netlink based attachments from replacing the
+    // program.
+    let xdp_prog1 = Xdp::new(prog1.as_fd());
+    assert!(xdp_prog1.attach(LO_IFINDEX, XdpFlags::SKB_MODE).is_err());
+
+    let () = link.update_prog(prog1).unwrap();
+    let attached = Xdp::query_attached(LO_IFINDEX).unwrap();
+    assert_eq!(attached.generic, Some(prog1_id));
+
+    let prog = obj.prog("xdp_filter").unwrap();
+    assert!(link.replace_prog(prog, prog1).is_err());
+
+    drop(link);
+    assert!(Xdp::query_attached(LO_IFINDEX).unwrap().is_empty());
+}
@@ -0,0 +1,126 @@
+use std::mem::size_of;
+use std::os::unix::io::AsFd as _;
+use std::thread::sleep;
+use std::time::Duration;
+
+use serial_test::serial;
+
+mod test;
+use test::bump_rlimit_mlock;
+
+use libbpf_rs::libbpf_sys;
+use libbpf_rs::xsk::Umem;
+use libbpf_rs::xsk::UmemConfig;
+use libbpf_rs::xsk::XdpDesc;
+use libbpf_rs::xsk::XskBindFlags;
+use libbpf_rs::xsk::XskConfig;
+use libbpf_rs::xsk::XskParts;
+use libbpf_rs::xsk::XskSocket;
+use libbpf_rs::ErrorKind;
+use libbpf_rs::MapHandle;
+use libbpf_rs::MapType;
+
+const LO_IFINDEX: i32 = 1;
+
+fn create_socket(bind_flags: XskBindFlags) -> XskSocket {
+    let umem_config = UmemConfig {
+        fill_size: 64,
+        comp_size: 64,
+        ..Default::default()
+    };
+    let config = XskConfig {
+        rx_size: 64,
+        tx_size: 64,
+        bind_flags: XskBindFlags::COPY | bind_flags,
+    };
+
+    // The kernel releases the queue of a closed socket asynchronously, so
+    // binding may fail temporarily if a previous test used the queue.
+    for _ in 0..100 {
+        let umem = Umem::new(64, umem_config).unwrap();
+        match XskSocket::new(LO_IFINDEX, 0, umem, &config) {
+            Ok(xsk) => return xsk,
+            Err(err) if err.kind() == ErrorKind::ResourceBusy => sleep(Duration::from_millis(10)),
+            Err(err) => panic!("failed to create socket: {err}"),
+        }
+    }
+    panic!("queue remained busy")
+}
+
+/// Check that we can transmit a packet via an AF_XDP socket and get its
+/// frame back via the completion ring.
+#[test]
+#[serial]
+fn test_sudo_xsk_transmit() {
+    bump_rlimit_mlock();
+
+    let mut xsk = create_socket(XskBindFlags::empty());
+    assert_eq!(xsk.ifindex(), LO_IFINDEX);
+    assert_eq!(xsk.queue_id(), 0);
+
+    let XskParts { umem, fill, tx, .. } = xsk.parts_mut();
+    assert_eq!(fill.free(), 64);
+    assert_eq!(tx.free(), 64);
+
+    let addr = umem.frame_addr(3);
+    let () = umem.data_mut(addr, 60).unwrap().fill(0xff);
+    assert!(umem.data(umem.frame_addr(64), 1).is_none());
+
+    let desc = XdpDesc {
+        addr,
+        len: 60,
+        options: 0,
+    };
+    assert_eq!(tx.produce(&[desc]), 1);
+    let () = xsk.wakeup_tx().unwrap();
+
+    let XskParts { completion, .. } = xsk.parts_mut();
+    let mut addrs = [0; 4];
+    for _ in 0..100 {
+        let count = completion.consume(&mut addrs);
+        if count > 0 {
+            assert_eq!(&addrs[..count], &[addr]);
+            return;
+        }
+        sleep(Duration::from_millis(10));
+    }
+    panic!("transmitted frame was not completed");
+}
+
+/// Check that we can register an AF_XDP socket in an XSKMAP.
+#[test]
+#[serial]
+fn test_sudo_xsk_register() {
+    bump_rlimit_mlock();
+
+    let xsk = create_socket(XskBindFlags::empty());
+    let opts = libbpf_sys::bpf_map_create_opts {
+        sz: size_of::<libbpf_sys::bpf_map_create_opts>() as libbpf_sys::size_t,
+        ..Default::default()
+    };
+    let map = MapHandle::create(MapType::Xskmap, Some("xsks"), 4, 4, 4, &opts).unwrap();
+    let () = xsk.register(&map, 2).unwrap();
+    // Any map file descriptor works.
+    let () = xsk.register(&map.as_fd(), 3).unwrap();
+
+    let map = MapHandle::create(MapType::Array, Some("array"), 4, 4, 4, &opts).unwrap();
+    let err = xsk.register(&map, 2).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+/// Check that we can wake up the kernel when using need-wakeup mode.
+#[test]
+#[serial]
+fn test_sudo_xsk_need_wakeup() {
+    bump_rlimit_mlock();
+
+    let mut xsk = create_socket(XskBindFlags::USE_NEED_WAKEUP);
+    let XskParts { umem, fill, tx, .. } = xsk.parts_mut();
+    // The tx ring has to be woken up initially.
+    assert!(tx.needs_wakeup());
+
+    let addrs = (0..32).map(|idx| umem.frame_addr(idx)).collect::<Vec<_>>();
+    assert_eq!(fill.produce(&addrs), addrs.len());
+    let () = xsk.wakeup_rx().unwrap();
+    let () = xsk.wakeup_tx().unwrap();
+}